@@ -1,6 +1,10 @@
 #![warn(missing_docs)]
 //! A simple library for working with angles in radians.
-use std::{error::Error, fmt::Display};
+//!
+//! When deserialized, an angle can be written as a plain number, or as a
+//! string with a unit, e.g. `"90deg"`, `"90°"` or `"1.57rad"`. Plain numbers
+//! are in the unit given to [`with_default_unit`], which is radians by default.
+use std::{cell::Cell, error::Error, fmt::Display, str::FromStr};
 
 use serde::{Deserialize, Deserializer, Serialize};
 
@@ -11,6 +15,10 @@ pub enum AngleError {
     OutOfRangeRadians(f64),
     /// The angle value is not in the interval [0, 360].
     OutOfRangeDegrees(f64),
+    /// The angle value is NaN or infinite.
+    NotFinite(f64),
+    /// The text is not a number, optionally followed by a unit.
+    Unparsable(String),
 }
 
 impl Display for AngleError {
@@ -22,12 +30,119 @@ impl Display for AngleError {
             Self::OutOfRangeDegrees(value) => {
                 write!(f, "Angle value {value} is not inside [0,360]")
             }
+            Self::NotFinite(value) => write!(f, "Angle value {value} is not finite"),
+            Self::Unparsable(text) => write!(
+                f,
+                "'{text}' is not an angle, expected a number optionally followed by 'deg' or 'rad'"
+            ),
         }
     }
 }
 
 impl Error for AngleError {}
 
+/// The unit of an angle written as a plain number.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AngleUnit {
+    /// The default, as used by the trigonometric functions of [`f64`].
+    #[default]
+    Radians,
+    /// Degrees, where a full turn is 360.
+    Degrees,
+}
+
+impl AngleUnit {
+    /// Converts `value` in this unit to radians.
+    #[must_use]
+    pub fn to_radians(self, value: f64) -> f64 {
+        match self {
+            Self::Radians => value,
+            Self::Degrees => value.to_radians(),
+        }
+    }
+}
+
+thread_local! {
+    static DEFAULT_UNIT: Cell<AngleUnit> = const { Cell::new(AngleUnit::Radians) };
+}
+
+/// Runs `f` with plain numbers deserialized as angles in `unit`, e.g. to
+/// parse a file that declares its angles to be in degrees.
+pub fn with_default_unit<T>(unit: AngleUnit, f: impl FnOnce() -> T) -> T {
+    let previous = DEFAULT_UNIT.with(|default| default.replace(unit));
+    let result = f();
+    DEFAULT_UNIT.with(|default| default.set(previous));
+    result
+}
+
+/// The unit plain numbers are deserialized in, see [`with_default_unit`].
+#[must_use]
+pub fn default_unit() -> AngleUnit {
+    DEFAULT_UNIT.with(Cell::get)
+}
+
+/// An angle in radians, that is not checked to be in any interval, parsed
+/// from a number with an optional unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UncheckedAngle(pub f64);
+
+impl UncheckedAngle {
+    /// Parses `text` as a number optionally followed by a unit, either `deg`,
+    /// `°` or `rad`. Without a unit the number is in `default`.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `text` is not a number, optionally followed by a
+    /// unit
+    pub fn parse_with_unit(text: &str, default: AngleUnit) -> Result<Self> {
+        let trimmed = text.trim();
+        let (number, unit) = [
+            ("deg", AngleUnit::Degrees),
+            ("°", AngleUnit::Degrees),
+            ("rad", AngleUnit::Radians),
+        ]
+        .into_iter()
+        .find_map(|(suffix, unit)| trimmed.strip_suffix(suffix).map(|number| (number, unit)))
+        .unwrap_or((trimmed, default));
+
+        number
+            .trim_end()
+            .parse::<f64>()
+            .map(|value| Self(unit.to_radians(value)))
+            .map_err(|_| AngleError::Unparsable(text.to_string()))
+    }
+}
+
+impl FromStr for UncheckedAngle {
+    type Err = AngleError;
+
+    /// See [`UncheckedAngle::parse_with_unit`], with the unit of
+    /// [`default_unit`]
+    fn from_str(text: &str) -> Result<Self> {
+        Self::parse_with_unit(text, default_unit())
+    }
+}
+
+impl<'de> Deserialize<'de> for UncheckedAngle {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Written {
+            Number(f64),
+            Text(String),
+        }
+
+        match Written::deserialize(deserializer)? {
+            Written::Number(value) => Ok(Self(default_unit().to_radians(value))),
+            Written::Text(text) => text.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
 /// Represents an angle in radians.
 #[derive(Debug, Clone, Copy, Serialize)]
 pub struct Angle(f64);
@@ -61,6 +176,34 @@ impl Angle {
         }
     }
 
+    /// Creates a new [`Angle`] from any finite value in radians, wrapping it
+    /// into the interval [0, 2π], e.g. -π/2 becomes 3π/2.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `value` is NaN or infinite
+    pub fn wrapped(value: f64) -> Result<Self> {
+        if value.is_finite() {
+            Ok(Self(value.rem_euclid(std::f64::consts::TAU)))
+        } else {
+            Err(AngleError::NotFinite(value))
+        }
+    }
+
+    /// Creates a new [`Angle`] from any finite value in degrees, wrapping it
+    /// into the interval [0, 360], e.g. -90 becomes 270 and 450 becomes 90.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `value` is NaN or infinite
+    pub fn wrapped_degrees(value: f64) -> Result<Self> {
+        if value.is_finite() {
+            Ok(Self(value.rem_euclid(360.0).to_radians()))
+        } else {
+            Err(AngleError::NotFinite(value))
+        }
+    }
+
     /// Returns the angle in radians.
     #[inline(always)]
     #[must_use]
@@ -150,7 +293,7 @@ impl<'de> Deserialize<'de> for Angle {
     where
         D: Deserializer<'de>,
     {
-        let value = f64::deserialize(deserializer)?;
+        let UncheckedAngle(value) = UncheckedAngle::deserialize(deserializer)?;
         Self::try_from(value).map_err(serde::de::Error::custom)
     }
 }
@@ -178,6 +321,24 @@ mod tests {
         assert!(Angle::from_degrees(361.0).is_err());
     }
 
+    #[test]
+    fn test_wrapped() {
+        let angle = Angle::wrapped(-std::f64::consts::FRAC_PI_2).unwrap();
+        assert_abs_diff_eq!(angle.as_degrees(), 270.0, epsilon = 1e-6);
+        let angle = Angle::wrapped(5.0 * std::f64::consts::PI).unwrap();
+        assert_abs_diff_eq!(angle.as_degrees(), 180.0, epsilon = 1e-6);
+        assert!(Angle::wrapped(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_wrapped_degrees() {
+        let angle = Angle::wrapped_degrees(-90.0).unwrap();
+        assert_abs_diff_eq!(angle.as_degrees(), 270.0, epsilon = 1e-6);
+        let angle = Angle::wrapped_degrees(450.0).unwrap();
+        assert_abs_diff_eq!(angle.as_degrees(), 90.0, epsilon = 1e-6);
+        assert!(Angle::wrapped_degrees(f64::INFINITY).is_err());
+    }
+
     #[test]
     fn test_as_radians() {
         let angle = Angle::from_degrees(180.0).expect("0.0 <= 180.0 <= 360.0");
@@ -272,6 +433,42 @@ mod tests {
         assert_abs_diff_eq!(angle1.as_degrees(), 340.0, epsilon = 1e-6);
     }
 
+    #[test]
+    fn test_parse_with_unit() {
+        let parse = |text| {
+            UncheckedAngle::parse_with_unit(text, AngleUnit::Radians)
+                .unwrap()
+                .0
+        };
+        assert_abs_diff_eq!(parse("90deg"), std::f64::consts::FRAC_PI_2, epsilon = 1e-9);
+        assert_abs_diff_eq!(parse("90 °"), std::f64::consts::FRAC_PI_2, epsilon = 1e-9);
+        assert_abs_diff_eq!(parse("1.5rad"), 1.5, epsilon = 1e-9);
+        assert_abs_diff_eq!(parse(" 1.5 "), 1.5, epsilon = 1e-9);
+        assert_abs_diff_eq!(
+            UncheckedAngle::parse_with_unit("180", AngleUnit::Degrees)
+                .unwrap()
+                .0,
+            std::f64::consts::PI,
+            epsilon = 1e-9
+        );
+        assert!(matches!(
+            UncheckedAngle::parse_with_unit("ninety", AngleUnit::Radians),
+            Err(AngleError::Unparsable(_))
+        ));
+    }
+
+    #[test]
+    fn test_default_unit() {
+        assert_eq!(default_unit(), AngleUnit::Radians);
+        let angle = with_default_unit(AngleUnit::Degrees, || "90".parse::<UncheckedAngle>());
+        assert_abs_diff_eq!(
+            angle.unwrap().0,
+            std::f64::consts::FRAC_PI_2,
+            epsilon = 1e-9
+        );
+        assert_eq!(default_unit(), AngleUnit::Radians);
+    }
+
     #[test]
     fn test_try_from_f64() {
         assert!(matches!(Angle::try_from(0.0), Ok(Angle(0.0))));
@@ -6,7 +6,7 @@
 use std::num::NonZeroU32;
 
 // use magics::config::Environment;
-use gbp_environment::{Environment, PlaceableShape, Polygon, RegularPolygon};
+use gbp_environment::Environment;
 use gbp_geometry::RelativePoint;
 use glam::{Vec2, Vec3Swizzles};
 use image::{imageops::FilterType::Triangle, RgbImage};
@@ -171,12 +171,35 @@ pub fn env_to_image(
 ) -> anyhow::Result<RgbImage> {
     let tile_size = env.tile_size();
     let (ncols, nrows) = (env.tiles.grid.ncols(), env.tiles.grid.nrows());
+    // the path width is relative to the shorter side of the tile
+    let path_width = env.path_width() * env.tile_extent();
+    let path_width_x = Percentage::new(path_width / env.tile_width());
+    let path_width_y = Percentage::new(path_width / env.tile_height());
 
     let mut image = RgbImage::new(
         ncols as u32 * resolution.get(),
         nrows as u32 * resolution.get(),
     );
 
+    // The boundary walls lie along the inside of the edge of the map, so they
+    // are drawn as a band along the edge of the image, as wide as the walls
+    // plus the obstacle expansion
+    let boundary = if env.add_boundary() {
+        let band = env.boundary_thickness() + expansion.get() * env.tile_extent();
+        Vec2::new(band / env.tile_width(), band / env.tile_height()) * resolution.get() as f32
+    } else {
+        Vec2::ZERO
+    };
+    let (width, height) = image.dimensions();
+    let on_boundary = |x: u32, y: u32| {
+        // measured from the center of the pixel
+        let (x, y) = (x as f32 + 0.5, y as f32 + 0.5);
+        x < boundary.x
+            || y < boundary.y
+            || width as f32 - x < boundary.x
+            || height as f32 - y < boundary.y
+    };
+
     // Start by making the whole image white.
     for y in 0..image.height() {
         for x in 0..image.width() {
@@ -186,12 +209,15 @@ pub fn env_to_image(
             let percentage_coords = tile_units_to_percentage(tile_dimensions, tile_size);
 
             if let Some(tile) = env.tiles.grid.get_tile(tile_coords.y, tile_coords.x) {
-                if is_tile_obstacle(
-                    tile,
-                    Percentage::new(env.path_width()),
-                    percentage_coords,
-                    expansion,
-                ) || is_placeable_obstacle(&env, tile_coords, percentage_coords, expansion)
+                if on_boundary(x, y)
+                    || is_tile_obstacle(
+                        tile,
+                        path_width_x,
+                        path_width_y,
+                        percentage_coords,
+                        expansion,
+                    )
+                    || is_placeable_obstacle(&env, tile_coords, percentage_coords, expansion)
                 {
                     image.put_pixel(x, y, image::Rgb([0, 0, 0]));
                 } else {
@@ -302,19 +328,7 @@ fn is_placeable_obstacle(
         let expanded_shape = obstacle.shape.expanded(expansion.0 as f64);
         let translated = Vec2::from(inverted_percentage) - Vec2::from(obstacle.translation); // - translation_offset;
                                                                                              // rotate the translated coordinated by the obstacle rotation
-        let rotation_offset = match obstacle.shape {
-            PlaceableShape::RegularPolygon(RegularPolygon { sides, radius: _ }) => {
-                std::f32::consts::FRAC_PI_2
-                    + std::f32::consts::FRAC_PI_2
-                    + if sides % 2 != 0 {
-                        std::f32::consts::PI / sides as f32
-                    } else {
-                        0.0
-                    }
-            }
-            PlaceableShape::Polygon { .. } => 0.0,
-            _ => std::f32::consts::FRAC_PI_2,
-        };
+        let rotation_offset = obstacle.shape.rotation_offset();
 
         let rotated =
             glam::Quat::from_rotation_z(obstacle.rotation.as_radians() as f32 + rotation_offset)
@@ -339,132 +353,139 @@ fn is_placeable_obstacle(
 /// the coordinate is within and obstacle.
 fn is_tile_obstacle(
     tile: char,
-    path_width: Percentage,
+    path_width_x: Percentage,
+    path_width_y: Percentage,
     percentage: PercentageCoords,
     expansion: Percentage,
 ) -> bool {
     // let tile = env.tiles.grid.get_tile(tile.0, tile.1);
-    let path_width = path_width - expansion;
-    let almost_full = Percentage::new(1.0 - path_width.clone().0);
-    let obstacle_width = Percentage::new(almost_full.0 / 2.0);
-    let obstacle_and_path_width = obstacle_width + path_width.clone();
+    // the path width relative to the tile differs along each axis, when the
+    // tile is not square
+    let bounds = |path_width: Percentage| {
+        let path_width = path_width - expansion;
+        let almost_full = Percentage::new(1.0 - path_width.clone().0);
+        let obstacle_width = Percentage::new(almost_full.0 / 2.0);
+        (obstacle_width, obstacle_width + path_width)
+    };
+    let (obstacle_width_x, obstacle_and_path_width_x) = bounds(path_width_x);
+    let (obstacle_width_y, obstacle_and_path_width_y) = bounds(path_width_y);
     let half = Percentage::new(0.5 - expansion.get() / 2.0);
 
     match tile {
         '─' => {
-            if percentage.y() < obstacle_width || percentage.y() > obstacle_and_path_width {
+            if percentage.y() < obstacle_width_y || percentage.y() > obstacle_and_path_width_y {
                 return true;
             }
         }
         '│' => {
-            if percentage.x() < obstacle_width || percentage.x() > obstacle_and_path_width {
+            if percentage.x() < obstacle_width_x || percentage.x() > obstacle_and_path_width_x {
                 return true;
             }
         }
         '╴' => {
-            if percentage.y() < obstacle_width
-                || percentage.y() > obstacle_and_path_width
+            if percentage.y() < obstacle_width_y
+                || percentage.y() > obstacle_and_path_width_y
                 || percentage.x() > Percentage::new(0.5 - expansion.get() / 2.0)
             {
                 return true;
             }
         }
         '╶' => {
-            if percentage.y() < obstacle_width
-                || percentage.y() > obstacle_and_path_width
+            if percentage.y() < obstacle_width_y
+                || percentage.y() > obstacle_and_path_width_y
                 || percentage.x() < Percentage::new(0.5 + expansion.get() / 2.0)
             {
                 return true;
             }
         }
         '╷' => {
-            if percentage.x() < obstacle_width
-                || percentage.x() > obstacle_and_path_width
+            if percentage.x() < obstacle_width_x
+                || percentage.x() > obstacle_and_path_width_x
                 || percentage.y() < Percentage::new(0.5 + expansion.get() / 2.0)
             {
                 return true;
             }
         }
         '╵' => {
-            if percentage.x() < obstacle_width
-                || percentage.x() > obstacle_and_path_width
+            if percentage.x() < obstacle_width_x
+                || percentage.x() > obstacle_and_path_width_x
                 || percentage.y() > Percentage::new(0.5 - expansion.get() / 2.0)
             {
                 return true;
             }
         }
         '┌' => {
-            if percentage.x() < obstacle_width
-                || percentage.y() < obstacle_width
-                || (percentage.x() > obstacle_and_path_width
-                    && percentage.y() > obstacle_and_path_width)
+            if percentage.x() < obstacle_width_x
+                || percentage.y() < obstacle_width_y
+                || (percentage.x() > obstacle_and_path_width_x
+                    && percentage.y() > obstacle_and_path_width_y)
             {
                 return true;
             }
         }
         '┐' => {
-            if percentage.x() > obstacle_and_path_width
-                || percentage.y() < obstacle_width
-                || (percentage.x() < obstacle_width && percentage.y() > obstacle_and_path_width)
+            if percentage.x() > obstacle_and_path_width_x
+                || percentage.y() < obstacle_width_y
+                || (percentage.x() < obstacle_width_x && percentage.y() > obstacle_and_path_width_y)
             {
                 return true;
             }
         }
         '└' => {
-            if percentage.x() < obstacle_width
-                || percentage.y() > obstacle_and_path_width
-                || (percentage.x() > obstacle_and_path_width && percentage.y() < obstacle_width)
+            if percentage.x() < obstacle_width_x
+                || percentage.y() > obstacle_and_path_width_y
+                || (percentage.x() > obstacle_and_path_width_x && percentage.y() < obstacle_width_y)
             {
                 return true;
             }
         }
         '┘' => {
-            if percentage.x() > obstacle_and_path_width
-                || percentage.y() > obstacle_and_path_width
-                || (percentage.x() < obstacle_width && percentage.y() < obstacle_width)
+            if percentage.x() > obstacle_and_path_width_x
+                || percentage.y() > obstacle_and_path_width_y
+                || (percentage.x() < obstacle_width_x && percentage.y() < obstacle_width_y)
             {
                 return true;
             }
         }
         '┬' => {
-            if percentage.y() < obstacle_width
-                || (percentage.y() > obstacle_and_path_width
-                    && (percentage.x() < obstacle_width
-                        || percentage.x() > obstacle_and_path_width))
+            if percentage.y() < obstacle_width_y
+                || (percentage.y() > obstacle_and_path_width_y
+                    && (percentage.x() < obstacle_width_x
+                        || percentage.x() > obstacle_and_path_width_x))
             {
                 return true;
             }
         }
         '┴' => {
-            if percentage.y() > obstacle_and_path_width
-                || (percentage.y() < obstacle_width
-                    && (percentage.x() < obstacle_width
-                        || percentage.x() > obstacle_and_path_width))
+            if percentage.y() > obstacle_and_path_width_y
+                || (percentage.y() < obstacle_width_y
+                    && (percentage.x() < obstacle_width_x
+                        || percentage.x() > obstacle_and_path_width_x))
             {
                 return true;
             }
         }
         '├' => {
-            if percentage.x() < obstacle_width
-                || (percentage.x() > obstacle_and_path_width
-                    && (percentage.y() < obstacle_width
-                        || percentage.y() > obstacle_and_path_width))
+            if percentage.x() < obstacle_width_x
+                || (percentage.x() > obstacle_and_path_width_x
+                    && (percentage.y() < obstacle_width_y
+                        || percentage.y() > obstacle_and_path_width_y))
             {
                 return true;
             }
         }
         '┤' => {
-            if percentage.x() > obstacle_and_path_width
-                || (percentage.x() < obstacle_width
-                    && (percentage.y() < obstacle_width
-                        || percentage.y() > obstacle_and_path_width))
+            if percentage.x() > obstacle_and_path_width_x
+                || (percentage.x() < obstacle_width_x
+                    && (percentage.y() < obstacle_width_y
+                        || percentage.y() > obstacle_and_path_width_y))
             {
                 return true;
             }
         }
         '┼' => {
-            if (percentage.x() < obstacle_width || percentage.x() > obstacle_and_path_width)
-                && (percentage.y() < obstacle_width || percentage.y() > obstacle_and_path_width)
+            if (percentage.x() < obstacle_width_x || percentage.x() > obstacle_and_path_width_x)
+                && (percentage.y() < obstacle_width_y || percentage.y() > obstacle_and_path_width_y)
             {
                 return true;
             }
@@ -518,7 +539,7 @@ mod tests {
         let percentage = PercentageCoords::new(0.3, 0.6);
         let expansion = Percentage::new(0.0);
         assert_eq!(
-            is_tile_obstacle(tile, path_width, percentage, expansion),
+            is_tile_obstacle(tile, path_width, path_width, percentage, expansion),
             false
         );
 
@@ -527,7 +548,7 @@ mod tests {
         let percentage = PercentageCoords::new(0.1, 0.6);
         let expansion = Percentage::new(0.0);
         assert_eq!(
-            is_tile_obstacle(tile, path_width, percentage, expansion),
+            is_tile_obstacle(tile, path_width, path_width, percentage, expansion),
             true
         );
     }
@@ -14,7 +14,10 @@ use bevy::{
 use itertools::Itertools;
 use min_len_vec::{one_or_more, OneOrMore};
 use num_traits::{Saturating, SaturatingMul};
-use rand::{thread_rng, Rng};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    thread_rng, Rng,
+};
 use serde::{Deserialize, Serialize};
 use typed_floats::StrictlyPositiveFinite;
 
@@ -147,6 +150,8 @@ impl RepeatTimes {
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Repeat {
     pub every: Duration,
+    /// Defaults to repeating forever, like `repeat-every` of version 1 files
+    #[serde(default)]
     pub times: RepeatTimes,
 }
 
@@ -229,6 +234,29 @@ pub struct Formation {
     pub waypoint_reached_when_intersects: ReachedWhen,
     #[serde(default = "Formation::default_finished_when_intersects")]
     pub finished_when_intersects: ReachedWhen,
+    /// Priority of the robots of this formation. Robots yield to robots with
+    /// a higher priority, e.g. to let emergency vehicles pass
+    #[serde(default)]
+    pub priority: u8,
+    /// Class of the robots of this formation, e.g. "agv" or "forklift", used
+    /// to look up the `interrobot-sigma-scales` of the `gbp` config
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    /// Candidate goal regions. When given, every robot samples one of them
+    /// with a probability proportional to its weight, and drives to a random
+    /// point in it after the last waypoint
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub goals: Vec<WeightedGoal>,
+}
+
+/// A goal region of a [`Formation`], and how likely robots are to pick it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct WeightedGoal {
+    /// The region a robot drives to, if it picks this goal
+    pub shape:  Shape,
+    /// Relative probability of picking this goal. **constraint**: >= 0.0
+    pub weight: f32,
 }
 
 impl Default for Formation {
@@ -291,9 +319,33 @@ impl Formation {
             waypoints: one_or_more![Waypoint::new(circle, ProjectionStrategy::Cross)],
             waypoint_reached_when_intersects: ReachedWhen::same_as_paper(),
             finished_when_intersects: ReachedWhen::same_as_paper(),
+            priority: 0,
+            class: None,
+            goals: Vec::new(),
         }
     }
 
+    /// Sample a goal for each of `robots` robots from [`Formation::goals`],
+    /// as a random point in the picked region. Returns `None` if there are no
+    /// goals, or the weights do not form a distribution, i.e. are negative or
+    /// all zero
+    pub fn sample_goals(
+        &self,
+        robots: usize,
+        world_dims: WorldDimensions,
+        rng: &mut impl Rng,
+    ) -> Option<Vec<Vec2>> {
+        let distribution = WeightedIndex::new(self.goals.iter().map(|goal| goal.weight)).ok()?;
+        Some(
+            (0..robots)
+                .map(|_| {
+                    let goal = &self.goals[distribution.sample(rng)];
+                    sample_point_in_shape(&goal.shape, world_dims, rng)
+                })
+                .collect(),
+        )
+    }
+
     /// Convert a `Formation` description into the waypoints the robot has to
     /// follow
     #[allow(
@@ -454,6 +506,65 @@ impl Formation {
     }
 }
 
+/// Uniformly random point in `shape`, in world coordinates. Polygons are
+/// sampled by rejection in their bounding box, falling back to the mean of
+/// their vertices
+#[allow(clippy::cast_precision_loss)]
+fn sample_point_in_shape(shape: &Shape, world_dims: WorldDimensions, rng: &mut impl Rng) -> Vec2 {
+    const ATTEMPTS: usize = 100;
+
+    match shape {
+        Shape::Circle { radius, center } => {
+            let center = world_dims.point_to_world_position(*center);
+            // the square root makes the points uniform over the area of the disc
+            let distance = radius.get() * rng.gen::<f32>().sqrt();
+            center + polar(rng.gen_range(0.0..TAU), distance)
+        }
+        Shape::LineSegment((start, end)) => world_dims
+            .point_to_world_position(*start)
+            .lerp(world_dims.point_to_world_position(*end), rng.gen()),
+        Shape::Polygon(vertices) => {
+            let vertices: Vec<Vec2> = vertices
+                .iter()
+                .map(|vertex| world_dims.point_to_world_position(*vertex))
+                .collect();
+            let min = vertices
+                .iter()
+                .copied()
+                .reduce(Vec2::min)
+                .unwrap_or_default();
+            let max = vertices
+                .iter()
+                .copied()
+                .reduce(Vec2::max)
+                .unwrap_or_default();
+
+            (0..ATTEMPTS)
+                .map(|_| {
+                    Vec2::new(
+                        min.x + (max.x - min.x) * rng.gen::<f32>(),
+                        min.y + (max.y - min.y) * rng.gen::<f32>(),
+                    )
+                })
+                .find(|point| polygon_contains(&vertices, *point))
+                .unwrap_or_else(|| vertices.iter().sum::<Vec2>() / vertices.len() as f32)
+        }
+    }
+}
+
+/// Whether `point` is inside the polygon with `vertices`, by the even-odd rule
+fn polygon_contains(vertices: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for (a, b) in vertices.iter().circular_tuple_windows() {
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
 /// Create a vector from polar coordinates
 #[must_use]
 #[inline]
@@ -658,16 +769,42 @@ pub enum ParseError {
 
     #[error("YAML error: {0}")]
     Yaml(#[from] serde_yaml::Error),
+
+    #[error("Migration error: {0}")]
+    Migration(#[from] gbp_migration::MigrationError),
 }
 
 /// A `FormationGroup` represent multiple `Formation`s
 #[derive(Debug, Clone, Serialize, Deserialize, Resource)]
 #[serde(rename_all = "kebab-case")]
 pub struct FormationGroup {
+    /// Version of the schema of the formation file. See
+    /// [`FormationGroup::SCHEMA`]
+    #[serde(default = "FormationGroup::latest_version")]
+    pub version:    u32,
     pub formations: OneOrMore<Formation>,
 }
 
 impl FormationGroup {
+    /// The schema of the formation file. Version 1 is the unversioned format.
+    /// Version 2 replaces `repeat-every` with the `every` of `repeat`.
+    /// Only YAML files are migrated, RON files must be at the latest version
+    pub const SCHEMA: gbp_migration::Schema = gbp_migration::Schema {
+        name:       "formation",
+        version:    2,
+        migrations: &[gbp_migration::Migration {
+            version: 2,
+            changes: &[gbp_migration::Change::Moved {
+                from: "formations.*.repeat-every",
+                to:   "formations.*.repeat.every",
+            }],
+        }],
+    };
+
+    const fn latest_version() -> u32 {
+        Self::SCHEMA.version
+    }
+
     /// Attempt to parse a `FormationGroup` from a RON file
     ///
     /// # Errors
@@ -699,14 +836,21 @@ impl FormationGroup {
     }
 
     /// Attempt to parse a `FormationGroup` from a YAML encoded string.
+    /// Files written for older versions of the schema are migrated, with a
+    /// warning for every deprecated key they set.
     ///
     /// # Errors
     ///
     /// Will return `Err`  if:
     /// 1. `contents` is not valid YAML.
     /// 2. The parsed data does not represent a valid `FormationGroup`.
+    /// 3. The file was written for a newer version of the schema.
     pub fn parse_from_yaml(contents: &str) -> Result<Self, ParseError> {
-        Ok(serde_yaml::from_str(contents)?)
+        let mut mapping: serde_yaml::Mapping = serde_yaml::from_str(contents)?;
+        for deprecation in Self::SCHEMA.migrate(&mut mapping)? {
+            bevy::log::warn!("{deprecation}");
+        }
+        Ok(serde_yaml::from_value(mapping.into())?)
         // unimplemented!()
         // Ok(ron::from_str::<Self>(contents).map_err(|span| span.code)?)
     }
@@ -722,6 +866,7 @@ impl FormationGroup {
 
     pub fn circle_from_paper() -> Self {
         Self {
+            version:    Self::SCHEMA.version,
             formations: one_or_more![Formation::circle_from_paper()],
         }
     }
@@ -729,6 +874,7 @@ impl FormationGroup {
     #[allow(clippy::missing_panics_doc)]
     pub fn intersection_from_paper() -> Self {
         Self {
+            version:    Self::SCHEMA.version,
             formations: one_or_more![
                 Formation {
                     // repeat: Some(Duration::from_secs(4)),
@@ -754,6 +900,9 @@ impl FormationGroup {
                         distance: IntersectionDistance::RobotRadius,
                         intersects_with: CheckIntersectionWith::Current,
                     },
+                    priority: 0,
+                    class: None,
+                    goals: Vec::new(),
                 },
                 Formation {
                     // repeat: Some(Duration::from_secs(4)),
@@ -779,10 +928,64 @@ impl FormationGroup {
                         distance: IntersectionDistance::RobotRadius,
                         intersects_with: CheckIntersectionWith::Current,
                     },
+                    priority: 0,
+                    class: None,
+                    goals: Vec::new(),
                 },
             ],
         }
     }
+
+    /// Two groups of `robots_per_side` robots, that swap sides through the
+    /// corridor of `gbp_environment::Environment::narrow_corridor`, with the
+    /// same `room_size` and `corridor_length`. Each group spawns along a line
+    /// in the back of one room, and has to reach the mirrored line in the
+    /// other room.
+    #[allow(clippy::missing_panics_doc, clippy::cast_precision_loss)]
+    pub fn narrow_corridor(
+        robots_per_side: usize,
+        room_size: usize,
+        corridor_length: usize,
+    ) -> Self {
+        let room_size = room_size.max(1) as f64;
+        let width = 2.0f64.mul_add(room_size, corridor_length as f64) + 2.0;
+        let height = room_size + 2.0;
+
+        // lines across the rooms, a quarter of the room from the outer walls
+        let y_range = (
+            0.2f64.mul_add(room_size, 1.0) / height,
+            0.8f64.mul_add(room_size, 1.0) / height,
+        );
+        let left_x = 0.25f64.mul_add(room_size, 1.0) / width;
+        let right_x = 1.0 - left_x;
+        let left = line![(left_x, y_range.0), (left_x, y_range.1)];
+        let right = line![(right_x, y_range.0), (right_x, y_range.1)];
+
+        let swap = |from: Shape, to: Shape| Formation {
+            repeat: None,
+            delay: Duration::from_secs(1),
+            robots: robots_per_side,
+            planning_strategy: PlanningStrategy::OnlyLocal,
+            initial_position: InitialPosition {
+                shape: from,
+                placement_strategy: InitialPlacementStrategy::Equal,
+            },
+            waypoints: one_or_more![Waypoint::new(to, ProjectionStrategy::Identity)],
+            waypoint_reached_when_intersects: ReachedWhen::same_as_paper(),
+            finished_when_intersects: ReachedWhen {
+                distance: IntersectionDistance::RobotRadius,
+                intersects_with: CheckIntersectionWith::Current,
+            },
+            priority: 0,
+            class: None,
+            goals: Vec::new(),
+        };
+
+        Self {
+            version:    Self::SCHEMA.version,
+            formations: one_or_more![swap(left.clone(), right.clone()), swap(right, left)],
+        }
+    }
 }
 
 impl Default for FormationGroup {
@@ -805,6 +1008,57 @@ mod tests {
         //     assert!(matches!(default.validate(), Ok(Formation { .. })));
         // }
 
+        mod goals {
+            use rand::SeedableRng;
+
+            use super::*;
+
+            #[test]
+            fn goals_are_sampled_by_weight() {
+                let world_dims = WorldDimensions::new(100.0, 100.0);
+                let formation = Formation {
+                    goals: vec![
+                        WeightedGoal {
+                            shape:  line![(0.0, 0.0), (0.0, 1.0)],
+                            weight: 1.0,
+                        },
+                        WeightedGoal {
+                            shape:  line![(1.0, 0.0), (1.0, 1.0)],
+                            weight: 0.0,
+                        },
+                    ],
+                    ..Formation::default()
+                };
+                let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+
+                let goals = formation
+                    .sample_goals(10, world_dims, &mut rng)
+                    .expect("the weights form a distribution");
+                assert_eq!(goals.len(), 10);
+                assert!(goals.iter().all(|goal| goal.x == -50.0));
+            }
+
+            #[test]
+            fn no_goals_samples_nothing() {
+                let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+                assert!(Formation::default()
+                    .sample_goals(1, WorldDimensions::new(1.0, 1.0), &mut rng)
+                    .is_none());
+            }
+
+            #[test]
+            fn polygon_contains_its_interior_only() {
+                let square = [
+                    Vec2::new(0.0, 0.0),
+                    Vec2::new(1.0, 0.0),
+                    Vec2::new(1.0, 1.0),
+                    Vec2::new(0.0, 1.0),
+                ];
+                assert!(polygon_contains(&square, Vec2::new(0.5, 0.5)));
+                assert!(!polygon_contains(&square, Vec2::new(1.5, 0.5)));
+            }
+        }
+
         mod polygon_macro {
 
             use pretty_assertions::assert_eq;
@@ -867,4 +1121,60 @@ mod tests {
             }
         }
     }
+
+    mod formation_group {
+        use super::*;
+
+        #[test]
+        fn unversioned_files_are_migrated() {
+            let group = FormationGroup::parse_from_yaml(include_str!(
+                "../../../config/scenarios/Merge/formation.yaml"
+            ))
+            .expect("the formation file of the merge scenario is valid");
+            assert_eq!(group.version, FormationGroup::SCHEMA.version);
+            assert!(group
+                .formations
+                .iter()
+                .all(|formation| formation.repeat.is_some()));
+        }
+
+        #[test]
+        fn repeat_every_is_moved_into_repeat() {
+            let group = FormationGroup::parse_from_yaml(
+                r"
+                formations:
+                - repeat-every:
+                    secs: 4
+                    nanos: 0
+                  delay:
+                    secs: 2
+                    nanos: 0
+                  robots: 1
+                  planning-strategy: only-local
+                  initial-position:
+                    shape: !line-segment
+                    - x: 0.45
+                      y: 0.0
+                    - x: 0.55
+                      y: 0.0
+                    placement-strategy: equal
+                  waypoints:
+                  - shape: !line-segment
+                    - x: 0.45
+                      y: 1.0
+                    - x: 0.55
+                      y: 1.0
+                    projection-strategy: identity
+                  waypoint-reached-when-intersects:
+                    intersects-with: horizon
+                ",
+            )
+            .expect("version 1 files are migrated");
+            assert_eq!(group.version, FormationGroup::SCHEMA.version);
+
+            let repeat = group.formations[0].repeat.expect("repeat-every is moved");
+            assert_eq!(repeat.every, Duration::from_secs(4));
+            assert!(matches!(repeat.times, RepeatTimes::Infinite));
+        }
+    }
 }
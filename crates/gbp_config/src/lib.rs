@@ -23,6 +23,44 @@ pub enum ParseError {
     Io(#[from] std::io::Error),
     #[error("TOML error: {0}")]
     Toml(#[from] toml::de::Error),
+    #[error("Migration error: {0}")]
+    Migration(#[from] gbp_migration::MigrationError),
+}
+
+/// Error returned by [`Config::with_overrides`]
+#[derive(Debug, thiserror::Error)]
+pub enum OverrideError {
+    #[error("unknown config key: {0}")]
+    UnknownKey(String),
+    #[error("TOML serialization error: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error("invalid config after overrides: {0}")]
+    Deserialize(#[from] toml::de::Error),
+}
+
+/// Set the config key `key` to `value`, e.g. `gbp.sigma-factor-interrobot`.
+/// The key is given as a path of the kebab-case names of the sections and
+/// fields, as in the config file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigOverride {
+    pub key:   String,
+    pub value: toml::Value,
+}
+
+impl ConfigOverride {
+    #[must_use]
+    pub fn new(key: impl Into<String>, value: impl Into<toml::Value>) -> Self {
+        Self {
+            key:   key.into(),
+            value: value.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigOverride {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.key, self.value)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -45,12 +83,130 @@ pub struct GraphvizInterrobotSection {
     // pub edge: GraphvizEdgeAttributes,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GraphvizNodeAttributes {
+    pub color: String,
+    // TODO: validate against the node shapes of graphviz: https://graphviz.org/doc/info/shapes.html
+    pub shape: String,
+    pub width: f32,
+}
+
+impl GraphvizNodeAttributes {
+    fn new(color: &str, shape: &str, width: f32) -> Self {
+        Self {
+            color: color.to_string(),
+            shape: shape.to_string(),
+            width,
+        }
+    }
+}
+
+/// Attributes of the nodes of each kind
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct GraphvizNodesSection {
+    pub variable:   GraphvizNodeAttributes,
+    pub interrobot: GraphvizNodeAttributes,
+    pub dynamic:    GraphvizNodeAttributes,
+    pub obstacle:   GraphvizNodeAttributes,
+    pub tracking:   GraphvizNodeAttributes,
+    #[serde(default = "GraphvizNodesSection::default_region")]
+    pub region:     GraphvizNodeAttributes,
+    #[serde(default = "GraphvizNodesSection::default_pose")]
+    pub pose:       GraphvizNodeAttributes,
+}
+
+impl GraphvizNodesSection {
+    fn default_region() -> GraphvizNodeAttributes {
+        GraphvizNodeAttributes::new("#c6a0f6", "square", 0.2)
+    }
+
+    fn default_pose() -> GraphvizNodeAttributes {
+        GraphvizNodeAttributes::new("#f5bde6", "square", 0.2)
+    }
+}
+
+impl Default for GraphvizNodesSection {
+    fn default() -> Self {
+        Self {
+            variable:   GraphvizNodeAttributes::new("#eff1f5", "circle", 0.8),
+            interrobot: GraphvizNodeAttributes::new("#a6da95", "square", 0.2),
+            dynamic:    GraphvizNodeAttributes::new("#8aadf4", "square", 0.2),
+            obstacle:   GraphvizNodeAttributes::new("#ee99a0", "square", 0.2),
+            tracking:   GraphvizNodeAttributes::new("#f4a15a", "square", 0.2),
+            region:     Self::default_region(),
+            pose:       Self::default_pose(),
+        }
+    }
+}
+
+/// Layout engine used to render the graph
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GraphvizLayout {
+    Dot,
+    #[default]
+    Neato,
+    Fdp,
+    Sfdp,
+    Circo,
+    Twopi,
+}
+
+impl GraphvizLayout {
+    /// Name of the layout engine, as used by the `layout` graph attribute
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Dot => "dot",
+            Self::Neato => "neato",
+            Self::Fdp => "fdp",
+            Self::Sfdp => "sfdp",
+            Self::Circo => "circo",
+            Self::Twopi => "twopi",
+        }
+    }
+}
+
+/// Format the exported graph is rendered to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GraphvizFormat {
+    #[default]
+    Png,
+    Svg,
+    Pdf,
+}
+
+impl GraphvizFormat {
+    /// File extension of the format, as passed to `dot -T`
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Svg => "svg",
+            Self::Pdf => "pdf",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct GraphvizSection {
-    pub interrobot:      GraphvizInterrobotSection,
+    pub interrobot: GraphvizInterrobotSection,
     #[serde(default = "GraphvizSection::default_export_location")]
     pub export_location: String,
+    #[serde(default)]
+    pub nodes: GraphvizNodesSection,
+    #[serde(default)]
+    pub layout: GraphvizLayout,
+    #[serde(default)]
+    pub format: GraphvizFormat,
+    /// Label the edges with the number of messages sent and received by the
+    /// factor of the edge
+    #[serde(default)]
+    pub message_counts: bool,
 }
 
 impl GraphvizSection {
@@ -62,7 +218,7 @@ impl GraphvizSection {
 impl Default for GraphvizSection {
     fn default() -> Self {
         Self {
-            interrobot:      GraphvizInterrobotSection {
+            interrobot: GraphvizInterrobotSection {
                 active:   GraphvizEdgeAttributes {
                     style: "solid".to_string(),
                     len:   8.0,
@@ -75,6 +231,10 @@ impl Default for GraphvizSection {
                 },
             },
             export_location: "./assets/".to_string(),
+            nodes: GraphvizNodesSection::default(),
+            layout: GraphvizLayout::default(),
+            format: GraphvizFormat::default(),
+            message_counts: false,
         }
     }
 }
@@ -125,6 +285,112 @@ impl Default for ManualSection {
     }
 }
 
+/// **Export section:**
+/// Contains parameters for the data exported when a simulation ends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ExportSection {
+    /// Write the trajectory of every robot as CSV and GeoJSON alongside the
+    /// JSON export
+    #[serde(default)]
+    pub trajectories: bool,
+    /// Directory the trajectories are written to
+    #[serde(default = "ExportSection::default_directory")]
+    pub directory: String,
+    /// Write a manifest of the exact inputs of every run, i.e. the git
+    /// commit, the effective config, the hashes of the simulation files and
+    /// the seed
+    #[serde(default = "ExportSection::default_manifest")]
+    pub manifest: bool,
+    /// Directory the manifests are written to
+    #[serde(default = "ExportSection::default_manifest_directory")]
+    pub manifest_directory: String,
+    /// Record the state of every robot each timestep, and write it to a file
+    /// when the simulation ends or is reloaded
+    #[serde(default)]
+    pub recorder: RecorderSection,
+}
+
+impl ExportSection {
+    pub fn default_directory() -> String {
+        "./assets/export/trajectories".to_string()
+    }
+
+    pub const fn default_manifest() -> bool {
+        true
+    }
+
+    pub fn default_manifest_directory() -> String {
+        "./assets/export/manifests".to_string()
+    }
+}
+
+impl Default for ExportSection {
+    fn default() -> Self {
+        Self {
+            trajectories: false,
+            directory: Self::default_directory(),
+            manifest: Self::default_manifest(),
+            manifest_directory: Self::default_manifest_directory(),
+            recorder: RecorderSection::default(),
+        }
+    }
+}
+
+/// **Recorder Section**
+/// Settings of the trajectory recorder, which samples the position, velocity
+/// and factorgraph energy of every robot each timestep for offline analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RecorderSection {
+    /// Whether to record the robots at all
+    #[serde(default)]
+    pub enabled:   bool,
+    /// Format of the written recordings
+    #[serde(default)]
+    pub format:    RecordingFormat,
+    /// Directory the recordings are written to
+    #[serde(default = "RecorderSection::default_directory")]
+    pub directory: String,
+}
+
+impl RecorderSection {
+    pub fn default_directory() -> String {
+        "./assets/export/recordings".to_string()
+    }
+}
+
+impl Default for RecorderSection {
+    fn default() -> Self {
+        Self {
+            enabled:   false,
+            format:    RecordingFormat::default(),
+            directory: Self::default_directory(),
+        }
+    }
+}
+
+/// File format of a recording
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RecordingFormat {
+    #[default]
+    Csv,
+    /// Needs the `parquet` feature, falls back to CSV without it
+    Parquet,
+}
+
+impl RecordingFormat {
+    /// File extension of the format
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Parquet => "parquet",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct VisualisationSection {
@@ -134,6 +400,93 @@ pub struct VisualisationSection {
     pub draw: DrawSection,
     #[serde(default)]
     pub uncertainty: UncertaintySection,
+    /// Bake the walls of the tile grid into one merged mesh per chunk of
+    /// tiles, instead of spawning an entity per wall. Merged walls can not be
+    /// clicked on
+    #[serde(default)]
+    pub merge_static_map: bool,
+    #[serde(default)]
+    pub follow_camera: FollowCameraSection,
+    /// Colour scheme of the application. Follows the theme of the window
+    /// manager if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<ThemeFlavour>,
+    /// Movement mode of the main camera at startup
+    #[serde(default)]
+    pub camera: CameraMode,
+    #[serde(default)]
+    pub only_selected: OnlySelectedSection,
+}
+
+/// **Only selected section:**
+/// Visualisations drawn only for the selected robot instead of every robot,
+/// when they are enabled in the draw section. Drawing them for every robot
+/// becomes unreadable, and slow, with many robots
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct OnlySelectedSection {
+    #[serde(default)]
+    pub predicted_trajectories: bool,
+    #[serde(default)]
+    pub uncertainty: bool,
+    /// Both the obstacle and the interrobot factors
+    #[serde(default)]
+    pub factors: bool,
+}
+
+/// One of the flavours of the catppuccin colour scheme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeFlavour {
+    Latte,
+    Frappe,
+    Macchiato,
+    Mocha,
+}
+
+/// How the main camera is moved
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CameraMode {
+    #[default]
+    Pan,
+    Orbit,
+    Fly,
+}
+
+/// **Follow camera section:**
+/// How the cameras following each robot move
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct FollowCameraSection {
+    /// Position of the camera relative to the robot, as (x, y, z) in world
+    /// coordinates. SI unit: m
+    pub offset:     [f32; 3],
+    /// Time constant of the exponential smoothing of the camera movement.
+    /// 0.0 moves the camera rigidly with the robot. SI unit: s
+    pub smoothing:  f32,
+    /// How far ahead, along the velocity of the robot, the camera looks.
+    /// SI unit: s
+    pub look_ahead: f32,
+}
+
+impl Default for FollowCameraSection {
+    fn default() -> Self {
+        Self {
+            offset:     [0.0, 30.0, 0.0],
+            smoothing:  0.3,
+            look_ahead: 0.5,
+        }
+    }
+}
+
+impl FollowCameraSection {
+    /// Whether the camera moves rigidly with the robot, i.e. neither smoothed
+    /// nor looking ahead
+    #[must_use]
+    pub fn is_rigid(&self) -> bool {
+        self.smoothing <= 0.0 && self.look_ahead <= 0.0
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::EnumIter, strum_macros::EnumString)]
@@ -159,6 +512,11 @@ pub enum DrawSetting {
     RobotRobotCollisions,
     EnvironmentColliders,
     RobotEnvironmentCollisions,
+    Velocities,
+    ObstacleGradients,
+    DroppedMessages,
+    SafetyDiscs,
+    Regions,
     // InfiniteGrid,
 }
 
@@ -185,6 +543,22 @@ pub struct DrawSection {
     pub environment_colliders: bool,
     pub robot_robot_collisions: bool,
     pub robot_environment_collisions: bool,
+    /// Arrow from every robot along its current velocity
+    #[serde(default)]
+    pub velocities: bool,
+    /// Measurement and gradient of the obstacle factors of the selected robot
+    #[serde(default)]
+    pub obstacle_gradients: bool,
+    /// Pulse on a communication link whenever messages on it are dropped
+    #[serde(default)]
+    pub dropped_messages: bool,
+    /// Disc of the size of each robot, with a ring at the interrobot safety
+    /// distance
+    #[serde(default)]
+    pub safety_discs: bool,
+    /// Outline of the cost regions of the environment
+    #[serde(default = "DrawSection::default_regions")]
+    pub regions: bool,
     // pub infinite_grid: bool,
 }
 
@@ -209,12 +583,21 @@ impl Default for DrawSection {
             environment_colliders: false,
             robot_robot_collisions: false,
             robot_environment_collisions: false,
+            velocities: false,
+            obstacle_gradients: false,
+            dropped_messages: false,
+            safety_discs: false,
+            regions: Self::default_regions(),
             // infinite_grid: true,
         }
     }
 }
 
 impl DrawSection {
+    const fn default_regions() -> bool {
+        true
+    }
+
     pub fn to_display_string(name: &str) -> &'static str {
         match name {
             "communication_graph" => "Communication Graph",
@@ -235,6 +618,11 @@ impl DrawSection {
             "environment_colliders" => "Environment Colliders",
             "robot_robot_collisions" => "Robot-Robot Collisions",
             "robot_environment_collisions" => "Robot-Environment Collisions",
+            "velocities" => "Velocities",
+            "obstacle_gradients" => "Obstacle Gradients",
+            "dropped_messages" => "Dropped Messages",
+            "safety_discs" => "Safety Discs",
+            "regions" => "Regions",
             // "infinite_grid" => "Infinite Grid",
             _ => "Unknown",
         }
@@ -277,6 +665,67 @@ impl DrawSection {
     }
 }
 
+/// Size of the simulated world, either given explicitly or derived from the
+/// environment. Written as `"auto"` or a side length in the config.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "WorldSizeRepr", into = "WorldSizeRepr")]
+pub enum WorldSize {
+    /// Derive the size from the tiles of the environment
+    #[default]
+    Auto,
+    /// Side length of the smallest square containing the environment
+    Fixed(StrictlyPositiveFinite<f32>),
+}
+
+impl WorldSize {
+    #[inline]
+    #[must_use]
+    pub const fn is_auto(&self) -> bool {
+        matches!(self, Self::Auto)
+    }
+
+    /// The explicitly given side length, or `derived` if the size is `auto`
+    #[must_use]
+    pub fn get_or(self, derived: f32) -> f32 {
+        match self {
+            Self::Auto => derived,
+            Self::Fixed(size) => size.get(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum WorldSizeRepr {
+    Keyword(String),
+    Size(f32),
+}
+
+impl TryFrom<WorldSizeRepr> for WorldSize {
+    type Error = String;
+
+    fn try_from(value: WorldSizeRepr) -> Result<Self, Self::Error> {
+        match value {
+            WorldSizeRepr::Keyword(keyword) if keyword == "auto" => Ok(Self::Auto),
+            WorldSizeRepr::Keyword(keyword) => Err(format!(
+                "expected a world size or \"auto\", got \"{keyword}\""
+            )),
+            WorldSizeRepr::Size(size) => StrictlyPositiveFinite::<f32>::new(size)
+                .map(Self::Fixed)
+                .map_err(|_| format!("world size must be positive and finite, got {size}")),
+        }
+    }
+}
+
+impl From<WorldSize> for WorldSizeRepr {
+    fn from(value: WorldSize) -> Self {
+        match value {
+            WorldSize::Auto => Self::Keyword("auto".to_string()),
+            WorldSize::Fixed(size) => Self::Size(size.get()),
+        }
+    }
+}
+
 /// **Simulation Section**
 /// Contains parameters for the simulation such as the fixed timestep frequency,
 /// max time to run the simulation, world size, and random seed to get
@@ -303,14 +752,23 @@ pub struct SimulationSection {
     /// SI unit: s
     pub hz: f64,
 
-    // /// The side length of the smallest square that contains the entire
-    // /// simulated environment. Size of the environment in meters.
-    // /// SI unit: m
-    // pub world_size: StrictlyPositiveFinite<f32>,
+    /// The side length of the smallest square that contains the entire
+    /// simulated environment. Computed from the tiles of the environment when
+    /// absent or set to `auto`.
+    /// SI unit: m
+    #[serde(default, skip_serializing_if = "WorldSize::is_auto")]
+    pub world_size: WorldSize,
+
     /// The seed at which random number generators should be seeded, to ensure
     /// deterministic results across simulation runs.
     pub prng_seed: u64,
 
+    /// Seeds to cycle through, one for each reload of the simulation. The
+    /// first seed is used when the simulation is loaded, and takes precedence
+    /// over `prng_seed`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub random_seeds: Vec<u64>,
+
     /// Whether to pause the simulation time when the first robot is spawned
     pub pause_on_spawn: bool,
 
@@ -322,12 +780,42 @@ pub struct SimulationSection {
 
     #[serde(default = "SimulationSection::default_exit_application_on_scenario_finished")]
     pub exit_application_on_scenario_finished: bool,
+
+    /// Whether to interpolate the rendered position of the robots between the
+    /// last two fixed timesteps, so they move smoothly when the display
+    /// refreshes at a different rate than `hz`. Only affects rendering
+    #[serde(default = "SimulationSection::default_interpolate_transforms")]
+    pub interpolate_transforms: bool,
+
+    /// Noise added to the formations when they are spawned
+    #[serde(default)]
+    pub perturbation: PerturbationSection,
+
+    /// Checks made before a robot of a formation is spawned
+    #[serde(default)]
+    pub spawn_validation: SpawnValidationSection,
 }
 
 impl SimulationSection {
     fn default_exit_application_on_scenario_finished() -> bool {
         false
     }
+
+    const fn default_interpolate_transforms() -> bool {
+        false
+    }
+
+    /// The seed following `prng_seed` in `random_seeds`, wrapping around at
+    /// the end. Returns `None` if no `random_seeds` are given
+    #[must_use]
+    pub fn next_random_seed(&self) -> Option<u64> {
+        let index = self
+            .random_seeds
+            .iter()
+            .position(|&seed| seed == self.prng_seed)
+            .map_or(0, |index| (index + 1) % self.random_seeds.len());
+        self.random_seeds.get(index).copied()
+    }
 }
 
 impl Default for SimulationSection {
@@ -338,17 +826,97 @@ impl Default for SimulationSection {
             time_scale: 1.0.try_into().expect("1.0 > 0.0"),
             manual_step_factor: 1,
             hz: 60.0,
-            // world_size: 100.0.try_into().expect("100.0 > 0.0"),
-            // world_size:         StrictlyPositiveFinite::<f32>::new(100.0).expect("100.0 > 0.0"),
+            world_size: WorldSize::Auto,
             prng_seed: 0,
+            random_seeds: Vec::new(),
             pause_on_spawn: false,
             despawn_robot_when_final_waypoint_reached: true,
             exit_application_on_scenario_finished:
                 Self::default_exit_application_on_scenario_finished(),
+            interpolate_transforms: Self::default_interpolate_transforms(),
+            perturbation: PerturbationSection::default(),
+            spawn_validation: SpawnValidationSection::default(),
         }
     }
 }
 
+/// **Spawn Validation Section**
+/// Before a robot of a formation is spawned, its initial position is checked
+/// to be clear of the environment and of the robots already present. A robot
+/// inside the environment is never spawned, as the environment does not move.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SpawnValidationSection {
+    /// Whether to check the initial position of the robots at all
+    #[serde(default = "SpawnValidationSection::default_enabled")]
+    pub enabled: bool,
+    /// What to do with a robot that would overlap another robot
+    #[serde(default)]
+    pub on_robot_overlap: SpawnOverlapPolicy,
+    /// How long to wait for the initial position of a deferred robot to clear,
+    /// before it is not spawned after all.
+    /// SI unit: s
+    #[serde(default = "SpawnValidationSection::default_max_deferral")]
+    pub max_deferral: f32,
+}
+
+impl SpawnValidationSection {
+    const fn default_enabled() -> bool {
+        true
+    }
+
+    const fn default_max_deferral() -> f32 {
+        10.0
+    }
+}
+
+impl Default for SpawnValidationSection {
+    fn default() -> Self {
+        Self {
+            enabled: Self::default_enabled(),
+            on_robot_overlap: SpawnOverlapPolicy::default(),
+            max_deferral: Self::default_max_deferral(),
+        }
+    }
+}
+
+/// What to do with a robot that would be spawned on top of another robot
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SpawnOverlapPolicy {
+    /// Spawn the robot once the other robot has moved out of the way
+    #[default]
+    Defer,
+    /// Do not spawn the robot
+    Reject,
+}
+
+/// **Perturbation Section**
+/// Random noise added to where and when the formations are spawned, so
+/// repeated runs with different seeds do not all start from the same
+/// perfectly symmetric arrangement. Drawn from the seeded PRNG of the
+/// simulation, so a run is reproducible from its seed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PerturbationSection {
+    /// Robots are spawned up to this far from their position in the
+    /// formation. SI unit: m
+    #[serde(default)]
+    pub position: f32,
+    /// Formations are spawned up to this much later than their delay.
+    /// SI unit: s
+    #[serde(default)]
+    pub delay:    f32,
+}
+
+impl PerturbationSection {
+    /// Whether any noise is added
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.position > 0.0 || self.delay > 0.0
+    }
+}
+
 #[derive(
     Debug,
     Clone,
@@ -461,6 +1029,8 @@ pub struct FactorsEnabledSection {
     pub obstacle:   bool,
     #[serde(default = "FactorsEnabledSection::default_tracking")]
     pub tracking:   bool,
+    #[serde(default = "FactorsEnabledSection::default_region")]
+    pub region:     bool,
 }
 
 impl FactorsEnabledSection {
@@ -479,6 +1049,10 @@ impl FactorsEnabledSection {
     fn default_obstacle() -> bool {
         true
     }
+
+    fn default_region() -> bool {
+        true
+    }
 }
 
 impl Default for FactorsEnabledSection {
@@ -489,6 +1063,7 @@ impl Default for FactorsEnabledSection {
             interrobot: Self::default_interrobot(),
             obstacle:   Self::default_obstacle(),
             tracking:   Self::default_tracking(),
+            region:     Self::default_region(),
         }
     }
 }
@@ -536,6 +1111,42 @@ impl Default for TrackingSection {
     }
 }
 
+/// **Conditioning Section**
+/// Contains parameters for monitoring and regularising the precision matrices
+/// of the variable beliefs
+/// - `regularisation_floor`: Added to the diagonal of every variable precision
+///   matrix before it is inverted. `0.0` disables regularisation.
+/// - `max_condition_number`: Condition number above which a variable precision
+///   matrix is considered close to singular. A warning is emitted when a
+///   variable crosses it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConditioningSection {
+    #[serde(default = "ConditioningSection::default_regularisation_floor")]
+    pub regularisation_floor: f64,
+    #[serde(default = "ConditioningSection::default_max_condition_number")]
+    pub max_condition_number: f64,
+}
+
+impl ConditioningSection {
+    fn default_regularisation_floor() -> f64 {
+        0.0
+    }
+
+    fn default_max_condition_number() -> f64 {
+        1e12
+    }
+}
+
+impl Default for ConditioningSection {
+    fn default() -> Self {
+        Self {
+            regularisation_floor: Self::default_regularisation_floor(),
+            max_condition_number: Self::default_max_condition_number(),
+        }
+    }
+}
+
 /// **GBP Section**
 /// Contains parameters for the GBP algorithm. These paraneters are used for
 /// initialisation of factors and prediction horizon steps.
@@ -552,6 +1163,10 @@ pub struct GbpSection {
     pub sigma_factor_obstacle: f32,
     /// Sigma for Tracking factors
     pub sigma_factor_tracking: f32,
+    /// Sigma for the factors penalising variables inside the cost regions of
+    /// the environment
+    #[serde(default = "GbpSection::default_sigma_factor_region")]
+    pub sigma_factor_region: f32,
     /// Parameter affecting how planned path is spaced out in time
     pub lookahead_multiple: usize,
     /// Tracking section
@@ -565,12 +1180,123 @@ pub struct GbpSection {
     /// Number of variables to create
     #[serde(default = "GbpSection::default_variables")]
     pub variables: usize,
+    /// Monitoring and regularisation of variable precision matrices
+    #[serde(default)]
+    pub conditioning: ConditioningSection,
+    /// Run the GBP iterations of a timestep on a background thread, and apply
+    /// the result in the following timestep, instead of blocking the frame
+    #[serde(default)]
+    pub asynchronous: bool,
+    /// Between two robots of different priority, the sigma of the interrobot
+    /// factors of the robot with the higher priority is multiplied by this
+    /// ratio, and the sigma of the other robot divided by it. The robot with
+    /// the lower priority then does most of the avoiding.
+    /// **constraint**: >= 1.0
+    #[serde(default = "GbpSection::default_priority_sigma_ratio")]
+    pub priority_sigma_ratio: f32,
+    /// What robots share of their plans with each other, i.e. which variables
+    /// of the other robot the interrobot factors are connected to
+    #[serde(default)]
+    pub intention_sharing: IntentionSharing,
+    /// In which order the factors of a factorgraph send their messages in an
+    /// internal iteration
+    #[serde(default)]
+    pub message_schedule: MessageSchedule,
+    /// Factors `sigma_factor_interrobot` is scaled by for specific pairs of
+    /// robot classes, e.g. to make forklifts keep a wider berth of AGVs than
+    /// AGVs of each other. Pairs without an entry are not scaled
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub interrobot_sigma_scales: Vec<InterRobotSigmaScale>,
+    /// Sample the SDF for the obstacle factors of all robots in one batch
+    /// before every internal iteration, instead of in each factor. The batch
+    /// runs on the GPU when built with the experimental `gpu` feature
+    #[serde(default)]
+    pub batch_obstacle_lookups: bool,
+    /// Relinearise nonlinear factors around the current means of their
+    /// variables on every update. If disabled, they are linearised once and
+    /// their potential is cached, like that of linear factors
+    #[serde(default = "GbpSection::default_jit_linearisation")]
+    pub jit_linearisation: bool,
+}
+
+/// Scale of the sigma of the interrobot factors a robot of class `robot`
+/// creates towards a robot of class `other`. The scale only applies in that
+/// direction, so asymmetric policies are possible. A scale below 1.0 makes
+/// the factors stricter, and one above 1.0 makes them more lenient
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct InterRobotSigmaScale {
+    /// Class of the robot the factors belong to
+    pub robot: String,
+    /// Class of the robot the factors are connected to
+    pub other: String,
+    /// **constraint**: > 0.0
+    pub scale: StrictlyPositiveFinite<f32>,
+}
+
+/// The order in which factors send messages within a factorgraph
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MessageSchedule {
+    /// Every factor sends its messages based on the beliefs of the previous
+    /// iteration, and the variables update their beliefs afterwards, as in
+    /// **gbpplanner**
+    #[default]
+    Synchronous,
+    /// The factors whose messages changed the most in the previous iteration
+    /// send first, and every variable updates its belief as soon as it
+    /// receives a message, so the factors after it see the new belief
+    ResidualPriority,
+    /// As [`MessageSchedule::ResidualPriority`], but the factors send in a
+    /// random order every iteration
+    RandomSequential,
+}
+
+/// What a robot shares with the robots it is connected to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IntentionSharing {
+    /// The full planned horizon. The interrobot factor of the i'th variable is
+    /// connected to the i'th variable of the other robot, as in
+    /// **gbpplanner**
+    #[default]
+    Horizon,
+    /// Only the current state. All interrobot factors are connected to the
+    /// current variable of the other robot, so other robots are avoided as if
+    /// they stood still
+    CurrentState,
 }
 
 impl GbpSection {
+    /// Factor the sigma of the interrobot factors of a robot of class `robot`
+    /// towards a robot of class `other` is scaled by. Robots without a class
+    /// only match the default of 1.0
+    #[must_use]
+    pub fn interrobot_sigma_scale(&self, robot: Option<&str>, other: Option<&str>) -> f32 {
+        let (Some(robot), Some(other)) = (robot, other) else {
+            return 1.0;
+        };
+        self.interrobot_sigma_scales
+            .iter()
+            .find(|entry| entry.robot == robot && entry.other == other)
+            .map_or(1.0, |entry| entry.scale.get())
+    }
+
     fn default_variables() -> usize {
         10
     }
+
+    const fn default_priority_sigma_ratio() -> f32 {
+        10.0
+    }
+
+    const fn default_sigma_factor_region() -> f32 {
+        0.1
+    }
+
+    const fn default_jit_linearisation() -> bool {
+        true
+    }
 }
 
 impl Default for GbpSection {
@@ -581,6 +1307,7 @@ impl Default for GbpSection {
             sigma_factor_interrobot: 0.01,
             sigma_factor_obstacle: 0.01,
             sigma_factor_tracking: 0.1,
+            sigma_factor_region: Self::default_sigma_factor_region(),
             lookahead_multiple: 3,
             tracking: TrackingSection::default(),
             // iterations_per_timestep: 10,
@@ -588,6 +1315,14 @@ impl Default for GbpSection {
             // FIXME: not properly read when desirialized from toml
             factors_enabled: FactorsEnabledSection::default(),
             variables: Self::default_variables(),
+            conditioning: ConditioningSection::default(),
+            asynchronous: false,
+            priority_sigma_ratio: Self::default_priority_sigma_ratio(),
+            intention_sharing: IntentionSharing::default(),
+            message_schedule: MessageSchedule::default(),
+            interrobot_sigma_scales: Vec::new(),
+            batch_obstacle_lookups: false,
+            jit_linearisation: Self::default_jit_linearisation(),
             // ..Default::default()
         }
     }
@@ -608,13 +1343,111 @@ pub struct CommunicationSection {
     // TODO: use a percentage type instead of f32
     /// Probability for failing to send/receive a message
     pub failure_rate: f32,
+
+    /// Model failures as bursts with a Gilbert–Elliott channel, instead of
+    /// independently with `failure_rate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gilbert_elliott: Option<GilbertElliottSection>,
+
+    /// Also create inter-robot factors between robots outside of `radius`,
+    /// when any pair of their variables at the same timestep are predicted to
+    /// come within the safety distance of each other. Lets fast robots
+    /// driving towards each other connect early enough to avoid a collision
+    #[serde(default)]
+    pub predicted_proximity: bool,
+
+    /// Only create inter-robot factors between robots that can see each
+    /// other, i.e. when the straight line between them is not blocked by the
+    /// environment. Models robots that can not communicate through walls,
+    /// e.g. in the aisles of a warehouse
+    #[serde(default)]
+    pub line_of_sight: bool,
 }
 
 impl Default for CommunicationSection {
     fn default() -> Self {
         Self {
-            radius:       20.0.try_into().expect("20.0 > 0.0"),
+            radius: 20.0.try_into().expect("20.0 > 0.0"),
             failure_rate: 0.2,
+            gilbert_elliott: None,
+            predicted_proximity: false,
+            line_of_sight: false,
+        }
+    }
+}
+
+/// **Gilbert–Elliott Section**
+/// Two-state channel model of the radio of each robot. Every timestep the
+/// channel may change between a good and a bad state, and the probability of
+/// failing to send/receive depends on the state. Gives bursts of failures,
+/// like real radio outages
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case", try_from = "UncheckedGilbertElliottSection")]
+pub struct GilbertElliottSection {
+    /// Probability of going from the good to the bad state
+    pub good_to_bad:       f32,
+    /// Probability of going from the bad to the good state
+    pub bad_to_good:       f32,
+    /// Probability of failing to send/receive a message in the good state
+    #[serde(default)]
+    pub failure_rate_good: f32,
+    /// Probability of failing to send/receive a message in the bad state
+    #[serde(default = "GilbertElliottSection::default_failure_rate_bad")]
+    pub failure_rate_bad:  f32,
+}
+
+impl GilbertElliottSection {
+    const fn default_failure_rate_bad() -> f32 {
+        1.0
+    }
+}
+
+/// [`GilbertElliottSection`] as written in the config, before the
+/// probabilities are checked to be within [0, 1]
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct UncheckedGilbertElliottSection {
+    good_to_bad:       f32,
+    bad_to_good:       f32,
+    #[serde(default)]
+    failure_rate_good: f32,
+    #[serde(default = "GilbertElliottSection::default_failure_rate_bad")]
+    failure_rate_bad:  f32,
+}
+
+impl TryFrom<UncheckedGilbertElliottSection> for GilbertElliottSection {
+    type Error = String;
+
+    fn try_from(value: UncheckedGilbertElliottSection) -> Result<Self, Self::Error> {
+        for (name, probability) in [
+            ("good-to-bad", value.good_to_bad),
+            ("bad-to-good", value.bad_to_good),
+            ("failure-rate-good", value.failure_rate_good),
+            ("failure-rate-bad", value.failure_rate_bad),
+        ] {
+            if !(0.0..=1.0).contains(&probability) {
+                return Err(format!(
+                    "{name} must be a probability in [0, 1], got {probability}"
+                ));
+            }
+        }
+
+        Ok(Self {
+            good_to_bad:       value.good_to_bad,
+            bad_to_good:       value.bad_to_good,
+            failure_rate_good: value.failure_rate_good,
+            failure_rate_bad:  value.failure_rate_bad,
+        })
+    }
+}
+
+impl Default for GilbertElliottSection {
+    fn default() -> Self {
+        Self {
+            good_to_bad:       0.01,
+            bad_to_good:       0.1,
+            failure_rate_good: 0.0,
+            failure_rate_bad:  Self::default_failure_rate_bad(),
         }
     }
 }
@@ -653,6 +1486,12 @@ pub struct RobotSection {
     pub planning_horizon: StrictlyPositiveFinite<f32>,
     /// SI unit: m/s
     pub target_speed: StrictlyPositiveFinite<f32>,
+    /// Relative noise on the target speed of each robot. The target speed of
+    /// a robot is scaled by a factor drawn uniformly from
+    /// `[1 - noise, 1 + noise]` when it is spawned, breaking the symmetry of
+    /// scenarios like the circle swap. **constraint**: in [0.0, 1.0)
+    #[serde(default)]
+    pub target_speed_noise: TargetSpeedNoise,
     /// Radius of the robot.
     /// If the robot is not a perfect circle, then set radius to be the smallest
     /// circle that fully encompass the shape of the robot. **constraint**:
@@ -661,6 +1500,64 @@ pub struct RobotSection {
     /// Communication parameters
     pub communication: CommunicationSection,
     pub inter_robot_safety_distance_multiplier: StrictlyPositiveFinite<f32>,
+    /// The local planner moving the robots
+    #[serde(default)]
+    pub local_planner: LocalPlannerKind,
+    /// Battery of the robots
+    #[serde(default)]
+    pub battery: BatterySection,
+    /// Noise on the state estimate of the robots
+    #[serde(default)]
+    pub localization: LocalizationSection,
+    /// Onboard obstacle sensing of the robots
+    #[serde(default)]
+    pub sensing: SensingSection,
+}
+
+/// Relative noise on the target speed of a robot, in [0.0, 1.0). Written as
+/// a plain number in the config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "f32", into = "f32")]
+pub struct TargetSpeedNoise(f32);
+
+impl TargetSpeedNoise {
+    /// Returns the noise as a fraction of the target speed
+    #[inline]
+    #[must_use]
+    pub const fn get(self) -> f32 {
+        self.0
+    }
+}
+
+impl TryFrom<f32> for TargetSpeedNoise {
+    type Error = String;
+
+    fn try_from(value: f32) -> Result<Self, Self::Error> {
+        if (0.0..1.0).contains(&value) {
+            Ok(Self(value))
+        } else {
+            Err(format!("target-speed-noise must be in [0.0, 1.0), got {value}"))
+        }
+    }
+}
+
+impl From<TargetSpeedNoise> for f32 {
+    fn from(value: TargetSpeedNoise) -> Self {
+        value.0
+    }
+}
+
+/// Local planner used by the robots of a simulation, to compare planners on
+/// the same scenario
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LocalPlannerKind {
+    /// Gaussian Belief Propagation
+    #[default]
+    Gbp,
+    /// Move straight towards the next waypoint at the target speed, ignoring
+    /// obstacles and other robots. A baseline for comparison.
+    Direct,
 }
 
 impl Default for RobotSection {
@@ -668,6 +1565,7 @@ impl Default for RobotSection {
         Self {
             planning_horizon: StrictlyPositiveFinite::<f32>::new(5.0).expect("5.0 > 0.0"),
             target_speed: StrictlyPositiveFinite::<f32>::new(4.0).expect("2.0 > 0.0"),
+            target_speed_noise: TargetSpeedNoise::default(),
             // radius: StrictlyPositiveFinite::<f32>::new(1.0).expect("1.0 > 0.0"),
             radius: RobotRadiusSection::default(),
             communication: CommunicationSection::default(),
@@ -675,6 +1573,134 @@ impl Default for RobotSection {
             // **gbpplanner** effectively uses 2.2 * radius with the way they calculate it
             inter_robot_safety_distance_multiplier: StrictlyPositiveFinite::<f32>::new(2.2)
                 .expect("2.2 > 0.0"),
+            local_planner: LocalPlannerKind::default(),
+            battery: BatterySection::default(),
+            localization: LocalizationSection::default(),
+            sensing: SensingSection::default(),
+        }
+    }
+}
+
+/// Battery Section
+/// Energy is measured in an arbitrary unit, relative to `capacity`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BatterySection {
+    /// Give every robot a battery that drains as it moves. Robots with an
+    /// empty battery stop
+    #[serde(default)]
+    pub enabled: bool,
+    /// Energy of a fully charged battery
+    #[serde(default = "BatterySection::default_capacity")]
+    pub capacity: f32,
+    /// Energy used per second, also when standing still
+    #[serde(default = "BatterySection::default_drain_per_second")]
+    pub drain_per_second: f32,
+    /// Energy used per meter driven
+    #[serde(default = "BatterySection::default_drain_per_meter")]
+    pub drain_per_meter: f32,
+    /// Additional energy used per meter driven, for every m/s of speed
+    #[serde(default)]
+    pub drag: f32,
+    /// Fraction of the capacity, below which a robot drives to the nearest
+    /// charging station. **constraint**: in [0.0, 1.0]
+    #[serde(default = "BatterySection::default_low_threshold")]
+    pub low_threshold: f32,
+}
+
+impl BatterySection {
+    pub const fn default_capacity() -> f32 {
+        100.0
+    }
+
+    pub const fn default_drain_per_second() -> f32 {
+        0.05
+    }
+
+    pub const fn default_drain_per_meter() -> f32 {
+        0.2
+    }
+
+    pub const fn default_low_threshold() -> f32 {
+        0.2
+    }
+}
+
+impl Default for BatterySection {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            capacity: Self::default_capacity(),
+            drain_per_second: Self::default_drain_per_second(),
+            drain_per_meter: Self::default_drain_per_meter(),
+            drag: 0.0,
+            low_threshold: Self::default_low_threshold(),
+        }
+    }
+}
+
+/// Localization Section
+/// Noise model for the state estimate of the robots. The current variable of
+/// each robot is anchored to its true state plus Gaussian noise, and a bias
+/// that drifts as a random walk. All zero means perfect ground truth.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LocalizationSection {
+    /// Standard deviation of the position noise. SI unit: m
+    #[serde(default)]
+    pub position:   f32,
+    /// Standard deviation of the velocity noise. SI unit: m/s
+    #[serde(default)]
+    pub velocity:   f32,
+    /// Standard deviation of the position bias after one second of drift.
+    /// SI unit: m/sqrt(s)
+    #[serde(default)]
+    pub bias_drift: f32,
+}
+
+impl LocalizationSection {
+    /// Whether any noise is added
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.position > 0.0 || self.velocity > 0.0 || self.bias_drift > 0.0
+    }
+}
+
+/// Sensing Section
+/// Limit the obstacles a robot knows of to those it has seen. Each robot
+/// starts with an empty map, and obstacles within its sensing range and field
+/// of view are added to the map of its obstacle factors as it drives.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SensingSection {
+    /// Sense obstacles onboard, instead of knowing the whole environment
+    #[serde(default)]
+    pub enabled: bool,
+    /// Range of the sensor. SI unit: m
+    #[serde(default = "SensingSection::default_radius")]
+    pub radius:  f32,
+    /// Field of view of the sensor, centered on the direction the robot
+    /// drives in. SI unit: degrees. **constraint**: in (0.0, 360.0]
+    #[serde(default = "SensingSection::default_fov")]
+    pub fov:     f32,
+}
+
+impl SensingSection {
+    pub const fn default_radius() -> f32 {
+        10.0
+    }
+
+    pub const fn default_fov() -> f32 {
+        360.0
+    }
+}
+
+impl Default for SensingSection {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            radius:  Self::default_radius(),
+            fov:     Self::default_fov(),
         }
     }
 }
@@ -689,14 +1715,70 @@ pub struct InteractionSection {
     pub ui_focus_cancels_inputs: bool,
     /// Default camera distance from the origin.
     /// Can also be interpreted as default zoom level
-    pub default_cam_distance:    f32,
+    pub default_cam_distance: f32,
+    /// Direct velocity control of a single robot
+    #[serde(default)]
+    pub teleoperation: TeleoperationSection,
+    #[serde(default)]
+    pub cursor: CursorSection,
 }
 
 impl Default for InteractionSection {
     fn default() -> Self {
         Self {
             ui_focus_cancels_inputs: true,
-            default_cam_distance:    125.0,
+            default_cam_distance: 125.0,
+            teleoperation: TeleoperationSection::default(),
+            cursor: CursorSection::default(),
+        }
+    }
+}
+
+/// Cursor Section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CursorSection {
+    /// Show the position of the cursor on the ground, and the tile under it,
+    /// in the bottom left corner of the window
+    pub show_readout: bool,
+    /// Snap the cursor to a grid with this spacing, aligned with the bottom
+    /// left corner of the map. 0.0 disables snapping. SI unit: m
+    pub snap: f32,
+}
+
+impl Default for CursorSection {
+    fn default() -> Self {
+        Self {
+            show_readout: true,
+            snap: 0.0,
+        }
+    }
+}
+
+/// Teleoperation Section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TeleoperationSection {
+    /// Listen for velocity commands on this UDP port. Every datagram holds
+    /// the velocity as two numbers `vx vy`, in meters per second.
+    #[serde(default)]
+    pub udp_port: Option<u16>,
+    /// Seconds without a command, before the teleoperated robot stops
+    #[serde(default = "TeleoperationSection::default_timeout")]
+    pub timeout:  f32,
+}
+
+impl TeleoperationSection {
+    pub const fn default_timeout() -> f32 {
+        0.5
+    }
+}
+
+impl Default for TeleoperationSection {
+    fn default() -> Self {
+        Self {
+            udp_port: None,
+            timeout:  Self::default_timeout(),
         }
     }
 }
@@ -756,10 +1838,102 @@ impl Default for SmoothingSection {
     }
 }
 
-#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+/// Verbosity of the log output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for bevy::log::Level {
+    fn from(value: LogLevel) -> Self {
+        match value {
+            LogLevel::Error => Self::ERROR,
+            LogLevel::Warn => Self::WARN,
+            LogLevel::Info => Self::INFO,
+            LogLevel::Debug => Self::DEBUG,
+            LogLevel::Trace => Self::TRACE,
+        }
+    }
+}
+
+/// How often a new log file is started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+/// **Debug Section**
+/// Contains parameters for debug instrumentation. Apart from
+/// `on-variable-clicked`, they are applied when the app is built, so only the
+/// values in the config of the initial simulation take effect
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct DebugSection {
+    #[serde(default)]
     pub on_variable_clicked: OnVariableClickedSection,
+    /// Level of the log output
+    #[serde(default)]
+    pub log_level: LogLevel,
+    /// Filter directives of the log output, e.g. `wgpu=error,magics=debug`
+    #[serde(default = "DebugSection::default_log_filter")]
+    pub log_filter: String,
+    /// Also write the log to this file as JSON lines. The date is appended to
+    /// the file name, when the file is rotated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_file: Option<std::path::PathBuf>,
+    /// How often a new log file is started
+    #[serde(default)]
+    pub log_rotation: LogRotation,
+    /// Measure the frame rate and frame time
+    #[serde(default = "DebugSection::default_enabled")]
+    pub fps_counter: bool,
+    /// Count the number of entities
+    #[serde(default = "DebugSection::default_enabled")]
+    pub entity_counter: bool,
+    /// Collect diagnostics of the solver, such as the number of variables,
+    /// factors and messages
+    #[serde(default = "DebugSection::default_enabled")]
+    pub solver_diagnostics: bool,
+    /// Periodically write the collected diagnostics to the log
+    #[serde(default = "DebugSection::default_enabled")]
+    pub log_diagnostics: bool,
+}
+
+impl DebugSection {
+    fn default_log_filter() -> String {
+        "wgpu=error,naga=warn".to_string()
+    }
+
+    const fn default_enabled() -> bool {
+        true
+    }
+}
+
+impl Default for DebugSection {
+    fn default() -> Self {
+        Self {
+            on_variable_clicked: OnVariableClickedSection::default(),
+            log_level: LogLevel::default(),
+            log_filter: Self::default_log_filter(),
+            log_file: None,
+            log_rotation: LogRotation::default(),
+            fps_counter: true,
+            entity_counter: true,
+            solver_diagnostics: true,
+            log_diagnostics: true,
+        }
+    }
 }
 
 #[derive(
@@ -796,6 +1970,9 @@ impl Default for OnVariableClickedSection {
 /// Collection of all the sections in the config file
 #[derive(Debug, Clone, Serialize, Deserialize, Resource)]
 pub struct Config {
+    /// Version of the schema of the config file. See [`Config::SCHEMA`]
+    #[serde(default = "Config::latest_version")]
+    pub version: u32,
     /// Path to the **.png** containing the environment sdf
     pub environment_image: String,
     /// Path to the environment configuration file
@@ -837,6 +2014,10 @@ pub struct Config {
     /// Contains parameters for manual time-stepping
     #[serde(default)]
     pub manual: ManualSection,
+    /// **Export section:**
+    /// Contains parameters for the data exported when a simulation ends
+    #[serde(default)]
+    pub export: ExportSection,
 
     #[serde(default)]
     pub debug: DebugSection,
@@ -855,6 +2036,7 @@ impl Default for Config {
         let default_formation_config = "./config/formation.ron".to_string();
 
         Self {
+            version: Self::SCHEMA.version,
             environment_image: default_environment_image,
             environment: default_environment_config,
             formation_group: default_formation_config,
@@ -866,12 +2048,36 @@ impl Default for Config {
             rrt: RRTSection::default(),
             graphviz: GraphvizSection::default(),
             manual: ManualSection::default(),
+            export: ExportSection::default(),
             debug: DebugSection::default(),
         }
     }
 }
 
 impl Config {
+    /// The schema of the config file. Version 1 is the unversioned format
+    pub const SCHEMA: gbp_migration::Schema = gbp_migration::Schema {
+        name:       "config",
+        version:    2,
+        migrations: &[gbp_migration::Migration {
+            version: 2,
+            changes: &[
+                gbp_migration::Change::Removed {
+                    key:    "visualisation.draw.infinite-grid",
+                    reason: "the infinite grid is toggled in the settings panel",
+                },
+                gbp_migration::Change::Removed {
+                    key:    "visualisation.draw.height-map",
+                    reason: "the height map is no longer drawn, see `visualisation.draw.sdf`",
+                },
+            ],
+        }],
+    };
+
+    const fn latest_version() -> u32 {
+        Self::SCHEMA.version
+    }
+
     /// Parse a config file from a given path
     pub fn from_file<P>(path: P) -> Result<Self, ParseError>
     where
@@ -884,11 +2090,157 @@ impl Config {
         // Self::parse(file_contents.as_str())
     }
 
-    /// Parse a config file
+    /// Parse a config file. Files written for older versions of the schema
+    /// are migrated, with a warning for every deprecated key they set
     /// Returns a `ParseError` if the file cannot be parsed
     pub fn parse(contents: &str) -> Result<Self, ParseError> {
-        toml::from_str(contents).map_err(Into::into)
+        let mut table: toml::Table = toml::from_str(contents)?;
+        for deprecation in Self::SCHEMA.migrate(&mut table)? {
+            bevy::log::warn!("{deprecation}");
+        }
+        toml::Value::Table(table).try_into().map_err(Into::into)
         // let config = toml::from_str(contents)?;
         // Ok(config)
     }
+
+    /// Create a copy of the config with each of `overrides` applied, in order
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a key does not exist in the config, e.g. an
+    /// optional section that is not set, or a value has the wrong type
+    pub fn with_overrides<'a>(
+        &self,
+        overrides: impl IntoIterator<Item = &'a ConfigOverride>,
+    ) -> Result<Self, OverrideError> {
+        let mut config = toml::Value::try_from(self)?;
+        for ConfigOverride { key, value } in overrides {
+            let mut field = &mut config;
+            for name in key.split('.') {
+                field = field
+                    .get_mut(name)
+                    .ok_or_else(|| OverrideError::UnknownKey(key.clone()))?;
+            }
+            field.clone_from(value);
+        }
+
+        config.try_into().map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn sigma_scale(robot: &str, other: &str, scale: f32) -> InterRobotSigmaScale {
+        InterRobotSigmaScale {
+            robot: robot.to_string(),
+            other: other.to_string(),
+            scale: scale.try_into().unwrap(),
+        }
+    }
+
+    #[test]
+    fn interrobot_sigma_scale_is_looked_up_per_direction() {
+        let gbp = GbpSection {
+            interrobot_sigma_scales: vec![
+                sigma_scale("forklift", "agv", 0.5),
+                sigma_scale("agv", "agv", 2.0),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            gbp.interrobot_sigma_scale(Some("forklift"), Some("agv")),
+            0.5
+        );
+        assert_eq!(gbp.interrobot_sigma_scale(Some("agv"), Some("agv")), 2.0);
+        // only the direction given is scaled
+        assert_eq!(
+            gbp.interrobot_sigma_scale(Some("agv"), Some("forklift")),
+            1.0
+        );
+        assert_eq!(gbp.interrobot_sigma_scale(Some("forklift"), None), 1.0);
+        assert_eq!(gbp.interrobot_sigma_scale(None, Some("agv")), 1.0);
+    }
+
+    #[test]
+    fn interrobot_sigma_scale_must_be_positive() {
+        let parse = |scale: &str| {
+            toml::from_str::<InterRobotSigmaScale>(&format!(
+                "robot = \"agv\"\nother = \"agv\"\nscale = {scale}"
+            ))
+        };
+        assert!(parse("1.5").is_ok());
+        for scale in ["0.0", "-1.0", "inf", "nan"] {
+            assert!(parse(scale).is_err(), "{scale}");
+        }
+    }
+
+    #[test]
+    fn target_speed_noise_is_checked_when_the_config_is_loaded() {
+        let mut config = toml::Value::try_from(Config::default()).unwrap();
+        for noise in [0.0, 0.5] {
+            config["robot"]["target-speed-noise"] = noise.into();
+            let parsed = Config::parse(&toml::to_string(&config).unwrap()).unwrap();
+            assert_eq!(f64::from(parsed.robot.target_speed_noise.get()), noise);
+        }
+
+        for noise in [1.0, -0.1, f64::NAN] {
+            config["robot"]["target-speed-noise"] = noise.into();
+            assert!(
+                matches!(
+                    Config::parse(&toml::to_string(&config).unwrap()),
+                    Err(ParseError::Toml(_))
+                ),
+                "{noise}"
+            );
+        }
+    }
+
+    #[test]
+    fn gilbert_elliott_defaults_the_failure_rates() {
+        let channel: GilbertElliottSection =
+            toml::from_str("good-to-bad = 0.05\nbad-to-good = 0.5").unwrap();
+        assert_eq!(channel.good_to_bad, 0.05);
+        assert_eq!(channel.bad_to_good, 0.5);
+        assert_eq!(channel.failure_rate_good, 0.0);
+        assert_eq!(channel.failure_rate_bad, 1.0);
+    }
+
+    #[test]
+    fn gilbert_elliott_rejects_probabilities_outside_the_unit_interval() {
+        for contents in [
+            "good-to-bad = 1.5\nbad-to-good = 0.5",
+            "good-to-bad = 0.1\nbad-to-good = -0.1",
+            "good-to-bad = 0.1\nbad-to-good = 0.5\nfailure-rate-good = 2.0",
+            "good-to-bad = 0.1\nbad-to-good = 0.5\nfailure-rate-bad = nan",
+        ] {
+            let error = toml::from_str::<GilbertElliottSection>(contents).unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .contains("must be a probability in [0, 1]"),
+                "{contents:?}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn gilbert_elliott_is_checked_when_the_config_is_loaded() {
+        let mut config = toml::Value::try_from(Config::default()).unwrap();
+        let channel = toml::Value::try_from(GilbertElliottSection::default()).unwrap();
+        config["robot"]["communication"]
+            .as_table_mut()
+            .unwrap()
+            .insert("gilbert-elliott".to_string(), channel);
+        assert!(Config::parse(&toml::to_string(&config).unwrap()).is_ok());
+
+        config["robot"]["communication"]["gilbert-elliott"]["good-to-bad"] = 10.0.into();
+        assert!(matches!(
+            Config::parse(&toml::to_string(&config).unwrap()),
+            Err(ParseError::Toml(_))
+        ));
+    }
 }
@@ -1,8 +1,9 @@
 use std::path::Path;
 
-use angle::Angle;
+use angle::{Angle, AngleError, AngleUnit, UncheckedAngle};
 use bevy::{
     ecs::{component::Component, system::Resource},
+    log::warn,
     math::Vec2,
 };
 use derive_more::IntoIterator;
@@ -12,7 +13,11 @@ use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use typed_floats::StrictlyPositiveFinite;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Component)]
+#[cfg(feature = "osm")]
+pub mod osm;
+pub mod random;
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, Component)]
 #[serde(rename_all = "kebab-case")]
 pub struct TileCoordinates {
     pub row: usize,
@@ -26,20 +31,87 @@ impl TileCoordinates {
     }
 }
 
+/// A grid of box drawing characters, where each character is a tile.
+/// Serialized as a list of strings, one per row, but stored as characters so
+/// a tile can be looked up in constant time
 #[derive(Debug, Clone, Serialize, Deserialize, IntoIterator)]
 #[into_iterator(owned, ref)]
-#[serde(rename_all = "kebab-case")]
-pub struct TileGrid(Vec<String>);
+#[serde(rename_all = "kebab-case", from = "Vec<String>", into = "Vec<String>")]
+pub struct TileGrid(Vec<Vec<char>>);
+
+impl From<Vec<String>> for TileGrid {
+    fn from(rows: Vec<String>) -> Self {
+        Self::new(rows)
+    }
+}
+
+impl From<TileGrid> for Vec<String> {
+    fn from(grid: TileGrid) -> Self {
+        grid.0
+            .into_iter()
+            .map(|row| row.into_iter().collect())
+            .collect()
+    }
+}
+
+impl std::ops::Index<(usize, usize)> for TileGrid {
+    type Output = char;
+
+    /// The tile at `(row, col)`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(row, col)` is outside of the grid, see
+    /// [`TileGrid::get_tile`] for a non-panicking alternative
+    fn index(&self, (row, col): (usize, usize)) -> &Self::Output {
+        &self.0[row][col]
+    }
+}
+
+impl std::ops::Index<TileCoordinates> for TileGrid {
+    type Output = char;
+
+    fn index(&self, TileCoordinates { row, col }: TileCoordinates) -> &Self::Output {
+        &self[(row, col)]
+    }
+}
 
 impl TileGrid {
     pub fn new(tiles: Vec<impl Into<String>>) -> Self {
-        Self(tiles.into_iter().map(Into::into).collect())
+        Self(
+            tiles
+                .into_iter()
+                .map(|row| row.into().chars().collect())
+                .collect(),
+        )
     }
 
-    pub fn iter(&self) -> std::slice::Iter<String> {
+    /// Iterate over the rows of the grid, from the top
+    pub fn iter(&self) -> std::slice::Iter<Vec<char>> {
         self.0.iter()
     }
 
+    /// Iterate over the rows of the grid, from the top
+    pub fn rows(&self) -> impl ExactSizeIterator<Item = &[char]> + '_ {
+        self.0.iter().map(Vec::as_slice)
+    }
+
+    /// Iterate over the columns of the grid, from the left. Each column is
+    /// iterated from the top
+    pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = char> + '_> + '_ {
+        (0..self.ncols()).map(move |col| self.0.iter().filter_map(move |row| row.get(col).copied()))
+    }
+
+    /// Iterate over every tile of the grid, row by row from the top left
+    pub fn cells(&self) -> impl Iterator<Item = (TileCoordinates, char)> + '_ {
+        self.0.iter().enumerate().flat_map(|(row, tiles)| {
+            tiles
+                .iter()
+                .enumerate()
+                .map(move |(col, &tile)| (TileCoordinates::new(row, col), tile))
+        })
+    }
+
     #[inline]
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
@@ -54,7 +126,7 @@ impl TileGrid {
     /// Returns number of columns in the tilegrid
     #[inline]
     pub fn ncols(&self) -> usize {
-        self.0[0].chars().count()
+        self.0.first().map_or(0, Vec::len)
     }
 
     /// Returns the shape of the tilegrid (rows, cols)
@@ -65,28 +137,138 @@ impl TileGrid {
 
     /// Returns the tile at the given coordinates
     pub fn get_tile(&self, row: usize, col: usize) -> Option<char> {
-        self.0.get(row).and_then(|r| r.chars().nth(col))
+        self.0.get(row).and_then(|r| r.get(col)).copied()
+    }
+
+    /// Create a tilegrid from an occupancy grid of `nrows` x `ncols` cells,
+    /// where `is_free(row, col)` tells whether a cell is free or blocked.
+    ///
+    /// Every free tile is connected to its free 4-neighbours, by picking the
+    /// box drawing character with the matching openings, e.g. a free cell
+    /// with free cells above and to the right becomes `└`. An isolated free
+    /// cell becomes `┼`, as its openings all lead into blocked tiles anyway.
+    pub fn from_occupancy(
+        nrows: usize,
+        ncols: usize,
+        is_free: impl Fn(usize, usize) -> bool,
+    ) -> Self {
+        let free = |row: usize, col: usize| row < nrows && col < ncols && is_free(row, col);
+
+        let rows = (0..nrows)
+            .map(|row| {
+                (0..ncols)
+                    .map(|col| {
+                        if !free(row, col) {
+                            return ' ';
+                        }
+                        let up = row > 0 && free(row - 1, col);
+                        let right = free(row, col + 1);
+                        let down = free(row + 1, col);
+                        let left = col > 0 && free(row, col - 1);
+                        match (up, right, down, left) {
+                            (true, true, true, true) | (false, false, false, false) => '┼',
+                            (true, false, true, false) => '│',
+                            (false, true, false, true) => '─',
+                            (true, true, true, false) => '├',
+                            (true, false, true, true) => '┤',
+                            (false, true, true, true) => '┬',
+                            (true, true, false, true) => '┴',
+                            (false, true, true, false) => '┌',
+                            (false, false, true, true) => '┐',
+                            (true, true, false, false) => '└',
+                            (true, false, false, true) => '┘',
+                            (false, false, false, true) => '╴',
+                            (false, true, false, false) => '╶',
+                            (false, false, true, false) => '╷',
+                            (true, false, false, false) => '╵',
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self(rows)
     }
 
-    // /// override the index operator to allow for easy access to the grid
-    // pub fn get(&self, row: usize, col: usize) -> Option<char> {
-    //     self.0.get(row).and_then(|r| r.chars().nth(col))
-    // }
+    /// Create a tilegrid from a black-and-white occupancy image, where each
+    /// pixel becomes one tile. Pixels with a luminance greater than or equal
+    /// to `threshold` are free, and the rest are blocked. See
+    /// [`TileGrid::from_occupancy`] for how free tiles are connected.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// 1. `path` can not be opened or decoded as an image
+    /// 2. The image has a width or height of 0
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn from_image<P: AsRef<Path>>(path: P, threshold: u8) -> Result<Self, FromImageError> {
+        let image = image::open(path)?.into_luma8();
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            return Err(FromImageError::EmptyImage);
+        }
+
+        Ok(Self::from_occupancy(
+            height as usize,
+            width as usize,
+            |row, col| image.get_pixel(col as u32, row as u32).0[0] >= threshold,
+        ))
+    }
 }
 
+/// A rotation around the up-axis. Any finite angle is accepted, and wrapped
+/// into [0, 2pi], so e.g. -90 and 450 degrees are both valid.
+/// Deserialized from a number in the `angle-unit` of the environment file, or
+/// a string with a unit, e.g. `90deg` or `1.57rad`. Serialized in radians.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case", try_from = "UncheckedAngle")]
 pub struct Rotation(Angle);
 
 impl Rotation {
-    /// Create a new `Rotation` from a given degree
+    /// Create a new `Rotation` from a given degree, wrapped into [0.0, 360.0]
     ///
     /// # Panics
     ///
-    /// If `degree` is not in [0.0, 360.0]
+    /// If `degree` is NaN or infinite
     #[must_use]
     pub fn new(degree: Float) -> Self {
-        Self(Angle::from_degrees(degree).expect("Invalid angle"))
+        Self::try_new(degree).expect("angle is finite")
+    }
+
+    /// Create a new `Rotation` from a given degree, wrapped into [0.0, 360.0]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `degree` is NaN or infinite
+    pub fn try_new(degree: Float) -> Result<Self, AngleError> {
+        Angle::wrapped_degrees(degree).map(Self)
+    }
+
+    /// Create a new `Rotation` from a given radian, wrapped into [0.0, 2pi]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `radians` is NaN or infinite
+    pub fn try_from_radians(radians: Float) -> Result<Self, AngleError> {
+        Angle::wrapped(radians).map(Self)
+    }
+}
+
+impl TryFrom<Float> for Rotation {
+    type Error = AngleError;
+
+    /// See [`Rotation::try_from_radians`]
+    fn try_from(radians: Float) -> Result<Self, Self::Error> {
+        Self::try_from_radians(radians)
+    }
+}
+
+impl TryFrom<UncheckedAngle> for Rotation {
+    type Error = AngleError;
+
+    /// See [`Rotation::try_from_radians`]
+    fn try_from(UncheckedAngle(radians): UncheckedAngle) -> Result<Self, Self::Error> {
+        Self::try_from_radians(radians)
     }
 }
 
@@ -139,6 +321,12 @@ impl Circle {
         let squared_distance = point.length_squared();
         squared_distance <= self.radius.get().powi(2) as f32
     }
+
+    /// Signed distance from `point` to the edge of the circle, negative
+    /// inside. Expects translation and rotation to be performed beforehand
+    pub fn signed_distance(&self, point: Vec2) -> f32 {
+        point.length() - self.radius.get() as f32
+    }
 }
 
 /// Two angles of a triangle
@@ -224,6 +412,12 @@ impl Triangle {
 
         !(has_neg && has_pos)
     }
+
+    /// Signed distance from `point` to the edges of the triangle, negative
+    /// inside. Expects translation and rotation to be performed beforehand
+    pub fn signed_distance(&self, point: Vec2) -> f32 {
+        polygon_signed_distance(point, &self.points())
+    }
 }
 
 fn sign(p1: Vec2, p2: Vec2, p3: Vec2) -> f32 {
@@ -311,6 +505,20 @@ impl RegularPolygon {
         }
         inside
     }
+
+    /// Signed distance from `point` to the edges of the polygon, negative
+    /// inside. Expects translation and rotation to be performed beforehand
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn signed_distance(&self, point: Vec2) -> f32 {
+        let vertices = self
+            .points()
+            .iter()
+            .map(|[x, y]| Vec2::new(*x as f32, *y as f32))
+            .collect::<Vec<_>>();
+        // the vertices are twice as far from the center as the polygon is
+        // large, see `Self::inside`
+        polygon_signed_distance(point * 2.0, &vertices) / 2.0
+    }
 }
 
 /// A rectangle to be placed in the environment
@@ -358,6 +566,86 @@ impl Rectangle {
 
         false
     }
+
+    /// Signed distance from `point` to the edges of the rectangle, negative
+    /// inside. Expects translation and rotation to be performed beforehand
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn signed_distance(&self, point: Vec2) -> f32 {
+        // same extents as `Self::inside`
+        let half_extents = Vec2::new(
+            self.height.get() as f32 / 4.0,
+            self.width.get() as f32 / 4.0,
+        );
+        let q = point.abs() - half_extents;
+        q.max(Vec2::ZERO).length() + q.max_element().min(0.0)
+    }
+}
+
+/// A capsule, or stadium, to be placed in the environment. A rectangle with a
+/// semicircular cap at each end, centered at the origin.
+/// - A [`PlaceableShape`] variant
+#[derive(Debug, Serialize, Deserialize, Clone, derive_more::Constructor)]
+#[serde(rename_all = "kebab-case")]
+pub struct Capsule {
+    /// The distance between the centers of the two caps, along the local
+    /// x-axis. This is a value in the range [0, 1]
+    pub length: StrictlyPositiveFinite<Float>,
+    /// The radius of the caps, i.e. half the width of the capsule
+    /// This is a value in the range [0, 1]
+    pub radius: StrictlyPositiveFinite<Float>,
+}
+
+impl Capsule {
+    /// Number of points along each cap, when approximating the outline
+    const CAP_RESOLUTION: usize = 16;
+
+    /// Expand the capsule's `radius` by `expansion`
+    pub fn expanded(&self, expansion: Float) -> Self {
+        Self {
+            length: self.length,
+            radius: StrictlyPositiveFinite::<Float>::new(self.radius.get() + expansion).unwrap(),
+        }
+    }
+
+    /// The two end points of the line segment at the core of the capsule
+    pub fn segment(&self) -> [Vec2; 2] {
+        let half_length = self.length.get() as f32 / 2.0;
+        [Vec2::new(-half_length, 0.0), Vec2::new(half_length, 0.0)]
+    }
+
+    /// Counter-clockwise outline of the capsule, with each cap approximated by
+    /// a fixed number of points
+    #[allow(clippy::cast_precision_loss)]
+    pub fn points(&self) -> Vec<Vec2> {
+        let [start, end] = self.segment();
+        let radius = self.radius.get() as f32;
+        let cap = |center: Vec2, from: f32| {
+            (0..Self::CAP_RESOLUTION).map(move |i| {
+                let angle = (i as f32 / (Self::CAP_RESOLUTION - 1) as f32)
+                    .mul_add(std::f32::consts::PI, from);
+                center + Vec2::from_angle(angle) * radius
+            })
+        };
+
+        cap(end, -std::f32::consts::FRAC_PI_2)
+            .chain(cap(start, std::f32::consts::FRAC_PI_2))
+            .collect()
+    }
+
+    /// Check if a given point is inside the capsule
+    /// Expects translation and rotation to be performed beforehand
+    pub fn inside(&self, point: Vec2) -> bool {
+        let [start, end] = self.segment();
+        let closest = point.clamp(start, end);
+        point.distance_squared(closest) <= (self.radius.get() as f32).powi(2)
+    }
+
+    /// Signed distance from `point` to the edge of the capsule, negative
+    /// inside. Expects translation and rotation to be performed beforehand
+    pub fn signed_distance(&self, point: Vec2) -> f32 {
+        let [start, end] = self.segment();
+        segment_distance(point, start, end) - self.radius.get() as f32
+    }
 }
 
 /// A irregular polygon to be placed in the environment
@@ -367,6 +655,7 @@ impl Rectangle {
 pub struct Polygon {
     /// The points of the polygon
     /// Each point with an x and y coordinate in the range [0, 1]
+    #[serde(alias = "vertices")]
     pub points: Vec<Point>,
 }
 
@@ -417,6 +706,18 @@ impl Polygon {
                 .as_slice(),
         )
     }
+
+    /// Signed distance from `point` to the edges of the polygon, negative
+    /// inside. Expects translation and rotation to be performed beforehand
+    pub fn signed_distance(&self, point: Vec2) -> f32 {
+        let vertices = self
+            .points
+            .iter()
+            .copied()
+            .map(Vec2::from)
+            .collect::<Vec<_>>();
+        polygon_signed_distance(point, &vertices)
+    }
 }
 
 fn is_point_in_polygon(point: (f64, f64), polygon: &[(f64, f64)]) -> bool {
@@ -437,6 +738,96 @@ fn is_point_in_polygon(point: (f64, f64), polygon: &[(f64, f64)]) -> bool {
     inside
 }
 
+/// Distance from `point` to the line segment from `start` to `end`
+fn segment_distance(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let t = (point - start).dot(segment) / segment.length_squared().max(f32::EPSILON);
+    point.distance(start + segment * t.clamp(0.0, 1.0))
+}
+
+/// Signed distance from `point` to the edges of the polygon with the corners
+/// `vertices`, negative inside by the even-odd rule
+fn polygon_signed_distance(point: Vec2, vertices: &[Vec2]) -> f32 {
+    if vertices.is_empty() {
+        return f32::INFINITY;
+    }
+    let distance = vertices
+        .iter()
+        .circular_tuple_windows()
+        .map(|(start, end)| segment_distance(point, *start, *end))
+        .fold(f32::INFINITY, f32::min);
+    let inside = is_point_in_polygon(
+        (f64::from(point.x), f64::from(point.y)),
+        &vertices
+            .iter()
+            .map(|vertex| (f64::from(vertex.x), f64::from(vertex.y)))
+            .collect::<Vec<_>>(),
+    );
+    if inside {
+        -distance
+    } else {
+        distance
+    }
+}
+
+/// One of the shapes of a [`Composite`]
+#[derive(Debug, Serialize, Deserialize, Clone, derive_more::Constructor)]
+#[serde(rename_all = "kebab-case")]
+pub struct CompositePart {
+    /// The shape of the part
+    pub shape:  PlaceableShape,
+    /// Offset of the part from the translation of the obstacle, relative to
+    /// the tile size. The offset is rotated together with the obstacle.
+    pub offset: Point,
+}
+
+impl CompositePart {
+    /// The offset as a [`Vec2`], rotated by `rotation` radians
+    pub fn rotated_offset(&self, rotation: f32) -> Vec2 {
+        Vec2::from_angle(rotation).rotate(Vec2::from(self.offset))
+    }
+}
+
+/// The union of several shapes, placed as a single obstacle, e.g. an L-shaped
+/// building made from two rectangles. All parts share the rotation and tile
+/// of the obstacle.
+/// - A [`PlaceableShape`] variant
+#[derive(Debug, Serialize, Deserialize, Clone, derive_more::Constructor)]
+#[serde(rename_all = "kebab-case")]
+pub struct Composite {
+    pub parts: Vec<CompositePart>,
+}
+
+impl Composite {
+    /// Expand every part by `expansion`
+    pub fn expanded(&self, expansion: Float) -> Self {
+        Self {
+            parts: self
+                .parts
+                .iter()
+                .map(|part| CompositePart::new(part.shape.expanded(expansion), part.offset))
+                .collect(),
+        }
+    }
+
+    /// Check if a given point is inside any of the parts
+    /// Expects translation and rotation to be performed beforehand
+    pub fn inside(&self, point: Vec2) -> bool {
+        self.parts
+            .iter()
+            .any(|part| part.shape.inside(point - Vec2::from(part.offset)))
+    }
+
+    /// Signed distance from `point` to the nearest part, negative inside any
+    /// of them. Expects translation and rotation to be performed beforehand
+    pub fn signed_distance(&self, point: Vec2) -> f32 {
+        self.parts
+            .iter()
+            .map(|part| part.shape.signed_distance(point - Vec2::from(part.offset)))
+            .fold(f32::INFINITY, f32::min)
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, strum_macros::EnumTryAs)]
 #[serde(rename_all = "kebab-case")]
 pub enum PlaceableShape {
@@ -445,6 +836,8 @@ pub enum PlaceableShape {
     RegularPolygon(RegularPolygon),
     Polygon(Polygon),
     Rectangle(Rectangle),
+    Capsule(Capsule),
+    Composite(Composite),
 }
 
 impl PlaceableShape {
@@ -476,30 +869,106 @@ impl PlaceableShape {
         ))
     }
 
-    #[allow(clippy::unwrap_used, clippy::missing_panics_doc)]
+    /// Create a new `Self::Rectangle`
+    ///
+    /// # Panics
+    ///
+    /// If `width` or `height` is not positive and finite, see
+    /// [`PlaceableShape::try_rectangle`]
     pub fn rectangle(width: Float, height: Float) -> Self {
-        Self::Rectangle(Rectangle::new(
-            StrictlyPositiveFinite::<Float>::new(width).unwrap(),
-            StrictlyPositiveFinite::<Float>::new(height).unwrap(),
-            // RelativePoint::new(center.0, center.1).unwrap(),
-        ))
+        Self::try_rectangle(width, height).expect("valid rectangle")
+    }
+
+    /// Create a new `Self::Rectangle`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `width` or `height` is not positive and finite
+    pub fn try_rectangle(width: Float, height: Float) -> Result<Self, ObstacleError> {
+        Ok(Self::Rectangle(Rectangle::new(
+            positive_finite("width", width)?,
+            positive_finite("height", height)?,
+        )))
     }
 
-    #[allow(clippy::unwrap_used, clippy::missing_panics_doc)]
+    /// Create a new square, i.e. a `Self::RegularPolygon` with 4 sides
+    ///
+    /// # Panics
+    ///
+    /// If `side_length` is not positive and finite
     pub fn square(side_length: Float) -> Self {
-        Self::RegularPolygon(RegularPolygon::new(
-            4,
-            StrictlyPositiveFinite::<Float>::new(side_length).unwrap(),
-            // RelativePoint::new(center.0, center.1).unwrap(),
-        ))
+        Self::regular_polygon(4, side_length)
     }
 
-    #[allow(clippy::unwrap_used, clippy::missing_panics_doc)]
+    /// Create a new `Self::RegularPolygon`
+    ///
+    /// # Panics
+    ///
+    /// If `sides` is less than 3, or `side_length` is not positive and
+    /// finite, see [`PlaceableShape::try_regular_polygon`]
     pub fn regular_polygon(sides: usize, side_length: Float) -> Self {
-        Self::RegularPolygon(RegularPolygon::new(
+        Self::try_regular_polygon(sides, side_length).expect("valid regular polygon")
+    }
+
+    /// Create a new `Self::RegularPolygon`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `sides` is less than 3, or `side_length` is not
+    /// positive and finite
+    pub fn try_regular_polygon(sides: usize, side_length: Float) -> Result<Self, ObstacleError> {
+        if sides < 3 {
+            return Err(ObstacleError::TooFewSides(sides));
+        }
+        Ok(Self::RegularPolygon(RegularPolygon::new(
             sides,
-            StrictlyPositiveFinite::<Float>::new(side_length).unwrap(),
-            // RelativePoint::new(translation.0, translation.1).unwrap(),
+            positive_finite("radius", side_length)?,
+        )))
+    }
+
+    /// Create a new `Self::Capsule`
+    ///
+    /// # Panics
+    ///
+    /// If `length` or `radius` is not positive and finite, see
+    /// [`PlaceableShape::try_capsule`]
+    pub fn capsule(length: Float, radius: Float) -> Self {
+        Self::try_capsule(length, radius).expect("valid capsule")
+    }
+
+    /// Create a new `Self::Capsule`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `length` or `radius` is not positive and finite
+    pub fn try_capsule(length: Float, radius: Float) -> Result<Self, ObstacleError> {
+        Ok(Self::Capsule(Capsule::new(
+            positive_finite("length", length)?,
+            positive_finite("radius", radius)?,
+        )))
+    }
+
+    /// Create a new `Self::Triangle`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the two angles leave no room for the third, or
+    /// `radius` is not positive and finite
+    pub fn try_triangle(angles: [Angle; 2], radius: Float) -> Result<Self, ObstacleError> {
+        let sum = angles[0].as_radians() + angles[1].as_radians();
+        if sum >= std::f64::consts::PI {
+            return Err(ObstacleError::InvalidTriangle(sum.to_degrees()));
+        }
+        Ok(Self::triangle(angles, positive_finite("radius", radius)?))
+    }
+
+    /// Create a new `Self::Composite` from a list of shapes and their offsets
+    pub fn composite(parts: impl IntoIterator<Item = (Self, (Float, Float))>) -> Self {
+        Self::Composite(Composite::new(
+            parts
+                .into_iter()
+                .map(|(shape, (x, y))| CompositePart::new(shape, Point::new(x, y)))
+                .collect(),
         ))
     }
 
@@ -514,6 +983,8 @@ impl PlaceableShape {
             }
             Self::Polygon(polygon) => Self::Polygon(polygon.expanded(factor)),
             Self::Rectangle(rectangle) => Self::Rectangle(rectangle.expanded(factor)),
+            Self::Capsule(capsule) => Self::Capsule(capsule.expanded(factor)),
+            Self::Composite(composite) => Self::Composite(composite.expanded(factor)),
         }
     }
 
@@ -525,6 +996,42 @@ impl PlaceableShape {
             Self::RegularPolygon(regular_polygon) => regular_polygon.inside(point),
             Self::Polygon(polygon) => polygon.inside(point),
             Self::Rectangle(rectangle) => rectangle.inside(point),
+            Self::Capsule(capsule) => capsule.inside(point),
+            Self::Composite(composite) => composite.inside(point),
+        }
+    }
+
+    /// Signed distance from `point` to the edge of the shape, relative to the
+    /// tile size and negative inside. Evaluated analytically, so unlike the
+    /// SDF image it does not depend on a resolution. Expects translation and
+    /// rotation to be performed beforehand
+    pub fn signed_distance(&self, point: Vec2) -> f32 {
+        match self {
+            Self::Circle(circle) => circle.signed_distance(point),
+            Self::Triangle(triangle) => triangle.signed_distance(point),
+            Self::RegularPolygon(regular_polygon) => regular_polygon.signed_distance(point),
+            Self::Polygon(polygon) => polygon.signed_distance(point),
+            Self::Rectangle(rectangle) => rectangle.signed_distance(point),
+            Self::Capsule(capsule) => capsule.signed_distance(point),
+            Self::Composite(composite) => composite.signed_distance(point),
+        }
+    }
+
+    /// Angle the local frame of the shape is rotated by, in addition to the
+    /// rotation of the obstacle, before checking a point against it
+    #[allow(clippy::cast_precision_loss)]
+    pub fn rotation_offset(&self) -> f32 {
+        use std::f32::consts::{FRAC_PI_2, PI};
+        match self {
+            Self::RegularPolygon(RegularPolygon { sides, .. }) => {
+                PI + if sides % 2 != 0 {
+                    PI / *sides as f32
+                } else {
+                    0.0
+                }
+            }
+            Self::Polygon(_) => 0.0,
+            _ => FRAC_PI_2,
         }
     }
 }
@@ -534,20 +1041,36 @@ impl PlaceableShape {
 pub struct Obstacle {
     /// The shape to be placed as an obstacle
     pub shape: PlaceableShape,
-    /// Rotation of the obstacle in degrees around the up-axis
+    /// Rotation of the obstacle around the up-axis, see [`Rotation`]
     pub rotation: Rotation,
     /// Translation of the obstacle within the tile
+    #[serde(default = "RelativePoint::center")]
     pub translation: RelativePoint,
     /// Which tile in the grid the obstacle should be placed
+    #[serde(default)]
     pub tile_coordinates: TileCoordinates,
+    /// Position of the obstacle in meters, measured from the bottom left
+    /// corner of the map. Takes precedence over `tile_coordinates` and
+    /// `translation`, which are derived from it when the environment is parsed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub world_position: Option<Point>,
+    /// Optional unique name of the obstacle, e.g. "loading-dock-3"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Optional semantic tags of the obstacle, e.g. "building" or "shelf"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
 impl Obstacle {
     /// Create a new `Obstacle`
     ///
+    /// `rotation` is given in radians, and wrapped into [0.0, 2pi]
+    ///
     /// # Panics
     ///
-    /// If `rotation` is not a normalized angle, i.e. within [0.0, 2pi]
+    /// If `rotation` is NaN or infinite, or `translation` is not within the
+    /// tile
     #[must_use]
     pub fn new(
         (row, col): (usize, usize),
@@ -558,11 +1081,45 @@ impl Obstacle {
         Self {
             tile_coordinates: TileCoordinates::new(row, col),
             shape,
-            rotation: Rotation(Angle::new(rotation).expect("Invalid angle")),
+            rotation: Rotation::try_from_radians(rotation).expect("rotation is finite"),
             translation: RelativePoint::new(translation.0, translation.1)
                 .expect("Invalid relative point"),
+            world_position: None,
+            name: None,
+            tags: Vec::new(),
         }
     }
+
+    /// Set the name of the obstacle
+    #[must_use]
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Set the tags of the obstacle
+    #[must_use]
+    pub fn with_tags(mut self, tags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.tags = tags.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Whether the obstacle has the tag `tag`
+    #[must_use]
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Signed distance from the point `offset` away from the translation of
+    /// the obstacle to its edge, negative inside. Both are relative to the
+    /// tile size, with the y-axis pointing down as in the SDF image
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn signed_distance(&self, offset: Vec2) -> f32 {
+        let angle = self.rotation.as_radians() as f32 + self.shape.rotation_offset();
+        self.shape
+            .signed_distance(Vec2::from_angle(angle).rotate(offset))
+    }
 }
 
 /// Struct to represent a list of shapes that can be placed in the map [`Grid`]
@@ -583,77 +1140,367 @@ impl Obstacles {
         Self(Vec::new())
     }
 
+    /// Start building a list of obstacles, see [`ObstaclesBuilder`]
+    #[must_use]
+    pub const fn builder() -> ObstaclesBuilder {
+        ObstaclesBuilder::new()
+    }
+
     pub fn iter(&self) -> std::slice::Iter<Obstacle> {
         self.0.iter()
     }
+
+    /// Remove the obstacle at `index`, shifting the ones after it down.
+    /// Returns `None` if `index` is out of bounds
+    pub fn remove(&mut self, index: usize) -> Option<Obstacle> {
+        (index < self.0.len()).then(|| self.0.remove(index))
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
-pub struct TileSettings {
-    pub tile_size: f32,
-    pub path_width: f32,
-    pub obstacle_height: f32,
-    #[serde(default)]
-    pub sdf: SdfSettings,
+/// Error of an invalid [`Obstacle`], see [`ObstaclesBuilder`]
+#[derive(Debug, thiserror::Error)]
+pub enum ObstacleError {
+    #[error("tile ({row}, {col}) is outside of the {nrows}x{ncols} tile grid")]
+    TileOutOfBounds {
+        row:   usize,
+        col:   usize,
+        nrows: usize,
+        ncols: usize,
+    },
+    #[error("translation ({x}, {y}) is not within the tile: {source}")]
+    TranslationOutOfBounds {
+        x:      Float,
+        y:      Float,
+        source: unit_interval::UnitIntervalError,
+    },
+    #[error("{name} must be positive and finite, but is {value}")]
+    NotPositiveFinite { name: &'static str, value: Float },
+    #[error("a regular polygon needs at least 3 sides, but has {0}")]
+    TooFewSides(usize),
+    #[error("the two angles of a triangle sum to {0} degrees, leaving no room for the third")]
+    InvalidTriangle(Float),
+    #[error("invalid rotation: {0}")]
+    InvalidRotation(#[from] AngleError),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
-pub struct SdfSettings {
-    pub resolution: u32,
-    pub expansion:  f32,
-    pub blur:       f32,
+/// Error returned by [`ObstaclesBuilder::build`], with the position of the
+/// first invalid obstacle in the order they were added
+#[derive(Debug, thiserror::Error)]
+#[error("obstacle #{index} is invalid: {source}")]
+pub struct InvalidObstacle {
+    pub index:  usize,
+    #[source]
+    pub source: ObstacleError,
 }
 
-impl Default for SdfSettings {
-    fn default() -> Self {
-        Self {
-            resolution: 200,
-            expansion:  0.1,
-            blur:       0.05,
-        }
-    }
+fn positive_finite(
+    name: &'static str,
+    value: Float,
+) -> Result<StrictlyPositiveFinite<Float>, ObstacleError> {
+    StrictlyPositiveFinite::<Float>::new(value)
+        .map_err(|_| ObstacleError::NotPositiveFinite { name, value })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "kebab-case")]
-pub struct Tiles {
-    pub grid:     TileGrid,
-    pub settings: TileSettings,
+/// Builder of [`Obstacles`], validating every obstacle as it is added.
+///
+/// Tile coordinates are checked against the shape of the tile grid given with
+/// [`ObstaclesBuilder::within`], translations must be within the tile, and
+/// the dimensions of the shapes must be positive and finite. The first error
+/// is kept, and returned by [`ObstaclesBuilder::build`], so obstacles can be
+/// added in a chain:
+///
+/// ```
+/// # use gbp_environment::{Obstacles, TileGrid};
+/// let grid = TileGrid::new(vec!["┌┐", "└┘"]);
+/// let obstacles = Obstacles::builder()
+///     .within(&grid)
+///     .add_circle((0, 1), 0.1, (0.5, 0.5))
+///     .add_rectangle((1, 0), 0.2, 0.1, 0.0, (0.25, 0.75))
+///     .build()
+///     .expect("all obstacles are valid");
+/// assert_eq!(obstacles.iter().count(), 2);
+/// ```
+#[derive(Debug, Default)]
+pub struct ObstaclesBuilder {
+    obstacles: Vec<Obstacle>,
+    shape:     Option<(usize, usize)>,
+    error:     Option<InvalidObstacle>,
 }
 
-impl Tiles {
-    /// Create an empty `Tiles`
+impl ObstaclesBuilder {
+    /// Create a builder without any obstacles, that accepts any tile
     #[must_use]
-    pub fn empty() -> Self {
+    pub const fn new() -> Self {
         Self {
-            grid:     TileGrid::new(vec!["█"]),
-            settings: TileSettings {
-                tile_size: 0.0,
-                path_width: 0.0,
-                obstacle_height: 0.0,
-                sdf: SdfSettings::default(),
-            },
+            obstacles: Vec::new(),
+            shape:     None,
+            error:     None,
         }
     }
 
-    /// Set the tile size
+    /// Only accept obstacles placed on a tile of `grid`
     #[must_use]
-    pub const fn with_tile_size(mut self, tile_size: f32) -> Self {
-        self.settings.tile_size = tile_size;
+    pub fn within(mut self, grid: &TileGrid) -> Self {
+        self.shape = Some(grid.shape());
         self
     }
 
-    /// Set the obstacle height
+    /// Add an already constructed obstacle, checking its tile coordinates
     #[must_use]
-    pub const fn with_obstacle_height(mut self, obstacle_height: f32) -> Self {
-        self.settings.obstacle_height = obstacle_height;
-        self
+    pub fn add(self, obstacle: Obstacle) -> Self {
+        self.try_add(|| Ok(obstacle))
     }
-}
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+    /// Add a circle with radius `radius` relative to the tile size
+    #[must_use]
+    pub fn add_circle(
+        self,
+        tile: (usize, usize),
+        radius: Float,
+        translation: (Float, Float),
+    ) -> Self {
+        self.try_add(|| {
+            let shape = PlaceableShape::circle(positive_finite("radius", radius)?);
+            place(tile, shape, 0.0, translation)
+        })
+    }
+
+    /// Add a rectangle, rotated by `rotation` radians
+    #[must_use]
+    pub fn add_rectangle(
+        self,
+        tile: (usize, usize),
+        width: Float,
+        height: Float,
+        rotation: Float,
+        translation: (Float, Float),
+    ) -> Self {
+        self.try_add(|| {
+            let shape = PlaceableShape::try_rectangle(width, height)?;
+            place(tile, shape, rotation, translation)
+        })
+    }
+
+    /// Add a regular polygon with `sides` sides, rotated by `rotation` radians
+    #[must_use]
+    pub fn add_regular_polygon(
+        self,
+        tile: (usize, usize),
+        sides: usize,
+        radius: Float,
+        rotation: Float,
+        translation: (Float, Float),
+    ) -> Self {
+        self.try_add(|| {
+            let shape = PlaceableShape::try_regular_polygon(sides, radius)?;
+            place(tile, shape, rotation, translation)
+        })
+    }
+
+    /// Add a triangle, rotated by `rotation` radians
+    #[must_use]
+    pub fn add_triangle(
+        self,
+        tile: (usize, usize),
+        angles: [Angle; 2],
+        radius: Float,
+        rotation: Float,
+        translation: (Float, Float),
+    ) -> Self {
+        self.try_add(|| {
+            let shape = PlaceableShape::try_triangle(angles, radius)?;
+            place(tile, shape, rotation, translation)
+        })
+    }
+
+    /// Add a capsule, rotated by `rotation` radians
+    #[must_use]
+    pub fn add_capsule(
+        self,
+        tile: (usize, usize),
+        length: Float,
+        radius: Float,
+        rotation: Float,
+        translation: (Float, Float),
+    ) -> Self {
+        self.try_add(|| {
+            let shape = PlaceableShape::try_capsule(length, radius)?;
+            place(tile, shape, rotation, translation)
+        })
+    }
+
+    /// The obstacles, or the first invalid obstacle that was added
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if any of the added obstacles is invalid
+    pub fn build(self) -> Result<Obstacles, InvalidObstacle> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(Obstacles(self.obstacles)),
+        }
+    }
+
+    fn try_add(mut self, make: impl FnOnce() -> Result<Obstacle, ObstacleError>) -> Self {
+        if self.error.is_some() {
+            return self;
+        }
+        match make().and_then(|obstacle| self.check_tile(obstacle)) {
+            Ok(obstacle) => self.obstacles.push(obstacle),
+            Err(source) => {
+                self.error = Some(InvalidObstacle {
+                    index: self.obstacles.len(),
+                    source,
+                });
+            }
+        }
+        self
+    }
+
+    fn check_tile(&self, obstacle: Obstacle) -> Result<Obstacle, ObstacleError> {
+        let TileCoordinates { row, col } = obstacle.tile_coordinates;
+        match self.shape {
+            Some((nrows, ncols)) if row >= nrows || col >= ncols => {
+                Err(ObstacleError::TileOutOfBounds {
+                    row,
+                    col,
+                    nrows,
+                    ncols,
+                })
+            }
+            _ => Ok(obstacle),
+        }
+    }
+}
+
+/// An obstacle of `shape` on the tile at `(row, col)`, validating `rotation`
+/// and `translation`
+fn place(
+    (row, col): (usize, usize),
+    shape: PlaceableShape,
+    rotation: Float,
+    (x, y): (Float, Float),
+) -> Result<Obstacle, ObstacleError> {
+    let translation = RelativePoint::new(x, y)
+        .map_err(|source| ObstacleError::TranslationOutOfBounds { x, y, source })?;
+    Ok(Obstacle {
+        tile_coordinates: TileCoordinates::new(row, col),
+        shape,
+        rotation: Rotation::try_from_radians(rotation)?,
+        translation,
+        world_position: None,
+        name: None,
+        tags: Vec::new(),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TileSettings {
+    pub tile_size: f32,
+    /// Width of a tile along the x-axis. Defaults to `tile_size`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tile_width: Option<f32>,
+    /// Height of a tile along the z-axis. Defaults to `tile_size`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tile_height: Option<f32>,
+    pub path_width: f32,
+    pub obstacle_height: f32,
+    /// Surround the environment with walls along the inside of its edge, so
+    /// robots can not leave it, see [`Environment::boundary_thickness`]
+    #[serde(default)]
+    pub add_boundary: bool,
+    #[serde(default)]
+    pub sdf: SdfSettings,
+}
+
+impl TileSettings {
+    /// Width of a tile along the x-axis
+    #[inline]
+    pub fn tile_width(&self) -> f32 {
+        self.tile_width.unwrap_or(self.tile_size)
+    }
+
+    /// Height of a tile along the z-axis
+    #[inline]
+    pub fn tile_height(&self) -> f32 {
+        self.tile_height.unwrap_or(self.tile_size)
+    }
+
+    /// Length of the shorter side of a tile. Path widths and the sizes of
+    /// obstacles are relative to this length, so they are not stretched by
+    /// rectangular tiles.
+    #[inline]
+    pub fn tile_extent(&self) -> f32 {
+        self.tile_width().min(self.tile_height())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SdfSettings {
+    pub resolution: u32,
+    pub expansion:  f32,
+    pub blur:       f32,
+    /// Let the obstacle factors measure the obstacles analytically from their
+    /// shapes, see [`Environment::signed_distance`], instead of from the
+    /// rasterized image
+    #[serde(default)]
+    pub analytic:   bool,
+}
+
+impl Default for SdfSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 200,
+            expansion:  0.1,
+            blur:       0.05,
+            analytic:   false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Tiles {
+    pub grid:     TileGrid,
+    pub settings: TileSettings,
+}
+
+impl Tiles {
+    /// Create an empty `Tiles`
+    #[must_use]
+    pub fn empty() -> Self {
+        Self {
+            grid:     TileGrid::new(vec!["█"]),
+            settings: TileSettings {
+                tile_size: 0.0,
+                tile_width: None,
+                tile_height: None,
+                path_width: 0.0,
+                obstacle_height: 0.0,
+                add_boundary: false,
+                sdf: SdfSettings::default(),
+            },
+        }
+    }
+
+    /// Set the tile size
+    #[must_use]
+    pub const fn with_tile_size(mut self, tile_size: f32) -> Self {
+        self.settings.tile_size = tile_size;
+        self
+    }
+
+    /// Set the obstacle height
+    #[must_use]
+    pub const fn with_obstacle_height(mut self, obstacle_height: f32) -> Self {
+        self.settings.obstacle_height = obstacle_height;
+        self
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(not(target_arch = "wasm32"), derive(clap::ValueEnum))]
 pub enum EnvironmentType {
     #[default]
@@ -663,6 +1510,7 @@ pub enum EnvironmentType {
     Circle,
     Maze,
     Test,
+    NarrowCorridor,
 }
 
 /// **Bevy** [`Resource`]
@@ -670,8 +1518,141 @@ pub enum EnvironmentType {
 #[derive(Debug, Clone, Serialize, Deserialize, Resource)]
 #[serde(rename_all = "kebab-case")]
 pub struct Environment {
-    pub tiles:     Tiles,
+    /// Version of the schema of the environment file. See
+    /// [`Environment::SCHEMA`]
+    #[serde(default = "Environment::latest_version")]
+    pub version: u32,
+    pub tiles: Tiles,
     pub obstacles: Obstacles,
+    /// Places where robots can recharge their battery
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub charging_stations: Vec<ChargingStation>,
+    /// Place traffic lights at every junction of the tile grid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traffic_lights: Option<TrafficLightSettings>,
+    /// Areas robots can drive through, but at a cost, e.g. grass or speed
+    /// restricted zones
+    #[serde(default, skip_serializing_if = "Regions::is_empty")]
+    pub regions: Regions,
+}
+
+/// A place where robots can recharge their battery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ChargingStation {
+    /// Position of the station in meters, measured from the bottom left
+    /// corner of the map
+    pub position: Point,
+    /// Robots within this distance of `position` are charged. SI unit: m
+    #[serde(default = "ChargingStation::default_radius")]
+    pub radius:   f32,
+    /// Energy charged per second
+    #[serde(default = "ChargingStation::default_rate")]
+    pub rate:     f32,
+}
+
+impl ChargingStation {
+    pub const fn default_radius() -> f32 {
+        2.0
+    }
+
+    pub const fn default_rate() -> f32 {
+        10.0
+    }
+}
+
+/// A polygonal area with a traversal cost, that robots prefer to drive
+/// around, unlike an obstacle they are free to cross if it is worth it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Region {
+    /// Shown in the UI and the logs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name:    Option<String>,
+    /// Corners of the polygon in meters, measured from the bottom left corner
+    /// of the map
+    pub points:  Vec<Point>,
+    /// Multiplier of the cost of driving through the region, where 1.0 is
+    /// the same as outside it. **constraint**: >= 1.0
+    #[serde(default = "Region::default_cost")]
+    pub cost:    f32,
+    /// Distance from the edge of the region over which the cost ramps up to
+    /// its full value. SI unit: m
+    #[serde(default = "Region::default_falloff")]
+    pub falloff: f32,
+}
+
+impl Region {
+    pub const fn default_cost() -> f32 {
+        2.0
+    }
+
+    pub const fn default_falloff() -> f32 {
+        1.0
+    }
+}
+
+/// The [`Region`]s of an [`Environment`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, IntoIterator)]
+#[serde(rename_all = "kebab-case")]
+#[into_iterator(owned, ref)]
+pub struct Regions(Vec<Region>);
+
+impl Regions {
+    /// Create a new empty list of [`Region`]
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<Region> {
+        self.0.iter()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<Region>> for Regions {
+    fn from(regions: Vec<Region>) -> Self {
+        Self(regions)
+    }
+}
+
+/// Phase timing of the traffic lights at the junctions of the environment.
+/// The lights let either the vertical or the horizontal road through, with a
+/// clearance phase where both are red in between.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TrafficLightSettings {
+    /// Duration of a green phase. SI unit: s
+    #[serde(default = "TrafficLightSettings::default_green")]
+    pub green:     f32,
+    /// Duration where all directions are red, between two green phases. SI
+    /// unit: s
+    #[serde(default = "TrafficLightSettings::default_clearance")]
+    pub clearance: f32,
+}
+
+impl TrafficLightSettings {
+    pub const fn default_green() -> f32 {
+        10.0
+    }
+
+    pub const fn default_clearance() -> f32 {
+        2.0
+    }
+}
+
+impl Default for TrafficLightSettings {
+    fn default() -> Self {
+        Self {
+            green:     Self::default_green(),
+            clearance: Self::default_clearance(),
+        }
+    }
 }
 
 impl Default for Environment {
@@ -690,19 +1671,69 @@ pub enum ParseError {
     Toml(#[from] toml::de::Error),
     #[error("YAML error: {0}")]
     Yaml(#[from] serde_yaml::Error),
+    #[error("Migration error: {0}")]
+    Migration(#[from] gbp_migration::MigrationError),
     #[error("Validation error: {0}")]
     InvalidEnvironment(#[from] EnvironmentError),
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum FromImageError {
+    #[error("Image error: {0}")]
+    Image(#[from] image::ImageError),
+    #[error("Image has no pixels")]
+    EmptyImage,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum EnvironmentError {
     #[error("Environment matrix representation is empty")]
     EmptyGrid,
     #[error("Environment matrix representation has rows of different lengths")]
     DifferentLengthRows,
+    #[error("Obstacle world position ({x}, {y}) is outside of the environment")]
+    WorldPositionOutOfBounds { x: f64, y: f64 },
+    #[error("Can not join environments with tiles of {0:?} and {1:?} meters")]
+    MismatchedTileSizes((f32, f32), (f32, f32)),
+    #[error("Region {index} has {points} points, a polygon needs at least 3")]
+    DegenerateRegion { index: usize, points: usize },
+    #[error("Region {index} has a cost of {cost}, it must be at least 1.0")]
+    InvalidRegionCost { index: usize, cost: f32 },
 }
 
 impl Environment {
+    /// Key of the unit of the angles written as plain numbers, either
+    /// `radians` (the default) or `degrees`
+    pub const ANGLE_UNIT_KEY: &'static str = "angle-unit";
+    /// The schema of the environment file. Version 1 is the unversioned
+    /// format. Version 2 places obstacles with their own `translation`, rather
+    /// than the `center` or `translation` of their shape
+    pub const SCHEMA: gbp_migration::Schema = gbp_migration::Schema {
+        name:       "environment",
+        version:    2,
+        migrations: &[gbp_migration::Migration {
+            version: 2,
+            changes: &[
+                gbp_migration::Change::Moved {
+                    from: "obstacles.*.shape.center",
+                    to:   "obstacles.*.translation",
+                },
+                gbp_migration::Change::Moved {
+                    from: "obstacles.*.shape.translation",
+                    to:   "obstacles.*.translation",
+                },
+                gbp_migration::Change::Removed {
+                    key:    "obstacles.*.shape.side-length",
+                    reason: "regular polygons are sized by their `radius`",
+                },
+            ],
+        }],
+    };
+
+    const fn latest_version() -> u32 {
+        Self::SCHEMA.version
+    }
+
     /// Attempt to parse an [`Environment`] from a YAML file at `path`
     ///
     /// # Errors
@@ -717,14 +1748,17 @@ impl Environment {
             .and_then(|contents| Self::parse(contents.as_str()))
     }
 
-    /// Attempt to parse an [`Environment`] from a YAML encoded string
+    /// Attempt to parse an [`Environment`] from a YAML encoded string. Files
+    /// written for older versions of the schema are migrated, with a warning
+    /// for every deprecated key they set
     ///
     /// # Errors
     ///
     /// Will return `Err` if:
     /// 1. `path` does not exist on the filesystem
     /// 2. The contents of `path` are not valid RON
-    /// 3. The parsed data does not represent a valid [`Environment`]
+    /// 3. The file was written for a newer version of the schema
+    /// 4. The parsed data does not represent a valid [`Environment`]
     pub fn parse(contents: &str) -> Result<Self, ParseError> {
         // ron::from_str::<Environment>(contents)
         //     .map_err(|span| span.code)?
@@ -732,9 +1766,25 @@ impl Environment {
         //     .map_err(Into::into)
         // with yaml
 
-        serde_yaml::from_str::<Self>(contents)
-            .map_err(Into::into)
-            .and_then(|env| env.validate().map_err(Into::into))
+        let mut mapping: serde_yaml::Mapping = serde_yaml::from_str(contents)?;
+        for deprecation in Self::SCHEMA.migrate(&mut mapping)? {
+            warn!("{deprecation}");
+        }
+
+        // Angles written as plain numbers are in `angle-unit`. The key is not
+        // part of [`Environment`], as angles are always serialized in radians
+        let angle_unit = mapping
+            .remove(Self::ANGLE_UNIT_KEY)
+            .map(serde_yaml::from_value::<AngleUnit>)
+            .transpose()?
+            .unwrap_or_default();
+
+        angle::with_default_unit(angle_unit, || {
+            serde_yaml::from_value::<Self>(mapping.into())
+        })
+        .map_err(Into::into)
+        .and_then(|env| env.validate().map_err(Into::into))
+        .and_then(|env| env.resolve_world_positions().map_err(Into::into))
     }
 
     /// Ensure that the [`Environment`] is valid
@@ -742,16 +1792,32 @@ impl Environment {
     /// # Errors
     ///
     /// Will return `Err` if:
-    /// 1. The matrix representation is not empty
-    /// 2. All rows in the matrix representation are the same length
+    /// 1. The matrix representation is empty, or its rows are empty
+    /// 2. The rows in the matrix representation have different lengths
+    /// 3. A region has less than 3 points, or a cost below 1.0
     pub fn validate(self) -> Result<Self, EnvironmentError> {
-        if self.tiles.grid.is_empty() {
+        for (index, region) in self.regions.iter().enumerate() {
+            if region.points.len() < 3 {
+                return Err(EnvironmentError::DegenerateRegion {
+                    index,
+                    points: region.points.len(),
+                });
+            }
+            if region.cost < 1.0 {
+                return Err(EnvironmentError::InvalidRegionCost {
+                    index,
+                    cost: region.cost,
+                });
+            }
+        }
+
+        if self.tiles.grid.is_empty() || self.tiles.grid.ncols() == 0 {
             Err(EnvironmentError::EmptyGrid)
         } else if self
             .tiles
             .grid
             .iter()
-            .any(|row| row.chars().count() != self.tiles.grid.ncols())
+            .any(|row| row.len() != self.tiles.grid.ncols())
         {
             Err(EnvironmentError::DifferentLengthRows)
         } else {
@@ -759,6 +1825,202 @@ impl Environment {
         }
     }
 
+    /// Convert the `world_position` of every obstacle, that has one, into
+    /// `tile_coordinates` and a `translation` within that tile
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a world position lies outside of the environment
+    pub fn resolve_world_positions(mut self) -> Result<Self, EnvironmentError> {
+        let placements = self
+            .obstacles
+            .iter()
+            .map(|obstacle| {
+                obstacle
+                    .world_position
+                    .map(|position| {
+                        self.world_to_tile(position).ok_or(
+                            EnvironmentError::WorldPositionOutOfBounds {
+                                x: position.x,
+                                y: position.y,
+                            },
+                        )
+                    })
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (obstacle, placement) in self.obstacles.0.iter_mut().zip(placements) {
+            if let Some((tile_coordinates, translation)) = placement {
+                obstacle.tile_coordinates = tile_coordinates;
+                obstacle.translation = translation;
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Place `other` to the right of `self`. The shorter of the two tile
+    /// grids is padded with empty rows at the bottom, and the tile
+    /// coordinates and world positions of everything in `other` are moved
+    /// along with its tiles. The tile settings of `self` are kept.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if either environment is invalid, or their tiles
+    /// differ in size
+    pub fn concat_horizontal(self, other: Self) -> Result<Self, EnvironmentError> {
+        self.concat(other, true)
+    }
+
+    /// Place `other` below `self`. The narrower of the two tile grids is
+    /// padded with empty columns to the right, and the tile coordinates and
+    /// world positions of everything in `other` are moved along with its
+    /// tiles. The tile settings of `self` are kept.
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if either environment is invalid, or their tiles
+    /// differ in size
+    pub fn concat_vertical(self, other: Self) -> Result<Self, EnvironmentError> {
+        self.concat(other, false)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn concat(self, other: Self, horizontal: bool) -> Result<Self, EnvironmentError> {
+        let mut this = self.validate()?;
+        let mut other = other.validate()?;
+        let tile_size = (this.tile_width(), this.tile_height());
+        let other_tile_size = (other.tile_width(), other.tile_height());
+        if tile_size != other_tile_size {
+            return Err(EnvironmentError::MismatchedTileSizes(
+                tile_size,
+                other_tile_size,
+            ));
+        }
+
+        let tile_width = Float::from(tile_size.0);
+        let tile_height = Float::from(tile_size.1);
+        let (nrows, ncols) = this.tiles.grid.shape();
+        let (other_nrows, other_ncols) = other.tiles.grid.shape();
+
+        let rows = if horizontal {
+            // rows are counted from the top, and world positions from the
+            // bottom, so padding at the bottom only moves world positions
+            let height = nrows.max(other_nrows);
+            this.pad(height, ncols);
+            other.pad(height, other_ncols);
+            this.translate(0, 0, 0.0, (height - nrows) as Float * tile_height);
+            other.translate(
+                0,
+                ncols,
+                ncols as Float * tile_width,
+                (height - other_nrows) as Float * tile_height,
+            );
+            this.tiles
+                .grid
+                .0
+                .into_iter()
+                .zip(other.tiles.grid.0)
+                .map(|(mut row, other_row)| {
+                    row.extend(other_row);
+                    row
+                })
+                .collect()
+        } else {
+            let width = ncols.max(other_ncols);
+            this.pad(nrows, width);
+            other.pad(other_nrows, width);
+            this.translate(0, 0, 0.0, other_nrows as Float * tile_height);
+            other.translate(nrows, 0, 0.0, 0.0);
+            let mut rows = this.tiles.grid.0;
+            rows.extend(other.tiles.grid.0);
+            rows
+        };
+
+        this.tiles.grid = TileGrid(rows);
+        this.obstacles.0.extend(other.obstacles.0);
+        this.charging_stations.extend(other.charging_stations);
+        this.regions.0.extend(other.regions.0);
+        this.traffic_lights = this.traffic_lights.or(other.traffic_lights);
+        Ok(this)
+    }
+
+    /// Pad the tile grid with empty tiles, to the bottom and right, until it
+    /// has `nrows` rows and `ncols` columns
+    fn pad(&mut self, nrows: usize, ncols: usize) {
+        let rows = &mut self.tiles.grid.0;
+        for row in rows.iter_mut() {
+            row.resize(ncols, ' ');
+        }
+        rows.resize(nrows, vec![' '; ncols]);
+    }
+
+    /// Move obstacles `rows` rows down and `cols` columns right in the tile
+    /// grid, and move everything placed by world position by `(dx, dy)`
+    /// meters
+    fn translate(&mut self, rows: usize, cols: usize, dx: Float, dy: Float) {
+        for obstacle in &mut self.obstacles.0 {
+            obstacle.tile_coordinates.row += rows;
+            obstacle.tile_coordinates.col += cols;
+            if let Some(position) = obstacle.world_position.as_mut() {
+                position.x += dx;
+                position.y += dy;
+            }
+        }
+        for station in &mut self.charging_stations {
+            station.position.x += dx;
+            station.position.y += dy;
+        }
+        for point in self
+            .regions
+            .0
+            .iter_mut()
+            .flat_map(|region| &mut region.points)
+        {
+            point.x += dx;
+            point.y += dy;
+        }
+    }
+
+    /// Find the tile containing `position`, given in meters from the bottom
+    /// left corner of the map, and the relative position within that tile.
+    /// Returns `None` if `position` is outside of the environment
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    pub fn world_to_tile(&self, position: Point) -> Option<(TileCoordinates, RelativePoint)> {
+        let tile_width = Float::from(self.tile_width());
+        let tile_height = Float::from(self.tile_height());
+        let ncols = self.tiles.grid.ncols();
+        let nrows = self.tiles.grid.nrows();
+
+        let x = position.x / tile_width;
+        let y = position.y / tile_height;
+        if !(0.0..=ncols as Float).contains(&x) || !(0.0..=nrows as Float).contains(&y) {
+            return None;
+        }
+
+        // a position on the right or top edge belongs to the last tile
+        let col = (x.floor() as usize).min(ncols - 1);
+        let row_from_bottom = (y.floor() as usize).min(nrows - 1);
+        let tile_origin = Point::new(
+            col as Float * tile_width,
+            row_from_bottom as Float * tile_height,
+        );
+        let translation =
+            RelativePoint::from_world(position, tile_origin, tile_width, tile_height).ok()?;
+
+        // rows of the grid are counted from the top
+        Some((
+            TileCoordinates::new(nrows - 1 - row_from_bottom, col),
+            translation,
+        ))
+    }
+
     #[must_use]
     pub fn new(
         matrix_representation: Vec<String>,
@@ -767,32 +2029,46 @@ impl Environment {
         tile_size: f32,
     ) -> Self {
         Self {
-            tiles:     Tiles {
-                grid:     TileGrid(matrix_representation),
+            tiles: Tiles {
+                grid:     TileGrid::new(matrix_representation),
                 settings: TileSettings {
                     tile_size,
+                    tile_width: None,
+                    tile_height: None,
                     path_width,
                     obstacle_height,
+                    add_boundary: false,
                     sdf: SdfSettings::default(),
                 },
             },
             obstacles: Obstacles::empty(),
+            charging_stations: Vec::new(),
+            traffic_lights: None,
+            regions: Regions::empty(),
+            version: Self::SCHEMA.version,
         }
     }
 
     #[must_use]
     pub fn intersection() -> Self {
         Self {
-            tiles:     Tiles {
+            tiles: Tiles {
                 grid:     TileGrid::new(vec!["┼"]),
                 settings: TileSettings {
                     tile_size: 100.0,
+                    tile_width: None,
+                    tile_height: None,
                     path_width: 0.1325,
                     obstacle_height: 1.0,
+                    add_boundary: false,
                     sdf: SdfSettings::default(),
                 },
             },
             obstacles: Obstacles::empty(),
+            charging_stations: Vec::new(),
+            traffic_lights: None,
+            regions: Regions::empty(),
+            version: Self::SCHEMA.version,
         }
     }
 
@@ -808,12 +2084,19 @@ impl Environment {
                 ]),
                 settings: TileSettings {
                     tile_size: 50.0,
+                    tile_width: None,
+                    tile_height: None,
                     path_width: 0.1325,
                     obstacle_height: 1.0,
+                    add_boundary: false,
                     sdf: SdfSettings::default(),
                 }
             },
             obstacles: Obstacles::empty(),
+            charging_stations: Vec::new(),
+            traffic_lights: None,
+            regions: Regions::empty(),
+            version: Self::SCHEMA.version,
         }
     }
 
@@ -831,12 +2114,19 @@ impl Environment {
                 ]),
                 settings: TileSettings {
                     tile_size: 25.0,
+                    tile_width: None,
+                    tile_height: None,
                     path_width: 0.4,
                     obstacle_height: 1.0,
+                    add_boundary: false,
                     sdf: SdfSettings::default(),
                 },
             },
             obstacles: Obstacles::empty(),
+            charging_stations: Vec::new(),
+            traffic_lights: None,
+            regions: Regions::empty(),
+            version: Self::SCHEMA.version,
         }
     }
 
@@ -866,12 +2156,19 @@ impl Environment {
                 ]),
                 settings: TileSettings {
                     tile_size: 10.0,
+                    tile_width: None,
+                    tile_height: None,
                     path_width: 0.75,
                     obstacle_height: 1.0,
+                    add_boundary: false,
                     sdf: SdfSettings::default(),
                 },
             },
             obstacles: Obstacles::empty(),
+            charging_stations: Vec::new(),
+            traffic_lights: None,
+            regions: Regions::empty(),
+            version: Self::SCHEMA.version,
         }
     }
 
@@ -888,12 +2185,19 @@ impl Environment {
                 ]),
                 settings: TileSettings {
                     tile_size: 50.0,
+                    tile_width: None,
+                    tile_height: None,
                     path_width: 0.1325,
                     obstacle_height: 1.0,
+                    add_boundary: false,
                     sdf: SdfSettings::default(),
                 },
             },
             obstacles: Obstacles::empty(),
+            charging_stations: Vec::new(),
+            traffic_lights: None,
+            regions: Regions::empty(),
+            version: Self::SCHEMA.version,
         }
     }
 
@@ -901,59 +2205,91 @@ impl Environment {
     #[allow(clippy::missing_panics_doc)]
     pub fn circle() -> Self {
         Self {
-            tiles:     Tiles::empty()
+            tiles: Tiles::empty()
                 .with_tile_size(100.0)
                 .with_obstacle_height(1.0),
-            obstacles: Obstacles(vec![
-                Obstacle::new(
-                    (0, 0),
-                    PlaceableShape::regular_polygon(4, 0.0525),
-                    0.0,
-                    (0.625, 0.60125),
-                ),
-                Obstacle::new(
-                    (0, 0),
-                    PlaceableShape::regular_polygon(4, 0.035),
-                    0.0,
-                    (0.44125, 0.57125),
-                ),
-                Obstacle::new(
+            obstacles: Obstacles::builder()
+                .add_regular_polygon((0, 0), 4, 0.0525, 0.0, (0.625, 0.60125))
+                .add_regular_polygon((0, 0), 4, 0.035, 0.0, (0.44125, 0.57125))
+                .add_regular_polygon((0, 0), 4, 0.0225, 0.0, (0.4835, 0.428))
+                .add_rectangle((0, 0), 0.0875, 0.035, 0.0, (0.589, 0.3965))
+                .add_triangle(
                     (0, 0),
-                    PlaceableShape::regular_polygon(4, 0.0225),
-                    0.0,
-                    (0.4835, 0.428),
-                ),
-                Obstacle::new(
-                    (0, 0),
-                    PlaceableShape::rectangle(0.0875, 0.035),
-                    0.0,
-                    (0.589, 0.3965),
-                ),
-                Obstacle::new(
-                    (0, 0),
-                    PlaceableShape::triangle(
-                        [
-                            Angle::from_degrees(30.0).expect("Invalid angle"),
-                            Angle::from_degrees(30.0).expect("Invalid angle"),
-                        ],
-                        0.05.try_into().expect("positive and finite"),
-                    ),
+                    [
+                        Angle::from_degrees(30.0).expect("Invalid angle"),
+                        Angle::from_degrees(30.0).expect("Invalid angle"),
+                    ],
+                    0.05,
                     0.0,
                     (0.5575, 0.5145),
-                ),
-                Obstacle::new(
+                )
+                .add_triangle(
                     (0, 0),
-                    PlaceableShape::triangle(
-                        [
-                            Angle::from_degrees(110.0).expect("Invalid angle"),
-                            Angle::from_degrees(40.0).expect("Invalid angle"),
-                        ],
-                        0.03.try_into().expect("positive and finite"),
-                    ),
+                    [
+                        Angle::from_degrees(110.0).expect("Invalid angle"),
+                        Angle::from_degrees(40.0).expect("Invalid angle"),
+                    ],
+                    0.03,
                     5.225,
                     (0.38, 0.432),
-                ),
-            ]),
+                )
+                .build()
+                .expect("obstacles of the circle environment are valid"),
+            charging_stations: Vec::new(),
+            traffic_lights: None,
+            regions: Regions::empty(),
+            version: Self::SCHEMA.version,
+        }
+    }
+
+    /// Two open square rooms of `room_size` x `room_size` tiles, connected by a
+    /// straight corridor of `corridor_length` tiles. `corridor_width` is the
+    /// width of the corridor relative to the tile size. The rooms are walled
+    /// in by a border of filled tiles.
+    ///
+    /// The layout is `2 * room_size + corridor_length + 2` tiles wide and
+    /// `room_size + 2` tiles high, with the corridor along the middle row.
+    /// Use together with `gbp_config::FormationGroup::narrow_corridor` with the
+    /// same `room_size` and `corridor_length`.
+    #[must_use]
+    pub fn narrow_corridor(
+        room_size: usize,
+        corridor_length: usize,
+        corridor_width: f32,
+        tile_size: f32,
+    ) -> Self {
+        let room_size = room_size.max(1);
+        let corridor_row = 1 + room_size / 2;
+        let border = " ".repeat(2 * room_size + corridor_length + 2);
+
+        let grid = std::iter::once(border.clone())
+            .chain((1..=room_size).map(|row| {
+                let room = "█".repeat(room_size);
+                let corridor =
+                    (if row == corridor_row { "─" } else { " " }).repeat(corridor_length);
+                format!(" {room}{corridor}{room} ")
+            }))
+            .chain(std::iter::once(border))
+            .collect();
+
+        Self {
+            tiles: Tiles {
+                grid:     TileGrid::new(grid),
+                settings: TileSettings {
+                    tile_size,
+                    tile_width: None,
+                    tile_height: None,
+                    path_width: corridor_width,
+                    obstacle_height: 1.0,
+                    add_boundary: false,
+                    sdf: SdfSettings::default(),
+                },
+            },
+            obstacles: Obstacles::empty(),
+            charging_stations: Vec::new(),
+            traffic_lights: None,
+            regions: Regions::empty(),
+            version: Self::SCHEMA.version,
         }
     }
 
@@ -968,4 +2304,444 @@ impl Environment {
     pub const fn tile_size(&self) -> f32 {
         self.tiles.settings.tile_size
     }
+
+    pub const fn add_boundary(&self) -> bool {
+        self.tiles.settings.add_boundary
+    }
+
+    /// Thickness of the walls along the edge of the map if
+    /// [`TileSettings::add_boundary`] is set, and 0.0 otherwise. The walls lie
+    /// inside the map, so the SDF covers them. SI unit: m
+    pub fn boundary_thickness(&self) -> f32 {
+        if self.add_boundary() {
+            BOUNDARY_THICKNESS * self.tile_extent()
+        } else {
+            0.0
+        }
+    }
+
+    pub fn tile_width(&self) -> f32 {
+        self.tiles.settings.tile_width()
+    }
+
+    pub fn tile_height(&self) -> f32 {
+        self.tiles.settings.tile_height()
+    }
+
+    /// See [`TileSettings::tile_extent`]
+    pub fn tile_extent(&self) -> f32 {
+        self.tiles.settings.tile_extent()
+    }
+
+    /// Width and height of the tile grid, i.e. the number of columns and rows
+    /// times the width and height of a tile
+    #[allow(clippy::cast_precision_loss)]
+    pub fn dimensions(&self) -> (f32, f32) {
+        let (nrows, ncols) = self.tiles.grid.shape();
+        (
+            ncols as f32 * self.tile_width(),
+            nrows as f32 * self.tile_height(),
+        )
+    }
+
+    /// Convert `position`, given in meters from the bottom left corner of the
+    /// map, into the coordinates of the simulation, where the origin is at
+    /// the center of the map
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn centered(&self, position: Point) -> Vec2 {
+        let (width, height) = self.dimensions();
+        Vec2::new(
+            position.x as f32 - width / 2.0,
+            position.y as f32 - height / 2.0,
+        )
+    }
+
+    /// Center of the tile at `coordinates`, in the coordinates of the
+    /// simulation
+    #[allow(clippy::cast_precision_loss)]
+    pub fn tile_center(&self, coordinates: TileCoordinates) -> Vec2 {
+        let (width, height) = self.dimensions();
+        Vec2::new(
+            (coordinates.col as f32 + 0.5).mul_add(self.tile_width(), -width / 2.0),
+            (coordinates.row as f32 + 0.5).mul_add(-self.tile_height(), height / 2.0),
+        )
+    }
+
+    /// The tiles where three or four roads meet
+    pub fn junctions(&self) -> impl Iterator<Item = TileCoordinates> + '_ {
+        self.tiles
+            .grid
+            .cells()
+            .filter(|(_, tile)| matches!(tile, '┼' | '┬' | '┴' | '├' | '┤'))
+            .map(|(coordinates, _)| coordinates)
+    }
+
+    /// Side length of the smallest square that contains the tile grid
+    pub fn world_size(&self) -> f32 {
+        let (width, height) = self.dimensions();
+        width.max(height)
+    }
+
+    /// Signed distance from `position`, in the coordinates of the simulation,
+    /// to the nearest obstacle, negative inside it. SI unit: m
+    ///
+    /// Evaluated analytically from the walls of the tile grid, the boundary
+    /// and the shapes of the obstacles, so no SDF image is needed. The
+    /// geometry is the same as the one rasterized by **env_to_png**, before
+    /// it is expanded and blurred. Returns `None` if `position` is outside
+    /// the environment
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    pub fn signed_distance(&self, position: Vec2) -> Option<f32> {
+        let (width, height) = self.dimensions();
+        // measured from the top left corner of the map with the y-axis
+        // pointing down, as the rows of the tile grid and the SDF image
+        let position = Vec2::new(position.x + width / 2.0, height / 2.0 - position.y);
+        if !(0.0..=width).contains(&position.x) || !(0.0..=height).contains(&position.y) {
+            return None;
+        }
+
+        let tile_size = Vec2::new(self.tile_width(), self.tile_height());
+        let (nrows, ncols) = self.tiles.grid.shape();
+        if nrows == 0 || ncols == 0 {
+            return None;
+        }
+        let row = ((position.y / tile_size.y) as usize).min(nrows - 1);
+        let col = ((position.x / tile_size.x) as usize).min(ncols - 1);
+
+        let walls = self.wall_signed_distance(position, row, col);
+        let boundary = if self.add_boundary() {
+            position
+                .min(Vec2::new(width, height) - position)
+                .min_element()
+                - self.boundary_thickness()
+        } else {
+            f32::INFINITY
+        };
+
+        // the shapes of the obstacles are relative to the tile they are placed
+        // in, with the y-axis pointing down
+        let extent = self.tile_extent();
+        let obstacles = self
+            .obstacles
+            .iter()
+            .map(|obstacle| {
+                let TileCoordinates { row, col } = obstacle.tile_coordinates;
+                let relative = position / tile_size - Vec2::new(col as f32, row as f32);
+                obstacle.signed_distance(relative - Vec2::from(obstacle.translation)) * extent
+            })
+            .fold(f32::INFINITY, f32::min);
+
+        Some(walls.min(boundary).min(obstacles))
+    }
+
+    /// Signed distance from `position`, in meters from the top left corner of
+    /// the map, to the walls of the tile grid. Only the tile at `row` and
+    /// `col` and its neighbours are considered
+    #[allow(clippy::cast_precision_loss)]
+    fn wall_signed_distance(&self, position: Vec2, row: usize, col: usize) -> f32 {
+        let tile_size = Vec2::new(self.tile_width(), self.tile_height());
+        let half_path = self.path_width() * self.tile_extent() / 2.0;
+        let tile = |row: Option<usize>, col: Option<usize>| self.tiles.grid.get_tile(row?, col?);
+
+        let free = (row.saturating_sub(1)..=row + 1)
+            .cartesian_product(col.saturating_sub(1)..=col + 1)
+            .filter_map(|(row, col)| Some((row, col, tile(Some(row), Some(col))?)))
+            .flat_map(|(row, col, kind)| {
+                let origin = Vec2::new(col as f32, row as f32) * tile_size;
+                let center = origin + tile_size / 2.0;
+                // [north, east, south, west] neighbours, and the side of them
+                // facing this tile
+                let neighbours = [
+                    (tile(row.checked_sub(1), Some(col)), 2),
+                    (tile(Some(row), col.checked_add(1)), 3),
+                    (tile(row.checked_add(1), Some(col)), 0),
+                    (tile(Some(row), col.checked_sub(1)), 1),
+                ];
+                // a path continuing into the neighbouring tile is extended
+                // into it, so the edge of the tile is not taken for a wall
+                let extension = |side: usize| {
+                    let (neighbour, facing) = neighbours[side];
+                    let connected = neighbour.is_some_and(|neighbour| {
+                        tile_openings(neighbour).map_or(true, |openings| openings[facing])
+                    });
+                    if !connected {
+                        0.0
+                    } else if side % 2 == 0 {
+                        tile_size.y / 2.0
+                    } else {
+                        tile_size.x / 2.0
+                    }
+                };
+
+                let Some(openings) = tile_openings(kind) else {
+                    // a tile without walls is free everywhere, but only extends
+                    // into neighbours that are as well
+                    let free_extension = |side: usize| {
+                        if neighbours[side]
+                            .0
+                            .is_some_and(|neighbour| tile_openings(neighbour).is_none())
+                        {
+                            extension(side)
+                        } else {
+                            0.0
+                        }
+                    };
+                    let min = origin - Vec2::new(free_extension(3), free_extension(0));
+                    let max = origin + tile_size + Vec2::new(free_extension(1), free_extension(2));
+                    return vec![(min, max)];
+                };
+
+                // a path through the tile reaches across its center, a dead
+                // end stops at it
+                let inner = if openings.iter().filter(|open| **open).count() >= 2 {
+                    half_path
+                } else {
+                    0.0
+                };
+                let end = origin + tile_size;
+                let arms = [
+                    (
+                        Vec2::new(center.x - half_path, origin.y - extension(0)),
+                        Vec2::new(center.x + half_path, center.y + inner),
+                    ),
+                    (
+                        Vec2::new(center.x - inner, center.y - half_path),
+                        Vec2::new(end.x + extension(1), center.y + half_path),
+                    ),
+                    (
+                        Vec2::new(center.x - half_path, center.y - inner),
+                        Vec2::new(center.x + half_path, end.y + extension(2)),
+                    ),
+                    (
+                        Vec2::new(origin.x - extension(3), center.y - half_path),
+                        Vec2::new(center.x + inner, center.y + half_path),
+                    ),
+                ];
+                openings
+                    .into_iter()
+                    .zip(arms)
+                    .filter_map(|(open, arm)| open.then_some(arm))
+                    .collect()
+            })
+            .map(|(min, max)| box_signed_distance(position, min, max))
+            .fold(f32::INFINITY, f32::min);
+
+        if free.is_finite() {
+            -free
+        } else {
+            // surrounded by walls
+            -tile_size.max_element()
+        }
+    }
+}
+
+/// The sides of `tile` a path leaves it through, as [north, east, south,
+/// west]. `None` for tiles without walls
+/// Thickness of the boundary walls, relative to the shorter side of a tile
+const BOUNDARY_THICKNESS: f32 = 0.1;
+
+const fn tile_openings(tile: char) -> Option<[bool; 4]> {
+    let openings = match tile {
+        '─' => [false, true, false, true],
+        '│' => [true, false, true, false],
+        '╴' => [false, false, false, true],
+        '╶' => [false, true, false, false],
+        '╷' => [false, false, true, false],
+        '╵' => [true, false, false, false],
+        '┌' => [false, true, true, false],
+        '┐' => [false, false, true, true],
+        '└' => [true, true, false, false],
+        '┘' => [true, false, false, true],
+        '┬' => [false, true, true, true],
+        '┴' => [true, true, false, true],
+        '├' => [true, true, true, false],
+        '┤' => [true, false, true, true],
+        '┼' => [true, true, true, true],
+        ' ' => [false, false, false, false],
+        _ => return None,
+    };
+    Some(openings)
+}
+
+/// Signed distance from `point` to the axis aligned box from `min` to `max`,
+/// negative inside
+fn box_signed_distance(point: Vec2, min: Vec2, max: Vec2) -> f32 {
+    let center = (min + max) / 2.0;
+    let q = (point - center).abs() - (max - min) / 2.0;
+    q.max(Vec2::ZERO).length() + q.max_element().min(0.0)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn signed_distance_is_negative_inside_and_positive_outside() {
+        let circle = PlaceableShape::circle(StrictlyPositiveFinite::<Float>::new(0.5).unwrap());
+        assert_close(circle.signed_distance(Vec2::ZERO), -0.5);
+        assert_close(circle.signed_distance(Vec2::new(1.0, 0.0)), 0.5);
+
+        let polygon = PlaceableShape::Polygon(Polygon::new(vec![
+            Point::new(0.0, 0.0),
+            Point::new(1.0, 0.0),
+            Point::new(1.0, 1.0),
+            Point::new(0.0, 1.0),
+        ]));
+        assert_close(polygon.signed_distance(Vec2::new(0.5, 0.5)), -0.5);
+        assert_close(polygon.signed_distance(Vec2::new(0.5, 2.0)), 1.0);
+        assert_close(polygon.signed_distance(Vec2::new(2.0, 2.0)), 2.0f32.sqrt());
+    }
+
+    #[test]
+    fn signed_distance_agrees_with_inside() {
+        let shapes = [
+            PlaceableShape::rectangle(0.8, 0.4),
+            PlaceableShape::regular_polygon(5, 0.4),
+            PlaceableShape::capsule(0.4, 0.1),
+            PlaceableShape::composite([
+                (PlaceableShape::rectangle(0.4, 0.4), (0.0, 0.0)),
+                (PlaceableShape::capsule(0.2, 0.1), (0.2, 0.0)),
+            ]),
+        ];
+        for shape in &shapes {
+            for x in -10..=10 {
+                for y in -10..=10 {
+                    #[allow(clippy::cast_precision_loss)]
+                    let point = Vec2::new(x as f32, y as f32) * 0.05;
+                    let distance = shape.signed_distance(point);
+                    // points on the edge may go either way
+                    if distance.abs() > 1e-4 {
+                        assert_eq!(shape.inside(point), distance < 0.0, "{shape:?} at {point}");
+                    }
+                }
+            }
+        }
+    }
+
+    /// The rows of `grid` as strings, for readable assertions
+    fn rows(grid: &TileGrid) -> Vec<String> {
+        grid.rows().map(|row| row.iter().collect()).collect()
+    }
+
+    #[test]
+    fn from_occupancy_connects_free_neighbours() {
+        let occupancy = ["##.##", "#...#", "##.##", "#####", "..#.#"];
+        let grid = TileGrid::from_occupancy(occupancy.len(), occupancy[0].len(), |row, col| {
+            occupancy[row].as_bytes()[col] == b'.'
+        });
+        assert_eq!(rows(&grid), vec![
+            "  ╷  ",
+            " ╶┼╴ ",
+            "  ╵  ",
+            "     ",
+            "╶╴ ┼ "
+        ]);
+    }
+
+    #[test]
+    fn from_image_thresholds_the_luminance() {
+        let path = std::env::temp_dir().join(format!(
+            "gbp-environment-from-image-{}.png",
+            std::process::id()
+        ));
+        // a free corner in the top left, and a grey pixel in the bottom right
+        let image = image::GrayImage::from_fn(3, 2, |x, y| match (x, y) {
+            (0, 0) | (1, 0) | (0, 1) => image::Luma([255]),
+            (2, 1) => image::Luma([100]),
+            _ => image::Luma([0]),
+        });
+        image.save(&path).unwrap();
+
+        let dark = TileGrid::from_image(&path, 128);
+        let light = TileGrid::from_image(&path, 100);
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(rows(&dark.unwrap()), vec!["┌╴ ", "╵  "]);
+        assert_eq!(rows(&light.unwrap()), vec!["┌╴ ", "╵ ┼"]);
+    }
+
+    #[test]
+    fn from_image_fails_on_a_missing_file() {
+        let path = std::env::temp_dir().join("gbp-environment-from-image-missing.png");
+        assert!(matches!(
+            TileGrid::from_image(path, 128),
+            Err(FromImageError::Image(_))
+        ));
+    }
+
+    #[test]
+    fn boundary_walls_lie_inside_the_map() {
+        let mut environment = Environment::intersection();
+        assert_eq!(environment.boundary_thickness(), 0.0);
+        environment.tiles.settings.add_boundary = true;
+        let thickness = environment.boundary_thickness();
+        assert!(thickness > 0.0);
+
+        // along the road leaving the map on the left
+        let (width, _) = environment.dimensions();
+        let distance = |from_edge: f32| {
+            environment
+                .signed_distance(Vec2::new(from_edge - width / 2.0, 0.0))
+                .unwrap()
+        };
+        assert!(distance(thickness / 2.0) < 0.0);
+        assert!((distance(thickness + 1.0) - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn unversioned_files_are_migrated() {
+        let environment = Environment::parse(include_str!(
+            "../../../config/scenarios/Obstacle Shapes Showcase/environment.yaml"
+        ))
+        .unwrap();
+        assert_eq!(environment.version, Environment::SCHEMA.version);
+        assert!(environment.obstacles.iter().next().is_some());
+    }
+
+    #[test]
+    fn obstacles_are_moved_out_of_their_shape() {
+        let environment = Environment::parse(
+            r"
+            tiles:
+              grid:
+              - ┼
+              settings:
+                tile-size: 100.0
+                path-width: 0.1375
+                obstacle-height: 1.0
+            obstacles:
+            - shape: !circle
+                radius: 0.1
+                center: { x: 0.2, y: 0.3 }
+              rotation: 0.0
+            - shape: !regular-polygon
+                sides: 4
+                radius: 0.1
+                side-length: 0.1
+                translation: { x: 0.7, y: 0.6 }
+              rotation: 0.0
+            ",
+        )
+        .unwrap();
+        assert_eq!(environment.version, Environment::SCHEMA.version);
+
+        let translations = environment
+            .obstacles
+            .iter()
+            .map(|obstacle| (obstacle.translation.x.get(), obstacle.translation.y.get()))
+            .collect::<Vec<_>>();
+        assert_eq!(translations, vec![(0.2, 0.3), (0.7, 0.6)]);
+    }
 }
@@ -0,0 +1,405 @@
+//! Import of road networks from [OpenStreetMap](https://www.openstreetmap.org) extracts.
+//!
+//! Reads an `.osm` XML extract, keeps the `highway` ways, projects them into
+//! local metric coordinates relative to the south-west corner of a bounding
+//! box, and rasterises them onto a [`TileGrid`]. Every tile a road passes
+//! through becomes a path tile, so junctions and corridors are derived from the
+//! road geometry instead of being transcribed by hand. Roads are clipped to the
+//! bounding box before they are rasterised, so nodes far outside of it do not
+//! cost anything.
+
+use std::{collections::HashMap, path::Path};
+
+use crate::{Environment, Obstacles, Regions, SdfSettings, TileGrid, TileSettings, Tiles};
+
+/// Mean radius of the earth in meters
+const EARTH_RADIUS: f64 = 6_371_000.0;
+
+/// Geographic bounding box in degrees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+impl BoundingBox {
+    #[must_use]
+    pub const fn new(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Self {
+        Self {
+            min_lat,
+            min_lon,
+            max_lat,
+            max_lon,
+        }
+    }
+
+    /// Returns `true` if the coordinate lies within the bounding box
+    #[must_use]
+    pub fn contains(&self, lat: f64, lon: f64) -> bool {
+        (self.min_lat..=self.max_lat).contains(&lat) && (self.min_lon..=self.max_lon).contains(&lon)
+    }
+
+    /// Project a coordinate into meters relative to the south-west corner,
+    /// with x pointing east and y pointing north. Uses an equirectangular
+    /// projection, which is accurate for the small extents of a scenario.
+    #[must_use]
+    pub fn project(&self, lat: f64, lon: f64) -> (f64, f64) {
+        let mid_lat = ((self.min_lat + self.max_lat) / 2.0).to_radians();
+        let x = (lon - self.min_lon).to_radians() * mid_lat.cos() * EARTH_RADIUS;
+        let y = (lat - self.min_lat).to_radians() * EARTH_RADIUS;
+        (x, y)
+    }
+
+    /// Size of the bounding box in meters, (width, height)
+    #[must_use]
+    pub fn size(&self) -> (f64, f64) {
+        self.project(self.max_lat, self.max_lon)
+    }
+}
+
+/// Settings for the conversion of a road network into an [`Environment`]
+#[derive(Debug, Clone)]
+pub struct OsmImportSettings {
+    /// Area of the extract to import
+    pub bounding_box:    BoundingBox,
+    /// Side length of a tile in meters
+    pub tile_size:       f32,
+    /// Width of the roads relative to the tile size
+    pub path_width:      f32,
+    /// Height of the obstacles between the roads
+    pub obstacle_height: f32,
+    /// Values of the `highway` tag to import. If empty, all ways with a
+    /// `highway` tag are imported.
+    pub highway_types:   Vec<String>,
+}
+
+impl OsmImportSettings {
+    #[must_use]
+    pub const fn new(bounding_box: BoundingBox, tile_size: f32) -> Self {
+        Self {
+            bounding_box,
+            tile_size,
+            path_width: 0.5,
+            obstacle_height: 1.0,
+            highway_types: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OsmImportError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("XML error: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("Node {0} is missing a valid `{1}` attribute")]
+    InvalidNode(String, &'static str),
+    #[error("Tile size must be positive and finite, got {0}")]
+    InvalidTileSize(f32),
+    #[error("Bounding box is empty")]
+    EmptyBoundingBox,
+    #[error("No roads found within the bounding box")]
+    NoRoads,
+}
+
+impl Environment {
+    /// Import the road network of an OpenStreetMap extract as an
+    /// [`Environment`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if:
+    /// 1. `path` can not be read
+    /// 2. The contents of `path` are not valid XML
+    /// 3. A node is missing its coordinates
+    /// 4. The settings describe an empty area, or no roads lie within it
+    pub fn from_osm<P: AsRef<Path>>(
+        path: P,
+        settings: &OsmImportSettings,
+    ) -> Result<Self, OsmImportError> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse_osm(&contents, settings)
+    }
+
+    /// Import the road network of an OpenStreetMap XML document as an
+    /// [`Environment`]. See [`Environment::from_osm`]
+    ///
+    /// # Errors
+    ///
+    /// See [`Environment::from_osm`]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    pub fn parse_osm(contents: &str, settings: &OsmImportSettings) -> Result<Self, OsmImportError> {
+        if !(settings.tile_size.is_finite() && settings.tile_size > 0.0) {
+            return Err(OsmImportError::InvalidTileSize(settings.tile_size));
+        }
+        let bbox = settings.bounding_box;
+        let (width, height) = bbox.size();
+        if !(width > 0.0 && height > 0.0) {
+            return Err(OsmImportError::EmptyBoundingBox);
+        }
+
+        let document = roxmltree::Document::parse(contents)?;
+
+        let mut nodes = HashMap::<&str, (f64, f64)>::new();
+        for node in document.descendants().filter(|n| n.has_tag_name("node")) {
+            let Some(id) = node.attribute("id") else {
+                continue;
+            };
+            let coordinate = |name: &'static str| {
+                node.attribute(name)
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .ok_or_else(|| OsmImportError::InvalidNode(id.to_string(), name))
+            };
+            nodes.insert(id, (coordinate("lat")?, coordinate("lon")?));
+        }
+
+        let tile_size = f64::from(settings.tile_size);
+        let ncols = (width / tile_size).ceil() as usize;
+        let nrows = (height / tile_size).ceil() as usize;
+        let mut free = vec![vec![false; ncols]; nrows];
+
+        // Convert a projected point within the bounding box into (row, col),
+        // with row 0 being the northern most row. Points on the northern or
+        // eastern edge belong to the last row or column
+        let to_cell = |(x, y): (f64, f64)| {
+            let col = ((x / tile_size).floor() as i64).clamp(0, ncols as i64 - 1);
+            let row =
+                nrows as i64 - 1 - ((y / tile_size).floor() as i64).clamp(0, nrows as i64 - 1);
+            (row, col)
+        };
+
+        let is_road = |way: &roxmltree::Node| {
+            way.children()
+                .filter(|tag| tag.has_tag_name("tag"))
+                .any(|tag| {
+                    tag.attribute("k") == Some("highway")
+                        && (settings.highway_types.is_empty()
+                            || tag
+                                .attribute("v")
+                                .is_some_and(|v| settings.highway_types.iter().any(|t| t == v)))
+                })
+        };
+
+        for way in document
+            .descendants()
+            .filter(|n| n.has_tag_name("way"))
+            .filter(is_road)
+        {
+            let points = way
+                .children()
+                .filter(|nd| nd.has_tag_name("nd"))
+                .filter_map(|nd| nd.attribute("ref"))
+                .filter_map(|id| nodes.get(id))
+                .map(|&(lat, lon)| bbox.project(lat, lon))
+                .collect::<Vec<_>>();
+
+            for (&from, &to) in points.iter().zip(points.iter().skip(1)) {
+                let Some((from, to)) = clip_segment(from, to, (width, height)) else {
+                    continue;
+                };
+                for (row, col) in four_connected_line(to_cell(from), to_cell(to)) {
+                    free[row as usize][col as usize] = true;
+                }
+            }
+        }
+
+        if !free.iter().flatten().any(|&cell| cell) {
+            return Err(OsmImportError::NoRoads);
+        }
+
+        Ok(Self {
+            tiles: Tiles {
+                grid:     TileGrid::from_occupancy(nrows, ncols, |row, col| free[row][col]),
+                settings: TileSettings {
+                    tile_size: settings.tile_size,
+                    tile_width: None,
+                    tile_height: None,
+                    path_width: settings.path_width,
+                    obstacle_height: settings.obstacle_height,
+                    add_boundary: false,
+                    sdf: SdfSettings::default(),
+                },
+            },
+            obstacles: Obstacles::empty(),
+            charging_stations: Vec::new(),
+            traffic_lights: None,
+            regions: Regions::empty(),
+            version: Self::SCHEMA.version,
+        })
+    }
+}
+
+/// The part of the segment between `from` and `to` within the rectangle from
+/// the origin to `(width, height)`, or `None` if the segment lies outside of
+/// it. Uses the Liang-Barsky algorithm.
+fn clip_segment(
+    from: (f64, f64),
+    to: (f64, f64),
+    (width, height): (f64, f64),
+) -> Option<((f64, f64), (f64, f64))> {
+    if ![from.0, from.1, to.0, to.1].iter().all(|v| v.is_finite()) {
+        return None;
+    }
+
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let (mut t_min, mut t_max) = (0.0f64, 1.0f64);
+    // (p, q) for the left, right, bottom and top edge, where the segment is
+    // inside the edge where p * t <= q
+    for (p, q) in [
+        (-dx, from.0),
+        (dx, width - from.0),
+        (-dy, from.1),
+        (dy, height - from.1),
+    ] {
+        if p == 0.0 {
+            // parallel to the edge
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t_min = t_min.max(q / p);
+        } else {
+            t_max = t_max.min(q / p);
+        }
+    }
+
+    (t_min <= t_max).then(|| {
+        let at = |t: f64| (from.0 + t * dx, from.1 + t * dy);
+        (at(t_min), at(t_max))
+    })
+}
+
+/// Cells visited by a line between two cells, where consecutive cells always
+/// share an edge. Tiles only connect to their 4-neighbours, so a diagonal step
+/// would disconnect the road.
+fn four_connected_line(from: (i64, i64), to: (i64, i64)) -> Vec<(i64, i64)> {
+    let (mut row, mut col) = from;
+    let d_row = (to.0 - from.0).abs();
+    let d_col = (to.1 - from.1).abs();
+    let step_row = (to.0 - from.0).signum();
+    let step_col = (to.1 - from.1).signum();
+
+    let mut cells = Vec::with_capacity((d_row + d_col + 1) as usize);
+    cells.push((row, col));
+
+    let (mut i_row, mut i_col) = (0, 0);
+    while i_row < d_row || i_col < d_col {
+        // step along the axis whose next cell boundary is closest
+        if (1 + 2 * i_col) * d_row < (1 + 2 * i_row) * d_col {
+            col += step_col;
+            i_col += 1;
+        } else {
+            row += step_row;
+            i_row += 1;
+        }
+        cells.push((row, col));
+    }
+
+    cells
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    /// About 111 m x 111 m, i.e. 6 x 6 tiles of 20 m
+    const BOUNDING_BOX: BoundingBox = BoundingBox::new(0.0, 0.0, 0.001, 0.001);
+
+    /// An extract with a node for every `(lat, lon)`, and a road through all of
+    /// them in order
+    fn extract(road: &[(f64, f64)]) -> String {
+        let nodes = road
+            .iter()
+            .enumerate()
+            .map(|(i, (lat, lon))| format!(r#"<node id="{i}" lat="{lat}" lon="{lon}"/>"#))
+            .collect::<String>();
+        let refs = (0..road.len())
+            .map(|i| format!(r#"<nd ref="{i}"/>"#))
+            .collect::<String>();
+        format!(r#"<osm>{nodes}<way id="0">{refs}<tag k="highway" v="residential"/></way></osm>"#)
+    }
+
+    fn rows(environment: &Environment) -> Vec<String> {
+        environment
+            .tiles
+            .grid
+            .rows()
+            .map(|row| row.iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn clip_segment_keeps_the_part_inside() {
+        let size = (10.0, 5.0);
+        assert_eq!(
+            clip_segment((-10.0, 1.0), (20.0, 1.0), size),
+            Some(((0.0, 1.0), (10.0, 1.0)))
+        );
+        assert_eq!(
+            clip_segment((2.0, 2.0), (3.0, 3.0), size),
+            Some(((2.0, 2.0), (3.0, 3.0)))
+        );
+        assert_eq!(clip_segment((-1.0, -1.0), (-5.0, 3.0), size), None);
+        assert_eq!(clip_segment((0.0, 6.0), (10.0, 6.0), size), None);
+        assert_eq!(clip_segment((f64::NAN, 0.0), (1.0, 1.0), size), None);
+    }
+
+    #[test]
+    fn four_connected_line_steps_to_edge_neighbours() {
+        for (from, to) in [((0, 0), (3, 7)), ((5, 1), (-2, -4)), ((2, 2), (2, 2))] {
+            let cells = four_connected_line(from, to);
+            assert_eq!(cells.first(), Some(&from));
+            assert_eq!(cells.last(), Some(&to));
+            for pair in cells.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                assert_eq!((a.0 - b.0).abs() + (a.1 - b.1).abs(), 1, "{a:?} -> {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn roads_far_outside_are_clipped_to_the_bounding_box() {
+        let settings = OsmImportSettings::new(BOUNDING_BOX, 20.0);
+        // from far to the west, to the middle of the map
+        let environment =
+            Environment::parse_osm(&extract(&[(0.0005, -50.0), (0.0005, 0.0005)]), &settings)
+                .unwrap();
+        assert_eq!(rows(&environment), vec![
+            "      ",
+            "      ",
+            "      ",
+            "╶─╴   ",
+            "      ",
+            "      "
+        ]);
+    }
+
+    #[test]
+    fn crossing_roads_become_a_junction() {
+        let settings = OsmImportSettings::new(BOUNDING_BOX, 20.0);
+        let contents = extract(&[(0.0005, 0.0), (0.0005, 0.001)]).replace(
+            "</osm>",
+            r#"<node id="a" lat="0.0" lon="0.0005"/><node id="b" lat="0.001" lon="0.0005"/><way id="1"><nd ref="a"/><nd ref="b"/><tag k="highway" v="primary"/></way></osm>"#,
+        );
+        let environment = Environment::parse_osm(&contents, &settings).unwrap();
+        assert_eq!(environment.tiles.grid.get_tile(3, 2), Some('┼'));
+        assert_eq!(environment.tiles.grid.get_tile(0, 2), Some('╷'));
+        assert_eq!(environment.tiles.grid.get_tile(3, 5), Some('╴'));
+    }
+
+    #[test]
+    fn no_roads_within_the_bounding_box_is_an_error() {
+        let settings = OsmImportSettings::new(BOUNDING_BOX, 20.0);
+        assert!(matches!(
+            Environment::parse_osm(&extract(&[(1.0, 1.0), (2.0, 2.0)]), &settings),
+            Err(OsmImportError::NoRoads)
+        ));
+    }
+}
@@ -0,0 +1,734 @@
+//! Procedural generation of cluttered environments.
+//!
+//! [`Obstacles::random`] scatters non-overlapping shapes across the free space
+//! of a [`Tiles`] map, for benchmarking planners in cluttered environments.
+//! Every candidate placement is checked against a fine occupancy raster of the
+//! map, and is rejected if it would overlap the walls of the map, or cut off
+//! all routes between any of the configured spawn and goal regions. A path is
+//! kept for every route, and only placements crossing it are checked for
+//! connectivity again.
+
+use std::{collections::VecDeque, ops::RangeInclusive};
+
+use angle::Angle;
+use bevy::log::warn;
+use gbp_geometry::Point;
+use gbp_linalg::Float;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{Obstacle, Obstacles, PlaceableShape, Tiles};
+
+/// Number of raster cells along each side of a tile, when checking
+/// reachability
+const RESOLUTION: usize = 16;
+/// Number of consecutive rejected placements after which the generator gives
+/// up on reaching the requested density
+const MAX_CONSECUTIVE_REJECTIONS: usize = 1000;
+
+/// Relative weights of the shapes scattered by [`Obstacles::random`]. A weight
+/// of zero excludes the shape.
+#[derive(Debug, Clone, Copy)]
+pub struct ShapeMix {
+    pub circle:    Float,
+    pub triangle:  Float,
+    pub square:    Float,
+    pub rectangle: Float,
+}
+
+impl Default for ShapeMix {
+    fn default() -> Self {
+        Self {
+            circle:    1.0,
+            triangle:  1.0,
+            square:    1.0,
+            rectangle: 1.0,
+        }
+    }
+}
+
+impl ShapeMix {
+    /// Only place circles
+    #[must_use]
+    pub const fn circles() -> Self {
+        Self {
+            circle:    1.0,
+            triangle:  0.0,
+            square:    0.0,
+            rectangle: 0.0,
+        }
+    }
+
+    fn total(&self) -> Float {
+        self.circle + self.triangle + self.square + self.rectangle
+    }
+}
+
+/// An axis aligned region of the map, in coordinates relative to the map,
+/// i.e. `(0.0, 0.0)` is the bottom left corner, and `(1.0, 1.0)` is the top
+/// right corner. The same convention as the shapes of a formation.
+#[derive(Debug, Clone, Copy)]
+pub struct Region {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Region {
+    #[must_use]
+    pub const fn new(min: Point, max: Point) -> Self {
+        Self { min, max }
+    }
+
+    /// Returns `true` if the relative point `(x, y)` lies within the region
+    #[must_use]
+    pub fn contains(&self, x: Float, y: Float) -> bool {
+        (self.min.x..=self.max.x).contains(&x) && (self.min.y..=self.max.y).contains(&y)
+    }
+}
+
+/// A pair of regions, that has to stay connected after the obstacles have been
+/// placed
+#[derive(Debug, Clone, Copy)]
+pub struct Route {
+    /// Where the robots spawn
+    pub spawn:     Region,
+    /// Where the robots are headed
+    pub goal:      Region,
+    /// Minimum width of the passage between obstacles, relative to the tile
+    /// size
+    pub clearance: Float,
+}
+
+impl Route {
+    #[must_use]
+    pub const fn new(spawn: Region, goal: Region) -> Self {
+        Self {
+            spawn,
+            goal,
+            clearance: 0.05,
+        }
+    }
+
+    /// Set the minimum width of the passage between obstacles
+    #[must_use]
+    pub const fn with_clearance(mut self, clearance: Float) -> Self {
+        self.clearance = clearance;
+        self
+    }
+}
+
+impl Obstacles {
+    /// Scatter random, non-overlapping obstacles across the free space of
+    /// `tiles`.
+    ///
+    /// - `seed` seeds the random number generator, so the same arguments always
+    ///   generate the same obstacles
+    /// - `density` is the fraction of the free space, in [0, 1], to cover with
+    ///   obstacles
+    /// - `size_range` is the range of the radius of the shapes, relative to the
+    ///   tile size
+    /// - `shape_mix` is the relative frequency of each shape
+    ///
+    /// A placement is rejected if it would block every path between the
+    /// spawn and goal region of any of the `routes`. Routes that are already
+    /// blocked by the tiles themselves are ignored. If the requested density
+    /// can not be reached, the obstacles placed so far are returned.
+    #[must_use]
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::missing_panics_doc
+    )]
+    pub fn random(
+        tiles: &Tiles,
+        routes: &[Route],
+        seed: u64,
+        density: Float,
+        size_range: RangeInclusive<Float>,
+        shape_mix: &ShapeMix,
+    ) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let raster = Raster::new(tiles);
+
+        let mut routes = routes
+            .iter()
+            .filter_map(|route| {
+                let connectivity = Connectivity::new(&raster, route);
+                if connectivity.is_none() {
+                    warn!("route {:?} is blocked by the tiles, ignoring it", route);
+                }
+                connectivity
+            })
+            .collect::<Vec<_>>();
+
+        let (nrows, ncols) = tiles.grid.shape();
+        let candidate_tiles = (0..nrows)
+            .flat_map(|row| (0..ncols).map(move |col| (row, col)))
+            .filter(|&(row, col)| {
+                tiles
+                    .grid
+                    .get_tile(row, col)
+                    .is_some_and(|tile| tile != ' ')
+            })
+            .collect::<Vec<_>>();
+
+        let (min_size, max_size) = (*size_range.start(), *size_range.end());
+        if candidate_tiles.is_empty()
+            || shape_mix.total() <= 0.0
+            || !(min_size > 0.0 && min_size <= max_size && max_size < 0.5)
+        {
+            return Self::empty();
+        }
+
+        let target_area = density.clamp(0.0, 1.0) * raster.free_area();
+        let mut covered_area = 0.0;
+        let mut placed: Vec<Placed> = Vec::new();
+        let mut obstacles = Vec::new();
+        let mut rejections = 0;
+
+        while covered_area < target_area && rejections < MAX_CONSECUTIVE_REJECTIONS {
+            let (row, col) = candidate_tiles[rng.gen_range(0..candidate_tiles.len())];
+            let size = rng.gen_range(size_range.clone());
+            let shape = random_shape(&mut rng, shape_mix, size);
+            let radius = bounding_radius(&shape);
+            if radius >= 0.5 {
+                rejections += 1;
+                continue;
+            }
+            let translation = (
+                rng.gen_range(radius..=1.0 - radius),
+                rng.gen_range(radius..=1.0 - radius),
+            );
+            let center = (
+                col as Float + translation.0,
+                (nrows - 1 - row) as Float + translation.1,
+            );
+
+            let candidate = Placed { center, radius };
+            let reroutes = (raster.is_free_within(center, radius)
+                && placed.iter().all(|other| !other.overlaps(&candidate)))
+            .then(|| {
+                routes
+                    .iter()
+                    .map(|route| route.reroute(&raster, &candidate))
+                    .collect::<Option<Vec<_>>>()
+            })
+            .flatten();
+
+            let Some(reroutes) = reroutes else {
+                rejections += 1;
+                continue;
+            };
+
+            for (route, reroute) in routes.iter_mut().zip(reroutes) {
+                route.place(&raster, &candidate, reroute);
+            }
+            rejections = 0;
+            covered_area += std::f64::consts::PI * radius * radius;
+            placed.push(candidate);
+            let rotation = rng.gen_range(0.0..std::f64::consts::TAU);
+            obstacles.push(Obstacle::new((row, col), shape, rotation, translation));
+        }
+
+        if covered_area < target_area {
+            warn!(
+                "only reached an obstacle density of {:.3} out of {:.3}",
+                covered_area / raster.free_area(),
+                density
+            );
+        }
+
+        Self(obstacles)
+    }
+}
+
+/// The bounding circle of a placed obstacle, in tile units with the origin in
+/// the bottom left corner of the map
+#[derive(Debug, Clone, Copy)]
+struct Placed {
+    center: (Float, Float),
+    radius: Float,
+}
+
+impl Placed {
+    fn overlaps(&self, other: &Self) -> bool {
+        let (dx, dy) = (
+            self.center.0 - other.center.0,
+            self.center.1 - other.center.1,
+        );
+        dx.hypot(dy) < self.radius + other.radius
+    }
+}
+
+/// Occupancy raster of the tiles, with [`RESOLUTION`] x [`RESOLUTION`] cells
+/// per tile. Row 0 is the bottom row of the map.
+struct Raster {
+    rows: usize,
+    cols: usize,
+    free: Vec<bool>,
+}
+
+impl Raster {
+    #[allow(clippy::cast_precision_loss)]
+    fn new(tiles: &Tiles) -> Self {
+        let (nrows, ncols) = tiles.grid.shape();
+        let path_width = Float::from(tiles.settings.path_width);
+        let (rows, cols) = (nrows * RESOLUTION, ncols * RESOLUTION);
+
+        let mut free = vec![false; rows * cols];
+        for i in 0..rows {
+            for j in 0..cols {
+                let row = nrows - 1 - i / RESOLUTION;
+                let col = j / RESOLUTION;
+                let u = ((j % RESOLUTION) as Float + 0.5) / RESOLUTION as Float;
+                let v = ((i % RESOLUTION) as Float + 0.5) / RESOLUTION as Float;
+                free[i * cols + j] = tiles
+                    .grid
+                    .get_tile(row, col)
+                    .is_some_and(|tile| is_free_within_tile(tile, u, v, path_width));
+            }
+        }
+
+        Self { rows, cols, free }
+    }
+
+    /// Free area of the map, in tiles
+    #[allow(clippy::cast_precision_loss)]
+    fn free_area(&self) -> Float {
+        self.free.iter().filter(|&&free| free).count() as Float / (RESOLUTION * RESOLUTION) as Float
+    }
+
+    /// The cell containing the point `(x, y)` in tile units
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn cell_at(&self, (x, y): (Float, Float)) -> Option<usize> {
+        let (i, j) = (y * RESOLUTION as Float, x * RESOLUTION as Float);
+        if i < 0.0 || j < 0.0 || i as usize >= self.rows || j as usize >= self.cols {
+            return None;
+        }
+        Some(i as usize * self.cols + j as usize)
+    }
+
+    /// Returns `true` if the center of `cell` lies within `reach` of `center`,
+    /// in tile units
+    #[allow(clippy::cast_precision_loss)]
+    fn covers(&self, cell: usize, (cx, cy): (Float, Float), reach: Float) -> bool {
+        let cell_size = 1.0 / RESOLUTION as Float;
+        let (x, y) = (
+            ((cell % self.cols) as Float + 0.5) * cell_size,
+            ((cell / self.cols) as Float + 0.5) * cell_size,
+        );
+        (x - cx).hypot(y - cy) < reach
+    }
+
+    /// The cells with a center within `reach` of `center`, in tile units
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn cells_within(
+        &self,
+        center: (Float, Float),
+        reach: Float,
+    ) -> impl Iterator<Item = usize> + '_ {
+        let cell_size = 1.0 / RESOLUTION as Float;
+        let (cx, cy) = center;
+        let i_range = ((cy - reach) / cell_size).floor().max(0.0) as usize
+            ..((cy + reach) / cell_size).ceil().min(self.rows as Float) as usize;
+        let j_range = ((cx - reach) / cell_size).floor().max(0.0) as usize
+            ..((cx + reach) / cell_size).ceil().min(self.cols as Float) as usize;
+        i_range
+            .flat_map(move |i| j_range.clone().map(move |j| i * self.cols + j))
+            .filter(move |&cell| self.covers(cell, center, reach))
+    }
+
+    /// Returns `true` if the circle with `center` and `radius`, in tile
+    /// units, lies within the map and only covers free cells
+    #[allow(clippy::cast_precision_loss)]
+    fn is_free_within(&self, center: (Float, Float), radius: Float) -> bool {
+        let (width, height) = (
+            self.cols as Float / RESOLUTION as Float,
+            self.rows as Float / RESOLUTION as Float,
+        );
+        let (cx, cy) = center;
+        let inside = cx - radius >= 0.0
+            && cy - radius >= 0.0
+            && cx + radius <= width
+            && cy + radius <= height;
+        inside
+            && self.cell_at(center).is_some()
+            && self
+                .cells_within(center, radius)
+                .all(|cell| self.free[cell])
+    }
+
+    /// The cells of a shortest 4-connected path between the spawn and goal
+    /// region of `route`, through the cells that are `free` and not `blocked`
+    #[allow(clippy::cast_precision_loss)]
+    fn path(
+        &self,
+        route: &Route,
+        free: &[bool],
+        blocked: impl Fn(usize) -> bool,
+    ) -> Option<Vec<usize>> {
+        let passable = |cell: usize| free[cell] && !blocked(cell);
+        // relative map coordinates of the center of a cell
+        let relative = |cell: usize| {
+            (
+                ((cell % self.cols) as Float + 0.5) / self.cols as Float,
+                ((cell / self.cols) as Float + 0.5) / self.rows as Float,
+            )
+        };
+
+        // the cell each visited cell was reached from, itself for the start
+        let mut parent = vec![usize::MAX; free.len()];
+        let mut queue = (0..free.len())
+            .filter(|&cell| {
+                let (x, y) = relative(cell);
+                route.spawn.contains(x, y) && passable(cell)
+            })
+            .collect::<VecDeque<_>>();
+        for &cell in &queue {
+            parent[cell] = cell;
+        }
+
+        while let Some(cell) = queue.pop_front() {
+            let (x, y) = relative(cell);
+            if route.goal.contains(x, y) {
+                let mut path = vec![cell];
+                let mut current = cell;
+                while parent[current] != current {
+                    current = parent[current];
+                    path.push(current);
+                }
+                return Some(path);
+            }
+
+            let (i, j) = (cell / self.cols, cell % self.cols);
+            let neighbours = [
+                (i > 0).then(|| cell - self.cols),
+                (i + 1 < self.rows).then(|| cell + self.cols),
+                (j > 0).then(|| cell - 1),
+                (j + 1 < self.cols).then(|| cell + 1),
+            ];
+            for neighbour in neighbours.into_iter().flatten() {
+                if parent[neighbour] == usize::MAX && passable(neighbour) {
+                    parent[neighbour] = cell;
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// A [`Route`] kept connected while obstacles are placed
+struct Connectivity<'a> {
+    route:   &'a Route,
+    /// Free cells of the raster, minus the cells within the clearance of the
+    /// route of the obstacles placed so far
+    free:    Vec<bool>,
+    /// Whether a cell is on the current path between the spawn and goal
+    /// region
+    on_path: Vec<bool>,
+}
+
+/// How a [`Route`] stays connected, when an obstacle is placed
+enum Reroute {
+    /// The obstacle does not touch the current path
+    Unaffected,
+    /// The obstacle blocks the current path, but not this one
+    Via(Vec<usize>),
+}
+
+impl<'a> Connectivity<'a> {
+    /// Returns `None` if `route` is not connected in `raster` to begin with
+    fn new(raster: &Raster, route: &'a Route) -> Option<Self> {
+        let free = raster.free.clone();
+        let path = raster.path(route, &free, |_| false)?;
+        let mut connectivity = Self {
+            route,
+            free,
+            on_path: vec![false; raster.free.len()],
+        };
+        connectivity.follow(&path);
+        Some(connectivity)
+    }
+
+    fn follow(&mut self, path: &[usize]) {
+        self.on_path.fill(false);
+        for &cell in path {
+            self.on_path[cell] = true;
+        }
+    }
+
+    /// How the route stays connected with `candidate` placed, or `None` if the
+    /// candidate cuts it off. Only searches for a new path if the candidate
+    /// blocks the current one.
+    fn reroute(&self, raster: &Raster, candidate: &Placed) -> Option<Reroute> {
+        let reach = candidate.radius + self.route.clearance;
+        if !raster
+            .cells_within(candidate.center, reach)
+            .any(|cell| self.on_path[cell])
+        {
+            return Some(Reroute::Unaffected);
+        }
+
+        raster
+            .path(self.route, &self.free, |cell| {
+                raster.covers(cell, candidate.center, reach)
+            })
+            .map(Reroute::Via)
+    }
+
+    /// Place `candidate`, which has been checked with
+    /// [`Connectivity::reroute`]
+    fn place(&mut self, raster: &Raster, candidate: &Placed, reroute: Reroute) {
+        let reach = candidate.radius + self.route.clearance;
+        for cell in raster.cells_within(candidate.center, reach) {
+            self.free[cell] = false;
+        }
+        if let Reroute::Via(path) = reroute {
+            self.follow(&path);
+        }
+    }
+}
+
+/// Returns `true` if the point `(u, v)` within a tile, with `v` pointing up,
+/// lies on the path of the tile. Tiles that are not box drawing characters,
+/// e.g. `█`, are open everywhere, and `' '` is blocked everywhere.
+fn is_free_within_tile(tile: char, u: Float, v: Float, path_width: Float) -> bool {
+    let [up, right, down, left] = match tile {
+        ' ' => return false,
+        '─' | '-' => [false, true, false, true],
+        '│' | '|' => [true, false, true, false],
+        '╴' => [false, false, false, true],
+        '╶' => [false, true, false, false],
+        '╷' => [false, false, true, false],
+        '╵' => [true, false, false, false],
+        '┌' => [false, true, true, false],
+        '┐' => [false, false, true, true],
+        '└' => [true, true, false, false],
+        '┘' => [true, false, false, true],
+        '┬' => [false, true, true, true],
+        '┴' => [true, true, false, true],
+        '├' => [true, true, true, false],
+        '┤' => [true, false, true, true],
+        '┼' => [true, true, true, true],
+        _ => return true,
+    };
+
+    let half = path_width / 2.0;
+    let on_vertical = (u - 0.5).abs() <= half;
+    let on_horizontal = (v - 0.5).abs() <= half;
+
+    (on_vertical && on_horizontal)
+        || (on_vertical && ((up && v >= 0.5) || (down && v <= 0.5)))
+        || (on_horizontal && ((right && u >= 0.5) || (left && u <= 0.5)))
+}
+
+/// Draw a shape from `shape_mix`, with a bounding radius of about `size`
+fn random_shape(rng: &mut impl Rng, shape_mix: &ShapeMix, size: Float) -> PlaceableShape {
+    let mut pick = rng.gen_range(0.0..shape_mix.total());
+    let weights = [
+        shape_mix.circle,
+        shape_mix.triangle,
+        shape_mix.square,
+        shape_mix.rectangle,
+    ];
+    let index = weights
+        .iter()
+        .position(|&weight| {
+            pick -= weight;
+            weight > 0.0 && pick < 0.0
+        })
+        .unwrap_or(0);
+
+    match index {
+        1 => {
+            let sixty = Angle::from_degrees(60.0).expect("60 degrees is a valid angle");
+            // the circumradius of an equilateral triangle is twice the inradius
+            PlaceableShape::triangle(
+                [sixty, sixty],
+                (size / 2.0)
+                    .try_into()
+                    .expect("size is positive and finite"),
+            )
+        }
+        2 => PlaceableShape::square(size),
+        3 => {
+            let aspect = rng.gen_range(0.25..=1.0);
+            let width = 2.0 * size / Float::hypot(1.0, aspect);
+            PlaceableShape::rectangle(width, width * aspect)
+        }
+        _ => PlaceableShape::circle(size.try_into().expect("size is positive and finite")),
+    }
+}
+
+/// Radius of a circle centered at the origin of the shape, that contains the
+/// whole shape
+fn bounding_radius(shape: &PlaceableShape) -> Float {
+    match shape {
+        PlaceableShape::Circle(circle) => circle.radius.get(),
+        PlaceableShape::Triangle(triangle) => triangle
+            .points()
+            .iter()
+            .map(|point| Float::from(point.length()))
+            .fold(0.0, Float::max),
+        PlaceableShape::RegularPolygon(polygon) => polygon.radius.get(),
+        PlaceableShape::Polygon(polygon) => polygon
+            .points
+            .iter()
+            .map(|point| point.x.hypot(point.y))
+            .fold(0.0, Float::max),
+        PlaceableShape::Rectangle(rectangle) => {
+            rectangle.width.get().hypot(rectangle.height.get()) / 2.0
+        }
+        PlaceableShape::Capsule(capsule) => capsule.length.get() / 2.0 + capsule.radius.get(),
+        PlaceableShape::Composite(composite) => composite
+            .parts
+            .iter()
+            .map(|part| part.offset.x.hypot(part.offset.y) + bounding_radius(&part.shape))
+            .fold(0.0, Float::max),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{SdfSettings, TileGrid, TileSettings};
+
+    const SEED: u64 = 0x5EED;
+
+    fn tiles(grid: Vec<&str>, path_width: f32) -> Tiles {
+        Tiles {
+            grid:     TileGrid::new(grid),
+            settings: TileSettings {
+                tile_size: 1.0,
+                tile_width: None,
+                tile_height: None,
+                path_width,
+                obstacle_height: 1.0,
+                add_boundary: false,
+                sdf: SdfSettings::default(),
+            },
+        }
+    }
+
+    /// From the left to the right end of a map
+    fn left_to_right() -> Route {
+        Route::new(
+            Region::new(Point::new(0.0, 0.0), Point::new(0.1, 1.0)),
+            Region::new(Point::new(0.9, 0.0), Point::new(1.0, 1.0)),
+        )
+    }
+
+    /// The bounding circles of `obstacles`, in tile units
+    #[allow(clippy::cast_precision_loss)]
+    fn placed(tiles: &Tiles, obstacles: &Obstacles) -> Vec<Placed> {
+        let (nrows, _) = tiles.grid.shape();
+        obstacles
+            .0
+            .iter()
+            .map(|obstacle| Placed {
+                center: (
+                    obstacle.tile_coordinates.col as Float + obstacle.translation.x.get(),
+                    (nrows - 1 - obstacle.tile_coordinates.row) as Float
+                        + obstacle.translation.y.get(),
+                ),
+                radius: bounding_radius(&obstacle.shape),
+            })
+            .collect()
+    }
+
+    fn generate(tiles: &Tiles, routes: &[Route], seed: u64) -> Obstacles {
+        Obstacles::random(tiles, routes, seed, 0.3, 0.05..=0.15, &ShapeMix::default())
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_obstacles() {
+        let tiles = tiles(vec!["┌┬┐", "├┼┤", "└┴┘"], 0.6);
+        let first = generate(&tiles, &[], SEED);
+        let second = generate(&tiles, &[], SEED);
+        let other = generate(&tiles, &[], SEED + 1);
+
+        assert!(!first.0.is_empty());
+        assert_eq!(format!("{first:?}"), format!("{second:?}"));
+        assert_ne!(format!("{first:?}"), format!("{other:?}"));
+    }
+
+    #[test]
+    fn obstacles_stay_clear_of_the_walls() {
+        let tiles = tiles(vec!["┌┬┐", "├┼┤", "└┴┘"], 0.4);
+        let raster = Raster::new(&tiles);
+        let obstacles = generate(&tiles, &[], SEED);
+
+        assert!(!obstacles.0.is_empty());
+        for obstacle in placed(&tiles, &obstacles) {
+            assert!(
+                raster.is_free_within(obstacle.center, obstacle.radius),
+                "{obstacle:?} overlaps a wall"
+            );
+        }
+    }
+
+    #[test]
+    fn routes_stay_connected() {
+        let tiles = tiles(vec!["─────"], 0.8);
+        let raster = Raster::new(&tiles);
+        let route = left_to_right();
+        let obstacles = generate(&tiles, &[route], SEED);
+        assert!(!obstacles.0.is_empty());
+
+        let mut free = raster.free.clone();
+        for obstacle in placed(&tiles, &obstacles) {
+            for cell in raster.cells_within(obstacle.center, obstacle.radius + route.clearance) {
+                free[cell] = false;
+            }
+        }
+        assert!(raster.path(&route, &free, |_| false).is_some());
+    }
+
+    #[test]
+    fn only_candidates_blocking_the_path_are_rerouted() {
+        let tiles = tiles(vec!["─────"], 0.8);
+        let raster = Raster::new(&tiles);
+        let route = left_to_right();
+        let connectivity = Connectivity::new(&raster, &route).unwrap();
+
+        // the corridor is 0.8 tiles wide, centered at y = 0.5
+        let across = Placed {
+            center: (2.5, 0.5),
+            radius: 0.4,
+        };
+        assert!(connectivity.reroute(&raster, &across).is_none());
+
+        let path_row = connectivity
+            .on_path
+            .iter()
+            .position(|&on_path| on_path)
+            .unwrap()
+            / raster.cols;
+        #[allow(clippy::cast_precision_loss)]
+        let path_y = (path_row as Float + 0.5) / RESOLUTION as Float;
+        let beside = Placed {
+            center: (2.5, if path_y < 0.5 { 0.75 } else { 0.25 }),
+            radius: 0.05,
+        };
+        let on = Placed {
+            center: (2.5, path_y),
+            radius: 0.05,
+        };
+        assert!(matches!(
+            connectivity.reroute(&raster, &beside),
+            Some(Reroute::Unaffected)
+        ));
+        assert!(matches!(
+            connectivity.reroute(&raster, &on),
+            Some(Reroute::Via(_))
+        ));
+    }
+}
@@ -100,11 +100,86 @@ impl RelativePoint {
         }
     }
 
+    /// Create a new `RelativePoint`, clamping `x` and `y` to [0.0, 1.0].
+    /// NaN is clamped to 0.0
+    #[allow(clippy::missing_panics_doc)] // invariant always satisfied
+    #[must_use]
+    pub fn clamped(x: f64, y: f64) -> Self {
+        let clamp = |value: f64| {
+            let value = if value.is_nan() {
+                0.0
+            } else {
+                value.clamp(0.0, 1.0)
+            };
+            UnitInterval::new(value).expect("clamped to [0.0, 1.0]")
+        };
+        Self {
+            x: clamp(x),
+            y: clamp(y),
+        }
+    }
+
     /// Calculate magnitude of the vector from the origin to the point
     pub fn squared_magnitude(&self) -> f64 {
         self.x.get().powi(2) + self.y.get().powi(2)
     }
 
+    /// Linearly interpolate between `self` and `other`, where `t` is clamped
+    /// to [0.0, 1.0], i.e. the result is always on the line segment between
+    /// the two points
+    #[must_use]
+    pub fn lerp(self, other: Self, t: f64) -> Self {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        Self::clamped(
+            t.mul_add(other.x.get() - self.x.get(), self.x.get()),
+            t.mul_add(other.y.get() - self.y.get(), self.y.get()),
+        )
+    }
+
+    /// Move the point by `(dx, dy)`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the moved point is not in [0.0, 1.0] x [0.0, 1.0]
+    pub fn offset(self, dx: f64, dy: f64) -> Result<Self, unit_interval::UnitIntervalError> {
+        Self::new(self.x.get() + dx, self.y.get() + dy)
+    }
+
+    /// Move the point by `(dx, dy)`, stopping at the boundary
+    #[must_use]
+    pub fn offset_clamped(self, dx: f64, dy: f64) -> Self {
+        Self::clamped(self.x.get() + dx, self.y.get() + dy)
+    }
+
+    /// The world position of the point in a tile of `tile_width` x
+    /// `tile_height`, whose bottom left corner is at `tile_origin`
+    #[must_use]
+    pub fn to_world(self, tile_origin: Point, tile_width: f64, tile_height: f64) -> Point {
+        Point::new(
+            self.x.get().mul_add(tile_width, tile_origin.x),
+            self.y.get().mul_add(tile_height, tile_origin.y),
+        )
+    }
+
+    /// The point relative to a tile of `tile_width` x `tile_height`, whose
+    /// bottom left corner is at `tile_origin`. The inverse of
+    /// [`RelativePoint::to_world`]
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `point` is not within the tile
+    pub fn from_world(
+        point: Point,
+        tile_origin: Point,
+        tile_width: f64,
+        tile_height: f64,
+    ) -> Result<Self, unit_interval::UnitIntervalError> {
+        Self::new(
+            (point.x - tile_origin.x) / tile_width,
+            (point.y - tile_origin.y) / tile_height,
+        )
+    }
+
     // /// Returns the x and y values as a tuple
     // #[inline]
     // pub const fn get(&self) -> (f64, f64) {
@@ -176,3 +251,60 @@ macro_rules! line {
         $crate::config::geometry::Shape::LineSegment(($crate::config::geometry::Point::new($x1, $y1), $crate::config::geometry::Point::new($x2, $y2)))
     };
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn clamped_stays_in_unit_square() {
+        let p = RelativePoint::clamped(-0.5, 1.5);
+        assert!(close(p.x.get(), 0.0));
+        assert!(close(p.y.get(), 1.0));
+        let p = RelativePoint::clamped(f64::NAN, 0.25);
+        assert!(close(p.x.get(), 0.0));
+        assert!(close(p.y.get(), 0.25));
+    }
+
+    #[test]
+    fn lerp_between_points() {
+        let a = RelativePoint::min();
+        let b = RelativePoint::new(1.0, 0.5).unwrap();
+        let mid = a.lerp(b, 0.5);
+        assert!(close(mid.x.get(), 0.5));
+        assert!(close(mid.y.get(), 0.25));
+        // t is clamped to the line segment
+        let end = a.lerp(b, 2.0);
+        assert!(close(end.x.get(), 1.0));
+        assert!(close(end.y.get(), 0.5));
+    }
+
+    #[test]
+    fn offset_fails_outside_and_clamps() {
+        let p = RelativePoint::center();
+        assert!(p.offset(0.25, -0.25).is_ok());
+        assert!(p.offset(0.75, 0.0).is_err());
+        let q = p.offset_clamped(0.75, -0.75);
+        assert!(close(q.x.get(), 1.0));
+        assert!(close(q.y.get(), 0.0));
+    }
+
+    #[test]
+    fn world_round_trip() {
+        let origin = Point::new(20.0, 10.0);
+        let p = RelativePoint::new(0.25, 0.75).unwrap();
+        let world = p.to_world(origin, 8.0, 4.0);
+        assert!(close(world.x, 22.0));
+        assert!(close(world.y, 13.0));
+
+        let back = RelativePoint::from_world(world, origin, 8.0, 4.0).unwrap();
+        assert!(close(back.x.get(), 0.25));
+        assert!(close(back.y.get(), 0.75));
+        assert!(RelativePoint::from_world(Point::new(0.0, 0.0), origin, 8.0, 4.0).is_err());
+    }
+}
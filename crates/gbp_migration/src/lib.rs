@@ -0,0 +1,570 @@
+#![warn(missing_docs)]
+//! Versioning and migration of the files describing a simulation.
+//!
+//! Every file carries a top-level `version` key, with the version of the
+//! schema it was written for. A file without one was written before versioning
+//! was introduced, and is at version 1. A [`Schema`] lists the [`Migration`]s
+//! between consecutive versions, which are applied to the parsed, but not yet
+//! deserialized, [`Document`] to upgrade it to the latest version. Renamed and
+//! removed keys are reported as [`Deprecation`]s, so existing simulation
+//! folders keep loading as the schema evolves, instead of silently ignoring
+//! keys they set.
+
+use std::fmt::Display;
+
+/// The key of the version of a file
+pub const VERSION_KEY: &str = "version";
+
+/// Error type for [`Schema::migrate`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MigrationError {
+    /// The file was written for a newer version of the schema
+    #[error("{schema} file is at version {found}, but only up to version {latest} is supported")]
+    UnsupportedVersion {
+        /// See [`Schema::name`]
+        schema: &'static str,
+        /// The version of the file
+        found:  u64,
+        /// See [`Schema::version`]
+        latest: u32,
+    },
+    /// The version of the file is not a positive integer
+    #[error("the version of the {0} file is not a positive integer")]
+    InvalidVersion(&'static str),
+    /// A key can not be moved, as its new location is already set
+    #[error("{schema} file sets both `{from}` and its new name `{to}`")]
+    Conflict {
+        /// See [`Schema::name`]
+        schema: &'static str,
+        /// The old key
+        from:   &'static str,
+        /// The new key
+        to:     &'static str,
+    },
+}
+
+/// A change to a schema, between two versions. Keys are paths of `.` separated
+/// tables, e.g. `robot.communication.radius`
+///
+/// A `*` stands for every table in a list, e.g. `obstacles.*.translation`. A
+/// moved key stays in the same table of the list, so both keys share the path
+/// up to the `*`. YAML tags, as used for enum variants, are looked through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// The key was renamed, or moved to another table
+    Moved {
+        /// The old key
+        from: &'static str,
+        /// The new key
+        to:   &'static str,
+    },
+    /// The key is no longer used
+    Removed {
+        /// The removed key
+        key:    &'static str,
+        /// Why the key was removed, or what replaces it
+        reason: &'static str,
+    },
+}
+
+/// The changes to a schema, upgrading a file from the previous version to
+/// `version`
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// The version the migration upgrades to
+    pub version: u32,
+    /// The changes, applied in order
+    pub changes: &'static [Change],
+}
+
+/// The versions of a file format, and how to upgrade between them
+#[derive(Debug, Clone, Copy)]
+pub struct Schema {
+    /// Name of the file format, used in errors and warnings
+    pub name:       &'static str,
+    /// The latest version
+    pub version:    u32,
+    /// The migrations to the later versions, in order
+    pub migrations: &'static [Migration],
+}
+
+/// A key of a migrated file, that has been renamed or removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deprecation {
+    /// See [`Schema::name`]
+    pub schema:  &'static str,
+    /// The version the change was made in
+    pub version: u32,
+    /// The change
+    pub change:  Change,
+}
+
+impl Display for Deprecation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.change {
+            Change::Moved { from, to } => write!(
+                f,
+                "{} file: `{from}` is deprecated, it was renamed to `{to}` in version {}",
+                self.schema, self.version
+            ),
+            Change::Removed { key, reason } => write!(
+                f,
+                "{} file: `{key}` is deprecated, it was removed in version {}: {reason}",
+                self.schema, self.version
+            ),
+        }
+    }
+}
+
+impl Schema {
+    /// Upgrade `document` to the latest version of the schema, returning the
+    /// deprecated keys it used
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the version of `document` is invalid or newer than
+    /// the latest version, or a moved key is set both by its old and new name
+    ///
+    /// # Panics
+    ///
+    /// Panics if the keys of a moved key in a list differ before the `*`
+    pub fn migrate<D: Document>(
+        &self,
+        document: &mut D,
+    ) -> Result<Vec<Deprecation>, MigrationError> {
+        let found = match document.version() {
+            None => 1,
+            Some(Some(version)) if version > 0 => version,
+            Some(_) => return Err(MigrationError::InvalidVersion(self.name)),
+        };
+        if found > u64::from(self.version) {
+            return Err(MigrationError::UnsupportedVersion {
+                schema: self.name,
+                found,
+                latest: self.version,
+            });
+        }
+
+        let mut deprecations = vec![];
+        for migration in self
+            .migrations
+            .iter()
+            .filter(|migration| u64::from(migration.version) > found)
+        {
+            for &change in migration.changes {
+                if self.apply(document, change)? {
+                    deprecations.push(Deprecation {
+                        schema: self.name,
+                        version: migration.version,
+                        change,
+                    });
+                }
+            }
+        }
+
+        document.set_version(self.version);
+        Ok(deprecations)
+    }
+
+    /// Apply `change` to `document`, returning whether it set the changed key
+    fn apply<D: Document>(&self, document: &mut D, change: Change) -> Result<bool, MigrationError> {
+        if let Some((list, change)) = change.split_list() {
+            let mut applied = false;
+            for table in document.tables_mut(list) {
+                applied |= self.apply(table, change)?;
+            }
+            return Ok(applied);
+        }
+
+        Ok(match change {
+            Change::Moved { from, to } => match document.remove(from) {
+                Some(_) if document.contains(to) => {
+                    return Err(MigrationError::Conflict {
+                        schema: self.name,
+                        from,
+                        to,
+                    });
+                }
+                Some(value) => {
+                    document.insert(to, value);
+                    true
+                }
+                None => false,
+            },
+            Change::Removed { key, .. } => document.remove(key).is_some(),
+        })
+    }
+}
+
+/// Separator of the key of a list, and the keys in its tables
+const LIST: &str = ".*.";
+
+impl Change {
+    /// Split the change at the first list in its keys, into the key of the
+    /// list and the change to every table in it
+    fn split_list(self) -> Option<(&'static str, Self)> {
+        match self {
+            Self::Moved { from, to } => {
+                let (list, from) = from.split_once(LIST)?;
+                let to = to
+                    .strip_prefix(list)
+                    .and_then(|to| to.strip_prefix(LIST))
+                    .expect("a key moved in a list stays in the same table of the list");
+                Some((list, Self::Moved { from, to }))
+            }
+            Self::Removed { key, reason } => {
+                let (list, key) = key.split_once(LIST)?;
+                Some((list, Self::Removed { key, reason }))
+            }
+        }
+    }
+}
+
+/// A parsed file, before it is deserialized
+pub trait Document {
+    /// The values of the document
+    type Value;
+
+    /// The version of the document. `None` if it has no version, and
+    /// `Some(None)` if the version is not a non-negative integer
+    fn version(&self) -> Option<Option<u64>>;
+
+    /// Set the version of the document
+    fn set_version(&mut self, version: u32);
+
+    /// Whether `key` is set
+    fn contains(&self, key: &str) -> bool;
+
+    /// Remove `key`, returning its value if it was set
+    fn remove(&mut self, key: &str) -> Option<Self::Value>;
+
+    /// Set `key` to `value`, creating the tables of the path of `key` that do
+    /// not exist. Values in the way of the path are replaced by tables
+    fn insert(&mut self, key: &str, value: Self::Value);
+
+    /// The tables in the list at `key`, empty if `key` is not a list
+    fn tables_mut(&mut self, key: &str) -> Vec<&mut Self>;
+}
+
+/// Split `key` into the path of its table, and its name in the table
+fn split(key: &str) -> (Option<&str>, &str) {
+    match key.rsplit_once('.') {
+        Some((path, name)) => (Some(path), name),
+        None => (None, key),
+    }
+}
+
+fn toml_table<'a>(table: &'a toml::Table, path: Option<&str>) -> Option<&'a toml::Table> {
+    path.map_or(Some(table), |path| {
+        path.split('.')
+            .try_fold(table, |table, name| table.get(name)?.as_table())
+    })
+}
+
+fn toml_table_mut<'a>(
+    table: &'a mut toml::Table,
+    path: Option<&str>,
+) -> Option<&'a mut toml::Table> {
+    let Some(path) = path else {
+        return Some(table);
+    };
+    path.split('.')
+        .try_fold(table, |table, name| table.get_mut(name)?.as_table_mut())
+}
+
+impl Document for toml::Table {
+    type Value = toml::Value;
+
+    fn version(&self) -> Option<Option<u64>> {
+        self.get(VERSION_KEY).map(|version| {
+            version
+                .as_integer()
+                .and_then(|version| u64::try_from(version).ok())
+        })
+    }
+
+    fn set_version(&mut self, version: u32) {
+        self.insert(VERSION_KEY.to_string(), i64::from(version).into());
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        let (path, name) = split(key);
+        toml_table(self, path).is_some_and(|table| table.contains_key(name))
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Self::Value> {
+        let (path, name) = split(key);
+        toml_table_mut(self, path)?.remove(name)
+    }
+
+    fn insert(&mut self, key: &str, value: Self::Value) {
+        let (path, name) = split(key);
+        let mut table = self;
+        for name in path.into_iter().flat_map(|path| path.split('.')) {
+            let entry = table
+                .entry(name)
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            if !entry.is_table() {
+                *entry = toml::Value::Table(toml::Table::new());
+            }
+            table = entry
+                .as_table_mut()
+                .expect("the entry was just made a table");
+        }
+        table.insert(name.to_string(), value);
+    }
+
+    fn tables_mut(&mut self, key: &str) -> Vec<&mut Self> {
+        let (path, name) = split(key);
+        toml_table_mut(self, path)
+            .and_then(|table| table.get_mut(name))
+            .and_then(toml::Value::as_array_mut)
+            .map_or_else(Vec::new, |array| {
+                array
+                    .iter_mut()
+                    .filter_map(toml::Value::as_table_mut)
+                    .collect()
+            })
+    }
+}
+
+/// `value`, without the YAML tags of enum variants
+fn untagged(value: &serde_yaml::Value) -> &serde_yaml::Value {
+    match value {
+        serde_yaml::Value::Tagged(tagged) => untagged(&tagged.value),
+        value => value,
+    }
+}
+
+/// `value`, without the YAML tags of enum variants
+fn untagged_mut(value: &mut serde_yaml::Value) -> &mut serde_yaml::Value {
+    match value {
+        serde_yaml::Value::Tagged(tagged) => untagged_mut(&mut tagged.value),
+        value => value,
+    }
+}
+
+fn yaml_mapping<'a>(
+    mapping: &'a serde_yaml::Mapping,
+    path: Option<&str>,
+) -> Option<&'a serde_yaml::Mapping> {
+    path.map_or(Some(mapping), |path| {
+        path.split('.').try_fold(mapping, |mapping, name| {
+            untagged(mapping.get(name)?).as_mapping()
+        })
+    })
+}
+
+fn yaml_mapping_mut<'a>(
+    mapping: &'a mut serde_yaml::Mapping,
+    path: Option<&str>,
+) -> Option<&'a mut serde_yaml::Mapping> {
+    let Some(path) = path else {
+        return Some(mapping);
+    };
+    path.split('.').try_fold(mapping, |mapping, name| {
+        untagged_mut(mapping.get_mut(name)?).as_mapping_mut()
+    })
+}
+
+impl Document for serde_yaml::Mapping {
+    type Value = serde_yaml::Value;
+
+    fn version(&self) -> Option<Option<u64>> {
+        self.get(VERSION_KEY).map(serde_yaml::Value::as_u64)
+    }
+
+    fn set_version(&mut self, version: u32) {
+        self.insert(VERSION_KEY.into(), version.into());
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        let (path, name) = split(key);
+        yaml_mapping(self, path).is_some_and(|mapping| mapping.contains_key(name))
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Self::Value> {
+        let (path, name) = split(key);
+        yaml_mapping_mut(self, path)?.remove(name)
+    }
+
+    fn insert(&mut self, key: &str, value: Self::Value) {
+        let (path, name) = split(key);
+        let mut mapping = self;
+        for name in path.into_iter().flat_map(|path| path.split('.')) {
+            let entry = untagged_mut(
+                mapping
+                    .entry(name.into())
+                    .or_insert_with(|| serde_yaml::Mapping::new().into()),
+            );
+            if !entry.is_mapping() {
+                *entry = serde_yaml::Mapping::new().into();
+            }
+            mapping = entry
+                .as_mapping_mut()
+                .expect("the entry was just made a mapping");
+        }
+        mapping.insert(name.into(), value);
+    }
+
+    fn tables_mut(&mut self, key: &str) -> Vec<&mut Self> {
+        let (path, name) = split(key);
+        yaml_mapping_mut(self, path)
+            .and_then(|mapping| mapping.get_mut(name))
+            .and_then(|value| untagged_mut(value).as_sequence_mut())
+            .map_or_else(Vec::new, |sequence| {
+                sequence
+                    .iter_mut()
+                    .filter_map(|value| untagged_mut(value).as_mapping_mut())
+                    .collect()
+            })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: Schema = Schema {
+        name:       "test",
+        version:    3,
+        migrations: &[
+            Migration {
+                version: 2,
+                changes: &[Change::Moved {
+                    from: "robot.radius",
+                    to:   "robot.body.radius",
+                }],
+            },
+            Migration {
+                version: 3,
+                changes: &[Change::Removed {
+                    key:    "gbp.legacy",
+                    reason: "no longer used",
+                }],
+            },
+        ],
+    };
+
+    #[test]
+    fn unversioned_files_are_migrated_from_the_first_version() {
+        let mut table: toml::Table = toml::from_str(
+            r#"
+            [robot]
+            radius = 1.0
+            [gbp]
+            legacy = true
+            iterations = 10
+            "#,
+        )
+        .unwrap();
+
+        let deprecations = SCHEMA.migrate(&mut table).unwrap();
+        assert_eq!(deprecations.len(), 2);
+        assert_eq!(table.version(), Some(Some(3)));
+        assert_eq!(table["robot"]["body"]["radius"].as_float(), Some(1.0));
+        assert!(!table.contains("robot.radius"));
+        assert!(!table.contains("gbp.legacy"));
+        assert!(table.contains("gbp.iterations"));
+    }
+
+    #[test]
+    fn migrations_up_to_the_version_of_the_file_are_skipped() {
+        let mut mapping: serde_yaml::Mapping = serde_yaml::from_str(
+            r"
+            version: 2
+            robot:
+              radius: 1.0
+            gbp:
+              legacy: true
+            ",
+        )
+        .unwrap();
+
+        let deprecations = SCHEMA.migrate(&mut mapping).unwrap();
+        assert_eq!(deprecations.len(), 1);
+        assert_eq!(deprecations[0].version, 3);
+        // only moved in version 2, so the key is unknown to the schema
+        assert!(mapping.contains("robot.radius"));
+        assert_eq!(mapping.version(), Some(Some(3)));
+    }
+
+    #[test]
+    fn newer_and_invalid_versions_are_rejected() {
+        let mut table: toml::Table = toml::from_str("version = 4").unwrap();
+        assert_eq!(
+            SCHEMA.migrate(&mut table),
+            Err(MigrationError::UnsupportedVersion {
+                schema: "test",
+                found:  4,
+                latest: 3,
+            })
+        );
+
+        let mut table: toml::Table = toml::from_str(r#"version = "2""#).unwrap();
+        assert_eq!(
+            SCHEMA.migrate(&mut table),
+            Err(MigrationError::InvalidVersion("test"))
+        );
+    }
+
+    #[test]
+    fn moving_onto_a_set_key_is_a_conflict() {
+        let mut table: toml::Table = toml::from_str(
+            r"
+            [robot]
+            radius = 1.0
+            body = { radius = 2.0 }
+            ",
+        )
+        .unwrap();
+        assert!(matches!(
+            SCHEMA.migrate(&mut table),
+            Err(MigrationError::Conflict { .. })
+        ));
+    }
+
+    #[test]
+    fn keys_of_tables_in_lists_are_migrated() {
+        const SCHEMA: Schema = Schema {
+            name:       "test",
+            version:    2,
+            migrations: &[Migration {
+                version: 2,
+                changes: &[
+                    Change::Moved {
+                        from: "obstacles.*.shape.center",
+                        to:   "obstacles.*.translation",
+                    },
+                    Change::Removed {
+                        key:    "obstacles.*.shape.side-length",
+                        reason: "no longer used",
+                    },
+                ],
+            }],
+        };
+
+        let mut mapping: serde_yaml::Mapping = serde_yaml::from_str(
+            r"
+            obstacles:
+            - shape: !circle
+                radius: 0.1
+                center: { x: 0.2, y: 0.3 }
+            - shape: !circle
+                radius: 0.1
+            ",
+        )
+        .unwrap();
+
+        let deprecations = SCHEMA.migrate(&mut mapping).unwrap();
+        assert_eq!(deprecations.len(), 1);
+        let obstacles = mapping["obstacles"].as_sequence().unwrap();
+        assert_eq!(obstacles[0]["translation"]["x"].as_f64(), Some(0.2));
+        assert!(!obstacles[1].as_mapping().unwrap().contains("translation"));
+        let serde_yaml::Value::Tagged(shape) = &obstacles[0]["shape"] else {
+            panic!("the tag of the shape is kept");
+        };
+        assert!(!shape.value.as_mapping().unwrap().contains("center"));
+    }
+}
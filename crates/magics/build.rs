@@ -6,4 +6,38 @@ fn main() {
         // on windows we will set our game icon as icon for the executable
         embed_resource::compile("build/windows/icon.rc", embed_resource::NONE);
     }
+
+    git_commit();
+}
+
+/// Expose the git commit the binary is built from as `GIT_COMMIT`, with a
+/// `-dirty` suffix if there are uncommitted changes. Used in the manifest
+/// written for every run
+fn git_commit() {
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+
+    let commit = git(&["rev-parse", "HEAD"]).map_or_else(
+        || "unknown".to_string(),
+        |commit| {
+            let dirty = git(&["status", "--porcelain"]).is_some_and(|status| !status.is_empty());
+            if dirty {
+                format!("{commit}-dirty")
+            } else {
+                commit
+            }
+        },
+    );
+    println!("cargo:rustc-env=GIT_COMMIT={commit}");
+
+    if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+        println!("cargo:rerun-if-changed={git_dir}/HEAD");
+        println!("cargo:rerun-if-changed={git_dir}/index");
+    }
 }
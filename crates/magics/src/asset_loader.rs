@@ -11,6 +11,9 @@ pub struct Meshes {
     pub variable: Handle<Mesh>,
     pub waypoint: Handle<Mesh>,
     pub plane:    Handle<Mesh>,
+    /// Unit cube, scaled by the transform of each entity using it. Entities
+    /// sharing a mesh and material are drawn with a single instanced draw call
+    pub cuboid:   Handle<Mesh>,
 }
 
 impl FromWorld for Meshes {
@@ -38,6 +41,7 @@ impl FromWorld for Meshes {
                     .expect("4 subdivisions is less than the maximum allowed of 80"),
             ),
             plane:    meshes.add(Mesh::from(Rectangle::new(100.0f32, 100.0f32))),
+            cuboid:   meshes.add(Cuboid::new(1.0, 1.0, 1.0)),
         }
     }
 }
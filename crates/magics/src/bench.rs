@@ -0,0 +1,291 @@
+//! Module for benchmarking the speed of the simulation, with `--bench-sim`.
+//!
+//! The active simulation is run for a given amount of simulated time as fast
+//! as possible, and a report of the wall-clock time, the timesteps per second
+//! and the state of the solvers is printed when it has finished. The planner
+//! still runs in the fixed schedules at `config.simulation.hz`, but instead of
+//! following the wall clock, the virtual clock is advanced by as many
+//! timesteps every frame as fit in [`FRAME_BUDGET`], so the cost of rendering
+//! is spread out over many timesteps.
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    app::AppExit,
+    prelude::*,
+    time::TimeSystem,
+    winit::{UpdateMode, WinitSettings},
+};
+use serde::Serialize;
+
+use crate::{
+    factorgraph::{factorgraph::SolveReport, prelude::FactorGraph},
+    simulation_loader::SimulationManager,
+};
+
+/// Wall-clock time to spend on fixed timesteps every frame
+pub const FRAME_BUDGET: Duration = Duration::from_millis(100);
+
+/// Plugin that runs the active simulation for `duration` of simulated time as
+/// fast as possible, reports how long it took and exits
+#[derive(Debug)]
+pub struct BenchPlugin {
+    duration: Duration,
+    report:   Option<PathBuf>,
+}
+
+impl BenchPlugin {
+    /// Run the simulation for `duration` of simulated time
+    #[must_use]
+    pub const fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            report: None,
+        }
+    }
+
+    /// Also write the report as JSON to `path`
+    #[must_use]
+    pub fn report_to(mut self, path: PathBuf) -> Self {
+        self.report = Some(path);
+        self
+    }
+}
+
+impl Plugin for BenchPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WinitSettings {
+            focused_mode:   UpdateMode::Continuous,
+            unfocused_mode: UpdateMode::Continuous,
+        })
+        .insert_resource(Bench {
+            duration:    self.duration,
+            report:      self.report.clone(),
+            simulated:   Duration::ZERO,
+            steps:       0,
+            last_steps:  0,
+            started:     None,
+            peak_robots: 0,
+        })
+        .add_systems(
+            First,
+            advance_virtual_time
+                .after(TimeSystem)
+                .run_if(simulation_active),
+        )
+        .add_systems(Last, finish_bench.run_if(simulation_active));
+    }
+}
+
+/// **Bevy** [`Resource`]
+/// Progress of the benchmark
+#[derive(Debug, Resource)]
+struct Bench {
+    duration:    Duration,
+    report:      Option<PathBuf>,
+    /// Simulated time so far
+    simulated:   Duration,
+    /// Fixed timesteps run so far
+    steps:       u64,
+    /// Fixed timesteps run in the latest frame
+    last_steps:  u32,
+    /// When the first timestep was run
+    started:     Option<Instant>,
+    /// Largest number of robots alive at the same time
+    peak_robots: usize,
+}
+
+/// Report of a finished benchmark
+#[derive(Debug, Serialize)]
+pub struct BenchReport {
+    /// Name of the benchmarked simulation
+    pub simulation: String,
+    /// Simulated time. SI unit: s
+    pub simulated_seconds: f64,
+    /// Wall-clock time it took to simulate. SI unit: s
+    pub wall_clock_seconds: f64,
+    /// Fixed timesteps run
+    pub timesteps: u64,
+    pub timesteps_per_second: f64,
+    /// Simulated seconds per wall-clock second
+    pub real_time_factor: f64,
+    /// Largest number of robots alive at the same time
+    pub peak_robots: usize,
+    /// Robots alive when the benchmark finished
+    pub robots: usize,
+    /// Internal GBP iterations of the robots alive when the benchmark finished
+    pub gbp_iterations: usize,
+    /// Messages sent by the robots alive when the benchmark finished
+    pub messages_sent: usize,
+    /// Mean energy of the factorgraphs of the robots alive when the benchmark
+    /// finished
+    pub mean_energy: f64,
+    /// Mean residual of the factorgraphs of the robots alive when the
+    /// benchmark finished
+    pub mean_residual: f64,
+}
+
+impl std::fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "simulation:         {}", self.simulation)?;
+        writeln!(f, "simulated time:     {:.2} s", self.simulated_seconds)?;
+        writeln!(f, "wall-clock time:    {:.2} s", self.wall_clock_seconds)?;
+        writeln!(f, "timesteps:          {}", self.timesteps)?;
+        writeln!(f, "timesteps/s:        {:.1}", self.timesteps_per_second)?;
+        writeln!(f, "real-time factor:   {:.2}x", self.real_time_factor)?;
+        writeln!(
+            f,
+            "robots:             {} (peak {})",
+            self.robots, self.peak_robots
+        )?;
+        writeln!(f, "gbp iterations:     {}", self.gbp_iterations)?;
+        writeln!(f, "messages sent:      {}", self.messages_sent)?;
+        writeln!(f, "mean energy:        {:.4}", self.mean_energy)?;
+        write!(f, "mean residual:      {:.4}", self.mean_residual)
+    }
+}
+
+#[inline]
+fn simulation_active(simulation_manager: Res<SimulationManager>) -> bool {
+    simulation_manager.active_id().is_some()
+}
+
+/// Number of timesteps to run in the next frame, given that `last_steps`
+/// timesteps took `frame_time`, such that the next frame takes about
+/// [`FRAME_BUDGET`]
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn steps_per_frame(last_steps: u32, frame_time: Duration) -> u32 {
+    if last_steps == 0 || frame_time.is_zero() {
+        return 1;
+    }
+    let seconds_per_step = frame_time.as_secs_f64() / last_steps as f64;
+    // grow at most by a factor of two per frame, to not overshoot after a cheap
+    // frame
+    let steps = (FRAME_BUDGET.as_secs_f64() / seconds_per_step) as u32;
+    steps.clamp(1, last_steps.saturating_mul(2))
+}
+
+/// Advance the virtual clock by the number of timesteps to run this frame. The
+/// clock does not move by itself during the benchmark, so the fixed schedules
+/// run exactly these timesteps, and systems in `Update` see the same amount of
+/// time pass
+fn advance_virtual_time(
+    mut bench: ResMut<Bench>,
+    mut time_virtual: ResMut<Time<Virtual>>,
+    mut time: ResMut<Time>,
+    time_fixed: Res<Time<Fixed>>,
+    time_real: Res<Time<Real>>,
+) {
+    bench.started.get_or_insert_with(Instant::now);
+
+    let timestep = time_fixed.timestep();
+    let remaining = bench.duration.saturating_sub(bench.simulated);
+    let remaining_steps = remaining.as_nanos().div_ceil(timestep.as_nanos().max(1));
+    let steps = steps_per_frame(bench.last_steps, time_real.delta())
+        .min(u32::try_from(remaining_steps).unwrap_or(u32::MAX));
+
+    time_virtual.unpause();
+    time_virtual.set_relative_speed(0.0);
+    time_virtual.advance_by(timestep * steps);
+    *time = time_virtual.as_generic();
+
+    bench.simulated += timestep * steps;
+    bench.steps += u64::from(steps);
+    bench.last_steps = steps;
+}
+
+/// Print the report and exit once the benchmark has run for its duration
+#[allow(clippy::cast_precision_loss)]
+fn finish_bench(
+    mut bench: ResMut<Bench>,
+    factorgraphs: Query<&FactorGraph>,
+    simulation_manager: Res<SimulationManager>,
+    mut evw_app_exit: EventWriter<AppExit>,
+) {
+    let robots = factorgraphs.iter().count();
+    bench.peak_robots = bench.peak_robots.max(robots);
+
+    if bench.simulated < bench.duration {
+        return;
+    }
+    let Some(started) = bench.started else {
+        return;
+    };
+
+    let simulated_seconds = bench.simulated.as_secs_f64();
+    let wall_clock_seconds = started.elapsed().as_secs_f64();
+    let reports = factorgraphs
+        .iter()
+        .map(FactorGraph::solve_report)
+        .collect::<Vec<_>>();
+    let mean = |value: fn(&SolveReport) -> f64| {
+        if reports.is_empty() {
+            0.0
+        } else {
+            reports.iter().map(value).sum::<f64>() / reports.len() as f64
+        }
+    };
+
+    let report = BenchReport {
+        simulation: simulation_manager
+            .active_name()
+            .unwrap_or_default()
+            .to_string(),
+        simulated_seconds,
+        wall_clock_seconds,
+        timesteps: bench.steps,
+        timesteps_per_second: bench.steps as f64 / wall_clock_seconds,
+        real_time_factor: simulated_seconds / wall_clock_seconds,
+        peak_robots: bench.peak_robots,
+        robots,
+        gbp_iterations: reports.iter().map(|report| report.iteration).sum(),
+        messages_sent: factorgraphs
+            .iter()
+            .map(|factorgraph| {
+                let sent = factorgraph.messages_sent();
+                sent.internal + sent.external
+            })
+            .sum(),
+        mean_energy: mean(|report| report.energy),
+        mean_residual: mean(|report| report.residual),
+    };
+
+    println!("{report}");
+    if let Some(ref path) = bench.report {
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => {
+                if let Err(err) = std::fs::write(path, json) {
+                    error!(
+                        "failed to write bench report to '{}': {}",
+                        path.display(),
+                        err
+                    );
+                }
+            }
+            Err(err) => error!("failed to serialize bench report: {}", err),
+        }
+    }
+
+    evw_app_exit.send(AppExit);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_the_frame_budget() {
+        assert_eq!(steps_per_frame(0, Duration::ZERO), 1);
+        assert_eq!(steps_per_frame(10, FRAME_BUDGET), 10);
+        assert_eq!(steps_per_frame(10, FRAME_BUDGET * 2), 5);
+        // grows at most by a factor of two
+        assert_eq!(steps_per_frame(10, FRAME_BUDGET / 10), 20);
+    }
+}
@@ -0,0 +1,146 @@
+//! Module for periodically saving checkpoints of a running simulation, so long
+//! batch runs interrupted part way, e.g. by a cluster scheduler, can be
+//! resumed with `--resume` instead of starting over.
+
+use std::{path::PathBuf, time::Duration};
+
+use bevy::prelude::*;
+
+use crate::{
+    playlist::Playlist,
+    simulation_loader::{LoadSimulation, SimulationManager},
+    snapshot::{
+        events::{RestoreSnapshot, SaveSnapshot},
+        SimulationSnapshot,
+    },
+};
+
+/// Default file checkpoints are written to
+pub const DEFAULT_CHECKPOINT_PATH: &str = "checkpoint.json";
+
+/// Plugin that saves a checkpoint every `interval` of virtual time, and
+/// restores a checkpoint when the simulation is first loaded
+#[derive(Debug, Default)]
+pub struct CheckpointPlugin {
+    interval: Option<Duration>,
+    path:     Option<PathBuf>,
+    resume:   Option<(PathBuf, SimulationSnapshot)>,
+}
+
+impl CheckpointPlugin {
+    /// Save a checkpoint every `interval` of virtual time
+    #[must_use]
+    pub const fn every(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Save checkpoints to `path` instead of [`DEFAULT_CHECKPOINT_PATH`]
+    #[must_use]
+    pub fn path(mut self, path: PathBuf) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// Restore `checkpoint`, read from `path`, once its simulation has loaded
+    #[must_use]
+    pub fn resume_from(mut self, path: PathBuf, checkpoint: SimulationSnapshot) -> Self {
+        self.resume = Some((path, checkpoint));
+        self
+    }
+}
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(interval) = self.interval {
+            let path = self
+                .path
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_CHECKPOINT_PATH));
+            app.insert_resource(CheckpointTimer {
+                timer: Timer::new(interval, TimerMode::Repeating),
+                path,
+            })
+            .add_systems(Update, save_checkpoint);
+        }
+
+        if let Some((ref path, ref checkpoint)) = self.resume {
+            app.insert_resource(Resume {
+                path:      path.clone(),
+                prng_seed: checkpoint.prng_seed,
+            })
+            .add_systems(PostStartup, override_prng_seed)
+            .add_systems(
+                Update,
+                resume_from_checkpoint
+                    .run_if(resource_exists::<Resume>.and_then(on_event::<LoadSimulation>())),
+            );
+        }
+    }
+}
+
+/// **Bevy** [`Resource`]
+/// Timer until the next checkpoint is saved
+#[derive(Debug, Resource)]
+struct CheckpointTimer {
+    timer: Timer,
+    path:  PathBuf,
+}
+
+/// **Bevy** [`Resource`]
+/// Checkpoint to restore once its simulation has loaded
+#[derive(Debug, Resource)]
+struct Resume {
+    path:      PathBuf,
+    prng_seed: u64,
+}
+
+/// Save a checkpoint whenever the timer finishes. Virtual time is used, so no
+/// checkpoints are saved while the simulation is paused
+fn save_checkpoint(
+    mut checkpoint_timer: ResMut<CheckpointTimer>,
+    mut evw_save_snapshot: EventWriter<SaveSnapshot>,
+    time_virtual: Res<Time<Virtual>>,
+) {
+    if checkpoint_timer
+        .timer
+        .tick(time_virtual.delta())
+        .just_finished()
+    {
+        info!("saving checkpoint to '{}'", checkpoint_timer.path.display());
+        evw_save_snapshot.send(SaveSnapshot {
+            path: Some(checkpoint_timer.path.clone()),
+        });
+    }
+}
+
+/// Load the simulation with the seed it had when the checkpoint was saved. A
+/// playlist restored from the checkpoint sets the seed of its entries itself
+fn override_prng_seed(
+    resume: Res<Resume>,
+    playlist: Option<Res<Playlist>>,
+    mut simulation_manager: ResMut<SimulationManager>,
+) {
+    if playlist.is_none() {
+        simulation_manager.override_prng_seed(Some(resume.prng_seed));
+    }
+}
+
+fn resume_from_checkpoint(
+    mut commands: Commands,
+    mut evw_restore_snapshot: EventWriter<RestoreSnapshot>,
+    mut simulation_manager: ResMut<SimulationManager>,
+    resume: Res<Resume>,
+    playlist: Option<Res<Playlist>>,
+) {
+    info!("resuming from checkpoint '{}'", resume.path.display());
+    evw_restore_snapshot.send(RestoreSnapshot {
+        path: Some(resume.path.clone()),
+    });
+
+    // later loads and reloads should use the seed of their config again
+    if playlist.is_none() {
+        simulation_manager.override_prng_seed(None);
+    }
+    commands.remove_resource::<Resume>();
+}
@@ -60,7 +60,98 @@ pub struct Cli {
     #[arg(short, long)]
     pub initial_scenario: Option<String>,
 
-    /// Run the app without a window for rendering the environment
+    /// Run the given simulations one after another, exporting a report after
+    /// each, and exit when all have finished
+    #[arg(
+        long,
+        value_name = "SIMULATIONS",
+        value_delimiter = ',',
+        conflicts_with_all = ["initial_scenario", "playlist_file"]
+    )]
+    pub playlist: Vec<String>,
+
+    /// Read the playlist from a file, with one simulation per line optionally
+    /// followed by a seed
+    #[arg(
+        long,
+        value_name = "PLAYLIST_FILE",
+        conflicts_with = "initial_scenario"
+    )]
+    pub playlist_file: Option<std::path::PathBuf>,
+
+    /// Run a parameter sweep, i.e. every combination of the config values
+    /// listed in the given sweep file, see `magics::sweep`
+    #[arg(
+        long,
+        value_name = "SWEEP_FILE",
+        conflicts_with_all = ["initial_scenario", "playlist", "playlist_file"]
+    )]
+    pub sweep: Option<std::path::PathBuf>,
+
+    /// Run every simulation in the playlist once for each of the given seeds
+    #[arg(long, value_name = "SEEDS", value_delimiter = ',')]
+    pub playlist_seeds: Vec<u64>,
+
+    /// Run the initial scenario, or every simulation in the playlist, the given
+    /// number of times with the seeds 0, 1, 2, ... Set
+    /// `simulation.perturbation` in the config to vary the initial conditions
+    /// between the runs. The success rate of each simulation is written to
+    /// the summary file
+    #[arg(
+        long,
+        value_name = "REPETITIONS",
+        conflicts_with_all = ["playlist_seeds", "resume"]
+    )]
+    pub monte_carlo: Option<std::num::NonZeroU64>,
+
+    /// File to write the success rates of a Monte Carlo run to [default:
+    /// monte-carlo.json]
+    #[arg(long, value_name = "SUMMARY_FILE", requires = "monte_carlo")]
+    pub monte_carlo_summary: Option<std::path::PathBuf>,
+
+    /// Save a checkpoint of the running simulation every given number of
+    /// seconds of simulated time
+    #[arg(long, value_name = "SECONDS")]
+    pub checkpoint_every: Option<f64>,
+
+    /// File to save checkpoints to [default: checkpoint.json]
+    #[arg(long, value_name = "CHECKPOINT_FILE")]
+    pub checkpoint_file: Option<std::path::PathBuf>,
+
+    /// Resume from a checkpoint saved with `--checkpoint-every`, including
+    /// the remaining entries of the playlist it was saved during
+    #[arg(
+        long,
+        value_name = "CHECKPOINT_FILE",
+        conflicts_with_all = ["initial_scenario", "playlist", "playlist_file", "sweep"]
+    )]
+    pub resume: Option<std::path::PathBuf>,
+
+    /// Record the planned horizon of every robot each timestep to the given
+    /// binary log, see `magics::horizon_log`
+    #[arg(long, value_name = "HORIZON_LOG")]
+    pub record_horizons: Option<std::path::PathBuf>,
+
+    /// Replay the horizons recorded in the given log on top of the simulation,
+    /// with a window to scrub through them
+    #[arg(long, value_name = "HORIZON_LOG")]
+    pub replay_horizons: Option<std::path::PathBuf>,
+
+    /// Run the initial scenario for the given number of simulated seconds as
+    /// fast as possible, print how long it took and exit, see `magics::bench`
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        conflicts_with_all = ["playlist", "playlist_file", "sweep", "monte_carlo", "resume"]
+    )]
+    pub bench_sim: Option<f64>,
+
+    /// Also write the report of `--bench-sim` as JSON to the given file
+    #[arg(long, value_name = "REPORT_FILE", requires = "bench_sim")]
+    pub bench_sim_report: Option<std::path::PathBuf>,
+
+    /// Run the app without showing its window, e.g. for a `--sweep` or a
+    /// playlist
     #[arg(long, group = "display")]
     pub headless:   bool,
     /// Start the app in fullscreen mode
@@ -1,12 +1,16 @@
 use bevy::{
-    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    diagnostic::{
+        Diagnostic, DiagnosticMeasurement, DiagnosticPath, Diagnostics, DiagnosticsStore,
+        RegisterDiagnostic,
+    },
     prelude::*,
     time::common_conditions::on_timer,
+    utils::{HashMap, Instant},
 };
 use units::sample_rate::SampleRate;
 
 use crate::{
-    factorgraph::prelude::FactorGraph,
+    factorgraph::{prelude::FactorGraph, MessagesReceived, MessagesSent},
     planner::{collisions::resources::RobotRobotCollisions, RobotConnections},
     simulation_loader::{LoadSimulation, ReloadSimulation},
 };
@@ -20,7 +24,7 @@ pub struct SampleRates {
     pub robots: Option<SampleRate>,
     pub robot_collisions: Option<SampleRate>,
     pub variables_and_factors: Option<SampleRate>,
-    // pub messages_sent: Option<SampleRate>,
+    pub messages: Option<SampleRate>,
 }
 
 impl Default for SampleRates {
@@ -29,7 +33,7 @@ impl Default for SampleRates {
             robots: None,
             robot_collisions: Some(SampleRate::from_hz(5.try_into().expect("1 > 0"))),
             variables_and_factors: Some(SampleRate::from_hz(2.try_into().expect("2 > 0"))),
-            // messages_sent: Some(SampleRate::from_hz(2.try_into().expect("2 > 0"))),
+            messages: Some(SampleRate::from_hz(2.try_into().expect("2 > 0"))),
         }
     }
 }
@@ -50,11 +54,24 @@ macro_rules! add_diagnostic_system {
     };
 }
 
+/// Diagnostic paths of the per-robot message counts, assigned to the robots
+/// that are currently alive. Bounded by the number of robots alive at once.
+#[derive(Default)]
+struct RobotDiagnosticSlots {
+    /// Paths of the message diagnostics of each slot
+    paths:    Vec<[DiagnosticPath; 2]>,
+    /// Slot assigned to each robot
+    assigned: HashMap<Entity, usize>,
+    /// Slots of despawned robots, ready to be reused
+    free:     Vec<usize>,
+}
+
 impl Plugin for RobotDiagnosticsPlugin {
     fn build(&self, app: &mut App) {
         app.register_diagnostic(Diagnostic::new(Self::ROBOT_COUNT))
             .register_diagnostic(Diagnostic::new(Self::VARIABLE_COUNT))
             .register_diagnostic(Diagnostic::new(Self::FACTOR_COUNT))
+            .register_diagnostic(Diagnostic::new(Self::NON_FINITE_NODE_COUNT))
             // .register_diagnostic(Diagnostic::new(Self::EXTERNAL_MESSAGES_SENT_COUNT))
             .register_diagnostic(Diagnostic::new(Self::MESSAGES_RECEIVED_INTERNAL_COUNT))
             .register_diagnostic(Diagnostic::new(Self::MESSAGES_RECEIVED_EXTERNAL_COUNT))
@@ -68,8 +85,8 @@ impl Plugin for RobotDiagnosticsPlugin {
             self.sample_rates.variables_and_factors,
             Self::variables_and_factors
         );
-        // add_diagnostic_system!(app, self.sample_rates.messages_sent,
-        // Self::messages_sent);
+        add_diagnostic_system!(app, self.sample_rates.messages, Self::messages);
+        add_diagnostic_system!(app, self.sample_rates.messages, Self::messages_per_robot);
 
         add_diagnostic_system!(
             app,
@@ -92,15 +109,17 @@ impl RobotDiagnosticsPlugin {
         DiagnosticPath::const_new("external_messages_sent_count");
     pub const FACTOR_COUNT: DiagnosticPath = DiagnosticPath::const_new("factor_count");
     pub const MESSAGES_RECEIVED_EXTERNAL_COUNT: DiagnosticPath =
-        DiagnosticPath::const_new("messages_received_internal_count");
-    pub const MESSAGES_RECEIVED_INTERNAL_COUNT: DiagnosticPath =
         DiagnosticPath::const_new("messages_received_external_count");
+    pub const MESSAGES_RECEIVED_INTERNAL_COUNT: DiagnosticPath =
+        DiagnosticPath::const_new("messages_received_internal_count");
     // pub const MESSAGES_SENT_COUNT: DiagnosticPath =
     // DiagnosticPath::const_new("messages_sent_count");
     pub const MESSAGES_SENT_EXTERNAL_COUNT: DiagnosticPath =
         DiagnosticPath::const_new("messages_sent_external_count");
     pub const MESSAGES_SENT_INTERNAL_COUNT: DiagnosticPath =
         DiagnosticPath::const_new("messages_sent_internal_count");
+    pub const NON_FINITE_NODE_COUNT: DiagnosticPath =
+        DiagnosticPath::const_new("non_finite_node_count");
     pub const ROBOT_COLLISION_COUNT: DiagnosticPath =
         DiagnosticPath::const_new("robot_collision_count");
     pub const ROBOT_COUNT: DiagnosticPath = DiagnosticPath::const_new("robot_count");
@@ -129,6 +148,107 @@ impl RobotDiagnosticsPlugin {
                 .map(|factorgraph| factorgraph.node_count().factors)
                 .sum::<usize>() as f64
         });
+        diagnostics.add_measurement(&Self::NON_FINITE_NODE_COUNT, || {
+            factorgraphs
+                .iter()
+                .map(FactorGraph::non_finite_node_count)
+                .sum::<usize>() as f64
+        });
+    }
+
+    /// Messages sent and received by all robots, since they were spawned
+    #[allow(clippy::cast_precision_loss)]
+    fn messages(
+        mut diagnostics: Diagnostics,
+        factorgraphs: Query<&FactorGraph, With<RobotConnections>>,
+    ) {
+        let sent = factorgraphs
+            .iter()
+            .map(FactorGraph::messages_sent)
+            .sum::<MessagesSent>();
+        let received = factorgraphs
+            .iter()
+            .map(FactorGraph::messages_received)
+            .sum::<MessagesReceived>();
+
+        diagnostics.add_measurement(&Self::MESSAGES_SENT_INTERNAL_COUNT, || sent.internal as f64);
+        diagnostics.add_measurement(&Self::MESSAGES_SENT_EXTERNAL_COUNT, || sent.external as f64);
+        diagnostics.add_measurement(&Self::MESSAGES_RECEIVED_INTERNAL_COUNT, || {
+            received.internal as f64
+        });
+        diagnostics.add_measurement(&Self::MESSAGES_RECEIVED_EXTERNAL_COUNT, || {
+            received.external as f64
+        });
+    }
+
+    /// Messages sent and received by each robot, under
+    /// `robot/<slot>/messages_sent` and `robot/<slot>/messages_received`.
+    /// Diagnostics can not be removed from the store, so the slot of a
+    /// despawned robot is disabled and handed to the next robot spawned,
+    /// instead of registering new diagnostics for every robot ever spawned.
+    #[allow(clippy::cast_precision_loss)]
+    fn messages_per_robot(
+        mut store: ResMut<DiagnosticsStore>,
+        factorgraphs: Query<(Entity, &FactorGraph), With<RobotConnections>>,
+        mut slots: Local<RobotDiagnosticSlots>,
+    ) {
+        let now = Instant::now();
+        let RobotDiagnosticSlots {
+            paths,
+            assigned,
+            free,
+        } = &mut *slots;
+
+        assigned.retain(|robot, slot| {
+            let alive = factorgraphs.contains(*robot);
+            if !alive {
+                for path in &paths[*slot] {
+                    if let Some(diagnostic) = store.get_mut(path) {
+                        diagnostic.is_enabled = false;
+                        diagnostic.clear_history();
+                    }
+                }
+                free.push(*slot);
+            }
+            alive
+        });
+
+        for (robot, factorgraph) in &factorgraphs {
+            let slot = *assigned.entry(robot).or_insert_with(|| {
+                if let Some(slot) = free.pop() {
+                    for path in &paths[slot] {
+                        if let Some(diagnostic) = store.get_mut(path) {
+                            diagnostic.is_enabled = true;
+                        }
+                    }
+                    return slot;
+                }
+                let slot = paths.len();
+                let slot_paths = [
+                    DiagnosticPath::new(format!("robot/{slot}/messages_sent")),
+                    DiagnosticPath::new(format!("robot/{slot}/messages_received")),
+                ];
+                for path in &slot_paths {
+                    store.add(Diagnostic::new(path.clone()));
+                }
+                paths.push(slot_paths);
+                slot
+            });
+
+            let sent = factorgraph.messages_sent();
+            let received = factorgraph.messages_received();
+            for (path, value) in paths[slot].iter().zip([
+                sent.internal + sent.external,
+                received.internal + received.external,
+            ]) {
+                if let Some(diagnostic) = store.get_mut(path) {
+                    diagnostic.add_measurement(DiagnosticMeasurement {
+                        time:  now,
+                        value: value as f64,
+                    });
+                }
+            }
+        }
     }
 
     // #[allow(clippy::cast_precision_loss)]
@@ -224,6 +344,7 @@ impl RobotDiagnosticsPlugin {
             Self::FACTOR_COUNT,
             Self::ROBOT_COUNT,
             Self::VARIABLE_COUNT,
+            Self::NON_FINITE_NODE_COUNT,
             Self::MESSAGES_SENT_EXTERNAL_COUNT,
             Self::MESSAGES_SENT_INTERNAL_COUNT,
             Self::MESSAGES_RECEIVED_EXTERNAL_COUNT,
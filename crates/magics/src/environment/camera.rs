@@ -1,10 +1,10 @@
 // https://github.com/marcelchampagne/bevy-basics/blob/main/episode-3/src/camera.rs
 use bevy::prelude::*;
-use gbp_config::Config;
+use gbp_config::{CameraMode, Config};
 
 use crate::{
     movement::{LinearMovementBundle, Local, Orbit, OrbitMovementBundle},
-    simulation_loader::{LoadSimulation, ReloadSimulation},
+    simulation_loader::LoadSimulation,
 };
 
 // const CAMERA_UP: Vec3 = Vec3::NEG_Y;
@@ -24,14 +24,9 @@ impl Plugin for CameraPlugin {
                 (
                     // update_main_camera.run_if(resource_exists_and_changed::<Config>),
                     reset_main_camera.run_if(on_event::<events::ResetCamera>()),
-                    (
-                        // reset_main_camera,
-                        activate_main_camera,
-                    )
-                        .chain()
-                        .run_if(
-                            on_event::<ReloadSimulation>().or_else(on_event::<LoadSimulation>()),
-                        ),
+                    // the camera entity is not reloadable, so on reload it is
+                    // left where the user put it
+                    activate_main_camera.run_if(on_event::<LoadSimulation>()),
                 ),
             );
     }
@@ -46,6 +41,11 @@ pub struct CameraSettings {
     pub speed: f32,
     /// The speed at which the camera rotates in [`CameraMovementMode::Orbit`]
     pub angular_speed: f32,
+    /// The speed at which the camera moves in [`CameraMovement::Fly`]
+    pub fly_speed: f32,
+    /// Radians the camera turns per pixel of mouse motion in
+    /// [`CameraMovement::Fly`]
+    pub look_sensitivity: f32,
     /// The initial position of the camera in 3D space
     pub start_pos: Vec3,
 }
@@ -68,6 +68,8 @@ impl Default for CameraSettings {
         Self {
             speed: DEFAULT_CAMERA_DISTANCE / 10.0,
             angular_speed: 2.0,
+            fly_speed: 20.0,
+            look_sensitivity: 0.003,
             start_pos: Vec3::new(0.0, DEFAULT_CAMERA_DISTANCE, 0.0),
         }
     }
@@ -99,26 +101,48 @@ impl MainCamera {
 }
 
 /// **Bevy** [`State`] representing the main camera's movement mode
-/// Enables the camera to `Pan` and `Orbit`
+/// Enables the camera to `Pan`, `Orbit` and `Fly`
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
 pub enum CameraMovement {
     #[default]
     Pan,
     Orbit,
+    /// Free flight, moving along the view direction with WASD and looking
+    /// around with the mouse
+    Fly,
+}
+
+impl From<CameraMode> for CameraMovement {
+    fn from(mode: CameraMode) -> Self {
+        match mode {
+            CameraMode::Pan => Self::Pan,
+            CameraMode::Orbit => Self::Orbit,
+            CameraMode::Fly => Self::Fly,
+        }
+    }
+}
+
+impl From<CameraMovement> for CameraMode {
+    fn from(movement: CameraMovement) -> Self {
+        match movement {
+            CameraMovement::Pan => Self::Pan,
+            CameraMovement::Orbit => Self::Orbit,
+            CameraMovement::Fly => Self::Fly,
+        }
+    }
 }
 
 impl CameraMovement {
     pub fn cycle(&mut self) {
-        *self = match self {
-            CameraMovement::Pan => CameraMovement::Orbit,
-            CameraMovement::Orbit => CameraMovement::Pan,
-        }
+        *self = self.next();
     }
 
+    /// The next mode when toggling between `Pan` and `Orbit`.
+    /// Leaving `Fly` returns to `Pan`
     pub fn next(&self) -> Self {
         match self {
             CameraMovement::Pan => CameraMovement::Orbit,
-            CameraMovement::Orbit => CameraMovement::Pan,
+            CameraMovement::Orbit | CameraMovement::Fly => CameraMovement::Pan,
         }
     }
 }
@@ -159,10 +183,12 @@ fn reset_main_camera(
 fn activate_main_camera(
     mut q: Query<(&mut Camera, &mut Transform), With<MainCamera>>,
     mut cam_settings: ResMut<CameraSettings>,
+    mut next_camera_movement: ResMut<NextState<CameraMovement>>,
     config: Res<Config>,
 ) {
     let (mut main_camera, mut tf) = q.single_mut();
     main_camera.is_active = true;
+    next_camera_movement.set(config.visualisation.camera.into());
     tf.translation.y = -config.interaction.default_cam_distance;
     cam_settings.start_pos.y = -config.interaction.default_cam_distance;
     // cam_settings.
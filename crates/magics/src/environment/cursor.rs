@@ -1,4 +1,7 @@
 use bevy::{prelude::*, window::PrimaryWindow};
+use gbp_config::Config;
+use gbp_environment::{Environment, TileCoordinates};
+use gbp_geometry::{Point, RelativePoint};
 
 use super::camera::MainCamera;
 use crate::asset_loader::{Materials, Meshes};
@@ -9,7 +12,12 @@ impl Plugin for CursorToGroundPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<CursorCoordinates>()
             .add_systems(Startup, spawn_invisible_ground_plane)
-            .add_systems(Update, cursor_to_ground_plane);
+            .add_systems(
+                Update,
+                (cursor_to_ground_plane, snap_cursor_to_map)
+                    .chain()
+                    .run_if(resource_exists::<Environment>),
+            );
     }
 }
 
@@ -21,9 +29,13 @@ impl Plugin for CursorToGroundPlugin {
 #[derive(Resource, Default)]
 pub struct CursorCoordinates {
     // Global (world-space) coordinates
-    global: Vec3,
+    global:  Vec3,
     // Local (relative to the ground plane) coordinates
-    local:  Vec2,
+    local:   Vec2,
+    // Global coordinates snapped to `interaction.cursor.snap`
+    snapped: Vec3,
+    // The tile under the snapped cursor, and the position within it
+    tile:    Option<(TileCoordinates, RelativePoint)>,
 }
 
 impl CursorCoordinates {
@@ -36,6 +48,20 @@ impl CursorCoordinates {
     pub const fn local(&self) -> Vec2 {
         self.local
     }
+
+    /// Get the global coordinates of the cursor, snapped to the grid of
+    /// `interaction.cursor.snap`. The same as [`Self::global`] if snapping is
+    /// disabled
+    pub const fn snapped(&self) -> Vec3 {
+        self.snapped
+    }
+
+    /// Get the tile under the snapped cursor, and the position of the cursor
+    /// within that tile, i.e. the `tile-coordinates` and `translation` of an
+    /// obstacle placed there. `None` if the cursor is outside of the map
+    pub const fn tile(&self) -> Option<(TileCoordinates, RelativePoint)> {
+        self.tile
+    }
 }
 
 /// Used to help identify our ground plane
@@ -117,3 +143,37 @@ fn cursor_to_ground_plane(
     // (our point is supposed to be on the plane)
     ground_coords.local = local_cursor.xz();
 }
+
+/// Snap the cursor to the grid of `interaction.cursor.snap`, and find the tile
+/// under it
+fn snap_cursor_to_map(
+    mut cursor: ResMut<CursorCoordinates>,
+    environment: Res<Environment>,
+    config: Res<Config>,
+) {
+    if !cursor.is_changed() && !environment.is_changed() && !config.is_changed() {
+        return;
+    }
+
+    // the map is centered at the origin, while the grid and tiles are measured
+    // from its bottom left corner. The y-axis of the map is the z-axis of the
+    // world
+    let (width, height) = environment.dimensions();
+    let corner = Vec2::new(-width / 2.0, -height / 2.0);
+    let position = cursor.global.xz() - corner;
+
+    let snap = config.interaction.cursor.snap;
+    let position = if snap > 0.0 {
+        (position / snap).round() * snap
+    } else {
+        position
+    };
+
+    // bypass change detection, so snapping alone does not count as moving
+    // the cursor
+    let cursor = cursor.bypass_change_detection();
+    let snapped = position + corner;
+    cursor.snapped = Vec3::new(snapped.x, cursor.global.y, snapped.y);
+    cursor.tile =
+        environment.world_to_tile(Point::new(f64::from(position.x), f64::from(position.y)));
+}
@@ -8,7 +8,10 @@ pub struct FollowCamerasPlugin;
 
 impl Plugin for FollowCamerasPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (move_cameras, add_follow_cameras));
+        app.add_systems(
+            Update,
+            (add_follow_cameras, move_cameras, despawn_orphaned_cameras),
+        );
     }
 }
 
@@ -34,16 +37,21 @@ impl Default for PID {
 #[derive(Component, Debug, Default, Clone, Copy)]
 pub struct FollowCameraMe {
     pub offset:       Option<Vec3>,
+    /// Fixed up direction of the camera. If `None` the camera turns with the
+    /// yaw of the entity
     pub up_direction: Option<Direction3d>,
     pub attached:     bool,
+    /// See [`FollowCameraSettings::smoothing`]
+    pub smoothing:    f32,
+    /// See [`FollowCameraSettings::look_ahead`]
+    pub look_ahead:   f32,
 }
 
 impl From<Vec3> for FollowCameraMe {
     fn from(v: Vec3) -> Self {
         Self {
-            offset:       Some(v),
-            up_direction: None,
-            attached:     false,
+            offset: Some(v),
+            ..Default::default()
         }
     }
 }
@@ -55,6 +63,8 @@ impl FollowCameraMe {
             offset:       Some(Vec3::new(x, y, z)),
             up_direction: None,
             attached:     false,
+            smoothing:    0.0,
+            look_ahead:   0.0,
         }
     }
 
@@ -77,6 +87,28 @@ impl FollowCameraMe {
         self.attached = attached;
         self
     }
+
+    #[must_use]
+    pub const fn with_smoothing(mut self, smoothing: f32) -> Self {
+        self.smoothing = smoothing;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_look_ahead(mut self, look_ahead: f32) -> Self {
+        self.look_ahead = look_ahead;
+        self
+    }
+
+    /// A camera following a robot, as configured in `config`. The camera is
+    /// attached to the robot, unless it is smoothed or looks ahead
+    #[must_use]
+    pub fn from_config(config: &gbp_config::FollowCameraSection) -> Self {
+        Self::from(Vec3::from(config.offset))
+            .with_attached(config.is_rigid())
+            .with_smoothing(config.smoothing)
+            .with_look_ahead(config.look_ahead)
+    }
 }
 
 /// `Component` to store the settings for a `FollowCamera`
@@ -84,7 +116,18 @@ impl FollowCameraMe {
 pub struct FollowCameraSettings {
     pub target: Entity,
     pub offset: Vec3,
-    pub pid:    PID,
+    pub pid: PID,
+    /// Time constant of the exponential smoothing of the camera movement.
+    /// 0.0 snaps the camera to the target every frame. SI unit: s
+    pub smoothing: f32,
+    /// How far ahead, along the velocity of the target, the camera looks.
+    /// SI unit: s
+    pub look_ahead: f32,
+    /// Turn the camera with the yaw of the target
+    pub follow_yaw: bool,
+    /// Position of the target in the previous frame, to estimate its
+    /// velocity
+    last_target: Option<Vec3>,
 }
 
 impl FollowCameraSettings {
@@ -96,6 +139,10 @@ impl FollowCameraSettings {
                 p: 1.0,
                 ..Default::default()
             },
+            smoothing: 0.0,
+            look_ahead: 0.0,
+            follow_yaw: true,
+            last_target: None,
         }
     }
 
@@ -104,6 +151,29 @@ impl FollowCameraSettings {
         self.offset = offset;
         self
     }
+
+    #[must_use]
+    pub const fn with_smoothing(mut self, smoothing: f32, look_ahead: f32) -> Self {
+        self.smoothing = smoothing;
+        self.look_ahead = look_ahead;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_follow_yaw(mut self, follow_yaw: bool) -> Self {
+        self.follow_yaw = follow_yaw;
+        self
+    }
+
+    /// Fraction of the remaining distance to the desired pose to move in a
+    /// frame of `delta_t` seconds
+    fn blend(&self, delta_t: f32) -> f32 {
+        if self.smoothing > 0.0 {
+            1.0 - (-delta_t / self.smoothing).exp()
+        } else {
+            1.0
+        }
+    }
 }
 
 // **Bevy** marker [`Component`] for follow cameras that are attached as
@@ -161,7 +231,10 @@ impl FollowCameraBundle {
         // let offset = (target.compute_matrix() * offset.extend(1.0)).xyz();
 
         Self {
-            settings: FollowCameraSettings::new(entity).with_offset(offset),
+            settings: FollowCameraSettings::new(entity)
+                .with_offset(offset)
+                .with_smoothing(params.smoothing, params.look_ahead)
+                .with_follow_yaw(params.up_direction.is_none()),
             movement: OrbitMovementBundle::default(),
             velocity: Velocity(Vec3::ZERO),
             camera: Camera3dBundle {
@@ -184,13 +257,10 @@ impl FollowCameraBundle {
 fn add_follow_cameras(
     mut commands: Commands,
     entities_to_attach_a_follow_cam_to: Query<(Entity, &Transform, &FollowCameraMe)>,
-    cameras: Query<Entity, With<Camera3d>>,
-    children: Query<&Children>,
+    cameras: Query<&FollowCameraSettings>,
 ) {
     for (entity, transform, follow_camera_flag) in &entities_to_attach_a_follow_cam_to {
-        let camera_already_attached = children
-            .iter_descendants(entity)
-            .any(|e| cameras.get(e).is_ok());
+        let camera_already_attached = cameras.iter().any(|settings| settings.target == entity);
 
         if camera_already_attached {
             // an entity can only have one follower camera attached to it
@@ -204,8 +274,11 @@ fn add_follow_cameras(
             ))
             .id();
 
-        // Make the camera a child of the entity
-        commands.entity(entity).push_children(&[follower_camera]);
+        // Attached cameras are children of the entity, and move rigidly with
+        // it. Free cameras are moved by `move_cameras`
+        if follow_camera_flag.attached {
+            commands.entity(entity).push_children(&[follower_camera]);
+        }
     }
 }
 
@@ -214,41 +287,68 @@ fn add_follow_cameras(
 /// with `FollowCameraSettings` to move cameras correctly
 #[allow(clippy::type_complexity)]
 fn move_cameras(
-    mut query_cameras: Query<(&mut Transform, &FollowCameraSettings, &CameraType), With<Camera>>,
-    query_targets: Query<(Entity, &Transform), (With<FollowCameraMe>, Without<Camera>)>,
+    mut query_cameras: Query<
+        (&mut Transform, &mut FollowCameraSettings, &CameraType),
+        With<Camera>,
+    >,
+    query_targets: Query<&Transform, (With<FollowCameraMe>, Without<Camera>)>,
+    time: Res<Time>,
 ) {
-    for (mut camera_transform, follow_settings, cam_type) in &mut query_cameras {
+    let delta_t = time.delta_seconds();
+    for (mut camera_transform, mut follow_settings, cam_type) in &mut query_cameras {
         if matches!(cam_type, CameraType::Attached) {
             continue;
         }
-        for (target_entity, target_transform) in query_targets.iter() {
-            if target_entity == follow_settings.target {
-                let (target_yaw, ..) = target_transform.rotation.to_euler(EulerRot::YXZ);
-                let (camera_yaw, ..) = camera_transform.rotation.to_euler(EulerRot::YXZ);
-                let mut delta_yaw = (target_yaw + PI) - camera_yaw;
+        let Ok(target_transform) = query_targets.get(follow_settings.target) else {
+            continue;
+        };
 
-                if delta_yaw > PI {
-                    delta_yaw -= PI * 2.0;
-                } else if delta_yaw < -PI {
-                    delta_yaw += PI * 2.0;
-                }
+        let velocity = match follow_settings.last_target {
+            Some(last) if delta_t > 0.0 => (target_transform.translation - last) / delta_t,
+            _ => Vec3::ZERO,
+        };
+        follow_settings.last_target = Some(target_transform.translation);
+        let blend = follow_settings.blend(delta_t);
+
+        let (target_yaw, ..) = target_transform.rotation.to_euler(EulerRot::YXZ);
+        let offset = if follow_settings.follow_yaw {
+            let (camera_yaw, ..) = camera_transform.rotation.to_euler(EulerRot::YXZ);
+            let mut delta_yaw = (target_yaw + PI) - camera_yaw;
+
+            if delta_yaw > PI {
+                delta_yaw -= PI * 2.0;
+            } else if delta_yaw < -PI {
+                delta_yaw += PI * 2.0;
+            }
 
-                let rotate_by_yaw = Quat::from_axis_angle(Vec3::Y, target_yaw);
-                let offset = rotate_by_yaw * follow_settings.offset;
+            // rotate by yaw
+            camera_transform.rotate(Quat::from_axis_angle(Vec3::Y, delta_yaw * blend));
+            Quat::from_axis_angle(Vec3::Y, target_yaw) * follow_settings.offset
+        } else {
+            follow_settings.offset
+        };
 
-                let target_position = target_transform.translation + offset;
+        let target_position =
+            target_transform.translation + velocity * follow_settings.look_ahead + offset;
 
-                let delta = target_position - camera_transform.translation;
-                let distance = delta.length();
+        let delta = target_position - camera_transform.translation;
+        if delta.length() < f32::EPSILON {
+            continue;
+        }
 
-                if distance < f32::EPSILON {
-                    continue;
-                }
+        camera_transform.translation += delta * blend * follow_settings.pid.p;
+    }
+}
 
-                camera_transform.translation += delta * follow_settings.pid.p;
-                // rotate by yaw
-                camera_transform.rotate(Quat::from_axis_angle(Vec3::Y, delta_yaw));
-            }
+/// `Update` system to despawn free cameras, whose target has been despawned
+fn despawn_orphaned_cameras(
+    mut commands: Commands,
+    cameras: Query<(Entity, &FollowCameraSettings, &CameraType)>,
+    targets: Query<(), With<FollowCameraMe>>,
+) {
+    for (camera, settings, camera_type) in &cameras {
+        if *camera_type == CameraType::Free && targets.get(settings.target).is_err() {
+            commands.entity(camera).despawn_recursive();
         }
     }
 }
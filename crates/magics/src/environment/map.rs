@@ -150,10 +150,8 @@ fn spawn_sdf_map_representation(
         Visibility::Hidden
     };
 
-    let (nrows, ncols) = environment.tiles.grid.shape();
-    let tile_size = environment.tiles.settings.tile_size;
-    let (width, height) = (nrows as f32 * tile_size, ncols as f32 * tile_size);
-    let rectangle = bevy::math::primitives::Rectangle::new(height, width);
+    let (width, height) = environment.dimensions();
+    let rectangle = bevy::math::primitives::Rectangle::new(width, height);
     let mesh = mesh_assets.add(Mesh::from(rectangle));
 
     commands.spawn((SdfMapRepresentation, PbrBundle {
@@ -1,19 +1,31 @@
-use std::sync::Arc;
-
-use bevy::{prelude::*, reflect::Tuple};
+use std::{collections::BTreeMap, sync::Arc};
+
+use bevy::{
+    prelude::*,
+    reflect::Tuple,
+    render::{
+        mesh::{Indices, VertexAttributeValues},
+        render_asset::RenderAssetUsages,
+        render_resource::PrimitiveTopology,
+    },
+};
 use bevy_mod_picking::prelude::*;
 use gbp_config::{Config, DrawSetting};
 use gbp_environment::{
-    Circle, Environment, PlaceableShape, Rectangle, RegularPolygon, TileCoordinates, Triangle,
+    Circle, Environment, Obstacle, PlaceableShape, Rectangle, RegularPolygon, TileCoordinates,
+    Triangle,
 };
 use gbp_global_planner::Colliders;
 use parry2d::{
     na::{self, Isometry2, Vector2},
+    query::PointQuery,
     shape,
 };
 
 use crate::{
-    asset_loader::Materials, bevy_utils::run_conditions::event_exists, input::DrawSettingsEvent,
+    asset_loader::{Materials, Meshes},
+    bevy_utils::run_conditions::event_exists,
+    input::DrawSettingsEvent,
     simulation_loader::LoadSimulation,
 };
 
@@ -28,7 +40,15 @@ impl Plugin for GenMapPlugin {
             // .add_systems(PostStartup, create_static_colliders)
             .add_systems(
                 Update,
-                (build_tile_grid.pipe(build_obstacles.pipe(insert_colliders_resource))).chain().run_if(on_event::<LoadSimulation>()),
+                (
+                    despawn_map,
+                    build_tile_grid.pipe(build_obstacles.pipe(insert_colliders_resource)),
+                )
+                    .chain()
+                    .run_if(
+                        on_event::<LoadSimulation>()
+                            .or_else(resource_exists_and_changed::<Environment>),
+                    ),
             )
             .add_systems(
                 Update,
@@ -54,6 +74,119 @@ pub mod events {
 #[derive(Debug, Component)]
 pub struct ObstacleMarker;
 
+/// **Bevy** [`Component`]
+/// Index of a placed obstacle in the obstacles of the [`Environment`]. Tile
+/// obstacles do not have one
+#[derive(Debug, Clone, Copy, Component)]
+pub struct ObstacleIndex(pub usize);
+
+/// **Bevy** [`Component`]
+/// The name and tags of an obstacle given in the environment config, so
+/// other systems can refer to specific obstacles
+#[derive(Debug, Clone, Default, Component)]
+pub struct ObstacleMetadata {
+    pub name: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl ObstacleMetadata {
+    /// Whether the obstacle has the tag `tag`
+    #[must_use]
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+impl From<&Obstacle> for ObstacleMetadata {
+    fn from(obstacle: &Obstacle) -> Self {
+        Self {
+            name: obstacle.name.clone(),
+            tags: obstacle.tags.clone(),
+        }
+    }
+}
+
+/// **Bevy** [`Component`]
+/// The analytic shape and pose of an obstacle, in the plane of the planner,
+/// i.e. the (x, z) plane of the world. The same geometry as the collider of
+/// the obstacle in [`Colliders`], so other systems can run distance queries
+/// against `Query<&ObstacleShape>` instead of re-deriving it from the
+/// [`Environment`]
+#[derive(Clone, Component)]
+pub struct ObstacleShape {
+    /// Translation and rotation of the shape
+    pub isometry: Isometry2<f32>,
+    pub shape:    Arc<dyn shape::Shape>,
+}
+
+impl std::fmt::Debug for ObstacleShape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObstacleShape")
+            .field("isometry", &self.isometry)
+            .field("shape", &self.shape.shape_type())
+            .finish()
+    }
+}
+
+impl ObstacleShape {
+    #[must_use]
+    pub fn new(isometry: Isometry2<f32>, shape: Arc<dyn shape::Shape>) -> Self {
+        Self { isometry, shape }
+    }
+
+    /// Distance from `point` to the obstacle, 0.0 if `point` is inside it
+    #[must_use]
+    pub fn distance_to_point(&self, point: Vec2) -> f32 {
+        self.shape
+            .distance_to_point(&self.isometry, &na::Point2::new(point.x, point.y), true)
+    }
+
+    /// Distance from the boundary of a circle to the obstacle, 0.0 if they
+    /// overlap
+    #[must_use]
+    pub fn distance_to_circle(&self, center: Vec2, radius: f32) -> f32 {
+        (self.distance_to_point(center) - radius).max(0.0)
+    }
+
+    /// Whether `point` is inside the obstacle
+    #[must_use]
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        self.shape
+            .contains_point(&self.isometry, &na::Point2::new(point.x, point.y))
+    }
+
+    /// The point of the obstacle closest to `point`, i.e. `point` itself if
+    /// it is inside the obstacle
+    #[must_use]
+    pub fn closest_point(&self, point: Vec2) -> Vec2 {
+        let projection =
+            self.shape
+                .project_point(&self.isometry, &na::Point2::new(point.x, point.y), true);
+        Vec2::new(projection.point.x, projection.point.y)
+    }
+
+    /// The axis aligned bounding box of the obstacle
+    #[must_use]
+    pub fn aabb(&self) -> parry2d::bounding_volume::Aabb {
+        self.shape.compute_aabb(&self.isometry)
+    }
+}
+
+/// Add the collider of `entity` to `colliders`, and give the entity an
+/// [`ObstacleShape`] with the same geometry
+fn add_collider(
+    commands: &mut Commands,
+    colliders: &mut Colliders,
+    entity: Entity,
+    isometry: Isometry2<f32>,
+    shape: Arc<dyn shape::Shape>,
+) {
+    commands
+        .entity(entity)
+        .insert(ObstacleShape::new(isometry, Arc::clone(&shape)));
+    colliders.push(Some(entity), isometry, shape);
+}
+
 // #[derive(Clone)]
 // pub struct Collider {
 //     pub associated_mesh: Option<Entity>,
@@ -102,6 +235,25 @@ pub struct ObstacleMarker;
 //     }
 // }
 
+/// Despawn the tile walls and obstacles of the previous [`Environment`],
+/// before the map is rebuilt
+#[allow(clippy::type_complexity)]
+fn despawn_map(
+    mut commands: Commands,
+    map: Query<
+        Entity,
+        (
+            Or<(With<ObstacleMarker>, With<TileCoordinates>)>,
+            Without<Parent>,
+        ),
+    >,
+) {
+    for entity in &map {
+        commands.entity(entity).despawn_recursive();
+    }
+    debug!("despawned {} map entities", map.iter().count());
+}
+
 fn insert_colliders_resource(In(colliders): In<Colliders>, mut commands: Commands) {
     commands.insert_resource(colliders);
 }
@@ -148,7 +300,10 @@ fn build_obstacles(
     materials: Res<Materials>,
 ) -> Colliders {
     let tile_grid = &env_config.tiles.grid;
-    let tile_size = env_config.tile_size();
+    let tile_width = env_config.tile_width();
+    let tile_height = env_config.tile_height();
+    // obstacle sizes are relative to the shorter side of the tile
+    let tile_size = env_config.tile_extent();
     let obstacle_height = -env_config.obstacle_height();
 
     let grid_offset_x = tile_grid.ncols() as f32 / 2.0 - 0.5;
@@ -161,28 +316,31 @@ fn build_obstacles(
         env_config.obstacles.iter().count()
     );
 
-    let obstacles_to_spawn = env_config.obstacles.iter().map(|obstacle| {
-        let TileCoordinates { row, col } = obstacle.tile_coordinates;
-
-        info!("Spawning obstacle at {:?}", (row, col));
+    // Build the mesh and collider of a single shape, with `translation` being
+    // relative to the tile
+    let mut build_shape = |shape: &PlaceableShape, rotation: f32, translation: Vec2, tile| {
+        let TileCoordinates { row, col } = tile;
 
         let tile_offset_x = col as f32;
         let tile_offset_z = row as f32;
 
-        let offset_x = (tile_offset_x - grid_offset_x) * tile_size;
-        let offset_z = (tile_offset_z - grid_offset_z) * tile_size;
+        let offset_x = (tile_offset_x - grid_offset_x) * tile_width;
+        let offset_z = (tile_offset_z - grid_offset_z) * tile_height;
 
-        let pos_offset = tile_size / 2.0;
+        let pos_offset_x = tile_width / 2.0;
+        let pos_offset_z = tile_height / 2.0;
 
-        let translation = obstacle.translation;
+        // the world x coordinate of the center of the shape is the same for
+        // all shapes
+        let center_x = translation.x.mul_add(tile_width, offset_x) - pos_offset_x;
 
         // Construct the correct shape
-        match &obstacle.shape {
+        match shape {
             PlaceableShape::Circle(Circle { radius }) => {
                 let center = Vec3::new(
-                    (translation.x.get() as f32).mul_add(tile_size, offset_x) - pos_offset,
+                    center_x,
                     obstacle_height / 2.0,
-                    (1.0 - translation.y.get() as f32).mul_add(tile_size, offset_z) - pos_offset,
+                    (1.0 - translation.y).mul_add(tile_height, offset_z) - pos_offset_z,
                 );
 
                 info!("Spawning circle: r = {}, at {:?}", radius, center);
@@ -208,10 +366,10 @@ fn build_obstacles(
             }
             PlaceableShape::Triangle(ref triangle_shape @ Triangle { angles, radius }) => {
                 let center = Vec3::new(
-                    (translation.x.get() as f32).mul_add(tile_size, offset_x) - pos_offset,
+                    center_x,
                     // obstacle_height / 2.0,
                     obstacle_height,
-                    -((translation.y.get() as f32).mul_add(tile_size, offset_z) - pos_offset),
+                    -(translation.y.mul_add(tile_height, offset_z) - pos_offset_z),
                 );
 
                 // Example triangle
@@ -250,8 +408,7 @@ fn build_obstacles(
                     .expect("Failed to create triangle mesh"),
                 );
 
-                let rotation_angle: f32 =
-                    std::f32::consts::FRAC_PI_2 - obstacle.rotation.as_radians() as f32;
+                let rotation_angle: f32 = std::f32::consts::FRAC_PI_2 - rotation;
                 let rotation = Quat::from_rotation_y(rotation_angle);
 
                 let isometry = Isometry2::new(
@@ -276,9 +433,9 @@ fn build_obstacles(
             }
             PlaceableShape::RegularPolygon(ref polygon @ RegularPolygon { sides, radius }) => {
                 let center = Vec3::new(
-                    (translation.x.get() as f32).mul_add(tile_size, offset_x) - pos_offset,
+                    center_x,
                     obstacle_height / 2.0,
-                    -((translation.y.get() as f32).mul_add(tile_size, offset_z) - pos_offset),
+                    -(translation.y.mul_add(tile_height, offset_z) - pos_offset_z),
                 );
 
                 info!(
@@ -296,15 +453,14 @@ fn build_obstacles(
 
                 // info!(
                 //     "obstacle.rotation.as_radians() = {:?}, std::f32::consts::FRAC_PI_4 =
-                // {:?}",     obstacle.rotation.as_radians() as f32,
+                // {:?}",     rotation,
                 //     std::f32::consts::FRAC_PI_4
                 // );
 
-                let rotation_angle =
-                    std::f32::consts::FRAC_PI_4 + obstacle.rotation.as_radians() as f32;
+                let rotation_angle = std::f32::consts::FRAC_PI_4 + rotation;
                 let rotation = Quat::from_rotation_y(
                     rotation_angle, /* std::f32::consts::FRAC_PI_4 +
-                                     * obstacle.rotation.as_radians() as f32, */
+                                     * rotation, */
                 );
                 let transform = Transform::from_translation(center).with_rotation(rotation);
 
@@ -339,9 +495,8 @@ fn build_obstacles(
                 // let rotation2 = Quat::from_rotation_y(std::f32::consts::PI / polygon.sides as
                 // f32);
 
-                let rotation_angle = obstacle.rotation.as_radians() as f32 + rotation_offset;
-                let rotation2 =
-                    Quat::from_rotation_z(obstacle.rotation.as_radians() as f32 + rotation_offset);
+                let rotation_angle = rotation + rotation_offset;
+                let rotation2 = Quat::from_rotation_z(rotation + rotation_offset);
                 // let rotation2 = Quat::from_rotation_z(rotation_offset);
 
                 // let points: Vec<parry2d::math::Point<parry2d::math::Real>> = polygon
@@ -379,9 +534,9 @@ fn build_obstacles(
             }
             PlaceableShape::Polygon(gbp_environment::Polygon { points }) => {
                 let center = Vec3::new(
-                    (translation.x.get() as f32).mul_add(tile_size, offset_x) - pos_offset,
+                    center_x,
                     obstacle_height / 2.0,
-                    (translation.y.get() as f32).mul_add(tile_size, offset_z) - pos_offset,
+                    translation.y.mul_add(tile_height, offset_z) - pos_offset_z,
                 );
 
                 // let center = Vec3::new(0.0, 0.0, 0.0);
@@ -394,8 +549,8 @@ fn build_obstacles(
                             .iter()
                             .map(|point| {
                                 Vec2::new(
-                                    (point.x as f32) * tile_size,
-                                    (point.y as f32) * tile_size,
+                                    (point.x as f32) * tile_width,
+                                    (point.y as f32) * tile_height,
                                 )
                             })
                             .rev()
@@ -404,15 +559,15 @@ fn build_obstacles(
                     .expect("Failed to create irregular polygon mesh"),
                 );
 
-                let rotation = Quat::from_rotation_y(obstacle.rotation.as_radians() as f32);
+                let rotation = Quat::from_rotation_y(rotation);
                 let transform = Transform::from_translation(center).with_rotation(rotation);
 
                 let points: Vec<parry2d::math::Point<parry2d::math::Real>> = points
                     .iter()
                     .map(|point| {
                         parry2d::math::Point::new(
-                            (point.x as f32) * tile_size,
-                            (point.y as f32) * tile_size,
+                            (point.x as f32) * tile_width,
+                            (point.y as f32) * tile_height,
                         )
                     })
                     .collect();
@@ -438,9 +593,9 @@ fn build_obstacles(
                 //     height,
                 // ));
                 let center = Vec3::new(
-                    (translation.x.get() as f32).mul_add(tile_size, offset_x) - pos_offset,
+                    center_x,
                     obstacle_height / 2.0,
-                    -((translation.y.get() as f32).mul_add(tile_size, offset_z) - pos_offset),
+                    -(translation.y.mul_add(tile_height, offset_z) - pos_offset_z),
                 );
 
                 info!(
@@ -454,7 +609,7 @@ fn build_obstacles(
                     height.get() as f32 * tile_size / 2.0,
                 ));
 
-                // let rotation = Quat::from_rotation_y(obstacle.rotation.as_radians() as f32);
+                // let rotation = Quat::from_rotation_y(rotation);
                 // let transform = Transform::from_translation(center).with_rotation(rotation);
                 let transform = Transform::from_translation(center);
 
@@ -475,40 +630,173 @@ fn build_obstacles(
 
                 Some((mesh, transform, isometry, shape))
             }
+            PlaceableShape::Capsule(capsule) => {
+                let center = Vec3::new(
+                    center_x,
+                    obstacle_height,
+                    -(translation.y.mul_add(tile_height, offset_z) - pos_offset_z),
+                );
+
+                info!(
+                    "Spawning capsule: length = {}, radius = {}, at {:?}",
+                    capsule.length, capsule.radius, center
+                );
+
+                // Same orientation convention as the triangle
+                let points = capsule
+                    .points()
+                    .into_iter()
+                    .map(|point| Vec2::new(-point.x * tile_size, point.y * tile_size))
+                    .collect::<Vec<_>>();
+
+                let mesh = meshes.add(
+                    Mesh::try_from(bevy_more_shapes::Prism::new(-obstacle_height, points))
+                        .expect("Failed to create capsule mesh"),
+                );
+
+                let rotation = Quat::from_rotation_y(std::f32::consts::FRAC_PI_2 - rotation);
+                let transform = Transform::from_translation(center).with_rotation(rotation);
+
+                let rotate = |p: Vec2| -> parry2d::math::Point<f32> {
+                    rotation
+                        .mul_vec3(p.extend(0.0).xzy())
+                        .xz()
+                        .to_array()
+                        .into()
+                };
+
+                let [start, end] = capsule
+                    .segment()
+                    .map(|point| rotate(Vec2::new(-point.x * tile_size, point.y * tile_size)));
+                let shape = parry2d::shape::Capsule::new(
+                    start,
+                    end,
+                    capsule.radius.get() as f32 * tile_size,
+                );
+                let shape: Arc<dyn shape::Shape> = Arc::new(shape);
+
+                let isometry =
+                    Isometry2::new(parry2d::na::Vector2::new(center.x, center.z), na::zero());
+
+                Some((mesh, transform, isometry, shape))
+            }
+            PlaceableShape::Composite(_) => {
+                warn!("composite obstacles can not be nested, skipping inner composite");
+                None
+            }
         }
-    });
+    };
+
+    let visibility = if config.visualisation.draw.generated_map {
+        Visibility::Visible
+    } else {
+        Visibility::Hidden
+    };
+
+    for (index, obstacle) in env_config.obstacles.iter().enumerate() {
+        info!("Spawning obstacle at {:?}", obstacle.tile_coordinates);
+        let rotation = obstacle.rotation.as_radians() as f32;
+        let translation = Vec2::from(obstacle.translation);
+
+        let PlaceableShape::Composite(composite) = &obstacle.shape else {
+            let Some((mesh, transform, isometry, shape)) = build_shape(
+                &obstacle.shape,
+                rotation,
+                translation,
+                obstacle.tile_coordinates,
+            ) else {
+                continue;
+            };
 
-    obstacles_to_spawn
-        .flatten() // filter out None
-        .for_each(|(mesh, transform, isometry, shape)| {
             // TODO: remember to get rotation of obstacle, i.e. for triangles
-            let entity = commands.spawn((
-                PbrBundle {
-                    mesh,
-                    material: materials.obstacle.clone(),
-                    transform,
-                    visibility: if config.visualisation.draw.generated_map {
-                        Visibility::Visible
-                    } else {
-                        Visibility::Hidden
+            let entity = commands
+                .spawn((
+                    PbrBundle {
+                        mesh,
+                        material: materials.obstacle.clone(),
+                        transform,
+                        visibility,
+                        ..Default::default()
                     },
-                    ..Default::default()
-                },
+                    ObstacleMarker,
+                    ObstacleIndex(index),
+                    ObstacleMetadata::from(obstacle),
+                    bevy_mod_picking::PickableBundle::default(),
+                    On::<Pointer<Click>>::send_event::<events::ObstacleClickedOn>(),
+                ))
+                .id();
+
+            if let Some(ref name) = obstacle.name {
+                commands.entity(entity).insert(Name::new(name.clone()));
+            }
+
+            add_collider(&mut commands, &mut colliders, entity, isometry, shape);
+            continue;
+        };
+
+        // A composite obstacle is spawned as a single parent entity, with a
+        // child mesh for every part, and a single compound collider
+        let parts = composite
+            .parts
+            .iter()
+            .filter_map(|part| {
+                build_shape(
+                    &part.shape,
+                    rotation,
+                    translation + part.rotated_offset(rotation),
+                    obstacle.tile_coordinates,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        if parts.is_empty() {
+            continue;
+        }
+
+        let parent = commands
+            .spawn((
+                SpatialBundle::from_transform(Transform::IDENTITY),
                 ObstacleMarker,
-                bevy_mod_picking::PickableBundle::default(),
-                On::<Pointer<Click>>::send_event::<events::ObstacleClickedOn>(),
-            )).id();
-
-            colliders.push(
-                Some(entity),
-                isometry,
-                //Isometry2::new(parry2d::na::Vector2::new(
-                //    transform.translation.x,
-                //    transform.translation.z,
-                //), na::zero()), // FIXME: add rotation
-                shape
-            );
-        });
+                ObstacleIndex(index),
+                ObstacleMetadata::from(obstacle),
+            ))
+            .insert(visibility)
+            .with_children(|parent| {
+                for (mesh, transform, _, _) in &parts {
+                    parent.spawn((
+                        PbrBundle {
+                            mesh: mesh.clone(),
+                            material: materials.obstacle.clone(),
+                            transform: *transform,
+                            visibility,
+                            ..Default::default()
+                        },
+                        ObstacleMarker,
+                        bevy_mod_picking::PickableBundle::default(),
+                        On::<Pointer<Click>>::send_event::<events::ObstacleClickedOn>(),
+                    ));
+                }
+            })
+            .id();
+
+        if let Some(ref name) = obstacle.name {
+            commands.entity(parent).insert(Name::new(name.clone()));
+        }
+
+        let compound = shape::Compound::new(
+            parts
+                .into_iter()
+                .map(|(_, _, isometry, shape)| (isometry, shape::SharedShape(shape)))
+                .collect(),
+        );
+        add_collider(
+            &mut commands,
+            &mut colliders,
+            parent,
+            Isometry2::identity(),
+            Arc::new(compound),
+        );
+    }
 
     colliders
 }
@@ -536,47 +824,49 @@ fn build_obstacles(
 #[allow(clippy::too_many_lines, clippy::cast_precision_loss)]
 fn build_tile_grid(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
+    meshes: Res<Meshes>,
+    mut mesh_assets: ResMut<Assets<Mesh>>,
     // mut colliders: ResMut<Colliders>,
     env_config: Res<Environment>,
     config: Res<Config>,
     materials: Res<Materials>,
-    obstacles: Query<Entity, With<ObstacleMarker>>,
 ) -> Colliders {
-    for entity in &obstacles {
-        commands.entity(entity).despawn();
-        info!("despawn obstacle entity: {:?}", entity);
-    }
-
     let tile_grid = &env_config.tiles.grid;
 
     let obstacle_height = env_config.obstacle_height();
     let obstacle_y = -obstacle_height / 2.0;
 
-    let tile_size = env_config.tile_size();
+    let tile_width = env_config.tile_width();
+    let tile_height = env_config.tile_height();
 
-    let path_width = env_config.path_width();
-    let base_dim = tile_size * (1.0 - path_width) / 2.0;
+    // the path width is relative to the shorter side of the tile, so paths
+    // have the same width in both directions on rectangular tiles
+    let path_width = env_config.path_width() * env_config.tile_extent();
+    let base_dim_x = (tile_width - path_width) / 2.0;
+    let base_dim_z = (tile_height - path_width) / 2.0;
 
     // offset caused by the size of the grid
     // - this centers the map
     let grid_offset_x = tile_grid.ncols() as f32 / 2.0 - 0.5;
     let grid_offset_z = -(tile_grid.nrows() as f32 / 2.0 - 0.5);
 
-    let pos_offset = path_width.mul_add(tile_size, base_dim) / 2.0;
+    let pos_offset_x = (path_width + base_dim_x) / 2.0;
+    let pos_offset_z = (path_width + base_dim_z) / 2.0;
 
     let mut colliders = Colliders::default();
+    // walls to merge, grouped by the chunk of tiles they are in
+    let mut chunks: BTreeMap<(usize, usize), Vec<(Cuboid, Transform)>> = BTreeMap::new();
 
-    for (y, row) in tile_grid.iter().enumerate() {
-        for (x, tile) in row.chars().enumerate() {
+    for (y, row) in tile_grid.rows().enumerate() {
+        for (x, &tile) in row.iter().enumerate() {
             // offset of the individual tile in the grid
             // used in all match cases
             let tile_offset_x = x as f32;
             let tile_offset_z = -(y as f32);
 
             // total offset caused by grid and tile
-            let offset_x = (tile_offset_x - grid_offset_x) * tile_size;
-            let offset_z = (tile_offset_z - grid_offset_z) * tile_size;
+            let offset_x = (tile_offset_x - grid_offset_x) * tile_width;
+            let offset_z = (tile_offset_z - grid_offset_z) * tile_height;
             // Vec<(Handle<Mesh>, Transform, parry2d::shape::Cuboid)>
             if let Some(obstacle_information) = match tile {
                 '─' | '-' => {
@@ -584,8 +874,8 @@ fn build_tile_grid(
                     // - 2 equal-sized larger cuboid on either side, spanning the entire width of
                     //   the tile
 
-                    // let cuboid = Cuboid::new(base_dim, obstacle_height, tile_size);
-                    let cuboid = Cuboid::new(tile_size, obstacle_height, base_dim);
+                    // let cuboid = Cuboid::new(base_dim_x, obstacle_height, tile_height);
+                    let cuboid = Cuboid::new(tile_width, obstacle_height, base_dim_z);
                     // let parry_cuboid: parry2d::shape::Cuboid = cuboid.into();
                     // let mesh_handle = meshes.add(cuboid);
 
@@ -597,7 +887,7 @@ fn build_tile_grid(
                             Transform::from_translation(Vec3::new(
                                 offset_x,
                                 obstacle_y,
-                                offset_z - pos_offset,
+                                offset_z - pos_offset_z,
                             )),
                         ),
                         (
@@ -607,7 +897,7 @@ fn build_tile_grid(
                             Transform::from_translation(Vec3::new(
                                 offset_x,
                                 obstacle_y,
-                                offset_z + pos_offset,
+                                offset_z + pos_offset_z,
                             )),
                         ),
                     ])
@@ -617,7 +907,7 @@ fn build_tile_grid(
                     // - 2 equal-sized larger cuboid on either side, spanning the entire height of
                     //   the tile
 
-                    let cuboid = Cuboid::new(base_dim, obstacle_height, tile_size);
+                    let cuboid = Cuboid::new(base_dim_x, obstacle_height, tile_height);
                     // let parry_cuboid: parry2d::shape::Cuboid = cuboid.into();
                     // let mesh_handle = meshes.add(cuboid);
 
@@ -628,7 +918,7 @@ fn build_tile_grid(
                             cuboid,
                             // left side transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - pos_offset,
+                                offset_x - pos_offset_x,
                                 obstacle_y,
                                 offset_z,
                             )),
@@ -639,7 +929,7 @@ fn build_tile_grid(
                             cuboid,
                             // right side transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + pos_offset,
+                                offset_x + pos_offset_x,
                                 obstacle_y,
                                 offset_z,
                             )),
@@ -653,15 +943,14 @@ fn build_tile_grid(
                     // - 1 smaller 'plug' cuboid on the right, to terminate the path
 
                     // Top and bottom
-                    let cuboid = Cuboid::new(tile_size, obstacle_height, base_dim);
+                    let cuboid = Cuboid::new(tile_width, obstacle_height, base_dim_z);
                     // let parry_cuboid: parry2d::shape::Cuboid = cuboid.into();
                     // let mesh_handle = meshes.add(cuboid);
 
                     // Plug at the right
                     // let cuboid_plug =
-                    //     Cuboid::new(base_dim, obstacle_height, path_width * tile_size);
-                    let cuboid_plug =
-                        Cuboid::new(tile_size / 2.0, obstacle_height, path_width * tile_size);
+                    //     Cuboid::new(base_dim_x, obstacle_height, path_width);
+                    let cuboid_plug = Cuboid::new(tile_width / 2.0, obstacle_height, path_width);
                     // let parry_cuboid_plug: parry2d::shape::Cuboid = cuboid_plug.into();
 
                     Some(vec![
@@ -672,7 +961,7 @@ fn build_tile_grid(
                             Transform::from_translation(Vec3::new(
                                 offset_x,
                                 obstacle_y,
-                                offset_z - pos_offset,
+                                offset_z - pos_offset_z,
                             )),
                         ),
                         (
@@ -682,7 +971,7 @@ fn build_tile_grid(
                             Transform::from_translation(Vec3::new(
                                 offset_x,
                                 obstacle_y,
-                                offset_z + pos_offset,
+                                offset_z + pos_offset_z,
                             )),
                         ),
                         (
@@ -690,7 +979,7 @@ fn build_tile_grid(
                             cuboid_plug,
                             // right plug transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + tile_size / 4.0,
+                                offset_x + tile_width / 4.0,
                                 obstacle_y,
                                 offset_z,
                             )),
@@ -704,15 +993,14 @@ fn build_tile_grid(
                     // - 1 smaller 'plug' cuboid on the left, to terminate the path
 
                     // Top and bottom
-                    let cuboid = Cuboid::new(tile_size, obstacle_height, base_dim);
+                    let cuboid = Cuboid::new(tile_width, obstacle_height, base_dim_z);
                     // let parry_cuboid: parry2d::shape::Cuboid = cuboid.into();
                     // let mesh_handle = meshes.add(cuboid);
 
                     // Plug at the left
                     // let cuboid_plug =
-                    //     Cuboid::new(base_dim, obstacle_height, path_width * tile_size);
-                    let cuboid_plug =
-                        Cuboid::new(tile_size / 2.0, obstacle_height, path_width * tile_size);
+                    //     Cuboid::new(base_dim_x, obstacle_height, path_width);
+                    let cuboid_plug = Cuboid::new(tile_width / 2.0, obstacle_height, path_width);
                     // let parry_cuboid_plug: parry2d::shape::Cuboid = cuboid_plug.into();
 
                     Some(vec![
@@ -723,7 +1011,7 @@ fn build_tile_grid(
                             Transform::from_translation(Vec3::new(
                                 offset_x,
                                 obstacle_y,
-                                offset_z - pos_offset,
+                                offset_z - pos_offset_z,
                             )),
                         ),
                         (
@@ -733,7 +1021,7 @@ fn build_tile_grid(
                             Transform::from_translation(Vec3::new(
                                 offset_x,
                                 obstacle_y,
-                                offset_z + pos_offset,
+                                offset_z + pos_offset_z,
                             )),
                         ),
                         (
@@ -741,7 +1029,7 @@ fn build_tile_grid(
                             cuboid_plug,
                             // left plug transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - tile_size / 4.0,
+                                offset_x - tile_width / 4.0,
                                 obstacle_y,
                                 offset_z,
                             )),
@@ -755,15 +1043,14 @@ fn build_tile_grid(
                     // - 1 smaller 'plug' cuboid on the bottom, to terminate the path
 
                     // Left and right
-                    let cuboid = Cuboid::new(base_dim, obstacle_height, tile_size);
+                    let cuboid = Cuboid::new(base_dim_x, obstacle_height, tile_height);
                     // let parry_cuboid: parry2d::shape::Cuboid = cuboid.into();
                     // let mesh_handle = meshes.add(cuboid);
 
                     // Plug at the bottom
                     // let cuboid_plug =
-                    //     Cuboid::new(path_width * tile_size, obstacle_height, base_dim);
-                    let cuboid_plug =
-                        Cuboid::new(path_width * tile_size, obstacle_height, tile_size / 2.0);
+                    //     Cuboid::new(path_width, obstacle_height, base_dim_z);
+                    let cuboid_plug = Cuboid::new(path_width, obstacle_height, tile_height / 2.0);
                     // let parry_cuboid_plug: parry2d::shape::Cuboid = cuboid_plug.into();
 
                     Some(vec![
@@ -772,7 +1059,7 @@ fn build_tile_grid(
                             cuboid,
                             // left transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - pos_offset,
+                                offset_x - pos_offset_x,
                                 obstacle_y,
                                 offset_z,
                             )),
@@ -782,7 +1069,7 @@ fn build_tile_grid(
                             cuboid,
                             // right transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + pos_offset,
+                                offset_x + pos_offset_x,
                                 obstacle_y,
                                 offset_z,
                             )),
@@ -794,7 +1081,7 @@ fn build_tile_grid(
                             Transform::from_translation(Vec3::new(
                                 offset_x,
                                 obstacle_y,
-                                offset_z + tile_size / 4.0,
+                                offset_z + tile_height / 4.0,
                             )),
                         ),
                     ])
@@ -806,15 +1093,14 @@ fn build_tile_grid(
                     // - 1 smaller 'plug' cuboid on the top, to terminate the path
 
                     // Left and right
-                    let cuboid = Cuboid::new(base_dim, obstacle_height, tile_size);
+                    let cuboid = Cuboid::new(base_dim_x, obstacle_height, tile_height);
                     // let parry_cuboid: parry2d::shape::Cuboid = cuboid.into();
                     // let mesh_handle = meshes.add(cuboid);
 
                     // Plug at the top
                     // let cuboid_plug =
-                    //     Cuboid::new(path_width * tile_size, obstacle_height, base_dim);
-                    let cuboid_plug =
-                        Cuboid::new(path_width * tile_size, obstacle_height, tile_size / 2.0);
+                    //     Cuboid::new(path_width, obstacle_height, base_dim_z);
+                    let cuboid_plug = Cuboid::new(path_width, obstacle_height, tile_height / 2.0);
                     // let parry_cuboid_plug: parry2d::shape::Cuboid = cuboid_plug.into();
 
                     Some(vec![
@@ -823,7 +1109,7 @@ fn build_tile_grid(
                             cuboid,
                             // left transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - pos_offset,
+                                offset_x - pos_offset_x,
                                 obstacle_y,
                                 offset_z,
                             )),
@@ -833,7 +1119,7 @@ fn build_tile_grid(
                             cuboid,
                             // right transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + pos_offset,
+                                offset_x + pos_offset_x,
                                 obstacle_y,
                                 offset_z,
                             )),
@@ -845,7 +1131,7 @@ fn build_tile_grid(
                             Transform::from_translation(Vec3::new(
                                 offset_x,
                                 obstacle_y,
-                                offset_z - tile_size / 4.0,
+                                offset_z - tile_height / 4.0,
                             )),
                         ),
                     ])
@@ -858,9 +1144,9 @@ fn build_tile_grid(
                     // - 1 larger cuboid on the top side, spanning from the right to the above
                     //   cuboid
 
-                    let cuboid_bottom_right = Cuboid::new(base_dim, obstacle_height, base_dim);
-                    let cuboid_left = Cuboid::new(base_dim, obstacle_height, tile_size);
-                    let cuboid_top = Cuboid::new(tile_size, obstacle_height, base_dim);
+                    let cuboid_bottom_right = Cuboid::new(base_dim_x, obstacle_height, base_dim_z);
+                    let cuboid_left = Cuboid::new(base_dim_x, obstacle_height, tile_height);
+                    let cuboid_top = Cuboid::new(tile_width, obstacle_height, base_dim_z);
 
                     Some(vec![
                         (
@@ -868,9 +1154,9 @@ fn build_tile_grid(
                             cuboid_bottom_right,
                             // bottom right cube transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + pos_offset,
+                                offset_x + pos_offset_x,
                                 obstacle_y,
-                                offset_z - pos_offset,
+                                offset_z - pos_offset_z,
                             )),
                         ),
                         (
@@ -878,7 +1164,7 @@ fn build_tile_grid(
                             cuboid_left,
                             // left side transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - pos_offset,
+                                offset_x - pos_offset_x,
                                 obstacle_y,
                                 offset_z,
                             )),
@@ -890,7 +1176,7 @@ fn build_tile_grid(
                             Transform::from_translation(Vec3::new(
                                 offset_x,
                                 obstacle_y,
-                                offset_z + pos_offset,
+                                offset_z + pos_offset_z,
                             )),
                         ),
                     ])
@@ -902,9 +1188,9 @@ fn build_tile_grid(
                     //   tile
                     // - 1 larger cuboid on the top side, spanning from the left to the above cuboid
 
-                    let cuboid_bottom_left = Cuboid::new(base_dim, obstacle_height, base_dim);
-                    let cuboid_right = Cuboid::new(base_dim, obstacle_height, tile_size);
-                    let cuboid_top = Cuboid::new(tile_size, obstacle_height, base_dim);
+                    let cuboid_bottom_left = Cuboid::new(base_dim_x, obstacle_height, base_dim_z);
+                    let cuboid_right = Cuboid::new(base_dim_x, obstacle_height, tile_height);
+                    let cuboid_top = Cuboid::new(tile_width, obstacle_height, base_dim_z);
 
                     Some(vec![
                         (
@@ -912,9 +1198,9 @@ fn build_tile_grid(
                             cuboid_bottom_left,
                             // bottom left cube transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - pos_offset,
+                                offset_x - pos_offset_x,
                                 obstacle_y,
-                                offset_z - pos_offset,
+                                offset_z - pos_offset_z,
                             )),
                         ),
                         (
@@ -922,7 +1208,7 @@ fn build_tile_grid(
                             cuboid_right,
                             // right side transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + pos_offset,
+                                offset_x + pos_offset_x,
                                 obstacle_y,
                                 offset_z,
                             )),
@@ -934,7 +1220,7 @@ fn build_tile_grid(
                             Transform::from_translation(Vec3::new(
                                 offset_x,
                                 obstacle_y,
-                                offset_z + pos_offset,
+                                offset_z + pos_offset_z,
                             )),
                         ),
                     ])
@@ -947,9 +1233,9 @@ fn build_tile_grid(
                     // - 1 larger cuboid on the bottom side, spanning from the right to the above
                     //   cuboid
 
-                    let cuboid_top_right = Cuboid::new(base_dim, obstacle_height, base_dim);
-                    let cuboid_left = Cuboid::new(base_dim, obstacle_height, tile_size);
-                    let cuboid_bottom = Cuboid::new(tile_size, obstacle_height, base_dim);
+                    let cuboid_top_right = Cuboid::new(base_dim_x, obstacle_height, base_dim_z);
+                    let cuboid_left = Cuboid::new(base_dim_x, obstacle_height, tile_height);
+                    let cuboid_bottom = Cuboid::new(tile_width, obstacle_height, base_dim_z);
 
                     Some(vec![
                         (
@@ -957,9 +1243,9 @@ fn build_tile_grid(
                             cuboid_top_right,
                             // top right cube transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + pos_offset,
+                                offset_x + pos_offset_x,
                                 obstacle_y,
-                                offset_z + pos_offset,
+                                offset_z + pos_offset_z,
                             )),
                         ),
                         (
@@ -967,7 +1253,7 @@ fn build_tile_grid(
                             cuboid_left,
                             // left side transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - pos_offset,
+                                offset_x - pos_offset_x,
                                 obstacle_y,
                                 offset_z,
                             )),
@@ -979,7 +1265,7 @@ fn build_tile_grid(
                             Transform::from_translation(Vec3::new(
                                 offset_x,
                                 obstacle_y,
-                                offset_z - pos_offset,
+                                offset_z - pos_offset_z,
                             )),
                         ),
                     ])
@@ -992,9 +1278,9 @@ fn build_tile_grid(
                     // - 1 larger cuboid on the bottom side, spanning from the left to the above
                     //   cuboid
 
-                    let cuboid_top_left = Cuboid::new(base_dim, obstacle_height, base_dim);
-                    let cuboid_right = Cuboid::new(base_dim, obstacle_height, tile_size);
-                    let cuboid_bottom = Cuboid::new(tile_size, obstacle_height, base_dim);
+                    let cuboid_top_left = Cuboid::new(base_dim_x, obstacle_height, base_dim_z);
+                    let cuboid_right = Cuboid::new(base_dim_x, obstacle_height, tile_height);
+                    let cuboid_bottom = Cuboid::new(tile_width, obstacle_height, base_dim_z);
 
                     Some(vec![
                         (
@@ -1002,9 +1288,9 @@ fn build_tile_grid(
                             cuboid_top_left,
                             // top left cube transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - pos_offset,
+                                offset_x - pos_offset_x,
                                 obstacle_y,
-                                offset_z + pos_offset,
+                                offset_z + pos_offset_z,
                             )),
                         ),
                         (
@@ -1012,7 +1298,7 @@ fn build_tile_grid(
                             cuboid_right,
                             // right side transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + pos_offset,
+                                offset_x + pos_offset_x,
                                 obstacle_y,
                                 offset_z,
                             )),
@@ -1024,7 +1310,7 @@ fn build_tile_grid(
                             Transform::from_translation(Vec3::new(
                                 offset_x,
                                 obstacle_y,
-                                offset_z - pos_offset,
+                                offset_z - pos_offset_z,
                             )),
                         ),
                     ])
@@ -1034,8 +1320,8 @@ fn build_tile_grid(
                     // - 2 equal-sized cubes, one in each bottom corner
                     // - 1 larger cuboid in the top center, spanning the entire width of the tile
 
-                    let cube = Cuboid::new(base_dim, obstacle_height, base_dim);
-                    let top = Cuboid::new(tile_size, obstacle_height, base_dim);
+                    let cube = Cuboid::new(base_dim_x, obstacle_height, base_dim_z);
+                    let top = Cuboid::new(tile_width, obstacle_height, base_dim_z);
 
                     Some(vec![
                         (
@@ -1043,9 +1329,9 @@ fn build_tile_grid(
                             cube,
                             // bottom left cube transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - pos_offset,
+                                offset_x - pos_offset_x,
                                 obstacle_y,
-                                offset_z - pos_offset,
+                                offset_z - pos_offset_z,
                             )),
                         ),
                         (
@@ -1053,9 +1339,9 @@ fn build_tile_grid(
                             cube,
                             // bottom right cube transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + pos_offset,
+                                offset_x + pos_offset_x,
                                 obstacle_y,
-                                offset_z - pos_offset,
+                                offset_z - pos_offset_z,
                             )),
                         ),
                         (
@@ -1065,7 +1351,7 @@ fn build_tile_grid(
                             Transform::from_translation(Vec3::new(
                                 offset_x,
                                 obstacle_y,
-                                offset_z + pos_offset,
+                                offset_z + pos_offset_z,
                             )),
                         ),
                     ])
@@ -1075,8 +1361,8 @@ fn build_tile_grid(
                     // - 2 equal-sized cubes, one in each top corner
                     // - 1 larger cuboid in the bottom center, spanning the entire width of the tile
 
-                    let cube = Cuboid::new(base_dim, obstacle_height, base_dim);
-                    let bottom = Cuboid::new(tile_size, obstacle_height, base_dim);
+                    let cube = Cuboid::new(base_dim_x, obstacle_height, base_dim_z);
+                    let bottom = Cuboid::new(tile_width, obstacle_height, base_dim_z);
 
                     Some(vec![
                         (
@@ -1084,9 +1370,9 @@ fn build_tile_grid(
                             cube,
                             // top left cube transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - pos_offset,
+                                offset_x - pos_offset_x,
                                 obstacle_y,
-                                offset_z + pos_offset,
+                                offset_z + pos_offset_z,
                             )),
                         ),
                         (
@@ -1094,9 +1380,9 @@ fn build_tile_grid(
                             cube,
                             // top right cube transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + pos_offset,
+                                offset_x + pos_offset_x,
                                 obstacle_y,
-                                offset_z + pos_offset,
+                                offset_z + pos_offset_z,
                             )),
                         ),
                         (
@@ -1106,7 +1392,7 @@ fn build_tile_grid(
                             Transform::from_translation(Vec3::new(
                                 offset_x,
                                 obstacle_y,
-                                offset_z - pos_offset,
+                                offset_z - pos_offset_z,
                             )),
                         ),
                     ])
@@ -1116,8 +1402,8 @@ fn build_tile_grid(
                     // - 2 equal-sized cubes, one in each right corner
                     // - 1 larger cuboid in the left center, spanning the entire height of the tile
 
-                    let cube = Cuboid::new(base_dim, obstacle_height, base_dim);
-                    let left = Cuboid::new(base_dim, obstacle_height, tile_size);
+                    let cube = Cuboid::new(base_dim_x, obstacle_height, base_dim_z);
+                    let left = Cuboid::new(base_dim_x, obstacle_height, tile_height);
 
                     Some(vec![
                         (
@@ -1125,9 +1411,9 @@ fn build_tile_grid(
                             cube,
                             // top right cube transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + pos_offset,
+                                offset_x + pos_offset_x,
                                 obstacle_y,
-                                offset_z - pos_offset,
+                                offset_z - pos_offset_z,
                             )),
                         ),
                         (
@@ -1135,9 +1421,9 @@ fn build_tile_grid(
                             cube,
                             // bottom right cube transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + pos_offset,
+                                offset_x + pos_offset_x,
                                 obstacle_y,
-                                offset_z + pos_offset,
+                                offset_z + pos_offset_z,
                             )),
                         ),
                         (
@@ -1145,7 +1431,7 @@ fn build_tile_grid(
                             left,
                             // left center cuboid transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - pos_offset,
+                                offset_x - pos_offset_x,
                                 obstacle_y,
                                 offset_z,
                             )),
@@ -1157,8 +1443,8 @@ fn build_tile_grid(
                     // - 2 equal-sized cubes, one in each left corner
                     // - 1 larger cuboid in the right center, spanning the entire height of the tile
 
-                    let cube = Cuboid::new(base_dim, obstacle_height, base_dim);
-                    let right = Cuboid::new(base_dim, obstacle_height, tile_size);
+                    let cube = Cuboid::new(base_dim_x, obstacle_height, base_dim_z);
+                    let right = Cuboid::new(base_dim_x, obstacle_height, tile_height);
 
                     Some(vec![
                         (
@@ -1166,9 +1452,9 @@ fn build_tile_grid(
                             cube,
                             // top left cube transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - pos_offset,
+                                offset_x - pos_offset_x,
                                 obstacle_y,
-                                offset_z - pos_offset,
+                                offset_z - pos_offset_z,
                             )),
                         ),
                         (
@@ -1176,9 +1462,9 @@ fn build_tile_grid(
                             cube,
                             // bottom left cube transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - pos_offset,
+                                offset_x - pos_offset_x,
                                 obstacle_y,
-                                offset_z + pos_offset,
+                                offset_z + pos_offset_z,
                             )),
                         ),
                         (
@@ -1186,7 +1472,7 @@ fn build_tile_grid(
                             right,
                             // right center cuboid transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + pos_offset,
+                                offset_x + pos_offset_x,
                                 obstacle_y,
                                 offset_z,
                             )),
@@ -1197,7 +1483,7 @@ fn build_tile_grid(
                     // 4-way intersection
                     // - 4 equal-sized cubes, one in each corner
 
-                    let cube = Cuboid::new(base_dim, obstacle_height, base_dim);
+                    let cube = Cuboid::new(base_dim_x, obstacle_height, base_dim_z);
 
                     Some(vec![
                         (
@@ -1205,9 +1491,9 @@ fn build_tile_grid(
                             cube,
                             // top left transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - pos_offset,
+                                offset_x - pos_offset_x,
                                 obstacle_y,
-                                offset_z - pos_offset,
+                                offset_z - pos_offset_z,
                             )),
                         ),
                         (
@@ -1215,9 +1501,9 @@ fn build_tile_grid(
                             cube,
                             // top right transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + pos_offset,
+                                offset_x + pos_offset_x,
                                 obstacle_y,
-                                offset_z - pos_offset,
+                                offset_z - pos_offset_z,
                             )),
                         ),
                         (
@@ -1225,9 +1511,9 @@ fn build_tile_grid(
                             cube,
                             // bottom left transform
                             Transform::from_translation(Vec3::new(
-                                offset_x - pos_offset,
+                                offset_x - pos_offset_x,
                                 obstacle_y,
-                                offset_z + pos_offset,
+                                offset_z + pos_offset_z,
                             )),
                         ),
                         (
@@ -1235,9 +1521,9 @@ fn build_tile_grid(
                             cube,
                             // bottom right transform
                             Transform::from_translation(Vec3::new(
-                                offset_x + pos_offset,
+                                offset_x + pos_offset_x,
                                 obstacle_y,
-                                offset_z + pos_offset,
+                                offset_z + pos_offset_z,
                             )),
                         ),
                     ])
@@ -1246,7 +1532,7 @@ fn build_tile_grid(
                     // Filled space
                     // - 1 larger cuboid, spanning the entire tile
 
-                    let cuboid = Cuboid::new(tile_size, obstacle_height, tile_size);
+                    let cuboid = Cuboid::new(tile_width, obstacle_height, tile_height);
 
                     Some(vec![(
                         cuboid,
@@ -1255,12 +1541,20 @@ fn build_tile_grid(
                 }
                 _ => None,
             } {
+                if config.visualisation.merge_static_map {
+                    chunks
+                        .entry((x / MAP_CHUNK_SIZE, y / MAP_CHUNK_SIZE))
+                        .or_default()
+                        .extend(obstacle_information);
+                    continue;
+                }
+
                 for (cuboid, transform) in &obstacle_information {
                     let entity = commands
                         .spawn((
                             PbrBundle {
-                                mesh: meshes.add(*cuboid),
-                                transform: *transform,
+                                mesh: meshes.cuboid.clone(),
+                                transform: transform.with_scale(2.0 * cuboid.half_size),
                                 material: materials.obstacle.clone(),
                                 visibility: if config.visualisation.draw.generated_map {
                                     Visibility::Visible
@@ -1277,8 +1571,10 @@ fn build_tile_grid(
                         ))
                         .id();
 
-                    colliders.push(
-                        Some(entity),
+                    add_collider(
+                        &mut commands,
+                        &mut colliders,
+                        entity,
                         Isometry2::new(
                             Vector2::new(transform.translation.x, transform.translation.z),
                             na::zero(),
@@ -1289,9 +1585,163 @@ fn build_tile_grid(
             }
         }
     }
+
+    for walls in chunks.into_values() {
+        let entity = commands
+            .spawn((
+                PbrBundle {
+                    mesh: mesh_assets.add(merge_cuboids(&walls)),
+                    material: materials.obstacle.clone(),
+                    visibility: if config.visualisation.draw.generated_map {
+                        Visibility::Visible
+                    } else {
+                        Visibility::Hidden
+                    },
+                    ..Default::default()
+                },
+                ObstacleMarker,
+            ))
+            .id();
+
+        // one collider per wall, but a single compound shape for the merged
+        // entity
+        let parts = walls
+            .into_iter()
+            .map(|(cuboid, transform)| {
+                let isometry = Isometry2::new(
+                    Vector2::new(transform.translation.x, transform.translation.z),
+                    na::zero(),
+                );
+                let shape: Arc<dyn shape::Shape> = Arc::new(Into::<shape::Cuboid>::into(cuboid));
+                colliders.push(Some(entity), isometry, Arc::clone(&shape));
+                (isometry, shape::SharedShape(shape))
+            })
+            .collect::<Vec<_>>();
+        commands.entity(entity).insert(ObstacleShape::new(
+            Isometry2::identity(),
+            Arc::new(shape::Compound::new(parts)),
+        ));
+    }
+
+    if env_config.add_boundary() {
+        // walls are placed along the inside of the edge of the map, where the
+        // SDF has them as well
+        let thickness = env_config.boundary_thickness();
+        let width = tile_grid.ncols() as f32 * tile_width;
+        let height = tile_grid.nrows() as f32 * tile_height;
+
+        let horizontal = Cuboid::new(width, obstacle_height, thickness);
+        let vertical = Cuboid::new(
+            thickness,
+            obstacle_height,
+            2.0f32.mul_add(-thickness, height),
+        );
+        let walls = [
+            (
+                horizontal,
+                Vec3::new(0.0, obstacle_y, -(height - thickness) / 2.0),
+            ),
+            (
+                horizontal,
+                Vec3::new(0.0, obstacle_y, (height - thickness) / 2.0),
+            ),
+            (
+                vertical,
+                Vec3::new(-(width - thickness) / 2.0, obstacle_y, 0.0),
+            ),
+            (
+                vertical,
+                Vec3::new((width - thickness) / 2.0, obstacle_y, 0.0),
+            ),
+        ];
+
+        for (cuboid, translation) in walls {
+            let entity = commands
+                .spawn((
+                    PbrBundle {
+                        mesh: meshes.cuboid.clone(),
+                        transform: Transform::from_translation(translation)
+                            .with_scale(2.0 * cuboid.half_size),
+                        material: materials.obstacle.clone(),
+                        visibility: if config.visualisation.draw.generated_map {
+                            Visibility::Visible
+                        } else {
+                            Visibility::Hidden
+                        },
+                        ..Default::default()
+                    },
+                    ObstacleMarker,
+                ))
+                .id();
+
+            add_collider(
+                &mut commands,
+                &mut colliders,
+                entity,
+                Isometry2::new(Vector2::new(translation.x, translation.z), na::zero()),
+                Arc::new(Into::<shape::Cuboid>::into(cuboid)),
+            );
+        }
+    }
+
     colliders
 }
 
+/// Number of tiles along each side of a chunk, when the walls of the tile grid
+/// are merged into one mesh per chunk
+const MAP_CHUNK_SIZE: usize = 8;
+
+/// Merge the meshes of `cuboids` into a single mesh, with the vertices
+/// transformed into the space of the chunk
+#[allow(clippy::cast_possible_truncation)]
+fn merge_cuboids(cuboids: &[(Cuboid, Transform)]) -> Mesh {
+    let mut positions: Vec<[f32; 3]> = vec![];
+    let mut normals: Vec<[f32; 3]> = vec![];
+    let mut uvs: Vec<[f32; 2]> = vec![];
+    let mut indices: Vec<u32> = vec![];
+
+    for (cuboid, transform) in cuboids {
+        let mesh = Mesh::from(*cuboid);
+        let offset = positions.len() as u32;
+
+        if let Some(VertexAttributeValues::Float32x3(vertices)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        {
+            positions.extend(
+                vertices
+                    .iter()
+                    .map(|&vertex| transform.transform_point(Vec3::from(vertex)).to_array()),
+            );
+        }
+        if let Some(VertexAttributeValues::Float32x3(vertex_normals)) =
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+        {
+            normals.extend(
+                vertex_normals
+                    .iter()
+                    .map(|&normal| (transform.rotation * Vec3::from(normal)).to_array()),
+            );
+        }
+        if let Some(VertexAttributeValues::Float32x2(vertex_uvs)) =
+            mesh.attribute(Mesh::ATTRIBUTE_UV_0)
+        {
+            uvs.extend_from_slice(vertex_uvs);
+        }
+        if let Some(mesh_indices) = mesh.indices() {
+            indices.extend(mesh_indices.iter().map(|index| offset + index as u32));
+        }
+    }
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+    .with_inserted_indices(Indices::U32(indices))
+}
+
 /// **Bevy** [`Update`] _system_.
 /// Shows or hides the generated map based on event from [`DrawSettingsEvent`].
 /// - If `DrawSettingsEvent` is `ShowGeneratedMap`, all generated map entities'
@@ -3,6 +3,7 @@ pub mod cursor;
 pub mod follow_cameras;
 pub mod map;
 pub mod map_generator;
+pub mod remove_obstacle;
 
 use camera::CameraPlugin;
 pub use camera::MainCamera;
@@ -10,7 +11,8 @@ use cursor::CursorToGroundPlugin;
 pub use follow_cameras::FollowCameraMe;
 use follow_cameras::FollowCamerasPlugin;
 use map::MapPlugin;
-pub use map_generator::ObstacleMarker;
+pub use map_generator::{ObstacleMarker, ObstacleShape};
+use remove_obstacle::RemoveObstaclePlugin;
 
 use self::map_generator::GenMapPlugin;
 // pub use self::map_generator::TileCoordinates;
@@ -26,6 +28,7 @@ impl bevy::app::Plugin for EnvironmentPlugin {
             MapPlugin,
             CursorToGroundPlugin,
             GenMapPlugin,
+            RemoveObstaclePlugin,
         ));
     }
 }
@@ -0,0 +1,98 @@
+//! Remove placed obstacles at runtime, by shift-clicking them.
+//!
+//! The obstacle is removed from the [`Environment`], which rebuilds the map
+//! and its colliders. The SDF is regenerated, and handed to the obstacle
+//! factors of every robot, so robots blocked by the obstacle can recover.
+//! Obstacle factors measuring the obstacles analytically are handed the
+//! updated environment instead.
+//! Robots with a [`Perception`] keep their own map, and notice the obstacle
+//! is gone once they see where it was.
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy_notify::ToastEvent;
+use gbp_environment::Environment;
+
+use super::map_generator::{events::ObstacleClickedOn, ObstacleIndex};
+use crate::{
+    factorgraph::{factor::obstacle::ObstacleSource, prelude::FactorGraph},
+    planner::sensing::Perception,
+    simulation_loader::{self, AnalyticSdf, Sdf},
+};
+
+pub struct RemoveObstaclePlugin;
+
+impl Plugin for RemoveObstaclePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            remove_clicked_obstacle.run_if(on_event::<ObstacleClickedOn>()),
+        );
+    }
+}
+
+fn remove_clicked_obstacle(
+    mut evr_obstacle_clicked_on: EventReader<ObstacleClickedOn>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    obstacles: Query<(Option<&ObstacleIndex>, Option<&Parent>)>,
+    mut environment: ResMut<Environment>,
+    mut sdf: ResMut<Sdf>,
+    mut analytic_sdf: ResMut<AnalyticSdf>,
+    mut robots: Query<&mut FactorGraph, Without<Perception>>,
+    mut evw_toast: EventWriter<ToastEvent>,
+) {
+    if !keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        evr_obstacle_clicked_on.clear();
+        return;
+    }
+
+    // the parts of a composite obstacle are children of the entity with the index
+    let index_of = |entity| {
+        let (index, parent) = obstacles.get(entity).ok()?;
+        index
+            .or_else(|| obstacles.get(parent?.get()).ok()?.0)
+            .copied()
+    };
+
+    // removing an obstacle shifts the indices of the ones after it, so only the
+    // first click is handled, the map is rebuilt before the next
+    let Some(entity) = evr_obstacle_clicked_on.read().next().map(|event| event.0) else {
+        return;
+    };
+    evr_obstacle_clicked_on.clear();
+
+    let Some(ObstacleIndex(index)) = index_of(entity) else {
+        evw_toast.send(ToastEvent::warning(
+            "only placed obstacles can be removed, not tiles",
+        ));
+        return;
+    };
+
+    let Some(obstacle) = environment.obstacles.remove(index) else {
+        return;
+    };
+    let name = obstacle.name.unwrap_or_else(|| format!("obstacle {index}"));
+
+    *analytic_sdf = AnalyticSdf::new(&Arc::new(environment.clone()));
+    match simulation_loader::generate_sdf(&environment) {
+        Ok(new_sdf) => {
+            *sdf = new_sdf;
+            let source = analytic_sdf.0.clone().map_or_else(
+                || ObstacleSource::Image(Arc::clone(&sdf.0)),
+                ObstacleSource::Shapes,
+            );
+            for mut factorgraph in &mut robots {
+                factorgraph.modify_obstacle_factors(|factor| factor.set_source(source.clone()));
+            }
+        }
+        Err(err) => {
+            error!("failed to regenerate the SDF after removing {name}: {err}");
+            evw_toast.send(ToastEvent::error(format!(
+                "failed to regenerate the SDF: {err}"
+            )));
+        }
+    }
+
+    info!("removed {name}");
+    evw_toast.send(ToastEvent::info(format!("removed {name}")));
+}
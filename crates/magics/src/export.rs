@@ -1,3 +1,7 @@
+pub mod recorder;
+pub mod sdformat;
+pub mod trajectory;
+
 use std::{collections::HashMap, io::Write, time::Duration};
 
 use bevy::{
@@ -21,6 +25,7 @@ pub struct ExportPlugin;
 impl Plugin for ExportPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<events::Export>()
+            .add_event::<events::ExportSdformat>()
             .add_event::<events::TakeSnapshotOfRobot>()
             .add_event::<events::OpenLatestExport>()
             .init_resource::<resources::SnapshottedRobots>()
@@ -33,6 +38,8 @@ impl Plugin for ExportPlugin {
                 Update,
                 (
                     export.run_if(resource_exists::<gbp_global_planner::Colliders>),
+                    export_sdformat.run_if(resource_exists::<gbp_global_planner::Colliders>),
+                    send_export_sdformat_event.run_if(input_just_pressed(KeyCode::F3)),
                     open_latest_export,
                     send_default_export_event.run_if(
                         input_just_pressed(KeyCode::F7)
@@ -47,20 +54,25 @@ impl Plugin for ExportPlugin {
     }
 }
 
-mod resources {
+pub(crate) mod resources {
     use super::*;
 
     #[derive(Resource, Deref, DerefMut, Default)]
     pub(super) struct SnapshottedRobots(HashMap<Entity, RobotData>);
 
+    /// The file the latest export was written to
     #[derive(Resource, Deref, DerefMut, Default)]
-    pub(super) struct LatestExport(pub Option<std::path::PathBuf>);
+    pub(crate) struct LatestExport(pub Option<std::path::PathBuf>);
 }
 
 fn send_default_export_event(mut evw_export: EventWriter<events::Export>) {
     evw_export.send(events::Export::default());
 }
 
+fn send_export_sdformat_event(mut evw_export_sdformat: EventWriter<events::ExportSdformat>) {
+    evw_export_sdformat.send(events::ExportSdformat::default());
+}
+
 #[derive(Debug, Clone, Default)]
 pub enum ExportSaveLocation {
     At(std::path::PathBuf),
@@ -82,6 +94,53 @@ pub mod events {
 
     #[derive(Event)]
     pub struct TakeSnapshotOfRobot(pub Entity);
+
+    /// Export the environment as an SDFormat world, see [`super::sdformat`]
+    #[derive(Event, Default)]
+    pub struct ExportSdformat {
+        pub save_at_location: ExportSaveLocation,
+    }
+}
+
+fn export_sdformat(
+    mut evr_export_sdformat: EventReader<events::ExportSdformat>,
+    mut evw_toast: EventWriter<bevy_notify::ToastEvent>,
+    mut latest_export: ResMut<resources::LatestExport>,
+    colliders: Res<gbp_global_planner::Colliders>,
+    env_config: Res<gbp_environment::Environment>,
+    sim_manager: Res<crate::simulation_loader::SimulationManager>,
+) {
+    for event in evr_export_sdformat.read() {
+        let name = sim_manager.active_name().unwrap_or_default();
+        let world = sdformat::world(name, &colliders, env_config.obstacle_height());
+
+        let dirname = match event.save_at_location {
+            ExportSaveLocation::Cwd if cfg!(not(target_arch = "wasm32")) => {
+                std::env::current_dir().expect("current directory exists")
+            }
+            ExportSaveLocation::Cwd => {
+                evw_toast.send(bevy_notify::ToastEvent::warning("Not supported on wasm32"));
+                continue;
+            }
+            ExportSaveLocation::At(ref path) => path.clone(),
+        };
+        let output_filepath = dirname.join(format!("{}.world", name.to_lowercase()));
+
+        if let Err(err) = std::fs::write(&output_filepath, world) {
+            let err_msg = format!("Failed to write {}: {}", output_filepath.display(), err);
+            error!(err_msg);
+            evw_toast.send(bevy_notify::ToastEvent::error(err_msg));
+            continue;
+        }
+
+        let message = format!(
+            "Environment exported as SDFormat world to '{}'",
+            output_filepath.display()
+        );
+        info!(message);
+        evw_toast.send(bevy_notify::ToastEvent::success(message));
+        latest_export.0 = Some(output_filepath);
+    }
 }
 
 fn open_latest_export(
@@ -120,6 +179,8 @@ pub struct RobotData {
     // route: RouteData,
     mission: MissionData,
     planning_strategy: PlanningStrategy,
+    /// See [`planner::robot::Priority`]
+    priority: u8,
     color: String,
 }
 
@@ -255,6 +316,8 @@ struct ExportData {
     robots: HashMap<Entity, RobotData>,
     prng_seed: u64,
     config: gbp_config::Config,
+    /// The inputs of the run, see [`crate::manifest`]
+    manifest: Option<crate::manifest::Manifest>,
     // obstacles: Vec<Obstacle>,
     obstacles: HashMap<Entity, Obstacle>,
     collisions: CollisionData,
@@ -264,8 +327,18 @@ struct ExportData {
 #[derive(serde::Serialize)]
 #[serde(tag = "type")]
 enum Obstacle {
-    Circle { center: [f32; 2], radius: f32 },
-    Polygon { vertices: Vec<[f32; 2]> },
+    Circle {
+        center: [f32; 2],
+        radius: f32,
+    },
+    Polygon {
+        vertices: Vec<[f32; 2]>,
+    },
+    Capsule {
+        start:  [f32; 2],
+        end:    [f32; 2],
+        radius: f32,
+    },
 }
 
 #[derive(serde::Serialize)]
@@ -294,6 +367,7 @@ fn export(
         &planner::robot::Mission,
         &PlanningStrategy,
         &crate::theme::ColorAssociation,
+        Option<&planner::robot::Priority>,
         // &ColorAssociation,
         // &ColorAssociation,
     )>,
@@ -306,6 +380,7 @@ fn export(
     time_fixed: Res<Time<Fixed>>,
     catppuccin: Res<crate::theme::CatppuccinTheme>,
     obstacles: Res<gbp_global_planner::Colliders>,
+    manifest: Res<crate::manifest::CurrentManifest>,
 ) {
     // schema:
     //
@@ -366,6 +441,7 @@ fn export(
             mission,
             planning_strategy,
             color_assoc,
+            priority,
         ) in q_robots.iter()
         {
             if robot_snapshots.contains_key(&robot_entity) {
@@ -440,6 +516,7 @@ fn export(
                     },
                 },
                 planning_strategy: *planning_strategy,
+                priority: priority.map_or(0, |priority| priority.0),
                 color,
             };
 
@@ -512,6 +589,15 @@ fn export(
                             radius: circle.radius,
                             center: [ob.isometry.translation.x, ob.isometry.translation.y],
                         }
+                    } else if let Some(capsule) = ob.shape.downcast_ref::<parry2d::shape::Capsule>()
+                    {
+                        let x = ob.isometry.translation.x;
+                        let y = ob.isometry.translation.y;
+                        Obstacle::Capsule {
+                            start:  [capsule.segment.a.x + x, capsule.segment.a.y + y],
+                            end:    [capsule.segment.b.x + x, capsule.segment.b.y + y],
+                            radius: capsule.radius,
+                        }
                     } else if let Some(convex_polygon) =
                         ob.shape.downcast_ref::<parry2d::shape::ConvexPolygon>()
                     {
@@ -566,6 +652,7 @@ fn export(
             robots: robot_snapshots.drain().collect(),
             prng_seed: config.simulation.prng_seed,
             config: config.clone(),
+            manifest: (*manifest).clone(),
             obstacles,
             collisions,
             goal_areas,
@@ -624,9 +711,52 @@ fn export(
         }
 
         latest_export.0 = Some(output_filepath);
+
+        if config.export.trajectories {
+            let directory = std::path::Path::new(&config.export.directory);
+            let basename = format!("{}{}", prefix, basename_postfix);
+            if let Err(err) = export_trajectories(&export_data.robots, directory, &basename) {
+                let err_msg = format!(
+                    "Failed to export trajectories to '{}': {}",
+                    directory.display(),
+                    err
+                );
+                error!(err_msg);
+                evw_toast.send(bevy_notify::ToastEvent::error(err_msg));
+            }
+        }
     }
 }
 
+/// Write the trajectories of `robots` to `<basename>.csv` and
+/// `<basename>.geojson` in `directory`, see [`trajectory`]
+fn export_trajectories(
+    robots: &HashMap<Entity, RobotData>,
+    directory: &std::path::Path,
+    basename: &str,
+) -> std::io::Result<()> {
+    let trajectories: trajectory::Trajectories = robots
+        .iter()
+        .map(|(entity, robot)| {
+            (
+                entity.to_bits(),
+                robot.velocities.iter().map(Into::into).collect(),
+            )
+        })
+        .collect();
+
+    std::fs::create_dir_all(directory)?;
+    std::fs::write(
+        directory.join(format!("{basename}.csv")),
+        trajectory::to_csv(&trajectories),
+    )?;
+    let geojson = serde_json::to_string_pretty(&trajectory::to_geojson(&trajectories))?;
+    std::fs::write(directory.join(format!("{basename}.geojson")), geojson)?;
+
+    info!("Trajectories exported to '{}'", directory.display());
+    Ok(())
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ExportSavePostfix {
     Number,
@@ -661,6 +791,7 @@ fn take_snapshot_of_robot(
         &planner::robot::Mission,
         &PlanningStrategy,
         &crate::theme::ColorAssociation,
+        Option<&planner::robot::Priority>,
     )>,
 
     robot_collisions: &crate::planner::collisions::resources::RobotRobotCollisions,
@@ -669,8 +800,16 @@ fn take_snapshot_of_robot(
     time_fixed: &Time<Fixed>,
     catppuccin: &crate::theme::CatppuccinTheme,
 ) -> anyhow::Result<RobotData> {
-    let Ok((fgraph, positions, velocities, radius, mission, planning_strategy, color_assoc)) =
-        q_robots.get(robot_entity)
+    let Ok((
+        fgraph,
+        positions,
+        velocities,
+        radius,
+        mission,
+        planning_strategy,
+        color_assoc,
+        priority,
+    )) = q_robots.get(robot_entity)
     else {
         anyhow::bail!(
             "cannot take snapshot of non-existing robot {:?}",
@@ -718,6 +857,7 @@ fn take_snapshot_of_robot(
             },
         },
         planning_strategy: *planning_strategy,
+        priority: priority.map_or(0, |priority| priority.0),
         color,
         mission: MissionData {
             started_at:  mission.started_at(),
@@ -771,6 +911,7 @@ fn await_robot_snapshot_request(
         &planner::robot::Mission,
         &PlanningStrategy,
         &crate::theme::ColorAssociation,
+        Option<&planner::robot::Priority>,
     )>,
 
     robot_collisions: Res<crate::planner::collisions::resources::RobotRobotCollisions>,
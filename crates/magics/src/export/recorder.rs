@@ -0,0 +1,261 @@
+//! Record the state of every robot each timestep, for offline analysis of how
+//! well GBP converges, e.g. across seeds.
+//!
+//! Enabled with `config.export.recorder`. Every fixed timestep a
+//! [`Sample`] with the position, velocity and factorgraph energy of each robot
+//! is recorded, and when the simulation ends, is reloaded or another
+//! simulation is loaded, the samples are written as CSV or Parquet to
+//! `config.export.recorder.directory`. The CSV is written with
+//! [`trajectory::to_csv`], so robots are identified by the same id as in the
+//! trajectory export.
+use std::{io::Write, path::PathBuf};
+
+use bevy::{app::AppExit, prelude::*};
+use gbp_config::{Config, RecordingFormat};
+
+use super::trajectory::{self, Sample, Trajectories};
+use crate::{
+    factorgraph::prelude::FactorGraph,
+    simulation_loader::{EndSimulation, LoadSimulation, ReloadSimulation, SimulationManager},
+};
+
+pub struct TrajectoryRecorderPlugin;
+
+impl Plugin for TrajectoryRecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Recording>()
+            .add_systems(FixedPostUpdate, record.run_if(recorder_enabled))
+            .add_systems(
+                Update,
+                write_recording.run_if(
+                    on_event::<EndSimulation>()
+                        .or_else(on_event::<ReloadSimulation>())
+                        .or_else(on_event::<LoadSimulation>()),
+                ),
+            )
+            .add_systems(Last, write_recording.run_if(on_event::<AppExit>()));
+    }
+}
+
+#[inline]
+fn recorder_enabled(config: Res<Config>) -> bool {
+    config.export.recorder.enabled
+}
+
+/// **Bevy** [`Resource`] with the samples recorded since the recording was
+/// last written
+#[derive(Debug, Default, Resource)]
+struct Recording {
+    /// Name of the recorded simulation
+    simulation:   Option<String>,
+    trajectories: Trajectories,
+}
+
+/// Errors that can occur when writing a recording
+#[derive(Debug, thiserror::Error)]
+pub enum RecorderError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[cfg(feature = "parquet")]
+    #[error("Parquet error: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+}
+
+/// Write `trajectories` as a Parquet file with a single row group, and the
+/// same columns as [`trajectory::to_csv`] with the energy recorded
+///
+/// # Errors
+///
+/// If writing to `writer` fails
+#[cfg(feature = "parquet")]
+pub fn write_parquet(
+    trajectories: &Trajectories,
+    writer: impl Write + Send,
+) -> Result<(), RecorderError> {
+    use std::sync::Arc;
+
+    use parquet::{
+        data_type::{DataType, DoubleType, FloatType, Int64Type},
+        errors::ParquetError,
+        file::{
+            properties::WriterProperties,
+            writer::{SerializedFileWriter, SerializedRowGroupWriter},
+        },
+        schema::parser::parse_message_type,
+    };
+
+    fn write_column<T: DataType, W: Write + Send>(
+        row_group: &mut SerializedRowGroupWriter<'_, W>,
+        values: &[T::T],
+    ) -> Result<(), ParquetError> {
+        let mut column = row_group
+            .next_column()?
+            .ok_or_else(|| ParquetError::General("the schema has too few columns".into()))?;
+        column.typed::<T>().write_batch(values, None, None)?;
+        column.close()
+    }
+
+    let schema = parse_message_type(
+        "message recording {
+            REQUIRED INT64 robot;
+            REQUIRED DOUBLE time;
+            REQUIRED FLOAT x;
+            REQUIRED FLOAT y;
+            REQUIRED FLOAT vx;
+            REQUIRED FLOAT vy;
+            REQUIRED DOUBLE energy;
+        }",
+    )?;
+    let properties = WriterProperties::builder().build();
+    let mut file_writer =
+        SerializedFileWriter::new(writer, Arc::new(schema), Arc::new(properties))?;
+
+    let rows = || {
+        trajectories
+            .iter()
+            .flat_map(|(robot, samples)| samples.iter().map(move |sample| (*robot, sample)))
+    };
+    let mut row_group = file_writer.next_row_group()?;
+    #[allow(clippy::cast_possible_wrap)]
+    let robots = rows().map(|(robot, _)| robot as i64).collect::<Vec<_>>();
+    write_column::<Int64Type, _>(&mut row_group, &robots)?;
+    let column =
+        |value: fn(&Sample) -> f32| rows().map(|(_, sample)| value(sample)).collect::<Vec<_>>();
+    let time = rows().map(|(_, sample)| sample.time).collect::<Vec<_>>();
+    write_column::<DoubleType, _>(&mut row_group, &time)?;
+    write_column::<FloatType, _>(&mut row_group, &column(|sample| sample.position.x))?;
+    write_column::<FloatType, _>(&mut row_group, &column(|sample| sample.position.y))?;
+    write_column::<FloatType, _>(&mut row_group, &column(|sample| sample.velocity.x))?;
+    write_column::<FloatType, _>(&mut row_group, &column(|sample| sample.velocity.y))?;
+    let energy = rows()
+        .map(|(_, sample)| sample.energy.unwrap_or(f64::NAN))
+        .collect::<Vec<_>>();
+    write_column::<DoubleType, _>(&mut row_group, &energy)?;
+    row_group.close()?;
+
+    file_writer.close()?;
+    Ok(())
+}
+
+/// Write `trajectories` to
+/// `<directory>/<simulation>-<unix timestamp>.<extension>`, returning the path
+/// written to
+fn write_trajectories(
+    trajectories: &Trajectories,
+    simulation: &str,
+    directory: &std::path::Path,
+    format: RecordingFormat,
+) -> Result<PathBuf, RecorderError> {
+    let format = match format {
+        RecordingFormat::Parquet if !cfg!(feature = "parquet") => {
+            warn!("magics was built without the `parquet` feature, writing the recording as CSV");
+            RecordingFormat::Csv
+        }
+        format => format,
+    };
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    std::fs::create_dir_all(directory)?;
+    let path = directory.join(format!("{simulation}-{timestamp}.{}", format.extension()));
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(&path)?);
+
+    match format {
+        RecordingFormat::Csv => writer.write_all(trajectory::to_csv(trajectories).as_bytes())?,
+        RecordingFormat::Parquet => {
+            #[cfg(feature = "parquet")]
+            write_parquet(trajectories, writer)?;
+        }
+    }
+
+    Ok(path)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn record(
+    mut recording: ResMut<Recording>,
+    robots: Query<(Entity, &FactorGraph, &Transform)>,
+    simulation_manager: Res<SimulationManager>,
+    time_fixed: Res<Time<Fixed>>,
+) {
+    if recording.simulation.is_none() {
+        recording.simulation = simulation_manager.active_name().map(ToString::to_string);
+    }
+
+    let time = time_fixed.elapsed_seconds_f64();
+    for (entity, factorgraph, transform) in &robots {
+        let Some((_, current)) = factorgraph.nth_variable(0) else {
+            continue;
+        };
+        let mean = &current.belief.mean;
+        recording
+            .trajectories
+            .entry(entity.to_bits())
+            .or_default()
+            .push(Sample {
+                time,
+                position: Vec2::new(transform.translation.x, transform.translation.z),
+                velocity: Vec2::new(mean[2] as f32, mean[3] as f32),
+                energy: Some(factorgraph.solve_report().energy),
+            });
+    }
+}
+
+/// Write the rows recorded so far, and start a new recording
+fn write_recording(
+    mut recording: ResMut<Recording>,
+    mut evw_toast: EventWriter<bevy_notify::ToastEvent>,
+    config: Res<Config>,
+) {
+    let Recording {
+        simulation,
+        trajectories,
+    } = std::mem::take(&mut *recording);
+    if trajectories.is_empty() {
+        return;
+    }
+
+    let simulation = simulation.unwrap_or_else(|| "recording".to_string());
+    let directory = std::path::Path::new(&config.export.recorder.directory);
+    let format = config.export.recorder.format;
+    match write_trajectories(&trajectories, &simulation, directory, format) {
+        Ok(path) => info!(
+            "wrote the recorded trajectories of {} robots to '{}'",
+            trajectories.len(),
+            path.display()
+        ),
+        Err(err) => {
+            let message = format!(
+                "failed to write recording to '{}': {}",
+                directory.display(),
+                err
+            );
+            error!(message);
+            evw_toast.send(bevy_notify::ToastEvent::error(message));
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_recording_has_a_row_per_sample() {
+        let trajectories = Trajectories::from([(3, vec![Sample {
+            time:     0.5,
+            position: Vec2::new(1.0, -2.0),
+            velocity: Vec2::new(0.5, 0.0),
+            energy:   Some(4.25),
+        }])]);
+        let directory = std::env::temp_dir().join("magics-recorder-test");
+        let path =
+            write_trajectories(&trajectories, "test", &directory, RecordingFormat::Csv).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(csv, "robot,time,x,y,vx,vy,energy\n3,0.5,1,-2,0.5,0,4.25\n");
+    }
+}
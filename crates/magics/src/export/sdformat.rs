@@ -0,0 +1,275 @@
+//! Export the colliders of an environment as an [SDFormat] world, so a
+//! scenario can be replayed in Gazebo with physics for validation.
+//!
+//! The colliders are used rather than the [`gbp_environment::Environment`]
+//! description directly, as they are what the planner avoids. Every collider
+//! becomes a static model, extruded from the ground to the obstacle height.
+//! The planner plane is the `xy` plane of the world, with `z` pointing up.
+//!
+//! [SDFormat]: http://sdformat.org/spec
+use std::fmt::Write;
+
+use gbp_global_planner::{Collider, Colliders};
+use parry2d::{
+    bounding_volume::{Aabb, BoundingVolume},
+    na::Isometry2,
+    shape,
+};
+
+/// Version of the SDFormat specification the exported worlds conform to
+const SDF_VERSION: &str = "1.9";
+
+/// A single piece of geometry of a model, with a pose relative to the model
+struct Part {
+    isometry: Isometry2<f32>,
+    /// Height of the origin of the geometry above the ground
+    z: f32,
+    /// The `<geometry>` element of the part
+    geometry: String,
+}
+
+/// Convert `colliders` into an SDFormat world named `name`. Every obstacle is
+/// `height` meters tall.
+#[must_use]
+pub fn world(name: &str, colliders: &Colliders, height: f32) -> String {
+    let height = height.abs();
+    let mut sdf = String::new();
+
+    let _ = writeln!(sdf, r#"<?xml version="1.0" ?>"#);
+    let _ = writeln!(sdf, r#"<sdf version="{SDF_VERSION}">"#);
+    let _ = writeln!(sdf, r#"  <world name="{}">"#, escape(name));
+    sdf.push_str(SUN);
+
+    let bounds = colliders
+        .iter()
+        .map(Collider::aabb)
+        .reduce(|a, b| a.merged(&b));
+    sdf.push_str(&ground_plane(bounds));
+
+    for (i, collider) in colliders.iter().enumerate() {
+        let mut parts = vec![];
+        collect_parts(
+            collider.shape.as_ref(),
+            &collider.isometry,
+            height,
+            &mut parts,
+        );
+        if parts.is_empty() {
+            continue;
+        }
+        sdf.push_str(&model(&format!("obstacle_{i}"), &parts));
+    }
+
+    let _ = writeln!(sdf, "  </world>");
+    let _ = writeln!(sdf, "</sdf>");
+    sdf
+}
+
+/// Decompose `shape` into parts, that SDFormat has a geometry for
+fn collect_parts(
+    shape: &dyn shape::Shape,
+    isometry: &Isometry2<f32>,
+    height: f32,
+    parts: &mut Vec<Part>,
+) {
+    let centered = |geometry: String| Part {
+        isometry: *isometry,
+        z: height / 2.0,
+        geometry,
+    };
+
+    if let Some(cuboid) = shape.as_cuboid() {
+        let size = cuboid.half_extents * 2.0;
+        parts.push(centered(format!(
+            "<box><size>{} {} {height}</size></box>",
+            size.x, size.y
+        )));
+    } else if let Some(ball) = shape.as_ball() {
+        parts.push(centered(cylinder(ball.radius, height)));
+    } else if let Some(capsule) = shape.as_capsule() {
+        // A box spanning the segment, capped by a cylinder at either end
+        let [a, b] = [capsule.segment.a, capsule.segment.b];
+        let direction = b - a;
+        let length = direction.norm();
+        let center = Isometry2::new(
+            a.coords.lerp(&b.coords, 0.5),
+            direction.y.atan2(direction.x),
+        );
+        parts.push(Part {
+            isometry: isometry * center,
+            z: height / 2.0,
+            geometry: format!(
+                "<box><size>{length} {} {height}</size></box>",
+                capsule.radius * 2.0
+            ),
+        });
+        for end in [a, b] {
+            parts.push(Part {
+                isometry: isometry * Isometry2::translation(end.x, end.y),
+                z: height / 2.0,
+                geometry: cylinder(capsule.radius, height),
+            });
+        }
+    } else if let Some(triangle) = shape.as_triangle() {
+        parts.push(polyline(isometry, triangle.vertices(), height));
+    } else if let Some(polygon) = shape.as_convex_polygon() {
+        parts.push(polyline(isometry, polygon.points(), height));
+    } else if let Some(compound) = shape.as_compound() {
+        for (sub_isometry, sub_shape) in compound.shapes() {
+            collect_parts(
+                sub_shape.as_ref(),
+                &(isometry * sub_isometry),
+                height,
+                parts,
+            );
+        }
+    } else {
+        // Fall back to the bounding box of shapes without an SDFormat
+        // counterpart
+        let aabb = shape.compute_aabb(isometry);
+        let center = aabb.center();
+        let size = aabb.extents();
+        parts.push(Part {
+            isometry: Isometry2::translation(center.x, center.y),
+            z: height / 2.0,
+            geometry: format!("<box><size>{} {} {height}</size></box>", size.x, size.y),
+        });
+    }
+}
+
+fn cylinder(radius: f32, height: f32) -> String {
+    format!("<cylinder><radius>{radius}</radius><length>{height}</length></cylinder>")
+}
+
+/// A polyline is extruded upwards from the origin of its pose, so unlike the
+/// other geometries it is placed on the ground
+fn polyline(isometry: &Isometry2<f32>, points: &[parry2d::math::Point<f32>], height: f32) -> Part {
+    let points: String = points
+        .iter()
+        .map(|p| format!("<point>{} {}</point>", p.x, p.y))
+        .collect();
+    Part {
+        isometry: *isometry,
+        z: 0.0,
+        geometry: format!("<polyline>{points}<height>{height}</height></polyline>"),
+    }
+}
+
+fn model(name: &str, parts: &[Part]) -> String {
+    let mut sdf = String::new();
+    let _ = writeln!(sdf, r#"    <model name="{name}">"#);
+    let _ = writeln!(sdf, "      <static>true</static>");
+    let _ = writeln!(sdf, r#"      <link name="link">"#);
+    for (i, part) in parts.iter().enumerate() {
+        let translation = part.isometry.translation;
+        let pose = format!(
+            "<pose>{} {} {} 0 0 {}</pose>",
+            translation.x,
+            translation.y,
+            part.z,
+            part.isometry.rotation.angle()
+        );
+        for element in ["collision", "visual"] {
+            let _ = writeln!(
+                sdf,
+                r#"        <{element} name="{element}_{i}">{pose}<geometry>{}</geometry></{element}>"#,
+                part.geometry
+            );
+        }
+    }
+    let _ = writeln!(sdf, "      </link>");
+    let _ = writeln!(sdf, "    </model>");
+    sdf
+}
+
+/// A ground plane covering `bounds`, with some margin
+fn ground_plane(bounds: Option<Aabb>) -> String {
+    const MARGIN: f32 = 10.0;
+    let (center, size) = bounds.map_or(([0.0, 0.0], [100.0, 100.0]), |aabb| {
+        let center = aabb.center();
+        let size = aabb.extents();
+        ([center.x, center.y], [size.x + MARGIN, size.y + MARGIN])
+    });
+    let plane = format!(
+        "<plane><normal>0 0 1</normal><size>{} {}</size></plane>",
+        size[0], size[1]
+    );
+
+    format!(
+        r#"    <model name="ground_plane">
+      <static>true</static>
+      <pose>{} {} 0 0 0 0</pose>
+      <link name="link">
+        <collision name="collision"><geometry>{plane}</geometry></collision>
+        <visual name="visual"><geometry>{plane}</geometry></visual>
+      </link>
+    </model>
+"#,
+        center[0], center[1]
+    )
+}
+
+const SUN: &str = r#"    <light type="directional" name="sun">
+      <cast_shadows>true</cast_shadows>
+      <pose>0 0 10 0 0 0</pose>
+      <direction>-0.5 0.1 -0.9</direction>
+    </light>
+"#;
+
+/// Escape the characters that are not allowed in an XML attribute
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parry2d::na::Vector2;
+
+    use super::*;
+
+    fn colliders() -> Colliders {
+        let mut colliders = Colliders::default();
+        colliders.push(
+            None,
+            Isometry2::translation(1.0, 2.0),
+            Arc::new(shape::Cuboid::new(Vector2::new(2.0, 3.0))),
+        );
+        colliders.push(
+            None,
+            Isometry2::translation(-5.0, 0.0),
+            Arc::new(shape::Ball::new(1.5)),
+        );
+        colliders
+    }
+
+    #[test]
+    fn boxes_and_cylinders() {
+        let sdf = world("junction", &colliders(), 2.0);
+
+        assert!(sdf.starts_with(r#"<?xml version="1.0" ?>"#));
+        assert!(sdf.contains(r#"<world name="junction">"#));
+        assert!(sdf.contains("<box><size>4 6 2</size></box>"));
+        assert!(sdf.contains("<pose>1 2 1 0 0 0</pose>"));
+        assert!(sdf.contains("<cylinder><radius>1.5</radius><length>2</length></cylinder>"));
+        assert!(sdf.contains("<pose>-5 0 1 0 0 0</pose>"));
+        assert_eq!(sdf.matches("<model name=\"obstacle_").count(), 2);
+        assert!(sdf.trim_end().ends_with("</sdf>"));
+    }
+
+    #[test]
+    fn negative_height_is_extruded_upwards() {
+        let sdf = world("junction", &colliders(), -2.0);
+        assert!(sdf.contains("<box><size>4 6 2</size></box>"));
+    }
+
+    #[test]
+    fn world_name_is_escaped() {
+        let sdf = world(r#"a "b" & <c>"#, &Colliders::default(), 1.0);
+        assert!(sdf.contains(r#"<world name="a &quot;b&quot; &amp; &lt;c&gt;">"#));
+    }
+}
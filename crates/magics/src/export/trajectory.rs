@@ -0,0 +1,173 @@
+//! Export the trajectories of the robots as CSV and GeoJSON, for plotting in
+//! external tools.
+//!
+//! A trajectory is made of the measurements of the
+//! [`VelocityTracker`](crate::planner::tracking::VelocityTracker) of a robot.
+//! Robots are identified by the same id as in the JSON export. The
+//! [recorder](super::recorder) writes its recordings with the same CSV
+//! format, with the energy of the factorgraph in an additional column.
+use std::{collections::BTreeMap, fmt::Write};
+
+use bevy::math::{Vec2, Vec3Swizzles};
+
+use crate::planner::tracking::VelocityMeasurement;
+
+/// A single point of a trajectory, in the coordinates of the planner
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    /// Simulation time in seconds
+    pub time:     f64,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    /// Sum of the energy of every enabled factor of the factorgraph, if
+    /// recorded
+    pub energy:   Option<f64>,
+}
+
+impl From<&VelocityMeasurement> for Sample {
+    fn from(measurement: &VelocityMeasurement) -> Self {
+        Self {
+            time:     measurement.timestamp,
+            position: measurement.position.xz(),
+            velocity: measurement.velocity.xz(),
+            energy:   None,
+        }
+    }
+}
+
+/// Trajectories of the robots, keyed by robot id
+pub type Trajectories = BTreeMap<u64, Vec<Sample>>;
+
+/// One row per sample, with the columns `robot,time,x,y,vx,vy`, and `energy`
+/// if any sample has recorded it
+#[must_use]
+pub fn to_csv(trajectories: &Trajectories) -> String {
+    let with_energy = trajectories
+        .values()
+        .flatten()
+        .any(|sample| sample.energy.is_some());
+
+    let mut csv = String::from("robot,time,x,y,vx,vy");
+    if with_energy {
+        csv.push_str(",energy");
+    }
+    csv.push('\n');
+    for (robot, samples) in trajectories {
+        for sample in samples {
+            let _ = write!(
+                csv,
+                "{robot},{},{},{},{},{}",
+                sample.time,
+                sample.position.x,
+                sample.position.y,
+                sample.velocity.x,
+                sample.velocity.y
+            );
+            if with_energy {
+                csv.push(',');
+                if let Some(energy) = sample.energy {
+                    let _ = write!(csv, "{energy}");
+                }
+            }
+            csv.push('\n');
+        }
+    }
+    csv
+}
+
+/// A `FeatureCollection` with a `LineString` per robot. The time and velocity
+/// of every point are stored in the properties of the feature, as GeoJSON
+/// coordinates only hold positions.
+#[must_use]
+pub fn to_geojson(trajectories: &Trajectories) -> serde_json::Value {
+    let features: Vec<_> = trajectories
+        .iter()
+        .map(|(robot, samples)| {
+            serde_json::json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "LineString",
+                    "coordinates": samples
+                        .iter()
+                        .map(|s| [s.position.x, s.position.y])
+                        .collect::<Vec<_>>(),
+                },
+                "properties": {
+                    "robot": robot,
+                    "time": samples.iter().map(|s| s.time).collect::<Vec<_>>(),
+                    "velocity": samples
+                        .iter()
+                        .map(|s| [s.velocity.x, s.velocity.y])
+                        .collect::<Vec<_>>(),
+                },
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trajectories() -> Trajectories {
+        let sample = |time, x, vx| Sample {
+            time,
+            position: Vec2::new(x, 1.0),
+            velocity: Vec2::new(vx, 0.0),
+            energy: None,
+        };
+        BTreeMap::from([
+            (7, vec![sample(0.5, 0.0, 2.0), sample(1.0, 1.0, 2.0)]),
+            (3, vec![sample(0.5, -4.0, 0.5)]),
+        ])
+    }
+
+    #[test]
+    fn csv_has_a_row_per_sample_ordered_by_robot() {
+        let csv = to_csv(&trajectories());
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines, [
+            "robot,time,x,y,vx,vy",
+            "3,0.5,-4,1,0.5,0",
+            "7,0.5,0,1,2,0",
+            "7,1,1,1,2,0",
+        ]);
+    }
+
+    #[test]
+    fn csv_has_an_energy_column_if_recorded() {
+        let mut trajectories = trajectories();
+        trajectories.get_mut(&7).unwrap()[1].energy = Some(4.25);
+        let csv = to_csv(&trajectories);
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines, [
+            "robot,time,x,y,vx,vy,energy",
+            "3,0.5,-4,1,0.5,0,",
+            "7,0.5,0,1,2,0,",
+            "7,1,1,1,2,0,4.25",
+        ]);
+    }
+
+    #[test]
+    fn geojson_has_a_linestring_per_robot() {
+        let geojson = to_geojson(&trajectories());
+        assert_eq!(geojson["type"], "FeatureCollection");
+
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 2);
+
+        let feature = &features[1];
+        assert_eq!(feature["geometry"]["type"], "LineString");
+        assert_eq!(
+            feature["geometry"]["coordinates"],
+            serde_json::json!([[0.0, 1.0], [1.0, 1.0]])
+        );
+        assert_eq!(feature["properties"]["robot"], 7);
+        assert_eq!(feature["properties"]["time"], serde_json::json!([0.5, 1.0]));
+    }
+}
@@ -0,0 +1,243 @@
+//! wgpu compute backend for [`super::SdfSampler`]
+use bevy::math::Vec2;
+use gbp_linalg::Float;
+use wgpu::util::DeviceExt;
+
+use crate::{factorgraph::factor::obstacle::WorldSize, simulation_loader::SdfImage};
+
+/// Number of invocations per workgroup, must match `sdf_lookup.wgsl`
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Error returned by [`GpuSdfSampler::new`]
+#[derive(Debug, thiserror::Error)]
+pub enum GpuError {
+    #[error("no GPU adapter is available")]
+    NoAdapter,
+    #[error("unable to request a GPU device: {0}")]
+    RequestDevice(#[from] wgpu::RequestDeviceError),
+}
+
+/// Samples an SDF at a batch of positions with a wgpu compute shader.
+/// The SDF is uploaded once, so only the positions and the sampled values are
+/// transferred per batch.
+pub struct GpuSdfSampler {
+    device:   wgpu::Device,
+    queue:    wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    params:   wgpu::Buffer,
+    sdf:      wgpu::Buffer,
+}
+
+impl GpuSdfSampler {
+    /// Request a GPU device, and upload `sdf` to it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no adapter or device is available
+    pub fn new(sdf: &SdfImage, world_size: WorldSize) -> Result<Self, GpuError> {
+        let instance = wgpu::Instance::default();
+        let adapter =
+            pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+                .ok_or(GpuError::NoAdapter)?;
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("sdf sampler"),
+                required_features: wgpu::Features::empty(),
+                required_limits: wgpu::Limits::downlevel_defaults(),
+            },
+            None,
+        ))?;
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label:  Some("sdf lookup"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("sdf_lookup.wgsl").into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label:       Some("sdf lookup"),
+            layout:      None,
+            module:      &shader,
+            entry_point: "main",
+        });
+
+        #[allow(clippy::cast_possible_truncation)]
+        let params: [u32; 4] = [
+            sdf.width(),
+            sdf.height(),
+            (world_size.width as f32).to_bits(),
+            (world_size.height as f32).to_bits(),
+        ];
+        let params = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label:    Some("sdf lookup params"),
+            contents: bytemuck::cast_slice(&params),
+            usage:    wgpu::BufferUsages::UNIFORM,
+        });
+
+        // only the red channel is sampled
+        let red_channel: Vec<u32> = sdf.pixels().map(|pixel| u32::from(pixel[0])).collect();
+        let sdf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label:    Some("sdf"),
+            contents: bytemuck::cast_slice(&red_channel),
+            usage:    wgpu::BufferUsages::STORAGE,
+        });
+
+        Ok(Self {
+            device,
+            queue,
+            pipeline,
+            params,
+            sdf,
+        })
+    }
+
+    /// Sample the SDF at every position, blocking until the GPU is done
+    #[must_use]
+    pub fn sample(&self, positions: &[Vec2]) -> Vec<Option<Float>> {
+        if positions.is_empty() {
+            return vec![];
+        }
+
+        let positions: Vec<[f32; 2]> = positions.iter().map(|p| p.to_array()).collect();
+        let positions_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label:    Some("sdf lookup positions"),
+                contents: bytemuck::cast_slice(&positions),
+                usage:    wgpu::BufferUsages::STORAGE,
+            });
+
+        let size = (positions.len() * std::mem::size_of::<f32>()) as wgpu::BufferAddress;
+        let values = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sdf lookup values"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("sdf lookup staging"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label:   Some("sdf lookup"),
+            layout:  &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding:  0,
+                    resource: self.params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding:  1,
+                    resource: self.sdf.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding:  2,
+                    resource: positions_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding:  3,
+                    resource: values.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("sdf lookup"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            #[allow(clippy::cast_possible_truncation)]
+            pass.dispatch_workgroups((positions.len() as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&values, 0, &staging, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        self.device.poll(wgpu::Maintain::Wait);
+
+        let sampled = {
+            let data = slice.get_mapped_range();
+            bytemuck::cast_slice::<u8, f32>(&data)
+                .iter()
+                .map(|&value| (value >= 0.0).then_some(Float::from(value)))
+                .collect()
+        };
+        staging.unmap();
+
+        sampled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use approx::assert_relative_eq;
+
+    use super::*;
+    use crate::factorgraph::batch::SdfSampler;
+
+    /// A gradient from black in the left column to white in the right column
+    #[allow(clippy::cast_possible_truncation)]
+    fn sdf() -> SdfImage {
+        SdfImage::from_fn(64, 32, |x, _| image::Rgb([(x * 4) as u8, 0, 0]))
+    }
+
+    const WORLD_SIZE: WorldSize = WorldSize {
+        width:  100.0,
+        height: 50.0,
+    };
+
+    /// Positions at the center of pixels, so rounding in `f32` on the GPU and
+    /// `f64` on the CPU lands in the same pixel, plus a few outside the SDF
+    #[allow(clippy::cast_precision_loss)]
+    fn positions() -> Vec<Vec2> {
+        let pixel_size = 100.0 / 64.0;
+        let mut positions: Vec<Vec2> = (0..64)
+            .flat_map(|x| (0..32).map(move |y| (x, y)))
+            .map(|(x, y)| {
+                Vec2::new(
+                    (x as f32 + 0.5) * pixel_size - 50.0,
+                    25.0 - (y as f32 + 0.5) * pixel_size,
+                )
+            })
+            .collect();
+        positions.extend([Vec2::new(60.0, 0.0), Vec2::new(0.0, -30.0)]);
+        positions
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter, run with `--features gpu -- --ignored`"]
+    fn gpu_matches_cpu() {
+        let sdf = sdf();
+        let gpu = GpuSdfSampler::new(&sdf, WORLD_SIZE).expect("a GPU adapter is available");
+        let cpu = SdfSampler::cpu(Arc::new(sdf), WORLD_SIZE);
+
+        let positions = positions();
+        let expected = cpu.sample(&positions);
+        let actual = gpu.sample(&positions);
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.into_iter().zip(expected) {
+            match (actual, expected) {
+                (Some(actual), Some(expected)) => {
+                    assert_relative_eq!(actual, expected, epsilon = 1e-6);
+                }
+                (actual, expected) => assert_eq!(actual, expected),
+            }
+        }
+    }
+
+    #[test]
+    #[ignore = "requires a GPU adapter, run with `--features gpu -- --ignored`"]
+    fn empty_batch() {
+        let gpu = GpuSdfSampler::new(&sdf(), WORLD_SIZE).expect("a GPU adapter is available");
+        assert!(gpu.sample(&[]).is_empty());
+    }
+}
@@ -0,0 +1,235 @@
+//! Batched evaluation of the obstacle factor SDF lookups.
+//!
+//! [`SdfSampler`] samples the signed distance field at many positions at once.
+//! With the experimental `gpu` feature the lookups run in a wgpu compute
+//! shader, which pays off for very large swarms where the number of obstacle
+//! factors is in the tens of thousands. Without the feature, or when no GPU
+//! adapter is available, the lookups run on the CPU with the same
+//! [`sample_sdf`] used by the obstacle factors themselves.
+//!
+//! [`ObstacleLookups`] gathers the positions every obstacle factor of many
+//! factorgraphs is about to sample, samples them in one batch, and hands the
+//! values back to the factors, see [`ObstacleFactor::set_prefetched`].
+//!
+//! Only the SDF lookups are batched. The GBP message updates themselves still
+//! run on the CPU.
+#[cfg(feature = "gpu")]
+mod gpu;
+
+use std::{ops::DerefMut, sync::Arc};
+
+use bevy::math::Vec2;
+use gbp_linalg::Float;
+#[cfg(feature = "gpu")]
+pub use gpu::{GpuError, GpuSdfSampler};
+
+use super::{
+    factor::obstacle::{sample_sdf, ObstacleFactor, ObstacleSource, WorldSize},
+    factorgraph::FactorGraph,
+};
+use crate::simulation_loader::SdfImage;
+
+/// Samples an SDF at a batch of positions, on the GPU if available
+pub enum SdfSampler {
+    /// Sample on the CPU, one position at a time
+    Cpu {
+        /// The signed distance field of the environment
+        sdf: Arc<SdfImage>,
+        /// The size of the world the SDF covers
+        world_size: WorldSize,
+    },
+    /// Sample on the GPU, all positions in a single dispatch
+    #[cfg(feature = "gpu")]
+    Gpu(GpuSdfSampler),
+}
+
+impl SdfSampler {
+    /// Create a sampler for `sdf`, using the GPU if the `gpu` feature is
+    /// enabled and an adapter is available, and the CPU otherwise
+    #[must_use]
+    pub fn new(sdf: Arc<SdfImage>, world_size: WorldSize) -> Self {
+        #[cfg(feature = "gpu")]
+        match GpuSdfSampler::new(&sdf, world_size) {
+            Ok(sampler) => return Self::Gpu(sampler),
+            Err(err) => {
+                bevy::log::warn!(
+                    "unable to sample the SDF on the GPU, falling back to the CPU: {err}"
+                );
+            }
+        }
+
+        Self::cpu(sdf, world_size)
+    }
+
+    /// Create a sampler that always samples on the CPU
+    #[must_use]
+    pub const fn cpu(sdf: Arc<SdfImage>, world_size: WorldSize) -> Self {
+        Self::Cpu { sdf, world_size }
+    }
+
+    /// Returns `true` if the lookups run on the GPU
+    #[must_use]
+    pub const fn is_gpu(&self) -> bool {
+        !matches!(self, Self::Cpu { .. })
+    }
+
+    /// Sample the SDF at every position, see [`sample_sdf`]
+    #[must_use]
+    pub fn sample(&self, positions: &[Vec2]) -> Vec<Option<Float>> {
+        match self {
+            Self::Cpu { sdf, world_size } => positions
+                .iter()
+                .map(|position| {
+                    sample_sdf(
+                        sdf,
+                        *world_size,
+                        Float::from(position.x),
+                        Float::from(position.y),
+                    )
+                })
+                .collect(),
+            #[cfg(feature = "gpu")]
+            Self::Gpu(sampler) => sampler.sample(positions),
+        }
+    }
+}
+
+/// Prefetches the SDF lookups of the obstacle factors of many factorgraphs in
+/// one batch with an [`SdfSampler`]
+pub struct ObstacleLookups {
+    /// The SDF sampled, only factors measuring this one are prefetched
+    sdf:     Arc<SdfImage>,
+    sampler: SdfSampler,
+}
+
+impl ObstacleLookups {
+    /// Create a batch sampler for `sdf`, see [`SdfSampler::new`]
+    #[must_use]
+    pub fn new(sdf: Arc<SdfImage>, world_size: WorldSize) -> Self {
+        Self {
+            sampler: SdfSampler::new(Arc::clone(&sdf), world_size),
+            sdf,
+        }
+    }
+
+    /// The SDF sampled
+    #[inline]
+    pub const fn sdf(&self) -> &Arc<SdfImage> {
+        &self.sdf
+    }
+
+    /// Returns `true` if the lookups run on the GPU
+    #[inline]
+    #[must_use]
+    pub const fn is_gpu(&self) -> bool {
+        self.sampler.is_gpu()
+    }
+
+    /// Returns `true` if `factor` measures the SDF sampled
+    fn samples(&self, factor: &ObstacleFactor) -> bool {
+        matches!(factor.source(), ObstacleSource::Image(sdf) if Arc::ptr_eq(sdf, &self.sdf))
+    }
+
+    /// Sample the lookups of every obstacle factor of `factorgraphs` at the
+    /// position it is linearised at in its next update, and hand the values
+    /// to the factor. Factors measuring another source, e.g. the map a robot
+    /// has sensed, sample it themselves
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn prefetch(&self, factorgraphs: &mut [impl DerefMut<Target = FactorGraph>]) {
+        let mut positions: Vec<[Float; 2]> = vec![];
+        for factorgraph in factorgraphs.iter_mut() {
+            factorgraph.modify_obstacle_factors_at(|factor, position| {
+                if let Some([x, y]) = position.filter(|_| self.samples(factor)) {
+                    positions.extend(factor.lookup_positions(x, y));
+                }
+            });
+        }
+
+        let values = self.sampler.sample(
+            &positions
+                .iter()
+                .map(|[x, y]| Vec2::new(*x as f32, *y as f32))
+                .collect::<Vec<_>>(),
+        );
+
+        // the factors are visited in the same order as above
+        let mut lookups = positions.into_iter().zip(values);
+        for factorgraph in factorgraphs.iter_mut() {
+            factorgraph.modify_obstacle_factors_at(|factor, position| {
+                if position.is_some() && self.samples(factor) {
+                    factor.set_prefetched(lookups.by_ref().take(ObstacleFactor::LOOKUPS));
+                } else {
+                    factor.set_prefetched([]);
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use bevy::ecs::entity::Entity;
+    use gbp_linalg::prelude::*;
+    use ndarray::array;
+
+    use super::*;
+    use crate::factorgraph::prelude::*;
+
+    const WORLD_SIZE: WorldSize = WorldSize {
+        width:  10.0,
+        height: 10.0,
+    };
+
+    /// A variable at `(x, y)` with an obstacle factor measuring `sdf`
+    fn factorgraph(sdf: &Arc<SdfImage>, x: Float, y: Float) -> FactorGraph {
+        let id = Entity::from_raw(0);
+        let mut factorgraph = FactorGraph::new(id);
+        let variable = factorgraph.add_variable(VariableNode::new(
+            id,
+            array![x, y, 1.0, 0.0],
+            Matrix::<Float>::eye(DOFS),
+            DOFS,
+        ));
+        let factor = factorgraph.add_factor(FactorNode::new_obstacle_factor(
+            id,
+            0.01,
+            array![0.0],
+            ObstacleSource::Image(Arc::clone(sdf)),
+            WORLD_SIZE,
+            true,
+        ));
+        let _ =
+            factorgraph.add_internal_edge(VariableId::new(id, variable), FactorId::new(id, factor));
+        // the factor learns where its variable is
+        factorgraph.internal_variable_iteration();
+        factorgraph
+    }
+
+    #[test]
+    fn prefetched_factors_update_as_unbatched_ones() {
+        #[allow(clippy::cast_possible_truncation)]
+        let sdf = Arc::new(SdfImage::from_fn(10, 10, |x, y| {
+            image::Rgb([(x * 20 + y * 5) as u8; 3])
+        }));
+        let lookups = ObstacleLookups::new(Arc::clone(&sdf), WORLD_SIZE);
+
+        for (x, y) in [(1.3, -2.1), (-4.2, 3.7), (0.05, 0.05)] {
+            let mut batched = factorgraph(&sdf, x, y);
+            let mut unbatched = factorgraph(&sdf, x, y);
+
+            lookups.prefetch(&mut [&mut batched]);
+            for factorgraph in [&mut batched, &mut unbatched] {
+                factorgraph.internal_factor_iteration();
+                factorgraph.internal_variable_iteration();
+            }
+
+            let (_, batched) = batched.first_variable().unwrap();
+            let (_, unbatched) = unbatched.first_variable().unwrap();
+            // the GPU samples in single precision
+            for (batched, unbatched) in batched.belief.mean.iter().zip(&unbatched.belief.mean) {
+                approx::assert_relative_eq!(batched, unbatched, max_relative = 1e-4);
+            }
+        }
+    }
+}
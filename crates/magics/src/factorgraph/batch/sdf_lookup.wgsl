@@ -0,0 +1,39 @@
+// Batched lookup of the signed distance field used by the obstacle factors.
+// Mirrors `sample_sdf` in factor/obstacle.rs, writing -1.0 for positions
+// outside of the SDF.
+
+struct Params {
+    width:        u32,
+    height:       u32,
+    world_width:  f32,
+    world_height: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+// red channel of every pixel, row-major
+@group(0) @binding(1) var<storage, read> sdf: array<u32>;
+@group(0) @binding(2) var<storage, read> positions: array<vec2<f32>>;
+@group(0) @binding(3) var<storage, read_write> values: array<f32>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if (i >= arrayLength(&positions)) {
+        return;
+    }
+
+    let position = positions[i];
+    let x = (position.x + params.world_width / 2.0) * f32(params.width) / params.world_width;
+    // the y axis is flipped in the image
+    let y = (-position.y + params.world_height / 2.0) * f32(params.height) / params.world_height;
+
+    // `as u32` on the CPU saturates negative values to 0
+    let x_pixel = u32(max(x, 0.0));
+    let y_pixel = u32(max(y, 0.0));
+    if (x_pixel >= params.width || y_pixel >= params.height) {
+        values[i] = -1.0;
+        return;
+    }
+
+    values[i] = 1.0 - f32(sdf[y_pixel * params.width + x_pixel]) / 255.0;
+}
@@ -0,0 +1,114 @@
+//! Automatic differentiation of factor measurement functions.
+//!
+//! Instead of hand deriving the jacobian of a new factor, or relying on the
+//! finite differences of
+//! [`Factor::first_order_jacobian`](super::Factor::first_order_jacobian), a factor can
+//! implement [`DifferentiableMeasurement`] with a measurement function that is
+//! generic over the scalar type. The function is then evaluated with dual
+//! numbers, one forward pass per input dimension, which gives a jacobian that
+//! is exact up to floating point rounding.
+//!
+//! The built-in factors keep their hand written jacobians, as they are faster.
+//!
+//! ```ignore
+//! impl Factor for MyFactor {
+//!     fn jacobian(&self, state: &FactorState, x: &Vector<Float>) -> Cow<'_, Matrix<Float>> {
+//!         Cow::Owned(autodiff::jacobian(self, state, x))
+//!     }
+//!
+//!     fn measure(&self, state: &FactorState, x: &Vector<Float>) -> Measurement {
+//!         Measurement::new(autodiff::measure(self, state, x))
+//!     }
+//!     // ...
+//! }
+//! ```
+
+use gbp_linalg::prelude::*;
+use num_dual::{Dual64, DualNum};
+
+use super::FactorState;
+
+/// A measurement function that can be evaluated for any scalar type, and
+/// hence be differentiated automatically.
+pub trait DifferentiableMeasurement {
+    /// Evaluate the measurement function `h(x)` at `x`
+    fn measure_generic<D: DualNum<Float> + Copy>(&self, state: &FactorState, x: &[D]) -> Vec<D>;
+}
+
+/// Evaluate the measurement function at the linearisation point
+#[must_use]
+pub fn measure<F: DifferentiableMeasurement + ?Sized>(
+    factor: &F,
+    state: &FactorState,
+    linearisation_point: &Vector<Float>,
+) -> Vector<Float> {
+    let x: Vec<Float> = linearisation_point.iter().copied().collect();
+    Vector::from_vec(factor.measure_generic(state, &x))
+}
+
+/// Compute the jacobian of the measurement function at the linearisation
+/// point, using forward mode automatic differentiation
+#[must_use]
+pub fn jacobian<F: DifferentiableMeasurement + ?Sized>(
+    factor: &F,
+    state: &FactorState,
+    linearisation_point: &Vector<Float>,
+) -> Matrix<Float> {
+    let n = linearisation_point.len();
+    let mut x: Vec<Dual64> = linearisation_point
+        .iter()
+        .map(|&xi| Dual64::from_re(xi))
+        .collect();
+
+    let mut jacobian: Option<Matrix<Float>> = None;
+    for i in 0..n {
+        x[i].eps = 1.0; // seed the derivative of the ith input
+        let h = factor.measure_generic(state, &x);
+        let jacobian = jacobian.get_or_insert_with(|| Matrix::<Float>::zeros((h.len(), n)));
+        for (row, hi) in h.iter().enumerate() {
+            jacobian[[row, i]] = hi.eps;
+        }
+        x[i].eps = 0.0;
+    }
+
+    jacobian.unwrap_or_else(|| Matrix::<Float>::zeros((0, n)))
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray::array;
+
+    use super::*;
+    use crate::factorgraph::DOFS;
+
+    /// Distance from the origin, and the product of the velocity components
+    struct Polynomial;
+
+    impl DifferentiableMeasurement for Polynomial {
+        fn measure_generic<D: DualNum<Float> + Copy>(
+            &self,
+            _state: &FactorState,
+            x: &[D],
+        ) -> Vec<D> {
+            vec![(x[0] * x[0] + x[1] * x[1]).sqrt(), x[2] * x[3]]
+        }
+    }
+
+    #[test]
+    fn jacobian_matches_analytic_derivative() {
+        let state = FactorState::new(array![0.0, 0.0], 1.0, 1);
+        let x = array![3.0, 4.0, 2.0, -1.0];
+        assert_eq!(x.len(), DOFS);
+
+        let h = measure(&Polynomial, &state, &x);
+        assert_relative_eq!(h[0], 5.0);
+        assert_relative_eq!(h[1], -2.0);
+
+        let jacobian = jacobian(&Polynomial, &state, &x);
+        let expected = array![[3.0 / 5.0, 4.0 / 5.0, 0.0, 0.0], [0.0, 0.0, -1.0, 2.0]];
+        for (actual, expected) in jacobian.iter().zip(expected.iter()) {
+            assert_relative_eq!(*actual, *expected, epsilon = 1e-12);
+        }
+    }
+}
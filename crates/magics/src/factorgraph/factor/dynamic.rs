@@ -9,14 +9,17 @@ use super::{Factor, FactorState, Measurement};
 use crate::factorgraph::DOFS;
 
 /// Dynamic factor: constant velocity model
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DynamicFactor {
     cached_jacobian: Matrix<Float>,
 }
 
 impl DynamicFactor {
+    /// A dynamic factor has an edge to two consecutive variables
     pub const NEIGHBORS: usize = 2;
 
+    /// Creates a new [`DynamicFactor`], setting the measurement precision of
+    /// `state` to the process noise of the constant velocity model
     #[must_use]
     #[allow(clippy::similar_names)]
     pub fn new(state: &mut FactorState, delta_t: Float) -> Self {
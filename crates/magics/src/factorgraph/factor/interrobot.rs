@@ -1,3 +1,5 @@
+//! Interrobot factor
+
 use std::{borrow::Cow, num::NonZeroUsize, ops::Sub};
 
 use bevy::log::info;
@@ -41,16 +43,22 @@ pub struct InterRobotFactor {
     safety_distance: Float,
     robot_radius: Float,
     skip: bool,
+    /// The variable in the factorgraph of the other robot
     pub external_variable: ExternalVariableId,
     tiny_offset: Float,
     // all_zeros_jacobian: Matrix<Float>,
 }
 
 impl InterRobotFactor {
+    /// Safety distance as a multiple of the robot radius, if none is given
     pub const DEFAULT_SAFETY_DISTANCE_MULTIPLIER: Float = 2.2;
+    /// An interrobot factor has an edge to a variable of each robot
     pub const NEIGHBORS: usize = 2;
+    /// Offset per robot number, added to break the symmetry of robots at
+    /// the exact same position
     pub const TINY_OFFSET_SCALE: f32 = 1e-6;
 
+    /// Creates a new [`InterRobotFactor`]
     #[must_use]
     pub fn new(
         robot_radius: StrictlyPositiveFinite<Float>,
@@ -88,6 +96,14 @@ impl InterRobotFactor {
         self.safety_distance = multiplier.get() * self.robot_radius
     }
 
+    /// Update the radius of the robot, keeping the safety distance the same
+    /// multiple of it
+    pub fn update_robot_radius(&mut self, robot_radius: StrictlyPositiveFinite<Float>) {
+        let multiplier = self.safety_distance / self.robot_radius;
+        self.robot_radius = robot_radius.get();
+        self.safety_distance = multiplier * self.robot_radius;
+    }
+
     fn diff_between_estimated_positions(
         &self,
         linearisation_point: &Vector<Float>,
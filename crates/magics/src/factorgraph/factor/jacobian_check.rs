@@ -0,0 +1,308 @@
+//! Sanity check of factor jacobians against central finite differences.
+//!
+//! Enabled with the `jacobian-check` feature. On every relinearisation the
+//! jacobian returned by [`Factor::jacobian`] is compared element wise with a
+//! numerical approximation, and any divergence beyond the tolerance is logged.
+//! In debug builds the divergence also triggers an assertion, so a wrong
+//! hand written jacobian is caught the first time the factor is updated.
+//!
+//! Not every factor can be checked this way, see [`tolerance`].
+
+use bevy::log::error;
+use gbp_linalg::{prelude::*, pretty_format_matrix};
+use ndarray::s;
+
+use super::{Factor, FactorKind, FactorState, Measurement};
+use crate::factorgraph::DOFS;
+
+/// How far an analytic jacobian may diverge from the numerical one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Absolute tolerance between the analytic and numerical jacobian
+    pub absolute: Float,
+    /// Tolerance relative to the magnitude of the numerical jacobian
+    pub relative: Float,
+}
+
+impl Tolerance {
+    /// Tolerance of the factors with a smooth measurement function
+    pub const SMOOTH: Self = Self {
+        absolute: 1e-4,
+        relative: 1e-3,
+    };
+
+    /// Whether `analytic` is within tolerance of `numerical`
+    #[inline]
+    fn accepts(self, analytic: Float, numerical: Float) -> bool {
+        (analytic - numerical).abs() <= self.relative.mul_add(numerical.abs(), self.absolute)
+    }
+}
+
+/// The tolerance to check the jacobian of `kind` with, or `None` if it can
+/// not be checked against central differences:
+/// - The jacobian of the obstacle factor is itself a finite difference of the
+///   sampled signed distance field, so there is no analytic jacobian to check.
+/// - The measurement of the interrobot factor has a kink at the safety
+///   distance, where the two robots come into contact, and the central
+///   difference straddling it does not agree with either one sided jacobian.
+#[must_use]
+pub const fn tolerance(kind: &FactorKind) -> Option<Tolerance> {
+    match kind {
+        FactorKind::Obstacle(_) | FactorKind::InterRobot(_) => None,
+        FactorKind::Dynamic(_)
+        | FactorKind::Tracking(_)
+        | FactorKind::Region(_)
+        | FactorKind::Pose(_) => Some(Tolerance::SMOOTH),
+    }
+}
+
+/// The largest divergence found between an analytic and numerical jacobian
+#[derive(Debug, Clone, Copy)]
+pub struct JacobianMismatch {
+    /// Row of the offending element
+    pub row:       usize,
+    /// Column of the offending element
+    pub column:    usize,
+    /// Value of the analytic jacobian
+    pub analytic:  Float,
+    /// Value of the central finite difference approximation
+    pub numerical: Float,
+}
+
+/// Approximate the jacobian of the measurement function with central
+/// differences, `(h(x ⊞ δ) ⊟ h(x ⊞ -δ)) / 2δ`. Variables on a manifold are
+/// perturbed in their tangent space, like in
+/// [`Factor::first_order_jacobian`].
+#[must_use]
+pub fn central_difference_jacobian<F: Factor + ?Sized>(
+    factor: &F,
+    state: &FactorState,
+    linearisation_point: &Vector<Float>,
+) -> Matrix<Float> {
+    let delta = factor.jacobian_delta().max(1e-6);
+    let rows = factor.measure(state, linearisation_point).value.len();
+    let mut jacobian = Matrix::<Float>::zeros((rows, linearisation_point.len()));
+
+    let perturbed = |i: usize, step: Float| {
+        let mut x = linearisation_point.clone();
+        let block = s![(i / DOFS) * DOFS..(i / DOFS + 1) * DOFS];
+        let mut tangent_delta = Vector::<Float>::zeros(DOFS);
+        tangent_delta[i % DOFS] = step;
+        let original = x.slice(block).to_owned();
+        x.slice_mut(block).assign(
+            &state
+                .variable_manifold(i / DOFS)
+                .boxplus(&original, &tangent_delta),
+        );
+        x
+    };
+
+    for i in 0..linearisation_point.len() {
+        let Measurement { value: forward, .. } = factor.measure(state, &perturbed(i, delta));
+        let Measurement {
+            value: backward, ..
+        } = factor.measure(state, &perturbed(i, -delta));
+        let derivatives = state.measurement_manifold.boxminus(&forward, &backward) / (2.0 * delta);
+        jacobian.column_mut(i).assign(&derivatives);
+    }
+
+    jacobian
+}
+
+/// Compare `analytic` with the central difference jacobian of `factor`.
+/// Returns the element with the largest divergence, if any element diverges
+/// beyond `tolerance`.
+#[must_use]
+pub fn compare<F: Factor + ?Sized>(
+    factor: &F,
+    state: &FactorState,
+    linearisation_point: &Vector<Float>,
+    analytic: &Matrix<Float>,
+    tolerance: Tolerance,
+) -> Option<JacobianMismatch> {
+    let numerical = central_difference_jacobian(factor, state, linearisation_point);
+    if numerical.shape() != analytic.shape() {
+        error!(
+            "{}: analytic jacobian has shape {:?}, expected {:?}",
+            factor.name(),
+            analytic.shape(),
+            numerical.shape()
+        );
+        return Some(JacobianMismatch {
+            row:       0,
+            column:    0,
+            analytic:  Float::NAN,
+            numerical: Float::NAN,
+        });
+    }
+
+    let mut worst: Option<(Float, JacobianMismatch)> = None;
+    for ((row, column), &a) in analytic.indexed_iter() {
+        let n = numerical[[row, column]];
+        let error = (a - n).abs();
+        let diverges = !tolerance.accepts(a, n) || error.is_nan();
+        if diverges && worst.map_or(true, |(e, _)| error > e) {
+            worst = Some((error, JacobianMismatch {
+                row,
+                column,
+                analytic: a,
+                numerical: n,
+            }));
+        }
+    }
+
+    worst.map(|(_, mismatch)| mismatch)
+}
+
+/// Compare the jacobians and report any divergence. Factors without a
+/// [`tolerance`] are not checked.
+pub fn check(
+    factor: &FactorKind,
+    state: &FactorState,
+    linearisation_point: &Vector<Float>,
+    analytic: &Matrix<Float>,
+) {
+    let Some(tolerance) = tolerance(factor) else {
+        return;
+    };
+    let Some(mismatch) = compare(factor, state, linearisation_point, analytic, tolerance) else {
+        return;
+    };
+
+    error!(
+        "{}: analytic jacobian diverges from central differences at ({}, {}): analytic = {}, \
+         numerical = {}\n{}",
+        factor.name(),
+        mismatch.row,
+        mismatch.column,
+        mismatch.analytic,
+        mismatch.numerical,
+        pretty_format_matrix!("analytic jacobian", analytic, None)
+    );
+    debug_assert!(
+        false,
+        "{}: analytic jacobian diverges from central differences",
+        factor.name()
+    );
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{borrow::Cow, sync::Arc};
+
+    use ndarray::array;
+    use smallvec::smallvec;
+
+    use super::*;
+    use crate::{
+        factorgraph::{
+            factor::obstacle::{ObstacleFactor, WorldSize},
+            manifold::Manifold,
+        },
+        simulation_loader::SdfImage,
+    };
+
+    /// `h(x) = [x₀x₁, sin(x₂)]`, with either the correct or a wrong analytic
+    /// jacobian
+    struct TestFactor {
+        wrong: bool,
+    }
+
+    impl std::fmt::Display for TestFactor {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "TestFactor")
+        }
+    }
+
+    impl Factor for TestFactor {
+        fn name(&self) -> &'static str {
+            "TestFactor"
+        }
+
+        fn color(&self) -> [u8; 3] {
+            [0, 0, 0]
+        }
+
+        fn jacobian_delta(&self) -> Float {
+            1e-5
+        }
+
+        fn neighbours(&self) -> usize {
+            1
+        }
+
+        fn skip(&self, _state: &FactorState) -> bool {
+            false
+        }
+
+        fn linear(&self) -> bool {
+            false
+        }
+
+        fn jacobian(&self, _state: &FactorState, x: &Vector<Float>) -> Cow<'_, Matrix<Float>> {
+            let mut jacobian = Matrix::<Float>::zeros((2, DOFS));
+            jacobian[[0, 0]] = x[1];
+            jacobian[[0, 1]] = if self.wrong { 2.0 * x[0] } else { x[0] };
+            jacobian[[1, 2]] = x[2].cos();
+            Cow::Owned(jacobian)
+        }
+
+        fn measure(&self, _state: &FactorState, x: &Vector<Float>) -> Measurement {
+            Measurement::new(array![x[0] * x[1], x[2].sin()])
+        }
+    }
+
+    fn state() -> FactorState {
+        FactorState::new(array![0.0, 0.0], 1.0, 1)
+    }
+
+    #[test]
+    fn correct_jacobian_is_accepted() {
+        let factor = TestFactor { wrong: false };
+        let x = array![1.5, -2.0, 0.3, 4.0];
+        let analytic = factor.jacobian(&state(), &x);
+        assert!(compare(&factor, &state(), &x, &analytic, Tolerance::SMOOTH).is_none());
+    }
+
+    #[test]
+    fn wrong_jacobian_is_reported_at_the_diverging_element() {
+        let factor = TestFactor { wrong: true };
+        let x = array![1.5, -2.0, 0.3, 4.0];
+        let analytic = factor.jacobian(&state(), &x);
+        let mismatch = compare(&factor, &state(), &x, &analytic, Tolerance::SMOOTH).unwrap();
+        assert_eq!((mismatch.row, mismatch.column), (0, 1));
+        assert!((mismatch.analytic - 3.0).abs() < 1e-9);
+        assert!((mismatch.numerical - 1.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn manifold_variables_are_perturbed_in_their_tangent_space() {
+        // with a heading of π/2, a step along x in the tangent space of the
+        // pose is a step along y in the world
+        let factor = TestFactor { wrong: false };
+        let mut state = state();
+        state.variable_manifolds = smallvec![Manifold::Se2 { offset: 0 }];
+        let x = array![1.5, -2.0, std::f64::consts::FRAC_PI_2, 4.0];
+
+        let numerical = central_difference_jacobian(&factor, &state, &x);
+        let tangent = factor.first_order_jacobian(&state, x.clone());
+        for (n, t) in numerical.iter().zip(tangent.iter()) {
+            assert!((n - t).abs() < 1e-3, "{numerical} != {tangent}");
+        }
+        assert!((numerical[[0, 0]] - 1.5).abs() < 1e-4);
+        // the euclidean jacobian is rejected
+        let euclidean = factor.jacobian(&state, &x);
+        assert!(compare(&factor, &state, &x, &euclidean, Tolerance::SMOOTH).is_some());
+    }
+
+    #[test]
+    fn obstacle_factor_is_not_checked() {
+        let sdf = Arc::new(SdfImage::from_pixel(4, 4, image::Rgb([255, 255, 255])));
+        let factor = ObstacleFactor::new(sdf, WorldSize {
+            width:  1.0,
+            height: 1.0,
+        });
+        assert_eq!(tolerance(&FactorKind::Obstacle(factor)), None);
+    }
+}
@@ -1,8 +1,8 @@
 use gbp_linalg::prelude::*;
 use ndarray::prelude::*;
-use ndarray_inverse::Inverse;
 
 use crate::factorgraph::{
+    fixed,
     message::{InformationVec, Mean, PrecisionMatrix},
     prelude::Message,
     DOFS,
@@ -53,8 +53,8 @@ fn extract_submatrices_from_precision_matrix<T: GbpFloat>(
 
 #[allow(clippy::similar_names)]
 pub fn marginalise_factor_distance(
-    information_vector: Vector<Float>,
-    precision_matrix: Matrix<Float>,
+    information_vector: &Vector<Float>,
+    precision_matrix: &Matrix<Float>,
     marg_idx: usize,
 ) -> Message {
     debug_assert_eq!(information_vector.len(), precision_matrix.nrows());
@@ -65,8 +65,8 @@ pub fn marginalise_factor_distance(
         let mean = Vector::<Float>::zeros(information_vector.len());
 
         return Message::new(
-            InformationVec(information_vector),
-            PrecisionMatrix(precision_matrix),
+            InformationVec(information_vector.clone()),
+            PrecisionMatrix(precision_matrix.clone()),
             Mean(mean),
         );
     }
@@ -76,7 +76,7 @@ pub fn marginalise_factor_distance(
     } else {
         precision_matrix.slice(s![..marg_idx, ..marg_idx])
     };
-    let Some(lam_bb_inv) = lam_bb.to_owned().inv() else {
+    let Some(lam_bb_inv) = fixed::invert(lam_bb) else {
         return Message::empty();
     };
 
@@ -221,8 +221,8 @@ mod tests {
         let marginalisation_idx = 0;
 
         let mut marginalised_msg = marginalise_factor_distance(
-            information_vector.clone(),
-            precision_matrix.clone(),
+            &information_vector,
+            &precision_matrix,
             marginalisation_idx,
         );
 
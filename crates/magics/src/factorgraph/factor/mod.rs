@@ -1,30 +1,37 @@
-use std::{borrow::Cow, num::NonZeroUsize, ops::AddAssign};
+use std::{borrow::Cow, num::NonZeroUsize, ops::AddAssign, sync::Arc};
 
-use bevy::math::Vec2;
+use bevy::{log::error, math::Vec2};
 use gbp_linalg::{prelude::*, pretty_format_matrix, pretty_format_vector};
 use ndarray::{array, s};
+use smallvec::{smallvec, SmallVec};
 use typed_floats::StrictlyPositiveFinite;
 
 use self::{
     dynamic::DynamicFactor, interrobot::InterRobotFactor, obstacle::ObstacleFactor,
-    tracking::TrackingFactor,
+    pose::PoseFactor, region::RegionFactor, tracking::TrackingFactor,
 };
 use super::{
     factorgraph::{FactorGraphId, NodeIndex},
     id::VariableId,
-    message::MessagesToVariables,
+    manifold::Manifold,
+    message::{FactorResponses, Mean, MessagesToVariables},
     node::FactorGraphNode,
     prelude::Message,
     MessageCount, MessagesReceived, MessagesSent, DOFS,
 };
-use crate::{factorgraph::node::RemoveConnectionToError, simulation_loader::SdfImage};
-
-pub(in crate::factorgraph) mod dynamic;
-pub(in crate::factorgraph) mod interrobot;
+use crate::factorgraph::node::RemoveConnectionToError;
+
+#[cfg(feature = "autodiff")]
+pub mod autodiff;
+pub mod dynamic;
+pub mod interrobot;
+#[cfg(feature = "jacobian-check")]
+mod jacobian_check;
 mod marginalise_factor_distance;
-pub(crate) mod obstacle;
-pub(in crate::factorgraph) mod pose;
-pub(in crate::factorgraph) mod tracking;
+pub mod obstacle;
+pub mod pose;
+pub mod region;
+pub mod tracking;
 mod velocity;
 // pub(in crate::factorgraph) mod velocity;
 
@@ -114,14 +121,33 @@ pub trait Factor: std::fmt::Display {
         let delta = self.jacobian_delta();
 
         for i in 0..linearization_point.len() {
-            linearization_point[i] += delta; // perturb by delta
-            let Measurement {
-                value: h1,
-                position: _,
-            } = self.measure(state, &linearization_point);
-            let derivatives = (&h1 - &h0) / delta;
-            jacobian.column_mut(i).assign(&derivatives);
-            linearization_point[i] -= delta; // reset the perturbation
+            let manifold = state.variable_manifold(i / DOFS);
+            if manifold.is_euclidean() {
+                linearization_point[i] += delta; // perturb by delta
+                let Measurement {
+                    value: h1,
+                    position: _,
+                } = self.measure(state, &linearization_point);
+                let derivatives = state.measurement_manifold.boxminus(&h1, &h0) / delta;
+                jacobian.column_mut(i).assign(&derivatives);
+                linearization_point[i] -= delta; // reset the perturbation
+            } else {
+                // perturb by delta in the tangent space of the variable
+                let block = s![(i / DOFS) * DOFS..(i / DOFS + 1) * DOFS];
+                let original = linearization_point.slice(block).to_owned();
+                let mut tangent_delta = Vector::<Float>::zeros(DOFS);
+                tangent_delta[i % DOFS] = delta;
+                linearization_point
+                    .slice_mut(block)
+                    .assign(&manifold.boxplus(&original, &tangent_delta));
+                let Measurement {
+                    value: h1,
+                    position: _,
+                } = self.measure(state, &linearization_point);
+                let derivatives = state.measurement_manifold.boxminus(&h1, &h0) / delta;
+                jacobian.column_mut(i).assign(&derivatives);
+                linearization_point.slice_mut(block).assign(&original); // reset the perturbation
+            }
         }
 
         jacobian
@@ -129,7 +155,7 @@ pub trait Factor: std::fmt::Display {
 }
 
 /// Factor node in the factorgraph
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FactorNode {
     factorgraph_id: FactorGraphId,
     /// Unique identifier that associates the variable with the factorgraph it
@@ -145,6 +171,9 @@ pub struct FactorNode {
     message_count: MessageCount,
     /// Whether the factor is enabled
     pub enabled:   bool,
+    /// Whether the last update ran into a non-finite value. Only the update
+    /// it first happens in is logged
+    non_finite:    bool,
 }
 
 impl FactorNode {
@@ -162,6 +191,7 @@ impl FactorNode {
             inbox: MessagesToVariables::new(),
             message_count: MessageCount::default(),
             enabled,
+            non_finite: false,
         }
     }
 
@@ -240,15 +270,45 @@ impl FactorNode {
         factorgraph_id: FactorGraphId,
         strength: Float,
         measurement: Vector<Float>,
-        obstacle_sdf: SdfImage,
+        source: obstacle::ObstacleSource,
         world_size: obstacle::WorldSize,
         enabled: bool,
         // world_size_width: Float,
         // world_size_height: Float,
     ) -> Self {
         let state = FactorState::new(measurement, strength, ObstacleFactor::NEIGHBORS);
-        let obstacle_factor = ObstacleFactor::new(obstacle_sdf, world_size);
-        let kind = FactorKind::Obstacle(obstacle_factor);
+        let kind = FactorKind::Obstacle(ObstacleFactor::new(source, world_size));
+        Self::new(factorgraph_id, state, kind, enabled)
+    }
+
+    /// Create a new pose factor, measuring the state of a variable on
+    /// `manifold` directly. The variable is linearised in the tangent space of
+    /// its current mean, and the residual is computed with `⊟`, such that e.g.
+    /// angles are differenced on the circle.
+    pub fn new_pose_factor(
+        factorgraph_id: FactorGraphId,
+        strength: Float,
+        measurement: Vector<Float>,
+        manifold: Manifold,
+        enabled: bool,
+    ) -> Self {
+        let mut state = FactorState::new(measurement, strength, PoseFactor::NEIGHBORS);
+        manifold.normalise(&mut state.initial_measurement);
+        state.variable_manifolds = smallvec![manifold];
+        state.measurement_manifold = manifold;
+        Self::new(factorgraph_id, state, FactorKind::Pose(PoseFactor), enabled)
+    }
+
+    /// Create a new region factor
+    pub fn new_region_factor(
+        factorgraph_id: FactorGraphId,
+        strength: Float,
+        measurement: Vector<Float>,
+        regions: Arc<[region::CostPolygon]>,
+        enabled: bool,
+    ) -> Self {
+        let state = FactorState::new(measurement, strength, RegionFactor::NEIGHBORS);
+        let kind = FactorKind::Region(RegionFactor::new(regions));
         Self::new(factorgraph_id, state, kind, enabled)
     }
 
@@ -303,6 +363,20 @@ impl FactorNode {
         self.kind.skip(&self.state)
     }
 
+    /// Take over the messages received by `solved`, a copy of this factor that
+    /// has been iterated elsewhere. Skipped if the factor has been replaced by
+    /// one connected to other variables since the copy was taken.
+    pub(in crate::factorgraph) fn apply_solution(&mut self, solved: &Self) {
+        if !self.inbox.keys().eq(solved.inbox.keys()) {
+            return;
+        }
+        for (received, message) in self.inbox.values_mut().zip(solved.inbox.values()) {
+            received.clone_from(message);
+        }
+        self.message_count = solved.message_count;
+        self.non_finite = solved.non_finite;
+    }
+
     /// Add a message to this factors inbox
     pub fn receive_message_from(&mut self, from: VariableId, message: Message) {
         if !self.enabled {
@@ -326,12 +400,25 @@ impl FactorNode {
     #[inline(always)]
     #[must_use]
     fn residual(&self) -> Vector<Float> {
-        &self.state.initial_measurement - &self.state.cached_measurement
+        self.state
+            .measurement_manifold
+            .boxminus(&self.state.initial_measurement, &self.state.cached_measurement)
+    }
+
+    /// Energy of the factor, i.e. half the squared Mahalanobis norm of its
+    /// residual. Zero until the factor has been updated the first time
+    #[must_use]
+    pub fn energy(&self) -> Float {
+        if !self.state.initialized {
+            return 0.0;
+        }
+        let residual = self.residual();
+        0.5 * residual.dot(&self.state.measurement_precision.dot(&residual))
     }
 
     /// Update the factor using the gbp message passing algorithm
     #[must_use]
-    pub fn update(&mut self) -> MessagesToVariables {
+    pub fn update(&mut self) -> FactorResponses {
         // update the linearisation point
         for (i, (_, message)) in self.inbox.iter().enumerate() {
             let mut slice = self
@@ -348,24 +435,17 @@ impl FactorNode {
             }
         }
 
+        // A variable on a manifold has no origin that is meaningful to linearise
+        // around, so wait for its mean
+        let awaits_manifold_mean = self
+            .inbox
+            .values()
+            .zip(&self.state.variable_manifolds)
+            .any(|(message, manifold)| message.is_empty() && !manifold.is_euclidean());
+
         // If the factor is to be skipped, send empty messages to all variables
-        if self.skip() {
-            let mut messages_sent = MessagesSent::new();
-            let messages: MessagesToVariables = self
-                .inbox
-                .keys()
-                .map(|variable_id| {
-                    if variable_id.factorgraph_id == self.factorgraph_id {
-                        messages_sent.internal += 1;
-                    } else {
-                        messages_sent.external += 1;
-                    }
-
-                    (*variable_id, Message::empty())
-                })
-                .collect();
-            self.message_count.sent += messages_sent;
-            return messages;
+        if self.skip() || awaits_manifold_mean {
+            return self.empty_messages();
         }
 
         // let mut linearisation_point =
@@ -385,33 +465,105 @@ impl FactorNode {
         //     self.state.linearisation_point = new_linearisation_point;
         // }
 
-        let jacobian = self.jacobian(&self.state.linearisation_point);
+        // The jacobian of a linear factor does not depend on the linearisation
+        // point, so neither does `JᵀΛ` nor `JᵀΛJ`. Compute them once and reuse.
+        // Nonlinear factors are only relinearised with jit linearisation
+        let cache_potential = self.kind.linear() || !self.state.jit_linearisation;
+        if cache_potential && self.state.linear_potential.is_none() {
+            let jacobian = self.jacobian(&self.state.linearisation_point).into_owned();
+            self.state.linear_potential = Some(LinearPotential::new(
+                jacobian,
+                &self.state.measurement_precision,
+            ));
+        }
 
         // 2. Compute the Factor potential, Lambda and eta
-        let potential_precision_matrix = jacobian
-            .t()
-            .dot(&self.state.measurement_precision)
-            .dot(jacobian.as_ref());
-
-        let residual = &self.state.initial_measurement - measurement;
+        let (jacobian, jacobian_transpose_precision, potential_precision_matrix) =
+            match &self.state.linear_potential {
+                Some(linear_potential) => (
+                    Cow::Borrowed(&linear_potential.jacobian),
+                    Cow::Borrowed(&linear_potential.jacobian_transpose_precision),
+                    Cow::Borrowed(&linear_potential.precision_matrix),
+                ),
+                None => {
+                    let jacobian = self.jacobian(&self.state.linearisation_point);
+                    let jacobian_transpose_precision =
+                        jacobian.t().dot(&self.state.measurement_precision);
+                    let precision_matrix = jacobian_transpose_precision.dot(jacobian.as_ref());
+                    (
+                        jacobian,
+                        Cow::Owned(jacobian_transpose_precision),
+                        Cow::Owned(precision_matrix),
+                    )
+                }
+            };
+        #[cfg(feature = "jacobian-check")]
+        jacobian_check::check(
+            &self.kind,
+            &self.state,
+            &self.state.linearisation_point,
+            jacobian.as_ref(),
+        );
 
-        let potential_information_vec = jacobian
-            .t()
-            .dot(&self.state.measurement_precision)
-            .dot(&(jacobian.dot(&self.state.linearisation_point) + residual));
+        let residual = self
+            .state
+            .measurement_manifold
+            .boxminus(&self.state.initial_measurement, &measurement);
+
+        let potential_information_vec = jacobian_transpose_precision
+            .dot(&(jacobian.dot(self.state.tangent_linearisation_point().as_ref()) + residual));
+
+        // Stop NaNs and infs here, instead of letting them propagate through the
+        // whole graph
+        let non_finite = [
+            (
+                "linearisation point",
+                self.state.linearisation_point.iter().all(|x| x.is_finite()),
+            ),
+            ("measurement", measurement.iter().all(|x| x.is_finite())),
+            ("jacobian", jacobian.iter().all(|x| x.is_finite())),
+            (
+                "potential precision matrix",
+                potential_precision_matrix.iter().all(|x| x.is_finite()),
+            ),
+            (
+                "potential information vector",
+                potential_information_vec.iter().all(|x| x.is_finite()),
+            ),
+        ]
+        .into_iter()
+        .find_map(|(quantity, finite)| (!finite).then_some(quantity));
+        if let Some(quantity) = non_finite {
+            if !self.non_finite {
+                self.report_non_finite(quantity, &measurement);
+            }
+            self.non_finite = true;
+            return self.empty_messages();
+        }
 
         self.state.initialized = true;
 
         // 3. Marginalise Factor messages
         let mut marginalisation_idx = 0;
-        let mut messages = MessagesToVariables::new();
-        // let mut messages = MessagesToVariables::with_capacity(self.inbox.len());
+        let mut messages = FactorResponses::new();
 
         let mut messages_sent = MessagesSent::new();
+        let mut non_finite_to = None;
+
+        // Reuse the same buffers for the aggregated potential of every
+        // variable, instead of cloning the potential once per variable
+        let information_vec = &mut self.state.scratch_information_vec;
+        let precision_matrix = &mut self.state.scratch_precision_matrix;
+        if information_vec.dim() != potential_information_vec.dim() {
+            *information_vec = Vector::<Float>::zeros(potential_information_vec.dim());
+        }
+        if precision_matrix.dim() != potential_precision_matrix.dim() {
+            *precision_matrix = Matrix::<Float>::zeros(potential_precision_matrix.dim());
+        }
 
         for variable_id in self.inbox.keys() {
-            let mut information_vec = potential_information_vec.clone();
-            let mut precision_matrix = potential_precision_matrix.clone();
+            information_vec.assign(&potential_information_vec);
+            precision_matrix.assign(potential_precision_matrix.as_ref());
 
             for (j, (other_variable_id, other_message)) in self.inbox.iter().enumerate() {
                 if other_variable_id == variable_id {
@@ -436,9 +588,26 @@ impl FactorNode {
                 }
             }
 
-            let message =
+            let mut message =
                 marginalise_factor_distance(information_vec, precision_matrix, marginalisation_idx);
-            messages.insert(*variable_id, message);
+            if self
+                .state
+                .variable_manifolds
+                .get(marginalisation_idx / DOFS)
+                .is_some_and(|manifold| !manifold.is_euclidean())
+            {
+                // The message is in the tangent space of the linearisation point of
+                // the variable, which the variable needs to move it to its own mean
+                let block = s![marginalisation_idx..marginalisation_idx + DOFS];
+                message.set_mean(Mean(self.state.linearisation_point.slice(block).to_owned()));
+            }
+            if !message.is_finite() {
+                if non_finite_to.is_none() {
+                    non_finite_to = Some(variable_id.variable_index);
+                }
+                message = Message::empty();
+            }
+            messages.push((*variable_id, message));
 
             if variable_id.factorgraph_id == self.factorgraph_id {
                 messages_sent.internal += 1;
@@ -449,10 +618,65 @@ impl FactorNode {
             marginalisation_idx += DOFS;
         }
 
+        if let Some(variable_index) = non_finite_to.filter(|_| !self.non_finite) {
+            error!(
+                "{} {:?} in factorgraph {:?} produced a non-finite message to variable {:?} when \
+                 marginalising, sending an empty message instead",
+                self.kind.name(),
+                self.node_index,
+                self.factorgraph_id,
+                variable_index
+            );
+        }
+        self.non_finite = non_finite_to.is_some();
+
+        self.message_count.sent += messages_sent;
+        messages
+    }
+
+    /// Send an empty message to every connected variable
+    fn empty_messages(&mut self) -> FactorResponses {
+        let mut messages_sent = MessagesSent::new();
+        let messages: FactorResponses = self
+            .inbox
+            .keys()
+            .map(|variable_id| {
+                if variable_id.factorgraph_id == self.factorgraph_id {
+                    messages_sent.internal += 1;
+                } else {
+                    messages_sent.external += 1;
+                }
+
+                (*variable_id, Message::empty())
+            })
+            .collect();
         self.message_count.sent += messages_sent;
         messages
     }
 
+    /// Report which factor produced a non-finite value, together with the
+    /// values needed to track down why
+    fn report_non_finite(&self, quantity: &str, measurement: &Vector<Float>) {
+        error!(
+            "{} {:?} in factorgraph {:?} produced a non-finite {}, sending empty messages \
+             instead\nsigma: {}\n{}{}{}",
+            self.kind.name(),
+            self.node_index,
+            self.factorgraph_id,
+            quantity,
+            self.state.strength,
+            pretty_format_vector!("linearisation point", &self.state.linearisation_point, None),
+            pretty_format_vector!("initial measurement", &self.state.initial_measurement, None),
+            pretty_format_vector!("measurement", measurement, None),
+        );
+    }
+
+    /// Returns `true` if the last update ran into a non-finite value
+    #[inline]
+    pub const fn is_non_finite(&self) -> bool {
+        self.non_finite
+    }
+
     /// Check if the factor is an [`InterRobotFactor`]
     #[inline(always)]
     pub fn is_inter_robot(&self) -> bool {
@@ -486,7 +710,7 @@ impl FactorNode {
 /// Static dispatch enum for the various factors in the factorgraph
 /// Used instead of dynamic dispatch
 #[allow(missing_docs)]
-#[derive(Debug, derive_more::IsVariant, strum_macros::EnumTryAs)]
+#[derive(Debug, Clone, derive_more::IsVariant, strum_macros::EnumTryAs)]
 pub enum FactorKind {
     /// `InterRobotFactor`
     InterRobot(InterRobotFactor),
@@ -496,6 +720,10 @@ pub enum FactorKind {
     Obstacle(ObstacleFactor),
     /// `TrackingFactor`
     Tracking(TrackingFactor),
+    /// `RegionFactor`
+    Region(RegionFactor),
+    /// `PoseFactor`
+    Pose(PoseFactor),
 }
 
 impl std::fmt::Display for FactorKind {
@@ -505,6 +733,8 @@ impl std::fmt::Display for FactorKind {
             Self::Dynamic(f) => f.fmt(formatter),
             Self::Obstacle(f) => f.fmt(formatter),
             Self::Tracking(f) => f.fmt(formatter),
+            Self::Region(f) => f.fmt(formatter),
+            Self::Pose(f) => f.fmt(formatter),
         }
     }
 }
@@ -516,6 +746,8 @@ impl Factor for FactorKind {
             Self::Dynamic(f) => f.name(),
             Self::Obstacle(f) => f.name(),
             Self::Tracking(f) => f.name(),
+            Self::Region(f) => f.name(),
+            Self::Pose(f) => f.name(),
         }
     }
 
@@ -525,6 +757,8 @@ impl Factor for FactorKind {
             Self::Dynamic(f) => f.color(),
             Self::Obstacle(f) => f.color(),
             Self::Tracking(f) => f.color(),
+            Self::Region(f) => f.color(),
+            Self::Pose(f) => f.color(),
         }
     }
 
@@ -538,6 +772,8 @@ impl Factor for FactorKind {
             Self::InterRobot(f) => f.jacobian(state, linearisation_point),
             Self::Obstacle(f) => f.jacobian(state, linearisation_point),
             Self::Tracking(f) => f.jacobian(state, linearisation_point),
+            Self::Region(f) => f.jacobian(state, linearisation_point),
+            Self::Pose(f) => f.jacobian(state, linearisation_point),
         }
     }
 
@@ -549,6 +785,8 @@ impl Factor for FactorKind {
             Self::InterRobot(f) => f.measure(state, linearisation_point),
             Self::Obstacle(f) => f.measure(state, linearisation_point),
             Self::Tracking(f) => f.measure(state, linearisation_point),
+            Self::Region(f) => f.measure(state, linearisation_point),
+            Self::Pose(f) => f.measure(state, linearisation_point),
         }
     }
 
@@ -558,6 +796,8 @@ impl Factor for FactorKind {
             Self::InterRobot(f) => f.skip(state),
             Self::Obstacle(f) => f.skip(state),
             Self::Tracking(f) => f.skip(state),
+            Self::Region(f) => f.skip(state),
+            Self::Pose(f) => f.skip(state),
         }
     }
 
@@ -567,16 +807,19 @@ impl Factor for FactorKind {
             Self::InterRobot(f) => f.jacobian_delta(),
             Self::Obstacle(f) => f.jacobian_delta(),
             Self::Tracking(f) => f.jacobian_delta(),
+            Self::Region(f) => f.jacobian_delta(),
+            Self::Pose(f) => f.jacobian_delta(),
         }
     }
 
-    // TODO: not used so maybe just remove
     fn linear(&self) -> bool {
         match self {
             Self::Dynamic(f) => f.linear(),
             Self::InterRobot(f) => f.linear(),
             Self::Obstacle(f) => f.linear(),
             Self::Tracking(f) => f.linear(),
+            Self::Region(f) => f.linear(),
+            Self::Pose(f) => f.linear(),
         }
     }
 
@@ -586,6 +829,8 @@ impl Factor for FactorKind {
             FactorKind::Dynamic(f) => f.neighbours(),
             FactorKind::Obstacle(f) => f.neighbours(),
             FactorKind::Tracking(f) => f.neighbours(),
+            FactorKind::Region(f) => f.neighbours(),
+            FactorKind::Pose(f) => f.neighbours(),
         }
     }
 }
@@ -620,6 +865,49 @@ pub struct FactorState {
     pub cached_measurement: Vector<Float>,
     /// Set to true after the first call to `self.update()`
     initialized: bool,
+    /// Manifold of each connected variable. Defaults to euclidean.
+    /// A factor has at most two neighbours, so they are stored inline
+    pub variable_manifolds: SmallVec<[Manifold; 2]>,
+    /// Manifold of the measurement. Defaults to euclidean.
+    pub measurement_manifold: Manifold,
+    /// Buffer the information vector of the factor potential is aggregated
+    /// into, reused between calls to `FactorNode::update()`
+    scratch_information_vec: Vector<Float>,
+    /// Buffer the precision matrix of the factor potential is aggregated
+    /// into, reused between calls to `FactorNode::update()`
+    scratch_precision_matrix: Matrix<Float>,
+    /// Cached potential of a linear factor, computed on the first call to
+    /// `FactorNode::update()`. `None` for nonlinear factors, which are
+    /// relinearised every iteration, unless `jit_linearisation` is disabled
+    linear_potential: Option<LinearPotential>,
+    /// Relinearise a nonlinear factor around the means of its variables on
+    /// every update. If disabled, the factor is linearised once, on its first
+    /// update, and the potential is reused like that of a linear factor
+    jit_linearisation: bool,
+}
+
+/// The parts of the potential of a linear factor, that do not depend on the
+/// linearisation point
+#[derive(Debug, Clone)]
+struct LinearPotential {
+    /// `J`, the jacobian of the measurement function
+    jacobian: Matrix<Float>,
+    /// `JᵀΛ`, where `J` is the jacobian and `Λ` the measurement precision
+    jacobian_transpose_precision: Matrix<Float>,
+    /// `JᵀΛJ`, the precision matrix of the factor potential
+    precision_matrix: Matrix<Float>,
+}
+
+impl LinearPotential {
+    fn new(jacobian: Matrix<Float>, measurement_precision: &Matrix<Float>) -> Self {
+        let jacobian_transpose_precision = jacobian.t().dot(measurement_precision);
+        let precision_matrix = jacobian_transpose_precision.dot(&jacobian);
+        Self {
+            jacobian,
+            jacobian_transpose_precision,
+            precision_matrix,
+        }
+    }
 }
 
 impl FactorState {
@@ -639,7 +927,58 @@ impl FactorState {
             cached_jacobian: array![[]],
             cached_measurement: array![],
             initialized: false,
+            variable_manifolds: smallvec![Manifold::Euclidean; neighbor_amount],
+            measurement_manifold: Manifold::Euclidean,
+            scratch_information_vec: Vector::<Float>::zeros(0),
+            scratch_precision_matrix: Matrix::<Float>::zeros((0, 0)),
+            linear_potential: None,
+            jit_linearisation: true,
+        }
+    }
+
+    /// Change the strength of the factor, and with it the precision of its
+    /// measurement. The cached potential of a linear factor is recomputed on
+    /// the next update
+    pub fn set_strength(&mut self, strength: Float) {
+        self.measurement_precision =
+            Matrix::<Float>::eye(self.initial_measurement.len()) / Float::powi(strength, 2);
+        self.strength = strength;
+        self.linear_potential = None;
+    }
+
+    /// Enable or disable just in time linearisation of a nonlinear factor.
+    /// The cached potential is recomputed on the next update
+    pub fn set_jit_linearisation(&mut self, jit_linearisation: bool) {
+        if jit_linearisation != self.jit_linearisation {
+            self.jit_linearisation = jit_linearisation;
+            self.linear_potential = None;
+        }
+    }
+
+    /// Returns the manifold of the nth connected variable
+    #[inline]
+    fn variable_manifold(&self, n: usize) -> Manifold {
+        self.variable_manifolds.get(n).copied().unwrap_or_default()
+    }
+
+    /// The linearisation point as seen from the tangent space of each
+    /// connected variable. Euclidean variables are expressed in absolute
+    /// coordinates, while variables on a manifold are expressed relative to
+    /// their own mean, which is zero in their tangent space.
+    fn tangent_linearisation_point(&self) -> Cow<'_, Vector<Float>> {
+        if self.variable_manifolds.iter().all(Manifold::is_euclidean) {
+            return Cow::Borrowed(&self.linearisation_point);
+        }
+
+        let mut linearisation_point = self.linearisation_point.clone();
+        for (n, manifold) in self.variable_manifolds.iter().enumerate() {
+            if !manifold.is_euclidean() {
+                linearisation_point
+                    .slice_mut(s![n * DOFS..(n + 1) * DOFS])
+                    .fill(0.0);
+            }
         }
+        Cow::Owned(linearisation_point)
     }
 
     /// Set the linearisation point
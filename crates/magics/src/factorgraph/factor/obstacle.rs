@@ -1,29 +1,48 @@
 //! Obstacle factor
 
-use std::{borrow::Cow, cell::Cell, sync::Mutex};
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
 
 use bevy::math::Vec2;
 use gbp_linalg::prelude::*;
+use itertools::Itertools;
 use ndarray::array;
 
 use super::{Factor, FactorState, Measurement};
 use crate::simulation_loader::SdfImage;
 
+/// Factor keeping a variable away from the obstacles of the environment, by
+/// measuring the signed distance field at its position. Additional
+/// [`SdfLayer`]s, e.g. temporary exclusion zones, can be measured on top of
+/// the field of the environment, the strongest weighted value wins
 pub struct ObstacleFactor {
-    /// The signed distance field of the environment
-    obstacle_sdf:     SdfImage,
+    /// Where the obstacles of the environment are measured, shared between
+    /// all obstacle factors
+    source: ObstacleSource,
+    /// Additional signed distance fields, by name
+    layers: BTreeMap<String, SdfLayer>,
+    /// Values of the source sampled ahead of time, by position, see
+    /// [`ObstacleFactor::set_prefetched`]
+    prefetched: Vec<([Float; 2], Option<Float>)>,
     /// Copy of the `WORLD_SZ` setting from **gbpplanner**, that we store a copy
     /// of here since `ObstacleFactor` needs this information to calculate
     /// `.jacobian_delta()` and `.measurement()`
-    world_size:       WorldSize,
+    world_size: WorldSize,
     // world_size:       Float,
     last_measurement: Mutex<Cell<LastMeasurement>>,
-    jacobian_delta:   Float,
+    jacobian_delta: Float,
 }
 
+/// Size of the world covered by the signed distance field
 #[derive(Debug, Clone, Copy)]
 pub struct WorldSize {
+    /// SI unit: m
     pub width:  Float,
+    /// SI unit: m
     pub height: Float,
 }
 
@@ -33,9 +52,146 @@ impl std::fmt::Display for WorldSize {
     }
 }
 
+/// A signed distance field measured by an [`ObstacleFactor`] in addition to
+/// the one of the environment. Must cover the same world size
+#[derive(Clone)]
+pub struct SdfLayer {
+    /// Shared between all obstacle factors the layer is added to
+    pub sdf:    Arc<SdfImage>,
+    /// Scales the measurement of the layer, where values below 1.0 give zones
+    /// robots avoid, but can pass through if they have to. **constraint**: >=
+    /// 0.0
+    pub weight: Float,
+}
+
+impl std::fmt::Debug for SdfLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SdfLayer")
+            .field("size", &self.sdf.dimensions())
+            .field("weight", &self.weight)
+            .finish()
+    }
+}
+
+/// Where an [`ObstacleFactor`] measures the obstacles of the environment
+#[derive(Clone)]
+pub enum ObstacleSource {
+    /// A rasterized signed distance field, see
+    /// [`crate::simulation_loader::generate_sdf`]
+    Image(Arc<SdfImage>),
+    /// The shapes of the obstacles, evaluated analytically
+    Shapes(ObstacleShapes),
+}
+
+impl ObstacleSource {
+    /// Sample the source at `(x_pos, y_pos)`, where 1.0 is inside an obstacle
+    /// and 0.0 is free space. Returns `None` if the position is outside the
+    /// environment
+    fn sample(&self, world_size: WorldSize, x_pos: Float, y_pos: Float) -> Option<Float> {
+        match self {
+            Self::Image(sdf) => sample_sdf(sdf, world_size, x_pos, y_pos),
+            Self::Shapes(shapes) => shapes.sample(x_pos, y_pos),
+        }
+    }
+
+    /// Step size of the finite differences the jacobian is computed with. The
+    /// size of a pixel for an image
+    fn jacobian_delta(&self, world_size: WorldSize) -> Float {
+        match self {
+            Self::Image(sdf) => {
+                let width = world_size.width / Float::from(sdf.width());
+                let height = world_size.height / Float::from(sdf.height());
+                (width + height) / 2.0
+            }
+            Self::Shapes(_) => ObstacleShapes::JACOBIAN_DELTA,
+        }
+    }
+}
+
+impl From<Arc<SdfImage>> for ObstacleSource {
+    fn from(sdf: Arc<SdfImage>) -> Self {
+        Self::Image(sdf)
+    }
+}
+
+impl std::fmt::Debug for ObstacleSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // do not print the entire image as a pixel array
+        match self {
+            Self::Image(sdf) => f.debug_tuple("Image").field(&sdf.dimensions()).finish(),
+            Self::Shapes(shapes) => f.debug_tuple("Shapes").field(shapes).finish(),
+        }
+    }
+}
+
+/// The obstacles of the environment, measured by an [`ObstacleFactor`] from
+/// their shapes with [`gbp_environment::Environment::signed_distance`]
+/// instead of from an image. Measures the same as the SDF image, an edge
+/// `expansion` away from the obstacles blurred by `blur`
+#[derive(Clone)]
+pub struct ObstacleShapes {
+    /// Shared between all obstacle factors
+    pub environment: Arc<gbp_environment::Environment>,
+    /// SI unit: m
+    pub expansion: Float,
+    /// SI unit: m
+    pub blur: Float,
+}
+
+impl std::fmt::Debug for ObstacleShapes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObstacleShapes")
+            .field("obstacles", &self.environment.obstacles.iter().count())
+            .field("expansion", &self.expansion)
+            .field("blur", &self.blur)
+            .finish()
+    }
+}
+
+impl ObstacleShapes {
+    /// Step size of the finite differences the jacobian is computed with.
+    /// SI unit: m
+    pub const JACOBIAN_DELTA: Float = 1e-3;
+
+    /// Measure the obstacles of `environment`, with the expansion and blur of
+    /// its SDF settings
+    #[must_use]
+    pub fn new(environment: Arc<gbp_environment::Environment>) -> Self {
+        let extent = Float::from(environment.tile_extent());
+        let settings = &environment.tiles.settings.sdf;
+        let expansion = Float::from(settings.expansion) * extent;
+        let blur = Float::from(settings.blur) * extent;
+        Self {
+            environment,
+            expansion,
+            blur,
+        }
+    }
+
+    /// Sample the obstacles at `(x_pos, y_pos)`, where 1.0 is inside an
+    /// obstacle and 0.0 is free space. Returns `None` if the position is
+    /// outside the environment
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn sample(&self, x_pos: Float, y_pos: Float) -> Option<Float> {
+        let distance = self
+            .environment
+            .signed_distance(Vec2::new(x_pos as f32, y_pos as f32))?;
+        let distance = Float::from(distance) - self.expansion;
+        if self.blur <= 0.0 {
+            return Some(if distance <= 0.0 { 1.0 } else { 0.0 });
+        }
+        // a blurred edge is the cumulative normal distribution of the distance
+        // to it, approximated by a logistic function
+        Some(1.0 / (1.0 + (1.702 * distance / self.blur).exp()))
+    }
+}
+
+/// The latest sample of the signed distance field by an [`ObstacleFactor`]
 #[derive(Debug, Clone, Copy)]
 pub struct LastMeasurement {
+    /// Where the field was sampled
     pub pos:   bevy::math::Vec2,
+    /// The sampled value
     pub value: Float,
 }
 
@@ -76,42 +232,191 @@ impl Default for LastMeasurement {
     }
 }
 
+/// Sample `sdf` at `(x_pos, y_pos)` in a world of size `world_size` centered
+/// in the image, where 1.0 is inside an obstacle and 0.0 is free space.
+/// Returns `None` if the position is outside the SDF
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn sample_sdf(
+    sdf: &SdfImage,
+    world_size: WorldSize,
+    x_pos: Float,
+    y_pos: Float,
+) -> Option<Float> {
+    // The robots coordinate system is centered in the image, so we have to offset
+    // the pixel index, by half the height in the row index i.e. `y` and
+    // half the width in the column index i.e. `x`
+    let x_offset = world_size.width / 2.0;
+    let y_offset = world_size.height / 2.0;
+
+    let x_scale = Float::from(sdf.width()) / world_size.width;
+    let y_scale = Float::from(sdf.height()) / world_size.height;
+
+    let x_pixel = ((x_pos + x_offset) * x_scale) as u32;
+    // NOTE: the -y_pos is because the y axis is flipped in the image
+    let y_pixel = ((-y_pos + y_offset) * y_scale) as u32;
+
+    let pixel = sdf.get_pixel_checked(x_pixel, y_pixel)?;
+    let red_channel = pixel[0];
+    // Dark areas are obstacles, so h(0) should return a 1 for these regions.
+    Some(1.0 - Float::from(red_channel) / 255.0)
+}
+
 #[allow(clippy::missing_fields_in_debug)]
 impl std::fmt::Debug for ObstacleFactor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         // Use custom impl instead of `derive(Debug)`, to not print the entire `Image`
         // as a pixel array
         f.debug_struct("ObstacleFactor")
-            // .field("obstacle_sdf", &self.obstacle_sdf)
+            .field("source", &self.source)
             .field("world_size", &self.world_size)
+            .field("layers", &self.layers)
             .finish()
     }
 }
 
+impl Clone for ObstacleFactor {
+    fn clone(&self) -> Self {
+        Self {
+            source: self.source.clone(),
+            layers: self.layers.clone(),
+            prefetched: self.prefetched.clone(),
+            world_size: self.world_size,
+            last_measurement: Mutex::new(Cell::new(self.last_measurement())),
+            jacobian_delta: self.jacobian_delta,
+        }
+    }
+}
+
 impl ObstacleFactor {
+    /// Number of positions in [`ObstacleFactor::lookup_positions`]
+    pub const LOOKUPS: usize = 3;
     /// An obstacle factor has a single edge to another variable
     pub const NEIGHBORS: usize = 1;
 
     /// Creates a new [`ObstacleFactor`].
     #[must_use]
-    pub fn new(obstacle_sdf: SdfImage, world_size: WorldSize) -> Self {
-        let jacobian_delta = {
-            let width = world_size.width / Float::from(obstacle_sdf.width());
-            let height = world_size.height / Float::from(obstacle_sdf.height());
-            (width + height) / 2.0
-        };
-
+    pub fn new(source: impl Into<ObstacleSource>, world_size: WorldSize) -> Self {
+        let source = source.into();
         Self {
-            obstacle_sdf,
+            jacobian_delta: source.jacobian_delta(world_size),
+            source,
+            layers: BTreeMap::new(),
+            prefetched: Vec::new(),
             world_size,
             last_measurement: Default::default(),
-            jacobian_delta,
         }
     }
 
+    /// Replace the signed distance field the factor measures, e.g. with the
+    /// obstacles a robot has sensed so far. Must cover the same `world_size`
+    pub fn set_sdf(&mut self, obstacle_sdf: Arc<SdfImage>) {
+        self.set_source(ObstacleSource::Image(obstacle_sdf));
+    }
+
+    /// Replace where the factor measures the obstacles of the environment
+    pub fn set_source(&mut self, source: ObstacleSource) {
+        self.jacobian_delta = source.jacobian_delta(self.world_size);
+        self.source = source;
+        self.prefetched.clear();
+    }
+
+    /// Positions the factor samples its source at, when it is linearised at
+    /// `(x_pos, y_pos)`. The position itself, and the ones the finite
+    /// differences of the jacobian are taken at
+    #[must_use]
+    pub fn lookup_positions(&self, x_pos: Float, y_pos: Float) -> [[Float; 2]; Self::LOOKUPS] {
+        let delta = self.jacobian_delta;
+        [[x_pos, y_pos], [x_pos + delta, y_pos], [
+            x_pos,
+            y_pos + delta,
+        ]]
+    }
+
+    /// Values of the source at some positions, sampled ahead of time, e.g. in
+    /// a batch with [`crate::factorgraph::batch::ObstacleLookups`]. Measured
+    /// instead of the source at those positions, until replaced
+    pub fn set_prefetched(
+        &mut self,
+        lookups: impl IntoIterator<Item = ([Float; 2], Option<Float>)>,
+    ) {
+        self.prefetched.clear();
+        self.prefetched.extend(lookups);
+    }
+
+    /// Sample the source at `(x_pos, y_pos)`, or take the prefetched value
+    fn sample_source(&self, x_pos: Float, y_pos: Float) -> Option<Float> {
+        // undoing a perturbation of the jacobian may be off by a rounding error
+        let tolerance = self.jacobian_delta * 1e-6;
+        self.prefetched
+            .iter()
+            .find(|([x, y], _)| (x - x_pos).abs() <= tolerance && (y - y_pos).abs() <= tolerance)
+            .map_or_else(
+                || self.source.sample(self.world_size, x_pos, y_pos),
+                |(_, value)| *value,
+            )
+    }
+
+    /// Where the factor measures the obstacles of the environment
+    #[inline]
+    pub const fn source(&self) -> &ObstacleSource {
+        &self.source
+    }
+
+    /// Measure `layer` on top of the signed distance field of the
+    /// environment. Returns the layer previously stored under `name`, if any
+    pub fn insert_layer(&mut self, name: impl Into<String>, layer: SdfLayer) -> Option<SdfLayer> {
+        self.layers.insert(name.into(), layer)
+    }
+
+    /// Stop measuring the layer stored under `name`, returning it
+    pub fn remove_layer(&mut self, name: &str) -> Option<SdfLayer> {
+        self.layers.remove(name)
+    }
+
+    /// Remove all additional layers, leaving only the signed distance field of
+    /// the environment
+    pub fn clear_layers(&mut self) {
+        self.layers.clear();
+    }
+
+    /// The additional layers, by name
+    #[inline]
+    pub const fn layers(&self) -> &BTreeMap<String, SdfLayer> {
+        &self.layers
+    }
+
     pub fn last_measurement(&self) -> LastMeasurement {
         self.last_measurement.lock().unwrap().get()
     }
+
+    /// Sample the SDF and every layer at `(x_pos, y_pos)`, where 1.0 is inside
+    /// an obstacle and 0.0 is free space, and take the largest weighted value.
+    /// Returns `None` if the position is outside all of them
+    fn sample(&self, x_pos: Float, y_pos: Float) -> Option<Float> {
+        let environment = self.sample_source(x_pos, y_pos);
+        let layers = self.layers.values().map(|layer| {
+            sample_sdf(&layer.sdf, self.world_size, x_pos, y_pos).map(|value| value * layer.weight)
+        });
+        std::iter::once(environment)
+            .chain(layers)
+            .flatten()
+            .reduce(Float::max)
+    }
+
+    /// Gradient of the measurement at `pos`, computed with central differences
+    /// of size `jacobian_delta`. Points towards the nearest obstacle, i.e. the
+    /// opposite direction of where the factor pushes its variable
+    #[must_use]
+    pub fn gradient(&self, pos: Vec2) -> Vec2 {
+        let (x, y) = (Float::from(pos.x), Float::from(pos.y));
+        let delta = self.jacobian_delta;
+        let sample = |x, y| self.sample(x, y).unwrap_or(0.0);
+
+        let dx = (sample(x + delta, y) - sample(x - delta, y)) / (2.0 * delta);
+        let dy = (sample(x, y + delta) - sample(x, y - delta)) / (2.0 * delta);
+        #[allow(clippy::cast_possible_truncation)]
+        Vec2::new(dx as f32, dy as f32)
+    }
 }
 
 impl Factor for ObstacleFactor {
@@ -141,44 +446,13 @@ impl Factor for ObstacleFactor {
     fn measure(&self, _state: &FactorState, linearisation_point: &Vector<Float>) -> Measurement {
         let x_pos = linearisation_point[0];
         let y_pos = linearisation_point[1];
-        // The robots coordinate system is centered in the image, so we have to offset
-        // the pixel index, by half the height in the row index i.e. `y` and
-        // half the width in the column index i.e. `x`
-        let x_offset = self.world_size.width / 2.0;
-        let y_offset = self.world_size.height / 2.0;
-
-        let x_scale = Float::from(self.obstacle_sdf.width()) / self.world_size.width;
-        let y_scale = Float::from(self.obstacle_sdf.height()) / self.world_size.height;
-
-        let x_pixel = ((x_pos + x_offset) * x_scale) as u32;
-        // NOTE: the -y_pos is because the y axis is flipped in the image
-        let y_pixel = ((-y_pos + y_offset) * y_scale) as u32;
-
-        // dbg!((
-        //     x_pos,
-        //     y_pos,
-        //     x_offset,
-        //     y_offset,
-        //     x_scale,
-        //     y_scale,
-        //     x_pixel,
-        //     y_pixel,
-        //     self.obstacle_sdf.dimensions()
-        // ));
-
-        let Some(pixel) = self.obstacle_sdf.get_pixel_checked(x_pixel, y_pixel) else {
-            // let Some(pixel) = self.obstacle_sdf.get_pixel_checked(y_pixel, x_pixel) else
-            // { Measurement point outside of image
-            // Return 1.0 to indicate that it is an obstacle
-            // return array![1.0];
+
+        let Some(hsv_value) = self.sample(x_pos, y_pos) else {
+            // Measurement point outside of image
             // Return 0.0 to indicate that it is an empty space
             return Measurement::new(array![0.0]);
         };
 
-        let red_channel = pixel[0];
-        // Dark areas are obstacles, so h(0) should return a 1 for these regions.
-        let hsv_value = 1.0 - Float::from(red_channel) / 255.0;
-
         self.last_measurement.lock().unwrap().set(LastMeasurement {
             pos:   Vec2::new(x_pos as f32, y_pos as f32),
             value: hsv_value,
@@ -211,6 +485,119 @@ impl Factor for ObstacleFactor {
 impl std::fmt::Display for ObstacleFactor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "world_size: {}", self.world_size)?;
+        if !self.layers.is_empty() {
+            writeln!(f, "layers: {}", self.layers.keys().join(", "))?;
+        }
         writeln!(f, "last_measurement: {}", self.last_measurement())
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use gbp_environment::{Environment, Obstacles};
+
+    use super::*;
+
+    /// SDF of a 10x10 m world, with the pixel values `left` and `right` in each
+    /// half, where 0 is inside an obstacle
+    fn halves(left: u8, right: u8) -> Arc<SdfImage> {
+        Arc::new(SdfImage::from_fn(10, 10, |x, _| {
+            image::Rgb([if x < 5 { left } else { right }; 3])
+        }))
+    }
+
+    const WORLD_SIZE: WorldSize = WorldSize {
+        width:  10.0,
+        height: 10.0,
+    };
+
+    #[test]
+    fn layers_are_weighted_and_the_largest_value_wins() {
+        let mut factor = ObstacleFactor::new(halves(0, 255), WORLD_SIZE);
+        assert_eq!(factor.sample(2.5, 0.0), Some(0.0));
+
+        factor.insert_layer("zone", SdfLayer {
+            sdf:    halves(255, 0),
+            weight: 0.5,
+        });
+        assert_eq!(factor.sample(2.5, 0.0), Some(0.5));
+        assert_eq!(factor.sample(-2.5, 0.0), Some(1.0));
+
+        assert!(factor.remove_layer("zone").is_some());
+        assert_eq!(factor.sample(2.5, 0.0), Some(0.0));
+    }
+
+    #[test]
+    fn prefetched_values_are_measured_at_their_position() {
+        let mut factor = ObstacleFactor::new(halves(0, 255), WORLD_SIZE);
+        let [at, dx, dy] = factor.lookup_positions(2.5, 0.0);
+        factor.set_prefetched([(at, Some(0.25)), (dx, Some(0.5)), (dy, None)]);
+
+        assert_eq!(factor.sample(at[0], at[1]), Some(0.25));
+        assert_eq!(factor.sample(dx[0], dx[1]), Some(0.5));
+        assert_eq!(factor.sample(dy[0], dy[1]), None);
+        // anywhere else the image is sampled
+        assert_eq!(factor.sample(-2.5, 0.0), Some(1.0));
+
+        factor.set_sdf(halves(0, 255));
+        assert_eq!(factor.sample(at[0], at[1]), Some(0.0));
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+    fn analytic_shapes_agree_with_the_rasterized_sdf() {
+        const SAMPLES: usize = 81;
+
+        let mut environment = Environment::intersection();
+        environment.tiles.settings.add_boundary = true;
+        environment.tiles.settings.sdf.expansion = 0.01;
+        // without blur the edge is where the pixels turn black
+        environment.tiles.settings.sdf.blur = 0.0;
+        environment.obstacles = Obstacles::builder()
+            .add_circle((0, 0), 0.03, (0.5, 0.2))
+            .add_rectangle((0, 0), 0.06, 0.04, 0.3, (0.8, 0.5))
+            .build()
+            .unwrap();
+
+        let sdf = crate::simulation_loader::generate_sdf(&environment)
+            .unwrap()
+            .0;
+        let (width, height) = environment.dimensions();
+        let world_size = WorldSize {
+            width:  Float::from(width),
+            height: Float::from(height),
+        };
+        let shapes = ObstacleShapes::new(Arc::new(environment));
+        let factor = ObstacleFactor::new(ObstacleSource::Shapes(shapes.clone()), world_size);
+        // pixels on the edge may go either way
+        let margin = 2.0 * world_size.width / Float::from(sdf.width());
+
+        for i in 0..SAMPLES {
+            for j in 0..SAMPLES {
+                let x = ((i as Float + 0.5) / SAMPLES as Float - 0.5) * world_size.width;
+                let y = ((j as Float + 0.5) / SAMPLES as Float - 0.5) * world_size.height;
+                let distance = shapes
+                    .environment
+                    .signed_distance(Vec2::new(x as f32, y as f32))
+                    .unwrap();
+                let distance = Float::from(distance) - shapes.expansion;
+                if distance.abs() < margin {
+                    continue;
+                }
+
+                let rasterized = sample_sdf(&sdf, world_size, x, y).unwrap();
+                assert_eq!(
+                    rasterized > 0.5,
+                    distance < 0.0,
+                    "at ({x}, {y}), {distance} from the edge"
+                );
+                assert_eq!(
+                    factor.sample(x, y),
+                    Some(if distance < 0.0 { 1.0 } else { 0.0 }),
+                    "at ({x}, {y}), {distance} from the edge"
+                );
+            }
+        }
+    }
+}
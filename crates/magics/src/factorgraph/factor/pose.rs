@@ -6,10 +6,14 @@ use gbp_linalg::prelude::*;
 
 use super::{Factor, FactorState, Measurement};
 
-#[derive(Debug)]
+/// Pose factor: measures the state of a single variable directly. Anchors a
+/// variable on a manifold, where the prior alone cannot be differenced
+/// correctly, e.g. an orientation close to ±π
+#[derive(Debug, Clone, Copy)]
 pub struct PoseFactor;
 
 impl PoseFactor {
+    /// Number of variables the factor is connected to
     pub const NEIGHBORS: usize = 1;
 }
 
@@ -43,13 +47,14 @@ impl Factor for PoseFactor {
 
     #[inline(always)]
     fn linear(&self) -> bool {
-        false
+        true
     }
 
-    /// Default jacobian is the first order taylor series jacobian
+    /// The measurement is the identity function, so the jacobian is the
+    /// identity matrix
     #[inline]
-    fn jacobian(&self, state: &FactorState, x: &Vector<Float>) -> Cow<'_, Matrix<Float>> {
-        Cow::Owned(self.first_order_jacobian(state, x.clone()))
+    fn jacobian(&self, _state: &FactorState, x: &Vector<Float>) -> Cow<'_, Matrix<Float>> {
+        Cow::Owned(Matrix::<Float>::eye(x.len()))
     }
 
     /// Default measurement function is the identity function
@@ -0,0 +1,226 @@
+//! Region factor
+
+use std::{borrow::Cow, sync::Arc};
+
+use bevy::math::Vec2;
+use gbp_linalg::prelude::*;
+use itertools::Itertools;
+use ndarray::array;
+
+use super::{Factor, FactorState, Measurement};
+
+/// A cost region of the environment, in the coordinates of the simulation
+#[derive(Debug, Clone)]
+pub struct CostPolygon {
+    /// Corners of the polygon
+    pub vertices: Vec<Vec2>,
+    /// Multiplier of the cost of driving through the region
+    pub cost:     f32,
+    /// Distance from the edge over which the cost ramps up to its full value.
+    /// SI unit: m
+    pub falloff:  f32,
+}
+
+impl CostPolygon {
+    /// Whether `point` is inside the polygon, by the even-odd rule
+    #[must_use]
+    pub fn contains(&self, point: Vec2) -> bool {
+        let mut inside = false;
+        for (a, b) in self.vertices.iter().circular_tuple_windows() {
+            if (a.y > point.y) != (b.y > point.y)
+                && point.x < (b.x - a.x) * (point.y - a.y) / (b.y - a.y) + a.x
+            {
+                inside = !inside;
+            }
+        }
+        inside
+    }
+
+    /// Distance from `point` to the nearest edge of the polygon
+    #[must_use]
+    pub fn distance_to_edge(&self, point: Vec2) -> f32 {
+        self.vertices
+            .iter()
+            .circular_tuple_windows()
+            .map(|(a, b)| {
+                let ab = *b - *a;
+                let t = (point - *a).dot(ab) / ab.length_squared().max(f32::EPSILON);
+                point.distance(*a + ab * t.clamp(0.0, 1.0))
+            })
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    /// Penalty of being at `point`, from 0.0 outside the region up to
+    /// `cost - 1.0` at `falloff` or more inside it. The ramp gives the factor a
+    /// gradient pointing out of the region
+    #[must_use]
+    pub fn penalty(&self, point: Vec2) -> f32 {
+        if self.vertices.len() < 3 || !self.contains(point) {
+            return 0.0;
+        }
+        let depth = self.distance_to_edge(point) / self.falloff.max(f32::EPSILON);
+        (self.cost - 1.0).max(0.0) * depth.min(1.0)
+    }
+}
+
+/// Factor penalising a variable for being inside the cost regions of the
+/// environment, pushing it towards the nearest edge
+#[derive(Debug, Clone)]
+pub struct RegionFactor {
+    /// The cost regions, shared between all region factors
+    regions: Arc<[CostPolygon]>,
+}
+
+impl RegionFactor {
+    /// A region factor has a single edge to another variable
+    pub const NEIGHBORS: usize = 1;
+
+    /// Creates a new [`RegionFactor`].
+    #[must_use]
+    pub const fn new(regions: Arc<[CostPolygon]>) -> Self {
+        Self { regions }
+    }
+
+    /// Largest penalty of the regions containing `pos`
+    #[must_use]
+    pub fn penalty(&self, pos: Vec2) -> f32 {
+        self.regions
+            .iter()
+            .map(|region| region.penalty(pos))
+            .fold(0.0, f32::max)
+    }
+}
+
+impl Factor for RegionFactor {
+    #[inline]
+    fn name(&self) -> &'static str {
+        "RegionFactor"
+    }
+
+    fn color(&self) -> [u8; 3] {
+        // #c6a0f6
+        [198, 160, 246]
+    }
+
+    #[inline]
+    fn jacobian(
+        &self,
+        state: &FactorState,
+        linearisation_point: &Vector<Float>,
+    ) -> Cow<'_, Matrix<Float>> {
+        // Same as ObstacleFactor
+        Cow::Owned(self.first_order_jacobian(state, linearisation_point.clone()))
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn measure(&self, _state: &FactorState, linearisation_point: &Vector<Float>) -> Measurement {
+        let pos = Vec2::new(linearisation_point[0] as f32, linearisation_point[1] as f32);
+        Measurement::new(array![Float::from(self.penalty(pos))])
+    }
+
+    #[inline(always)]
+    fn jacobian_delta(&self) -> Float {
+        // well below the falloff of any sensible region
+        1e-2
+    }
+
+    #[inline(always)]
+    fn skip(&self, _state: &FactorState) -> bool {
+        false
+    }
+
+    #[inline(always)]
+    fn linear(&self) -> bool {
+        false
+    }
+
+    #[inline(always)]
+    fn neighbours(&self) -> usize {
+        Self::NEIGHBORS
+    }
+}
+
+impl std::fmt::Display for RegionFactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "regions: {}", self.regions.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::entity::Entity;
+
+    use super::*;
+    use crate::factorgraph::{
+        factor::FactorNode,
+        factorgraph::{NodeIndex, VariableIndex},
+        id::VariableId,
+        message::{InformationVec, Mean, Message, PrecisionMatrix},
+        DOFS,
+    };
+
+    fn square() -> CostPolygon {
+        CostPolygon {
+            vertices: vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(4.0, 0.0),
+                Vec2::new(4.0, 4.0),
+                Vec2::new(0.0, 4.0),
+            ],
+            cost:     3.0,
+            falloff:  1.0,
+        }
+    }
+
+    #[test]
+    fn penalty_ramps_up_from_the_edge() {
+        let region = square();
+        assert!(region.penalty(Vec2::new(-1.0, 2.0)).abs() < 1e-6);
+        assert!((region.penalty(Vec2::new(0.5, 2.0)) - 1.0).abs() < 1e-6);
+        assert!((region.penalty(Vec2::new(2.0, 2.0)) - 2.0).abs() < 1e-6);
+    }
+
+    /// Precision of the message `factor` sends to its variable, after it has
+    /// been told that the variable is at `(x, 2.0)`
+    #[allow(clippy::unwrap_used)]
+    fn message_precision(factor: &mut FactorNode, x: Float) -> Float {
+        let variable = VariableId::new(Entity::from_raw(0), VariableIndex(NodeIndex::new(0)));
+        factor.receive_message_from(
+            variable,
+            Message::new(
+                InformationVec(Vector::<Float>::zeros(DOFS)),
+                PrecisionMatrix(Matrix::<Float>::zeros((DOFS, DOFS))),
+                Mean(array![x, 2.0, 0.0, 0.0]),
+            ),
+        );
+        let responses = factor.update();
+        responses[0].1.precision_matrix().unwrap()[[0, 0]]
+    }
+
+    fn factor() -> FactorNode {
+        FactorNode::new_region_factor(
+            Entity::from_raw(0),
+            1.0,
+            array![0.0],
+            vec![square()].into(),
+            true,
+        )
+    }
+
+    #[test]
+    fn region_factor_is_relinearised_when_its_variable_moves() {
+        let mut factor = factor();
+        // on the ramp the penalty changes with x, in the middle it is flat
+        assert!(message_precision(&mut factor, 0.5) > 0.0);
+        assert!(message_precision(&mut factor, 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn without_jit_linearisation_the_first_linearisation_is_kept() {
+        let mut factor = factor();
+        factor.state.set_jit_linearisation(false);
+        let on_the_ramp = message_precision(&mut factor, 0.5);
+        assert!(on_the_ramp > 0.0);
+        assert_eq!(message_precision(&mut factor, 2.0), on_the_ramp);
+    }
+}
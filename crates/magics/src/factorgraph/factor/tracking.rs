@@ -39,12 +39,26 @@ impl Default for Tracking {
     }
 }
 
+impl Clone for Tracking {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            index: self.index,
+            record: Mutex::new(Cell::new(self.get_record())),
+            connections: Mutex::new(Cell::new(self.connections.lock().unwrap().get())),
+            config: self.config.clone(),
+        }
+    }
+}
+
 impl Tracking {
+    /// Follow `path`
     pub fn with_path(mut self, path: Vec<Vec2>) -> Self {
         self.path = Some(path);
         self
     }
 
+    /// Use `config` for the tracking
     pub fn with_config(mut self, config: gbp_config::TrackingSection) -> Self {
         self.config = config;
         self
@@ -63,11 +77,13 @@ impl Tracking {
         record.set(new_record);
     }
 
+    /// Index of the latest waypoint the factor has reached
     pub fn get_record(&self) -> usize {
         self.record.lock().unwrap().get()
     }
 }
 
+/// Tracking factor: pulls a variable towards the path it is following
 #[derive(Debug)]
 pub struct TrackingFactor {
     /// Tracking information from global path finder
@@ -78,9 +94,12 @@ pub struct TrackingFactor {
     timeout: Mutex<Cell<Option<usize>>>,
 }
 
+/// Position and value of the most recent measurement of a tracking factor
 #[derive(Debug, Clone, Copy)]
 pub struct LastMeasurement {
+    /// Where the measurement was taken
     pub pos:   bevy::math::Vec2,
+    /// The measured value
     pub value: Float,
 }
 
@@ -93,8 +112,18 @@ impl Default for LastMeasurement {
     }
 }
 
+impl Clone for TrackingFactor {
+    fn clone(&self) -> Self {
+        Self {
+            tracking: self.tracking.clone(),
+            last_measurement: Mutex::new(Cell::new(self.last_measurement())),
+            timeout: Mutex::new(Cell::new(self.timeout.lock().unwrap().get())),
+        }
+    }
+}
+
 impl TrackingFactor {
-    /// An obstacle factor has a single edge to another variable
+    /// A tracking factor has a single edge to another variable
     pub const NEIGHBORS: usize = 1;
 
     /// Creates a new [`TrackingFactor`].
@@ -107,6 +136,7 @@ impl TrackingFactor {
         }
     }
 
+    /// Start from a measurement of `value` at `pos`
     pub fn with_last_measurement(self, pos: Vec2, value: Float) -> Self {
         self.last_measurement
             .lock()
@@ -116,6 +146,7 @@ impl TrackingFactor {
         self
     }
 
+    /// Use `config` for the tracking
     pub fn with_config(mut self, config: gbp_config::TrackingSection) -> Self {
         self.tracking.config = config;
         self
@@ -131,10 +162,12 @@ impl TrackingFactor {
         &self.tracking
     }
 
+    /// Follow `tracking_path` instead of the current path
     pub fn set_tracking_path(&mut self, tracking_path: min_len_vec::TwoOrMore<Vec2>) {
         self.tracking.path = Some(tracking_path.into());
     }
 
+    /// Move towards the waypoint at `index` of the path
     pub fn set_tracking_index(&mut self, index: usize) {
         if let Some(path) = &self.tracking.path {
             assert!(index < path.len());
@@ -142,6 +175,7 @@ impl TrackingFactor {
         }
     }
 
+    /// Measure from `mean` until the next update
     pub fn set_linearisation_point(&mut self, mean: Vec2) {
         // self.tracking.path = None;
         self.last_measurement.lock().unwrap().set(LastMeasurement {
@@ -150,6 +184,7 @@ impl TrackingFactor {
         });
     }
 
+    /// Skip the next `iterations` updates of the factor
     pub fn set_timeout(&mut self, iterations: usize) {
         self.timeout.lock().unwrap().set(Some(iterations));
     }
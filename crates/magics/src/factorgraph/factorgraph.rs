@@ -1,8 +1,11 @@
+use std::collections::{HashMap, HashSet};
+
 use bevy::{
     ecs::{component::Component, entity::Entity},
     log::{debug, info},
     math::Vec2,
 };
+use gbp_config::MessageSchedule;
 // use gbp_linalg::Float;
 use gbp_linalg::prelude::*;
 use itertools::Itertools;
@@ -15,13 +18,39 @@ use super::{
         FactorKind, FactorNode,
     },
     id::{FactorId, VariableId},
+    json,
     message::{FactorToVariableMessage, VariableToFactorMessage},
     node::{FactorGraphNode, Node, NodeKind, RemoveConnectionToError},
     prelude::Message,
+    settings::{GbpSettings, SolveSettings},
     variable::VariableNode,
-    MessageCount, MessagesReceived, MessagesSent,
+    MessageCount, MessagesReceived, MessagesSent, DOFS,
 };
 
+/// How much `message` differs from `previous`, the message it replaces, as the
+/// norm of the difference of their information vectors plus the Frobenius
+/// norm of the difference of their precision matrices. Infinite if there is
+/// nothing to compare with
+fn message_residual(previous: Option<&Message>, message: &Message) -> Float {
+    let (Some(previous), Some(current)) = (previous.and_then(Message::payload), message.payload())
+    else {
+        return Float::INFINITY;
+    };
+    if previous.information_vector.shape() != current.information_vector.shape()
+        || previous.precision_matrix.shape() != current.precision_matrix.shape()
+    {
+        return Float::INFINITY;
+    }
+
+    let information = (&current.information_vector - &previous.information_vector)
+        .mapv(|x| x * x)
+        .sum();
+    let precision = (&current.precision_matrix - &previous.precision_matrix)
+        .mapv(|x| x * x)
+        .sum();
+    information.sqrt() + precision.sqrt()
+}
+
 /// type alias used to represent the id of the factorgraph
 /// Since we use **Bevy** we can use the `Entity` id of the whatever entity the
 /// the factorgraph is attached to as a Component, as its unique identifier.
@@ -69,9 +98,21 @@ struct IterationCount {
     factor:   usize,
 }
 
+/// Summary of the convergence of a factorgraph, taken after a GBP iteration
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SolveReport {
+    /// Number of internal variable iterations run so far
+    pub iteration: usize,
+    /// Sum of the energy of every enabled factor
+    pub energy:    Float,
+    /// Largest change of the belief mean of a variable in the latest internal
+    /// variable iteration
+    pub residual:  Float,
+}
+
 /// A factor graph is a bipartite graph consisting of two types of nodes:
 /// factors and variables.
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone)]
 // #[cfg_attr(feature = "bevy", derive(Component))]
 pub struct FactorGraph {
     /// The id of the factorgraph. We store a copy of it here, for convenience.
@@ -85,7 +126,10 @@ pub struct FactorGraph {
 
     iteration_count: IterationCount,
 
-    message_count:    MessageCount,
+    message_count: MessageCount,
+    /// Largest change of the belief mean of a variable in the latest internal
+    /// variable iteration
+    residual: Float,
     /// In **gbpplanner** the sequence in which variables are inserted/created
     /// in the graph is meaningful. `self.graph` does not capture this
     /// ordering, so we use an extra vector to manage the order in which
@@ -96,7 +140,7 @@ pub struct FactorGraph {
     variable_indices: Vec<NodeIndex>,
     /// List of indices of the factors in the graph. Order is not important.
     /// Used to speed up iteration over factors.
-    factor_indices:   Vec<NodeIndex>,
+    factor_indices: Vec<NodeIndex>,
 
     /// List of indices of the interrobot factors in the graph. Order is not
     /// important. Used to speed up iteration over interrobot factors.
@@ -117,6 +161,65 @@ pub struct FactorGraph {
     /// List of indices of the tracking factors in the graph.
     /// Used to speed up iteration over tracking factors.
     tracking_factor_indices: Vec<NodeIndex>,
+
+    /// List of indices of the region factors in the graph.
+    /// Used to speed up iteration over region factors.
+    region_factor_indices: Vec<NodeIndex>,
+
+    /// List of indices of the pose factors in the graph.
+    /// Used to speed up iteration over pose factors.
+    pose_factor_indices: Vec<NodeIndex>,
+
+    /// Settings of the message passing, applied to every node in the graph
+    settings: GbpSettings,
+    /// Largest change of the messages sent by each internal factor, the last
+    /// time it sent. Only kept for [`MessageSchedule::ResidualPriority`]
+    factor_residuals: HashMap<NodeIndex, Float>,
+    /// Source of the order of [`MessageSchedule::RandomSequential`]
+    rng: fastrand::Rng,
+}
+
+/// Builder of a [`FactorGraph`], created with [`FactorGraph::builder`]
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct FactorGraphBuilder {
+    id:       FactorGraphId,
+    settings: GbpSettings,
+    nodes:    usize,
+    edges:    usize,
+}
+
+impl FactorGraphBuilder {
+    /// Start building an empty factorgraph with a given id and the default
+    /// [`GbpSettings`]
+    pub fn new(id: FactorGraphId) -> Self {
+        Self {
+            id,
+            settings: GbpSettings::default(),
+            nodes: 0,
+            edges: 0,
+        }
+    }
+
+    /// Use `settings` for the message passing in the factorgraph
+    pub const fn settings(mut self, settings: GbpSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Reserve capacity for `nodes` nodes and `edges` edges
+    pub const fn capacity(mut self, nodes: usize, edges: usize) -> Self {
+        self.nodes = nodes;
+        self.edges = edges;
+        self
+    }
+
+    /// Build the empty factorgraph
+    pub fn build(self) -> FactorGraph {
+        let mut factorgraph = FactorGraph::with_capacity(self.id, self.nodes, self.edges);
+        factorgraph.settings = self.settings;
+        factorgraph
+    }
 }
 
 // macro_rules! internal_factor_iteration_inner {
@@ -150,12 +253,18 @@ impl FactorGraph {
             graph: Graph::with_capacity(0, 0),
             message_count: MessageCount::default(),
             iteration_count: IterationCount::default(),
+            residual: 0.0,
             variable_indices: Vec::new(),
             factor_indices: Vec::new(),
             interrobot_factor_indices: Vec::new(),
             obstacle_factor_indices: Vec::new(),
             dynamic_factor_indices: Vec::new(),
             tracking_factor_indices: Vec::new(),
+            region_factor_indices: Vec::new(),
+            pose_factor_indices: Vec::new(),
+            settings: GbpSettings::default(),
+            factor_residuals: HashMap::new(),
+            rng: fastrand::Rng::with_seed(id.to_bits()),
         }
     }
 
@@ -170,13 +279,26 @@ impl FactorGraph {
             factor_indices: Vec::with_capacity(edges),
             message_count: MessageCount::default(),
             iteration_count: IterationCount::default(),
+            residual: 0.0,
             interrobot_factor_indices: Vec::new(),
             obstacle_factor_indices: Vec::new(),
             dynamic_factor_indices: Vec::new(),
             tracking_factor_indices: Vec::new(),
+            region_factor_indices: Vec::new(),
+            pose_factor_indices: Vec::new(),
+            settings: GbpSettings::default(),
+            factor_residuals: HashMap::new(),
+            rng: fastrand::Rng::with_seed(id.to_bits()),
         }
     }
 
+    /// Start building a factorgraph with a given id, see
+    /// [`FactorGraphBuilder`]
+    #[must_use]
+    pub fn builder(id: FactorGraphId) -> FactorGraphBuilder {
+        FactorGraphBuilder::new(id)
+    }
+
     /// Returns the `FactorGraphId` of the factorgraph
     #[inline(always)]
     #[must_use]
@@ -195,6 +317,10 @@ impl FactorGraph {
             .as_variable_mut()
             .expect("just added the variable to the graph in the previous statement")
             .set_node_index(node_index);
+        self.graph[node_index]
+            .as_variable_mut()
+            .expect("just added the variable to the graph in the previous statement")
+            .set_regularisation_floor(self.settings.regularisation_floor);
         debug!(
             "added a variable with node_index: {:?} to factorgraph: {:?}",
             node_index, self.id
@@ -213,6 +339,7 @@ impl FactorGraph {
             .as_factor_mut()
             .expect("just added the factor to the graph in the previous statement");
         factor.set_node_index(node_index);
+        factor.state.set_jit_linearisation(self.settings.jit_linearisation);
 
         self.factor_indices.push(node_index);
         match factor.kind {
@@ -220,6 +347,8 @@ impl FactorGraph {
             FactorKind::Dynamic(_) => self.dynamic_factor_indices.push(node_index),
             FactorKind::Obstacle(_) => self.obstacle_factor_indices.push(node_index),
             FactorKind::Tracking(_) => self.tracking_factor_indices.push(node_index),
+            FactorKind::Region(_) => self.region_factor_indices.push(node_index),
+            FactorKind::Pose(_) => self.pose_factor_indices.push(node_index),
         }
 
         node_index.into()
@@ -266,6 +395,8 @@ impl FactorGraph {
             interrobot: self.interrobot_factor_indices.len(),
             dynamic:    self.dynamic_factor_indices.len(),
             tracking:   self.tracking_factor_indices.len(),
+            region:     self.region_factor_indices.len(),
+            pose:       self.pose_factor_indices.len(),
         }
     }
 
@@ -310,7 +441,14 @@ impl FactorGraph {
         variable.receive_message_from(factor_id, Message::empty());
 
         let variable_message = variable.prepare_message().clone();
+        let manifold = variable.manifold();
         let node = &mut self.graph[factor_id.factor_index.0];
+        if let NodeKind::Factor(ref factor) = node.kind {
+            debug_assert!(
+                manifold.is_euclidean() || factor.state.variable_manifolds.contains(&manifold),
+                "a factor connected to a variable on {manifold:?} has to be created for it",
+            );
+        }
         match node.kind {
             NodeKind::Factor(ref mut factor) if factor.is_tracking() => {
                 factor.receive_message_from(variable_id, variable_message);
@@ -350,6 +488,7 @@ impl FactorGraph {
         //     variable_index, factor_id, self.id
         // );
         variable.receive_message_from(factor_id, Message::empty());
+        self.debug_assert_consistent("adding an external edge");
     }
 
     /// Get the index of the nth variable in the factorgraph
@@ -411,6 +550,8 @@ impl FactorGraph {
                 );
 
                 self.factor_indices.retain(|&idx| idx != node_index);
+                self.interrobot_factor_indices
+                    .retain(|&idx| idx != node_index);
 
                 factor_indices_to_remove.push(FactorIndex(node_index));
             }
@@ -433,6 +574,8 @@ impl FactorGraph {
                     .remove(&FactorId::new(self.id, *factor_index));
             }
         }
+
+        self.debug_assert_consistent("deleting interrobot factors");
     }
 
     pub(crate) fn delete_messages_from_interrobot_factor_at(&mut self, other: FactorGraphId) {
@@ -683,34 +826,144 @@ impl FactorGraph {
         messages_to_external_factors
     }
 
+    /// Set in which order the internal factors send their messages, see
+    /// [`MessageSchedule`]
+    pub fn set_message_schedule(&mut self, message_schedule: MessageSchedule) {
+        if message_schedule != self.settings.message_schedule {
+            self.factor_residuals.clear();
+        }
+        self.settings.message_schedule = message_schedule;
+    }
+
+    /// Whether the factor at `ix` takes part in the internal factor iteration.
+    /// Interrobot factors are iterated externally, and the tracking factors
+    /// are only used once the path has settled a bit
+    fn is_internally_iterated(&self, ix: NodeIndex) -> bool {
+        let factor = self.graph[ix].factor();
+        if !factor.enabled {
+            return false;
+        }
+        match factor.kind {
+            FactorKind::InterRobot(_) => false,
+            FactorKind::Tracking(_) => self.iteration_count.factor >= 10,
+            _ => true,
+        }
+    }
+
     /// Internal Factor Iteration in Gaussian Belief Propagation (GBP).
     /// Only takes into account factors that are not interrobot factors.
+    /// The order of the messages depends on the [`MessageSchedule`] set with
+    /// [`FactorGraph::set_message_schedule`]
     pub fn internal_factor_iteration(&mut self) {
+        match self.settings.message_schedule {
+            MessageSchedule::Synchronous => self.synchronous_factor_iteration(),
+            MessageSchedule::ResidualPriority | MessageSchedule::RandomSequential => {
+                self.sequential_factor_iteration();
+            }
+        }
+        self.iteration_count.factor += 1;
+    }
+
+    /// Every factor sends its messages, based on the messages the variables
+    /// sent in the previous iteration
+    fn synchronous_factor_iteration(&mut self) {
         for i in 0..self.factor_indices.len() {
             let ix = self.factor_indices[i];
-            let node = &mut self.graph[ix];
-            let factor = node.factor_mut();
-            // Ignore if interrobot factor
-
-            if !factor.enabled {
+            if !self.is_internally_iterated(ix) {
                 continue;
             }
 
-            match factor.kind {
-                FactorKind::InterRobot(_) => continue,
-                FactorKind::Tracking(_) if self.iteration_count.factor < 10 => continue,
-                _ => (),
+            let variable_messages = self.graph[ix].factor_mut().update();
+            let factor_id = FactorId::new(self.id, FactorIndex(ix));
+
+            for (variable_id, message) in variable_messages {
+                let variable = self.variable_mut(variable_id.variable_index);
+                variable.receive_message_from(factor_id, message);
+            }
+        }
+    }
+
+    /// The factors send their messages one at a time, in the order of the
+    /// [`MessageSchedule`], and every variable that receives a message updates
+    /// its belief and responds to its factors right away. A factor later in
+    /// the order therefore sees the effect of the factors before it in the
+    /// same iteration.
+    fn sequential_factor_iteration(&mut self) {
+        let mut order = self
+            .factor_indices
+            .iter()
+            .copied()
+            .filter(|&ix| self.is_internally_iterated(ix))
+            .collect::<Vec<_>>();
+
+        match self.settings.message_schedule {
+            MessageSchedule::RandomSequential => self.rng.shuffle(&mut order),
+            MessageSchedule::ResidualPriority => {
+                // factors that have not sent yet go first
+                let residual = |ix: &NodeIndex| {
+                    self.factor_residuals
+                        .get(ix)
+                        .copied()
+                        .unwrap_or(Float::INFINITY)
+                };
+                order.sort_by(|a, b| residual(b).total_cmp(&residual(a)));
             }
+            MessageSchedule::Synchronous => {}
+        }
 
-            let variable_messages = factor.update();
+        let track_residuals = self.settings.message_schedule == MessageSchedule::ResidualPriority;
+        if track_residuals {
+            // every factor in the order records its residual again below, which
+            // also forgets the factors that are no longer iterated
+            self.factor_residuals.clear();
+        }
+
+        for ix in order {
+            let variable_messages = self.graph[ix].factor_mut().update();
             let factor_id = FactorId::new(self.id, FactorIndex(ix));
 
+            let mut factor_residual: Float = 0.0;
             for (variable_id, message) in variable_messages {
                 let variable = self.variable_mut(variable_id.variable_index);
+                if track_residuals {
+                    factor_residual = factor_residual
+                        .max(message_residual(variable.inbox.get(&factor_id), &message));
+                }
                 variable.receive_message_from(factor_id, message);
+                let factor_messages = variable.update_belief_and_create_factor_responses();
+                self.send_to_internal_factors(variable_id, factor_messages);
+            }
+
+            if track_residuals {
+                self.factor_residuals.insert(ix, factor_residual);
             }
         }
-        self.iteration_count.factor += 1;
+    }
+
+    /// Deliver the messages a variable of this factorgraph created to the
+    /// enabled factors of this factorgraph. Messages to factors of other
+    /// factorgraphs are dropped
+    fn send_to_internal_factors(
+        &mut self,
+        variable_id: VariableId,
+        factor_messages: impl IntoIterator<Item = (FactorId, Message)>,
+    ) {
+        for (factor_id, message) in factor_messages {
+            let in_internal_graph = factor_id.factorgraph_id == self.id;
+            if !in_internal_graph {
+                // TODO: should not happen
+                continue;
+            }
+            let factor = self.graph[factor_id.factor_index.0]
+                .as_factor_mut()
+                .expect("a factor only has variables as neighbours");
+
+            if !factor.enabled {
+                continue;
+            }
+
+            factor.receive_message_from(variable_id, message);
+        }
     }
 
     /// External Factor Iteration in Gaussian Belief Propagation (GBP).
@@ -721,7 +974,17 @@ impl FactorGraph {
         // So we can preallocate a vec of length the number of interrobot factors
         let mut messages_to_external_variables: Vec<FactorToVariableMessage> =
             Vec::with_capacity(self.interrobot_factor_indices.len());
+        self.external_factor_iteration_into(&mut messages_to_external_variables);
+        messages_to_external_variables
+    }
 
+    /// Same as [`FactorGraph::external_factor_iteration`], but appends the
+    /// messages to `messages_to_external_variables`, so the caller can reuse
+    /// the same buffer between iterations
+    pub fn external_factor_iteration_into(
+        &mut self,
+        messages_to_external_variables: &mut Vec<FactorToVariableMessage>,
+    ) {
         for i in 0..self.interrobot_factor_indices.len() {
             let ix = self.interrobot_factor_indices[i];
             if !self.graph.contains_node(ix) {
@@ -755,44 +1018,70 @@ impl FactorGraph {
         }
 
         self.iteration_count.factor += 1;
-
-        messages_to_external_variables
     }
 
     pub fn internal_variable_iteration(&mut self) {
-        for &ix in &self.variable_indices {
+        let mut residual: Float = 0.0;
+        for i in 0..self.variable_indices.len() {
+            let ix = self.variable_indices[i];
             let node = &mut self.graph[ix];
             let variable = node.variable_mut();
             let variable_index = VariableIndex(ix);
             let variable_id = VariableId::new(self.id, variable_index);
+            // copied onto the stack, to not allocate a vector per variable per iteration
+            let previous_mean: [Float; DOFS] = std::array::from_fn(|i| variable.belief.mean[i]);
             // TODO: do internal only
             let factor_messages = variable.update_belief_and_create_factor_responses();
+            residual = residual.max(
+                previous_mean
+                    .iter()
+                    .zip(variable.belief.mean.iter())
+                    .map(|(previous, current)| (current - previous).powi(2))
+                    .sum::<Float>()
+                    .sqrt(),
+            );
 
-            for (factor_id, message) in factor_messages {
-                let in_internal_graph = factor_id.factorgraph_id == self.id;
-                if !in_internal_graph {
-                    // TODO: should not happen
-                    continue;
-                }
-                let factor = self.graph[factor_id.factor_index.0]
-                    .as_factor_mut()
-                    .expect("a factor only has variables as neighbours");
-
-                if !factor.enabled {
-                    continue;
-                }
-
-                factor.receive_message_from(variable_id, message);
-            }
+            self.send_to_internal_factors(variable_id, factor_messages);
         }
 
+        self.residual = residual;
         self.iteration_count.variable += 1;
     }
 
+    /// Summarise the convergence of the factorgraph. The energy is computed
+    /// on demand, so this is linear in the number of factors
+    #[must_use]
+    pub fn solve_report(&self) -> SolveReport {
+        let energy = self
+            .factor_indices
+            .iter()
+            .filter_map(|&ix| self.graph.node_weight(ix)?.as_factor())
+            .filter(|factor| factor.enabled)
+            .map(FactorNode::energy)
+            .sum();
+
+        SolveReport {
+            iteration: self.iteration_count.variable,
+            energy,
+            residual: self.residual,
+        }
+    }
+
     // TODO(kpbaks): does this method even make sense?
     #[must_use]
     pub fn external_variable_iteration(&mut self) -> Vec<VariableToFactorMessage> {
         let mut messages_to_external_factors: Vec<VariableToFactorMessage> = Vec::new();
+        self.external_variable_iteration_into(&mut messages_to_external_factors);
+        messages_to_external_factors
+    }
+
+    /// Same as [`FactorGraph::external_variable_iteration`], but appends the
+    /// messages to `messages_to_external_factors`, so the caller can reuse the
+    /// same buffer between iterations
+    pub fn external_variable_iteration_into(
+        &mut self,
+        messages_to_external_factors: &mut Vec<VariableToFactorMessage>,
+    ) {
         for &ix in &self.variable_indices {
             let node = &mut self.graph[ix];
             let variable = node.variable_mut();
@@ -821,8 +1110,6 @@ impl FactorGraph {
         }
 
         self.iteration_count.variable += 1;
-
-        messages_to_external_factors
     }
 
     /// Aggregate and marginalise over all adjacent variables, and send.
@@ -880,6 +1167,19 @@ impl FactorGraph {
             .sum()
     }
 
+    /// Returns the number of variables and factors whose last update ran into
+    /// a non-finite value
+    #[must_use]
+    pub fn non_finite_node_count(&self) -> usize {
+        self.graph
+            .node_weights()
+            .filter(|node| match &node.kind {
+                NodeKind::Variable(variable) => variable.is_non_finite(),
+                NodeKind::Factor(factor) => factor.is_non_finite(),
+            })
+            .count()
+    }
+
     /// Returns the number of messages received by all variables and factors
     #[must_use]
     pub fn messages_received(&self) -> MessagesReceived {
@@ -909,6 +1209,110 @@ impl FactorGraph {
         }
     }
 
+    /// Update the radius of the robot used by the interrobot factors, keeping
+    /// their safety distance multiplier
+    pub fn update_robot_radius(&mut self, robot_radius: StrictlyPositiveFinite<Float>) {
+        for ix in &self.interrobot_factor_indices {
+            let factor = self.graph[*ix].factor_mut();
+            let FactorKind::InterRobot(ref mut interrobot) = factor.kind else {
+                panic!("Expected an interrobot factor");
+            };
+            interrobot.update_robot_radius(robot_radius);
+        }
+    }
+
+    /// Scale the strength of every factor for which `applies` returns true by
+    /// `scale`
+    pub fn scale_factor_strengths(&mut self, applies: impl Fn(&FactorKind) -> bool, scale: Float) {
+        for &ix in &self.factor_indices {
+            let factor = self.graph[ix].factor_mut();
+            if applies(&factor.kind) {
+                let strength = factor.state.strength * scale;
+                factor.state.set_strength(strength);
+            }
+        }
+    }
+
+    /// Enable or disable just in time linearisation of the nonlinear factors
+    /// in the factorgraph, including factors added later
+    pub fn set_jit_linearisation(&mut self, jit_linearisation: bool) {
+        self.settings.jit_linearisation = jit_linearisation;
+        for ix in &self.factor_indices {
+            let Some(factor) = self.graph.node_weight_mut(*ix).and_then(Node::as_factor_mut)
+            else {
+                continue;
+            };
+            factor.state.set_jit_linearisation(jit_linearisation);
+        }
+    }
+
+    /// Set the regularisation floor added to the diagonal of the precision
+    /// matrix of every variable in the factorgraph
+    pub fn set_precision_regularisation_floor(&mut self, regularisation_floor: Float) {
+        self.settings.regularisation_floor = regularisation_floor;
+        for ix in &self.variable_indices {
+            let Some(variable) = self
+                .graph
+                .node_weight_mut(*ix)
+                .and_then(Node::as_variable_mut)
+            else {
+                continue;
+            };
+            variable.set_regularisation_floor(regularisation_floor);
+        }
+    }
+
+    /// The settings of the message passing in the factorgraph
+    #[inline]
+    #[must_use]
+    pub const fn settings(&self) -> &GbpSettings {
+        &self.settings
+    }
+
+    /// Apply `settings` to every node in the factorgraph, including nodes
+    /// added later
+    pub fn apply_settings(&mut self, settings: &GbpSettings) {
+        self.set_message_schedule(settings.message_schedule);
+        self.set_jit_linearisation(settings.jit_linearisation);
+        self.set_precision_regularisation_floor(settings.regularisation_floor);
+    }
+
+    /// Iterate the factorgraph on its own, without exchanging messages with
+    /// the factorgraphs of other robots, and report how far it converged
+    pub fn solve(&mut self, settings: &SolveSettings) -> SolveReport {
+        for _ in 0..settings.iterations {
+            self.internal_factor_iteration();
+            self.internal_variable_iteration();
+        }
+        self.solve_report()
+    }
+
+    /// Take over the state of `solved`, a copy of this factorgraph that has
+    /// been iterated elsewhere, e.g. on another thread.
+    /// The graph may have changed since the copy was taken, so only nodes and
+    /// connections present in both graphs are updated. The priors of the
+    /// variables are left untouched.
+    pub fn apply_solution(&mut self, solved: &Self) {
+        debug_assert_eq!(self.id, solved.id);
+        for node_index in self.graph.node_indices().collect::<Vec<_>>() {
+            let Some(solved_node) = solved.graph.node_weight(node_index) else {
+                continue;
+            };
+            match (&mut self.graph[node_index].kind, &solved_node.kind) {
+                (NodeKind::Variable(variable), NodeKind::Variable(solved_variable)) => {
+                    variable.apply_solution(solved_variable);
+                }
+                (NodeKind::Factor(factor), NodeKind::Factor(solved_factor)) => {
+                    factor.apply_solution(solved_factor);
+                }
+                _ => {}
+            }
+        }
+
+        self.iteration_count = solved.iteration_count;
+        self.residual = solved.residual;
+    }
+
     // pub fn receive_variable_message_from(&mut self,)
 }
 
@@ -941,6 +1345,10 @@ pub struct FactorCount {
     pub dynamic:    usize,
     /// Number of `TrackingFactor`s
     pub tracking:   usize,
+    /// Number of `RegionFactor`s
+    pub region:     usize,
+    /// Number of `PoseFactor`s
+    pub pose:       usize,
 }
 
 /// Iterator over the factors in the factorgraph.
@@ -1463,6 +1871,42 @@ impl FactorGraph {
 }
 
 impl FactorGraph {
+    /// Modify the obstacle factors in the factorgraph
+    pub fn modify_obstacle_factors(&mut self, mut f: impl FnMut(&mut ObstacleFactor)) {
+        for ix in &self.obstacle_factor_indices {
+            let node = &mut self.graph[*ix];
+            let factor = node.factor_mut();
+            let FactorKind::Obstacle(ref mut inner) = factor.kind else {
+                panic!("Expected an obstacle factor");
+            };
+            f(inner);
+        }
+    }
+
+    /// Modify the obstacle factors in the factorgraph, along with the position
+    /// each will be linearised at in its next update, i.e. the mean of its
+    /// variable as of the last message it received. `None` if it has not
+    /// received one yet
+    pub fn modify_obstacle_factors_at(
+        &mut self,
+        mut f: impl FnMut(&mut ObstacleFactor, Option<[Float; 2]>),
+    ) {
+        for ix in &self.obstacle_factor_indices {
+            let node = &mut self.graph[*ix];
+            let factor = node.factor_mut();
+            let position = factor
+                .inbox
+                .values()
+                .next()
+                .and_then(Message::mean)
+                .map(|mean| [mean[0], mean[1]]);
+            let FactorKind::Obstacle(ref mut inner) = factor.kind else {
+                panic!("Expected an obstacle factor");
+            };
+            f(inner, position);
+        }
+    }
+
     /// Modify the tracking factors in the factorgraph
     pub fn modify_tracking_factors(&mut self, mut f: impl FnMut(&mut TrackingFactor)) {
         for ix in &self.tracking_factor_indices {
@@ -1485,9 +1929,12 @@ impl graphviz::ExportGraph for FactorGraph {
             .node_indices()
             .map(|node_index| {
                 let node = &self.graph[node_index];
+                let sent = node.messages_sent();
+                let received = node.messages_received();
                 graphviz::Node {
-                    index: node_index.index(),
-                    kind:  match &node.kind {
+                    index:    node_index.index(),
+                    messages: sent.internal + sent.external + received.internal + received.external,
+                    kind:     match &node.kind {
                         NodeKind::Factor(factor) => match factor.kind {
                             FactorKind::Dynamic(_) => graphviz::NodeKind::DynamicFactor,
                             FactorKind::Obstacle(_) => graphviz::NodeKind::ObstacleFactor,
@@ -1498,6 +1945,8 @@ impl graphviz::ExportGraph for FactorGraph {
                                 }
                             }
                             FactorKind::Tracking(_) => graphviz::NodeKind::TrackingFactor,
+                            FactorKind::Region(_) => graphviz::NodeKind::RegionFactor,
+                            FactorKind::Pose(_) => graphviz::NodeKind::PoseFactor,
                         },
                         NodeKind::Variable(variable) => {
                             let [x, y] = variable.estimated_position();
@@ -1525,6 +1974,61 @@ impl graphviz::ExportGraph for FactorGraph {
     }
 }
 
+impl FactorGraph {
+    /// Take a snapshot of the beliefs of all variables and the state of all
+    /// factors, e.g. to serialize it as JSON with
+    /// [`json::FactorGraphDump::to_json_pretty()`]
+    #[must_use]
+    pub fn dump(&self) -> json::FactorGraphDump {
+        let variables = self
+            .variables()
+            .map(|(VariableIndex(index), variable)| json::VariableDump {
+                index: index.index(),
+                mean: variable.belief.mean.to_vec(),
+                covariance: json::rows(&variable.belief.covariance_matrix),
+                information_vector: variable.belief.information_vector.to_vec(),
+                precision: json::rows(&variable.belief.precision_matrix),
+                condition_number: variable.belief.condition_number,
+            })
+            .collect();
+
+        let factors = self
+            .factors()
+            .map(|(index, factor)| json::FactorDump {
+                index: index.index(),
+                kind: factor.kind.name(),
+                enabled: factor.enabled,
+                variables: self
+                    .graph
+                    .neighbors(index)
+                    .map(|neighbour| neighbour.index())
+                    .sorted()
+                    .collect(),
+                external_variable: match factor.kind {
+                    FactorKind::InterRobot(ref interrobot) => Some(format!(
+                        "{:?}-{}",
+                        interrobot.external_variable.factorgraph_id,
+                        interrobot.external_variable.variable_index.0.index()
+                    )),
+                    _ => None,
+                },
+                measurement: factor.state.initial_measurement.to_vec(),
+                predicted: factor.state.cached_measurement.to_vec(),
+                linearisation_point: factor.state.linearisation_point.to_vec(),
+                precision: json::rows(&factor.state.measurement_precision),
+                strength: factor.state.strength,
+                energy: factor.energy(),
+            })
+            .collect();
+
+        json::FactorGraphDump {
+            factorgraph: format!("{:?}", self.id),
+            variables,
+            factors,
+        }
+    }
+}
+
 impl FactorGraph {
     pub fn change_factor_enabled(&mut self, settings: gbp_config::FactorsEnabledSection) {
         for &ix in self.factor_indices.iter() {
@@ -1534,6 +2038,10 @@ impl FactorGraph {
                 FactorKind::Obstacle(_) => settings.obstacle,
                 FactorKind::InterRobot(_) => settings.interrobot,
                 FactorKind::Tracking(_) => settings.tracking,
+                FactorKind::Region(_) => settings.region,
+                // Pose factors are not created by the planner, so they have no
+                // setting of their own
+                FactorKind::Pose(_) => factor.enabled,
             };
         }
     }
@@ -1608,3 +2116,610 @@ impl FactorGraph {
     //    }
     //}
 }
+
+/// Structural defect found by [`FactorGraph::check_consistency`]
+#[derive(Debug, thiserror::Error)]
+pub enum ConsistencyError {
+    /// An index in one of the index lists does not point to a node in the graph
+    #[error("node index {0:?} does not exist in the graph")]
+    DanglingIndex(NodeIndex),
+    /// An index points to a node of the wrong kind
+    #[error("node index {index:?} was expected to point to a {expected} node")]
+    WrongNodeKind {
+        /// The offending index
+        index:    NodeIndex,
+        /// The kind of node the index was expected to point to
+        expected: &'static str,
+    },
+    /// A node in the graph is not tracked by any of the index lists
+    #[error("node {0:?} is in the graph but not in any index list")]
+    UntrackedNode(NodeIndex),
+    /// A factor is connected to more variables than it expects
+    #[error("factor {factor:?} expects at most {expected} neighbours, but has {actual}")]
+    TooManyNeighbours {
+        /// The offending factor
+        factor:   NodeIndex,
+        /// Number of neighbours the factor kind expects
+        expected: usize,
+        /// Number of neighbours the factor has
+        actual:   usize,
+    },
+    /// An internal neighbour of a node is either missing or not connected by
+    /// an edge
+    #[error("node {node:?} refers to neighbour {neighbour:?} which it has no edge to")]
+    MissingEdge {
+        /// The node holding the reference
+        node:      NodeIndex,
+        /// The neighbour referred to
+        neighbour: NodeIndex,
+    },
+    /// A message or belief does not match the degrees of freedom of the
+    /// variables
+    #[error("node {node:?} holds a {what} of dimension {actual}, expected {expected}")]
+    DimensionMismatch {
+        /// The offending node
+        node:     NodeIndex,
+        /// Which quantity has the wrong dimension
+        what:     &'static str,
+        /// The expected dimension
+        expected: usize,
+        /// The actual dimension
+        actual:   usize,
+    },
+    /// A variable is not connected to any factor
+    #[error("variable {0:?} is not connected to any factor")]
+    OrphanedVariable(NodeIndex),
+}
+
+impl FactorGraph {
+    /// Verify the structural invariants of the factorgraph:
+    /// - every index list points to existing nodes of the right kind, and every
+    ///   node is tracked by an index list
+    /// - every factor has a valid adjacency, i.e. no more neighbours than it
+    ///   expects, and an edge to every internal variable in its inbox
+    /// - every message, belief and linearisation point matches [`DOFS`]
+    /// - no variable is orphaned
+    ///
+    /// Intended to be called in debug builds after mutating the graph, as a
+    /// malformed graph otherwise fails deep inside the linear algebra.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first [`ConsistencyError`] found.
+    pub fn check_consistency(&self) -> Result<(), ConsistencyError> {
+        use super::DOFS;
+
+        let check_message = |node: NodeIndex, message: &Message| {
+            if let Some(information_vector) = message.information_vector() {
+                if information_vector.len() != DOFS {
+                    return Err(ConsistencyError::DimensionMismatch {
+                        node,
+                        what: "message information vector",
+                        expected: DOFS,
+                        actual: information_vector.len(),
+                    });
+                }
+            }
+            if let Some(precision_matrix) = message.precision_matrix() {
+                if precision_matrix.shape() != [DOFS, DOFS] {
+                    return Err(ConsistencyError::DimensionMismatch {
+                        node,
+                        what: "message precision matrix",
+                        expected: DOFS * DOFS,
+                        actual: precision_matrix.len(),
+                    });
+                }
+            }
+            Ok(())
+        };
+
+        for &index in &self.variable_indices {
+            let node = self
+                .graph
+                .node_weight(index)
+                .ok_or(ConsistencyError::DanglingIndex(index))?;
+            let variable = node.as_variable().ok_or(ConsistencyError::WrongNodeKind {
+                index,
+                expected: "variable",
+            })?;
+
+            if variable.belief.mean.len() != DOFS {
+                return Err(ConsistencyError::DimensionMismatch {
+                    node:     index,
+                    what:     "belief mean",
+                    expected: DOFS,
+                    actual:   variable.belief.mean.len(),
+                });
+            }
+
+            if variable.inbox.is_empty() && self.graph.neighbors(index).next().is_none() {
+                return Err(ConsistencyError::OrphanedVariable(index));
+            }
+
+            for (factor_id, message) in &variable.inbox {
+                check_message(index, message)?;
+                let factor_index = factor_id.factor_index.0;
+                if factor_id.factorgraph_id == self.id
+                    && self.graph.find_edge(index, factor_index).is_none()
+                {
+                    return Err(ConsistencyError::MissingEdge {
+                        node:      index,
+                        neighbour: factor_index,
+                    });
+                }
+            }
+        }
+
+        for &index in &self.factor_indices {
+            let node = self
+                .graph
+                .node_weight(index)
+                .ok_or(ConsistencyError::DanglingIndex(index))?;
+            if !node.is_factor() {
+                return Err(ConsistencyError::WrongNodeKind {
+                    index,
+                    expected: "factor",
+                });
+            }
+        }
+
+        let factor_lists: [(&Vec<NodeIndex>, fn(&FactorKind) -> bool, &'static str); 6] = [
+            (
+                &self.interrobot_factor_indices,
+                FactorKind::is_inter_robot,
+                "interrobot factor",
+            ),
+            (
+                &self.obstacle_factor_indices,
+                FactorKind::is_obstacle,
+                "obstacle factor",
+            ),
+            (
+                &self.dynamic_factor_indices,
+                FactorKind::is_dynamic,
+                "dynamic factor",
+            ),
+            (
+                &self.tracking_factor_indices,
+                FactorKind::is_tracking,
+                "tracking factor",
+            ),
+            (
+                &self.region_factor_indices,
+                FactorKind::is_region,
+                "region factor",
+            ),
+            (&self.pose_factor_indices, FactorKind::is_pose, "pose factor"),
+        ];
+        for (indices, is_kind, expected) in factor_lists {
+            for &index in indices {
+                let node = self
+                    .graph
+                    .node_weight(index)
+                    .ok_or(ConsistencyError::DanglingIndex(index))?;
+                if !node.as_factor().is_some_and(|factor| is_kind(&factor.kind)) {
+                    return Err(ConsistencyError::WrongNodeKind { index, expected });
+                }
+            }
+        }
+
+        for &index in &self.factor_indices {
+            let factor = self.graph[index].factor();
+            let expected = factor.kind.neighbours();
+
+            if factor.inbox.len() > expected {
+                return Err(ConsistencyError::TooManyNeighbours {
+                    factor: index,
+                    expected,
+                    actual: factor.inbox.len(),
+                });
+            }
+
+            if factor.state.linearisation_point.len() != expected * DOFS {
+                return Err(ConsistencyError::DimensionMismatch {
+                    node: index,
+                    what: "linearisation point",
+                    expected: expected * DOFS,
+                    actual: factor.state.linearisation_point.len(),
+                });
+            }
+
+            for (variable_id, message) in &factor.inbox {
+                check_message(index, message)?;
+                let variable_index = variable_id.variable_index.0;
+                if variable_id.factorgraph_id != self.id {
+                    continue;
+                }
+                match self.graph.node_weight(variable_index) {
+                    Some(node) if node.is_variable() => {}
+                    Some(_) => {
+                        return Err(ConsistencyError::WrongNodeKind {
+                            index:    variable_index,
+                            expected: "variable",
+                        })
+                    }
+                    None => return Err(ConsistencyError::DanglingIndex(variable_index)),
+                }
+                if self.graph.find_edge(index, variable_index).is_none() {
+                    return Err(ConsistencyError::MissingEdge {
+                        node:      index,
+                        neighbour: variable_index,
+                    });
+                }
+            }
+        }
+
+        let tracked: HashSet<NodeIndex> = self
+            .variable_indices
+            .iter()
+            .chain(&self.factor_indices)
+            .copied()
+            .collect();
+        if let Some(untracked) = self
+            .graph
+            .node_indices()
+            .find(|index| !tracked.contains(index))
+        {
+            return Err(ConsistencyError::UntrackedNode(untracked));
+        }
+
+        Ok(())
+    }
+
+    /// Assert that [`FactorGraph::check_consistency`] holds in debug builds,
+    /// after `mutation` has been applied to the graph
+    #[track_caller]
+    pub(crate) fn debug_assert_consistent(&self, mutation: &str) {
+        if cfg!(debug_assertions) {
+            if let Err(error) = self.check_consistency() {
+                panic!(
+                    "factorgraph {:?} is inconsistent after {mutation}: {error}",
+                    self.id
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::{f64::consts::PI, num::NonZeroUsize};
+
+    use ndarray::array;
+
+    use super::*;
+    use crate::factorgraph::{
+        factor::interrobot::ExternalVariableId,
+        manifold::{wrap_angle, Manifold},
+    };
+
+    /// Three variables chained by two dynamic factors
+    fn chain(id: FactorGraphId) -> FactorGraph {
+        chain_at(id, [0.0, 1.0, 2.0])
+    }
+
+    /// Three variables at `xs` chained by two dynamic factors
+    fn chain_at(id: FactorGraphId, xs: [Float; 3]) -> FactorGraph {
+        let mut factorgraph = FactorGraph::new(id);
+        add_chain(&mut factorgraph, xs);
+        factorgraph
+    }
+
+    /// Add three variables at `xs` chained by two dynamic factors
+    fn add_chain(factorgraph: &mut FactorGraph, xs: [Float; 3]) {
+        let id = factorgraph.id();
+        let variables = xs
+            .into_iter()
+            .map(|x| {
+                factorgraph.add_variable(VariableNode::new(
+                    id,
+                    array![x, 0.0, 1.0, 0.0],
+                    Matrix::<Float>::eye(DOFS),
+                    DOFS,
+                ))
+            })
+            .collect::<Vec<_>>();
+        for pair in variables.windows(2) {
+            let factor = factorgraph.add_factor(FactorNode::new_dynamic_factor(
+                id,
+                0.1,
+                Vector::<Float>::zeros(DOFS),
+                1.0,
+                true,
+            ));
+            for &variable in pair {
+                let _ = factorgraph
+                    .add_internal_edge(VariableId::new(id, variable), FactorId::new(id, factor));
+            }
+        }
+    }
+
+    /// Iterate `factorgraph` with `message_schedule`, and return the belief
+    /// means of its variables
+    fn iterate_with(mut factorgraph: FactorGraph, message_schedule: MessageSchedule) -> Vec<Float> {
+        factorgraph.set_message_schedule(message_schedule);
+        for _ in 0..50 {
+            factorgraph.internal_factor_iteration();
+            factorgraph.internal_variable_iteration();
+        }
+        factorgraph
+            .variables()
+            .flat_map(|(_, variable)| variable.belief.mean.to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn builder_settings_apply_to_nodes_added_later() {
+        let id = Entity::from_raw(0);
+        let settings = GbpSettings {
+            message_schedule:     MessageSchedule::ResidualPriority,
+            jit_linearisation:    false,
+            regularisation_floor: 1.0,
+        };
+        let xs = [0.0, 3.0, 1.0];
+
+        let mut built = FactorGraph::builder(id).settings(settings).capacity(5, 4).build();
+        add_chain(&mut built, xs);
+        let mut applied = chain_at(id, xs);
+        applied.apply_settings(&settings);
+        assert_eq!(built.settings(), &settings);
+        assert_eq!(applied.settings(), &settings);
+
+        let solve = SolveSettings { iterations: 20 };
+        assert_eq!(built.solve(&solve).iteration, 20);
+        assert_eq!(applied.solve(&solve).iteration, 20);
+        let means = |factorgraph: &FactorGraph| {
+            factorgraph
+                .variables()
+                .flat_map(|(_, variable)| variable.belief.mean.to_vec())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(means(&built), means(&applied));
+        assert_ne!(means(&built), iterate_with(chain_at(id, xs), MessageSchedule::Synchronous));
+    }
+
+    #[test]
+    fn sequential_schedules_converge_to_the_synchronous_beliefs() {
+        // the priors disagree with the dynamic factors, so the beliefs move
+        let xs = [0.0, 3.0, 1.0];
+        let synchronous = iterate_with(
+            chain_at(Entity::from_raw(0), xs),
+            MessageSchedule::Synchronous,
+        );
+
+        for message_schedule in [
+            MessageSchedule::ResidualPriority,
+            MessageSchedule::RandomSequential,
+        ] {
+            let sequential = iterate_with(chain_at(Entity::from_raw(0), xs), message_schedule);
+            assert_eq!(sequential.len(), synchronous.len());
+            for (sequential, synchronous) in sequential.iter().zip(&synchronous) {
+                approx::assert_relative_eq!(
+                    sequential,
+                    synchronous,
+                    epsilon = 1e-9,
+                    max_relative = 1e-6
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn residual_priority_tracks_the_iterated_factors() {
+        let mut factorgraph = chain_at(Entity::from_raw(0), [0.0, 3.0, 1.0]);
+        factorgraph.set_message_schedule(MessageSchedule::ResidualPriority);
+        factorgraph.internal_factor_iteration();
+
+        let tracked = factorgraph
+            .factor_residuals
+            .keys()
+            .copied()
+            .collect::<HashSet<_>>();
+        assert_eq!(
+            tracked,
+            factorgraph
+                .factor_indices
+                .iter()
+                .copied()
+                .collect::<HashSet<_>>()
+        );
+        assert!(factorgraph
+            .factor_residuals
+            .values()
+            .all(|residual| residual.is_finite() && *residual >= 0.0));
+
+        // a disabled factor is no longer iterated, so its residual is forgotten
+        let disabled = factorgraph.factor_indices[0];
+        factorgraph
+            .get_factor_mut(FactorIndex(disabled))
+            .unwrap()
+            .enabled = false;
+        factorgraph.internal_factor_iteration();
+        assert!(!factorgraph.factor_residuals.contains_key(&disabled));
+        assert_eq!(factorgraph.factor_residuals.len(), 1);
+    }
+
+    #[test]
+    fn random_sequential_does_not_track_residuals() {
+        let mut factorgraph = chain(Entity::from_raw(0));
+        factorgraph.set_message_schedule(MessageSchedule::RandomSequential);
+        factorgraph.internal_factor_iteration();
+        assert!(factorgraph.factor_residuals.is_empty());
+    }
+
+    #[test]
+    fn random_sequential_is_reproducible() {
+        let xs = [0.0, 3.0, 1.0];
+        assert_eq!(
+            iterate_with(
+                chain_at(Entity::from_raw(7), xs),
+                MessageSchedule::RandomSequential
+            ),
+            iterate_with(
+                chain_at(Entity::from_raw(7), xs),
+                MessageSchedule::RandomSequential
+            )
+        );
+    }
+
+    #[test]
+    fn chain_is_consistent() {
+        let factorgraph = chain(Entity::from_raw(0));
+        assert!(factorgraph.check_consistency().is_ok());
+    }
+
+    /// [`chain`] with a region factor on its middle variable
+    fn chain_with_region_factor(id: FactorGraphId) -> (FactorGraph, FactorIndex) {
+        let mut factorgraph = chain(id);
+        let (variable, _) = factorgraph.nth_variable(1).unwrap();
+        let factor = factorgraph.add_factor(FactorNode::new_region_factor(
+            id,
+            0.1,
+            array![0.0],
+            Vec::new().into(),
+            true,
+        ));
+        let _ =
+            factorgraph.add_internal_edge(VariableId::new(id, variable), FactorId::new(id, factor));
+        (factorgraph, factor)
+    }
+
+    /// A variable on SE(2) with its prior at `prior_theta`, anchored by a pose
+    /// factor measuring it at `measured_theta`, with the same precision
+    fn se2_pose(id: FactorGraphId, prior_theta: Float, measured_theta: Float) -> FactorGraph {
+        let manifold = Manifold::Se2 { offset: 0 };
+        let mut factorgraph = FactorGraph::new(id);
+        let variable = factorgraph.add_variable(
+            VariableNode::new(
+                id,
+                array![0.0, 0.0, prior_theta, 0.0],
+                Matrix::<Float>::eye(DOFS),
+                DOFS,
+            )
+            .with_manifold(manifold),
+        );
+        let factor = factorgraph.add_factor(FactorNode::new_pose_factor(
+            id,
+            1.0,
+            array![0.0, 0.0, measured_theta, 0.0],
+            manifold,
+            true,
+        ));
+        let _ = factorgraph
+            .add_internal_edge(VariableId::new(id, variable), FactorId::new(id, factor));
+        factorgraph
+    }
+
+    #[test]
+    fn se2_pose_converges_across_pi() {
+        let mut factorgraph = se2_pose(Entity::from_raw(0), PI - 0.2, -PI + 0.2);
+        factorgraph.debug_assert_consistent("construction");
+        assert_eq!(factorgraph.factor_count().pose, 1);
+
+        let _ = factorgraph.solve(&SolveSettings { iterations: 20 });
+        let (_, variable) = factorgraph.first_variable().unwrap();
+        // the short way around the circle, not the euclidean midpoint at 0
+        assert!(wrap_angle(variable.belief.mean[2] - PI).abs() < 1e-6);
+        assert!(variable.belief.mean[0].abs() < 1e-6);
+        assert!(variable.belief.mean[1].abs() < 1e-6);
+    }
+
+    #[test]
+    fn se2_pose_does_not_drift_on_stale_messages() {
+        let mut factorgraph = se2_pose(Entity::from_raw(0), PI - 0.2, -PI + 0.2);
+        let _ = factorgraph.solve(&SolveSettings { iterations: 2 });
+        let theta = factorgraph.first_variable().unwrap().1.belief.mean[2];
+
+        // without new messages from the factor, solving again must not move the
+        // mean by the same increment once more
+        for _ in 0..5 {
+            factorgraph.internal_variable_iteration();
+        }
+        let (_, variable) = factorgraph.first_variable().unwrap();
+        assert!(wrap_angle(variable.belief.mean[2] - theta).abs() < 1e-9);
+    }
+
+    #[test]
+    fn chain_with_region_factor_is_consistent() {
+        let (factorgraph, _) = chain_with_region_factor(Entity::from_raw(0));
+        assert_eq!(factorgraph.factor_count().region, 1);
+        assert!(factorgraph.check_consistency().is_ok());
+    }
+
+    #[test]
+    fn factor_of_the_wrong_kind_is_reported() {
+        let (mut factorgraph, region_factor) = chain_with_region_factor(Entity::from_raw(0));
+        let dynamic_factor = factorgraph.dynamic_factor_indices[0];
+        factorgraph.region_factor_indices.push(dynamic_factor);
+        assert!(matches!(
+            factorgraph.check_consistency(),
+            Err(ConsistencyError::WrongNodeKind { index, expected: "region factor" })
+                if index == dynamic_factor
+        ));
+
+        factorgraph.region_factor_indices.pop();
+        factorgraph.obstacle_factor_indices.push(region_factor.0);
+        assert!(matches!(
+            factorgraph.check_consistency(),
+            Err(ConsistencyError::WrongNodeKind { index, expected: "obstacle factor" })
+                if index == region_factor.0
+        ));
+    }
+
+    #[test]
+    fn deleting_interrobot_factors_leaves_the_graph_consistent() {
+        let (id, other) = (Entity::from_raw(0), Entity::from_raw(1));
+        let mut factorgraph = chain(id);
+        let mut other_factorgraph = chain(other);
+
+        let (variable, _) = factorgraph.nth_variable(1).unwrap();
+        let (other_variable, _) = other_factorgraph.nth_variable(1).unwrap();
+        let factor = factorgraph.add_factor(FactorNode::new_interrobot_factor(
+            id,
+            0.01,
+            Vector::<Float>::zeros(DOFS),
+            1.0.try_into().unwrap(),
+            2.0.try_into().unwrap(),
+            ExternalVariableId::new(other, other_variable),
+            NonZeroUsize::MIN,
+            true,
+        ));
+        let _ =
+            factorgraph.add_internal_edge(VariableId::new(id, variable), FactorId::new(id, factor));
+        other_factorgraph.add_external_edge(FactorId::new(id, factor), 1);
+        assert!(factorgraph.check_consistency().is_ok());
+        assert!(other_factorgraph.check_consistency().is_ok());
+
+        factorgraph.delete_interrobot_factors_connected_to(other);
+        assert!(factorgraph.check_consistency().is_ok());
+        assert_eq!(factorgraph.factor_count().interrobot, 0);
+    }
+
+    #[test]
+    fn orphaned_variable_is_reported() {
+        let id = Entity::from_raw(0);
+        let mut factorgraph = chain(id);
+        let variable = factorgraph.add_variable(VariableNode::new(
+            id,
+            array![0.0, 0.0, 0.0, 0.0],
+            Matrix::<Float>::eye(DOFS),
+            DOFS,
+        ));
+        assert!(matches!(
+            factorgraph.check_consistency(),
+            Err(ConsistencyError::OrphanedVariable(index)) if index == variable.0
+        ));
+    }
+
+    #[test]
+    fn dangling_index_is_reported() {
+        let mut factorgraph = chain(Entity::from_raw(0));
+        let dangling = NodeIndex::new(100);
+        factorgraph.interrobot_factor_indices.push(dangling);
+        assert!(matches!(
+            factorgraph.check_consistency(),
+            Err(ConsistencyError::DanglingIndex(index)) if index == dangling
+        ));
+    }
+}
@@ -0,0 +1,127 @@
+//! Fixed-size kernels for the `DOFS` x `DOFS` blocks of the planner.
+//!
+//! Every variable of the planner has [`DOFS`] = 4 degrees of freedom, so the
+//! matrices inverted in the hot loops of GBP are 4x4. `ndarray_inverse` is
+//! generic over the size, and allocates for every intermediate step of the
+//! LU decomposition. These kernels load the block into a stack allocated
+//! [`DMat4`] instead, where the size is known at compile time and `glam` can
+//! vectorise the arithmetic. Matrices of any other size fall back to
+//! `ndarray_inverse`.
+use bevy::math::{DMat4, DVec4};
+use gbp_linalg::{Float, Matrix, MatrixView, Vector};
+use ndarray_inverse::Inverse;
+
+use super::DOFS;
+
+// The kernels below are written for `glam`'s 4x4 double precision types
+const _: () = assert!(DOFS == 4);
+
+/// Load a row-major `ndarray` matrix into a `DMat4`
+#[inline]
+fn to_dmat4(matrix: MatrixView<Float>) -> DMat4 {
+    // `DMat4` is column-major
+    DMat4::from_cols_array(&std::array::from_fn(|i| matrix[[i % DOFS, i / DOFS]]))
+}
+
+/// Store a `DMat4` into a row-major `ndarray` matrix
+#[inline]
+fn from_dmat4(matrix: DMat4) -> Matrix<Float> {
+    Matrix::<Float>::from_shape_fn((DOFS, DOFS), |(row, col)| matrix.col(col)[row])
+}
+
+/// Inverse of a square matrix, or `None` if it is singular.
+/// Uses the fixed-size kernel for `DOFS` x `DOFS` matrices.
+pub(crate) fn invert(matrix: MatrixView<Float>) -> Option<Matrix<Float>> {
+    debug_assert_eq!(matrix.nrows(), matrix.ncols());
+    if matrix.dim() != (DOFS, DOFS) {
+        return matrix.to_owned().inv();
+    }
+
+    let matrix = to_dmat4(matrix);
+    let determinant = matrix.determinant();
+    // also rejects NaNs, infinities and determinants too small to invert
+    if !determinant.is_normal() {
+        return None;
+    }
+
+    Some(from_dmat4(matrix.inverse()))
+}
+
+/// Matrix-vector product `matrix * vector`.
+/// Uses the fixed-size kernel for a `DOFS` x `DOFS` matrix.
+pub(crate) fn mul_vec(matrix: &Matrix<Float>, vector: &Vector<Float>) -> Vector<Float> {
+    if matrix.dim() != (DOFS, DOFS) || vector.len() != DOFS {
+        return matrix.dot(vector);
+    }
+
+    let vector = DVec4::new(vector[0], vector[1], vector[2], vector[3]);
+    let product = to_dmat4(matrix.view()) * vector;
+    product.to_array().into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray::array;
+
+    use super::*;
+
+    const EPSILON: Float = 1e-12;
+
+    fn precision_matrix() -> Matrix<Float> {
+        array![
+            [4.0, 1.0, 0.5, 0.0],
+            [1.0, 3.0, 0.0, 0.2],
+            [0.5, 0.0, 2.0, 0.1],
+            [0.0, 0.2, 0.1, 1.0]
+        ]
+    }
+
+    fn assert_all_close<'a>(
+        actual: impl IntoIterator<Item = &'a Float>,
+        expected: impl IntoIterator<Item = &'a Float>,
+    ) {
+        for (actual, expected) in actual.into_iter().zip(expected) {
+            assert_relative_eq!(actual, expected, epsilon = EPSILON);
+        }
+    }
+
+    #[test]
+    fn invert_matches_ndarray_inverse() {
+        let matrix = precision_matrix();
+        let expected = matrix.inv().expect("matrix is invertible");
+        let inverse = invert(matrix.view()).expect("matrix is invertible");
+        assert_all_close(&inverse, &expected);
+    }
+
+    #[test]
+    fn invert_non_symmetric_matrix() {
+        let matrix = array![
+            [1.0, 2.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 3.0],
+            [0.0, 0.0, 0.0, 1.0]
+        ];
+        let inverse = invert(matrix.view()).expect("matrix is invertible");
+        assert_all_close(&inverse.dot(&matrix), &Matrix::<Float>::eye(DOFS));
+    }
+
+    #[test]
+    fn invert_singular_matrix_is_none() {
+        assert!(invert(Matrix::<Float>::zeros((DOFS, DOFS)).view()).is_none());
+    }
+
+    #[test]
+    fn invert_falls_back_for_other_sizes() {
+        let matrix = array![[2.0, 0.0], [0.0, 4.0]];
+        let inverse = invert(matrix.view()).expect("matrix is invertible");
+        assert_all_close(&inverse, &array![[0.5, 0.0], [0.0, 0.25]]);
+    }
+
+    #[test]
+    fn mul_vec_matches_dot() {
+        let matrix = precision_matrix();
+        let vector = array![1.0, -2.0, 0.5, 3.0];
+        assert_all_close(&mul_vec(&matrix, &vector), &matrix.dot(&vector));
+    }
+}
@@ -1,27 +1,24 @@
+use gbp_config::{GraphvizNodeAttributes, GraphvizNodesSection};
+
 use super::factor::ExternalVariableId;
 
 /// Represents a factorgraph node in the graphviz output
 pub struct Node {
     /// The index of the node
-    pub index: usize,
+    pub index:    usize,
     /// The kind of the node
-    pub kind:  NodeKind,
+    pub kind:     NodeKind,
+    /// Number of messages sent and received by the node
+    pub messages: usize,
 }
 
 impl Node {
-    /// Returns the color of the node
-    pub const fn color(&self) -> &'static str {
-        self.kind.color()
-    }
-
-    /// Returns the shape of the node
-    pub const fn shape(&self) -> &'static str {
-        self.kind.shape()
-    }
-
-    /// Returns the width of the node
-    pub const fn width(&self) -> f64 {
-        self.kind.width()
+    /// Returns the attributes of the node, as configured for its kind
+    pub const fn attributes<'a>(
+        &self,
+        nodes: &'a GraphvizNodesSection,
+    ) -> &'a GraphvizNodeAttributes {
+        self.kind.attributes(nodes)
     }
 }
 
@@ -42,33 +39,30 @@ pub enum NodeKind {
     // },
     DynamicFactor,
     ObstacleFactor,
-    TrackingFactor, // PoseFactor,
+    TrackingFactor,
+    RegionFactor,
+    PoseFactor,
 }
 
 impl NodeKind {
-    pub const fn color(&self) -> &'static str {
+    pub const fn attributes<'a>(
+        &self,
+        nodes: &'a GraphvizNodesSection,
+    ) -> &'a GraphvizNodeAttributes {
         match self {
-            Self::Variable { .. } => "#eff1f5",         // latte base (white)
-            Self::InterRobotFactor { .. } => "#a6da95", // green
-            Self::DynamicFactor => "#8aadf4",           // blue
-            Self::ObstacleFactor => "#ee99a0",          // mauve (purple)
-            // Self::PoseFactor => "#c6aof6",     // maroon (red)
-            Self::TrackingFactor => "#f4a15a", // orange
+            Self::Variable { .. } => &nodes.variable,
+            Self::InterRobotFactor { .. } => &nodes.interrobot,
+            Self::DynamicFactor => &nodes.dynamic,
+            Self::ObstacleFactor => &nodes.obstacle,
+            Self::TrackingFactor => &nodes.tracking,
+            Self::RegionFactor => &nodes.region,
+            Self::PoseFactor => &nodes.pose,
         }
     }
 
-    pub const fn shape(&self) -> &'static str {
-        match self {
-            Self::Variable { .. } => "circle",
-            _ => "square",
-        }
-    }
-
-    pub const fn width(&self) -> f64 {
-        match self {
-            Self::Variable { .. } => 0.8,
-            _ => 0.2,
-        }
+    /// Returns `true` if the node is a factor
+    pub const fn is_factor(&self) -> bool {
+        !matches!(self, Self::Variable { .. })
     }
 }
 
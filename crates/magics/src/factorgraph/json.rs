@@ -0,0 +1,106 @@
+//! Serializable snapshot of a factorgraph, for debugging numerical issues.
+//!
+//! Unlike the graphviz export, the snapshot keeps the full means,
+//! covariances and measurements, and is written as pretty printed JSON so two
+//! snapshots taken at different timesteps can be compared with `diff`.
+//! Non-finite numbers are written as `null`.
+
+use gbp_linalg::prelude::*;
+use serde::Serialize;
+
+/// Snapshot of a factorgraph, created with
+/// [`FactorGraph::dump()`](super::prelude::FactorGraph::dump)
+#[derive(Debug, Clone, Serialize)]
+pub struct FactorGraphDump {
+    /// The id of the factorgraph, i.e. the robot it belongs to
+    pub factorgraph: String,
+    /// The variables, in the order they were created
+    pub variables:   Vec<VariableDump>,
+    /// The factors, in the order they were created
+    pub factors:     Vec<FactorDump>,
+}
+
+/// Snapshot of the belief of a variable
+#[derive(Debug, Clone, Serialize)]
+pub struct VariableDump {
+    /// Index of the variable node in the graph
+    pub index: usize,
+    pub mean: Vec<Float>,
+    pub covariance: Vec<Vec<Float>>,
+    pub information_vector: Vec<Float>,
+    pub precision: Vec<Vec<Float>>,
+    pub condition_number: Float,
+}
+
+/// Snapshot of the state of a factor
+#[derive(Debug, Clone, Serialize)]
+pub struct FactorDump {
+    /// Index of the factor node in the graph
+    pub index: usize,
+    /// The kind of the factor e.g. `DynamicFactor`
+    pub kind: &'static str,
+    pub enabled: bool,
+    /// Indices of the variables in the same graph the factor is connected to
+    pub variables: Vec<usize>,
+    /// The variable in another factorgraph, for interrobot factors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub external_variable: Option<String>,
+    /// The measurement the factor pulls towards, `z` in **gbpplanner**
+    pub measurement: Vec<Float>,
+    /// The measurement at the linearisation point, `h` in **gbpplanner**
+    pub predicted: Vec<Float>,
+    pub linearisation_point: Vec<Float>,
+    pub precision: Vec<Vec<Float>>,
+    pub strength: Float,
+    /// See [`FactorNode::energy()`](super::factor::FactorNode::energy)
+    pub energy: Float,
+}
+
+impl FactorGraphDump {
+    /// Serialize the snapshot as pretty printed JSON
+    ///
+    /// # Errors
+    ///
+    /// Fails if the snapshot could not be serialized
+    pub fn to_json_pretty(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// Convert a matrix into a list of rows
+pub(super) fn rows(matrix: &Matrix<Float>) -> Vec<Vec<Float>> {
+    matrix.outer_iter().map(|row| row.to_vec()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ndarray::array;
+
+    use super::*;
+
+    #[test]
+    fn matrix_rows_are_serialized_in_order() {
+        let matrix: Matrix<Float> = array![[1.0, 2.0], [3.0, 4.0]];
+        assert_eq!(rows(&matrix), vec![vec![1.0, 2.0], vec![3.0, 4.0]]);
+    }
+
+    #[test]
+    fn non_finite_numbers_become_null() {
+        let dump = FactorGraphDump {
+            factorgraph: "0v1".to_string(),
+            variables:   vec![VariableDump {
+                index: 0,
+                mean: vec![Float::NAN],
+                covariance: vec![],
+                information_vector: vec![],
+                precision: vec![],
+                condition_number: Float::INFINITY,
+            }],
+            factors:     vec![],
+        };
+        let json = dump.to_json_pretty().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(value["variables"][0]["mean"][0].is_null());
+        assert!(value["variables"][0]["condition_number"].is_null());
+    }
+}
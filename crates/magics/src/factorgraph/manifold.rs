@@ -0,0 +1,277 @@
+//! On-manifold state representations for variables and measurements.
+//!
+//! Most variables in the factorgraph live in a plain euclidean vector space,
+//! where updates are simple additions. Orientations do not: adding a small
+//! increment to an angle close to ±π must wrap around instead of leaving the
+//! valid range. This module provides the SE(2) group, together with the
+//! `boxplus` (⊞) and `boxminus` (⊟) operators used to move between the
+//! manifold and its tangent space.
+
+use std::f64::consts::PI;
+
+use gbp_linalg::prelude::*;
+
+/// Wraps an angle in radians into the half-open interval `(-π, π]`
+#[must_use]
+pub fn wrap_angle(angle: Float) -> Float {
+    let wrapped = (angle + PI).rem_euclid(2.0 * PI) - PI;
+    if wrapped <= -PI {
+        wrapped + 2.0 * PI
+    } else {
+        wrapped
+    }
+}
+
+/// A rigid body transformation in the plane, i.e. a pose with a position and
+/// an orientation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Se2 {
+    /// Translation along the x-axis
+    pub x:     Float,
+    /// Translation along the y-axis
+    pub y:     Float,
+    /// Orientation in radians, always in `(-π, π]`
+    pub theta: Float,
+}
+
+impl Se2 {
+    /// Number of degrees of freedom of the tangent space
+    pub const DOFS: usize = 3;
+
+    /// The identity transformation
+    pub const IDENTITY: Self = Self {
+        x:     0.0,
+        y:     0.0,
+        theta: 0.0,
+    };
+
+    /// Create a new pose. The orientation is wrapped into `(-π, π]`
+    #[must_use]
+    pub fn new(x: Float, y: Float, theta: Float) -> Self {
+        Self {
+            x,
+            y,
+            theta: wrap_angle(theta),
+        }
+    }
+
+    /// Compose two poses, `self ∘ other`
+    #[must_use]
+    pub fn compose(&self, other: &Self) -> Self {
+        let (sin, cos) = self.theta.sin_cos();
+        Self::new(
+            cos.mul_add(other.x, -sin * other.y) + self.x,
+            sin.mul_add(other.x, cos * other.y) + self.y,
+            self.theta + other.theta,
+        )
+    }
+
+    /// The inverse transformation, such that `self ∘ self.inverse()` is the
+    /// identity
+    #[must_use]
+    pub fn inverse(&self) -> Self {
+        let (sin, cos) = self.theta.sin_cos();
+        Self::new(
+            -cos.mul_add(self.x, sin * self.y),
+            sin.mul_add(self.x, -cos * self.y),
+            -self.theta,
+        )
+    }
+
+    /// The exponential map, from a tangent vector `[ρx, ρy, θ]` to a pose
+    #[must_use]
+    pub fn exp(tangent: [Float; 3]) -> Self {
+        let [rho_x, rho_y, theta] = tangent;
+        let (a, b) = Self::v_coefficients(theta);
+        Self::new(
+            a.mul_add(rho_x, -b * rho_y),
+            b.mul_add(rho_x, a * rho_y),
+            theta,
+        )
+    }
+
+    /// The logarithmic map, from a pose to a tangent vector `[ρx, ρy, θ]`
+    #[must_use]
+    pub fn log(&self) -> [Float; 3] {
+        let (a, b) = Self::v_coefficients(self.theta);
+        let det = a.mul_add(a, b * b);
+        [
+            a.mul_add(self.x, b * self.y) / det,
+            (-b).mul_add(self.x, a * self.y) / det,
+            self.theta,
+        ]
+    }
+
+    /// Apply a tangent space increment in the local frame of the pose
+    /// `self ⊞ delta = self ∘ exp(delta)`
+    #[must_use]
+    pub fn boxplus(&self, delta: [Float; 3]) -> Self {
+        self.compose(&Self::exp(delta))
+    }
+
+    /// The tangent space increment taking `other` to `self`
+    /// `self ⊟ other = log(other⁻¹ ∘ self)`
+    #[must_use]
+    pub fn boxminus(&self, other: &Self) -> [Float; 3] {
+        other.inverse().compose(self).log()
+    }
+
+    /// Coefficients `(a, b)` of the left jacobian `V = [[a, -b], [b, a]]` of
+    /// SE(2), with a taylor expansion close to zero to avoid dividing by zero.
+    fn v_coefficients(theta: Float) -> (Float, Float) {
+        if theta.abs() < 1e-6 {
+            let theta2 = theta * theta;
+            (1.0 - theta2 / 6.0, theta / 2.0 - theta * theta2 / 24.0)
+        } else {
+            (theta.sin() / theta, (1.0 - theta.cos()) / theta)
+        }
+    }
+}
+
+/// Describes which manifold the state vector of a variable, or the
+/// measurement vector of a factor, lives on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Manifold {
+    /// Plain vector space, where `⊞` and `⊟` are `+` and `-`
+    #[default]
+    Euclidean,
+    /// The elements `[offset, offset + 3)` of the vector is an SE(2) pose
+    /// `[x, y, θ]`. The remaining elements are treated as euclidean.
+    Se2 {
+        /// Index of the first element of the pose in the vector
+        offset: usize,
+    },
+}
+
+impl Manifold {
+    /// Returns `true` if the manifold is [`Manifold::Euclidean`]
+    #[inline]
+    #[must_use]
+    pub const fn is_euclidean(&self) -> bool {
+        matches!(self, Self::Euclidean)
+    }
+
+    /// `x ⊞ delta`
+    #[must_use]
+    pub fn boxplus(&self, x: &Vector<Float>, delta: &Vector<Float>) -> Vector<Float> {
+        debug_assert_eq!(x.len(), delta.len());
+        let mut result = x + delta;
+        if let Self::Se2 { offset } = *self {
+            let pose = Self::pose_at(x, offset).boxplus([
+                delta[offset],
+                delta[offset + 1],
+                delta[offset + 2],
+            ]);
+            Self::assign_pose(&mut result, offset, &pose);
+        }
+        result
+    }
+
+    /// `x ⊟ y`
+    #[must_use]
+    pub fn boxminus(&self, x: &Vector<Float>, y: &Vector<Float>) -> Vector<Float> {
+        debug_assert_eq!(x.len(), y.len());
+        let mut result = x - y;
+        if let Self::Se2 { offset } = *self {
+            let tangent = Self::pose_at(x, offset).boxminus(&Self::pose_at(y, offset));
+            result[offset] = tangent[0];
+            result[offset + 1] = tangent[1];
+            result[offset + 2] = tangent[2];
+        }
+        result
+    }
+
+    /// Bring `x` back onto the manifold, i.e. wrap any angles into `(-π, π]`
+    pub fn normalise(&self, x: &mut Vector<Float>) {
+        if let Self::Se2 { offset } = *self {
+            x[offset + 2] = wrap_angle(x[offset + 2]);
+        }
+    }
+
+    fn pose_at(x: &Vector<Float>, offset: usize) -> Se2 {
+        Se2::new(x[offset], x[offset + 1], x[offset + 2])
+    }
+
+    fn assign_pose(x: &mut Vector<Float>, offset: usize, pose: &Se2) {
+        x[offset] = pose.x;
+        x[offset + 1] = pose.y;
+        x[offset + 2] = pose.theta;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_relative_eq;
+    use ndarray::array;
+
+    use super::*;
+
+    const EPSILON: Float = 1e-9;
+
+    #[test]
+    fn wrap_angle_is_in_half_open_interval() {
+        assert_relative_eq!(wrap_angle(PI), PI, epsilon = EPSILON);
+        assert_relative_eq!(wrap_angle(-PI), PI, epsilon = EPSILON);
+        assert_relative_eq!(wrap_angle(3.0 * PI / 2.0), -PI / 2.0, epsilon = EPSILON);
+        assert_relative_eq!(wrap_angle(-5.0 * PI / 2.0), -PI / 2.0, epsilon = EPSILON);
+        assert_relative_eq!(wrap_angle(0.5), 0.5, epsilon = EPSILON);
+    }
+
+    #[test]
+    fn exp_log_roundtrip() {
+        for tangent in [[1.0, -2.0, 0.3], [0.0, 0.0, 0.0], [-3.0, 0.5, 1e-8], [2.0, 1.0, -3.0]] {
+            let log = Se2::exp(tangent).log();
+            for i in 0..3 {
+                assert_relative_eq!(log[i], tangent[i], epsilon = EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn compose_with_inverse_is_identity() {
+        let pose = Se2::new(1.5, -0.5, 2.5);
+        let identity = pose.compose(&pose.inverse());
+        assert_relative_eq!(identity.x, 0.0, epsilon = EPSILON);
+        assert_relative_eq!(identity.y, 0.0, epsilon = EPSILON);
+        assert_relative_eq!(identity.theta, 0.0, epsilon = EPSILON);
+    }
+
+    #[test]
+    fn boxplus_wraps_around_pi() {
+        let pose = Se2::new(0.0, 0.0, PI - 0.1);
+        let updated = pose.boxplus([0.0, 0.0, 0.2]);
+        assert_relative_eq!(updated.theta, -PI + 0.1, epsilon = EPSILON);
+    }
+
+    #[test]
+    fn boxminus_across_pi_is_small() {
+        let a = Se2::new(1.0, 1.0, PI - 0.05);
+        let b = Se2::new(1.0, 1.0, -PI + 0.05);
+        let delta = b.boxminus(&a);
+        assert_relative_eq!(delta[2], 0.1, epsilon = EPSILON);
+        let back = a.boxplus(delta);
+        assert_relative_eq!(back.theta, b.theta, epsilon = EPSILON);
+    }
+
+    #[test]
+    fn manifold_boxplus_boxminus_roundtrip() {
+        let manifold = Manifold::Se2 { offset: 0 };
+        let x = array![1.0, 2.0, 3.0, 0.5];
+        let delta = array![0.1, -0.2, 0.4, 1.0];
+        let y = manifold.boxplus(&x, &delta);
+        assert!(y[2] > -PI && y[2] <= PI);
+        let recovered = manifold.boxminus(&y, &x);
+        for i in 0..4 {
+            assert_relative_eq!(recovered[i], delta[i], epsilon = EPSILON);
+        }
+    }
+
+    #[test]
+    fn euclidean_manifold_is_vector_space() {
+        let manifold = Manifold::Euclidean;
+        let x = array![1.0, 2.0, 3.0, 4.0];
+        let delta = array![4.0, 3.0, 2.0, 1.0];
+        assert_eq!(manifold.boxplus(&x, &delta), array![5.0, 5.0, 5.0, 5.0]);
+        assert_eq!(manifold.boxminus(&x, &delta), array![-3.0, -1.0, 1.0, 3.0]);
+    }
+}
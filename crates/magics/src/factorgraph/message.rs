@@ -6,6 +6,7 @@
 use std::collections::BTreeMap;
 
 use gbp_linalg::prelude::*;
+use smallvec::SmallVec;
 
 use super::{
     id::{FactorId, VariableId},
@@ -84,6 +85,24 @@ impl Message {
         self.payload.is_none()
     }
 
+    /// Returns `true` if the message is empty, or every element of its payload
+    /// is finite, i.e. neither NaN nor infinite.
+    pub fn is_finite(&self) -> bool {
+        self.payload.as_ref().map_or(true, |payload| {
+            payload.information_vector.iter().all(|x| x.is_finite())
+                && payload.precision_matrix.iter().all(|x| x.is_finite())
+                && payload.mean.iter().all(|x| x.is_finite())
+        })
+    }
+
+    /// Replace the mean of the message. Does nothing if the message is empty
+    pub fn set_mean(&mut self, mean: Mean) {
+        debug_assert_eq!(mean.0.len(), DOFS);
+        if let Some(payload) = self.payload.as_mut() {
+            payload.mean = mean.0;
+        }
+    }
+
     /// Take the inner `MultivariateNormal` from the message.
     /// Leaving the message in an empty state.
     #[inline]
@@ -215,3 +234,13 @@ pub type MessagesToFactors = BTreeMap<FactorId, Message>;
 /// stored in a consistent order This is necessary for the **gbpplanner**
 /// algorithm to work correctly.
 pub type MessagesToVariables = BTreeMap<VariableId, Message>;
+
+/// Messages a factor sends to its connected variables in a single update.
+/// A factor is connected to at most two variables, so the messages are stored
+/// inline instead of being heap allocated every iteration.
+pub type FactorResponses = SmallVec<[(VariableId, Message); 2]>;
+
+/// Messages a variable sends to its connected factors in a single update.
+/// Stored inline for up to 8 factors, which covers the dynamic, obstacle and
+/// tracking factors of a variable plus a handful of interrobot factors.
+pub type VariableResponses = SmallVec<[(FactorId, Message); 8]>;
@@ -1,14 +1,89 @@
 #![warn(missing_docs)]
-//! ...
+//! Gaussian Belief Propagation on the factorgraph of a single robot.
+//!
+//! The public API is re-exported from [`prelude`]:
+//!
+//! - [`FactorGraph`](factorgraph::FactorGraph) owns the nodes of one robot, and
+//!   runs the internal and external variable and factor iterations. It is
+//!   created with [`FactorGraph::builder`](factorgraph::FactorGraph::builder).
+//! - [`GbpSettings`](settings::GbpSettings) configure the message passing of
+//!   a factorgraph, and [`SolveSettings`](settings::SolveSettings) how long
+//!   [`FactorGraph::solve`](factorgraph::FactorGraph::solve) iterates it.
+//! - [`VariableNode`](variable::VariableNode) holds a prior and a belief over
+//!   the [`DOFS`] dimensional state of the robot at one timestep.
+//! - [`FactorNode`](factor::FactorNode) is created with one of its
+//!   `new_*_factor` constructors, and measures the states of the variables it
+//!   is connected to. New kinds of factors implement
+//!   [`Factor`](factor::Factor).
+//! - Nodes are addressed with [`VariableId`](id::VariableId) and
+//!   [`FactorId`](id::FactorId), which are unique across all factorgraphs, so
+//!   messages between the factorgraphs of different robots can be routed.
+//!
+//! How the factorgraphs of the robots are built, connected and iterated every
+//! timestep lives in [`crate::planner`].
+//!
+//! Two variables at different positions, connected by a dynamic factor, are
+//! pulled towards a common trajectory:
+//!
+//! ```
+//! use bevy::ecs::entity::Entity;
+//! use gbp_linalg::prelude::*;
+//! use magics::factorgraph::prelude::*;
+//! use ndarray::array;
+//!
+//! let robot_id = Entity::from_raw(0);
+//! let mut factorgraph = FactorGraph::builder(robot_id)
+//!     .settings(GbpSettings::default())
+//!     .capacity(3, 2)
+//!     .build();
+//! let variables = [0.0, 2.0].map(|x| {
+//!     factorgraph.add_variable(VariableNode::new(
+//!         robot_id,
+//!         array![x, 0.0, 1.0, 0.0],
+//!         Matrix::<Float>::eye(DOFS),
+//!         DOFS,
+//!     ))
+//! });
+//! let dynamic = factorgraph.add_factor(FactorNode::new_dynamic_factor(
+//!     robot_id,
+//!     0.1,
+//!     Vector::<Float>::zeros(DOFS),
+//!     1.0,
+//!     true,
+//! ));
+//! for variable in variables {
+//!     let _ = factorgraph.add_internal_edge(
+//!         VariableId::new(robot_id, variable),
+//!         FactorId::new(robot_id, dynamic),
+//!     );
+//! }
+//!
+//! let report = factorgraph.solve(&SolveSettings { iterations: 10 });
+//! assert_eq!(report.iteration, 10);
+//! let (_, first) = factorgraph.first_variable().unwrap();
+//! let (_, second) = factorgraph.nth_variable(1).unwrap();
+//! let distance = second.belief.mean[0] - first.belief.mean[0];
+//! assert!(0.0 < distance && distance < 2.0);
+//! ```
+//!
+//! Every type reachable from the public API is public itself. Nodes are
+//! identified by bevy [`Entity`](bevy::ecs::entity::Entity)s, and the
+//! obstacle factor samples a public
+//! [`SdfImage`](crate::simulation_loader::SdfImage).
 use derive_more::{Add, AddAssign};
 
+pub mod batch;
 pub mod factor;
 #[allow(clippy::module_inception)]
 pub mod factorgraph;
+mod fixed;
 pub mod graphviz;
 pub mod id;
+pub mod json;
+pub mod manifold;
 pub mod message;
 pub mod node;
+pub mod settings;
 pub mod variable;
 
 /// Degrees of Freedom of the ground robot.
@@ -20,10 +95,21 @@ pub mod variable;
 /// [x, y, x', y']
 pub const DOFS: usize = 4;
 
-/// prelude module bringing entire public API into score
+/// prelude module bringing entire public API into scope
 #[allow(unused_imports)]
 pub mod prelude {
-    pub use super::{factorgraph::FactorGraph, message::Message, DOFS};
+    pub use super::{
+        factor::{Factor, FactorKind, FactorNode, Measurement},
+        factorgraph::{
+            FactorGraph, FactorGraphBuilder, FactorGraphId, FactorIndex, SolveReport,
+            VariableIndex,
+        },
+        id::{FactorId, VariableId},
+        message::{FactorToVariableMessage, Message, VariableToFactorMessage},
+        settings::{GbpSettings, SolveSettings},
+        variable::{VariableBelief, VariableNode, VariablePrior},
+        MessageCount, MessagesReceived, MessagesSent, DOFS,
+    };
 }
 
 #[derive(Debug, Clone, Copy, Add, AddAssign, serde::Serialize)]
@@ -23,7 +23,7 @@ pub(in crate::factorgraph) trait FactorGraphNode {
 }
 
 /// Different variants a factorgraph node can be
-#[derive(Debug, derive_more::IsVariant, strum_macros::EnumTryAs)]
+#[derive(Debug, Clone, derive_more::IsVariant, strum_macros::EnumTryAs)]
 pub enum NodeKind {
     /// The node is a factor
     Factor(FactorNode),
@@ -33,7 +33,7 @@ pub enum NodeKind {
 }
 
 /// The node stored in the factorgraph
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Node {
     // factorgraph_id: FactorGraphId,
     /// The kind of this node, either Variable or some Factor
@@ -0,0 +1,57 @@
+//! Settings of the message passing in a
+//! [`FactorGraph`](super::factorgraph::FactorGraph), and of how long it is
+//! iterated with [`FactorGraph::solve`](super::factorgraph::FactorGraph::solve).
+use gbp_config::{GbpSection, MessageSchedule};
+use gbp_linalg::Float;
+
+/// Settings of the GBP message passing within a factorgraph. They apply to
+/// every node in the factorgraph, including nodes added after the settings
+/// have been changed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GbpSettings {
+    /// In which order the internal factors send their messages
+    pub message_schedule:     MessageSchedule,
+    /// Relinearise nonlinear factors around the means of their variables on
+    /// every update. If disabled, they are linearised once and their
+    /// potential is cached, like that of linear factors
+    pub jit_linearisation:    bool,
+    /// Added to the diagonal of the precision matrix of every variable when
+    /// its belief is solved for. `0.0` disables regularisation
+    pub regularisation_floor: Float,
+}
+
+impl Default for GbpSettings {
+    fn default() -> Self {
+        Self {
+            message_schedule:     MessageSchedule::default(),
+            jit_linearisation:    true,
+            regularisation_floor: 0.0,
+        }
+    }
+}
+
+impl From<&GbpSection> for GbpSettings {
+    fn from(gbp: &GbpSection) -> Self {
+        Self {
+            message_schedule:     gbp.message_schedule,
+            jit_linearisation:    gbp.jit_linearisation,
+            regularisation_floor: Float::from(gbp.conditioning.regularisation_floor),
+        }
+    }
+}
+
+/// How [`FactorGraph::solve`](super::factorgraph::FactorGraph::solve)
+/// iterates a factorgraph on its own, i.e. without exchanging messages with
+/// the factorgraphs of other robots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SolveSettings {
+    /// Number of internal iterations, each a factor iteration followed by a
+    /// variable iteration
+    pub iterations: usize,
+}
+
+impl Default for SolveSettings {
+    fn default() -> Self {
+        Self { iterations: 10 }
+    }
+}
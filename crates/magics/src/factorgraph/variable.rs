@@ -1,11 +1,17 @@
-use bevy::log::info;
+use std::borrow::Cow;
+
+use bevy::log::{error, info};
 use gbp_linalg::{Float, Matrix, Vector};
-use ndarray_inverse::Inverse;
 
 use super::{
     factorgraph::{FactorGraphId, NodeIndex},
+    fixed,
     id::FactorId,
-    message::{InformationVec, Mean, Message, MessagesToFactors, PrecisionMatrix},
+    manifold::Manifold,
+    message::{
+        InformationVec, Mean, Message, MessagesToFactors, Payload, PrecisionMatrix,
+        VariableResponses,
+    },
     node::{FactorGraphNode, RemoveConnectionToError},
     MessageCount, MessagesReceived, MessagesSent, DOFS,
 };
@@ -15,14 +21,23 @@ use super::{
 pub struct VariablePrior {
     information_vector: Vector<Float>,
     precision_matrix:   Matrix<Float>,
+    /// Kept around for variables on a non-euclidean manifold, where the
+    /// information vector has to be recomputed in the tangent space of the
+    /// current belief on every update.
+    mean:               Vector<Float>,
 }
 
 impl VariablePrior {
     #[must_use]
-    const fn new(information_vector: Vector<Float>, precision_matrix: Matrix<Float>) -> Self {
+    const fn new(
+        information_vector: Vector<Float>,
+        precision_matrix: Matrix<Float>,
+        mean: Vector<Float>,
+    ) -> Self {
         Self {
             information_vector,
             precision_matrix,
+            mean,
         }
     }
 }
@@ -34,8 +49,6 @@ impl VariablePrior {
 //     }
 // }
 
-/// PERF: use fixed size vectors and matrices, either bevy Vec4, or nalgebra
-/// Vec4
 #[derive(Debug, Clone)]
 pub struct VariableBelief {
     /// Information vector
@@ -51,6 +64,39 @@ pub struct VariableBelief {
     /// not contain NaNs or Infs In gbpplanner it is used to control if a
     /// variable can be rendered.
     valid: bool,
+    /// Condition number of the precision matrix, estimated as
+    /// `‖Λ‖_F * ‖Λ⁻¹‖_F`. Large values mean the belief is close to singular.
+    pub condition_number: Float,
+}
+
+/// Frobenius norm of a matrix
+fn frobenius_norm(matrix: &Matrix<Float>) -> Float {
+    matrix.iter().map(|x| x * x).sum::<Float>().sqrt()
+}
+
+/// Condition number of `precision_matrix`, estimated as `‖Λ‖_F * ‖Λ⁻¹‖_F`.
+/// Infinite if the matrix could not be inverted
+fn condition_number(precision_matrix: &Matrix<Float>, covariance: Option<&Matrix<Float>>) -> Float {
+    covariance.map_or(Float::INFINITY, |covariance| {
+        frobenius_norm(precision_matrix) * frobenius_norm(covariance)
+    })
+}
+
+/// Information vector of `message`, moved from the tangent space of its own
+/// mean, the point it was linearised around, to the tangent space of `mean`.
+/// To first order an increment `δ` around `mean` is the increment
+/// `δ + (mean ⊟ message.mean)` around the mean of the message, so
+/// `η' = η - Λ(mean ⊟ message.mean)`.
+fn information_vector_at<'a>(
+    manifold: Manifold,
+    message: &'a Payload,
+    mean: &Vector<Float>,
+) -> Cow<'a, Vector<Float>> {
+    if manifold.is_euclidean() {
+        return Cow::Borrowed(&message.information_vector);
+    }
+    let offset = manifold.boxminus(mean, &message.mean);
+    Cow::Owned(&message.information_vector - &message.precision_matrix.dot(&offset))
 }
 
 impl VariableBelief {
@@ -61,12 +107,15 @@ impl VariableBelief {
         covariance_matrix: Matrix<Float>,
     ) -> Self {
         let valid = covariance_matrix.iter().all(|x| x.is_finite());
+        let condition_number =
+            frobenius_norm(&precision_matrix) * frobenius_norm(&covariance_matrix);
         Self {
             information_vector,
             precision_matrix,
             mean,
             covariance_matrix,
             valid,
+            condition_number,
         }
     }
 }
@@ -82,7 +131,7 @@ impl From<VariableBelief> for Message {
 }
 
 /// A variable in the factor graph.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct VariableNode {
     factorgraph_id: FactorGraphId,
     /// Prior distribution
@@ -101,6 +150,16 @@ pub struct VariableNode {
     node_index: Option<NodeIndex>,
 
     message_count: MessageCount,
+
+    /// The manifold the state of the variable lives on
+    manifold: Manifold,
+
+    /// Added to the diagonal of the precision matrix before inverting it
+    regularisation_floor: Float,
+
+    /// Whether the last belief update ran into a non-finite message or
+    /// covariance. Only the update it first happens in is logged
+    non_finite: bool,
 }
 
 impl VariableNode {
@@ -149,22 +208,56 @@ impl VariableNode {
 
         let eta_prior = prior_precision_matrix.dot(&prior_mean);
 
-        let sigma = prior_precision_matrix
-            .inv()
+        let sigma = fixed::invert(prior_precision_matrix.view())
             .unwrap_or_else(|| Matrix::<Float>::zeros((dofs, dofs)));
         let eta = eta_prior.clone();
         let lam = prior_precision_matrix.clone();
 
         Self {
             factorgraph_id,
-            prior: VariablePrior::new(eta_prior, prior_precision_matrix),
+            prior: VariablePrior::new(eta_prior, prior_precision_matrix, prior_mean.clone()),
             belief: VariableBelief::new(eta, lam, prior_mean, sigma),
             inbox: MessagesToFactors::new(),
             node_index: None,
             message_count: MessageCount::default(),
+            manifold: Manifold::default(),
+            regularisation_floor: 0.0,
+            non_finite: false,
         }
     }
 
+    /// Set the value added to the diagonal of the precision matrix of the
+    /// belief when it is inverted, with the matching `floor * mean` added to the
+    /// information vector. Keeps the solve away from singularity at the cost of
+    /// a slight bias towards the current mean. The stored belief and the
+    /// outgoing messages are not affected.
+    pub fn set_regularisation_floor(&mut self, regularisation_floor: Float) {
+        self.regularisation_floor = regularisation_floor.max(0.0);
+    }
+
+    /// Let the state of the variable live on the given manifold.
+    /// For a non-euclidean manifold, the information vector of the belief and
+    /// of all messages to and from the variable are expressed in the tangent
+    /// space of the current mean, and the mean is updated with `⊞` instead of
+    /// being solved for directly. Messages from factors are linearised around
+    /// an earlier mean, which they carry along, and are moved to the tangent
+    /// space of the current mean before they are used. The factors connected
+    /// to the variable have to be created for the same manifold, e.g. with
+    /// [`FactorNode::new_pose_factor`](super::factor::FactorNode::new_pose_factor).
+    #[must_use]
+    pub fn with_manifold(mut self, manifold: Manifold) -> Self {
+        self.manifold = manifold;
+        self.manifold.normalise(&mut self.prior.mean);
+        self.manifold.normalise(&mut self.belief.mean);
+        self
+    }
+
+    /// Returns the manifold the state of the variable lives on
+    #[inline]
+    pub const fn manifold(&self) -> Manifold {
+        self.manifold
+    }
+
     /// Sets the node index
     ///
     /// # Panics
@@ -200,14 +293,16 @@ impl VariableNode {
     /// It updates the belief of the variable.
     /// The prior acts as the pose factor
     /// Called `Variable::change_variable_prior` in **gbpplanner**
-    pub fn change_prior(&mut self, mean: &Vector<Float>) -> MessagesToFactors {
+    pub fn change_prior(&mut self, mean: &Vector<Float>) -> VariableResponses {
         self.prior.information_vector = self.prior.precision_matrix.dot(mean);
+        self.prior.mean.clone_from(mean);
+        self.manifold.normalise(&mut self.prior.mean);
         // self.belief.mean = mean;
-        self.belief.mean.clone_from(mean);
+        self.belief.mean.clone_from(&self.prior.mean);
 
         let mut messages_sent = MessagesSent::new();
 
-        let messages: MessagesToFactors = self
+        let messages: VariableResponses = self
             .inbox
             .keys()
             .map(|factor_id| {
@@ -229,6 +324,20 @@ impl VariableNode {
         messages
     }
 
+    /// Take over the belief and the messages received by `solved`, a copy of
+    /// this variable that has been iterated elsewhere. Messages from factors
+    /// that are no longer connected are ignored.
+    pub(in crate::factorgraph) fn apply_solution(&mut self, solved: &Self) {
+        self.belief.clone_from(&solved.belief);
+        for (factor_id, message) in &solved.inbox {
+            if let Some(received) = self.inbox.get_mut(factor_id) {
+                received.clone_from(message);
+            }
+        }
+        self.message_count = solved.message_count;
+        self.non_finite = solved.non_finite;
+    }
+
     // PERF: try return Arc<Message> instead of clone
     /// Construct a new message from the variables current belief
     pub fn prepare_message(&self) -> Message {
@@ -248,57 +357,127 @@ impl VariableNode {
     // *******************************************************/
     /// Variable Belief Update step (Step 1 in the GBP algorithm)
     /// called `Variable::update_belief` in **gbpplanner**
-    pub fn update_belief_and_create_factor_responses(&mut self) -> MessagesToFactors {
+    pub fn update_belief_and_create_factor_responses(&mut self) -> VariableResponses {
         // Collect messages from all other factors, begin by "collecting message from
         // pose factor prior"
-        self.belief
-            .information_vector
-            .clone_from(&self.prior.information_vector);
+        if self.manifold.is_euclidean() {
+            self.belief
+                .information_vector
+                .clone_from(&self.prior.information_vector);
+        } else {
+            // The prior is expressed in the tangent space of the current mean
+            let offset = self.manifold.boxminus(&self.prior.mean, &self.belief.mean);
+            self.belief.information_vector = self.prior.precision_matrix.dot(&offset);
+        }
 
         self.belief
             .precision_matrix
             .clone_from(&self.prior.precision_matrix);
 
         // Go through received messages and update belief
-        for message in self.inbox.values() {
+        let mut non_finite_from = None;
+        for (factor_id, message) in &self.inbox {
             let Some(payload) = message.payload() else {
                 continue;
             };
-            self.belief.information_vector =
-                &self.belief.information_vector + &payload.information_vector;
-            self.belief.precision_matrix =
-                &self.belief.precision_matrix + &payload.precision_matrix;
+            if !message.is_finite() {
+                if non_finite_from.is_none() {
+                    non_finite_from = Some(*factor_id);
+                }
+                continue;
+            }
+            // accumulate in place, to not allocate a new belief per message
+            self.belief.information_vector +=
+                information_vector_at(self.manifold, payload, &self.belief.mean).as_ref();
+            self.belief.precision_matrix += &payload.precision_matrix;
+        }
+        if let Some(factor_id) = non_finite_from.filter(|_| !self.non_finite) {
+            error!(
+                "variable {:?} in factorgraph {:?} received a non-finite message from factor {:?} \
+                 in factorgraph {:?}, ignoring it",
+                self.node_index,
+                self.factorgraph_id,
+                factor_id.factor_index,
+                factor_id.factorgraph_id
+            );
         }
+        let mut non_finite = non_finite_from.is_some();
 
         // Update belief
         // NOTE: This might not be correct, but it seems the `.inv()` method doesn't
         // catch and all-zero matrix
         let precision_not_zero = self.belief.precision_matrix.iter().any(|x| *x - 1e-6 > 0.0);
         if precision_not_zero {
-            if let Some(covariance) = self.belief.precision_matrix.inv() {
+            // The floor only damps the solve, Levenberg style: `(Λ + εI)⁻¹(η + εμ)`.
+            // It is kept out of the stored belief, so the outgoing messages do not
+            // carry it, and it is not counted again around loops in the graph.
+            let (precision_matrix, information_vector) = if self.regularisation_floor > 0.0 {
+                let mut precision_matrix = self.belief.precision_matrix.clone();
+                precision_matrix
+                    .diag_mut()
+                    .mapv_inplace(|x| x + self.regularisation_floor);
+                // For a non-euclidean manifold the solve is for an increment in the
+                // tangent space of the mean, where the mean itself is at the origin
+                let information_vector = if self.manifold.is_euclidean() {
+                    &self.belief.information_vector
+                        + &(self.regularisation_floor * &self.belief.mean)
+                } else {
+                    self.belief.information_vector.clone()
+                };
+                (
+                    Cow::Owned(precision_matrix),
+                    Cow::Owned(information_vector),
+                )
+            } else {
+                (
+                    Cow::Borrowed(&self.belief.precision_matrix),
+                    Cow::Borrowed(&self.belief.information_vector),
+                )
+            };
+
+            let covariance = fixed::invert(precision_matrix.view());
+            // Estimated on the undamped precision, so the floor does not hide the
+            // ill-conditioning it is there to counter
+            self.belief.condition_number = if self.regularisation_floor > 0.0 {
+                condition_number(
+                    &self.belief.precision_matrix,
+                    fixed::invert(self.belief.precision_matrix.view()).as_ref(),
+                )
+            } else {
+                condition_number(&self.belief.precision_matrix, covariance.as_ref())
+            };
+
+            if let Some(covariance) = covariance {
                 self.belief.covariance_matrix = covariance;
                 self.belief.valid = self.belief.covariance_matrix.iter().all(|x| x.is_finite());
                 if self.belief.valid {
-                    self.belief.mean = self
-                        .belief
-                        .covariance_matrix
-                        .dot(&self.belief.information_vector);
+                    let mean = fixed::mul_vec(&self.belief.covariance_matrix, &information_vector);
+                    if self.manifold.is_euclidean() {
+                        self.belief.mean = mean;
+                    } else {
+                        // The solution is an increment in the tangent space. Move the
+                        // belief along to the tangent space of the new mean, so solving
+                        // again without new messages does not apply the increment twice
+                        self.belief.mean = self.manifold.boxplus(&self.belief.mean, &mean);
+                        self.belief.information_vector -= &self.belief.precision_matrix.dot(&mean);
+                    }
                 } else {
-                    println!(
-                        "{}:{},Variable covariance is not finite",
-                        file!()
-                            .split('/')
-                            .last()
-                            .expect("the basename of the filename always exist"),
-                        line!()
-                    );
+                    if !self.non_finite {
+                        error!(
+                            "variable {:?} in factorgraph {:?} has a non-finite covariance, \
+                             keeping the previous mean {}",
+                            self.node_index, self.factorgraph_id, self.belief.mean
+                        );
+                    }
+                    non_finite = true;
                 }
             }
         }
+        self.non_finite = non_finite;
 
         let mut messages_sent = MessagesSent::new();
 
-        let messages: MessagesToFactors = self
+        let messages: VariableResponses = self
             .inbox
             .iter()
             .map(|(&factor_id, received_message)| {
@@ -308,13 +487,24 @@ impl VariableNode {
                         Message::new(
                             InformationVec(
                                 &self.belief.information_vector
-                                    - &message_from_factor.information_vector,
+                                    - information_vector_at(
+                                        self.manifold,
+                                        message_from_factor,
+                                        &self.belief.mean,
+                                    )
+                                    .as_ref(),
                             ),
                             PrecisionMatrix(
                                 &self.belief.precision_matrix
                                     - &message_from_factor.precision_matrix,
                             ),
-                            Mean(&self.belief.mean - &message_from_factor.mean),
+                            Mean(if self.manifold.is_euclidean() {
+                                &self.belief.mean - &message_from_factor.mean
+                            } else {
+                                // Factors linearise around the mean, so it has to be a
+                                // valid point on the manifold
+                                self.belief.mean.clone()
+                            }),
                         )
                     },
                 );
@@ -341,6 +531,13 @@ impl VariableNode {
         messages
     }
 
+    /// Returns `true` if the last belief update ran into a non-finite message
+    /// or covariance
+    #[inline]
+    pub const fn is_non_finite(&self) -> bool {
+        self.non_finite
+    }
+
     /// Returns `true` if the covariance matrix is finite, `false` otherwise.
     #[inline]
     pub const fn finite_covariance(&self) -> bool {
@@ -349,6 +546,7 @@ impl VariableNode {
 
     pub fn reset(&mut self, mean: &[f64; 4], sigma: f64) {
         self.belief.mean = Vector::from_iter(mean.to_owned());
+        self.manifold.normalise(&mut self.belief.mean);
         self.belief.precision_matrix = Matrix::from_diag_elem(DOFS, sigma);
         self.inbox.values_mut().for_each(|message| {
             *message = Message::empty();
@@ -400,3 +598,94 @@ impl FactorGraphNode for VariableNode {
 //         // writeln!(f, )
 //     }
 // }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use bevy::ecs::entity::Entity;
+    use ndarray::array;
+
+    use super::*;
+    use crate::factorgraph::factorgraph::FactorIndex;
+
+    fn assert_all_close<'a>(
+        actual: impl IntoIterator<Item = &'a Float>,
+        expected: impl IntoIterator<Item = &'a Float>,
+    ) {
+        for (actual, expected) in actual.into_iter().zip(expected) {
+            approx::assert_relative_eq!(actual, expected, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn regularised_variable_keeps_its_mean() {
+        let id = Entity::from_raw(0);
+        let mean = array![3.0, 4.0, 1.0, 2.0];
+        let mut variable = VariableNode::new(id, mean.clone(), Matrix::<Float>::eye(DOFS), DOFS);
+        variable.set_regularisation_floor(10.0);
+
+        // a factor that agrees with the prior
+        let factor = FactorId::new(id, FactorIndex(NodeIndex::new(1)));
+        variable.receive_message_from(
+            factor,
+            Message::new(
+                InformationVec(mean.clone()),
+                PrecisionMatrix(Matrix::<Float>::eye(DOFS)),
+                Mean(mean.clone()),
+            ),
+        );
+
+        let responses = variable.update_belief_and_create_factor_responses();
+
+        assert_all_close(&variable.belief.mean, &mean);
+        // the floor stays out of the stored belief and the outgoing messages
+        assert_all_close(
+            &variable.belief.precision_matrix,
+            &(2.0 * Matrix::<Float>::eye(DOFS)),
+        );
+        let (_, response) = responses.iter().find(|(to, _)| *to == factor).unwrap();
+        let response = response.payload().unwrap();
+        assert_all_close(&response.precision_matrix, &Matrix::<Float>::eye(DOFS));
+        assert_all_close(&response.information_vector, &mean);
+    }
+
+    #[test]
+    fn singular_precision_is_infinitely_ill_conditioned() {
+        let id = Entity::from_raw(0);
+        let singular = Matrix::<Float>::from_diag(&array![1.0, 1.0, 1.0, 0.0]);
+        for regularisation_floor in [0.0, 1.0] {
+            let mut variable =
+                VariableNode::new(id, array![1.0, 2.0, 0.0, 0.0], singular.clone(), DOFS);
+            variable.set_regularisation_floor(regularisation_floor);
+            let _ = variable.update_belief_and_create_factor_responses();
+            assert_eq!(
+                variable.belief.condition_number,
+                Float::INFINITY,
+                "regularisation floor: {regularisation_floor}"
+            );
+        }
+    }
+
+    #[test]
+    fn non_finite_flag_clears_when_the_messages_are_finite_again() {
+        let id = Entity::from_raw(0);
+        let mean = array![0.0, 0.0, 0.0, 0.0];
+        let mut variable = VariableNode::new(id, mean.clone(), Matrix::<Float>::eye(DOFS), DOFS);
+        let factor = FactorId::new(id, FactorIndex(NodeIndex::new(1)));
+        let message = |x: Float| {
+            Message::new(
+                InformationVec(array![x, 0.0, 0.0, 0.0]),
+                PrecisionMatrix(Matrix::<Float>::eye(DOFS)),
+                Mean(mean.clone()),
+            )
+        };
+
+        variable.receive_message_from(factor, message(Float::NAN));
+        let _ = variable.update_belief_and_create_factor_responses();
+        assert!(variable.is_non_finite());
+
+        variable.receive_message_from(factor, message(1.0));
+        let _ = variable.update_belief_and_create_factor_responses();
+        assert!(!variable.is_non_finite());
+    }
+}
@@ -0,0 +1,410 @@
+//! Module for recording the planned horizon of every robot to a compact binary
+//! log, and replaying the log to see how the plans evolved over time. The key
+//! artifact for debugging oscillating plans.
+//!
+//! A log starts with [`MAGIC`] and the format [`VERSION`] as a `u32`, followed
+//! by a frame per fixed timestep. All numbers are little endian:
+//!
+//! ```text
+//! frame := time: f64, robots: u32, robot * robots
+//! robot := id: u64, variables: u32, [x, y, x', y']: [f32; 4] * variables
+//! ```
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::PathBuf,
+};
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use gbp_config::Config;
+
+use crate::{
+    bevy_utils::run_conditions::time::virtual_time_is_paused, factorgraph::prelude::FactorGraph,
+    ui::UiState,
+};
+
+/// The first bytes of every horizon log
+pub const MAGIC: &[u8; 4] = b"GBPH";
+/// Version of the format written by this module
+pub const VERSION: u32 = 1;
+
+/// The horizons of all robots at a single point in time
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    /// Simulation time in seconds
+    pub time:     f64,
+    pub horizons: Vec<Horizon>,
+}
+
+/// The means of all variables of a robot, from the current state to the end
+/// of its horizon
+#[derive(Debug, Clone, PartialEq)]
+pub struct Horizon {
+    /// The bits of the entity of the robot
+    pub robot: u64,
+    /// [x, y, x', y'] of every variable
+    pub means: Vec<Vec4>,
+}
+
+/// Error reading a horizon log
+#[derive(Debug, thiserror::Error)]
+pub enum HorizonLogError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("not a horizon log, it does not start with {MAGIC:?}")]
+    NotAHorizonLog,
+    #[error("unsupported version {0} of the horizon log, expected {VERSION}")]
+    UnsupportedVersion(u32),
+}
+
+/// Write the magic bytes and version that start a horizon log
+///
+/// # Errors
+///
+/// Will return `Err` if writing to `writer` fails
+pub fn write_header(writer: &mut impl Write) -> std::io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())
+}
+
+impl Frame {
+    /// Append the frame to a horizon log
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if writing to `writer` fails
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writer.write_all(&self.time.to_le_bytes())?;
+        writer.write_all(&(self.horizons.len() as u32).to_le_bytes())?;
+        for horizon in &self.horizons {
+            writer.write_all(&horizon.robot.to_le_bytes())?;
+            writer.write_all(&(horizon.means.len() as u32).to_le_bytes())?;
+            for value in horizon.means.iter().flat_map(Vec4::to_array) {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the next frame of a horizon log
+    fn read_from(reader: &mut impl Read) -> std::io::Result<Self> {
+        let time = f64::from_le_bytes(read_bytes(reader)?);
+        let robots = u32::from_le_bytes(read_bytes(reader)?);
+        let horizons = (0..robots)
+            .map(|_| {
+                let robot = u64::from_le_bytes(read_bytes(reader)?);
+                let variables = u32::from_le_bytes(read_bytes(reader)?);
+                let means = (0..variables)
+                    .map(|_| {
+                        let mut mean = [0.0; 4];
+                        for value in &mut mean {
+                            *value = f32::from_le_bytes(read_bytes(reader)?);
+                        }
+                        Ok(Vec4::from_array(mean))
+                    })
+                    .collect::<std::io::Result<_>>()?;
+                Ok(Horizon { robot, means })
+            })
+            .collect::<std::io::Result<_>>()?;
+
+        Ok(Self { time, horizons })
+    }
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Read all frames of a horizon log. A truncated last frame, e.g. from a
+/// simulation that crashed while recording, is ignored
+///
+/// # Errors
+///
+/// Will return `Err` if reading fails, or `reader` is not a horizon log of
+/// the supported version
+pub fn read_log(mut reader: impl Read) -> Result<Vec<Frame>, HorizonLogError> {
+    if read_bytes::<4>(&mut reader)? != *MAGIC {
+        return Err(HorizonLogError::NotAHorizonLog);
+    }
+    let version = u32::from_le_bytes(read_bytes(&mut reader)?);
+    if version != VERSION {
+        return Err(HorizonLogError::UnsupportedVersion(version));
+    }
+
+    let mut frames = Vec::new();
+    loop {
+        match Frame::read_from(&mut reader) {
+            Ok(frame) => frames.push(frame),
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(frames),
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// Plugin that records the horizons of the robots every fixed timestep,
+/// and/or replays a recorded log on top of the simulation
+#[derive(Debug, Default)]
+pub struct HorizonLogPlugin {
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+}
+
+impl HorizonLogPlugin {
+    /// Record the horizons to a new log at `path`
+    #[must_use]
+    pub fn record_to(mut self, path: PathBuf) -> Self {
+        self.record = Some(path);
+        self
+    }
+
+    /// Replay the log at `path`
+    #[must_use]
+    pub fn replay(mut self, path: PathBuf) -> Self {
+        self.replay = Some(path);
+        self
+    }
+}
+
+impl Plugin for HorizonLogPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(ref path) = self.record {
+            let recorder = File::create(path).and_then(|file| {
+                let mut writer = BufWriter::new(file);
+                write_header(&mut writer)?;
+                Ok(HorizonRecorder { writer })
+            });
+            match recorder {
+                Ok(recorder) => {
+                    info!("recording horizons to '{}'", path.display());
+                    app.insert_resource(recorder).add_systems(
+                        FixedPostUpdate,
+                        record_horizons.run_if(
+                            resource_exists::<HorizonRecorder>
+                                .and_then(not(virtual_time_is_paused)),
+                        ),
+                    );
+                }
+                Err(err) => error!("failed to create horizon log '{}': {err}", path.display()),
+            }
+        }
+
+        if let Some(ref path) = self.replay {
+            let frames = File::open(path)
+                .map_err(HorizonLogError::from)
+                .and_then(|file| read_log(BufReader::new(file)));
+            match frames {
+                Ok(frames) if frames.is_empty() => {
+                    warn!("horizon log '{}' has no frames", path.display());
+                }
+                Ok(frames) => {
+                    info!(
+                        "replaying {} frames from horizon log '{}'",
+                        frames.len(),
+                        path.display()
+                    );
+                    let time = frames[0].time;
+                    app.insert_resource(HorizonReplay {
+                        frames,
+                        time,
+                        playing: true,
+                    })
+                    .add_systems(
+                        Update,
+                        (advance_replay, draw_replayed_horizons, render_replay_window).chain(),
+                    );
+                }
+                Err(err) => error!("failed to read horizon log '{}': {err}", path.display()),
+            }
+        }
+    }
+}
+
+/// **Bevy** [`Resource`]
+/// The log horizons are recorded to
+#[derive(Resource)]
+struct HorizonRecorder {
+    writer: BufWriter<File>,
+}
+
+/// **Bevy** [`Resource`]
+/// The frames of a replayed log, and the point in time shown
+#[derive(Debug, Resource)]
+struct HorizonReplay {
+    /// Sorted by time
+    frames:  Vec<Frame>,
+    /// Simulation time of the log shown. SI unit: s
+    time:    f64,
+    /// Advance `time` with the virtual time of the simulation
+    playing: bool,
+}
+
+impl HorizonReplay {
+    /// The last frame at or before `self.time`
+    fn current(&self) -> Option<&Frame> {
+        let index = self.frames.partition_point(|frame| frame.time <= self.time);
+        self.frames.get(index.checked_sub(1)?)
+    }
+
+    /// Time of the first and last frame
+    fn span(&self) -> (f64, f64) {
+        let first = self.frames.first().map_or(0.0, |frame| frame.time);
+        let last = self.frames.last().map_or(0.0, |frame| frame.time);
+        (first, last)
+    }
+}
+
+fn record_horizons(
+    mut commands: Commands,
+    mut recorder: ResMut<HorizonRecorder>,
+    robots: Query<(Entity, &FactorGraph)>,
+    time_virtual: Res<Time<Virtual>>,
+) {
+    #[allow(clippy::cast_possible_truncation)]
+    let horizons = robots
+        .iter()
+        .map(|(entity, factorgraph)| Horizon {
+            robot: entity.to_bits(),
+            means: factorgraph
+                .variables()
+                .map(|(_, variable)| {
+                    let mean = &variable.belief.mean;
+                    Vec4::new(
+                        mean[0] as f32,
+                        mean[1] as f32,
+                        mean[2] as f32,
+                        mean[3] as f32,
+                    )
+                })
+                .collect(),
+        })
+        .collect();
+    let frame = Frame {
+        time: time_virtual.elapsed_seconds_f64(),
+        horizons,
+    };
+
+    if let Err(err) = frame
+        .write_to(&mut recorder.writer)
+        .and_then(|()| recorder.writer.flush())
+    {
+        error!("failed to write to the horizon log, stopping the recording: {err}");
+        commands.remove_resource::<HorizonRecorder>();
+    }
+}
+
+fn advance_replay(mut replay: ResMut<HorizonReplay>, time_virtual: Res<Time<Virtual>>) {
+    if !replay.playing {
+        return;
+    }
+
+    let (_, last) = replay.span();
+    replay.time = (replay.time + time_virtual.delta_seconds_f64()).min(last);
+}
+
+/// Draw the replayed horizon of every robot as a line through the means of its
+/// variables, with a circle at its current state
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+fn draw_replayed_horizons(mut gizmos: Gizmos, replay: Res<HorizonReplay>, config: Res<Config>) {
+    let Some(frame) = replay.current() else {
+        return;
+    };
+
+    let height = config.visualisation.height.objects;
+    for horizon in &frame.horizons {
+        // a stable colour per robot, as the robots of the log need not exist
+        let color = Color::hsl((horizon.robot.wrapping_mul(47) % 360) as f32, 0.7, 0.6);
+        let points = horizon
+            .means
+            .iter()
+            .map(|mean| Vec3::new(mean.x, height, mean.y));
+        gizmos.linestrip(points, color);
+
+        if let Some(current) = horizon.means.first() {
+            gizmos.circle(
+                Vec3::new(current.x, height, current.y),
+                Direction3d::Y,
+                0.5,
+                color,
+            );
+        }
+    }
+}
+
+fn render_replay_window(
+    mut egui_ctx: EguiContexts,
+    mut replay: ResMut<HorizonReplay>,
+    mut ui_state: ResMut<UiState>,
+    config: Res<Config>,
+) {
+    let (first, last) = replay.span();
+    egui::Window::new("Horizon Replay")
+        .collapsible(true)
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui_state.mouse_over.floating_window = ui.rect_contains_pointer(ui.max_rect())
+                && config.interaction.ui_focus_cancels_inputs;
+
+            ui.checkbox(&mut replay.playing, "Play");
+            ui.add(egui::Slider::new(&mut replay.time, first..=last).suffix(" s"));
+            let robots = replay.current().map_or(0, |frame| frame.horizons.len());
+            ui.label(format!("{} frames, {robots} robots", replay.frames.len()));
+        });
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn frames() -> Vec<Frame> {
+        vec![
+            Frame {
+                time:     0.0,
+                horizons: vec![Horizon {
+                    robot: 7,
+                    means: vec![Vec4::new(1.0, 2.0, 3.0, 4.0), Vec4::splat(-1.5)],
+                }],
+            },
+            Frame {
+                time:     0.1,
+                horizons: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn frames_survive_a_round_trip() {
+        let mut log = Vec::new();
+        write_header(&mut log).unwrap();
+        for frame in frames() {
+            frame.write_to(&mut log).unwrap();
+        }
+
+        assert_eq!(read_log(log.as_slice()).unwrap(), frames());
+    }
+
+    #[test]
+    fn truncated_frames_are_ignored() {
+        let mut log = Vec::new();
+        write_header(&mut log).unwrap();
+        for frame in frames() {
+            frame.write_to(&mut log).unwrap();
+        }
+        log.truncate(log.len() - 2);
+
+        assert_eq!(read_log(log.as_slice()).unwrap(), frames()[..1]);
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        assert!(matches!(
+            read_log(b"{\"json\": true}".as_slice()),
+            Err(HorizonLogError::NotAHorizonLog)
+        ));
+    }
+}
@@ -35,7 +35,14 @@ impl Plugin for CameraInputPlugin {
         app.init_resource::<CameraSensitivity>()
             .add_plugins(InputManagerPlugin::<CameraAction>::default())
             .add_systems(PostStartup, bind_camera_input)
-            .add_systems(Update, (camera_actions, switch_camera));
+            .add_systems(
+                Update,
+                (
+                    camera_actions,
+                    switch_camera,
+                    fly_camera.run_if(in_state(CameraMovement::Fly)),
+                ),
+            );
     }
 }
 
@@ -78,6 +85,8 @@ pub enum CameraAction {
     ZoomOut,
     Switch,
     Reset,
+    ToggleFlyMode,
+    Fly,
 }
 
 impl std::fmt::Display for CameraAction {
@@ -90,6 +99,8 @@ impl std::fmt::Display for CameraAction {
             Self::ZoomOut => "Zoom Out",
             Self::Switch => "Switch",
             Self::Reset => "Reset",
+            Self::ToggleFlyMode => "Toggle Fly Mode",
+            Self::Fly => "Fly",
         })
     }
 }
@@ -119,6 +130,8 @@ impl CameraAction {
             }
             Self::Switch => Some(UserInput::Single(InputKind::PhysicalKey(KeyCode::Tab))),
             Self::Reset => Some(UserInput::Single(InputKind::PhysicalKey(KeyCode::KeyR))),
+            Self::ToggleFlyMode => Some(UserInput::Single(InputKind::PhysicalKey(KeyCode::KeyV))),
+            Self::Fly => Some(UserInput::VirtualDPad(VirtualDPad::wasd())),
             _ => None,
         }
     }
@@ -229,6 +242,24 @@ fn camera_actions(
             camera_reset_event.send(ResetCamera);
         }
 
+        if action_state.just_pressed(&CameraAction::ToggleFlyMode) {
+            next_state.set(if *state.get() == CameraMovement::Fly {
+                CameraMovement::Pan
+            } else {
+                CameraMovement::Fly
+            });
+        }
+
+        if *state.get() == CameraMovement::Fly {
+            // handled by `fly_camera`
+            velocity.0 = Vec3::ZERO;
+            angular_velocity.value = Vec3::ZERO;
+            if action_state.just_pressed(&CameraAction::ToggleMovementMode) {
+                next_state.set(state.get().next());
+            }
+            return;
+        }
+
         let mut tmp_velocity = Vec3::ZERO;
         let mut tmp_angular_velocity = Vec3::ZERO;
         let camera_distance = transform.translation.distance(orbit.origin);
@@ -253,6 +284,7 @@ fn camera_actions(
                         // * camera_settings.speed;
                     }
                 }
+                CameraMovement::Fly => unreachable!("handled by `fly_camera`"),
                 CameraMovement::Orbit => {
                     if let Some(action) = action_state
                         .axis_pair(&CameraAction::MouseMove)
@@ -286,6 +318,7 @@ fn camera_actions(
                             / 35.0;
                     }
                 }
+                CameraMovement::Fly => unreachable!("handled by `fly_camera`"),
                 CameraMovement::Orbit => {
                     // action represents the direction to move the camera around it's origin
                     if let Some(direction) = action_state
@@ -350,6 +383,71 @@ fn camera_actions(
     }
 }
 
+/// **Bevy** [`Update`] system moving the main camera in
+/// [`CameraMovement::Fly`] mode. WASD and the arrow keys move the camera along
+/// its view direction, and dragging with the mouse turns it in place
+#[allow(clippy::type_complexity)]
+fn fly_camera(
+    mut query: Query<
+        (
+            &ActionState<CameraAction>,
+            &mut Transform,
+            &mut Orbit,
+            &Camera,
+        ),
+        With<MainCamera>,
+    >,
+    currently_changing: Res<ChangingBinding>,
+    action_block: Option<Res<ActionBlock>>,
+    sensitivity: Res<CameraSensitivity>,
+    camera_settings: Res<CameraSettings>,
+    time: Res<Time<Real>>,
+) {
+    let Ok((action_state, mut transform, mut orbit, camera)) = query.get_single_mut() else {
+        return;
+    };
+
+    let is_action_blocked = action_block.is_some_and(|block| block.is_blocked());
+    if currently_changing.on_cooldown()
+        || currently_changing.is_changing()
+        || is_action_blocked
+        || !camera.is_active
+    {
+        return;
+    }
+
+    if action_state.pressed(&CameraAction::MouseMove) {
+        if let Some(motion) = action_state
+            .axis_pair(&CameraAction::MouseMove)
+            .map(|axis| axis.xy())
+        {
+            let yaw = -motion.x * camera_settings.look_sensitivity * sensitivity.move_sensitivity;
+            let pitch = -motion.y * camera_settings.look_sensitivity * sensitivity.move_sensitivity;
+            let right = *transform.right();
+            transform.rotate_axis(Vec3::Y, yaw);
+            transform.rotate_axis(right, pitch);
+        }
+    }
+
+    let direction = [CameraAction::Fly, CameraAction::Move]
+        .iter()
+        .filter_map(|action| action_state.clamped_axis_pair(action))
+        .map(|axis| axis.xy())
+        .sum::<Vec2>()
+        .normalize_or_zero();
+
+    if direction != Vec2::ZERO {
+        let translation = (*transform.forward() * direction.y + *transform.right() * direction.x)
+            * camera_settings.fly_speed
+            * sensitivity.move_sensitivity
+            * time.delta_seconds();
+        transform.translation += translation;
+        // keep the orbit origin under the camera, so switching back to pan or
+        // orbit continues from the current view
+        orbit.origin += translation;
+    }
+}
+
 // #[derive(Debug, Event, Clone, Copy)]
 // pub enum ChangeCameraFocus;
 
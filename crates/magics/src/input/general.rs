@@ -19,8 +19,12 @@ use crate::{
         graphviz::{ExportGraph, NodeKind},
         prelude::FactorGraph,
     },
-    pause_play::PausePlay,
-    planner::{robot::RadioAntenna, RobotConnections, RobotId},
+    pause_play::{PausePlay, StepSimulation},
+    persist::VisualisationChanges,
+    planner::{
+        click_spawn::ToggleClickToSpawn, robot::RadioAntenna, spawner::SelectedRobot,
+        teleoperation::ToggleTeleoperation, RobotConnections, RobotId,
+    },
     simulation_loader::SaveSettings,
     theme::CatppuccinTheme,
 };
@@ -41,6 +45,7 @@ impl Plugin for GeneralInputPlugin {
             .add_event::<DrawSettingsEvent>()
             .add_event::<QuitApplication>()
             .add_event::<ExportFactorGraphAsGraphvizFinished>()
+            .add_event::<DumpFactorGraphAsJson>()
             .add_plugins(InputManagerPlugin::<GeneralAction>::default())
             .add_systems(PostStartup, bind_general_input)
             .add_systems(
@@ -48,7 +53,14 @@ impl Plugin for GeneralInputPlugin {
                 (
                     general_actions_system,
                     pause_play_simulation.run_if(event_exists::<PausePlay>),
+                    step_simulation.run_if(event_exists::<StepSimulation>),
+                    toggle_click_to_spawn.run_if(event_exists::<ToggleClickToSpawn>),
+                    toggle_teleoperation.run_if(event_exists::<ToggleTeleoperation>),
                     export_graph_on_event.run_if(on_event::<ExportFactorGraphAsGraphviz>()),
+                    dump_factorgraph,
+                    dump_factorgraph_as_json_on_event
+                        .run_if(on_event::<DumpFactorGraphAsJson>())
+                        .after(dump_factorgraph),
                     export_graph_finished_system.run_if(
                         event_exists::<ToastEvent>
                             .and_then(on_event::<ExportFactorGraphAsGraphvizFinished>()),
@@ -70,6 +82,12 @@ pub struct EnvironmentEvent;
 #[derive(Event, Debug, Copy, Clone)]
 pub struct ExportFactorGraphAsGraphviz;
 
+/// Simple **Bevy** trigger `Event`
+/// Write to this event whenever you want to dump the factorgraph of the
+/// selected robot to a `.json` file
+#[derive(Event, Debug, Copy, Clone)]
+pub struct DumpFactorGraphAsJson;
+
 /// **Bevy** `Event` for the draw settings
 /// This event is triggered when a draw setting is toggled
 #[derive(Event, Debug, Clone)]
@@ -139,6 +157,14 @@ pub enum GeneralAction {
     QuitApplication,
     /// Toggle the simulation time between paused and playing
     PausePlaySimulation,
+    /// Advance the paused simulation by `manual.timesteps-per-step` timesteps
+    StepSimulation,
+    /// Toggle the interaction mode where clicking spawns robots
+    ToggleClickToSpawn,
+    /// Toggle direct velocity control of the selected robot
+    ToggleTeleoperation,
+    /// Dump the factorgraph of the selected robot as `json`
+    DumpFactorGraph,
 }
 
 impl std::fmt::Display for GeneralAction {
@@ -150,6 +176,10 @@ impl std::fmt::Display for GeneralAction {
             Self::SaveSettings => "Save Settings",
             Self::QuitApplication => "Quit Application",
             Self::PausePlaySimulation => "Pause/Play Simulation",
+            Self::StepSimulation => "Step Simulation",
+            Self::ToggleClickToSpawn => "Toggle Click to Spawn",
+            Self::ToggleTeleoperation => "Toggle Teleoperation",
+            Self::DumpFactorGraph => "Dump Factorgraph",
         })
     }
 }
@@ -169,6 +199,10 @@ impl GeneralAction {
                 UserInput::modified(Modifier::Control, InputKind::PhysicalKey(KeyCode::KeyQ))
             }
             Self::PausePlaySimulation => UserInput::Single(InputKind::PhysicalKey(KeyCode::Space)),
+            Self::StepSimulation => UserInput::Single(InputKind::PhysicalKey(KeyCode::KeyM)),
+            Self::ToggleClickToSpawn => UserInput::Single(InputKind::PhysicalKey(KeyCode::KeyN)),
+            Self::ToggleTeleoperation => UserInput::Single(InputKind::PhysicalKey(KeyCode::KeyB)),
+            Self::DumpFactorGraph => UserInput::Single(InputKind::PhysicalKey(KeyCode::KeyI)),
         }
     }
 }
@@ -213,14 +247,23 @@ fn export_factorgraphs_as_graphviz(
     append_line_to_output("  dpi=96;");
     append_line_to_output(r#"  label="factorgraph""#);
     append_line_to_output("  node [style=filled];");
-    append_line_to_output("  layout=neato;");
+    append_line_to_output(&format!("  layout={};", config.graphviz.layout.as_str()));
+
+    // label for an edge with the number of messages of the factor of the edge
+    let message_label = |messages: usize| {
+        if config.graphviz.message_counts {
+            format!(r#" [label="{messages}"]"#)
+        } else {
+            String::new()
+        }
+    };
 
     // A hashmap used to keep track of which variable in another robots factorgraph,
     // is connected to a interrobot factor in the current robots factorgraph.
-    let mut all_external_connections =
-        HashMap::<RobotId, HashMap<usize, (RobotId, usize, bool)>>::with_capacity(
-            query.iter().len(),
-        );
+    let mut all_external_connections = HashMap::<
+        RobotId,
+        HashMap<usize, (RobotId, usize, bool, usize)>,
+    >::with_capacity(query.iter().len());
 
     for (robot_id, factorgraph, antenna) in query.iter() {
         let (nodes, edges) = factorgraph.export_graph();
@@ -242,8 +285,11 @@ fn export_factorgraphs_as_graphviz(
                 NodeKind::DynamicFactor => "fd".to_string(),
                 NodeKind::ObstacleFactor => "fo".to_string(),
                 NodeKind::TrackingFactor => "ft".to_string(),
+                NodeKind::RegionFactor => "fc".to_string(),
+                NodeKind::PoseFactor => "fp".to_string(),
             };
 
+            let attributes = node.attributes(&config.graphviz.nodes);
             let line = {
                 let mut line = String::with_capacity(32);
                 line.push_str(&format!(
@@ -252,9 +298,9 @@ fn export_factorgraphs_as_graphviz(
                     node.index,
                     label,
                     // node.index,
-                    node.color(),
-                    node.shape(),
-                    node.width()
+                    attributes.color,
+                    attributes.shape,
+                    attributes.width
                 ));
                 if let Some((x, y)) = pos {
                     // line.push_str(&format!(r#", pos="{x},{y}!""#));
@@ -283,15 +329,29 @@ fn export_factorgraphs_as_graphviz(
 
         append_line_to_output("");
         // Add all internal edges
+        let factor_messages: HashMap<usize, usize> = nodes
+            .iter()
+            .filter(|node| node.kind.is_factor())
+            .map(|node| (node.index, node.messages))
+            .collect();
         for edge in &edges {
+            let messages = factor_messages
+                .get(&edge.from)
+                .or_else(|| factor_messages.get(&edge.to))
+                .copied()
+                .unwrap_or_default();
             let line = format!(
-                r#""{:?}_{:?}" -- "{:?}_{:?}""#,
-                robot_id, edge.from, robot_id, edge.to
+                r#""{:?}_{:?}" -- "{:?}_{:?}"{}"#,
+                robot_id,
+                edge.from,
+                robot_id,
+                edge.to,
+                message_label(messages)
             );
             append_line_to_output(&line);
         }
 
-        let external_connections: HashMap<usize, (RobotId, usize, bool)> = nodes
+        let external_connections: HashMap<usize, (RobotId, usize, bool, usize)> = nodes
             .into_iter()
             .filter_map(|node| match node.kind {
                 NodeKind::InterRobotFactor {
@@ -303,6 +363,7 @@ fn export_factorgraphs_as_graphviz(
                         external_variable_id.factorgraph_id,
                         external_variable_id.variable_index.index(),
                         antenna.active,
+                        node.messages,
                         // connection.id_of_robot_connected_with,
                         // connection
                         //     .index_of_connected_variable_in_other_robots_factorgraph
@@ -319,9 +380,14 @@ fn export_factorgraphs_as_graphviz(
     // Add edges between interrobot factors and the variable they are connected to
     // in another robots graph
     for (from_robot_id, from_connections) in all_external_connections {
-        for (from_factor, (to_robot_id, to_variable_index, active)) in from_connections {
+        for (from_factor, (to_robot_id, to_variable_index, active, messages)) in from_connections {
+            let label = if config.graphviz.message_counts {
+                format!(r#", label="{messages}""#)
+            } else {
+                String::new()
+            };
             append_line_to_output(&format!(
-                r#" "{:?}_{:?}" -- "{:?}_{:?}" [len={}, style={}, color="{}", penwidth=3.0]"#,
+                r#" "{:?}_{:?}" -- "{:?}_{:?}" [len={}, style={}, color="{}", penwidth=3.0{}]"#,
                 from_robot_id,
                 from_factor,
                 to_robot_id,
@@ -340,7 +406,8 @@ fn export_factorgraphs_as_graphviz(
                     &config.graphviz.interrobot.active.color
                 } else {
                     &config.graphviz.interrobot.inactive.color
-                }
+                },
+                label
             ));
         }
     }
@@ -437,9 +504,9 @@ fn handle_export_graph(
 
     std::fs::write(&dot_output_path, output.as_bytes())?;
 
+    let extension = config.graphviz.format.extension();
     IoTaskPool::get()
         .spawn(async move {
-            let extension = "png";
             let image_output_path = dot_output_path.with_extension(extension);
             let args = [
                 "-T",
@@ -512,11 +579,23 @@ pub struct QuitApplication;
 fn quit_application_system(
     mut quit_application_reader: EventReader<QuitApplication>,
     mut app_exit_event: EventWriter<AppExit>,
+    visualisation_changes: Option<ResMut<VisualisationChanges>>,
+    config: Res<Config>,
 ) {
-    for _ in quit_application_reader.read() {
-        info!("quitting application");
-        app_exit_event.send(AppExit);
+    if quit_application_reader.read().last().is_none() {
+        return;
+    }
+
+    if let Some(mut changes) = visualisation_changes {
+        if changes.is_dirty(&config.visualisation) {
+            info!("visualisation settings changed, asking to save them before quitting");
+            changes.open_prompt();
+            return;
+        }
     }
+
+    info!("quitting application");
+    app_exit_event.send(AppExit);
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -596,6 +675,142 @@ fn pause_play_simulation(
     }
 }
 
+fn step_simulation(
+    query: Query<&ActionState<GeneralAction>, With<GeneralInputs>>,
+    currently_changing: Res<ChangingBinding>,
+    mut evw_step_simulation: EventWriter<StepSimulation>,
+) {
+    if currently_changing.on_cooldown() || currently_changing.is_changing() {
+        return;
+    }
+
+    let Ok(action_state) = query.get_single() else {
+        warn!("step_simulation was called without an action state!");
+        return;
+    };
+
+    if action_state.just_pressed(&GeneralAction::StepSimulation) {
+        evw_step_simulation.send(StepSimulation);
+    }
+}
+
+fn toggle_click_to_spawn(
+    query: Query<&ActionState<GeneralAction>, With<GeneralInputs>>,
+    currently_changing: Res<ChangingBinding>,
+    mut evw_toggle_click_to_spawn: EventWriter<ToggleClickToSpawn>,
+) {
+    if currently_changing.on_cooldown() || currently_changing.is_changing() {
+        return;
+    }
+
+    let Ok(action_state) = query.get_single() else {
+        warn!("toggle_click_to_spawn was called without an action state!");
+        return;
+    };
+
+    if action_state.just_pressed(&GeneralAction::ToggleClickToSpawn) {
+        evw_toggle_click_to_spawn.send(ToggleClickToSpawn);
+    }
+}
+
+fn toggle_teleoperation(
+    query: Query<&ActionState<GeneralAction>, With<GeneralInputs>>,
+    currently_changing: Res<ChangingBinding>,
+    mut evw_toggle_teleoperation: EventWriter<ToggleTeleoperation>,
+) {
+    if currently_changing.on_cooldown() || currently_changing.is_changing() {
+        return;
+    }
+
+    let Ok(action_state) = query.get_single() else {
+        warn!("toggle_teleoperation was called without an action state!");
+        return;
+    };
+
+    if action_state.just_pressed(&GeneralAction::ToggleTeleoperation) {
+        evw_toggle_teleoperation.send(ToggleTeleoperation);
+    }
+}
+
+fn dump_factorgraph(
+    query: Query<&ActionState<GeneralAction>, With<GeneralInputs>>,
+    currently_changing: Res<ChangingBinding>,
+    mut evw_dump_factorgraph: EventWriter<DumpFactorGraphAsJson>,
+) {
+    if currently_changing.on_cooldown() || currently_changing.is_changing() {
+        return;
+    }
+
+    let Ok(action_state) = query.get_single() else {
+        warn!("dump_factorgraph was called without an action state!");
+        return;
+    };
+
+    if action_state.just_pressed(&GeneralAction::DumpFactorGraph) {
+        evw_dump_factorgraph.send(DumpFactorGraphAsJson);
+    }
+}
+
+/// **Bevy** [`Update`] system, that writes the factorgraph of the selected
+/// robot to `./factorgraph-<robot>-<time>.json`, where `<time>` is the
+/// simulated time in milliseconds. Successive dumps of the same robot sort in
+/// the order they were taken, so they can be compared with `diff`
+fn dump_factorgraph_as_json_on_event(
+    mut evr_dump_factorgraph: EventReader<DumpFactorGraphAsJson>,
+    selected_robot: Res<SelectedRobot>,
+    q_factorgraphs: Query<&FactorGraph>,
+    time: Res<Time<Virtual>>,
+    mut evw_toast: EventWriter<ToastEvent>,
+) {
+    if evr_dump_factorgraph.read().last().is_none() {
+        return;
+    }
+
+    let Some(factorgraph) = selected_robot.and_then(|robot| q_factorgraphs.get(robot).ok()) else {
+        evw_toast.send(ToastEvent::warning(
+            "select a robot to dump its factorgraph by clicking on it".to_string(),
+        ));
+        return;
+    };
+
+    if cfg!(target_arch = "wasm32") {
+        error!("there is not filesystem access on target_arch wasm32");
+        return;
+    }
+
+    let json = match factorgraph.dump().to_json_pretty() {
+        Ok(json) => json,
+        Err(err) => {
+            error!("failed to serialize factorgraph with error: {:?}", err);
+            return;
+        }
+    };
+
+    let path = std::path::PathBuf::from(format!(
+        "factorgraph-{:?}-{:08}.json",
+        factorgraph.id(),
+        time.elapsed().as_millis()
+    ));
+    if let Err(err) = std::fs::write(&path, json) {
+        error!("failed to write {:?} with error: {:?}", path, err);
+        evw_toast.send(ToastEvent::error(format!(
+            "failed to dump factorgraph to ./{}",
+            path.display()
+        )));
+        return;
+    }
+
+    info!(
+        "dumped factorgraph of robot {:?} to ./{}",
+        factorgraph.id(),
+        path.display()
+    );
+    evw_toast.send(ToastEvent::info(format!(
+        "dumped factorgraph to ./{}",
+        path.display()
+    )));
+}
+
 fn screenshot(
     query: Query<&ActionState<GeneralAction>, With<GeneralInputs>>,
     currently_changing: Res<ChangingBinding>,
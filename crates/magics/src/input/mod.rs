@@ -10,7 +10,9 @@ pub mod screenshot;
 pub mod ui;
 
 pub use camera::{CameraAction, CameraSensitivity};
-pub use general::{DrawSettingsEvent, ExportFactorGraphAsGraphviz, GeneralAction};
+pub use general::{
+    DrawSettingsEvent, DumpFactorGraphAsJson, ExportFactorGraphAsGraphviz, GeneralAction,
+};
 pub use moveable_object::{MoveableObjectAction, MoveableObjectSensitivity};
 use screenshot::ScreenshotPlugin;
 pub use ui::UiAction;
@@ -81,6 +83,10 @@ impl ToUiString for GeneralAction {
             Self::ScreenShot => "Take Screenshot".to_string(),
             Self::QuitApplication => "Quit Application".to_string(),
             Self::PausePlaySimulation => "Pause/Play Simulation".to_string(),
+            Self::StepSimulation => "Step Simulation".to_string(),
+            Self::ToggleClickToSpawn => "Toggle Click to Spawn".to_string(),
+            Self::ToggleTeleoperation => "Toggle Teleoperation".to_string(),
+            Self::DumpFactorGraph => "Dump Factorgraph".to_string(),
         }
     }
 }
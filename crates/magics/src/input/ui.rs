@@ -4,7 +4,7 @@ use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
 use super::super::ui::UiState;
-use crate::{input::ChangingBinding, ui::UiScaleType};
+use crate::{environment::camera::CameraMovement, input::ChangingBinding, ui::UiScaleType};
 
 pub struct UiInputPlugin;
 
@@ -41,6 +41,8 @@ pub enum UiAction {
     ToggleBottomPanel,
     #[display(fmt = "Toggle Metrics Window")]
     ToggleMetricsWindow,
+    #[display(fmt = "Toggle Convergence Window")]
+    ToggleConvergenceWindow,
     ChangeScaleKind,
 }
 
@@ -52,7 +54,8 @@ impl UiAction {
             Self::ToggleTopPanel => InputKind::PhysicalKey(KeyCode::KeyK),
             Self::ToggleBottomPanel => InputKind::PhysicalKey(KeyCode::KeyJ),
             Self::ChangeScaleKind => InputKind::PhysicalKey(KeyCode::KeyU),
-            Self::ToggleMetricsWindow => InputKind::PhysicalKey(KeyCode::KeyD), // d for diagnostics
+            Self::ToggleMetricsWindow => InputKind::PhysicalKey(KeyCode::KeyD), /* d for diagnostics */
+            Self::ToggleConvergenceWindow => InputKind::PhysicalKey(KeyCode::KeyO), /* o for optimisation */
         };
 
         UserInput::Single(input_kind)
@@ -74,6 +77,7 @@ fn handle_ui_actions(
     query: Query<&ActionState<UiAction>>,
     mut ui_state: ResMut<UiState>,
     currently_changing: Res<ChangingBinding>,
+    camera_movement: Res<State<CameraMovement>>,
 ) {
     if currently_changing.on_cooldown() || currently_changing.is_changing() {
         return;
@@ -98,10 +102,17 @@ fn handle_ui_actions(
         ui_state.bottom_panel_visible = !ui_state.bottom_panel_visible;
     }
 
-    if action_state.just_pressed(&UiAction::ToggleMetricsWindow) {
+    // `D` also strafes the camera in fly mode
+    if action_state.just_pressed(&UiAction::ToggleMetricsWindow)
+        && *camera_movement.get() != CameraMovement::Fly
+    {
         ui_state.metrics_window_visible = !ui_state.metrics_window_visible;
     }
 
+    if action_state.just_pressed(&UiAction::ToggleConvergenceWindow) {
+        ui_state.convergence_window_visible = !ui_state.convergence_window_visible;
+    }
+
     if action_state.just_pressed(&UiAction::ChangeScaleKind) {
         ui_state.scale_type = match ui_state.scale_type {
             UiScaleType::None => UiScaleType::Custom,
@@ -3,7 +3,9 @@
 use bevy::ecs::schedule::States;
 
 pub mod asset_loader;
+pub mod bench;
 pub mod bevy_utils;
+pub mod checkpoint;
 pub mod cli;
 pub mod despawn_entity_after;
 pub mod diagnostic;
@@ -11,12 +13,19 @@ pub mod environment;
 pub mod export;
 pub mod factorgraph;
 pub mod goal_area;
+pub mod horizon_log;
 pub mod input;
+pub mod logging;
+pub mod manifest;
 pub mod moveable_object;
 pub mod movement;
 pub mod pause_play;
+pub mod persist;
 pub mod planner;
+pub mod playlist;
 pub mod simulation_loader;
+pub mod snapshot;
+pub mod sweep;
 pub mod theme;
 pub mod ui;
 pub(crate) mod utils;
@@ -0,0 +1,133 @@
+//! Module for writing the log to a rolling file as JSON lines, one object per
+//! event, so headless runs can be investigated afterwards.
+
+use std::{
+    io::Write,
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+
+use bevy::{
+    log::{
+        tracing_subscriber::{
+            layer::{Context, SubscriberExt},
+            Layer,
+        },
+        BoxedSubscriber,
+    },
+    utils::tracing::{
+        field::{Field, Visit},
+        Event, Subscriber,
+    },
+};
+use gbp_config::{DebugSection, LogRotation};
+use tracing_appender::rolling::{InitError, RollingFileAppender, Rotation};
+
+/// The file the log is written to. `LogPlugin::update_subscriber` only takes
+/// a function pointer, so the file can not be captured
+static LOG_FILE: OnceLock<Mutex<RollingFileAppender>> = OnceLock::new();
+
+/// Open the log file set in `debug`, and return the function to pass as
+/// `LogPlugin::update_subscriber` to write the log to it. Returns `None` if no
+/// log file is set
+///
+/// # Errors
+///
+/// Will return `Err` if the log file can not be created
+pub fn file_logging(
+    debug: &DebugSection,
+) -> Result<Option<fn(BoxedSubscriber) -> BoxedSubscriber>, InitError> {
+    let Some(ref path) = debug.log_file else {
+        return Ok(None);
+    };
+
+    let directory = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let prefix = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "magics.log".to_string());
+
+    let rotation = match debug.log_rotation {
+        LogRotation::Minutely => Rotation::MINUTELY,
+        LogRotation::Hourly => Rotation::HOURLY,
+        LogRotation::Daily => Rotation::DAILY,
+        LogRotation::Never => Rotation::NEVER,
+    };
+
+    let appender = RollingFileAppender::builder()
+        .rotation(rotation)
+        .filename_prefix(prefix)
+        .build(directory)?;
+
+    if LOG_FILE.set(Mutex::new(appender)).is_err() {
+        // only one log subscriber can be installed
+        return Ok(None);
+    }
+
+    Ok(Some(add_file_layer))
+}
+
+fn add_file_layer(subscriber: BoxedSubscriber) -> BoxedSubscriber {
+    Box::new(subscriber.with(JsonFileLayer))
+}
+
+/// Writes every event that passes the log filter to [`LOG_FILE`]
+struct JsonFileLayer;
+
+impl<S: Subscriber> Layer<S> for JsonFileLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let Some(file) = LOG_FILE.get() else {
+            return;
+        };
+
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+
+        let metadata = event.metadata();
+        let line = serde_json::json!({
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "level": metadata.level().as_str(),
+            "target": metadata.target(),
+            "fields": fields.0,
+        });
+
+        if let Ok(mut file) = file.lock() {
+            // there is nowhere left to report a failure to write the log
+            let _ = writeln!(file, "{line}");
+        }
+    }
+}
+
+/// Collects the fields of an event, including its message, into a JSON object
+#[derive(Default)]
+struct JsonFields(serde_json::Map<String, serde_json::Value>);
+
+impl Visit for JsonFields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}").into());
+    }
+}
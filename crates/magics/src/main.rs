@@ -1,21 +1,29 @@
 #![feature(iter_repeat_n)]
 //! The main entry point of the simulation.
 pub(crate) mod asset_loader;
+pub(crate) mod bench;
 mod bevy_utils;
+pub(crate) mod checkpoint;
 pub mod cli;
 pub mod despawn_entity_after;
 mod diagnostic;
 mod environment;
 mod factorgraph;
 pub mod goal_area;
+pub(crate) mod horizon_log;
 mod input;
+pub(crate) mod logging;
 mod moveable_object;
 mod movement;
 pub(crate) mod pause_play;
+pub(crate) mod persist;
 // mod scene;
 
 pub mod planner;
+pub(crate) mod playlist;
 pub(crate) mod simulation_loader;
+pub(crate) mod snapshot;
+pub(crate) mod sweep;
 
 pub(crate) mod theme;
 pub(crate) mod ui;
@@ -25,6 +33,7 @@ pub mod export;
 
 pub(crate) mod escape_codes;
 pub(crate) mod macros;
+pub(crate) mod manifest;
 
 // #[cfg(feature = "dhat-heap")]
 // #[global_allocator]
@@ -38,7 +47,9 @@ use std::{path::Path, time::Duration};
 
 use bevy::{
     asset::AssetMetaCheck,
+    diagnostic::{EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
     input::common_conditions::input_just_pressed,
+    log::LogPlugin,
     prelude::*,
     render::{
         camera::RenderTarget,
@@ -48,7 +59,7 @@ use bevy::{
         RenderPlugin,
     },
     time::common_conditions::once_after_real_delay,
-    window::{PrimaryWindow, WindowMode, WindowResolution},
+    window::{PresentMode, PrimaryWindow, WindowMode, WindowResolution},
 };
 use bevy_image_export::{
     ImageExportBundle, ImageExportPlugin, ImageExportSettings, ImageExportSource,
@@ -190,6 +201,7 @@ fn main() -> anyhow::Result<()> {
             EnvironmentType::Complex => Environment::complex(),
             EnvironmentType::Maze => Environment::maze(),
             EnvironmentType::Test => Environment::test(),
+            EnvironmentType::NarrowCorridor => Environment::narrow_corridor(3, 4, 0.3, 10.0),
         };
 
         let yaml = serde_yaml::to_string(&env)?;
@@ -300,6 +312,8 @@ fn main() -> anyhow::Result<()> {
 
     eprintln!("initial window mode: {:?}", window_mode);
 
+    let headless = cli.headless || cli.bench_sim.is_some();
+
     let window_plugin = if cfg!(target_arch = "wasm32") {
         WindowPlugin {
             primary_window: Some(Window {
@@ -328,7 +342,14 @@ fn main() -> anyhow::Result<()> {
                 mode: window_mode,
                 window_theme: None,
                 position: WindowPosition::Centered(MonitorSelection::Primary),
-                visible: true,
+                // headless runs and the benchmark only need the window for the UI
+                // to not panic
+                visible: !headless,
+                present_mode: if headless {
+                    PresentMode::AutoNoVsync
+                } else {
+                    PresentMode::default()
+                },
                 resizable: !cli.record,
                 resolution: WindowResolution::new(width as f32, height as f32)
                     .with_scale_factor_override(1.0),
@@ -346,6 +367,83 @@ fn main() -> anyhow::Result<()> {
     let verbosity = cli.verbosity();
     eprintln!("verbosity level: {:?}", verbosity);
 
+    let resume = cli
+        .resume
+        .as_ref()
+        .map(|path| {
+            snapshot::SimulationSnapshot::load(path).map(|snapshot| (path.clone(), snapshot))
+        })
+        .transpose()?;
+
+    let playlist = if let Some((_, ref checkpoint)) = resume {
+        (!checkpoint.playlist.is_empty())
+            .then(|| playlist::Playlist::from_entries(checkpoint.playlist.clone()))
+            .transpose()?
+    } else if let Some(ref path) = cli.sweep {
+        Some(sweep::Sweep::from_file(path)?.into_playlist()?)
+    } else if let Some(ref path) = cli.playlist_file {
+        Some(playlist::Playlist::from_file(path)?)
+    } else if !cli.playlist.is_empty() {
+        Some(playlist::Playlist::new(cli.playlist.clone())?)
+    } else if let (Some(_), Some(ref scenario)) = (cli.monte_carlo, &cli.initial_scenario) {
+        Some(playlist::Playlist::new([scenario.clone()])?)
+    } else {
+        None
+    }
+    .map(|playlist| playlist.with_seeds(&cli.playlist_seeds));
+
+    let playlist = match cli.monte_carlo {
+        Some(repetitions) => {
+            let Some(playlist) = playlist else {
+                anyhow::bail!("--monte-carlo needs an --initial-scenario or a playlist to run");
+            };
+            let seeds = (0..repetitions.get()).collect::<Vec<_>>();
+            let summary = cli
+                .monte_carlo_summary
+                .clone()
+                .unwrap_or_else(|| "monte-carlo.json".into());
+            Some(playlist.with_seeds(&seeds).with_summary(summary))
+        }
+        None => playlist,
+    };
+
+    let initial_scenario = playlist
+        .as_ref()
+        .and_then(playlist::Playlist::first)
+        .map(|entry| entry.simulation.clone())
+        .or_else(|| {
+            resume
+                .as_ref()
+                .map(|(_, checkpoint)| checkpoint.simulation.clone())
+        })
+        .or_else(|| cli.initial_scenario.clone());
+
+    // the debug settings of the initial simulation decide what instrumentation
+    // is set up, as it can not be changed after the app is built
+    let debug = simulation_loader::initial_config(initial_scenario.as_deref())
+        .map(|config| config.debug)
+        .unwrap_or_default();
+    let update_subscriber = logging::file_logging(&debug)?;
+
+    let mut checkpoint_plugin = checkpoint::CheckpointPlugin::default();
+    if let Some(seconds) = cli.checkpoint_every {
+        checkpoint_plugin = checkpoint_plugin.every(Duration::from_secs_f64(seconds));
+    }
+    if let Some(ref path) = cli.checkpoint_file {
+        checkpoint_plugin = checkpoint_plugin.path(path.clone());
+    }
+    if let Some((path, checkpoint)) = resume {
+        checkpoint_plugin = checkpoint_plugin.resume_from(path, checkpoint);
+    }
+
+    let mut horizon_log_plugin = horizon_log::HorizonLogPlugin::default();
+    if let Some(ref path) = cli.record_horizons {
+        horizon_log_plugin = horizon_log_plugin.record_to(path.clone());
+    }
+    if let Some(ref path) = cli.replay_horizons {
+        horizon_log_plugin = horizon_log_plugin.replay(path.clone());
+    }
+
     // bevy app
     let mut app = App::new();
 
@@ -394,6 +492,11 @@ fn main() -> anyhow::Result<()> {
         .add_plugins(DefaultPlugins
             .set(window_plugin)
             .set(image_plugin)
+            .set(LogPlugin {
+                level: debug.log_level.into(),
+                filter: debug.log_filter.clone(),
+                update_subscriber,
+            })
             .set(RenderPlugin {
                                     synchronous_pipeline_compilation: true,
                                     ..default()
@@ -409,7 +512,7 @@ fn main() -> anyhow::Result<()> {
         .add_plugins((
             // simulation_loader::SimulationLoaderPlugin::default(),
             despawn_entity_after::DespawnEntityAfterPlugin,
-            simulation_loader::SimulationLoaderPlugin::new(true, cli.initial_scenario.clone()),
+            simulation_loader::SimulationLoaderPlugin::new(true, initial_scenario),
             pause_play::PausePlayPlugin::default(),
             theme::ThemePlugin,
             asset_loader::AssetLoaderPlugin,
@@ -422,9 +525,42 @@ fn main() -> anyhow::Result<()> {
             export::ExportPlugin::default(),
             bevy_fullscreen::ToggleFullscreenPlugin::default(),
             goal_area::GoalAreaPlugin,
+            snapshot::SnapshotPlugin,
+        ))
+        .add_plugins((
+            manifest::ManifestPlugin,
+            persist::PersistPlugin,
+            export::recorder::TrajectoryRecorderPlugin,
         ))
+        .add_plugins((checkpoint_plugin, horizon_log_plugin))
         .add_systems(Update, draw_coordinate_system.run_if(input_just_pressed(KeyCode::F1)))
-        .add_systems(PostUpdate, end_simulation.run_if(virtual_time_exceeds_max_time));
+        .add_systems(
+            PostUpdate,
+            end_simulation.run_if(
+                virtual_time_exceeds_max_time.and_then(not(resource_exists::<playlist::Playlist>)),
+            ),
+        );
+
+    if let Some(playlist) = playlist {
+        app.add_plugins(playlist::PlaylistPlugin::new(playlist));
+    }
+
+    if debug.fps_counter {
+        app.add_plugins(FrameTimeDiagnosticsPlugin);
+    }
+    if debug.entity_counter {
+        app.add_plugins(EntityCountDiagnosticsPlugin::default());
+    }
+    if debug.solver_diagnostics {
+        app.add_plugins(diagnostic::prelude::RobotDiagnosticsPlugin::default());
+    }
+    if debug.log_diagnostics {
+        app.add_plugins(LogDiagnosticsPlugin {
+            debug: true,
+            wait_duration: Duration::from_secs(1),
+            ..Default::default()
+        });
+    }
 
     if let Some(schedule) = cli.schedule_graph {
         match schedule {
@@ -457,6 +593,26 @@ fn main() -> anyhow::Result<()> {
         return Ok(());
     }
 
+    if cli.headless {
+        // the invisible window is never focused, and would otherwise only be
+        // updated a few times per second
+        app.insert_resource(bevy::winit::WinitSettings {
+            focused_mode:   bevy::winit::UpdateMode::Continuous,
+            unfocused_mode: bevy::winit::UpdateMode::Continuous,
+        });
+    }
+
+    if let Some(seconds) = cli.bench_sim {
+        if !(seconds.is_finite() && seconds > 0.0) {
+            anyhow::bail!("--bench-sim needs a positive number of seconds, got {seconds}");
+        }
+        let mut bench_plugin = bench::BenchPlugin::new(Duration::from_secs_f64(seconds));
+        if let Some(ref path) = cli.bench_sim_report {
+            bench_plugin = bench_plugin.report_to(path.clone());
+        }
+        app.add_plugins(bench_plugin);
+    }
+
     if cli.record {
         app.add_plugins(export_plugin);
         app.add_systems(
@@ -0,0 +1,138 @@
+//! Reproducibility manifests, recording the exact inputs of every run.
+//!
+//! Whenever a simulation is loaded or reloaded, a [`Manifest`] with the git
+//! commit the binary was built from, the command line, the effective config
+//! after overrides, the hashes of the simulation files and the seed is
+//! written to `export.manifest-directory`. The manifest of the current run is
+//! also embedded in the exported report, so any result can be traced back to
+//! the inputs that produced it.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use bevy::prelude::*;
+use gbp_config::Config;
+use sha2::{Digest, Sha256};
+
+use crate::simulation_loader::{
+    latest_loaded_simulation, LoadSimulation, LoadedSimulation, ReloadSimulation,
+};
+
+/// The git commit the binary was built from, with a `-dirty` suffix if the
+/// working tree had uncommitted changes
+pub const GIT_COMMIT: &str = env!("GIT_COMMIT");
+
+pub struct ManifestPlugin;
+
+impl Plugin for ManifestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentManifest>().add_systems(
+            Update,
+            write_manifest
+                .run_if(on_event::<LoadSimulation>().or_else(on_event::<ReloadSimulation>())),
+        );
+    }
+}
+
+/// The exact inputs of a run
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Manifest {
+    /// See [`GIT_COMMIT`]
+    pub git_commit: String,
+    pub version:    String,
+    /// The command line the application was started with
+    pub args:       Vec<String>,
+    /// When the run started, in RFC 3339 format
+    pub started_at: String,
+    pub simulation: String,
+    pub prng_seed:  u64,
+    /// SHA-256 of the files the simulation was loaded from
+    pub sources:    BTreeMap<String, String>,
+    /// The config used for the run, after all overrides
+    pub config:     Config,
+}
+
+impl Manifest {
+    /// Create the manifest of a run of `simulation`
+    #[must_use]
+    pub fn new(simulation: &LoadedSimulation) -> Self {
+        Self {
+            git_commit: GIT_COMMIT.to_string(),
+            version:    env!("CARGO_PKG_VERSION").to_string(),
+            args:       std::env::args().collect(),
+            started_at: chrono::Utc::now().to_rfc3339(),
+            simulation: simulation.name.to_string(),
+            prng_seed:  simulation.config.simulation.prng_seed,
+            sources:    (*simulation.sources).clone(),
+            config:     (*simulation.config).clone(),
+        }
+    }
+
+    /// Write the manifest as pretty printed JSON to a new file in `directory`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the directory can not be created, or the file can
+    /// not be written
+    pub fn write_to(&self, directory: impl Into<PathBuf>) -> std::io::Result<PathBuf> {
+        let directory = directory.into();
+        std::fs::create_dir_all(&directory)?;
+
+        let basename = format!(
+            "manifest_{}_{}.json",
+            self.simulation.to_lowercase().replace(' ', "-"),
+            chrono::Utc::now().timestamp_millis()
+        );
+        let path = directory.join(basename);
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+}
+
+/// **Bevy** [`Resource`] with the manifest of the current run
+#[derive(Debug, Default, Resource, Deref)]
+pub struct CurrentManifest(Option<Manifest>);
+
+/// SHA-256 of `bytes`, as a lowercase hex string
+#[must_use]
+pub fn sha256(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn write_manifest(
+    mut evr_load_simulation: EventReader<LoadSimulation>,
+    mut evr_reload_simulation: EventReader<ReloadSimulation>,
+    mut current: ResMut<CurrentManifest>,
+    config: Res<Config>,
+) {
+    let Some(simulation) =
+        latest_loaded_simulation(&mut evr_load_simulation, &mut evr_reload_simulation)
+    else {
+        return;
+    };
+
+    let manifest = Manifest::new(simulation);
+    if config.export.manifest {
+        match manifest.write_to(&config.export.manifest_directory) {
+            Ok(path) => info!("wrote run manifest to {}", path.display()),
+            Err(err) => error!("failed to write run manifest: {err}"),
+        }
+    }
+    current.0 = Some(manifest);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_of_known_input() {
+        assert_eq!(
+            sha256(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
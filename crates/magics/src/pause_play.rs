@@ -9,6 +9,7 @@ pub struct PausePlayPlugin;
 impl Plugin for PausePlayPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<PausePlay>()
+            .add_event::<StepSimulation>()
             .add_systems(PreUpdate, pause_play_virtual_time);
     }
 }
@@ -45,3 +46,9 @@ fn pause_play_virtual_time(
         }
     }
 }
+
+/// Event for advancing the paused simulation by
+/// `config.manual.timesteps_per_step` fixed timesteps, see
+/// [`ManualModeState`](crate::planner::robot::ManualModeState)
+#[derive(Debug, Clone, Copy, Default, Event)]
+pub struct StepSimulation;
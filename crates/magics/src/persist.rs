@@ -0,0 +1,293 @@
+//! Persisting visualisation settings changed at runtime.
+//!
+//! The draw toggles, the theme and the camera mode can all be changed while a
+//! simulation is running. When the application is quit with unsaved changes to
+//! any of them, a prompt offers to write the `[visualisation]` table back into
+//! the `config.toml` of the active simulation, or into [`USER_OVERRIDES`].
+//! The user override file is applied on top of the config of every simulation
+//! when it is loaded, so a preferred setup survives restarts without editing
+//! each scenario.
+
+use std::path::Path;
+
+use bevy::{app::AppExit, prelude::*};
+use bevy_egui::{egui, EguiContexts};
+use gbp_config::{Config, VisualisationSection};
+
+use crate::{
+    environment::camera::CameraMovement,
+    simulation_loader::{LoadSimulation, SaveSettings, SimulationManager, SIMULATIONS_DIR},
+    theme::{flavour_from_config, flavour_to_config, CatppuccinTheme, CycleTheme},
+};
+
+/// File with the visualisation settings applied on top of the config of every
+/// simulation
+pub const USER_OVERRIDES: &str = "./config/visualisation.toml";
+
+#[derive(Debug, thiserror::Error)]
+pub enum PersistError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to parse TOML: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("failed to serialize TOML: {0}")]
+    Serialize(#[from] toml::ser::Error),
+}
+
+pub struct PersistPlugin;
+
+impl Plugin for PersistPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<bevy_egui::EguiPlugin>() {
+            app.add_plugins(bevy_egui::EguiPlugin);
+        }
+
+        app.init_resource::<VisualisationChanges>().add_systems(
+            Update,
+            (
+                apply_theme.run_if(on_event::<LoadSimulation>()),
+                take_snapshot
+                    .run_if(on_event::<LoadSimulation>().or_else(on_event::<SaveSettings>())),
+                record_theme.run_if(on_event::<CycleTheme>()),
+                record_camera_movement.run_if(state_changed::<CameraMovement>),
+                render_prompt.run_if(|changes: Res<VisualisationChanges>| changes.prompt),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// **Bevy** [`Resource`] tracking whether the visualisation settings have
+/// changed since the simulation was loaded, or last saved
+#[derive(Debug, Default, Resource)]
+pub struct VisualisationChanges {
+    /// The `[visualisation]` table when the simulation was loaded, or last
+    /// saved
+    snapshot: Option<String>,
+    /// Whether the prompt to save the changes is open
+    prompt:   bool,
+}
+
+impl VisualisationChanges {
+    /// Whether `visualisation` differs from the settings at the last snapshot
+    #[must_use]
+    pub fn is_dirty(&self, visualisation: &VisualisationSection) -> bool {
+        self.snapshot
+            .as_ref()
+            .is_some_and(|snapshot| toml::to_string(visualisation).ok().as_ref() != Some(snapshot))
+    }
+
+    /// Open the prompt to save the changes before quitting
+    pub fn open_prompt(&mut self) {
+        self.prompt = true;
+    }
+}
+
+/// Replace the `[visualisation]` table of the TOML file at `path` with
+/// `visualisation`, keeping the rest of the file. The file is created if it
+/// does not exist.
+///
+/// # Errors
+///
+/// Will return `Err` if the file can not be read, parsed or written
+pub fn write_visualisation<P: AsRef<Path>>(
+    path: P,
+    visualisation: &VisualisationSection,
+) -> Result<(), PersistError> {
+    let path = path.as_ref();
+    let mut table: toml::Table = match std::fs::read_to_string(path) {
+        Ok(contents) => toml::from_str(&contents)?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => toml::Table::new(),
+        Err(err) => return Err(err.into()),
+    };
+    table.insert(
+        "visualisation".to_string(),
+        toml::Value::try_from(visualisation)?,
+    );
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, toml::to_string_pretty(&table)?)?;
+    Ok(())
+}
+
+/// Apply the visualisation settings of [`USER_OVERRIDES`] to `config`, if the
+/// file exists
+///
+/// # Errors
+///
+/// Will return `Err` if the file exists, but can not be read or parsed
+pub fn apply_user_overrides(config: &mut Config) -> Result<(), PersistError> {
+    #[derive(serde::Deserialize)]
+    struct Overrides {
+        visualisation: Option<VisualisationSection>,
+    }
+
+    let contents = match std::fs::read_to_string(USER_OVERRIDES) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let overrides: Overrides = toml::from_str(&contents)?;
+    if let Some(visualisation) = overrides.visualisation {
+        config.visualisation = visualisation;
+    }
+    Ok(())
+}
+
+fn apply_theme(
+    mut evr_load_simulation: EventReader<LoadSimulation>,
+    theme: Res<CatppuccinTheme>,
+    mut evw_cycle_theme: EventWriter<CycleTheme>,
+) {
+    let Some(loaded) = evr_load_simulation.read().last() else {
+        return;
+    };
+    let Some(flavour) = loaded.config.visualisation.theme else {
+        return;
+    };
+    let flavour = flavour_from_config(flavour);
+    if flavour != theme.flavour {
+        evw_cycle_theme.send(CycleTheme(flavour));
+    }
+}
+
+fn take_snapshot(mut changes: ResMut<VisualisationChanges>, config: Res<Config>) {
+    changes.snapshot = toml::to_string(&config.visualisation).ok();
+}
+
+fn record_theme(mut evr_cycle_theme: EventReader<CycleTheme>, mut config: ResMut<Config>) {
+    if let Some(CycleTheme(flavour)) = evr_cycle_theme.read().last() {
+        config.visualisation.theme = Some(flavour_to_config(*flavour));
+    }
+}
+
+fn record_camera_movement(movement: Res<State<CameraMovement>>, mut config: ResMut<Config>) {
+    config.visualisation.camera = (*movement.get()).into();
+}
+
+/// Where to save the visualisation settings
+#[derive(Debug, Clone, Copy)]
+enum Destination {
+    /// The `config.toml` of the active simulation
+    Simulation,
+    /// See [`USER_OVERRIDES`]
+    User,
+}
+
+fn render_prompt(
+    mut egui_ctx: EguiContexts,
+    mut changes: ResMut<VisualisationChanges>,
+    mut simulation_manager: ResMut<SimulationManager>,
+    config: Res<Config>,
+    mut evw_app_exit: EventWriter<AppExit>,
+) {
+    let mut destination = None;
+    let mut quit = false;
+    egui::Window::new("Unsaved Visualisation Settings")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.label("The visualisation settings have changed. Save them before quitting?");
+            ui.separator();
+            ui.horizontal(|ui| {
+                let simulation = ui.add_enabled(
+                    simulation_manager.active_name().is_some(),
+                    egui::Button::new("Save to Simulation"),
+                );
+                if simulation
+                    .on_hover_text("config.toml of the active simulation")
+                    .clicked()
+                {
+                    destination = Some(Destination::Simulation);
+                }
+                if ui
+                    .button("Save as Default")
+                    .on_hover_text(USER_OVERRIDES)
+                    .clicked()
+                {
+                    destination = Some(Destination::User);
+                }
+                if ui.button("Discard").clicked() {
+                    quit = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    changes.prompt = false;
+                }
+            });
+        });
+
+    if let Some(destination) = destination {
+        let path = match destination {
+            Destination::Simulation => {
+                let Some(name) = simulation_manager.active_name() else {
+                    return;
+                };
+                Path::new(SIMULATIONS_DIR).join(name).join("config.toml")
+            }
+            Destination::User => USER_OVERRIDES.into(),
+        };
+
+        match write_visualisation(&path, &config.visualisation) {
+            Ok(()) => {
+                info!("saved visualisation settings to: {}", path.display());
+                if let (Destination::Simulation, Some(simulation)) =
+                    (destination, simulation_manager.active_mut())
+                {
+                    simulation.config.visualisation = config.visualisation.clone();
+                }
+                quit = true;
+            }
+            Err(err) => {
+                // keep the prompt open, so the user can try the other destination
+                error!(
+                    "failed to save visualisation settings to {}: {err}",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    if quit {
+        info!("quitting application");
+        evw_app_exit.send(AppExit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_visualisation_keeps_the_rest_of_the_file() {
+        let path =
+            std::env::temp_dir().join(format!("gbp-persist-{}-config.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "[simulation]\nhz = 10.0\n\n[visualisation]\ncamera = \"fly\"\n",
+        )
+        .unwrap();
+
+        write_visualisation(&path, &VisualisationSection::default()).unwrap();
+
+        let table: toml::Table = toml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(table["simulation"]["hz"].as_float(), Some(10.0));
+        assert_eq!(table["visualisation"]["camera"].as_str(), Some("pan"));
+    }
+
+    #[test]
+    fn changes_are_dirty_after_the_snapshot() {
+        let mut visualisation = VisualisationSection::default();
+        let mut changes = VisualisationChanges::default();
+        assert!(!changes.is_dirty(&visualisation));
+
+        changes.snapshot = toml::to_string(&visualisation).ok();
+        assert!(!changes.is_dirty(&visualisation));
+
+        visualisation.camera = gbp_config::CameraMode::Orbit;
+        assert!(changes.is_dirty(&visualisation));
+    }
+}
@@ -0,0 +1,349 @@
+//! Batteries and charging stations, for long running logistics scenarios.
+//!
+//! With `robot.battery.enabled` every robot gets a [`Battery`], which drains
+//! over time and with the distance driven. When the charge drops below
+//! `robot.battery.low-threshold`, a detour via the nearest
+//! [`ChargingStation`] of the environment is inserted into the mission of the
+//! robot. Once inside the station the robot parks until fully charged, and
+//! then continues its mission. A robot with an empty battery parks for good.
+use bevy::prelude::*;
+use bevy_notify::ToastEvent;
+use gbp_config::Config;
+
+use super::{
+    local_planner::{LocalPlan, LocalPlanner, LocalPlannerPlugin, PlannerInput},
+    robot::{Mission, MissionState, StateVector},
+    RobotConnections, RobotId,
+};
+use crate::{
+    bevy_utils::run_conditions::time::virtual_time_is_paused,
+    simulation_loader::{self, LoadSimulation, ReloadSimulation},
+    theme::{CatppuccinTheme, ColorFromCatppuccinColourExt},
+};
+
+pub struct BatteryPlugin;
+
+impl Plugin for BatteryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(LocalPlannerPlugin::<Parked>::default())
+            .add_event::<BatteryDepleted>()
+            .add_systems(
+                Update,
+                spawn_charging_stations
+                    .run_if(on_event::<LoadSimulation>().or_else(on_event::<ReloadSimulation>())),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    attach_batteries,
+                    drain_batteries,
+                    seek_charging_stations,
+                    charge_batteries,
+                )
+                    .chain()
+                    .run_if(not(virtual_time_is_paused)),
+            );
+    }
+}
+
+/// **Bevy** [`Event`] emitted when the battery of a robot runs empty
+#[derive(Debug, Clone, Copy, Event)]
+pub struct BatteryDepleted(pub RobotId);
+
+/// **Bevy** [`Component`] with the battery of a robot
+#[derive(Debug, Component)]
+pub struct Battery {
+    /// Remaining energy
+    pub charge:    f32,
+    /// Energy when fully charged
+    pub capacity:  f32,
+    /// The station the robot is on its way to, or charging at
+    pub station:   Option<Entity>,
+    /// Position of the robot at the last update, to measure the distance
+    /// driven
+    last_position: Vec2,
+}
+
+impl Battery {
+    /// A fully charged battery
+    #[must_use]
+    pub const fn full(capacity: f32, position: Vec2) -> Self {
+        Self {
+            charge: capacity,
+            capacity,
+            station: None,
+            last_position: position,
+        }
+    }
+
+    /// Remaining charge, as a fraction of the capacity
+    #[must_use]
+    pub fn fraction(&self) -> f32 {
+        if self.capacity > 0.0 {
+            self.charge / self.capacity
+        } else {
+            0.0
+        }
+    }
+
+    /// Use the energy of driving `distance` meters in `delta_t` seconds
+    pub fn drain(&mut self, settings: &gbp_config::BatterySection, distance: f32, delta_t: f32) {
+        let speed = if delta_t > 0.0 {
+            distance / delta_t
+        } else {
+            0.0
+        };
+        let energy = settings.drain_per_second * delta_t
+            + distance * settings.drag.mul_add(speed, settings.drain_per_meter);
+        self.charge = (self.charge - energy).max(0.0);
+    }
+
+    /// Whether the battery is empty
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.charge <= 0.0
+    }
+
+    /// Whether the battery is fully charged
+    #[inline]
+    #[must_use]
+    pub fn is_full(&self) -> bool {
+        self.charge >= self.capacity
+    }
+}
+
+/// **Bevy** [`Component`] of a charging station in the environment
+#[derive(Debug, Component, Clone, Copy)]
+pub struct ChargingStation {
+    /// Robots within this distance are charged. SI unit: m
+    pub radius: f32,
+    /// Energy charged per second
+    pub rate:   f32,
+}
+
+/// **Bevy** [`Component`] stopping a robot, while it is charging or after its
+/// battery has run empty
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq)]
+pub enum Parked {
+    Charging,
+    Depleted,
+}
+
+impl LocalPlanner for Parked {
+    fn plan(&mut self, _input: &PlannerInput) -> LocalPlan {
+        LocalPlan::velocity(Vec2::ZERO)
+    }
+}
+
+fn spawn_charging_stations(
+    mut commands: Commands,
+    mut evr_load_simulation: EventReader<LoadSimulation>,
+    mut evr_reload_simulation: EventReader<ReloadSimulation>,
+    theme: Res<CatppuccinTheme>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(loaded) = simulation_loader::latest_loaded_simulation(
+        &mut evr_load_simulation,
+        &mut evr_reload_simulation,
+    ) else {
+        return;
+    };
+    let (environment, config) = (&loaded.environment, &loaded.config);
+    if environment.charging_stations.is_empty() {
+        return;
+    }
+
+    let material = materials.add(StandardMaterial {
+        base_color: Color::from_catppuccin_colour_with_alpha(theme.green(), 0.5),
+        alpha_mode: AlphaMode::Blend,
+        ..default()
+    });
+
+    for station in &environment.charging_stations {
+        let position = environment.centered(station.position);
+        commands.spawn((
+            simulation_loader::Reloadable,
+            ChargingStation {
+                radius: station.radius,
+                rate:   station.rate,
+            },
+            PbrBundle {
+                mesh: meshes.add(Cylinder::new(station.radius, 0.1)),
+                material: material.clone(),
+                transform: Transform::from_translation(Vec3::new(
+                    position.x,
+                    -config.visualisation.height.objects / 2.0,
+                    position.y,
+                )),
+                ..default()
+            },
+        ));
+        info!("spawned charging station at {}", position);
+    }
+}
+
+fn attach_batteries(
+    mut commands: Commands,
+    robots: Query<(Entity, &Transform), Added<RobotConnections>>,
+    config: Res<Config>,
+) {
+    if !config.robot.battery.enabled {
+        return;
+    }
+
+    for (robot, transform) in &robots {
+        commands.entity(robot).insert(Battery::full(
+            config.robot.battery.capacity,
+            transform.translation.xz(),
+        ));
+    }
+}
+
+fn drain_batteries(
+    mut commands: Commands,
+    mut robots: Query<(Entity, &Transform, &mut Battery, Option<&Parked>)>,
+    config: Res<Config>,
+    time: Res<Time>,
+    mut evw_battery_depleted: EventWriter<BatteryDepleted>,
+    mut evw_toast: EventWriter<ToastEvent>,
+) {
+    for (robot, transform, mut battery, parked) in &mut robots {
+        let position = transform.translation.xz();
+        let distance = position.distance(battery.last_position);
+        battery.last_position = position;
+        if parked == Some(&Parked::Charging) {
+            continue;
+        }
+
+        battery.drain(&config.robot.battery, distance, time.delta_seconds());
+        if battery.is_empty() && parked.is_none() {
+            warn!("battery of robot {:?} is depleted", robot);
+            commands.entity(robot).insert(Parked::Depleted);
+            evw_battery_depleted.send(BatteryDepleted(robot));
+            evw_toast.send(ToastEvent::warning(format!(
+                "battery of robot {robot:?} is depleted"
+            )));
+        }
+    }
+}
+
+/// Send robots low on charge to the nearest charging station
+fn seek_charging_stations(
+    mut robots: Query<(Entity, &Transform, &mut Battery, &mut Mission), Without<Parked>>,
+    stations: Query<(Entity, &Transform), With<ChargingStation>>,
+    config: Res<Config>,
+    time: Res<Time>,
+) {
+    for (robot, transform, mut battery, mut mission) in &mut robots {
+        // Wait with the detour while a route is being planned, as the planned
+        // route would replace it
+        if battery.station.is_some()
+            || battery.fraction() >= config.robot.battery.low_threshold
+            || mission.state != MissionState::Active
+        {
+            continue;
+        }
+
+        let position = transform.translation.xz();
+        let Some((station, station_transform)) = stations.iter().min_by(|(_, a), (_, b)| {
+            let a = a.translation.xz().distance_squared(position);
+            let b = b.translation.xz().distance_squared(position);
+            a.total_cmp(&b)
+        }) else {
+            continue;
+        };
+
+        let station_position = station_transform.translation.xz();
+        let velocity =
+            (station_position - position).normalize_or_zero() * config.robot.target_speed.get();
+        mission.detour(
+            StateVector::new(position.extend(velocity.x).extend(velocity.y)),
+            StateVector::new(station_position.extend(0.0).extend(0.0)),
+            &time,
+        );
+        battery.station = Some(station);
+        info!(
+            "robot {:?} is low on battery, detouring via charging station {:?}",
+            robot, station
+        );
+    }
+}
+
+fn charge_batteries(
+    mut commands: Commands,
+    mut robots: Query<(Entity, &Transform, &mut Battery, Option<&Parked>)>,
+    stations: Query<(&Transform, &ChargingStation)>,
+    time: Res<Time>,
+) {
+    for (robot, transform, mut battery, parked) in &mut robots {
+        let Some(station) = battery.station else {
+            continue;
+        };
+        let Ok((station_transform, station)) = stations.get(station) else {
+            // the station is gone, look for another one
+            battery.station = None;
+            continue;
+        };
+
+        let distance = transform
+            .translation
+            .xz()
+            .distance(station_transform.translation.xz());
+        if distance > station.radius {
+            continue;
+        }
+
+        if parked.is_none() {
+            info!("robot {:?} started charging", robot);
+            commands.entity(robot).insert(Parked::Charging);
+        }
+        battery.charge = station
+            .rate
+            .mul_add(time.delta_seconds(), battery.charge)
+            .min(battery.capacity);
+
+        if battery.is_full() {
+            info!("robot {:?} finished charging", robot);
+            commands.entity(robot).remove::<Parked>();
+            battery.station = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drain_grows_with_distance_and_speed() {
+        let settings = gbp_config::BatterySection {
+            enabled: true,
+            capacity: 100.0,
+            drain_per_second: 1.0,
+            drain_per_meter: 2.0,
+            drag: 0.5,
+            low_threshold: 0.2,
+        };
+
+        let mut battery = Battery::full(100.0, Vec2::ZERO);
+        battery.drain(&settings, 0.0, 1.0);
+        assert!((battery.charge - 99.0).abs() < 1e-5);
+
+        // 4 m in 1 s: 1 + 4 * (2 + 0.5 * 4)
+        let mut battery = Battery::full(100.0, Vec2::ZERO);
+        battery.drain(&settings, 4.0, 1.0);
+        assert!((battery.charge - 83.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn charge_does_not_go_negative() {
+        let settings = gbp_config::BatterySection::default();
+        let mut battery = Battery::full(1.0, Vec2::ZERO);
+        battery.drain(&settings, 100.0, 1.0);
+        assert!(battery.is_empty());
+        assert!(battery.charge.abs() < f32::EPSILON);
+        assert!(battery.fraction().abs() < f32::EPSILON);
+    }
+}
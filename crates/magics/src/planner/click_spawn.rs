@@ -0,0 +1,171 @@
+//! Interaction mode to spawn robots by clicking on the ground plane.
+//!
+//! The first click chooses the initial position of the robot, the second click
+//! its goal. The robot is spawned with a [`RobotSpawner`], so the same events
+//! are emitted as for robots spawned by a formation.
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy_notify::ToastEvent;
+use gbp_config::{
+    formation::{PlanningStrategy, ReachedWhen},
+    Config,
+};
+use gbp_global_planner::Colliders;
+
+use super::{
+    robot::Radius,
+    spawn_validation::{overlaps_environment, overlaps_robot},
+    spawner::{RobotSpawnDescription, RobotSpawner},
+    RobotConnections,
+};
+use crate::{
+    bevy_utils::run_conditions::event_exists, environment::cursor::CursorCoordinates,
+    ui::ActionBlock,
+};
+
+pub struct ClickToSpawnPlugin;
+
+impl Plugin for ClickToSpawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ToggleClickToSpawn>()
+            .init_resource::<ClickToSpawn>()
+            .add_systems(
+                Update,
+                (
+                    toggle_click_to_spawn.run_if(event_exists::<ToggleClickToSpawn>),
+                    handle_click.run_if(enabled.and_then(input_just_pressed(MouseButton::Left))),
+                    draw_pending_robot.run_if(enabled),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// **Bevy** [`Event`] to turn the click-to-spawn interaction mode on or off
+#[derive(Debug, Event, Clone, Copy)]
+pub struct ToggleClickToSpawn;
+
+/// **Bevy** [`Resource`] with the state of the click-to-spawn interaction mode
+#[derive(Debug, Default, Resource, Clone, Copy, PartialEq)]
+pub enum ClickToSpawn {
+    /// Clicks are not used to spawn robots
+    #[default]
+    Disabled,
+    /// The next click chooses the initial position of a robot
+    AwaitingStart,
+    /// The next click chooses the goal of a robot starting at `start`
+    AwaitingGoal {
+        /// Initial position of the robot
+        start: Vec2,
+    },
+}
+
+#[inline]
+fn enabled(click_to_spawn: Res<ClickToSpawn>) -> bool {
+    *click_to_spawn != ClickToSpawn::Disabled
+}
+
+fn toggle_click_to_spawn(
+    mut evr_toggle: EventReader<ToggleClickToSpawn>,
+    mut click_to_spawn: ResMut<ClickToSpawn>,
+    mut evw_toast: EventWriter<ToastEvent>,
+) {
+    for _ in evr_toggle.read() {
+        let caption = if *click_to_spawn == ClickToSpawn::Disabled {
+            *click_to_spawn = ClickToSpawn::AwaitingStart;
+            "click-to-spawn enabled: click a free point, then the goal of the robot"
+        } else {
+            *click_to_spawn = ClickToSpawn::Disabled;
+            "click-to-spawn disabled"
+        };
+        info!("{caption}");
+        evw_toast.send(ToastEvent::info(caption.to_string()));
+    }
+}
+
+/// Radius of a robot spawned by clicking, the midpoint of the configured range
+fn robot_radius(config: &Config) -> f32 {
+    (config.robot.radius.min.get() + config.robot.radius.max.get()) / 2.0
+}
+
+/// Advance the click-to-spawn state machine, spawning a robot on every second
+/// click
+fn handle_click(
+    mut click_to_spawn: ResMut<ClickToSpawn>,
+    mut spawner: RobotSpawner,
+    mut evw_toast: EventWriter<ToastEvent>,
+    robots: Query<(&Transform, &Radius), With<RobotConnections>>,
+    colliders: Option<Res<Colliders>>,
+    cursor: Res<CursorCoordinates>,
+    action_block: Option<Res<ActionBlock>>,
+) {
+    if action_block.is_some_and(|block| block.is_blocked()) {
+        return;
+    }
+
+    let position = cursor.global().xz();
+    let radius = robot_radius(&spawner.config);
+    // the goal only has to be clear of the environment, as the other robots
+    // move
+    let is_start = matches!(*click_to_spawn, ClickToSpawn::AwaitingStart);
+    let robots = robots
+        .iter()
+        .map(|(transform, radius)| (transform.translation.xz(), radius.0));
+    if (is_start && overlaps_robot(position, radius, robots))
+        || colliders.is_some_and(|colliders| overlaps_environment(position, radius, &colliders))
+    {
+        let caption = format!("cannot place a robot at {position}, it is not free");
+        warn!("{caption}");
+        evw_toast.send(ToastEvent::warning(caption));
+        return;
+    }
+
+    match *click_to_spawn {
+        ClickToSpawn::Disabled => {}
+        ClickToSpawn::AwaitingStart => {
+            *click_to_spawn = ClickToSpawn::AwaitingGoal { start: position };
+        }
+        ClickToSpawn::AwaitingGoal { start } => {
+            let velocity =
+                (position - start).normalize_or_zero() * spawner.config.robot.target_speed.get();
+            let robot = spawner.spawn(RobotSpawnDescription {
+                initial_pose: start.extend(velocity.x).extend(velocity.y),
+                waypoints: vec![position.extend(velocity.x).extend(velocity.y)],
+                radius,
+                planning_strategy: PlanningStrategy::OnlyLocal,
+                waypoint_reached_when_intersects: ReachedWhen::same_as_paper(),
+                finished_when_intersects: ReachedWhen::same_as_paper(),
+                priority: 0,
+                class: None,
+                color: None,
+            });
+            info!("spawned robot {:?} from {} to {}", robot, start, position);
+            *click_to_spawn = ClickToSpawn::AwaitingStart;
+        }
+    }
+}
+
+/// Draw the robot being placed, and a line from it to the cursor once its
+/// initial position has been chosen
+fn draw_pending_robot(
+    mut gizmos: Gizmos,
+    click_to_spawn: Res<ClickToSpawn>,
+    cursor: Res<CursorCoordinates>,
+    config: Res<Config>,
+) {
+    let height = -config.visualisation.height.objects;
+    let radius = robot_radius(&config);
+    let color = Color::WHITE;
+    let cursor = cursor.global().xz().extend(height).xzy();
+
+    match *click_to_spawn {
+        ClickToSpawn::Disabled => {}
+        ClickToSpawn::AwaitingStart => {
+            gizmos.circle(cursor, Direction3d::Y, radius, color);
+        }
+        ClickToSpawn::AwaitingGoal { start } => {
+            let start = start.extend(height).xzy();
+            gizmos.circle(start, Direction3d::Y, radius, color);
+            gizmos.arrow(start, cursor, color);
+        }
+    }
+}
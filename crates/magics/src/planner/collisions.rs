@@ -59,7 +59,10 @@ impl Plugin for RobotCollisionsPlugin {
             )
             .add_systems(
                 PostUpdate,
-                clear_robot_robot_collisions
+                (
+                    clear_robot_robot_collisions,
+                    clear_robot_environment_collisions,
+                )
                     .run_if(on_event::<LoadSimulation>().or_else(on_event::<ReloadSimulation>())),
             );
     }
@@ -69,6 +72,12 @@ fn clear_robot_robot_collisions(mut robot_collisions: ResMut<resources::RobotRob
     robot_collisions.clear();
 }
 
+fn clear_robot_environment_collisions(
+    mut robot_collisions: ResMut<resources::RobotEnvironmentCollisions>,
+) {
+    robot_collisions.clear();
+}
+
 fn update_robot_robot_collisions(
     mut robot_collisions: ResMut<resources::RobotRobotCollisions>,
     robots: Query<(Entity, &Transform, &Ball), With<RobotConnections>>,
@@ -198,6 +207,7 @@ pub mod resources {
 
         pub(super) fn clear(&mut self) {
             self.inner.clear();
+            self.collisions = 0;
         }
 
         pub(super) fn record_collision(&mut self, event: &events::RobotRobotCollision) {
@@ -290,8 +300,9 @@ pub mod resources {
                 .filter(|(_, v)| v.len() > 0)
         }
 
-        fn clear(&mut self) {
+        pub(super) fn clear(&mut self) {
             self.inner.clear();
+            self.collisions = 0;
         }
 
         pub fn robots_collided_with(&self, obstacle_entity: Entity) -> Option<Vec<Entity>> {
@@ -670,12 +681,21 @@ fn on_obstacle_clicked_on(
         crate::environment::map_generator::events::ObstacleClickedOn,
     >,
     robot_environment_collisions: Res<resources::RobotEnvironmentCollisions>,
+    q_obstacle_metadata: Query<&crate::environment::map_generator::ObstacleMetadata>,
 ) {
     use colored::Colorize;
     for event in evr_obstacle_clicked_on.read() {
         // print all the robots that have hit the obstacle
         let obstacle_entity: Entity = event.0;
         println!("{}: {:?}", "obstacle".magenta(), obstacle_entity);
+        if let Ok(metadata) = q_obstacle_metadata.get(obstacle_entity) {
+            if let Some(ref name) = metadata.name {
+                println!("  {}: {}", "name".cyan(), name);
+            }
+            if !metadata.tags.is_empty() {
+                println!("  {}: {}", "tags".cyan(), metadata.tags.join(", "));
+            }
+        }
         if let Some(robot_entities) =
             robot_environment_collisions.robots_collided_with(obstacle_entity)
         {
@@ -0,0 +1,156 @@
+//! Drag the goal of the selected robot around with the mouse.
+//!
+//! While a robot is selected, pressing the right mouse button spawns a goal
+//! marker under the cursor, which follows the cursor on the ground plane until
+//! the button is released. On release the mission of the robot is retargeted to
+//! go straight from its current position to the new goal.
+use bevy::{
+    input::common_conditions::{input_just_pressed, input_just_released},
+    prelude::*,
+};
+use gbp_config::Config;
+
+use super::{
+    robot::{Mission, StateVector},
+    spawner::{SelectedRobot, WaypointCreated},
+    RobotId,
+};
+use crate::{
+    asset_loader::{Materials, Meshes},
+    environment::cursor::CursorCoordinates,
+    factorgraph::prelude::FactorGraph,
+    simulation_loader,
+    ui::ActionBlock,
+};
+
+pub struct GoalDragPlugin;
+
+impl Plugin for GoalDragPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<GoalDragged>().add_systems(
+            Update,
+            (
+                start_goal_drag.run_if(input_just_pressed(MouseButton::Right)),
+                move_goal_marker,
+                end_goal_drag.run_if(input_just_released(MouseButton::Right)),
+                retarget_robot.run_if(on_event::<GoalDragged>()),
+            )
+                .chain(),
+        );
+    }
+}
+
+/// **Bevy** [`Event`] emitted when a goal marker is released
+#[derive(Debug, Event)]
+pub struct GoalDragged {
+    /// The robot whose goal was dragged
+    pub robot: RobotId,
+    /// The new goal in world coordinates
+    pub goal:  Vec2,
+}
+
+/// **Bevy** [`Component`] marking the goal marker being dragged for a robot
+#[derive(Component)]
+struct GoalMarker(RobotId);
+
+/// Spawn a goal marker under the cursor for the selected robot
+#[allow(clippy::too_many_arguments)]
+fn start_goal_drag(
+    mut commands: Commands,
+    selected_robot: Res<SelectedRobot>,
+    robots: Query<(), With<Mission>>,
+    markers: Query<(), With<GoalMarker>>,
+    cursor: Res<CursorCoordinates>,
+    action_block: Option<Res<ActionBlock>>,
+    meshes: Res<Meshes>,
+    materials: Res<Materials>,
+    config: Res<Config>,
+) {
+    if action_block.is_some_and(|block| block.is_blocked()) || !markers.is_empty() {
+        return;
+    }
+
+    let Some(robot) = selected_robot.0.filter(|&robot| robots.contains(robot)) else {
+        return;
+    };
+
+    let position = cursor.global().xz();
+    commands.spawn((
+        simulation_loader::Reloadable,
+        GoalMarker(robot),
+        PbrBundle {
+            mesh: meshes.waypoint.clone(),
+            material: materials.waypoint.clone(),
+            transform: Transform::from_translation(Vec3::new(
+                position.x,
+                -config.visualisation.height.objects,
+                position.y,
+            )),
+            ..default()
+        },
+    ));
+}
+
+/// Keep the goal marker under the cursor while it is dragged
+fn move_goal_marker(
+    mut markers: Query<&mut Transform, With<GoalMarker>>,
+    cursor: Res<CursorCoordinates>,
+) {
+    let position = cursor.global();
+    for mut transform in &mut markers {
+        transform.translation.x = position.x;
+        transform.translation.z = position.z;
+    }
+}
+
+/// Drop the goal marker, and emit a [`GoalDragged`] event for its robot
+fn end_goal_drag(
+    mut commands: Commands,
+    markers: Query<(Entity, &GoalMarker, &Transform)>,
+    mut evw_goal_dragged: EventWriter<GoalDragged>,
+) {
+    for (entity, &GoalMarker(robot), transform) in &markers {
+        commands.entity(entity).despawn();
+        evw_goal_dragged.send(GoalDragged {
+            robot,
+            goal: transform.translation.xz(),
+        });
+    }
+}
+
+/// Retarget the mission of every robot whose goal was dragged, so the prior of
+/// its horizon state pulls it towards the new goal
+fn retarget_robot(
+    mut evr_goal_dragged: EventReader<GoalDragged>,
+    mut evw_waypoint_created: EventWriter<WaypointCreated>,
+    mut robots: Query<(&FactorGraph, &mut Mission)>,
+    config: Res<Config>,
+    time: Res<Time>,
+) {
+    for &GoalDragged { robot, goal } in evr_goal_dragged.read() {
+        let Ok((factorgraph, mut mission)) = robots.get_mut(robot) else {
+            warn!(
+                "robot {:?} was despawned before its goal was dropped",
+                robot
+            );
+            continue;
+        };
+
+        let (_, current) = factorgraph
+            .first_variable()
+            .expect("factorgraph should have >= 2 variables");
+        let position = current.estimated_position_vec2();
+        let velocity = (goal - position).normalize_or_zero() * config.robot.target_speed.get();
+
+        mission.retarget(
+            StateVector::new(position.extend(velocity.x).extend(velocity.y)),
+            StateVector::new(goal.extend(velocity.x).extend(velocity.y)),
+            &time,
+        );
+        evw_waypoint_created.send(WaypointCreated {
+            for_robot: robot,
+            position:  goal,
+        });
+        info!("retargeted robot {:?} to goal {}", robot, goal);
+    }
+}
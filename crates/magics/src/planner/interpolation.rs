@@ -0,0 +1,164 @@
+//! Interpolation of the rendered position of the robots.
+//!
+//! The robots are planned and moved in `FixedUpdate` at `config.simulation.hz`,
+//! so the result of a simulation does not depend on the frame rate of the
+//! display. Rendered as is, the robots would stutter whenever the frame rate
+//! is not a multiple of `hz`. Instead the [`Transform`] of every robot is set
+//! to a point between its last two fixed timesteps before rendering, and set
+//! back to where the robot actually is before the next fixed timestep. Systems
+//! in the fixed schedules always see the simulated position, while systems in
+//! `Update` see the rendered one.
+use bevy::{prelude::*, transform::TransformSystem};
+use gbp_config::Config;
+
+use crate::factorgraph::prelude::FactorGraph;
+
+pub struct InterpolationPlugin;
+
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedFirst,
+            (track_new_robots, restore_simulated_translation).chain(),
+        )
+        .add_systems(FixedLast, record_simulated_translation)
+        .add_systems(
+            PostUpdate,
+            interpolate_translation
+                .run_if(interpolation_enabled)
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+#[inline]
+fn interpolation_enabled(config: Res<Config>) -> bool {
+    config.simulation.interpolate_transforms
+}
+
+/// **Bevy** [`Component`] with the translation of a robot at the last two
+/// fixed timesteps
+#[derive(Component, Debug, Clone, Copy)]
+pub struct InterpolatedTranslation {
+    /// Translation before the last fixed timestep
+    previous: Vec3,
+    /// Translation after the last fixed timestep
+    current:  Vec3,
+    /// Translation the robot was last rendered at
+    rendered: Vec3,
+}
+
+impl InterpolatedTranslation {
+    /// Create a new [`InterpolatedTranslation`] for a robot at `translation`
+    #[must_use]
+    pub const fn new(translation: Vec3) -> Self {
+        Self {
+            previous: translation,
+            current:  translation,
+            rendered: translation,
+        }
+    }
+
+    /// Move `translation` back to the simulated position of the robot before a
+    /// fixed timestep. A robot moved outside of the fixed schedules, e.g. by
+    /// dragging it, stays where it was moved to
+    fn restore(&mut self, translation: &mut Vec3) {
+        if *translation == self.rendered {
+            *translation = self.current;
+        } else {
+            self.current = *translation;
+        }
+        self.previous = self.current;
+    }
+
+    /// Record the simulated position of the robot after a fixed timestep
+    fn record(&mut self, translation: Vec3) {
+        self.current = translation;
+        self.rendered = translation;
+    }
+
+    /// Move `translation` to `alpha` of the way between the last two fixed
+    /// timesteps
+    fn interpolate(&mut self, translation: &mut Vec3, alpha: f32) {
+        if *translation != self.rendered {
+            // moved outside of the fixed schedules, do not interpolate the jump
+            *self = Self::new(*translation);
+            return;
+        }
+        *translation = self.previous.lerp(self.current, alpha);
+        self.rendered = *translation;
+    }
+}
+
+fn track_new_robots(
+    mut commands: Commands,
+    robots: Query<(Entity, &Transform), (With<FactorGraph>, Without<InterpolatedTranslation>)>,
+) {
+    for (entity, transform) in &robots {
+        commands
+            .entity(entity)
+            .insert(InterpolatedTranslation::new(transform.translation));
+    }
+}
+
+fn restore_simulated_translation(
+    mut robots: Query<(&mut Transform, &mut InterpolatedTranslation)>,
+) {
+    for (mut transform, mut interpolated) in &mut robots {
+        interpolated.restore(&mut transform.translation);
+    }
+}
+
+fn record_simulated_translation(mut robots: Query<(&Transform, &mut InterpolatedTranslation)>) {
+    for (transform, mut interpolated) in &mut robots {
+        interpolated.record(transform.translation);
+    }
+}
+
+fn interpolate_translation(
+    mut robots: Query<(&mut Transform, &mut InterpolatedTranslation)>,
+    time_fixed: Res<Time<Fixed>>,
+) {
+    let alpha = time_fixed.overstep_fraction();
+    for (mut transform, mut interpolated) in &mut robots {
+        interpolated.interpolate(&mut transform.translation, alpha);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_between_the_last_two_timesteps() {
+        let mut translation = Vec3::ZERO;
+        let mut interpolated = InterpolatedTranslation::new(translation);
+
+        interpolated.restore(&mut translation);
+        translation.x = 1.0;
+        interpolated.record(translation);
+
+        interpolated.interpolate(&mut translation, 0.25);
+        assert!((translation.x - 0.25).abs() < 1e-6);
+
+        interpolated.restore(&mut translation);
+        assert!((translation.x - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn does_not_interpolate_a_robot_moved_outside_the_fixed_schedules() {
+        let mut translation = Vec3::ZERO;
+        let mut interpolated = InterpolatedTranslation::new(translation);
+        interpolated.restore(&mut translation);
+        translation.x = 1.0;
+        interpolated.record(translation);
+        interpolated.interpolate(&mut translation, 0.5);
+
+        translation = Vec3::new(5.0, 0.0, 5.0);
+        interpolated.interpolate(&mut translation, 0.5);
+        assert_eq!(translation, Vec3::new(5.0, 0.0, 5.0));
+
+        interpolated.restore(&mut translation);
+        assert_eq!(translation, Vec3::new(5.0, 0.0, 5.0));
+    }
+}
@@ -0,0 +1,76 @@
+//! Events marking the moments in the life of a robot.
+//!
+//! They are the extension point for plugins that want to react to robots,
+//! e.g. to collect metrics, drive a script or play a sound, without touching
+//! the planner:
+//!
+//! ```ignore
+//! fn beep(mut evr_robot_reached_waypoint: EventReader<RobotReachedWaypoint>) {
+//!     for event in evr_robot_reached_waypoint.read() {
+//!         info!("robot {:?} reached waypoint {}", event.robot_id, event.waypoint_index);
+//!     }
+//! }
+//!
+//! app.add_systems(Update, beep);
+//! ```
+//!
+//! In order, a robot goes through:
+//! 1. [`RobotSpawned`], sent by the
+//!    [`RobotSpawner`](super::spawner::RobotSpawner)
+//! 2. [`RobotReachedWaypoint`], once for every waypoint of its mission
+//! 3. [`RobotFinishedRoute`], when the last waypoint is reached
+//! 4. [`RobotDespawned`], after the robot entity has been despawned. Either
+//!    because it finished and
+//!    `simulation.despawn-robot-when-final-waypoint-reached` is set, or because
+//!    the simulation was reloaded or a snapshot restored.
+//!
+//! The events are sent from different schedules, so readers should not
+//! assume they all arrive in the same frame.
+use bevy::prelude::*;
+
+use super::{RobotConnections, RobotId};
+
+pub struct RobotLifecyclePlugin;
+
+impl Plugin for RobotLifecyclePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RobotSpawned>()
+            .add_event::<RobotReachedWaypoint>()
+            .add_event::<RobotFinishedRoute>()
+            .add_event::<RobotDespawned>()
+            .add_systems(PostUpdate, send_robot_despawned);
+    }
+}
+
+/// Event emitted when a robot is spawned
+#[derive(Debug, Clone, Copy, Event)]
+pub struct RobotSpawned(pub RobotId);
+
+/// Event emitted when a robot reaches a waypoint
+#[derive(Debug, Clone, Copy, Event)]
+pub struct RobotReachedWaypoint {
+    pub robot_id:       RobotId,
+    /// Index of the reached waypoint in the active route of the robot
+    pub waypoint_index: usize,
+    /// Position of the reached waypoint
+    pub position:       Vec2,
+}
+
+/// Event emitted when a robot reached its final waypoint and finished its path
+#[derive(Debug, Clone, Copy, Event)]
+pub struct RobotFinishedRoute(pub RobotId);
+
+/// Event emitted when a robot is despawned
+#[derive(Debug, Clone, Copy, Event)]
+pub struct RobotDespawned(pub RobotId);
+
+/// Send [`RobotDespawned`] for every robot despawned since the last frame,
+/// regardless of which system despawned it
+fn send_robot_despawned(
+    mut removed_robots: RemovedComponents<RobotConnections>,
+    mut evw_robot_despawned: EventWriter<RobotDespawned>,
+) {
+    for robot_id in removed_robots.read() {
+        evw_robot_despawned.send(RobotDespawned(robot_id));
+    }
+}
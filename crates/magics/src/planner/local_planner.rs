@@ -0,0 +1,382 @@
+//! Pluggable local planners, to compare alternative planners on the same
+//! scenario.
+//!
+//! A [`LocalPlanner`] is a [`Component`] on a robot, deciding the next
+//! velocity of the robot from its own state, its neighbours and the
+//! environment. Adding a [`LocalPlannerPlugin<P>`] drives every robot with a
+//! `P` component with it, bypassing the GBP planner of the robot. The priors
+//! of the current and horizon variables of the factorgraph of the robot are
+//! set from the planned velocity, so robots planning with GBP still plan
+//! around it.
+//!
+//! GBP itself implements [`LocalPlanner`] for [`FactorGraph`], but is driven
+//! by the systems in [`super::robot`], as its iterations exchange messages
+//! between the factorgraphs of all robots at once.
+use std::{any::TypeId, collections::HashMap, marker::PhantomData, time::Duration};
+
+use bevy::prelude::*;
+use gbp_config::Config;
+use gbp_global_planner::Colliders;
+use gbp_linalg::prelude::*;
+use ndarray::array;
+
+use super::{
+    robot::{Mission, Radius},
+    RobotConnections, RobotId,
+};
+use crate::factorgraph::{factorgraph::FactorGraph, message::VariableToFactorMessage};
+
+/// **Bevy** [`SystemSet`]s of the systems added by [`LocalPlannerPlugin`].
+/// Ordered relative to the GBP systems in [`super::robot::RobotPlugin`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemSet)]
+pub enum LocalPlannerSet {
+    /// Decide which planner drives each robot
+    Mark,
+    /// Move the robots driven by a local planner
+    Drive,
+}
+
+/// The state of a robot, in the coordinates of the planner
+#[derive(Debug, Clone, Copy)]
+pub struct RobotState {
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub radius:   f32,
+}
+
+/// A robot within communication range of the planning robot
+#[derive(Debug, Clone, Copy)]
+pub struct Neighbour {
+    pub id:    RobotId,
+    pub state: RobotState,
+}
+
+/// Everything a [`LocalPlanner`] can base its plan on
+pub struct PlannerInput<'a> {
+    pub robot: RobotState,
+    /// The next waypoint of the mission of the robot, if it is not idle
+    pub goal: Option<Vec2>,
+    pub neighbours: &'a [Neighbour],
+    /// The obstacles of the environment, once they have been generated
+    pub colliders: Option<&'a Colliders>,
+    /// SI unit: m/s
+    pub target_speed: f32,
+    /// How far ahead the plan reaches. SI unit: s
+    pub horizon: f32,
+    /// Duration of the timestep being planned. SI unit: s
+    pub delta_t: f32,
+    /// Elapsed virtual time
+    pub now: Duration,
+}
+
+/// The output of a [`LocalPlanner`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalPlan {
+    /// Velocity of the robot for the next timestep
+    pub velocity: Vec2,
+    /// Expected position of the robot at the end of the horizon.
+    /// Extrapolated from `velocity` if `None`.
+    pub horizon:  Option<Vec2>,
+}
+
+impl LocalPlan {
+    /// A plan of moving with `velocity`
+    #[must_use]
+    pub const fn velocity(velocity: Vec2) -> Self {
+        Self {
+            velocity,
+            horizon: None,
+        }
+    }
+}
+
+/// A planner deciding the motion of a single robot
+pub trait LocalPlanner: Component {
+    /// Plan the next timestep of the robot
+    fn plan(&mut self, input: &PlannerInput) -> LocalPlan;
+}
+
+/// GBP is iterated by `iterate_gbp_v2` for all robots at once, so planning
+/// only reads out the current solution of the factorgraph
+impl LocalPlanner for FactorGraph {
+    fn plan(&mut self, _input: &PlannerInput) -> LocalPlan {
+        let (Some((_, current)), Some((_, horizon))) =
+            (self.first_variable(), self.last_variable())
+        else {
+            return LocalPlan::velocity(Vec2::ZERO);
+        };
+        let (current, horizon) = (&current.belief.mean, &horizon.belief.mean);
+
+        #[allow(clippy::cast_possible_truncation)]
+        LocalPlan {
+            velocity: Vec2::new(current[2] as f32, current[3] as f32),
+            horizon:  Some(Vec2::new(horizon[0] as f32, horizon[1] as f32)),
+        }
+    }
+}
+
+/// **Bevy** [`Component`] marking a robot as driven by a [`LocalPlanner`]
+/// other than GBP. Holds the type of the planner in control, in case a robot
+/// has more than one, e.g. while it is teleoperated.
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq)]
+pub struct ExternallyPlanned(TypeId);
+
+/// Drives the robots with a `P` component with `P`
+pub struct LocalPlannerPlugin<P>(PhantomData<P>);
+
+impl<P> Default for LocalPlannerPlugin<P> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<P: LocalPlanner> Plugin for LocalPlannerPlugin<P> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (
+                (unmark_externally_planned::<P>, mark_externally_planned::<P>)
+                    .chain()
+                    .in_set(LocalPlannerSet::Mark),
+                drive_robots::<P>.in_set(LocalPlannerSet::Drive),
+            ),
+        );
+    }
+}
+
+/// The planner added last takes control of a robot
+fn mark_externally_planned<P: LocalPlanner>(
+    mut commands: Commands,
+    query: Query<Entity, (With<P>, Or<(Added<P>, Without<ExternallyPlanned>)>)>,
+) {
+    for entity in &query {
+        commands
+            .entity(entity)
+            .insert(ExternallyPlanned(TypeId::of::<P>()));
+    }
+}
+
+/// Hand control back to GBP, or another planner of the robot, when `P` is
+/// removed
+fn unmark_externally_planned<P: LocalPlanner>(
+    mut commands: Commands,
+    mut removed: RemovedComponents<P>,
+    query: Query<&ExternallyPlanned>,
+) {
+    for entity in removed.read() {
+        // the robot may have been despawned
+        if query
+            .get(entity)
+            .is_ok_and(|marker| marker.0 == TypeId::of::<P>())
+        {
+            commands.entity(entity).remove::<ExternallyPlanned>();
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn drive_robots<P: LocalPlanner>(
+    mut queries: ParamSet<(
+        Query<(Entity, &Transform, &Radius, &FactorGraph)>,
+        Query<(
+            Entity,
+            &mut P,
+            &mut Transform,
+            &Radius,
+            &Mission,
+            &RobotConnections,
+            &ExternallyPlanned,
+        )>,
+        Query<&mut FactorGraph>,
+    )>,
+    colliders: Option<Res<Colliders>>,
+    config: Res<Config>,
+    time: Res<Time>,
+    time_virtual: Res<Time<Virtual>>,
+) {
+    let states: HashMap<RobotId, RobotState> = queries
+        .p0()
+        .iter()
+        .map(|(robot_id, transform, radius, factorgraph)| {
+            let velocity = factorgraph.first_variable().map_or(Vec2::ZERO, |(_, v)| {
+                #[allow(clippy::cast_possible_truncation)]
+                Vec2::new(v.belief.mean[2] as f32, v.belief.mean[3] as f32)
+            });
+            (robot_id, RobotState {
+                position: transform.translation.xz(),
+                velocity,
+                radius: radius.0,
+            })
+        })
+        .collect();
+
+    let horizon = config.robot.planning_horizon.get();
+    let delta_t = time.delta_seconds();
+
+    let mut plans = vec![];
+    let mut neighbours = vec![];
+    for (robot_id, mut planner, mut transform, radius, mission, connections, marker) in
+        &mut queries.p1()
+    {
+        if marker.0 != TypeId::of::<P>() {
+            continue;
+        }
+
+        neighbours.clear();
+        neighbours.extend(
+            connections
+                .robots_within_comms_range
+                .iter()
+                .filter_map(|id| states.get(id).map(|&state| Neighbour { id: *id, state })),
+        );
+
+        let robot = states.get(&robot_id).copied().unwrap_or(RobotState {
+            position: transform.translation.xz(),
+            velocity: Vec2::ZERO,
+            radius:   radius.0,
+        });
+        let input = PlannerInput {
+            robot,
+            goal: (!mission.state.idle())
+                .then(|| mission.next_waypoint().map(|waypoint| waypoint.position()))
+                .flatten(),
+            neighbours: &neighbours,
+            colliders: colliders.as_deref(),
+            target_speed: config.robot.target_speed.get(),
+            horizon,
+            delta_t,
+            now: time_virtual.elapsed(),
+        };
+        let plan = planner.plan(&input);
+
+        // bevy uses xzy coordinates, so the y component is put at the z coordinate
+        transform.translation.x += plan.velocity.x * delta_t;
+        transform.translation.z += plan.velocity.y * delta_t;
+        plans.push((robot_id, transform.translation.xz(), plan));
+    }
+
+    let mut q_factorgraphs = queries.p2();
+    let mut messages_to_external_factors = vec![];
+    for (robot_id, position, plan) in plans {
+        let Ok(mut factorgraph) = q_factorgraphs.get_mut(robot_id) else {
+            continue;
+        };
+        let horizon_position = plan
+            .horizon
+            .unwrap_or_else(|| position + plan.velocity * horizon);
+        messages_to_external_factors.extend(set_priors(
+            &mut factorgraph,
+            position,
+            plan.velocity,
+            horizon_position,
+        ));
+    }
+
+    // Send messages to external factors
+    for message in messages_to_external_factors {
+        let Ok(mut external_factorgraph) = q_factorgraphs.get_mut(message.to.factorgraph_id) else {
+            continue;
+        };
+        if let Some(factor) = external_factorgraph.get_factor_mut(message.to.factor_index) {
+            factor.receive_message_from(message.from, message.message);
+        }
+    }
+}
+
+/// Set the prior of the current variable to `position` and of the horizon
+/// variable to `horizon`, both moving with `velocity`.
+/// Returns the messages to send to the external factors of the two
+/// variables. The current variable only has external factors with
+/// [`IntentionSharing::CurrentState`](gbp_config::IntentionSharing).
+fn set_priors(
+    factorgraph: &mut FactorGraph,
+    position: Vec2,
+    velocity: Vec2,
+    horizon: Vec2,
+) -> Vec<VariableToFactorMessage> {
+    let state = |position: Vec2| {
+        array![
+            Float::from(position.x),
+            Float::from(position.y),
+            Float::from(velocity.x),
+            Float::from(velocity.y)
+        ]
+    };
+
+    let (current_variable_index, _) = factorgraph
+        .first_variable()
+        .expect("factorgraph should have a current variable");
+    let mut external_factor_messages =
+        factorgraph.change_prior_of_variable(current_variable_index, state(position));
+
+    let (horizon_variable_index, _) = factorgraph
+        .last_variable()
+        .expect("factorgraph should have a horizon variable");
+    external_factor_messages
+        .extend(factorgraph.change_prior_of_variable(horizon_variable_index, state(horizon)));
+    external_factor_messages
+}
+
+/// Moves straight towards the next waypoint at the target speed, ignoring
+/// obstacles and other robots. A baseline to compare other planners against.
+#[derive(Debug, Component, Default, Clone, Copy)]
+pub struct DirectPlanner;
+
+impl LocalPlanner for DirectPlanner {
+    fn plan(&mut self, input: &PlannerInput) -> LocalPlan {
+        let Some(goal) = input.goal else {
+            return LocalPlan::velocity(Vec2::ZERO);
+        };
+        let to_goal = goal - input.robot.position;
+        // do not overshoot the goal within a timestep
+        let speed = input
+            .target_speed
+            .min(to_goal.length() / input.delta_t.max(f32::EPSILON));
+
+        LocalPlan {
+            velocity: to_goal.normalize_or_zero() * speed,
+            horizon:  Some(goal),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(position: Vec2, goal: Option<Vec2>) -> PlannerInput<'static> {
+        PlannerInput {
+            robot: RobotState {
+                position,
+                velocity: Vec2::ZERO,
+                radius: 1.0,
+            },
+            goal,
+            neighbours: &[],
+            colliders: None,
+            target_speed: 2.0,
+            horizon: 5.0,
+            delta_t: 0.1,
+            now: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn direct_planner_moves_towards_goal_at_target_speed() {
+        let plan = DirectPlanner.plan(&input(Vec2::ZERO, Some(Vec2::new(10.0, 0.0))));
+        assert_eq!(plan.velocity, Vec2::new(2.0, 0.0));
+        assert_eq!(plan.horizon, Some(Vec2::new(10.0, 0.0)));
+    }
+
+    #[test]
+    fn direct_planner_does_not_overshoot() {
+        let plan = DirectPlanner.plan(&input(Vec2::ZERO, Some(Vec2::new(0.0, 0.1))));
+        assert!((plan.velocity - Vec2::new(0.0, 1.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn direct_planner_stops_without_goal() {
+        let plan = DirectPlanner.plan(&input(Vec2::ONE, None));
+        assert_eq!(plan.velocity, Vec2::ZERO);
+    }
+}
@@ -0,0 +1,128 @@
+//! Noisy state estimates, to evaluate the planner without perfect ground
+//! truth.
+//!
+//! With any of the `robot.localization` standard deviations above zero, every
+//! robot gets a [`LocalizationError`]. Each fixed timestep the current
+//! variable of the robot is anchored to its true state plus Gaussian noise,
+//! offset by a bias that drifts as a random walk. The robot itself still moves
+//! by the change in its belief, so the estimate and the true pose diverge.
+use std::ops::DerefMut;
+
+use bevy::prelude::*;
+use bevy_prng::WyRand;
+use bevy_rand::prelude::GlobalEntropy;
+use gbp_config::{Config, LocalizationSection};
+use rand::Rng;
+use rand_distr::{Distribution, Normal};
+
+use super::RobotConnections;
+use crate::bevy_utils::run_conditions::time::virtual_time_is_paused;
+
+pub struct LocalizationPlugin;
+
+impl Plugin for LocalizationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (attach_localization_error, drift_bias)
+                .chain()
+                .before(super::robot::update_prior_of_current_state_v3)
+                .run_if(not(virtual_time_is_paused)),
+        );
+    }
+}
+
+/// **Bevy** [`Component`] with the error of the state estimate of a robot
+#[derive(Debug, Default, Component)]
+pub struct LocalizationError {
+    /// Offset of the estimated position from the true position. SI unit: m
+    pub bias: Vec2,
+}
+
+impl LocalizationError {
+    /// Noisy estimate of the true `state` [x, y, x', y']
+    #[must_use]
+    pub fn estimate(&self, state: Vec4, config: &LocalizationSection, rng: &mut impl Rng) -> Vec4 {
+        let position = Vec2::new(
+            gaussian(config.position, rng),
+            gaussian(config.position, rng),
+        );
+        let velocity = Vec2::new(
+            gaussian(config.velocity, rng),
+            gaussian(config.velocity, rng),
+        );
+        state
+            + (position + self.bias).extend(0.0).extend(0.0)
+            + Vec4::new(0.0, 0.0, velocity.x, velocity.y)
+    }
+}
+
+/// Sample from a zero mean normal distribution, with `std_dev` <= 0 meaning
+/// no noise
+fn gaussian(std_dev: f32, rng: &mut impl Rng) -> f32 {
+    Normal::new(0.0, std_dev)
+        .ok()
+        .filter(|_| std_dev > 0.0)
+        .map_or(0.0, |normal| normal.sample(rng))
+}
+
+fn attach_localization_error(
+    mut commands: Commands,
+    robots: Query<Entity, Added<RobotConnections>>,
+    config: Res<Config>,
+) {
+    if !config.robot.localization.is_enabled() {
+        return;
+    }
+
+    for robot in &robots {
+        commands.entity(robot).insert(LocalizationError::default());
+    }
+}
+
+/// Random walk of the bias, scaled so the variance grows linearly with time
+fn drift_bias(
+    mut robots: Query<&mut LocalizationError>,
+    config: Res<Config>,
+    time: Res<Time>,
+    mut prng: ResMut<GlobalEntropy<WyRand>>,
+) {
+    let std_dev = config.robot.localization.bias_drift * time.delta_seconds().sqrt();
+    if std_dev <= 0.0 {
+        return;
+    }
+
+    for mut error in &mut robots {
+        let step = Vec2::new(
+            gaussian(std_dev, prng.deref_mut()),
+            gaussian(std_dev, prng.deref_mut()),
+        );
+        error.bias += step;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn no_noise_is_ground_truth() {
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let state = Vec4::new(1.0, 2.0, 3.0, 4.0);
+        let estimate =
+            LocalizationError::default().estimate(state, &LocalizationSection::default(), &mut rng);
+        assert_eq!(estimate, state);
+    }
+
+    #[test]
+    fn bias_offsets_the_position_only() {
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let error = LocalizationError {
+            bias: Vec2::new(0.5, -0.5),
+        };
+        let estimate = error.estimate(Vec4::ZERO, &LocalizationSection::default(), &mut rng);
+        assert_eq!(estimate, Vec4::new(0.5, -0.5, 0.0, 0.0));
+    }
+}
@@ -1,8 +1,21 @@
+pub mod battery;
+pub mod click_spawn;
 pub mod collisions;
+pub mod goal;
+pub mod interpolation;
+pub mod lifecycle;
+pub mod local_planner;
+pub mod localization;
 pub mod mission;
 pub mod robot;
+pub mod sdf_layers;
+pub mod sensing;
+mod solver;
+pub mod spawn_validation;
 pub mod spawner;
+pub mod teleoperation;
 pub mod tracking;
+pub mod traffic_light;
 mod visualiser;
 
 use bevy::prelude::*;
@@ -18,12 +31,27 @@ pub struct PlannerPlugin;
 impl Plugin for PlannerPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((
+            lifecycle::RobotLifecyclePlugin,
             RobotPlugin,
             RobotSpawnerPlugin,
             VisualiserPlugin,
             collisions::RobotCollisionsPlugin,
             tracking::TrackingPlugin,
             mission::MissionPlugin,
+            goal::GoalDragPlugin,
+            click_spawn::ClickToSpawnPlugin,
+            solver::AsyncSolverPlugin,
+            teleoperation::TeleoperationPlugin,
+            battery::BatteryPlugin,
+            traffic_light::TrafficLightPlugin,
+            local_planner::LocalPlannerPlugin::<local_planner::DirectPlanner>::default(),
+        ))
+        .add_plugins((
+            localization::LocalizationPlugin,
+            sensing::SensingPlugin,
+            sdf_layers::SdfLayersPlugin,
+            interpolation::InterpolationPlugin,
+            spawn_validation::SpawnValidationPlugin,
         ));
     }
 }
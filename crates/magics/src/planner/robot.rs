@@ -1,20 +1,17 @@
 use std::{
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     num::NonZeroUsize,
+    ops::DerefMut,
     sync::{Arc, Mutex},
     time::Duration,
 };
 
-use bevy::{
-    input::{keyboard::KeyboardInput, ButtonState},
-    prelude::*,
-    tasks::futures_lite::future,
-};
+use bevy::{prelude::*, tasks::futures_lite::future};
 use bevy_prng::WyRand;
 use bevy_rand::{component::EntropyComponent, prelude::GlobalEntropy};
 use gbp_config::{
     formation::{CheckIntersectionWith, IntersectionDistance, PlanningStrategy, ReachedWhen},
-    Config,
+    Config, IntentionSharing,
 };
 use gbp_global_planner::PathfindingTask;
 use gbp_linalg::prelude::*;
@@ -22,23 +19,35 @@ use itertools::Itertools;
 use ndarray::{array, concatenate, s, Axis};
 use rand::Rng;
 
+pub use super::lifecycle::{
+    RobotDespawned, RobotFinishedRoute, RobotReachedWaypoint, RobotSpawned,
+};
 use super::{
     collisions::resources::{RobotEnvironmentCollisions, RobotRobotCollisions},
+    local_planner::{ExternallyPlanned, LocalPlannerSet},
+    localization::LocalizationError,
+    solver,
     spawner::RobotClickedOn,
 };
 use crate::{
     bevy_utils::run_conditions::time::virtual_time_is_paused,
     export::events::TakeSnapshotOfRobot,
     factorgraph::{
-        factor::{ExternalVariableId, FactorNode},
+        batch::ObstacleLookups,
+        factor::{
+            obstacle::{ObstacleSource, WorldSize},
+            region::CostPolygon,
+            ExternalVariableId, FactorNode,
+        },
         factorgraph::{FactorGraph, NodeIndex, VariableIndex},
         id::{FactorId, VariableId},
         message::{FactorToVariableMessage, VariableToFactorMessage},
+        settings::GbpSettings,
         variable::VariableNode,
         DOFS,
     },
-    pause_play::PausePlay,
-    simulation_loader::{LoadSimulation, ReloadSimulation, SdfImage},
+    pause_play::{PausePlay, StepSimulation},
+    simulation_loader::{LoadSimulation, ReloadSimulation, Sdf},
 };
 
 pub type RobotId = Entity;
@@ -52,12 +61,23 @@ impl Plugin for RobotPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<GbpIterationSchedule>()
             .init_resource::<RobotNumberGenerator>()
+            .init_resource::<BatchedObstacleLookups>()
             .insert_state(ManualModeState::Disabled)
-            .add_event::<RobotSpawned>()
-            .add_event::<RobotDespawned>()
-            .add_event::<RobotFinishedRoute>()
-            .add_event::<RobotReachedWaypoint>()
             .add_event::<GbpScheduleChanged>()
+            .add_event::<PrecisionIllConditioned>()
+            .add_event::<MessagesDropped>()
+            .register_type::<DroppedMessages>()
+            .configure_sets(
+                FixedUpdate,
+                (
+                    LocalPlannerSet::Mark.before(update_robot_neighbours),
+                    LocalPlannerSet::Drive
+                        .after(update_prior_of_current_state_v3)
+                        .before(iterate_gbp_v2)
+                        .before(solver::apply_finished_solve),
+                )
+                    .run_if(not(virtual_time_is_paused)),
+            )
             .add_systems(PreUpdate, start_manual_step.run_if(virtual_time_is_paused))
             .add_systems(
                 Update,
@@ -72,6 +92,9 @@ impl Plugin for RobotPlugin {
                     attach_despawn_timer_when_robot_finishes_route,
                     request_snapshot_of_robot_when_it_finishes_its_route,
                     progress_missions.run_if(resource_exists::<gbp_global_planner::Colliders>),
+                    update_gbp_settings.run_if(resource_changed::<Config>),
+                    update_batched_obstacle_lookups
+                        .run_if(resource_changed::<Config>.or_else(resource_changed::<Sdf>)),
                 ),
             )
             .add_systems(
@@ -98,9 +121,13 @@ impl Plugin for RobotPlugin {
                     // update_prior_of_horizon_state_v2,
                     update_prior_of_horizon_state,
                     update_prior_of_current_state_v3,
-                    iterate_gbp_v2,
+                    iterate_gbp_v2.run_if(not(solver::solve_asynchronously)),
+                    (solver::apply_finished_solve, solver::spawn_solve)
+                        .chain()
+                        .run_if(solver::solve_asynchronously),
                     // update_prior_of_current_state,
                     // despawn_robots,
+                    monitor_precision_conditioning,
                     finish_manual_step.run_if(ManualModeState::enabled),
                 )
                     .chain()
@@ -109,6 +136,15 @@ impl Plugin for RobotPlugin {
     }
 }
 
+/// Use the [`GbpSettings`] of the config for the factorgraphs of all robots,
+/// e.g. after they have been changed in the settings
+fn update_gbp_settings(mut factorgraphs: Query<&mut FactorGraph>, config: Res<Config>) {
+    let settings = GbpSettings::from(&config.gbp);
+    for mut factorgraph in &mut factorgraphs {
+        factorgraph.apply_settings(&settings);
+    }
+}
+
 fn request_snapshot_of_robot_when_it_finishes_its_route(
     mut evr_robot_finished_route: EventReader<RobotFinishedRoute>,
     mut evw_take_snapshot_of_robot: EventWriter<TakeSnapshotOfRobot>,
@@ -152,18 +188,6 @@ impl From<gbp_config::GbpIterationSchedule> for GbpScheduleChanged {
     }
 }
 
-/// Event emitted when a robot is spawned
-#[derive(Debug, Event)]
-pub struct RobotSpawned(pub RobotId);
-
-/// Event emitted when a robot is despawned
-#[derive(Debug, Event)]
-pub struct RobotDespawned(pub RobotId);
-
-/// Event emitted when a robot reached its final waypoint and finished its path
-#[derive(Debug, Event)]
-pub struct RobotFinishedRoute(pub RobotId);
-
 fn attach_despawn_timer_when_robot_finishes_route(
     mut commands: Commands,
     mut evr_robot_finished_route: EventReader<RobotFinishedRoute>,
@@ -187,11 +211,68 @@ fn attach_despawn_timer_when_robot_finishes_route(
     }
 }
 
-/// Event emitted when a robot reaches a waypoint
-#[derive(Event)]
-pub struct RobotReachedWaypoint {
-    pub robot_id:       RobotId,
-    pub waypoint_index: usize,
+/// Event emitted when the precision matrix of a variable approaches
+/// singularity, i.e. its condition number exceeds
+/// `gbp.conditioning.max-condition-number`
+#[derive(Debug, Event)]
+pub struct PrecisionIllConditioned {
+    pub robot_id: RobotId,
+    pub variable_index: VariableIndex,
+    pub condition_number: Float,
+}
+
+/// Event emitted when the GBP messages of an external iteration between two
+/// connected robots are dropped, because the antenna of either has failed
+#[derive(Debug, Clone, Copy, Event)]
+pub struct MessagesDropped {
+    pub from: RobotId,
+    pub to:   RobotId,
+}
+
+/// Component counting the external iterations in which the messages from a
+/// connected robot to this robot were dropped
+#[derive(Component, Debug, Default, Reflect)]
+#[reflect(Component)]
+pub struct DroppedMessages(pub usize);
+
+/// Report variables whose precision matrix has become ill-conditioned.
+/// Only the tick a variable crosses into the ill-conditioned state is
+/// reported, so a degenerate robot does not flood the log and the event queue.
+/// Variables that have recovered, or whose robot has been despawned, are
+/// forgotten, and are reported again if they become ill-conditioned later.
+fn monitor_precision_conditioning(
+    query: Query<(Entity, &FactorGraph)>,
+    config: Res<Config>,
+    mut ill_conditioned: Local<HashSet<(RobotId, VariableIndex)>>,
+    mut evw_precision_ill_conditioned: EventWriter<PrecisionIllConditioned>,
+) {
+    let max_condition_number = Float::from(config.gbp.conditioning.max_condition_number);
+    let mut still_ill_conditioned = HashSet::with_capacity(ill_conditioned.len());
+    for (robot_id, factorgraph) in &query {
+        for (variable_index, variable) in factorgraph.variables() {
+            let condition_number = variable.belief.condition_number;
+            if condition_number.is_finite() && condition_number <= max_condition_number {
+                continue;
+            }
+
+            still_ill_conditioned.insert((robot_id, variable_index));
+            if ill_conditioned.contains(&(robot_id, variable_index)) {
+                continue;
+            }
+
+            warn!(
+                "precision matrix of variable {:?} of robot {:?} is close to singular, condition \
+                 number: {:e}",
+                variable_index, robot_id, condition_number
+            );
+            evw_precision_ill_conditioned.send(PrecisionIllConditioned {
+                robot_id,
+                variable_index,
+                condition_number,
+            });
+        }
+    }
+    *ill_conditioned = still_ill_conditioned;
 }
 
 // fn despawn_robots(
@@ -323,6 +404,67 @@ impl CreateVariableTimesteps for GbpplannerVariableTimesteps {
 #[derive(Component, Debug, Deref, DerefMut)]
 pub struct Radius(pub f32);
 
+/// Component scaling the target speed of a robot, drawn from
+/// `config.robot.target_speed_noise` when the robot is spawned
+#[derive(Component, Debug, Clone, Copy, Deref, DerefMut)]
+pub struct SpeedFactor(pub f32);
+
+impl Default for SpeedFactor {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// Component with the sigmas of a single robot that override the ones in
+/// `config.gbp`, set from the robot inspector. `None` uses the value from the
+/// config
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq)]
+pub struct SigmaOverrides {
+    pub dynamic:    Option<f32>,
+    pub interrobot: Option<f32>,
+    pub obstacle:   Option<f32>,
+    pub tracking:   Option<f32>,
+    pub region:     Option<f32>,
+}
+
+impl SigmaOverrides {
+    /// Whether no sigma is overridden
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.dynamic.is_none()
+            && self.interrobot.is_none()
+            && self.obstacle.is_none()
+            && self.tracking.is_none()
+            && self.region.is_none()
+    }
+}
+
+/// Component with the class of a robot, e.g. "agv" or "forklift", set by the
+/// formation it was spawned from. Used to scale the sigma of the interrobot
+/// factors between specific classes, see
+/// [`gbp_config::GbpSection::interrobot_sigma_scale`]
+#[derive(Component, Debug, Clone, PartialEq, Eq, Deref)]
+pub struct RobotClass(pub String);
+
+/// Component with the priority of a robot, set by the formation it was
+/// spawned from. Robots yield to robots with a higher priority
+#[derive(Component, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deref)]
+pub struct Priority(pub u8);
+
+impl Priority {
+    /// Sigma of the interrobot factors of this robot towards `other`, given
+    /// the sigma between robots of equal priority. Larger sigmas make for
+    /// weaker factors, so the robot with the lower priority avoids the most
+    #[must_use]
+    pub fn interrobot_sigma(self, other: Self, sigma: Float, ratio: Float) -> Float {
+        match self.cmp(&other) {
+            std::cmp::Ordering::Less => sigma / ratio,
+            std::cmp::Ordering::Equal => sigma,
+            std::cmp::Ordering::Greater => sigma * ratio,
+        }
+    }
+}
+
 /// Represents a robotic route consisting of several waypoints that define
 /// positions and velocities the robot should achieve as it progresses along the
 /// path.
@@ -487,15 +629,30 @@ impl Route {
 #[derive(Component, Debug)]
 pub struct RadioAntenna {
     /// The radius that the radio antenna can cover
-    pub radius: f32,
+    pub radius:  f32,
     /// Whether the antenna is currently active
-    pub active: bool,
+    pub active:  bool,
+    /// State of the channel, when failures are modelled with a
+    /// Gilbert–Elliott channel
+    pub channel: ChannelState,
+}
+
+/// State of the channel of a [`RadioAntenna`] in the Gilbert–Elliott model
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChannelState {
+    #[default]
+    Good,
+    Bad,
 }
 
 impl RadioAntenna {
     /// Creates a new radio antenna.
     pub fn new(radius: f32, active: bool) -> Self {
-        Self { radius, active }
+        Self {
+            radius,
+            active,
+            channel: ChannelState::default(),
+        }
     }
 
     /// Toggle the state of the antenna between on and off
@@ -532,6 +689,27 @@ impl RobotConnections {
     }
 }
 
+/// Component recording when a GBP message from each other robot was last
+/// delivered to this robot, as elapsed fixed time
+#[derive(Component, Debug, Default, Deref)]
+pub struct LastDelivered(BTreeMap<RobotId, Duration>);
+
+impl LastDelivered {
+    /// Time since a message from `robot` was last delivered, or `None` if no
+    /// message from `robot` has been delivered
+    #[must_use]
+    pub fn age(&self, robot: RobotId, now: Duration) -> Option<Duration> {
+        self.0
+            .get(&robot)
+            .map(|&delivered| now.saturating_sub(delivered))
+    }
+
+    /// Record that a message from `robot` was delivered at `now`
+    pub fn delivered(&mut self, robot: RobotId, now: Duration) {
+        self.0.insert(robot, now);
+    }
+}
+
 // TODO: change to collider
 #[derive(Debug, Component, Deref)]
 pub struct Ball(parry2d::shape::Ball);
@@ -1008,6 +1186,79 @@ impl Mission {
     pub fn waypoints(&self) -> impl Iterator<Item = &StateVector> + '_ {
         self.routes.iter().flat_map(|r| r.waypoints())
     }
+
+    /// Replace the remaining waypoints with a single route from `from` to
+    /// `goal`, and make the mission active again, even if it was completed
+    pub fn retarget(&mut self, from: StateVector, goal: StateVector, time: &Time) {
+        let waypoints = min_len_vec::TwoOrMore::new(vec![from, goal])
+            .expect("a route from `from` to `goal` has two waypoints");
+        self.routes = vec![Route::new(waypoints, time.elapsed_seconds_f64())];
+        self.taskpoints = vec![from, goal];
+        self.active_route = 0;
+        self.finished_at = None;
+        self.state = MissionState::Active;
+    }
+
+    /// Insert `via` as the next waypoint of the active route, with the route
+    /// continuing from `from` i.e. the current position of the robot. Does
+    /// nothing if the mission is completed
+    pub fn detour(&mut self, from: StateVector, via: StateVector, time: &Time) {
+        let Some(route) = self.active_route() else {
+            return;
+        };
+        let Some(index) = route.current_waypoint_index() else {
+            return;
+        };
+
+        let waypoints = [from, via]
+            .into_iter()
+            .chain(route.waypoints()[index..].iter().copied())
+            .collect_vec();
+        let route = Route::new(
+            waypoints
+                .try_into()
+                .expect("a detour has at least two waypoints"),
+            time.elapsed_seconds_f64(),
+        );
+
+        // the active route starts at `from`, and the taskpoints already
+        // reached are dropped
+        self.taskpoints = std::iter::once(from)
+            .chain(self.taskpoints.iter().skip(self.active_route + 1).copied())
+            .collect();
+        self.routes = vec![route];
+        self.active_route = 0;
+        self.state = MissionState::Active;
+    }
+
+    /// Waypoints not yet reached, i.e. the rest of the active route followed
+    /// by the remaining taskpoints
+    pub fn remaining_waypoints(&self) -> Vec<StateVector> {
+        let Some(route) = self.active_route() else {
+            return Vec::new();
+        };
+        let Some(index) = route.current_waypoint_index() else {
+            return Vec::new();
+        };
+
+        route.waypoints()[index..]
+            .iter()
+            .chain(self.taskpoints.iter().skip(self.active_route + 2))
+            .copied()
+            .collect()
+    }
+
+    /// Criteria for when the mission is considered finished
+    #[inline]
+    pub const fn finished_when_intersects(&self) -> ReachedWhen {
+        self.finished_when_intersects
+    }
+
+    /// Criteria for when a taskpoint is considered reached
+    #[inline]
+    pub const fn taskpoint_reached_when_intersects(&self) -> ReachedWhen {
+        self.taskpoint_reached_when_intersects
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -1066,6 +1317,10 @@ pub struct RobotBundle {
     pub planning_strategy: PlanningStrategy,
 
     pub variable_timesteps: VariableTimesteps,
+
+    pub last_delivered: LastDelivered,
+
+    pub dropped_messages: DroppedMessages,
 }
 
 /// State vector of a robot
@@ -1141,7 +1396,7 @@ impl RobotBundle {
         config: &Config,
         env_config: &gbp_environment::Environment,
         radius: f32,
-        sdf: &SdfImage,
+        obstacles: &ObstacleSource,
         started_at: f64,
         waypoints: min_len_vec::TwoOrMore<StateVector>,
         // use_tracking: bool,
@@ -1168,7 +1423,9 @@ impl RobotBundle {
                 (config.robot.planning_horizon * config.robot.target_speed).get(),
             ) * start2goal.normalize();
 
-        let mut factorgraph = FactorGraph::new(robot_id);
+        let mut factorgraph = FactorGraph::builder(robot_id)
+            .settings(GbpSettings::from(&config.gbp))
+            .build();
         let last_variable_timestep = *variable_timesteps
             .last()
             .expect("Know that variable_timesteps has at least one element");
@@ -1256,11 +1513,10 @@ impl RobotBundle {
 
         // Create Obstacle factors for all variables excluding start,
         // excluding horizon
-        let tile_size = env_config.tiles.settings.tile_size as f64;
-        let (nrows, ncols) = env_config.tiles.grid.shape();
-        let world_size = crate::factorgraph::factor::obstacle::WorldSize {
-            width:  tile_size * ncols as f64,
-            height: tile_size * nrows as f64,
+        let (width, height) = env_config.dimensions();
+        let world_size = WorldSize {
+            width:  f64::from(width),
+            height: f64::from(height),
         };
 
         // Create Obstacle factors for all variables excluding start and
@@ -1271,7 +1527,7 @@ impl RobotBundle {
                 factorgraph.id(),
                 Float::from(config.gbp.sigma_factor_obstacle),
                 array![0.0],
-                sdf.clone(),
+                obstacles.clone(),
                 world_size,
                 config.gbp.factors_enabled.obstacle,
             );
@@ -1284,6 +1540,41 @@ impl RobotBundle {
             );
         }
 
+        // Create Region factors for the same variables as the obstacle factors,
+        // if the environment has any cost regions
+        if !env_config.regions.is_empty() {
+            let regions: Arc<[CostPolygon]> = env_config
+                .regions
+                .iter()
+                .map(|region| CostPolygon {
+                    vertices: region
+                        .points
+                        .iter()
+                        .map(|point| env_config.centered(*point))
+                        .collect(),
+                    cost:     region.cost,
+                    falloff:  region.falloff,
+                })
+                .collect();
+
+            for i in 1..variable_timesteps.len() - 1 {
+                let region_factor = FactorNode::new_region_factor(
+                    factorgraph.id(),
+                    Float::from(config.gbp.sigma_factor_region),
+                    array![0.0],
+                    Arc::clone(&regions),
+                    config.gbp.factors_enabled.region,
+                );
+
+                let factor_node_index = factorgraph.add_factor(region_factor);
+                let factor_id = FactorId::new(factorgraph.id(), factor_node_index);
+                let _ = factorgraph.add_internal_edge(
+                    VariableId::new(factorgraph.id(), variable_node_indices[i]),
+                    factor_id,
+                );
+            }
+        }
+
         let mission = match planning_strategy {
             PlanningStrategy::OnlyLocal => Mission::local(
                 waypoints.try_into().unwrap(),
@@ -1334,6 +1625,8 @@ impl RobotBundle {
         }
         // }
 
+        factorgraph.debug_assert_consistent("construction");
+
         Self {
             factorgraph,
             radius: Radius(radius),
@@ -1351,6 +1644,8 @@ impl RobotBundle {
             // intersects_when,
             planning_strategy,
             variable_timesteps: VariableTimesteps(variable_timesteps.to_owned()),
+            last_delivered: LastDelivered::default(),
+            dropped_messages: DroppedMessages::default(),
         }
     }
 }
@@ -1360,29 +1655,103 @@ pub struct VariableTimesteps(Vec<u32>);
 
 /// Called `Simulator::calculateRobotNeighbours` in **gbpplanner**
 fn update_robot_neighbours(
-    robots: Query<(Entity, &Transform), With<RobotConnections>>,
+    robots: Query<(Entity, &Transform, &FactorGraph, &Radius), With<RobotConnections>>,
     mut query: Query<(Entity, &Transform, &mut RobotConnections)>,
+    colliders: Option<Res<gbp_global_planner::Colliders>>,
     config: Res<Config>,
 ) {
+    // predicted positions of the variables of every robot, ordered by timestep
+    let predictions: HashMap<Entity, (Vec<Vec2>, f32)> =
+        if config.robot.communication.predicted_proximity {
+            robots
+                .iter()
+                .map(|(entity, _, factorgraph, radius)| {
+                    #[allow(clippy::cast_possible_truncation)]
+                    let positions = factorgraph
+                        .variables()
+                        .map(|(_, variable)| {
+                            Vec2::new(
+                                variable.belief.mean[0] as f32,
+                                variable.belief.mean[1] as f32,
+                            )
+                        })
+                        .collect();
+                    (entity, (positions, radius.0))
+                })
+                .collect()
+        } else {
+            HashMap::new()
+        };
+    let multiplier = config.robot.inter_robot_safety_distance_multiplier.get();
+    let occluders = colliders
+        .as_deref()
+        .filter(|_| config.robot.communication.line_of_sight);
+
     // TODO: use kdtree to speed up, and to have something in the report
     for (robot_id, transform, mut robotstate) in &mut query {
         robotstate.robots_within_comms_range = robots
             .iter()
-            .filter_map(|(other_robot_id, other_transform)| {
-                if other_robot_id == robot_id
-                    || config.robot.communication.radius.get()
-                        < transform.translation.distance(other_transform.translation)
-                {
+            .filter_map(|(other_robot_id, other_transform, _, _)| {
+                if other_robot_id == robot_id {
                     // Do not compute the distance to self
-                    None
-                } else {
-                    Some(other_robot_id)
+                    return None;
                 }
+                let within_comms_range =
+                    transform.translation.distance(other_transform.translation)
+                        <= config.robot.communication.radius.get();
+                let predicted_to_meet = || {
+                    let (Some((positions, radius)), Some((other_positions, other_radius))) =
+                        (predictions.get(&robot_id), predictions.get(&other_robot_id))
+                    else {
+                        return false;
+                    };
+                    predicted_within(
+                        positions,
+                        other_positions,
+                        multiplier * radius.max(*other_radius),
+                    )
+                };
+
+                let in_line_of_sight = || {
+                    occluders.map_or(true, |colliders| {
+                        !line_of_sight_blocked(
+                            transform.translation.xz(),
+                            other_transform.translation.xz(),
+                            colliders,
+                        )
+                    })
+                };
+
+                ((within_comms_range || predicted_to_meet()) && in_line_of_sight())
+                    .then_some(other_robot_id)
             })
             .collect();
     }
 }
 
+/// Whether the straight line from `from` to `to` passes through any of the
+/// `colliders`
+fn line_of_sight_blocked(from: Vec2, to: Vec2, colliders: &gbp_global_planner::Colliders) -> bool {
+    use parry2d::query::RayCast;
+
+    let ray = parry2d::query::Ray::new(
+        parry2d::na::Point2::new(from.x, from.y),
+        parry2d::na::Vector2::new(to.x - from.x, to.y - from.y),
+    );
+    // the direction is not normalised, so the segment ends at a time of 1.0
+    colliders
+        .iter()
+        .any(|collider| collider.shape.intersects_ray(&collider.isometry, &ray, 1.0))
+}
+
+/// Whether any pair of positions at the same index of `a` and `b` are closer
+/// than `distance`
+fn predicted_within(a: &[Vec2], b: &[Vec2], distance: f32) -> bool {
+    a.iter()
+        .zip(b)
+        .any(|(a, b)| a.distance_squared(*b) < distance * distance)
+}
+
 fn delete_interrobot_factors(mut query: Query<(Entity, &mut FactorGraph, &mut RobotConnections)>) {
     // the set of robots connected with will (possibly) be mutated
     // the robots factorgraph will (possibly) be mutated
@@ -1439,7 +1808,15 @@ fn delete_interrobot_factors(mut query: Query<(Entity, &mut FactorGraph, &mut Ro
 }
 
 fn create_interrobot_factors(
-    mut query: Query<(Entity, &mut FactorGraph, &mut RobotConnections, &Radius)>,
+    mut query: Query<(
+        Entity,
+        &mut FactorGraph,
+        &mut RobotConnections,
+        &Radius,
+        Option<&Priority>,
+        Option<&SigmaOverrides>,
+        Option<&RobotClass>,
+    )>,
     config: Res<Config>,
     mut robot_number_gen: ResMut<RobotNumberGenerator>,
 ) {
@@ -1448,7 +1825,7 @@ fn create_interrobot_factors(
     // {a -> [b, c, d], b -> [a, c], c -> [a, b], d -> [c]}
     let new_connections_to_establish: HashMap<RobotId, Vec<RobotId>> = query
         .iter()
-        .map(|(entity, _, robotstate, _)| {
+        .map(|(entity, _, robotstate, _, _, _, _)| {
             let new_connections = robotstate
                 .robots_within_comms_range
                 .difference(&robotstate.robots_connected_with)
@@ -1464,14 +1841,13 @@ fn create_interrobot_factors(
     // PERF(kpbaks): store a slice instead of a Vec<NodeIndex>
     let variable_indices_of_each_factorgraph: HashMap<RobotId, Vec<NodeIndex>> = query
         .iter()
-        .map(|(robot_id, factorgraph, _, _)| {
+        .map(|(robot_id, factorgraph, _, _, _, _, _)| {
             let variable_indices = factorgraph
                 .variable_indices_ordered_by_creation()
-                .skip(1) // skip current variable
                 .collect::<Vec<_>>();
 
             let num_variables = factorgraph.node_count().variables;
-            debug_assert_eq!(num_variables - 1, variable_indices.len());
+            debug_assert_eq!(num_variables, variable_indices.len());
             (robot_id, variable_indices)
         })
         .collect();
@@ -1485,10 +1861,25 @@ fn create_interrobot_factors(
     // }
     // debug_assert!(variable_indices_of_each_factorgraph.values().all_equal());
 
+    let priorities: HashMap<RobotId, Priority> = query
+        .iter()
+        .map(|(robot_id, _, _, _, priority, _, _)| {
+            (robot_id, priority.copied().unwrap_or_default())
+        })
+        .collect();
+
+    let classes: HashMap<RobotId, Option<String>> = query
+        .iter()
+        .map(|(robot_id, _, _, _, _, _, class)| (robot_id, class.map(|class| class.0.clone())))
+        .collect();
+
     let mut external_edges_to_add = Vec::new();
 
-    for (robot_id, mut factorgraph, mut robotstate, radius) in &mut query {
+    for (robot_id, mut factorgraph, mut robotstate, radius, _, overrides, _) in &mut query {
         let num_variables = factorgraph.node_count().variables;
+        let sigma_factor_interrobot = overrides
+            .and_then(|overrides| overrides.interrobot)
+            .unwrap_or(config.gbp.sigma_factor_interrobot);
         for other_robot_id in new_connections_to_establish
             .get(&robot_id)
             .expect("the key is in the map")
@@ -1496,6 +1887,15 @@ fn create_interrobot_factors(
             let other_variable_indices = variable_indices_of_each_factorgraph
                 .get(other_robot_id)
                 .expect("the key is in the map");
+            let class_scale = config.gbp.interrobot_sigma_scale(
+                classes[&robot_id].as_deref(),
+                classes[other_robot_id].as_deref(),
+            );
+            let sigma = priorities[&robot_id].interrobot_sigma(
+                priorities[other_robot_id],
+                Float::from(sigma_factor_interrobot * class_scale),
+                Float::from(config.gbp.priority_sigma_ratio.max(1.0)),
+            );
 
             for i in 1..num_variables {
                 let initial_measurement = Vector::<Float>::zeros(DOFS);
@@ -1503,10 +1903,14 @@ fn create_interrobot_factors(
                 // let eps = 0.2 * radius.0;
                 // let safety_radius = 2.0f32.mul_add(config.robot.radius.get(), eps);
                 // let safety_radius = 2.0f32.mul_add(radius.0, eps);
-                // TODO: should it be i - 1 or i?
+                // Which variable of the other robot to connect to
+                let other_i = match config.gbp.intention_sharing {
+                    IntentionSharing::Horizon => i,
+                    IntentionSharing::CurrentState => 0,
+                };
                 let external_variable_id = ExternalVariableId::new(
                     *other_robot_id,
-                    VariableIndex(other_variable_indices[i - 1]),
+                    VariableIndex(other_variable_indices[other_i]),
                 );
                 // let connection =
                 //     InterRobotFactorConnection::new(*other_robot_id, other_variable_indices[i
@@ -1514,7 +1918,7 @@ fn create_interrobot_factors(
                 //
                 let interrobot_factor = FactorNode::new_interrobot_factor(
                     factorgraph.id(),
-                    Float::from(config.gbp.sigma_factor_interrobot),
+                    sigma,
                     initial_measurement,
                     Float::from(radius.0).try_into().expect("> 0.0"),
                     Float::from(config.robot.inter_robot_safety_distance_multiplier.get())
@@ -1537,11 +1941,12 @@ fn create_interrobot_factors(
                 let factor_id = FactorId::new(robot_id, factor_index);
                 let graph_id = factorgraph.id();
                 factorgraph.add_internal_edge(VariableId::new(graph_id, variable_index), factor_id);
-                external_edges_to_add.push((robot_id, factor_index, *other_robot_id, i));
+                external_edges_to_add.push((robot_id, factor_index, *other_robot_id, other_i));
             }
 
             robotstate.robots_connected_with.insert(*other_robot_id);
         }
+        factorgraph.debug_assert_consistent("adding interrobot factors");
     }
 
     let mut temp = Vec::new();
@@ -1550,7 +1955,7 @@ fn create_interrobot_factors(
         // TODO: use query.get_mut()
         let mut other_factorgraph = query
             .iter_mut()
-            .find(|(id, _, _, _)| *id == other_robot_id)
+            .find(|(id, _, _, _, _, _, _)| *id == other_robot_id)
             .expect("the other_robot_id should be in the query")
             .1;
 
@@ -1570,7 +1975,7 @@ fn create_interrobot_factors(
         // TODO: use query.get_mut()
         let mut factorgraph = query
             .iter_mut()
-            .find(|(id, _, _, _)| *id == robot_id)
+            .find(|(id, _, _, _, _, _, _)| *id == robot_id)
             .expect("the robot_id should be in the query")
             .1;
 
@@ -1588,7 +1993,8 @@ fn create_interrobot_factors(
 /// At random turn on/off the robots "radio".
 /// When the radio is turned of the robot will not be able to communicate with
 /// any other robot. The probability of failure is set by the user in the config
-/// file. `config.robot.communication.failure_rate`
+/// file. `config.robot.communication.failure_rate`, or by the state of the
+/// channel if `config.robot.communication.gilbert-elliott` is set
 /// Called `Simulator::setCommsFailure` in **gbpplanner**
 fn update_failed_comms(
     mut antennas: Query<&mut RadioAntenna>,
@@ -1596,7 +2002,24 @@ fn update_failed_comms(
     mut prng: ResMut<GlobalEntropy<WyRand>>,
 ) {
     for mut antenna in &mut antennas {
-        antenna.active = !prng.gen_bool(config.robot.communication.failure_rate.into());
+        let failure_rate = match config.robot.communication.gilbert_elliott {
+            Some(ref channel) => {
+                let (transition, other) = match antenna.channel {
+                    ChannelState::Good => (channel.good_to_bad, ChannelState::Bad),
+                    ChannelState::Bad => (channel.bad_to_good, ChannelState::Good),
+                };
+                if prng.gen_bool(transition.into()) {
+                    antenna.channel = other;
+                }
+
+                match antenna.channel {
+                    ChannelState::Good => channel.failure_rate_good,
+                    ChannelState::Bad => channel.failure_rate_bad,
+                }
+            }
+            None => config.robot.communication.failure_rate,
+        };
+        antenna.active = !prng.gen_bool(failure_rate.into());
     }
 }
 
@@ -1766,96 +2189,98 @@ fn iterate_gbp_external_sync(
     }
 }
 
+/// Batch sampler for the SDF lookups of the obstacle factors, when enabled with
+/// `gbp.batch-obstacle-lookups`
+#[derive(Resource, Default)]
+pub(super) struct BatchedObstacleLookups(pub(super) Option<Arc<ObstacleLookups>>);
+
+/// Create the batch sampler when enabled, or when the SDF it samples is
+/// replaced, and drop it when disabled
+fn update_batched_obstacle_lookups(
+    mut batched: ResMut<BatchedObstacleLookups>,
+    config: Res<Config>,
+    sdf: Res<Sdf>,
+    environment: Res<gbp_environment::Environment>,
+) {
+    if !config.gbp.batch_obstacle_lookups {
+        batched.0 = None;
+        return;
+    }
+    if batched
+        .0
+        .as_ref()
+        .is_some_and(|lookups| Arc::ptr_eq(lookups.sdf(), &sdf.0))
+    {
+        return;
+    }
+
+    let (width, height) = environment.dimensions();
+    let world_size = WorldSize {
+        width:  f64::from(width),
+        height: f64::from(height),
+    };
+    let lookups = Arc::new(ObstacleLookups::new(Arc::clone(&sdf.0), world_size));
+    info!(
+        "batching the obstacle factor lookups on the {}",
+        if lookups.is_gpu() { "GPU" } else { "CPU" }
+    );
+    batched.0 = Some(lookups);
+}
+
+#[allow(clippy::too_many_arguments)]
 fn iterate_gbp_v2(
     mut query: Query<
-        (
-            &mut FactorGraph,
-            &GbpIterationSchedule,
-            &RadioAntenna,
-            &Mission,
-        ),
-        With<RobotConnections>,
+        (Entity, &mut FactorGraph, &RadioAntenna, &Mission),
+        (With<RobotConnections>, With<GbpIterationSchedule>),
     >,
+    mut q_last_delivered: Query<&mut LastDelivered>,
+    mut q_dropped_messages: Query<(Entity, &RobotConnections, &mut DroppedMessages)>,
+    mut evw_messages_dropped: EventWriter<MessagesDropped>,
     config: Res<Config>,
+    time: Res<Time>,
+    obstacle_lookups: Res<BatchedObstacleLookups>,
+    // PERF: the buffers are reused between system calls
+    mut buffers: Local<solver::MessageBuffers>,
 ) {
     let schedule_config = gbp_schedule::GbpScheduleParams {
         internal: config.gbp.iteration_schedule.internal as u8,
         external: config.gbp.iteration_schedule.external as u8,
     };
-    let schedule = config.gbp.iteration_schedule.schedule.get(schedule_config);
-
-    for gbp_schedule::GbpScheduleAtIteration { internal, external } in schedule {
-        if internal {
-            query
-                .par_iter_mut()
-                .for_each(|(mut factorgraph, _, antenna, mission)| {
-                    // if antenna.active {
-                    // if matches!(mission.state, MissionState::Active) {
-                    if !mission.state.idle() {
-                        factorgraph.internal_factor_iteration();
-                        factorgraph.internal_variable_iteration();
-                    }
-                    //}
-                    // }
-                });
-        }
-
-        if external {
-            let mut messages_to_external_variables = vec![];
-            for (mut factorgraph, _, antenna, mission) in query.iter_mut() {
-                if !antenna.active || mission.state.idle() {
-                    continue;
-                }
-                messages_to_external_variables
-                    .extend(factorgraph.external_factor_iteration().drain(..));
-            }
-
-            // Send messages to external variables
-            for message in messages_to_external_variables.into_iter() {
-                let Ok((mut external_factorgraph, _, antenna, mission)) =
-                    query.get_mut(message.to.factorgraph_id)
-                else {
-                    continue;
-                };
-
-                // cannot receive any new messages if antenna is turned off
-                if !antenna.active || mission.state.idle() {
-                    continue;
-                }
-
-                if let Some(variable) =
-                    external_factorgraph.get_variable_mut(message.to.variable_index)
-                {
-                    variable.receive_message_from(message.from, message.message);
-                }
-            }
-
-            let mut messages_to_external_factors = vec![];
-            for (mut factorgraph, _, antenna, mission) in query.iter_mut() {
-                if !antenna.active || mission.state.idle() {
-                    continue;
-                }
-                messages_to_external_factors
-                    .extend(factorgraph.external_variable_iteration().drain(..));
-            }
-
-            // Send messages to external factors
-            for message in messages_to_external_factors.into_iter() {
-                let Ok((mut external_factorgraph, _, antenna, mission)) =
-                    query.get_mut(message.to.factorgraph_id)
-                else {
-                    continue;
-                };
-
-                // cannot receive any new messages if antenna is turned off
-                if !antenna.active || mission.state.idle() {
-                    continue;
-                }
-
-                if let Some(factor) = external_factorgraph.get_factor_mut(message.to.factor_index) {
-                    factor.receive_message_from(message.from, message.message);
-                }
-            }
+    let schedule = config
+        .gbp
+        .iteration_schedule
+        .schedule
+        .get(schedule_config)
+        .collect::<Vec<_>>();
+
+    let mut robots = query
+        .iter_mut()
+        .map(|(id, factorgraph, antenna, mission)| solver::SolverRobot {
+            id,
+            factorgraph,
+            antenna_active: antenna.active,
+            idle: mission.state.idle(),
+        })
+        .collect::<Vec<_>>();
+
+    solver::drop_messages(
+        &robots,
+        schedule.iter().filter(|at| at.external).count(),
+        &mut q_dropped_messages,
+        &mut evw_messages_dropped,
+    );
+
+    let delivered = solver::iterate(
+        &mut robots,
+        schedule,
+        obstacle_lookups.0.as_deref(),
+        &mut buffers,
+    );
+
+    let now = time.elapsed();
+    for (to, from) in delivered {
+        if let Ok(mut last_delivered) = q_last_delivered.get_mut(to) {
+            last_delivered.delivered(from, now);
         }
     }
 }
@@ -2088,10 +2513,8 @@ fn reached_waypoint(
         //&PlanningStrategy,
     )>,
     // mut factorgraphs: Query<&mut FactorGraph>,
-    config: Res<Config>,
     time: Res<Time>,
     mut evw_robot_reached_waypoint: EventWriter<RobotReachedWaypoint>,
-    mut evw_robot_despawned: EventWriter<RobotDespawned>,
     mut evw_robot_finalized_path: EventWriter<RobotFinishedRoute>,
 ) {
     for (robot_entity, mut fgraph, r, transform, mut mission) in &mut q {
@@ -2146,10 +2569,13 @@ fn reached_waypoint(
         };
 
         if reached {
+            let waypoint_index = mission.current_waypoint_index().unwrap_or_default();
+            let position = next_waypoint.position();
             mission.advance_to_next_waypoint(&time);
             evw_robot_reached_waypoint.send(RobotReachedWaypoint {
-                robot_id:       robot_entity,
-                waypoint_index: 0,
+                robot_id: robot_entity,
+                waypoint_index,
+                position,
             });
 
             info!("robot: {:?} reached a waypoint", robot_entity);
@@ -2168,9 +2594,6 @@ fn reached_waypoint(
         if mission.is_completed() {
             info!("robot {:?} completed its mission", robot_entity);
             evw_robot_finalized_path.send(RobotFinishedRoute(robot_entity));
-            if config.simulation.despawn_robot_when_final_waypoint_reached {
-                evw_robot_despawned.send(RobotDespawned(robot_entity));
-            }
         }
     }
 }
@@ -2191,6 +2614,8 @@ fn update_prior_of_horizon_state(
             &mut FinishedPath,
             &Radius,
             &RadioAntenna,
+            Option<&SpeedFactor>,
+            Has<ExternallyPlanned>,
             // &GbpIterationSchedule,
         ),
         With<RobotConnections>,
@@ -2208,8 +2633,19 @@ fn update_prior_of_horizon_state(
 
     let mut robots_to_despawn = Vec::new();
 
-    for (robot_id, mut factorgraph, mission, mut finished_path, radius, antenna) in &mut query {
-        if finished_path.0 || mission.state.idle()
+    for (
+        robot_id,
+        mut factorgraph,
+        mission,
+        mut finished_path,
+        radius,
+        antenna,
+        speed_factor,
+        externally_planned,
+    ) in &mut query
+    {
+        // the prior of the horizon is set by the local planner of the robot instead
+        if finished_path.0 || mission.state.idle() || externally_planned
         // || !antenna.active
         {
             continue;
@@ -2251,6 +2687,7 @@ fn update_prior_of_horizon_state(
         let horizon2waypoint = next_waypoint_pos - estimated_position;
         let horizon2goal_dist = horizon2waypoint.euclidean_norm();
 
+        let max_speed = max_speed * Float::from(speed_factor.map_or(1.0, |factor| factor.0));
         let new_velocity = Float::min(max_speed, horizon2goal_dist) * horizon2waypoint.normalized();
         let new_position = estimated_position.into_owned() + (&new_velocity * delta_t);
 
@@ -2270,7 +2707,7 @@ fn update_prior_of_horizon_state(
 
     // Send messages to external factors
     for message in all_messages_to_external_factors.drain(..) {
-        let Ok((_, mut external_factorgraph, _, _, _, _)) =
+        let Ok((_, mut external_factorgraph, _, _, _, _, _)) =
             query.get_mut(message.to.factorgraph_id)
         else {
             continue;
@@ -2283,7 +2720,7 @@ fn update_prior_of_horizon_state(
 }
 
 /// Called `Robot::updateCurrent` in **gbpplanner**
-fn update_prior_of_current_state_v3(
+pub(super) fn update_prior_of_current_state_v3(
     mut query: Query<
         (
             &mut FactorGraph,
@@ -2291,15 +2728,19 @@ fn update_prior_of_current_state_v3(
             &T0,
             &Mission,
             &RadioAntenna,
+            Option<&LocalizationError>,
         ),
-        With<RobotConnections>,
+        (With<RobotConnections>, Without<ExternallyPlanned>),
     >,
     config: Res<Config>,
     time_fixed: Res<Time<Fixed>>,
+    mut prng: ResMut<GlobalEntropy<WyRand>>,
 ) {
-    // let mut messages_to_external_factors: Vec<FactorToVariableMessage> = vec![];
+    // With `IntentionSharing::CurrentState` the current variable is connected to
+    // the interrobot factors of other robots
+    let mut all_messages_to_external_factors = vec![];
 
-    for (mut factorgraph, mut transform, &t0, mission, antenna) in &mut query {
+    for (mut factorgraph, mut transform, &t0, mission, antenna, localization_error) in &mut query {
         if mission.state.idle()
         // || !antenna.active
         {
@@ -2316,15 +2757,7 @@ fn update_prior_of_current_state_v3(
 
         let change_in_state =
             Float::from(time_scale) * (&next_variable.belief.mean - &current_variable.belief.mean);
-        let mean_updated = &current_variable.belief.mean + &change_in_state;
-
-        let external_factor_messages =
-            factorgraph.change_prior_of_variable(current_variable_index, mean_updated);
-        assert!(
-            external_factor_messages.is_empty(),
-            "the current variable is not connected to any external factors"
-        );
-        // messages_to_external_factors.extend(external_factor_messages);
+        let mut mean_updated = &current_variable.belief.mean + &change_in_state;
 
         #[allow(clippy::cast_possible_truncation)]
         // bevy uses xzy coordinates, so the y component is put at the z coordinate
@@ -2333,8 +2766,42 @@ fn update_prior_of_current_state_v3(
 
         transform.translation.x += change_in_state[0] as f32;
         transform.translation.z += change_in_state[1] as f32;
+
+        // anchor to a noisy estimate of the true state, instead of the belief
+        if let Some(localization_error) = localization_error {
+            #[allow(clippy::cast_possible_truncation)]
+            let state = Vec4::new(
+                transform.translation.x,
+                transform.translation.z,
+                mean_updated[2] as f32,
+                mean_updated[3] as f32,
+            );
+            let estimate =
+                localization_error.estimate(state, &config.robot.localization, prng.deref_mut());
+            mean_updated = array![
+                Float::from(estimate.x),
+                Float::from(estimate.y),
+                Float::from(estimate.z),
+                Float::from(estimate.w)
+            ];
+        }
+
+        let external_factor_messages =
+            factorgraph.change_prior_of_variable(current_variable_index, mean_updated);
+        all_messages_to_external_factors.extend(external_factor_messages);
         // transform.translation += position_increment;
     }
+
+    // Send messages to external factors
+    for message in all_messages_to_external_factors {
+        let Ok((mut external_factorgraph, ..)) = query.get_mut(message.to.factorgraph_id) else {
+            continue;
+        };
+
+        if let Some(factor) = external_factorgraph.get_factor_mut(message.to.factor_index) {
+            factor.receive_message_from(message.from, message.message);
+        }
+    }
 }
 
 // /// Called `Robot::updateCurrent` in **gbpplanner**
@@ -2466,18 +2933,17 @@ impl ManualModeState {
     }
 }
 
+/// Start a manual step on [`StepSimulation`], by playing the simulation until
+/// [`finish_manual_step`] has counted down `config.manual.timesteps_per_step`
+/// fixed timesteps
 fn start_manual_step(
     config: Res<Config>,
     manual_mode_state: Res<State<ManualModeState>>,
     mut next_manual_mode_state: ResMut<NextState<ManualModeState>>,
-    mut evr_keyboard_input: EventReader<KeyboardInput>,
+    mut evr_step_simulation: EventReader<StepSimulation>,
     mut evw_pause_play: EventWriter<PausePlay>,
 ) {
-    for event in evr_keyboard_input.read() {
-        let (KeyCode::KeyM, ButtonState::Pressed) = (event.key_code, event.state) else {
-            continue;
-        };
-
+    for _ in evr_step_simulation.read() {
         match manual_mode_state.get() {
             ManualModeState::Disabled => {
                 next_manual_mode_state.set(ManualModeState::Enabled {
@@ -2620,6 +3086,7 @@ fn on_robot_clicked(
             "tracking".yellow(),
             factor_counts.tracking
         );
+        println!("        {}: {}", "region".yellow(), factor_counts.region);
 
         println!("  {}:", "messages".magenta());
         // let message_count = factorgraph.message_count();
@@ -2707,3 +3174,225 @@ fn on_gbp_schedule_changed(
         }
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use bevy::ecs::event::ManualEventReader;
+
+    use super::*;
+
+    fn app() -> (App, Entity, VariableIndex) {
+        let mut app = App::new();
+        app.add_event::<PrecisionIllConditioned>()
+            .insert_resource(Config::default())
+            .add_systems(Update, monitor_precision_conditioning);
+
+        let robot_id = app.world.spawn_empty().id();
+        let mut factorgraph = FactorGraph::new(robot_id);
+        let variable_index = factorgraph.add_variable(VariableNode::new(
+            robot_id,
+            array![0.0, 0.0, 0.0, 0.0],
+            Matrix::<Float>::eye(DOFS),
+            DOFS,
+        ));
+        app.world.entity_mut(robot_id).insert(factorgraph);
+        (app, robot_id, variable_index)
+    }
+
+    fn set_condition_number(
+        app: &mut App,
+        robot_id: Entity,
+        variable_index: VariableIndex,
+        condition_number: Float,
+    ) {
+        app.world
+            .get_mut::<FactorGraph>(robot_id)
+            .unwrap()
+            .get_variable_mut(variable_index)
+            .unwrap()
+            .belief
+            .condition_number = condition_number;
+    }
+
+    /// Number of [`PrecisionIllConditioned`] events sent during one update
+    fn reported(app: &mut App, reader: &mut ManualEventReader<PrecisionIllConditioned>) -> usize {
+        app.update();
+        reader
+            .read(app.world.resource::<Events<PrecisionIllConditioned>>())
+            .count()
+    }
+
+    #[test]
+    fn ill_conditioned_variable_is_reported_once_per_crossing() {
+        let (mut app, robot_id, variable_index) = app();
+        let mut reader = ManualEventReader::default();
+
+        set_condition_number(&mut app, robot_id, variable_index, Float::INFINITY);
+        assert_eq!(reported(&mut app, &mut reader), 1);
+        assert_eq!(reported(&mut app, &mut reader), 0);
+        assert_eq!(reported(&mut app, &mut reader), 0);
+
+        // recovers, then crosses the threshold again
+        set_condition_number(&mut app, robot_id, variable_index, 1.0);
+        assert_eq!(reported(&mut app, &mut reader), 0);
+        set_condition_number(&mut app, robot_id, variable_index, Float::INFINITY);
+        assert_eq!(reported(&mut app, &mut reader), 1);
+    }
+}
+
+/// End-to-end smoke test of the planner: robots on a circle swap places with
+/// the robot opposite of them, which forces all of them through the center at
+/// the same time. Runs the fixed update systems of [`RobotPlugin`] headless,
+/// with a fixed seed and a fixed timestep, so every run is identical.
+#[cfg(test)]
+mod regression {
+    use bevy::time::TimeUpdateStrategy;
+    use bevy_rand::prelude::EntropyPlugin;
+    use gbp_environment::{Environment, Obstacles, Regions, Tiles};
+
+    use super::*;
+    use crate::{simulation_loader::SdfImage, utils::get_variable_timesteps};
+
+    const ROBOTS: usize = 4;
+    /// Radius of the circle the robots start on. SI unit: m
+    const CIRCLE_RADIUS: f32 = 8.0;
+    /// SI unit: s
+    const DURATION: f32 = 10.0;
+
+    fn app(config: Config) -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            bevy::time::TimePlugin,
+            EntropyPlugin::<WyRand>::with_seed(config.simulation.prng_seed.to_le_bytes()),
+        ))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            1.0 / config.simulation.hz,
+        )))
+        .insert_resource(Time::<Fixed>::from_hz(config.simulation.hz))
+        .init_resource::<RobotNumberGenerator>()
+        .init_resource::<BatchedObstacleLookups>()
+        .add_event::<PrecisionIllConditioned>()
+        .add_event::<MessagesDropped>()
+        .add_event::<RobotReachedWaypoint>()
+        .add_event::<RobotFinishedRoute>()
+        .add_systems(
+            FixedUpdate,
+            (
+                update_robot_neighbours,
+                delete_interrobot_factors,
+                create_interrobot_factors,
+                update_failed_comms,
+                update_prior_of_horizon_state,
+                update_prior_of_current_state_v3,
+                iterate_gbp_v2,
+                monitor_precision_conditioning,
+                reached_waypoint,
+            )
+                .chain(),
+        )
+        .insert_resource(config);
+        app
+    }
+
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn spawn_robots(app: &mut App) {
+        let config = app.world.resource::<Config>().clone();
+        let environment = Environment {
+            tiles: Tiles::empty().with_tile_size(4.0 * CIRCLE_RADIUS),
+            obstacles: Obstacles::empty(),
+            charging_stations: vec![],
+            traffic_lights: None,
+            regions: Regions::empty(),
+            version: Environment::SCHEMA.version,
+        };
+        // no obstacles, white is free space
+        let obstacles = ObstacleSource::Image(Arc::new(SdfImage::from_pixel(
+            1,
+            1,
+            image::Rgb([255, 255, 255]),
+        )));
+        let radius = config.robot.radius.max.get();
+        let speed = config.robot.target_speed.get();
+        let lookahead_horizon = (config.robot.target_speed * config.robot.planning_horizon).get();
+        let variable_timesteps = get_variable_timesteps(
+            lookahead_horizon as u32,
+            config.gbp.lookahead_multiple as u32,
+        );
+
+        for i in 0..ROBOTS {
+            let angle = std::f32::consts::TAU * i as f32 / ROBOTS as f32;
+            let start = Vec2::from_angle(angle) * CIRCLE_RADIUS;
+            let goal = -start;
+            let velocity = (goal - start).normalize() * speed;
+
+            let entity = app.world.spawn_empty().id();
+            let mut bundle = RobotBundle::new(
+                entity,
+                StateVector::new(start.extend(velocity.x).extend(velocity.y)),
+                &variable_timesteps,
+                &config,
+                &environment,
+                radius,
+                &obstacles,
+                0.0,
+                vec![
+                    StateVector::new(start.extend(velocity.x).extend(velocity.y)),
+                    StateVector::new(goal.extend(velocity.x).extend(velocity.y)),
+                ]
+                .try_into()
+                .unwrap(),
+                PlanningStrategy::OnlyLocal,
+                ReachedWhen::same_as_paper(),
+                ReachedWhen::same_as_paper(),
+            );
+            // a purely local mission does not wait for a route to be planned
+            bundle.mission.state = MissionState::Active;
+            app.world
+                .entity_mut(entity)
+                .insert((bundle, Transform::from_xyz(start.x, 0.0, start.y)));
+        }
+    }
+
+    #[test]
+    fn robots_swapping_places_do_not_collide() {
+        let mut config = Config::default();
+        config.robot.communication.failure_rate = 0.0;
+        let mut app = app(config);
+        spawn_robots(&mut app);
+
+        let mut min_clearance = f32::INFINITY;
+        while app.world.resource::<Time<Fixed>>().elapsed_seconds() < DURATION {
+            app.update();
+
+            let robots = app
+                .world
+                .query::<(&Transform, &Radius)>()
+                .iter(&app.world)
+                .map(|(transform, radius)| (transform.translation.xz(), radius.0))
+                .collect::<Vec<_>>();
+            for ((a, ra), (b, rb)) in robots.iter().tuple_combinations() {
+                min_clearance = min_clearance.min(a.distance(*b) - ra - rb);
+            }
+        }
+
+        assert!(
+            min_clearance > 0.0,
+            "robots collided, smallest clearance was {min_clearance} m"
+        );
+        let completed = app
+            .world
+            .query::<&Mission>()
+            .iter(&app.world)
+            .filter(|mission| mission.is_completed())
+            .count();
+        assert_eq!(
+            completed, ROBOTS,
+            "only {completed} of {ROBOTS} robots reached their goal"
+        );
+    }
+}
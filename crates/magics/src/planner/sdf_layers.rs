@@ -0,0 +1,81 @@
+//! Signed distance fields measured by the obstacle factors in addition to the
+//! one of the environment, e.g. temporary exclusion zones or dynamic
+//! obstacles.
+//!
+//! Insert or remove an [`SdfLayer`] in the [`SdfLayers`] resource, and every
+//! robot, including robots spawned later, measures it through its obstacle
+//! factors. The SDF of the environment is left untouched, so layers are cheap
+//! to add and remove at runtime. The layers are cleared when a simulation is
+//! loaded or reloaded, as they only cover the environment they were made for.
+//! Layers inserted into the obstacle factors of a single robot, like the
+//! barriers of the [traffic lights](super::traffic_light), are left alone.
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy::prelude::*;
+
+use crate::{
+    factorgraph::{factor::obstacle::SdfLayer, prelude::FactorGraph},
+    simulation_loader::{LoadSimulation, ReloadSimulation},
+};
+
+pub struct SdfLayersPlugin;
+
+impl Plugin for SdfLayersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SdfLayers>().add_systems(
+            Update,
+            (
+                clear_sdf_layers
+                    .run_if(on_event::<LoadSimulation>().or_else(on_event::<ReloadSimulation>())),
+                sync_sdf_layers.run_if(resource_changed::<SdfLayers>),
+                add_sdf_layers_to_new_robots,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// **Bevy** [`Resource`] with the layers every robot measures, by name
+#[derive(Debug, Default, Resource, Deref, DerefMut)]
+pub struct SdfLayers(pub BTreeMap<String, SdfLayer>);
+
+fn clear_sdf_layers(mut layers: ResMut<SdfLayers>) {
+    layers.clear();
+}
+
+/// Replace the `previous` layers in the obstacle factors of `factorgraph`
+/// with `layers`
+fn apply_layers(factorgraph: &mut FactorGraph, layers: &SdfLayers, previous: &BTreeSet<String>) {
+    factorgraph.modify_obstacle_factors(|factor| {
+        for name in previous {
+            factor.remove_layer(name);
+        }
+        for (name, layer) in layers.iter() {
+            factor.insert_layer(name.as_str(), layer.clone());
+        }
+    });
+}
+
+fn sync_sdf_layers(
+    mut robots: Query<&mut FactorGraph>,
+    layers: Res<SdfLayers>,
+    mut applied: Local<BTreeSet<String>>,
+) {
+    for mut factorgraph in &mut robots {
+        apply_layers(&mut factorgraph, &layers, &applied);
+    }
+    *applied = layers.keys().cloned().collect();
+}
+
+fn add_sdf_layers_to_new_robots(
+    mut robots: Query<&mut FactorGraph, Added<FactorGraph>>,
+    layers: Res<SdfLayers>,
+) {
+    if layers.is_empty() {
+        return;
+    }
+
+    for mut factorgraph in &mut robots {
+        apply_layers(&mut factorgraph, &layers, &BTreeSet::new());
+    }
+}
@@ -0,0 +1,155 @@
+//! Onboard obstacle sensing, instead of an omniscient map.
+//!
+//! With `robot.sensing.enabled` every robot gets a [`Perception`], starting
+//! out as an empty map of the environment. Each fixed timestep the pixels of
+//! the signed distance field within the sensing radius and field of view of
+//! the robot are copied into its map, and when anything new is seen, the
+//! obstacle factors of the robot are updated to measure the new map.
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use gbp_config::Config;
+use gbp_environment::Environment;
+
+use super::RobotConnections;
+use crate::{
+    bevy_utils::run_conditions::time::virtual_time_is_paused,
+    factorgraph::prelude::FactorGraph,
+    simulation_loader::{Sdf, SdfImage},
+};
+
+pub struct SensingPlugin;
+
+impl Plugin for SensingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (attach_perception, sense_obstacles)
+                .chain()
+                .before(super::robot::update_prior_of_current_state_v3)
+                .run_if(sensing_enabled)
+                .run_if(not(virtual_time_is_paused)),
+        );
+    }
+}
+
+fn sensing_enabled(config: Res<Config>) -> bool {
+    config.robot.sensing.enabled
+}
+
+/// **Bevy** [`Component`] with the obstacles a robot has sensed so far
+#[derive(Debug, Component)]
+pub struct Perception {
+    /// Signed distance field of the sensed obstacles, free space where nothing
+    /// has been seen
+    pub map:   SdfImage,
+    /// Direction the sensor is facing, the last direction the robot drove in
+    heading:   Vec2,
+    /// Whether the obstacle factors of the robot measure `map` yet
+    published: bool,
+}
+
+impl Perception {
+    /// An empty map of the same size as `sdf`
+    #[must_use]
+    pub fn empty(sdf: &SdfImage) -> Self {
+        Self {
+            map:       SdfImage::from_pixel(sdf.width(), sdf.height(), image::Rgb([255, 255, 255])),
+            heading:   Vec2::X,
+            published: false,
+        }
+    }
+}
+
+/// Whether `offset` from the sensor lies within a field of view of `fov`
+/// degrees centered on `heading`
+fn in_field_of_view(offset: Vec2, heading: Vec2, fov: f32) -> bool {
+    fov >= 360.0
+        || offset == Vec2::ZERO
+        || heading.angle_between(offset).abs() <= fov.to_radians() / 2.0
+}
+
+fn attach_perception(
+    mut commands: Commands,
+    robots: Query<Entity, Added<RobotConnections>>,
+    sdf: Res<Sdf>,
+) {
+    for robot in &robots {
+        commands.entity(robot).insert(Perception::empty(&sdf.0));
+    }
+}
+
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn sense_obstacles(
+    mut robots: Query<(&Transform, &mut FactorGraph, &mut Perception)>,
+    sdf: Res<Sdf>,
+    environment: Res<Environment>,
+    config: Res<Config>,
+) {
+    let sensing = &config.robot.sensing;
+    let (width, height) = environment.dimensions();
+    let size = Vec2::new(sdf.0.width() as f32, sdf.0.height() as f32);
+    // pixels per meter
+    let scale = size / Vec2::new(width, height);
+    let reach = sensing.radius * scale;
+
+    for (transform, mut factorgraph, mut perception) in &mut robots {
+        if let Some((_, current)) = factorgraph.nth_variable(0) {
+            let velocity = Vec2::new(current.belief.mean[2] as f32, current.belief.mean[3] as f32);
+            if let Some(heading) = velocity.try_normalize() {
+                perception.heading = heading;
+            }
+        }
+
+        let position = transform.translation.xz();
+        // the y axis is flipped in the image
+        let center = Vec2::new(
+            (position.x + width / 2.0) * scale.x,
+            (-position.y + height / 2.0) * scale.y,
+        );
+        let min = (center - reach).max(Vec2::ZERO).as_uvec2();
+        let max = (center + reach).min(size).as_uvec2();
+
+        let mut seen_new = false;
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                let pixel = Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - center;
+                let offset = Vec2::new(pixel.x / scale.x, -pixel.y / scale.y);
+                if offset.length() > sensing.radius
+                    || !in_field_of_view(offset, perception.heading, sensing.fov)
+                {
+                    continue;
+                }
+
+                let value = *sdf.0.get_pixel(x, y);
+                if *perception.map.get_pixel(x, y) != value {
+                    perception.map.put_pixel(x, y, value);
+                    seen_new = true;
+                }
+            }
+        }
+
+        if seen_new || !perception.published {
+            let map = Arc::new(perception.map.clone());
+            factorgraph.modify_obstacle_factors(|factor| factor.set_sdf(Arc::clone(&map)));
+            perception.published = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_of_view_is_centered_on_the_heading() {
+        assert!(in_field_of_view(Vec2::new(1.0, 0.5), Vec2::X, 90.0));
+        assert!(!in_field_of_view(Vec2::new(0.0, 1.0), Vec2::X, 90.0));
+        assert!(!in_field_of_view(Vec2::new(-1.0, 0.0), Vec2::X, 270.0));
+        assert!(in_field_of_view(Vec2::new(-1.0, 0.0), Vec2::X, 360.0));
+    }
+}
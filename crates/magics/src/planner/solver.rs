@@ -0,0 +1,530 @@
+//! Run the GBP iterations of a timestep off the main thread.
+//!
+//! When `gbp.asynchronous` is set, [`spawn_solve`] copies the factorgraph of
+//! every robot into a task on the [`AsyncComputeTaskPool`], which runs the
+//! iteration schedule of the timestep with [`iterate`], the same function
+//! `iterate_gbp_v2` runs on the factorgraphs in the world.
+//! [`apply_finished_solve`] polls the task in the following timesteps, and once
+//! it has finished applies the result to the factorgraphs in the world with
+//! [`FactorGraph::apply_solution`].
+//!
+//! The planner lags at least one timestep behind, but a solve that takes
+//! longer than a frame no longer stalls rendering. While a task is running no
+//! new task is spawned, so slow solves skip timesteps instead of piling up.
+use std::{collections::HashMap, ops::DerefMut, sync::Arc};
+
+use bevy::{
+    prelude::*,
+    tasks::{futures_lite::future, AsyncComputeTaskPool, ComputeTaskPool, Task, TaskPool},
+};
+use gbp_config::Config;
+use gbp_schedule::GbpScheduleAtIteration;
+
+use super::robot::{
+    BatchedObstacleLookups, DroppedMessages, LastDelivered, MessagesDropped, Mission, RadioAntenna,
+    RobotConnections, RobotId,
+};
+use crate::{
+    factorgraph::{
+        batch::ObstacleLookups,
+        factorgraph::FactorGraph,
+        message::{FactorToVariableMessage, VariableToFactorMessage},
+    },
+    simulation_loader::{LoadSimulation, ReloadSimulation},
+};
+
+pub struct AsyncSolverPlugin;
+
+impl Plugin for AsyncSolverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingSolve>().add_systems(
+            Update,
+            discard_pending_solve
+                .run_if(on_event::<LoadSimulation>().or_else(on_event::<ReloadSimulation>())),
+        );
+    }
+}
+
+/// **Bevy** [`Resource`] holding the solver task currently running, if any
+#[derive(Default, Resource)]
+pub(super) struct PendingSolve(Option<Task<Solution>>);
+
+/// The result of a solver task
+pub(super) struct Solution {
+    factorgraphs: Vec<(RobotId, FactorGraph)>,
+    /// `(to, from)` pairs of robots, where a message from `from` was delivered
+    /// to `to`
+    delivered:    Vec<(RobotId, RobotId)>,
+}
+
+/// A robot taking part in [`iterate`], with either its own copy of its
+/// factorgraph, or a reference to the one in the world
+pub(super) struct SolverRobot<F> {
+    pub(super) id: RobotId,
+    pub(super) factorgraph: F,
+    pub(super) antenna_active: bool,
+    pub(super) idle: bool,
+}
+
+impl<F> SolverRobot<F> {
+    /// Messages to and from a robot are dropped, if its antenna has failed or
+    /// it is not on a mission
+    #[inline]
+    const fn communicates(&self) -> bool {
+        self.antenna_active && !self.idle
+    }
+}
+
+impl SolverRobot<FactorGraph> {
+    fn as_mut(&mut self) -> SolverRobot<&mut FactorGraph> {
+        SolverRobot {
+            id: self.id,
+            factorgraph: &mut self.factorgraph,
+            antenna_active: self.antenna_active,
+            idle: self.idle,
+        }
+    }
+}
+
+/// Buffers for the messages between robots, reused between calls to
+/// [`iterate`]. Emptied with `.drain(..)` every external iteration
+#[derive(Default)]
+pub(super) struct MessageBuffers {
+    to_external_variables: Vec<FactorToVariableMessage>,
+    to_external_factors:   Vec<VariableToFactorMessage>,
+}
+
+/// run criteria if the GBP iterations should run off the main thread
+#[inline]
+pub(super) fn solve_asynchronously(config: Res<Config>) -> bool {
+    config.gbp.asynchronous
+}
+
+/// The result of a solve started for another simulation is of no use
+fn discard_pending_solve(mut pending: ResMut<PendingSolve>) {
+    // dropping a task cancels it
+    pending.0 = None;
+}
+
+/// Apply the result of the solver task to the factorgraphs in the world, if
+/// it has finished
+pub(super) fn apply_finished_solve(
+    mut pending: ResMut<PendingSolve>,
+    mut q_factorgraphs: Query<&mut FactorGraph>,
+    mut q_last_delivered: Query<&mut LastDelivered>,
+    time: Res<Time>,
+) {
+    let Some(task) = pending.0.as_mut() else {
+        return;
+    };
+    let Some(solution) = future::block_on(future::poll_once(task)) else {
+        return;
+    };
+    pending.0 = None;
+
+    for (robot_id, solved) in &solution.factorgraphs {
+        // the robot may have been despawned while the task was running
+        if let Ok(mut factorgraph) = q_factorgraphs.get_mut(*robot_id) {
+            factorgraph.apply_solution(solved);
+        }
+    }
+
+    let now = time.elapsed();
+    for &(to, from) in &solution.delivered {
+        if let Ok(mut last_delivered) = q_last_delivered.get_mut(to) {
+            last_delivered.delivered(from, now);
+        }
+    }
+}
+
+/// Spawn a solver task for the current timestep, unless one is still running
+pub(super) fn spawn_solve(
+    mut pending: ResMut<PendingSolve>,
+    query: Query<(Entity, &FactorGraph, &RadioAntenna, &Mission), With<RobotConnections>>,
+    mut q_dropped_messages: Query<(Entity, &RobotConnections, &mut DroppedMessages)>,
+    mut evw_messages_dropped: EventWriter<MessagesDropped>,
+    obstacle_lookups: Res<BatchedObstacleLookups>,
+    config: Res<Config>,
+) {
+    if pending.0.is_some() {
+        return;
+    }
+
+    let schedule_config = gbp_schedule::GbpScheduleParams {
+        internal: config.gbp.iteration_schedule.internal as u8,
+        external: config.gbp.iteration_schedule.external as u8,
+    };
+    let schedule = config
+        .gbp
+        .iteration_schedule
+        .schedule
+        .get(schedule_config)
+        .collect::<Vec<_>>();
+
+    let mut robots = query
+        .iter()
+        .map(|(id, factorgraph, antenna, mission)| SolverRobot {
+            id,
+            factorgraph: factorgraph.clone(),
+            antenna_active: antenna.active,
+            idle: mission.state.idle(),
+        })
+        .collect::<Vec<_>>();
+
+    drop_messages(
+        &robots,
+        schedule.iter().filter(|at| at.external).count(),
+        &mut q_dropped_messages,
+        &mut evw_messages_dropped,
+    );
+
+    let obstacle_lookups = obstacle_lookups.0.clone();
+    let task = AsyncComputeTaskPool::get().spawn(async move {
+        let delivered = iterate(
+            &mut robots
+                .iter_mut()
+                .map(SolverRobot::as_mut)
+                .collect::<Vec<_>>(),
+            schedule,
+            obstacle_lookups.as_deref(),
+            &mut MessageBuffers::default(),
+        );
+        Solution {
+            factorgraphs: robots
+                .into_iter()
+                .map(|robot| (robot.id, robot.factorgraph))
+                .collect(),
+            delivered,
+        }
+    });
+    pending.0 = Some(task);
+}
+
+/// Count the messages between connected robots dropped in each of
+/// `external_iterations`, because the antenna of either has failed. The
+/// antennas do not change during the iterations of a timestep, so the same
+/// messages are dropped in every one of them
+pub(super) fn drop_messages<F>(
+    robots: &[SolverRobot<F>],
+    external_iterations: usize,
+    q_dropped_messages: &mut Query<(Entity, &RobotConnections, &mut DroppedMessages)>,
+    evw_messages_dropped: &mut EventWriter<MessagesDropped>,
+) {
+    let robot_by_id: HashMap<RobotId, &SolverRobot<F>> =
+        robots.iter().map(|robot| (robot.id, robot)).collect();
+
+    let mut dropped = vec![];
+    for (robot_id, connections, _) in q_dropped_messages.iter() {
+        let Some(robot) = robot_by_id.get(&robot_id).filter(|robot| !robot.idle) else {
+            continue;
+        };
+        for &other_id in &connections.robots_connected_with {
+            let Some(other) = robot_by_id.get(&other_id).filter(|other| !other.idle) else {
+                continue;
+            };
+            if !(robot.antenna_active && other.antenna_active) {
+                dropped.push(MessagesDropped {
+                    from: robot_id,
+                    to:   other_id,
+                });
+            }
+        }
+    }
+
+    for event in &dropped {
+        if let Ok((_, _, mut dropped_messages)) = q_dropped_messages.get_mut(event.to) {
+            dropped_messages.0 += external_iterations;
+        }
+    }
+    for _ in 0..external_iterations {
+        evw_messages_dropped.send_batch(dropped.iter().copied());
+    }
+}
+
+/// Run the GBP iteration `schedule` on the factorgraphs of `robots`. Before
+/// every internal iteration the SDF lookups of the obstacle factors are
+/// prefetched with `obstacle_lookups`, if given. In every external iteration
+/// messages are exchanged between the robots that communicate, see
+/// [`SolverRobot::communicates`].
+///
+/// Returns `(to, from)` pairs of robots, where a message from `from` was
+/// delivered to `to`
+pub(super) fn iterate<F>(
+    robots: &mut [SolverRobot<F>],
+    schedule: impl IntoIterator<Item = GbpScheduleAtIteration>,
+    obstacle_lookups: Option<&ObstacleLookups>,
+    buffers: &mut MessageBuffers,
+) -> Vec<(RobotId, RobotId)>
+where
+    F: DerefMut<Target = FactorGraph> + Send,
+{
+    let index_of: HashMap<RobotId, usize> = robots
+        .iter()
+        .enumerate()
+        .map(|(index, robot)| (robot.id, index))
+        .collect();
+
+    let mut delivered = vec![];
+
+    for GbpScheduleAtIteration { internal, external } in schedule {
+        if internal {
+            if let Some(lookups) = obstacle_lookups {
+                let mut factorgraphs = robots
+                    .iter_mut()
+                    .filter(|robot| !robot.idle)
+                    .map(|robot| &mut *robot.factorgraph)
+                    .collect::<Vec<_>>();
+                lookups.prefetch(&mut factorgraphs);
+            }
+            ComputeTaskPool::get_or_init(TaskPool::default).scope(|scope| {
+                for robot in robots.iter_mut().filter(|robot| !robot.idle) {
+                    scope.spawn(async move {
+                        robot.factorgraph.internal_factor_iteration();
+                        robot.factorgraph.internal_variable_iteration();
+                    });
+                }
+            });
+        }
+
+        if !external {
+            continue;
+        }
+
+        for robot in robots.iter_mut().filter(|robot| robot.communicates()) {
+            robot
+                .factorgraph
+                .external_factor_iteration_into(&mut buffers.to_external_variables);
+        }
+
+        // Send messages to external variables
+        for message in buffers.to_external_variables.drain(..) {
+            let Some(&index) = index_of.get(&message.to.factorgraph_id) else {
+                continue;
+            };
+            let robot = &mut robots[index];
+            // cannot receive any new messages if antenna is turned off
+            if !robot.communicates() {
+                continue;
+            }
+
+            if let Some(variable) = robot
+                .factorgraph
+                .get_variable_mut(message.to.variable_index)
+            {
+                variable.receive_message_from(message.from, message.message);
+                delivered.push((robot.id, message.from.factorgraph_id));
+            }
+        }
+
+        for robot in robots.iter_mut().filter(|robot| robot.communicates()) {
+            robot
+                .factorgraph
+                .external_variable_iteration_into(&mut buffers.to_external_factors);
+        }
+
+        // Send messages to external factors
+        for message in buffers.to_external_factors.drain(..) {
+            let Some(&index) = index_of.get(&message.to.factorgraph_id) else {
+                continue;
+            };
+            let robot = &mut robots[index];
+            // cannot receive any new messages if antenna is turned off
+            if !robot.communicates() {
+                continue;
+            }
+
+            if let Some(factor) = robot.factorgraph.get_factor_mut(message.to.factor_index) {
+                factor.receive_message_from(message.from, message.message);
+                delivered.push((robot.id, message.from.factorgraph_id));
+            }
+        }
+    }
+
+    delivered.sort_unstable();
+    delivered.dedup();
+    delivered
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use gbp_linalg::prelude::*;
+    use ndarray::array;
+
+    use super::*;
+    use crate::{
+        factorgraph::{
+            factor::{
+                interrobot::ExternalVariableId,
+                obstacle::{ObstacleSource, WorldSize},
+            },
+            prelude::*,
+        },
+        simulation_loader::SdfImage,
+    };
+
+    const INTERNAL: GbpScheduleAtIteration = GbpScheduleAtIteration {
+        internal: true,
+        external: false,
+    };
+    const BOTH: GbpScheduleAtIteration = GbpScheduleAtIteration {
+        internal: true,
+        external: true,
+    };
+
+    const WORLD_SIZE: WorldSize = WorldSize {
+        width:  10.0,
+        height: 10.0,
+    };
+
+    const fn robot(id: RobotId, factorgraph: FactorGraph) -> SolverRobot<FactorGraph> {
+        SolverRobot {
+            id,
+            factorgraph,
+            antenna_active: true,
+            idle: false,
+        }
+    }
+
+    /// A factorgraph with a single variable at `(x, y)`
+    fn single_variable(id: RobotId, x: Float, y: Float) -> (FactorGraph, VariableIndex) {
+        let mut factorgraph = FactorGraph::new(id);
+        let variable = factorgraph.add_variable(VariableNode::new(
+            id,
+            array![x, y, 1.0, 0.0],
+            Matrix::<Float>::eye(DOFS),
+            DOFS,
+        ));
+        (factorgraph, variable)
+    }
+
+    /// Two robots close to each other, where the first has an interrobot
+    /// factor between its variable and the variable of the second
+    fn connected_pair() -> [SolverRobot<FactorGraph>; 2] {
+        let (id, other) = (Entity::from_raw(0), Entity::from_raw(1));
+        let (mut factorgraph, variable) = single_variable(id, 0.0, 0.0);
+        let (mut other_factorgraph, other_variable) = single_variable(other, 0.5, 0.0);
+
+        let factor = factorgraph.add_factor(FactorNode::new_interrobot_factor(
+            id,
+            0.01,
+            Vector::<Float>::zeros(DOFS),
+            1.0.try_into().unwrap(),
+            2.0.try_into().unwrap(),
+            ExternalVariableId::new(other, other_variable),
+            NonZeroUsize::MIN,
+            true,
+        ));
+        let _ =
+            factorgraph.add_internal_edge(VariableId::new(id, variable), FactorId::new(id, factor));
+        other_factorgraph.add_external_edge(FactorId::new(id, factor), 0);
+
+        [robot(id, factorgraph), robot(other, other_factorgraph)]
+    }
+
+    /// A robot with a variable at `(x, y)`, and an obstacle factor measuring
+    /// `sdf`
+    fn near_obstacles(sdf: &Arc<SdfImage>, x: Float, y: Float) -> SolverRobot<FactorGraph> {
+        let id = Entity::from_raw(0);
+        let (mut factorgraph, variable) = single_variable(id, x, y);
+        let factor = factorgraph.add_factor(FactorNode::new_obstacle_factor(
+            id,
+            0.01,
+            array![0.0],
+            ObstacleSource::Image(Arc::clone(sdf)),
+            WORLD_SIZE,
+            true,
+        ));
+        let _ =
+            factorgraph.add_internal_edge(VariableId::new(id, variable), FactorId::new(id, factor));
+        // the factor learns where its variable is
+        factorgraph.internal_variable_iteration();
+        robot(id, factorgraph)
+    }
+
+    fn run(
+        robots: &mut [SolverRobot<FactorGraph>],
+        schedule: &[GbpScheduleAtIteration],
+        obstacle_lookups: Option<&ObstacleLookups>,
+    ) -> Vec<(RobotId, RobotId)> {
+        iterate(
+            &mut robots
+                .iter_mut()
+                .map(SolverRobot::as_mut)
+                .collect::<Vec<_>>(),
+            schedule.iter().copied(),
+            obstacle_lookups,
+            &mut MessageBuffers::default(),
+        )
+    }
+
+    #[test]
+    fn messages_are_exchanged_between_communicating_robots() {
+        let mut robots = connected_pair();
+        let (id, other) = (robots[0].id, robots[1].id);
+
+        let delivered = run(&mut robots, &[BOTH, BOTH], None);
+
+        let mut expected = vec![(id, other), (other, id)];
+        expected.sort_unstable();
+        assert_eq!(delivered, expected);
+    }
+
+    #[test]
+    fn messages_to_and_from_robots_that_do_not_communicate_are_dropped() {
+        for (antenna_active, idle) in [(false, false), (true, true), (false, true)] {
+            let mut robots = connected_pair();
+            robots[1].antenna_active = antenna_active;
+            robots[1].idle = idle;
+
+            let delivered = run(&mut robots, &[BOTH], None);
+            assert!(
+                delivered.is_empty(),
+                "{antenna_active} {idle}: {delivered:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn only_external_iterations_exchange_messages() {
+        let mut robots = connected_pair();
+        assert!(run(&mut robots, &[INTERNAL, INTERNAL], None).is_empty());
+    }
+
+    #[test]
+    fn idle_robots_are_not_iterated() {
+        let mut robots = connected_pair();
+        robots[1].idle = true;
+
+        let _ = run(&mut robots, &[INTERNAL, BOTH], None);
+
+        // two internal and one external variable iteration
+        assert_eq!(robots[0].factorgraph.solve_report().iteration, 3);
+        assert_eq!(robots[1].factorgraph.solve_report().iteration, 0);
+    }
+
+    #[test]
+    fn prefetching_the_obstacle_lookups_gives_the_same_solution() {
+        #[allow(clippy::cast_possible_truncation)]
+        let sdf = Arc::new(SdfImage::from_fn(10, 10, |x, y| {
+            image::Rgb([(x * 20 + y * 5) as u8; 3])
+        }));
+        let lookups = ObstacleLookups::new(Arc::clone(&sdf), WORLD_SIZE);
+
+        for (x, y) in [(1.3, -2.1), (-4.2, 3.7)] {
+            let mut prefetched = [near_obstacles(&sdf, x, y)];
+            let mut sampled = [near_obstacles(&sdf, x, y)];
+            let _ = run(&mut prefetched, &[INTERNAL, INTERNAL], Some(&lookups));
+            let _ = run(&mut sampled, &[INTERNAL, INTERNAL], None);
+
+            let (_, prefetched) = prefetched[0].factorgraph.first_variable().unwrap();
+            let (_, sampled) = sampled[0].factorgraph.first_variable().unwrap();
+            // the GPU samples in single precision
+            for (prefetched, sampled) in prefetched.belief.mean.iter().zip(&sampled.belief.mean) {
+                approx::assert_relative_eq!(prefetched, sampled, max_relative = 1e-4);
+            }
+        }
+    }
+}
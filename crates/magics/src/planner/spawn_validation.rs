@@ -0,0 +1,288 @@
+//! Validation of the initial position of robots before they are spawned.
+//!
+//! A robot of a formation is only spawned if its initial position is clear of
+//! the environment and of the robots already present. A robot that would
+//! overlap another robot is deferred until the other robot has moved out of the
+//! way, or rejected, depending on `config.simulation.spawn_validation`. Every
+//! deferred or rejected robot is reported with a [`SpawnObstructed`] event.
+use std::time::Duration;
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_notify::ToastEvent;
+use gbp_config::{Config, SpawnOverlapPolicy};
+use gbp_global_planner::Colliders;
+use parry2d::shape;
+
+use super::{
+    robot::Radius,
+    spawner::{FormationSpawnerSet, RobotSpawnDescription, RobotSpawner, Scoreboard},
+    RobotConnections,
+};
+use crate::simulation_loader::{LoadSimulation, ReloadSimulation};
+
+pub struct SpawnValidationPlugin;
+
+impl Plugin for SpawnValidationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SpawnObstructed>()
+            .init_resource::<DeferredSpawns>()
+            .add_systems(
+                PreUpdate,
+                (
+                    clear_deferred_spawns.run_if(
+                        on_event::<LoadSimulation>().or_else(on_event::<ReloadSimulation>()),
+                    ),
+                    forget_recent_spawns,
+                ),
+            )
+            .add_systems(
+                Update,
+                (
+                    retry_deferred_spawns.in_set(FormationSpawnerSet),
+                    report_obstructed_spawns.run_if(on_event::<SpawnObstructed>()),
+                ),
+            );
+    }
+}
+
+/// What is in the way of a robot being spawned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpawnObstruction {
+    /// The initial position is inside the environment
+    Environment,
+    /// The initial position overlaps another robot
+    Robot,
+}
+
+/// What happened to a robot that could not be spawned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpawnOutcome {
+    /// The robot is spawned once its initial position is clear
+    Deferred,
+    /// The robot is not spawned
+    Rejected,
+}
+
+/// **Bevy** [`Event`] sent when a robot could not be spawned right away
+#[derive(Debug, Clone, Copy, Event)]
+pub struct SpawnObstructed {
+    /// Initial position of the robot
+    pub position:    Vec2,
+    pub obstruction: SpawnObstruction,
+    pub outcome:     SpawnOutcome,
+}
+
+/// A robot waiting for its initial position to clear
+#[derive(Debug, Clone)]
+struct DeferredSpawn {
+    description: RobotSpawnDescription,
+    /// Virtual time at which the robot was deferred
+    deferred_at: Duration,
+}
+
+/// **Bevy** [`Resource`] with the robots waiting to be spawned
+#[derive(Debug, Default, Resource)]
+pub struct DeferredSpawns {
+    waiting: Vec<DeferredSpawn>,
+    /// Robots spawned this frame, which are not in the world yet
+    recent:  Vec<(Vec2, f32)>,
+}
+
+impl DeferredSpawns {
+    /// Number of robots waiting to be spawned
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.waiting.len()
+    }
+
+    /// Whether no robots are waiting to be spawned
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.waiting.is_empty()
+    }
+}
+
+/// Returns `true` if a robot with radius `radius` placed at `position` would
+/// overlap one of the `robots`, given by their position and radius
+pub fn overlaps_robot(
+    position: Vec2,
+    radius: f32,
+    robots: impl IntoIterator<Item = (Vec2, f32)>,
+) -> bool {
+    robots
+        .into_iter()
+        .any(|(other, other_radius)| other.distance(position) < radius + other_radius)
+}
+
+/// Returns `true` if a robot with radius `radius` placed at `position` would
+/// overlap the environment
+pub fn overlaps_environment(position: Vec2, radius: f32, colliders: &Colliders) -> bool {
+    let ball = shape::Ball::new(radius);
+    let isometry = parry2d::na::Isometry2::translation(position.x, position.y);
+    colliders.iter().any(|collider| {
+        parry2d::query::intersection_test(
+            &collider.isometry,
+            collider.shape.as_ref(),
+            &isometry,
+            &ball,
+        )
+        .expect("used shapes are supported")
+    })
+}
+
+/// **Bevy** [`SystemParam`] deciding whether a robot can be spawned
+#[derive(SystemParam)]
+pub struct SpawnValidator<'w, 's> {
+    robots: Query<'w, 's, (&'static Transform, &'static Radius), With<RobotConnections>>,
+    colliders: Option<Res<'w, Colliders>>,
+    deferred: ResMut<'w, DeferredSpawns>,
+    scoreboard: Option<ResMut<'w, Scoreboard>>,
+    evw_spawn_obstructed: EventWriter<'w, SpawnObstructed>,
+    time: Res<'w, Time<Virtual>>,
+}
+
+impl SpawnValidator<'_, '_> {
+    /// What is in the way of a robot with radius `radius` at `position`, if
+    /// anything
+    fn obstruction(&self, position: Vec2, radius: f32) -> Option<SpawnObstruction> {
+        if self
+            .colliders
+            .as_ref()
+            .is_some_and(|colliders| overlaps_environment(position, radius, colliders))
+        {
+            return Some(SpawnObstruction::Environment);
+        }
+
+        let robots = self
+            .robots
+            .iter()
+            .map(|(transform, radius)| (transform.translation.xz(), radius.0))
+            .chain(self.deferred.recent.iter().copied());
+        overlaps_robot(position, radius, robots).then_some(SpawnObstruction::Robot)
+    }
+
+    /// Spawn the robot described by `description` with `spawner` if its
+    /// initial position is clear, and otherwise defer or reject it
+    pub fn spawn(&mut self, spawner: &mut RobotSpawner, description: RobotSpawnDescription) {
+        let settings = &spawner.config.simulation.spawn_validation;
+        let on_robot_overlap = settings.on_robot_overlap;
+        if !settings.enabled {
+            spawner.spawn(description);
+            return;
+        }
+
+        let position = description.initial_pose.xy();
+        let Some(obstruction) = self.obstruction(position, description.radius) else {
+            self.deferred.recent.push((position, description.radius));
+            spawner.spawn(description);
+            return;
+        };
+
+        let outcome = match (obstruction, on_robot_overlap) {
+            (SpawnObstruction::Robot, SpawnOverlapPolicy::Defer) => {
+                self.deferred.waiting.push(DeferredSpawn {
+                    description,
+                    deferred_at: self.time.elapsed(),
+                });
+                SpawnOutcome::Deferred
+            }
+            _ => {
+                self.reject();
+                SpawnOutcome::Rejected
+            }
+        };
+
+        self.evw_spawn_obstructed.send(SpawnObstructed {
+            position,
+            obstruction,
+            outcome,
+        });
+    }
+
+    /// A rejected robot never finishes its route, so it is not counted as left
+    fn reject(&mut self) {
+        if let Some(ref mut scoreboard) = self.scoreboard {
+            scoreboard.robots_left = scoreboard.robots_left.saturating_sub(1);
+        }
+    }
+}
+
+fn clear_deferred_spawns(mut deferred: ResMut<DeferredSpawns>) {
+    deferred.waiting.clear();
+}
+
+/// The robots spawned last frame are in the world by now
+fn forget_recent_spawns(mut deferred: ResMut<DeferredSpawns>) {
+    deferred.recent.clear();
+}
+
+/// Spawn the deferred robots whose initial position has cleared, and reject
+/// the ones that have waited for longer than
+/// `config.simulation.spawn_validation.max_deferral`
+fn retry_deferred_spawns(
+    mut spawner: RobotSpawner,
+    mut validator: SpawnValidator,
+    config: Res<Config>,
+) {
+    if validator.deferred.waiting.is_empty() || validator.time.is_paused() {
+        return;
+    }
+
+    let max_deferral =
+        Duration::from_secs_f32(config.simulation.spawn_validation.max_deferral.max(0.0));
+    let now = validator.time.elapsed();
+    for deferred in std::mem::take(&mut validator.deferred.waiting) {
+        let position = deferred.description.initial_pose.xy();
+        let radius = deferred.description.radius;
+        match validator.obstruction(position, radius) {
+            None => {
+                validator.deferred.recent.push((position, radius));
+                spawner.spawn(deferred.description);
+            }
+            Some(obstruction) if now.saturating_sub(deferred.deferred_at) > max_deferral => {
+                validator.reject();
+                validator.evw_spawn_obstructed.send(SpawnObstructed {
+                    position,
+                    obstruction,
+                    outcome: SpawnOutcome::Rejected,
+                });
+            }
+            Some(_) => validator.deferred.waiting.push(deferred),
+        }
+    }
+}
+
+fn report_obstructed_spawns(
+    mut evr_spawn_obstructed: EventReader<SpawnObstructed>,
+    mut evw_toast: EventWriter<ToastEvent>,
+) {
+    for event in evr_spawn_obstructed.read() {
+        let reason = match event.obstruction {
+            SpawnObstruction::Environment => "it is inside the environment",
+            SpawnObstruction::Robot => "it overlaps another robot",
+        };
+        let caption = match event.outcome {
+            SpawnOutcome::Deferred => {
+                format!("deferred spawning a robot at {}, {reason}", event.position)
+            }
+            SpawnOutcome::Rejected => {
+                format!("did not spawn a robot at {}, {reason}", event.position)
+            }
+        };
+        warn!("{caption}");
+        evw_toast.send(ToastEvent::warning(caption));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_robots() {
+        let robots = [(Vec2::ZERO, 1.0), (Vec2::new(10.0, 0.0), 1.0)];
+        assert!(overlaps_robot(Vec2::new(1.5, 0.0), 1.0, robots));
+        assert!(!overlaps_robot(Vec2::new(5.0, 0.0), 1.0, robots));
+        assert!(!overlaps_robot(Vec2::ZERO, 1.0, []));
+    }
+}
@@ -1,12 +1,12 @@
 use std::{num::NonZeroUsize, ops::DerefMut, time::Duration};
 
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 use bevy_mod_picking::prelude::*;
 use bevy_notify::ToastEvent;
 use bevy_rand::prelude::{ForkableRng, GlobalEntropy};
 use gbp_config::{
-    formation::{PlanningStrategy, RepeatTimes, WorldDimensions},
-    Config,
+    formation::{PlanningStrategy, ReachedWhen, RepeatTimes, WorldDimensions},
+    Config, LocalPlannerKind,
 };
 use itertools::Itertools;
 use rand::{seq::IteratorRandom, Rng};
@@ -14,16 +14,18 @@ use strum::IntoEnumIterator;
 
 use super::{
     robot::{RobotFinishedRoute, RobotSpawned},
+    spawn_validation::SpawnValidator,
     RobotId,
 };
 use crate::{
     // asset_loader::SceneAssets,
     asset_loader::Meshes,
     environment::FollowCameraMe,
+    factorgraph::factor::obstacle::ObstacleSource,
     pause_play::PausePlay,
-    planner::robot::{RobotBundle, Route, StateVector},
+    planner::robot::{Priority, RobotBundle, RobotClass, Route, SpeedFactor, StateVector},
     simulation_loader::{
-        self, EndSimulation, LoadSimulation, ReloadSimulation, Sdf, SimulationManager,
+        self, AnalyticSdf, EndSimulation, LoadSimulation, ReloadSimulation, Sdf, SimulationManager,
     },
     theme::{CatppuccinTheme, ColorAssociation, ColorFromCatppuccinColourExt, DisplayColour},
     utils::get_variable_timesteps,
@@ -38,6 +40,7 @@ impl Plugin for RobotSpawnerPlugin {
             .add_event::<WaypointCreated>()
             // .add_event::<RobotReachedWaypoint>()
             .add_event::<AllFormationsFinished>()
+            .init_resource::<SelectedRobot>()
             .add_systems(
                 Update,
                 (
@@ -56,9 +59,14 @@ impl Plugin for RobotSpawnerPlugin {
             .add_systems(
                 Update,
                 (
-                    spawn_formation,
-                    advance_time.run_if(not(virtual_time_is_paused)),
-                    exit_application_on_scenario_finished,
+                    (
+                        spawn_formation,
+                        advance_time.run_if(not(virtual_time_is_paused)),
+                    )
+                        .in_set(FormationSpawnerSet),
+                    // a playlist decides itself when to exit
+                    exit_application_on_scenario_finished
+                        .run_if(not(resource_exists::<crate::playlist::Playlist>)),
                     // exit_application_on_scenario_finished.run_if(on_event::<AllFormationsFinished>())
                 ),
             )
@@ -67,6 +75,7 @@ impl Plugin for RobotSpawnerPlugin {
                 (
                     track_score.run_if(resource_exists::<Scoreboard>),
                     notify_on_all_formations_finished.run_if(on_event::<AllFormationsFinished>()),
+                    select_clicked_robot.run_if(on_event::<RobotClickedOn>()),
                 ),
             );
     }
@@ -75,6 +84,10 @@ impl Plugin for RobotSpawnerPlugin {
 #[derive(Event)]
 pub struct AllFormationsFinished;
 
+/// Systems that advance the [`FormationSpawner`]s and spawn their formations
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FormationSpawnerSet;
+
 fn track_score(
     mut scoreboard: ResMut<Scoreboard>,
     // mut evr_robot_despawned: EventReader<RobotDespawned>,
@@ -225,6 +238,9 @@ pub struct FormationSpawner {
     pub formation_group_index: usize,
     initial_delay: Timer,
     timer: RepeatingTimer,
+    /// `timer` as it was when the spawner was created, see
+    /// [`FormationSpawner::reset`]
+    initial_timer: RepeatingTimer,
     spawned: usize,
     state: FormationSpawnerState,
 }
@@ -250,6 +266,7 @@ impl FormationSpawner {
         Self {
             formation_group_index,
             initial_delay: Timer::new(initial_delay, TimerMode::Once),
+            initial_timer: timer.clone(),
             timer,
             spawned: 0,
             state: FormationSpawnerState::Inactive,
@@ -300,6 +317,32 @@ impl FormationSpawner {
         self.spawned
     }
 
+    /// Reset the spawner to the state it was created in, as if no time had
+    /// passed and nothing had been spawned
+    pub fn reset(&mut self) {
+        self.initial_delay.reset();
+        self.timer = self.initial_timer.clone();
+        self.spawned = 0;
+        self.state = FormationSpawnerState::Inactive;
+    }
+
+    /// Advance the spawner as if `elapsed` time had passed, without spawning
+    /// anything. Used when the robots are restored from elsewhere, e.g. a
+    /// snapshot, after a [`FormationSpawner::reset`] to fast forward from the
+    /// start
+    pub fn fast_forward(&mut self, elapsed: Duration) {
+        const STEP: Duration = Duration::from_millis(10);
+        let mut remaining = elapsed;
+        while !remaining.is_zero() && !self.exhausted() {
+            let delta = remaining.min(STEP);
+            self.tick(delta);
+            if self.ready_to_spawn() {
+                self.spawn();
+            }
+            remaining -= delta;
+        }
+    }
+
     fn spawn(&mut self) {
         if matches!(self.state, FormationSpawnerState::Active {
             on_cooldown: false,
@@ -340,12 +383,19 @@ pub struct Scoreboard {
 
 fn create_formation_group_spawners(
     mut commands: Commands,
-    simulation_manager: Res<SimulationManager>,
+    mut evr_load_simulation: EventReader<LoadSimulation>,
+    mut evr_reload_simulation: EventReader<ReloadSimulation>,
+    mut prng: ResMut<GlobalEntropy<bevy_prng::WyRand>>,
 ) {
-    let Some(formation_group) = simulation_manager.active_formation_group() else {
+    let Some(loaded) = simulation_loader::latest_loaded_simulation(
+        &mut evr_load_simulation,
+        &mut evr_reload_simulation,
+    ) else {
         warn!("No active formation group!");
         return;
     };
+    let formation_group = &loaded.formation_group;
+    let perturbation = &loaded.config.simulation.perturbation;
 
     let robots_to_spawn = formation_group.robots_to_spawn();
 
@@ -356,12 +406,18 @@ fn create_formation_group_spawners(
             None => RepeatingTimer::new(Duration::from_secs(0), RepeatTimes::ONCE),
         };
 
+        let delay = if perturbation.delay > 0.0 {
+            formation.delay + Duration::from_secs_f32(prng.gen_range(0.0..=perturbation.delay))
+        } else {
+            formation.delay
+        };
+
         info!(
             "spawning FormationSpawner[{i}] with delay {:?} and timer {:?}",
-            formation.delay, repeating_timer
+            delay, repeating_timer
         );
 
-        commands.spawn(FormationSpawner::new(i, formation.delay, repeating_timer));
+        commands.spawn(FormationSpawner::new(i, delay, repeating_timer));
     }
     commands.insert_resource(Scoreboard {
         robots_left: robots_to_spawn,
@@ -411,24 +467,16 @@ fn advance_time(
     }
 }
 
-#[allow(clippy::too_many_arguments, clippy::too_many_lines)]
+#[allow(clippy::too_many_lines)]
 fn spawn_formation(
-    mut commands: Commands,
     mut evr_robot_formation_spawned: EventReader<RobotFormationSpawned>,
-    mut evw_robot_spawned: EventWriter<RobotSpawned>,
-    mut evw_waypoint_created: EventWriter<WaypointCreated>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    config: Res<Config>,
-    env_config: Res<gbp_environment::Environment>,
-    theme: Res<CatppuccinTheme>,
     simulation_manager: Res<SimulationManager>,
-    sdf: Res<Sdf>,
-    mut prng: ResMut<GlobalEntropy<bevy_prng::WyRand>>,
-    mut mesh_assets: ResMut<Assets<Mesh>>,
-    // time_virtual: Res<Time<Virtual>>,
-    time_fixed: Res<Time<Fixed>>,
+    mut spawner: RobotSpawner,
+    mut validator: SpawnValidator,
 ) {
     for event in evr_robot_formation_spawned.read() {
+        let config = spawner.config.clone();
+        let env_config = &spawner.env_config;
         let formation_group = simulation_manager
             .active_formation_group()
             .expect("there is an active formation group");
@@ -437,26 +485,24 @@ fn spawn_formation(
         // TODO: check this gets reloaded correctly
 
         let world_dims = {
-            let tile_size = env_config.tiles.settings.tile_size as f64;
-            let width = tile_size * env_config.tiles.grid.ncols() as f64;
-            let height = tile_size * env_config.tiles.grid.nrows() as f64;
-            WorldDimensions::new(width, height)
+            let (width, height) = env_config.dimensions();
+            WorldDimensions::new(f64::from(width), f64::from(height))
         };
 
         let max_placement_attempts = NonZeroUsize::new(1000).expect("1000 is not zero");
 
         let radii = (0..formation.robots)
-            .map(|_| prng.gen_range(config.robot.radius.range()))
+            .map(|_| spawner.prng.gen_range(config.robot.radius.range()))
             .collect::<Vec<_>>();
 
-        let Some((initial_position_for_each_robot, waypoint_positions_for_each_robot)) = formation
-            .as_positions(
+        let Some((initial_position_for_each_robot, mut waypoint_positions_for_each_robot)) =
+            formation.as_positions(
                 world_dims,
                 &radii, /* config.robot.radius,
                          * max_placement_attempts,
                          * &mut prng.rng as &mut dyn Rng,
                          * prng as &mut dyn Rng, */
-                prng.deref_mut(),
+                spawner.prng.deref_mut(),
             )
         else {
             error!(
@@ -468,6 +514,27 @@ fn spawn_formation(
             return;
         };
 
+        if !formation.goals.is_empty() {
+            match formation.sample_goals(formation.robots, world_dims, spawner.prng.deref_mut()) {
+                Some(goals) => waypoint_positions_for_each_robot.push(goals),
+                None => error!(
+                    "the goals of formation {} have invalid weights, all must be >= 0 and at \
+                     least one > 0, ignoring them",
+                    event.formation_group_index
+                ),
+            }
+        }
+
+        let max_offset = config.simulation.perturbation.position;
+        let initial_position_for_each_robot = if max_offset > 0.0 {
+            initial_position_for_each_robot
+                .into_iter()
+                .map(|position| perturb(position, max_offset, spawner.prng.deref_mut()))
+                .collect()
+        } else {
+            initial_position_for_each_robot
+        };
+
         let initial_pose_for_each_robot: Vec<Vec4> = initial_position_for_each_robot
             .iter()
             .zip(
@@ -498,17 +565,8 @@ fn spawn_formation(
             })
             .collect();
 
-        #[rustfmt::skip]
-        let Some(min_radius) = radii.iter().copied().map(ordered_float::OrderedFloat).min() else {
-            return;
-        };
-        #[rustfmt::skip]
-        let Some(max_radius) = radii.iter().copied().map(ordered_float::OrderedFloat).max() else {
-            return;
-        };
-
         for (i, initial_pose) in initial_pose_for_each_robot.iter().enumerate() {
-            let mut waypoints: Vec<Vec4> = waypoint_poses_for_each_robot
+            let waypoints: Vec<Vec4> = waypoint_poses_for_each_robot
                 .iter()
                 .map(|wps| wps[i])
                 .collect();
@@ -518,130 +576,209 @@ fn spawn_formation(
                 waypoints
             );
 
-            let initial_direction = initial_pose.yz().extend(0.0);
-            let initial_translation = Vec3::new(initial_pose.x, -1.5, initial_pose.y);
-            // let initial_translation = Vec3::new(initial_pose.x, -5.5, initial_pose.y);
+            validator.spawn(&mut spawner, RobotSpawnDescription {
+                initial_pose: *initial_pose,
+                waypoints,
+                radius: radii[i],
+                planning_strategy: formation.planning_strategy,
+                waypoint_reached_when_intersects: formation.waypoint_reached_when_intersects,
+                finished_when_intersects: formation.finished_when_intersects,
+                priority: formation.priority,
+                class: formation.class.clone(),
+                color: None,
+            });
+        }
+    }
+}
+
+/// Move `position` to a uniformly random point at most `max_offset` away
+fn perturb(position: Vec2, max_offset: f32, rng: &mut impl Rng) -> Vec2 {
+    let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+    // the square root makes the points uniform over the area of the disc
+    let distance = max_offset * rng.gen::<f32>().sqrt();
+    position + Vec2::from_angle(angle) * distance
+}
+
+/// Description of a single robot to spawn with a [`RobotSpawner`]
+#[derive(Debug, Clone)]
+pub struct RobotSpawnDescription {
+    /// Initial pose of the robot [x, y, x', y']
+    pub initial_pose: Vec4,
+    /// Poses the robot should visit after its initial pose, at least one
+    pub waypoints: Vec<Vec4>,
+    pub radius: f32,
+    pub planning_strategy: PlanningStrategy,
+    pub waypoint_reached_when_intersects: ReachedWhen,
+    pub finished_when_intersects: ReachedWhen,
+    /// See [`Priority`]
+    pub priority: u8,
+    /// See [`RobotClass`]
+    pub class: Option<String>,
+    /// Colour of the robot, chosen at random if `None`
+    pub color: Option<DisplayColour>,
+}
 
-            let mut entity = commands.spawn_empty();
-            let robot_entity = entity.id();
-            evw_waypoint_created.send_batch(waypoints.iter().map(|pose| WaypointCreated {
+/// **Bevy** [`SystemParam`] with everything needed to spawn a robot
+#[derive(SystemParam)]
+pub struct RobotSpawner<'w, 's> {
+    pub commands: Commands<'w, 's>,
+    evw_robot_spawned: EventWriter<'w, RobotSpawned>,
+    evw_waypoint_created: EventWriter<'w, WaypointCreated>,
+    materials: ResMut<'w, Assets<StandardMaterial>>,
+    mesh_assets: ResMut<'w, Assets<Mesh>>,
+    theme: Res<'w, CatppuccinTheme>,
+    sdf: Res<'w, Sdf>,
+    analytic_sdf: Res<'w, AnalyticSdf>,
+    time_fixed: Res<'w, Time<Fixed>>,
+    pub config: Res<'w, Config>,
+    pub env_config: Res<'w, gbp_environment::Environment>,
+    pub prng: ResMut<'w, GlobalEntropy<bevy_prng::WyRand>>,
+}
+
+impl RobotSpawner<'_, '_> {
+    /// Spawn a robot, returning its entity
+    ///
+    /// # Panics
+    ///
+    /// If `description.waypoints` is empty
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn spawn(&mut self, description: RobotSpawnDescription) -> Entity {
+        let RobotSpawnDescription {
+            initial_pose,
+            waypoints,
+            radius,
+            planning_strategy,
+            waypoint_reached_when_intersects,
+            finished_when_intersects,
+            priority,
+            class,
+            color,
+        } = description;
+
+        assert!(!waypoints.is_empty(), "a robot needs at least one waypoint");
+
+        let initial_direction = initial_pose.yz().extend(0.0);
+        let initial_translation = Vec3::new(initial_pose.x, -1.5, initial_pose.y);
+
+        let mut entity = self.commands.spawn_empty();
+        let robot_entity = entity.id();
+        self.evw_waypoint_created
+            .send_batch(waypoints.iter().map(|pose| WaypointCreated {
                 for_robot: robot_entity,
                 position:  pose.xy(),
             }));
 
-            // let second_last = waypoints.get(waypoints.len() - 2).copied().unwrap();
-            // let last = waypoints.last_mut().unwrap();
-            // last.z = second_last.z;
-            // last.w = second_last.w;
-
-            // let mu
-            let mut waypoints = std::iter::once(initial_pose)
-                .chain(waypoints.iter())
-                .copied()
-                .map_into::<StateVector>()
-                .collect::<Vec<_>>();
-
-            let second_last = waypoints.get(waypoints.len() - 2).copied().unwrap();
-            let last = waypoints.last_mut().unwrap();
-            last.update_velocity(second_last.velocity());
-            // last.z = second_last.z;
-            // last.w = second_last.w;
-            //
-
-            // let lookahead_horizon = (5.0 / 0.25) as u32;
-            // let lookahead_multiple = 3;
-
-            //     globals.T_HORIZON / globals.T0, globals.LOOKAHEAD_MULTIPLE);
-            // num_variables_ = variable_timesteps.size();
-            // let t0: f32 = radii[i] / 2.0 / config.robot.max_speed.get();
-
-            // let divisor: f32 = (min_radius / 2.0 / config.robot.max_speed.get()).into();
-            let divisor: f32 = (max_radius / 2.0 / config.robot.target_speed.get()).into();
-
-            let lookahead_horizon: u32 = (config.robot.planning_horizon.get() / divisor) as u32;
-            let lookahead_horizon: u32 = config.robot.planning_horizon.get() as u32;
-            let lookahead_horizon: u32 =
-                (config.robot.target_speed * config.robot.planning_horizon).get() as u32;
-            // let lookahead_horizon: u32 = (config.robot.planning_horizon.get()
-            //     / radii.iter().map(ordered_float::OrderedFloat).min().unwrap())
-            //     as u32;
-            let lookahead_multiple = config.gbp.lookahead_multiple as u32;
-            let variable_timesteps = get_variable_timesteps(lookahead_horizon, lookahead_multiple);
-
-            let robotbundle = RobotBundle::new(
-                robot_entity,
-                StateVector::new(*initial_pose),
-                // route,
-                variable_timesteps.as_slice(),
-                &config,
-                &env_config,
-                radii[i],
-                &sdf.0,
-                time_fixed.elapsed().as_secs_f64(),
-                waypoints.try_into().unwrap(),
-                // config
-                formation.planning_strategy,
-                formation.waypoint_reached_when_intersects,
-                formation.finished_when_intersects,
-                // matches!(formation.planning_strategy, PlanningStrategy::RrtStar
-                // ),
-            );
+        let mut waypoints = std::iter::once(initial_pose)
+            .chain(waypoints)
+            .map_into::<StateVector>()
+            .collect::<Vec<_>>();
 
-            let initial_visibility = if config.visualisation.draw.robots {
-                Visibility::Visible
-            } else {
-                Visibility::Hidden
-            };
+        let second_last = waypoints.get(waypoints.len() - 2).copied().unwrap();
+        let last = waypoints.last_mut().unwrap();
+        last.update_velocity(second_last.velocity());
 
-            let random_color = DisplayColour::iter()
-                .choose(prng.deref_mut())
-                .expect("there is more than 0 colors");
+        let lookahead_horizon: u32 =
+            (self.config.robot.target_speed * self.config.robot.planning_horizon).get() as u32;
+        let lookahead_multiple = self.config.gbp.lookahead_multiple as u32;
+        let variable_timesteps = get_variable_timesteps(lookahead_horizon, lookahead_multiple);
 
-            let material = materials.add(StandardMaterial {
-                base_color: Color::from_catppuccin_colour(theme.get_display_colour(&random_color)),
-                ..Default::default()
-            });
+        // shared between all obstacle factors of the robot
+        let obstacles = self.analytic_sdf.0.clone().map_or_else(
+            || ObstacleSource::Image(std::sync::Arc::clone(&self.sdf.0)),
+            ObstacleSource::Shapes,
+        );
 
-            let mesh = mesh_assets.add(
-                Sphere::new(radii[i])
-                    .mesh()
-                    .ico(2)
-                    .expect("4 subdivisions is less than the maximum allowed of 80"),
-            );
+        let robotbundle = RobotBundle::new(
+            robot_entity,
+            StateVector::new(initial_pose),
+            variable_timesteps.as_slice(),
+            &self.config,
+            &self.env_config,
+            radius,
+            &obstacles,
+            self.time_fixed.elapsed().as_secs_f64(),
+            waypoints.try_into().unwrap(),
+            planning_strategy,
+            waypoint_reached_when_intersects,
+            finished_when_intersects,
+        );
+
+        let initial_visibility = if self.config.visualisation.draw.robots {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+
+        let speed_noise = self.config.robot.target_speed_noise.get();
+        let speed_factor = if speed_noise > 0.0 {
+            SpeedFactor(1.0 + self.prng.gen_range(-speed_noise..=speed_noise))
+        } else {
+            SpeedFactor::default()
+        };
+
+        let color = color.unwrap_or_else(|| {
+            DisplayColour::iter()
+                .choose(self.prng.deref_mut())
+                .expect("there is more than 0 colors")
+        });
+
+        let material = self.materials.add(StandardMaterial {
+            base_color: Color::from_catppuccin_colour(self.theme.get_display_colour(&color)),
+            ..Default::default()
+        });
+
+        let mesh = self.mesh_assets.add(
+            Sphere::new(radius)
+                .mesh()
+                .ico(2)
+                .expect("4 subdivisions is less than the maximum allowed of 80"),
+        );
+
+        let pbrbundle = PbrBundle {
+            mesh,
+            material,
+            transform: Transform::from_translation(initial_translation),
+            visibility: initial_visibility,
+            ..Default::default()
+        };
+
+        entity.insert((
+            robotbundle,
+            pbrbundle,
+            speed_factor,
+            Priority(priority),
+            self.prng.fork_rng(),
+            simulation_loader::Reloadable,
+            super::tracking::PositionTracker::new(10000, Duration::from_millis(100)),
+            super::tracking::VelocityTracker::new(10000, Duration::from_millis(100)),
+            PickableBundle::default(),
+            On::<Pointer<Click>>::send_event::<RobotClickedOn>(),
+            ColorAssociation { name: color },
+            FollowCameraMe::from_config(&self.config.visualisation.follow_camera)
+                .with_up_direction(Direction3d::new(initial_direction).expect(
+                    "Vector between initial position and first waypoint should be different from \
+                     0, NaN, and infinity.",
+                )),
+            crate::goal_area::components::Collider(Box::new(parry2d::shape::Ball::new(radius))),
+        ));
+
+        if let Some(class) = class {
+            entity.insert(RobotClass(class));
+        }
 
-            let pbrbundle = PbrBundle {
-                mesh,
-                material,
-                transform: Transform::from_translation(initial_translation),
-                visibility: initial_visibility,
-                ..Default::default()
-            };
-
-            entity.insert((
-                robotbundle,
-                pbrbundle,
-                prng.fork_rng(),
-                simulation_loader::Reloadable,
-                // super::tracking::PositionTracker::new(1000, Duration::from_millis(50)),
-                // super::tracking::VelocityTracker::new(1000, Duration::from_millis(50)),
-                super::tracking::PositionTracker::new(10000, Duration::from_millis(100)),
-                super::tracking::VelocityTracker::new(10000, Duration::from_millis(100)),
-                PickableBundle::default(),
-                On::<Pointer<Click>>::send_event::<RobotClickedOn>(),
-                ColorAssociation { name: random_color },
-                FollowCameraMe::new(0.0, 30.0, 0.0)
-                    .with_up_direction(Direction3d::new(initial_direction).expect(
-                        "Vector between initial position and first waypoint should be different \
-                         from 0, NaN, and infinity.",
-                    ))
-                    .with_attached(true),
-                crate::goal_area::components::Collider(Box::new(parry2d::shape::Ball::new(
-                    radii[i],
-                ))),
-            ));
-
-            evw_robot_spawned.send(RobotSpawned(robot_entity));
+        if self.config.robot.local_planner == LocalPlannerKind::Direct {
+            entity.insert(super::local_planner::DirectPlanner);
         }
+
+        info!(
+            robot = ?robot_entity,
+            x = initial_pose.x,
+            y = initial_pose.y,
+            waypoints = waypoints.len(),
+            "robot spawned"
+        );
+        self.evw_robot_spawned.send(RobotSpawned(robot_entity));
+        robot_entity
     }
 }
 
@@ -655,6 +792,26 @@ impl From<ListenerInput<Pointer<Click>>> for RobotClickedOn {
     }
 }
 
+/// **Bevy** [`Resource`]
+/// The robot last clicked on, if any. Debug visualisations of a single robot
+/// are drawn for this robot
+#[derive(Debug, Default, Resource, Deref, DerefMut)]
+pub struct SelectedRobot(pub Option<Entity>);
+
+/// Select the robot clicked on, or deselect it if it is already selected
+fn select_clicked_robot(
+    mut evr_robot_clicked_on: EventReader<RobotClickedOn>,
+    mut selected_robot: ResMut<SelectedRobot>,
+) {
+    for RobotClickedOn(robot) in evr_robot_clicked_on.read() {
+        selected_robot.0 = if selected_robot.0 == Some(*robot) {
+            None
+        } else {
+            Some(*robot)
+        };
+    }
+}
+
 struct DelayTimer(pub Timer);
 
 impl Default for DelayTimer {
@@ -688,3 +845,39 @@ fn exit_application_on_scenario_finished(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Spawns after 1 s, and then every 2 s, 3 times in total
+    fn formation_spawner() -> FormationSpawner {
+        let every = Duration::from_secs(2);
+        let times = RepeatTimes::Finite(3);
+        FormationSpawner::new(0, Duration::from_secs(1), RepeatingTimer::new(every, times))
+    }
+
+    #[test]
+    fn fast_forward_spawns_as_ticking_would() {
+        let mut formation_spawner = formation_spawner();
+        formation_spawner.fast_forward(Duration::from_secs(4));
+        assert_eq!(formation_spawner.spawned(), 2);
+        formation_spawner.fast_forward(Duration::from_secs(4));
+        assert_eq!(formation_spawner.spawned(), 3);
+        assert!(formation_spawner.exhausted());
+    }
+
+    #[test]
+    fn reset_fast_forwards_from_the_start() {
+        // restoring a snapshot taken at 4 s, after the spawner has run for 8 s
+        let mut formation_spawner = formation_spawner();
+        formation_spawner.fast_forward(Duration::from_secs(8));
+        formation_spawner.reset();
+        formation_spawner.fast_forward(Duration::from_secs(4));
+
+        let mut expected = formation_spawner();
+        expected.fast_forward(Duration::from_secs(4));
+        assert_eq!(formation_spawner.spawned(), expected.spawned());
+        assert!(!formation_spawner.exhausted());
+    }
+}
@@ -0,0 +1,258 @@
+//! Direct velocity control of a single robot, for human-in-the-loop
+//! experiments.
+//!
+//! Toggling teleoperation attaches [`Teleoperated`] to the
+//! [`SelectedRobot`]. From then on the robot moves with the commanded
+//! velocity instead of following its planner. Commands come from the left
+//! stick of a gamepad, or from an external process sending datagrams to the
+//! UDP port set with `interaction.teleoperation.udp-port`. [`Teleoperated`]
+//! is a [`LocalPlanner`], so the other robots still plan around it.
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_notify::ToastEvent;
+use gbp_config::Config;
+
+use super::{
+    local_planner::{LocalPlan, LocalPlanner, LocalPlannerPlugin, PlannerInput},
+    spawner::SelectedRobot,
+};
+use crate::bevy_utils::run_conditions::event_exists;
+
+pub struct TeleoperationPlugin;
+
+impl Plugin for TeleoperationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(LocalPlannerPlugin::<Teleoperated>::default())
+            .add_event::<ToggleTeleoperation>()
+            .add_event::<TeleoperationCommand>()
+            .add_systems(
+                Update,
+                (
+                    toggle_teleoperation.run_if(event_exists::<ToggleTeleoperation>),
+                    send_gamepad_commands.run_if(any_with_component::<Teleoperated>),
+                    receive_commands,
+                )
+                    .chain(),
+            );
+
+        #[cfg(not(target_arch = "wasm32"))]
+        app.add_systems(Startup, udp::bind_socket).add_systems(
+            Update,
+            udp::send_udp_commands
+                .run_if(resource_exists::<udp::TeleoperationSocket>)
+                .before(receive_commands),
+        );
+    }
+}
+
+/// **Bevy** [`Event`] to start teleoperating the selected robot, or to stop
+/// teleoperating the robot currently teleoperated
+#[derive(Debug, Event, Clone, Copy)]
+pub struct ToggleTeleoperation;
+
+/// **Bevy** [`Event`] with a new velocity for the teleoperated robot, in
+/// meters per second
+#[derive(Debug, Event, Clone, Copy)]
+pub struct TeleoperationCommand(pub Vec2);
+
+/// **Bevy** [`Component`] attached to the robot being teleoperated.
+/// Its planner is bypassed, and it moves with `velocity` instead.
+#[derive(Debug, Component, Default)]
+pub struct Teleoperated {
+    /// The last commanded velocity
+    pub velocity: Vec2,
+    /// When the last command was received
+    commanded_at: Duration,
+    /// Duration without a command, before the robot stops
+    timeout:      Duration,
+}
+
+/// Below this deflection the gamepad stick is considered at rest
+const GAMEPAD_DEADZONE: f32 = 0.1;
+
+fn toggle_teleoperation(
+    mut commands: Commands,
+    mut evr_toggle: EventReader<ToggleTeleoperation>,
+    selected_robot: Res<SelectedRobot>,
+    q_teleoperated: Query<Entity, With<Teleoperated>>,
+    mut evw_toast: EventWriter<ToastEvent>,
+    config: Res<Config>,
+    time: Res<Time<Virtual>>,
+) {
+    for _ in evr_toggle.read() {
+        if let Ok(robot) = q_teleoperated.get_single() {
+            commands.entity(robot).remove::<Teleoperated>();
+            info!("stopped teleoperating robot {:?}", robot);
+            evw_toast.send(ToastEvent::info("teleoperation disabled".to_string()));
+            continue;
+        }
+
+        let Some(robot) = selected_robot.0 else {
+            evw_toast.send(ToastEvent::warning(
+                "select a robot to teleoperate by clicking on it".to_string(),
+            ));
+            continue;
+        };
+
+        commands.entity(robot).insert(Teleoperated {
+            velocity:     Vec2::ZERO,
+            commanded_at: time.elapsed(),
+            timeout:      Duration::from_secs_f32(config.interaction.teleoperation.timeout),
+        });
+        info!("teleoperating robot {:?}", robot);
+        evw_toast.send(ToastEvent::info(format!("teleoperating robot {robot:?}")));
+    }
+}
+
+/// Command the velocity with the left stick of the first connected gamepad,
+/// scaled by the target speed of the robots
+fn send_gamepad_commands(
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    config: Res<Config>,
+    mut evw_command: EventWriter<TeleoperationCommand>,
+) {
+    let Some(gamepad) = gamepads.iter().next() else {
+        return;
+    };
+
+    let axis = |axis_type| {
+        axes.get(GamepadAxis::new(gamepad, axis_type))
+            .unwrap_or_default()
+    };
+    let stick = Vec2::new(
+        axis(GamepadAxisType::LeftStickX),
+        axis(GamepadAxisType::LeftStickY),
+    );
+    if stick.length() < GAMEPAD_DEADZONE {
+        return;
+    }
+
+    evw_command.send(TeleoperationCommand(
+        stick.clamp_length_max(1.0) * config.robot.target_speed.get(),
+    ));
+}
+
+fn receive_commands(
+    mut evr_command: EventReader<TeleoperationCommand>,
+    mut q_teleoperated: Query<&mut Teleoperated>,
+    time: Res<Time<Virtual>>,
+) {
+    let Some(&TeleoperationCommand(velocity)) = evr_command.read().last() else {
+        return;
+    };
+    for mut teleoperated in &mut q_teleoperated {
+        teleoperated.velocity = velocity;
+        teleoperated.commanded_at = time.elapsed();
+    }
+}
+
+/// Moves with the commanded velocity, or stands still if the operator has
+/// gone quiet
+impl LocalPlanner for Teleoperated {
+    fn plan(&mut self, input: &PlannerInput) -> LocalPlan {
+        if input.now.saturating_sub(self.commanded_at) > self.timeout {
+            self.velocity = Vec2::ZERO;
+        }
+        LocalPlan::velocity(self.velocity.clamp_length_max(input.target_speed))
+    }
+}
+
+/// Parse a datagram of the form `vx vy`, where the two numbers may also be
+/// separated by a comma
+fn parse_command(datagram: &[u8]) -> Option<Vec2> {
+    let text = std::str::from_utf8(datagram).ok()?;
+    let mut numbers = text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(str::parse::<f32>);
+
+    let vx = numbers.next()?.ok()?;
+    let vy = numbers.next()?.ok()?;
+    if numbers.next().is_some() || !vx.is_finite() || !vy.is_finite() {
+        return None;
+    }
+    Some(Vec2::new(vx, vy))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod udp {
+    use std::net::UdpSocket;
+
+    use super::*;
+
+    /// **Bevy** [`Resource`] with the socket commands are received on
+    #[derive(Resource)]
+    pub(super) struct TeleoperationSocket(UdpSocket);
+
+    pub(super) fn bind_socket(mut commands: Commands, config: Res<Config>) {
+        let Some(port) = config.interaction.teleoperation.udp_port else {
+            return;
+        };
+
+        let socket = match UdpSocket::bind(("0.0.0.0", port)) {
+            Ok(socket) => socket,
+            Err(err) => {
+                error!("failed to bind teleoperation socket to port {port}: {err}");
+                return;
+            }
+        };
+        if let Err(err) = socket.set_nonblocking(true) {
+            error!("failed to make teleoperation socket non-blocking: {err}");
+            return;
+        }
+
+        info!("listening for teleoperation commands on udp port {port}");
+        commands.insert_resource(TeleoperationSocket(socket));
+    }
+
+    /// Forward every datagram received since the last frame as a
+    /// [`TeleoperationCommand`]
+    pub(super) fn send_udp_commands(
+        socket: Res<TeleoperationSocket>,
+        mut evw_command: EventWriter<TeleoperationCommand>,
+    ) {
+        let mut buf = [0u8; 64];
+        loop {
+            match socket.0.recv(&mut buf) {
+                Ok(len) => match parse_command(&buf[..len]) {
+                    Some(velocity) => {
+                        evw_command.send(TeleoperationCommand(velocity));
+                    }
+                    None => warn!(
+                        "ignoring malformed teleoperation command: {:?}",
+                        String::from_utf8_lossy(&buf[..len])
+                    ),
+                },
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(err) => {
+                    error!("failed to receive teleoperation command: {err}");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_whitespace_and_comma_separated() {
+        assert_eq!(parse_command(b"1.5 -2"), Some(Vec2::new(1.5, -2.0)));
+        assert_eq!(parse_command(b"1.5,-2\n"), Some(Vec2::new(1.5, -2.0)));
+        assert_eq!(parse_command(b" 0, 0 "), Some(Vec2::ZERO));
+    }
+
+    #[test]
+    fn reject_malformed() {
+        assert_eq!(parse_command(b""), None);
+        assert_eq!(parse_command(b"1.0"), None);
+        assert_eq!(parse_command(b"1 2 3"), None);
+        assert_eq!(parse_command(b"a b"), None);
+        assert_eq!(parse_command(b"NaN 0"), None);
+        assert_eq!(parse_command(&[0xFF, 0xFE]), None);
+    }
+}
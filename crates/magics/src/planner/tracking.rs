@@ -137,6 +137,9 @@ fn track_positions(
 
 #[derive(Clone, Copy, serde::Serialize)]
 pub struct VelocityMeasurement {
+    /// The position at the end of the measurement
+    #[serde(skip)]
+    pub position:      Vec3,
     pub velocity:      Vec3,
     // pub timestamp:     Instant,
     pub timestamp:     f64,
@@ -220,6 +223,7 @@ fn track_velocities(
             if let Some(previous_position) = tracker.previous_position {
                 let dt = now - previous_position.timestamp;
                 let measurement = VelocityMeasurement {
+                    position:      transform.translation,
                     velocity:      (transform.translation - previous_position.position) / dt as f32,
                     timestamp:     now,
                     measured_over: Duration::from_secs_f64(dt),
@@ -0,0 +1,470 @@
+//! Traffic lights at the junctions of the environment, for studying the
+//! coordination between the planners of the robots and the infrastructure.
+//!
+//! With `traffic-lights` set in the environment, a [`TrafficLight`] is placed
+//! at every tile where three or four roads meet. The lights alternate between
+//! letting the vertical and the horizontal road through, with a clearance
+//! phase in between. A robot approaching a junction on a road with a red light
+//! is stopped at the stop line by the [`StoppedAtRedLight`] planner, until the
+//! light turns green. Robots already inside the junction drive on.
+//!
+//! The robot is not overridden, but stopped by its own factorgraph: while it
+//! waits, its obstacle factors measure a virtual obstacle covering the
+//! junction, so GBP plans a path that halts before the stop line.
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use gbp_environment::TrafficLightSettings;
+
+use super::{robot::Radius, RobotConnections};
+use crate::{
+    bevy_utils::run_conditions::time::virtual_time_is_paused,
+    factorgraph::{
+        factor::obstacle::{SdfLayer, WorldSize},
+        prelude::FactorGraph,
+    },
+    simulation_loader::{self, LoadSimulation, ReloadSimulation, SdfImage},
+    theme::{CatppuccinTheme, ColorFromCatppuccinColourExt},
+};
+
+/// Name of the layer with the virtual obstacle of a red light, in the
+/// obstacle factors of a stopped robot
+const BARRIER_LAYER: &str = "traffic-light";
+
+/// Number of pixels along half the width of a road in the image of the
+/// virtual obstacle
+const BARRIER_PIXELS_PER_HALF_WIDTH: f32 = 8.0;
+
+/// Upper bound on the side length of the image of the virtual obstacle
+const BARRIER_MAX_PIXELS: u32 = 2048;
+
+pub struct TrafficLightPlugin;
+
+impl Plugin for TrafficLightPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_traffic_lights
+                    .run_if(on_event::<LoadSimulation>().or_else(on_event::<ReloadSimulation>())),
+                colour_traffic_lights,
+            ),
+        )
+        .add_systems(
+            FixedUpdate,
+            (advance_traffic_lights, stop_at_red_lights)
+                .chain()
+                .run_if(not(virtual_time_is_paused)),
+        );
+    }
+}
+
+/// One of the two roads crossing at a junction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Road {
+    /// Along the y-axis of the planner
+    Vertical,
+    /// Along the x-axis of the planner
+    Horizontal,
+}
+
+impl Road {
+    /// Unit vector along the road
+    #[must_use]
+    pub const fn axis(self) -> Vec2 {
+        match self {
+            Self::Vertical => Vec2::Y,
+            Self::Horizontal => Vec2::X,
+        }
+    }
+}
+
+/// The phase of a [`TrafficLight`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// The road is green, the other road is red
+    Green(Road),
+    /// Both roads are red, to let robots clear the junction
+    Clearance,
+}
+
+/// **Bevy** [`Component`] of a traffic light at a junction
+#[derive(Debug, Component)]
+pub struct TrafficLight {
+    /// Time since the start of the current cycle. SI unit: s
+    pub elapsed:    f32,
+    /// See [`TrafficLightSettings::green`]
+    pub green:      f32,
+    /// See [`TrafficLightSettings::clearance`]
+    pub clearance:  f32,
+    /// Center of the junction
+    pub center:     Vec2,
+    /// Half of the width of the roads, i.e. the distance from the center of
+    /// the junction to the stop lines. SI unit: m
+    pub half_width: f32,
+    /// Robots closer than this to the center of the junction are approaching
+    /// it. SI unit: m
+    pub reach:      f32,
+    /// Virtual obstacle over the junction, measured by the robots stopped at
+    /// the light
+    pub barrier:    SdfLayer,
+    /// The material of the stop lines of each road
+    materials:      [Handle<StandardMaterial>; 2],
+}
+
+impl TrafficLight {
+    /// Duration of a full cycle, where both roads have been green once.
+    #[must_use]
+    pub fn cycle(&self) -> f32 {
+        2.0 * (self.green + self.clearance)
+    }
+
+    /// The current phase of the light
+    #[must_use]
+    pub fn phase(&self) -> Phase {
+        let t = self.elapsed;
+        if t < self.green {
+            Phase::Green(Road::Vertical)
+        } else if t < self.green + self.clearance {
+            Phase::Clearance
+        } else if t < 2.0f32.mul_add(self.green, self.clearance) {
+            Phase::Green(Road::Horizontal)
+        } else {
+            Phase::Clearance
+        }
+    }
+
+    /// Whether robots on `road` may drive into the junction
+    #[inline]
+    #[must_use]
+    pub fn is_green(&self, road: Road) -> bool {
+        self.phase() == Phase::Green(road)
+    }
+
+    /// The road `position` is on, if it is in the approach to the junction
+    /// and not inside the junction itself
+    #[must_use]
+    pub fn approach(&self, position: Vec2) -> Option<Road> {
+        let offset = (position - self.center).abs();
+        match (offset.x <= self.half_width, offset.y <= self.half_width) {
+            (true, false) => Some(Road::Vertical),
+            (false, true) => Some(Road::Horizontal),
+            _ => None,
+        }
+    }
+
+    /// Advance the light by `delta_t` seconds
+    pub fn advance(&mut self, delta_t: f32) {
+        let cycle = self.cycle();
+        if cycle > 0.0 {
+            self.elapsed = (self.elapsed + delta_t) % cycle;
+        }
+    }
+}
+
+/// **Bevy** [`Component`] of a robot waiting in front of a red
+/// [`TrafficLight`], whose obstacle factors measure the
+/// [barrier](TrafficLight::barrier) of the light. Removed, together with the
+/// barrier, when the light turns green.
+#[derive(Debug, Component, Clone, Copy)]
+pub struct StoppedAtRedLight {
+    /// The traffic light entity
+    pub light: Entity,
+    pub road:  Road,
+}
+
+/// Image of a virtual obstacle covering the junction with `center`, that
+/// fades out over `half_width` beyond the stop lines. The image covers all of
+/// `world_size`, like the signed distance field of the environment.
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn barrier_sdf(center: Vec2, half_width: f32, world_size: WorldSize) -> SdfImage {
+    let (width, height) = (world_size.width as f32, world_size.height as f32);
+    let pixels = |extent: f32| {
+        (extent / half_width * BARRIER_PIXELS_PER_HALF_WIDTH)
+            .ceil()
+            .clamp(1.0, BARRIER_MAX_PIXELS as f32) as u32
+    };
+    let (columns, rows) = (pixels(width), pixels(height));
+
+    SdfImage::from_fn(columns, rows, |column, row| {
+        // the y axis is flipped in the image
+        let position = Vec2::new(
+            ((column as f32 + 0.5) / columns as f32).mul_add(width, -width / 2.0),
+            ((row as f32 + 0.5) / rows as f32).mul_add(-height, height / 2.0),
+        );
+        let outside = ((position - center).abs() - Vec2::splat(half_width)).max(Vec2::ZERO);
+        let obstacle = (1.0 - outside.length() / half_width).clamp(0.0, 1.0);
+        image::Rgb([((1.0 - obstacle) * 255.0).round() as u8; 3])
+    })
+}
+
+fn spawn_traffic_lights(
+    mut commands: Commands,
+    mut evr_load_simulation: EventReader<LoadSimulation>,
+    mut evr_reload_simulation: EventReader<ReloadSimulation>,
+    theme: Res<CatppuccinTheme>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(loaded) = simulation_loader::latest_loaded_simulation(
+        &mut evr_load_simulation,
+        &mut evr_reload_simulation,
+    ) else {
+        return;
+    };
+    let environment = &loaded.environment;
+    let Some(&TrafficLightSettings { green, clearance }) = environment.traffic_lights.as_ref()
+    else {
+        return;
+    };
+
+    let half_width = environment.path_width() * environment.tile_extent() / 2.0;
+    let (width, height) = environment.dimensions();
+    let world_size = WorldSize {
+        width:  f64::from(width),
+        height: f64::from(height),
+    };
+    let stop_line_thickness = 0.2;
+    let mut stop_line = |road: Road| {
+        let size = match road {
+            Road::Vertical => Vec3::new(2.0 * half_width, 0.05, stop_line_thickness),
+            Road::Horizontal => Vec3::new(stop_line_thickness, 0.05, 2.0 * half_width),
+        };
+        meshes.add(Cuboid::from_size(size))
+    };
+    let meshes = [stop_line(Road::Vertical), stop_line(Road::Horizontal)];
+
+    for junction in environment.junctions() {
+        let center = environment.tile_center(junction);
+        let materials = [
+            materials.add(Color::from_catppuccin_colour(theme.green())),
+            materials.add(Color::from_catppuccin_colour(theme.red())),
+        ];
+
+        commands
+            .spawn((
+                simulation_loader::Reloadable,
+                TrafficLight {
+                    elapsed: 0.0,
+                    green,
+                    clearance,
+                    center,
+                    half_width,
+                    reach: environment.tile_extent() / 2.0,
+                    barrier: SdfLayer {
+                        sdf:    Arc::new(barrier_sdf(center, half_width, world_size)),
+                        weight: 1.0,
+                    },
+                    materials: materials.clone(),
+                },
+                SpatialBundle::from_transform(Transform::from_translation(Vec3::new(
+                    center.x, 0.0, center.y,
+                ))),
+            ))
+            .with_children(|parent| {
+                for (i, road) in [Road::Vertical, Road::Horizontal].into_iter().enumerate() {
+                    for side in [-1.0, 1.0] {
+                        let offset = road.axis() * side * half_width;
+                        parent.spawn(PbrBundle {
+                            mesh: meshes[i].clone(),
+                            material: materials[i].clone(),
+                            transform: Transform::from_translation(Vec3::new(
+                                offset.x, 0.0, offset.y,
+                            )),
+                            ..default()
+                        });
+                    }
+                }
+            });
+        info!("spawned traffic light at junction {:?}", junction);
+    }
+}
+
+fn advance_traffic_lights(mut lights: Query<&mut TrafficLight>, time: Res<Time>) {
+    for mut light in &mut lights {
+        light.advance(time.delta_seconds());
+    }
+}
+
+fn colour_traffic_lights(
+    lights: Query<&TrafficLight>,
+    theme: Res<CatppuccinTheme>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for light in &lights {
+        for (road, handle) in [Road::Vertical, Road::Horizontal]
+            .into_iter()
+            .zip(&light.materials)
+        {
+            let Some(material) = materials.get_mut(handle) else {
+                continue;
+            };
+            let colour = if light.is_green(road) {
+                theme.green()
+            } else {
+                theme.red()
+            };
+            material.base_color = Color::from_catppuccin_colour(colour);
+        }
+    }
+}
+
+/// Stop robots approaching a junction on a red road, by adding the barrier
+/// of the light to their factorgraph, and release them again when it turns
+/// green
+#[allow(clippy::type_complexity)]
+fn stop_at_red_lights(
+    mut commands: Commands,
+    mut robots: Query<
+        (
+            Entity,
+            &Transform,
+            &Radius,
+            &mut FactorGraph,
+            Option<&StoppedAtRedLight>,
+        ),
+        With<RobotConnections>,
+    >,
+    lights: Query<(Entity, &TrafficLight)>,
+) {
+    for (robot, transform, radius, mut factorgraph, stopped) in &mut robots {
+        if let Some(stopped) = stopped {
+            let released = lights
+                .get(stopped.light)
+                .map_or(true, |(_, light)| light.is_green(stopped.road));
+            if released {
+                factorgraph.modify_obstacle_factors(|factor| {
+                    factor.remove_layer(BARRIER_LAYER);
+                });
+                commands.entity(robot).remove::<StoppedAtRedLight>();
+            }
+            continue;
+        }
+
+        let position = transform.translation.xz();
+        let velocity = factorgraph.first_variable().map_or(Vec2::ZERO, |(_, v)| {
+            #[allow(clippy::cast_possible_truncation)]
+            Vec2::new(v.belief.mean[2] as f32, v.belief.mean[3] as f32)
+        });
+
+        for (entity, light) in &lights {
+            let Some(road) = light.approach(position) else {
+                continue;
+            };
+            if light.is_green(road) {
+                continue;
+            }
+
+            let along = (position - light.center).dot(road.axis());
+            let stop_distance = light.half_width + radius.0;
+            // only robots driving towards the junction, that can still stop
+            // before it, are stopped
+            if !(stop_distance..=light.reach).contains(&along.abs())
+                || velocity.dot(road.axis()) * along >= 0.0
+            {
+                continue;
+            }
+
+            factorgraph.modify_obstacle_factors(|factor| {
+                factor.insert_layer(BARRIER_LAYER, light.barrier.clone());
+            });
+            commands.entity(robot).insert(StoppedAtRedLight {
+                light: entity,
+                road,
+            });
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::factorgraph::factor::obstacle::sample_sdf;
+
+    const WORLD_SIZE: WorldSize = WorldSize {
+        width:  20.0,
+        height: 10.0,
+    };
+
+    fn light() -> TrafficLight {
+        let center = Vec2::ZERO;
+        let half_width = 1.0;
+        TrafficLight {
+            elapsed: 0.0,
+            green: 10.0,
+            clearance: 2.0,
+            center,
+            half_width,
+            reach: 5.0,
+            barrier: SdfLayer {
+                sdf:    Arc::new(barrier_sdf(center, half_width, WORLD_SIZE)),
+                weight: 1.0,
+            },
+            materials: default(),
+        }
+    }
+
+    #[test]
+    fn phases_alternate_between_roads_with_clearance() {
+        let mut light = light();
+        let mut phases = vec![];
+        for _ in 0..24 {
+            phases.push(light.phase());
+            light.advance(1.0);
+        }
+
+        assert!(phases[..10]
+            .iter()
+            .all(|phase| *phase == Phase::Green(Road::Vertical)));
+        assert!(phases[10..12]
+            .iter()
+            .all(|phase| *phase == Phase::Clearance));
+        assert!(phases[12..22]
+            .iter()
+            .all(|phase| *phase == Phase::Green(Road::Horizontal)));
+        assert!(phases[22..].iter().all(|phase| *phase == Phase::Clearance));
+
+        // the cycle starts over
+        assert_eq!(light.phase(), Phase::Green(Road::Vertical));
+    }
+
+    #[test]
+    fn approach_excludes_the_junction_itself() {
+        let light = light();
+        assert_eq!(light.approach(Vec2::new(0.5, 5.0)), Some(Road::Vertical));
+        assert_eq!(light.approach(Vec2::new(-5.0, 0.5)), Some(Road::Horizontal));
+        assert_eq!(light.approach(Vec2::new(0.5, 0.5)), None);
+        assert_eq!(light.approach(Vec2::new(5.0, 5.0)), None);
+    }
+
+    #[test]
+    fn barrier_covers_the_junction_and_fades_out_beyond_the_stop_lines() {
+        let center = Vec2::new(4.0, -2.0);
+        let sdf = barrier_sdf(center, 1.0, WORLD_SIZE);
+        let sample = |offset: Vec2| {
+            let position = center + offset;
+            sample_sdf(
+                &sdf,
+                WORLD_SIZE,
+                f64::from(position.x),
+                f64::from(position.y),
+            )
+            .unwrap()
+        };
+
+        // inside the junction
+        assert!(sample(Vec2::ZERO) > 0.99);
+        assert!(sample(Vec2::new(0.8, -0.8)) > 0.99);
+        // halfway between the stop line and where the barrier ends
+        assert!((sample(Vec2::new(0.0, 1.5)) - 0.5).abs() < 0.1);
+        assert!((sample(Vec2::new(-1.5, 0.0)) - 0.5).abs() < 0.1);
+        // far from the junction
+        assert!(sample(Vec2::new(0.0, 2.5)) < 0.01);
+        assert!(sample(Vec2::new(-6.0, 3.0)) < 0.01);
+    }
+}
@@ -1,14 +1,19 @@
 //! A **Bevy** Plugin for visualising the communication graph between robots
 
+use std::time::Duration;
+
 use bevy::prelude::*;
 use gbp_config::Config;
 
 use super::super::RobotConnections;
 use crate::{
-    planner::robot::RadioAntenna,
+    planner::robot::LastDelivered,
     theme::{CatppuccinTheme, ColorFromCatppuccinColourExt},
 };
 
+/// Age of the last delivered message at which a link is drawn fully red
+const STALE_AFTER: Duration = Duration::from_secs(1);
+
 /// A **Bevy** Plugin for visualising the communication graph between robots
 pub struct CommunicationGraphVisualiserPlugin;
 
@@ -80,28 +85,35 @@ fn enabled(config: Res<Config>) -> bool {
 //     }
 // }
 
+/// Draw a line from every robot halfway to each robot it is connected with.
+/// The half nearest a robot is coloured by the age of the last message it got
+/// from the other robot, from green for a fresh message to red for a message
+/// older than [`STALE_AFTER`] or no message at all
 fn draw_communication_graph_v3(
     mut gizmos: Gizmos,
     catppuccin_theme: Res<CatppuccinTheme>,
-    query: Query<(Entity, &RobotConnections, &RadioAntenna, &Transform)>,
+    query: Query<(&RobotConnections, &LastDelivered, &Transform)>,
+    time_fixed: Res<Time<Fixed>>,
 ) {
-    let connected_color = Color::from_catppuccin_colour(catppuccin_theme.green());
-    let disconnected_color = Color::from_catppuccin_colour(catppuccin_theme.red());
-
-    for (_, robot_state, antenna, transform) in &query {
-        let color = if antenna.active {
-            &connected_color
-        } else {
-            &disconnected_color
-        };
+    let fresh = Color::from_catppuccin_colour(catppuccin_theme.green()).rgb_linear_to_vec3();
+    let stale = Color::from_catppuccin_colour(catppuccin_theme.red()).rgb_linear_to_vec3();
+    let now = time_fixed.elapsed();
 
+    for (robot_state, last_delivered, transform) in &query {
         for connected_with_id in &robot_state.robots_connected_with {
-            let Ok((_, _, _, other_transform)) = query.get(*connected_with_id) else {
+            let Ok((_, _, other_transform)) = query.get(*connected_with_id) else {
                 continue;
             };
 
+            let staleness = last_delivered
+                .age(*connected_with_id, now)
+                .map_or(1.0, |age| {
+                    (age.as_secs_f32() / STALE_AFTER.as_secs_f32()).min(1.0)
+                });
+            let color = Color::rgb_linear_from_array(fresh.lerp(stale, staleness).to_array());
+
             let halfway_point = (transform.translation + other_transform.translation) / 2.;
-            gizmos.line(transform.translation, halfway_point, *color);
+            gizmos.line(transform.translation, halfway_point, color);
         }
     }
 }
@@ -0,0 +1,73 @@
+//! Visualize messages between robots dropped by the failure model
+use std::{collections::HashMap, time::Duration};
+
+use bevy::prelude::*;
+use gbp_config::Config;
+
+use crate::{
+    planner::{robot::MessagesDropped, RobotId},
+    theme::{CatppuccinTheme, ColorFromCatppuccinColourExt},
+};
+
+pub struct DroppedMessagesVisualizerPlugin;
+
+impl Plugin for DroppedMessagesVisualizerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Pulses>()
+            .add_systems(Update, (start_pulses, draw_pulses).chain().run_if(enabled));
+    }
+}
+
+/// **Bevy** run condition for drawing dropped messages
+#[inline]
+fn enabled(config: Res<Config>) -> bool {
+    config.visualisation.draw.dropped_messages
+}
+
+/// How long a pulse is visible after messages on a link have been dropped
+const PULSE_DURATION: Duration = Duration::from_millis(400);
+
+/// **Bevy** [`Resource`]
+/// When messages were last dropped on each directed link, in real time
+#[derive(Debug, Default, Resource, Deref, DerefMut)]
+struct Pulses(HashMap<(RobotId, RobotId), Duration>);
+
+fn start_pulses(
+    mut evr_messages_dropped: EventReader<MessagesDropped>,
+    mut pulses: ResMut<Pulses>,
+    time_real: Res<Time<Real>>,
+) {
+    let now = time_real.elapsed();
+    for MessagesDropped { from, to } in evr_messages_dropped.read() {
+        pulses.insert((*from, *to), now);
+    }
+}
+
+/// Draw an expanding, fading ring on the receiving half of every link that
+/// recently had messages dropped
+fn draw_pulses(
+    mut gizmos: Gizmos,
+    mut pulses: ResMut<Pulses>,
+    transforms: Query<&Transform>,
+    time_real: Res<Time<Real>>,
+    theme: Res<CatppuccinTheme>,
+) {
+    let now = time_real.elapsed();
+    pulses.retain(|_, started| now.saturating_sub(*started) < PULSE_DURATION);
+
+    let color = Color::from_catppuccin_colour(theme.maroon());
+    for (&(from, to), started) in pulses.iter() {
+        let (Ok(from), Ok(to)) = (transforms.get(from), transforms.get(to)) else {
+            continue;
+        };
+
+        let progress = now.saturating_sub(*started).as_secs_f32() / PULSE_DURATION.as_secs_f32();
+        let position = from.translation.lerp(to.translation, 0.75);
+        gizmos.circle(
+            position,
+            Direction3d::Y,
+            0.3 + 1.2 * progress,
+            color.with_a(1.0 - progress),
+        );
+    }
+}
@@ -1,17 +1,16 @@
 use bevy::prelude::*;
 use bevy_mod_picking::prelude::*;
-use gbp_config::{Config, DrawSetting};
+use gbp_config::Config;
 use itertools::Itertools;
 
-use super::RobotTracker;
+use super::{drawn_for, RobotTracker};
 use crate::{
     // asset_loader::SceneAssets,
     asset_loader::Meshes,
-    bevy_utils::run_conditions::event_exists,
     factorgraph::{factor::Factor, factorgraph::VariableIndex, prelude::FactorGraph},
-    input::DrawSettingsEvent,
     planner::{
         robot::{Radius, RobotDespawned, RobotSpawned},
+        spawner::SelectedRobot,
         RobotConnections,
     },
     simulation_loader::{self, EndSimulation},
@@ -28,7 +27,7 @@ impl Plugin for FactorGraphVisualiserPlugin {
             Update,
             (
                 update_factorgraph_visualizers,
-                show_or_hide_factorgraphs.run_if(event_exists::<DrawSettingsEvent>),
+                show_or_hide_factorgraphs,
                 draw_lines_between_variables.run_if(enabled),
                 remove_rendered_factorgraph_when_robot_despawns,
                 remove_rendered_factorgraphs.run_if(on_event::<EndSimulation>()),
@@ -333,23 +332,22 @@ fn update_factorgraph_visualizers(
 }
 
 /// A **Bevy** [`Update`] system
-/// Reads [`DrawSettingEvent`], where if `DrawSettingEvent.setting ==
-/// DrawSetting::PredictedTrajectories` the boolean `DrawSettingEvent.value`
-/// will be used to set the visibility of the [`VariableVisualiser`] entities
+/// Shows the [`VariableVisualiser`] entities of the robots the predicted
+/// trajectories are drawn for, and hides the rest
 fn show_or_hide_factorgraphs(
-    mut query: Query<&mut Visibility, With<VariableVisualiser>>,
-    mut draw_setting_event: EventReader<DrawSettingsEvent>,
+    mut query: Query<(&RobotTracker, &mut Visibility), With<VariableVisualiser>>,
+    config: Res<Config>,
+    selected_robot: Res<SelectedRobot>,
 ) {
-    for event in draw_setting_event.read() {
-        if matches!(event.setting, DrawSetting::PredictedTrajectories) {
-            for mut visibility in &mut query {
-                *visibility = if event.draw {
-                    Visibility::Visible
-                } else {
-                    Visibility::Hidden
-                };
-            }
-        }
+    let draw = config.visualisation.draw.predicted_trajectories;
+    let only_selected = config.visualisation.only_selected.predicted_trajectories;
+    for (tracker, mut visibility) in &mut query {
+        let new_visibility = if drawn_for(tracker.robot_id, draw, only_selected, &selected_robot) {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+        visibility.set_if_neq(new_visibility);
     }
 }
 
@@ -369,10 +367,17 @@ fn draw_lines_between_variables(
     query_variables: Query<(&RobotTracker, &Transform), With<VariableVisualiser>>,
     query_factorgraphs: Query<(Entity, &ColorAssociation), With<FactorGraph>>,
     theme: Res<CatppuccinTheme>,
+    config: Res<Config>,
+    selected_robot: Res<SelectedRobot>,
 ) {
     // let color = Color::from_catppuccin_colour(catppuccin_theme.text());
 
+    let only_selected = config.visualisation.only_selected.predicted_trajectories;
     for (entity, color_association) in &query_factorgraphs {
+        if !drawn_for(entity, true, only_selected, &selected_robot) {
+            continue;
+        }
+
         // PERF: reuse the same vector, as all factorgraphs have the same variables
         let positions = query_variables
             .iter()
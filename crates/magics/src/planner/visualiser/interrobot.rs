@@ -3,7 +3,11 @@
 use bevy::prelude::*;
 use gbp_config::Config;
 
-use crate::{factorgraph::prelude::FactorGraph, planner::robot::RadioAntenna};
+use super::drawn_for;
+use crate::{
+    factorgraph::prelude::FactorGraph,
+    planner::{robot::RadioAntenna, spawner::SelectedRobot},
+};
 
 pub struct InterRobotFactorVisualizerPlugin;
 
@@ -23,8 +27,9 @@ fn enabled(config: Res<Config>) -> bool {
 
 fn visualize_interrobot_factors(
     mut gizmos: Gizmos,
-    q: Query<(&FactorGraph, &RadioAntenna)>,
+    q: Query<(Entity, &FactorGraph, &RadioAntenna)>,
     config: Res<Config>,
+    selected_robot: Res<SelectedRobot>,
 ) {
     let red = colorgrad::Color::from_linear_rgba(1.0, 0.0, 0.0, 200.0);
     let yellow = colorgrad::Color::from_linear_rgba(1.0, 1.0, 0.0, 200.0);
@@ -38,12 +43,17 @@ fn visualize_interrobot_factors(
     // let height = 0.5f32;
     let height = -config.visualisation.height.objects;
 
-    for (factorgraph, antenna) in &q {
+    let only_selected = config.visualisation.only_selected.factors;
+    for (robot, factorgraph, antenna) in &q {
+        if !drawn_for(robot, true, only_selected, &selected_robot) {
+            continue;
+        }
         for (variable, interrobot) in factorgraph.variable_and_inter_robot_factors() {
             let estimated_position = variable.estimated_position_vec2();
             // get the estimated position of the variable that the interrobot factor is
             // connected to in the external factor graph
-            let Ok((external_factorgraph, _)) = q.get(interrobot.external_variable.factorgraph_id)
+            let Ok((_, external_factorgraph, _)) =
+                q.get(interrobot.external_variable.factorgraph_id)
             else {
                 continue;
             };
@@ -1,13 +1,17 @@
 mod collider;
 mod communication;
 pub mod communication_radius;
+mod dropped;
 pub mod factorgraphs;
 mod interrobot;
 mod obstacle;
+mod regions;
 mod robot;
+mod safety;
 mod tracer;
 mod tracking;
 mod uncertainty;
+mod velocity;
 pub mod waypoints;
 
 const Z_FIGHTING_OFFSET: f32 = 0.04;
@@ -24,7 +28,7 @@ use self::{
     tracer::TracerVisualiserPlugin, uncertainty::UncertaintyVisualiserPlugin,
     waypoints::WaypointVisualiserPlugin,
 };
-use super::RobotId;
+use super::{spawner::SelectedRobot, RobotId};
 
 /// A **Bevy** `Plugin` for visualising aspects of the planner
 /// Includes visualising parts of the factor graph
@@ -44,6 +48,10 @@ impl Plugin for VisualiserPlugin {
             interrobot::InterRobotFactorVisualizerPlugin,
             collider::ColliderVisualizerPlugin,
             tracking::TrackingVisualizerPlugin,
+            velocity::VelocityVisualizerPlugin,
+            dropped::DroppedMessagesVisualizerPlugin,
+            safety::SafetyDiscVisualiserPlugin,
+            regions::RegionVisualiserPlugin,
         ));
     }
 }
@@ -79,6 +87,18 @@ impl RobotTracker {
     }
 }
 
+/// Whether a visualisation enabled by `draw` is drawn for `robot`. With
+/// `only_selected` it is only drawn for the selected robot
+#[inline]
+fn drawn_for(
+    robot: Entity,
+    draw: bool,
+    only_selected: bool,
+    selected_robot: &SelectedRobot,
+) -> bool {
+    draw && (!only_selected || selected_robot.0 == Some(robot))
+}
+
 /// A **Bevy** marker [`Component`] for lines
 /// Generally used to identify previously spawned lines,
 /// so they can be updated or removed
@@ -4,14 +4,21 @@
 use bevy::prelude::*;
 use gbp_config::Config;
 
-use crate::factorgraph::prelude::FactorGraph;
+use super::drawn_for;
+use crate::{factorgraph::prelude::FactorGraph, planner::spawner::SelectedRobot};
 
 #[derive(Default)]
 pub struct ObstacleFactorVisualizerPlugin;
 
 impl Plugin for ObstacleFactorVisualizerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, visualize_obstacle_factors.run_if(enabled));
+        app.add_systems(
+            Update,
+            (
+                visualize_obstacle_factors.run_if(enabled),
+                visualize_obstacle_gradients.run_if(gradients_enabled),
+            ),
+        );
     }
 }
 
@@ -90,8 +97,9 @@ pub fn measurement_color(value: f32) -> bevy::render::color::Color {
 /// b = 0
 fn visualize_obstacle_factors(
     mut gizmos: Gizmos,
-    factorgraphs: Query<&FactorGraph>,
+    factorgraphs: Query<(Entity, &FactorGraph)>,
     config: Res<Config>,
+    selected_robot: Res<SelectedRobot>,
     theme: Res<crate::theme::CatppuccinTheme>,
 ) {
     use crate::theme::ColorFromCatppuccinColourExt;
@@ -104,7 +112,12 @@ fn visualize_obstacle_factors(
     // let green = theme.green();
     let gradient = gradient(&green, &red);
 
-    for factorgraph in &factorgraphs {
+    let only_selected = config.visualisation.only_selected.factors;
+    for (robot, factorgraph) in &factorgraphs {
+        if !drawn_for(robot, true, only_selected, &selected_robot) {
+            continue;
+        }
+
         for (variable, obstacle_factor) in factorgraph.variable_and_their_obstacle_factors() {
             // let estimated_position = variable.estimated_position_vec2();
             let last_measurement = obstacle_factor.last_measurement();
@@ -159,3 +172,60 @@ fn visualize_obstacle_factors(
 fn enabled(config: Res<Config>) -> bool {
     config.visualisation.draw.obstacle_factors && config.gbp.factors_enabled.obstacle
 }
+
+/// Length of the arrows showing the gradient direction
+/// SI unit: m
+const GRADIENT_ARROW_LENGTH: f32 = 1.5;
+
+/// Draw the measurement and gradient of every obstacle factor of the selected
+/// robot at the position it was sampled. The circle grows and turns red with
+/// the measured value, and the arrow points along the gradient, i.e. towards
+/// the nearest obstacle. A sampling position that does not follow its variable,
+/// or an arrow pointing away from a nearby obstacle, reveals sign or axis
+/// errors in the SDF lookup
+fn visualize_obstacle_gradients(
+    mut gizmos: Gizmos,
+    factorgraphs: Query<&FactorGraph>,
+    selected_robot: Res<SelectedRobot>,
+    config: Res<Config>,
+) {
+    let Some(factorgraph) = selected_robot.and_then(|robot| factorgraphs.get(robot).ok()) else {
+        return;
+    };
+
+    let height = -config.visualisation.height.objects;
+    let gradient = gradient(&Color::GREEN, &Color::RED);
+
+    for (variable, obstacle_factor) in factorgraph.variable_and_their_obstacle_factors() {
+        let last_measurement = obstacle_factor.last_measurement();
+        #[allow(clippy::cast_possible_truncation)]
+        let value = (last_measurement.value as f32).clamp(0.0, 1.0);
+        let [r, g, b, _] = gradient.at(f64::from(value)).to_array();
+        #[allow(clippy::cast_possible_truncation)]
+        let color = Color::rgb(r as f32, g as f32, b as f32);
+
+        let pos = last_measurement.pos.extend(height).xzy();
+        gizmos.circle(pos, Direction3d::Y, 0.2 + 0.5 * value, color);
+        gizmos.line(
+            variable.estimated_position_vec2().extend(height).xzy(),
+            pos,
+            Color::GRAY,
+        );
+
+        let direction = obstacle_factor
+            .gradient(last_measurement.pos)
+            .normalize_or_zero();
+        if direction != Vec2::ZERO {
+            let end = pos + direction.extend(0.0).xzy() * GRADIENT_ARROW_LENGTH;
+            gizmos.arrow(pos, end, color);
+        }
+    }
+}
+
+/// **Bevy** run condition for drawing the gradients of obstacle factors
+#[inline]
+fn gradients_enabled(config: Res<Config>, selected_robot: Res<SelectedRobot>) -> bool {
+    config.visualisation.draw.obstacle_gradients
+        && config.gbp.factors_enabled.obstacle
+        && selected_robot.is_some()
+}
@@ -0,0 +1,50 @@
+//! **Bevy** Plugin to visualise the cost regions of the environment
+use bevy::prelude::*;
+use gbp_config::Config;
+use gbp_environment::Environment;
+
+use super::Z_FIGHTING_OFFSET;
+use crate::theme::{CatppuccinTheme, ColorFromCatppuccinColourExt};
+
+/// **Bevy** Plugin that outlines every cost region of the environment on the
+/// ground
+pub struct RegionVisualiserPlugin;
+
+impl Plugin for RegionVisualiserPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, draw_regions.run_if(enabled));
+    }
+}
+
+#[inline]
+fn enabled(config: Res<Config>, environment: Res<Environment>) -> bool {
+    config.visualisation.draw.regions && !environment.regions.is_empty()
+}
+
+/// Draw the outline of every region, more opaque the higher its cost
+fn draw_regions(
+    mut gizmos: Gizmos,
+    environment: Res<Environment>,
+    config: Res<Config>,
+    theme: Res<CatppuccinTheme>,
+) {
+    let height = Z_FIGHTING_OFFSET - config.visualisation.height.objects;
+    for region in &environment.regions {
+        let Some(first) = region.points.first() else {
+            continue;
+        };
+        let alpha = (region.cost / 4.0).clamp(0.25, 1.0);
+        let points = region
+            .points
+            .iter()
+            .chain(std::iter::once(first))
+            .map(|point| {
+                let position = environment.centered(*point);
+                Vec3::new(position.x, height, position.y)
+            });
+        gizmos.linestrip(
+            points,
+            Color::from_catppuccin_colour_with_alpha(theme.mauve(), alpha),
+        );
+    }
+}
@@ -0,0 +1,160 @@
+//! **Bevy** Plugin to visualise the size and safety distance of each robot
+use bevy::prelude::*;
+use gbp_config::{Config, DrawSetting};
+
+use super::{RobotTracker, Z_FIGHTING_OFFSET};
+use crate::{
+    bevy_utils::run_conditions::event_exists,
+    input::DrawSettingsEvent,
+    planner::{robot::Radius, RobotConnections},
+    simulation_loader,
+    theme::{CatppuccinTheme, ColorAssociation, ColorFromCatppuccinColourExt},
+};
+
+/// **Bevy** Plugin that draws a translucent disc of radius `robot.radius`
+/// under each robot, and a ring at the interrobot safety distance around it,
+/// making near collisions visible
+pub struct SafetyDiscVisualiserPlugin;
+
+impl Plugin for SafetyDiscVisualiserPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                init_safety_discs,
+                update_safety_discs,
+                draw_safety_rings.run_if(enabled),
+                show_or_hide_safety_discs.run_if(event_exists::<DrawSettingsEvent>),
+            ),
+        );
+    }
+}
+
+#[inline]
+fn enabled(config: Res<Config>) -> bool {
+    config.visualisation.draw.safety_discs
+}
+
+/// **Bevy** [`Component`] to mark an entity as a visualised safety disc
+#[derive(Component)]
+pub struct SafetyDiscVisualiser;
+
+/// **Bevy** [`Component`] to mark a robot that has a corresponding
+/// [`SafetyDiscVisualiser`] entity
+#[derive(Component)]
+pub struct HasSafetyDisc;
+
+/// Spawn a disc for every robot that does not have one yet
+fn init_safety_discs(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    robots: Query<(Entity, &Radius, &ColorAssociation), Without<HasSafetyDisc>>,
+    config: Res<Config>,
+    theme: Res<CatppuccinTheme>,
+) {
+    for (robot, radius, color_association) in &robots {
+        commands.entity(robot).insert(HasSafetyDisc);
+
+        let material = materials.add(StandardMaterial {
+            base_color: Color::from_catppuccin_colour_with_alpha(
+                theme.get_display_colour(&color_association.name),
+                0.25,
+            ),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..Default::default()
+        });
+
+        commands.spawn((
+            RobotTracker::new(robot),
+            SafetyDiscVisualiser,
+            PbrBundle {
+                mesh: meshes.add(Circle::new(radius.0)),
+                material,
+                // placed by `update_safety_discs`
+                transform: Transform::from_rotation(Quat::from_rotation_x(
+                    -std::f32::consts::FRAC_PI_2,
+                )),
+                visibility: if config.visualisation.draw.safety_discs {
+                    Visibility::Visible
+                } else {
+                    Visibility::Hidden
+                },
+                ..Default::default()
+            },
+            simulation_loader::Reloadable,
+        ));
+    }
+}
+
+/// Move every disc under its robot, and despawn discs of despawned robots
+fn update_safety_discs(
+    mut commands: Commands,
+    mut discs: Query<(Entity, &RobotTracker, &mut Transform), With<SafetyDiscVisualiser>>,
+    robots: Query<&Transform, (With<RobotConnections>, Without<SafetyDiscVisualiser>)>,
+    config: Res<Config>,
+) {
+    for (disc, tracker, mut transform) in &mut discs {
+        let Ok(robot_transform) = robots.get(tracker.robot_id) else {
+            commands.entity(disc).despawn();
+            continue;
+        };
+
+        transform.translation = Vec3::new(
+            robot_transform.translation.x,
+            Z_FIGHTING_OFFSET - config.visualisation.height.objects,
+            robot_transform.translation.z,
+        );
+    }
+}
+
+/// Draw a ring at the interrobot safety distance around every robot
+fn draw_safety_rings(
+    mut gizmos: Gizmos,
+    robots: Query<(&Transform, &Radius, &ColorAssociation), With<RobotConnections>>,
+    config: Res<Config>,
+    theme: Res<CatppuccinTheme>,
+) {
+    let multiplier = config.robot.inter_robot_safety_distance_multiplier.get();
+    for (transform, radius, color_association) in &robots {
+        let position = Vec3::new(
+            transform.translation.x,
+            Z_FIGHTING_OFFSET - config.visualisation.height.objects,
+            transform.translation.z,
+        );
+        gizmos
+            .circle(
+                position,
+                Direction3d::Y,
+                multiplier * radius.0,
+                Color::from_catppuccin_colour_with_alpha(
+                    theme.get_display_colour(&color_association.name),
+                    0.6,
+                ),
+            )
+            .segments(32);
+    }
+}
+
+/// **Bevy** [`Update`] system
+/// Reads [`DrawSettingsEvent`], where if `DrawSettingsEvent.setting ==
+/// DrawSetting::SafetyDiscs` the boolean `DrawSettingsEvent.draw` will be used
+/// to set the visibility of the [`SafetyDiscVisualiser`] entities
+fn show_or_hide_safety_discs(
+    mut discs: Query<&mut Visibility, With<SafetyDiscVisualiser>>,
+    mut evr_draw_settings: EventReader<DrawSettingsEvent>,
+) {
+    for event in evr_draw_settings.read() {
+        if matches!(event.setting, DrawSetting::SafetyDiscs) {
+            let new_visibility_state = if event.draw {
+                Visibility::Visible
+            } else {
+                Visibility::Hidden
+            };
+            for mut visibility in &mut discs {
+                *visibility = new_visibility_state;
+            }
+        }
+    }
+}
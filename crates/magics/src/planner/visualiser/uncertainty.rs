@@ -2,12 +2,11 @@
 use bevy::prelude::*;
 use gbp_config::Config;
 
-use super::{RobotTracker, Z_FIGHTING_OFFSET};
+use super::{drawn_for, RobotTracker, Z_FIGHTING_OFFSET};
 use crate::{
     asset_loader::Materials,
-    bevy_utils::run_conditions::event_exists,
     factorgraph::prelude::FactorGraph,
-    input::DrawSettingsEvent,
+    planner::spawner::SelectedRobot,
     simulation_loader,
     theme::{CatppuccinTheme, ColorAssociation, ColorFromCatppuccinColourExt},
 };
@@ -25,7 +24,7 @@ impl Plugin for UncertaintyVisualiserPlugin {
                 Update,
                 (
                     init_uncertainty,
-                    show_or_hide_uncertainty.run_if(event_exists::<DrawSettingsEvent>),
+                    show_or_hide_uncertainty,
                     // show_or_hide_uncertainty.run_if(event_exists::<DrawSetting<Uncertainty>>),
                     update_uncertainty.run_if(uncertainty_visualizer_enabled),
                     // update_velocity_uncertainty,
@@ -195,7 +194,11 @@ fn init_uncertainty(
 /// that have matching [`Entity`] with the `RobotTracker.robot_id`
 /// and variables in the [`FactorGraph`] that have matching
 /// `RobotTracker.variable_id`
-#[allow(clippy::type_complexity, clippy::cast_possible_truncation)]
+#[allow(
+    clippy::type_complexity,
+    clippy::cast_possible_truncation,
+    clippy::too_many_arguments
+)]
 fn update_uncertainty(
     mut tracker_query: Query<
         (
@@ -213,9 +216,16 @@ fn update_uncertainty(
     materials: Res<Materials>,
     // scene_assets: Res<SceneAssets>,
     theme: Res<CatppuccinTheme>,
+    selected_robot: Res<SelectedRobot>,
 ) {
+    let only_selected = config.visualisation.only_selected.uncertainty;
     // Update the `RobotTracker` components
     for (tracker, mut transform, mut mesh, mut material) in &mut tracker_query {
+        // hidden anyway, so skip creating new meshes and materials for it
+        if !drawn_for(tracker.robot_id, true, only_selected, &selected_robot) {
+            continue;
+        }
+
         for (entity, factorgraph, color_association) in factorgraph_query.iter() {
             // continue if we're not looking at the right robot
             if tracker.robot_id != entity {
@@ -297,27 +307,27 @@ fn update_uncertainty(
 }
 
 /// A **Bevy** [`Update`] system
-/// Reads [`DrawSettingEvent`], where if `DrawSettingEvent.setting ==
-/// DrawSetting::Uncertainty` the boolean `DrawSettingEvent.value` will be used
-/// to set the visibility of the [`UncertaintyVisualiser`] entities
+/// Shows the [`UncertaintyVisualiser`] entities of the robots the uncertainty
+/// is drawn for, and hides the rest
 fn show_or_hide_uncertainty(
-    mut query: Query<(&UncertaintyVisualiser, &mut Visibility)>,
-    mut evr_draw_settings: EventReader<DrawSettingsEvent>,
+    mut query: Query<(&RobotTracker, &mut Visibility), With<UncertaintyVisualiser>>,
     mut enabled: ResMut<UncertaintyVisualizerEnabled>,
+    config: Res<Config>,
+    selected_robot: Res<SelectedRobot>,
 ) {
-    for event in evr_draw_settings.read() {
-        // debug!("received event to toggle draw visibility of gaussian uncertainty");
-        if matches!(event.setting, gbp_config::DrawSetting::Uncertainty) {
-            let new_visibility_state = if event.draw {
-                Visibility::Visible
-            } else {
-                Visibility::Hidden
-            };
-            enabled.0 = event.draw;
-            for (_, mut visibility) in &mut query {
-                *visibility = new_visibility_state;
-            }
-        }
+    let draw = config.visualisation.draw.uncertainty;
+    if enabled.0 != draw {
+        enabled.0 = draw;
+    }
+
+    let only_selected = config.visualisation.only_selected.uncertainty;
+    for (tracker, mut visibility) in &mut query {
+        let new_visibility = if drawn_for(tracker.robot_id, draw, only_selected, &selected_robot) {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+        visibility.set_if_neq(new_visibility);
     }
 }
 
@@ -0,0 +1,65 @@
+//! Visualize the current velocity of every robot
+use bevy::prelude::*;
+use gbp_config::Config;
+
+use crate::{
+    factorgraph::prelude::FactorGraph,
+    planner::robot::SpeedFactor,
+    theme::{CatppuccinTheme, ColorFromCatppuccinColourExt},
+};
+
+pub struct VelocityVisualizerPlugin;
+
+impl Plugin for VelocityVisualizerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, visualize_velocities.run_if(enabled));
+    }
+}
+
+/// **Bevy** run condition for drawing velocities
+fn enabled(config: Res<Config>) -> bool {
+    config.visualisation.draw.velocities
+}
+
+/// Time span of motion covered by an arrow, making the length of the arrow
+/// proportional to the speed of the robot
+/// SI unit: s
+const ARROW_SECONDS: f32 = 1.0;
+
+/// Draw an arrow from every robot along the velocity of its current state.
+/// The arrow is coloured from green to red by the speed of the robot relative
+/// to its target speed, so slow robots stand out
+fn visualize_velocities(
+    mut gizmos: Gizmos,
+    query: Query<(&FactorGraph, &Transform, Option<&SpeedFactor>)>,
+    config: Res<Config>,
+    theme: Res<CatppuccinTheme>,
+) {
+    let slow = Color::from_catppuccin_colour(theme.red());
+    let fast = Color::from_catppuccin_colour(theme.green());
+
+    for (factorgraph, transform, speed_factor) in &query {
+        let Some((_, variable)) = factorgraph.first_variable() else {
+            continue;
+        };
+
+        #[allow(clippy::cast_possible_truncation)]
+        let velocity = {
+            let [x, y] = variable.estimated_velocity();
+            Vec2::new(x as f32, y as f32)
+        };
+
+        let max_speed =
+            config.robot.target_speed.get() * speed_factor.map_or(1.0, |factor| factor.0);
+        let ratio = (velocity.length() / max_speed).clamp(0.0, 1.0);
+        let color = Color::rgb_linear_from_array(
+            slow.rgb_linear_to_vec3()
+                .lerp(fast.rgb_linear_to_vec3(), ratio)
+                .to_array(),
+        );
+
+        let start = transform.translation;
+        let end = start + velocity.extend(0.0).xzy() * ARROW_SECONDS;
+        gizmos.arrow(start, end, color);
+    }
+}
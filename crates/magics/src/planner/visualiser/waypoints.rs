@@ -1,36 +1,58 @@
 //! **Bevy** Plugin to visualize robot waypoints
+//!
+//! The remaining waypoints of every robot are drawn as numbered markers in the
+//! colour of the robot, connected by the path the robot is going to follow.
+//! The final goal of a robot is drawn larger and with a double ring. A reached
+//! waypoint does not disappear at once, but fades out over
+//! [`FADE_DURATION`] seconds.
 use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
 use gbp_config::{Config, DrawSetting};
 use itertools::Itertools;
 
 use crate::{
-    // asset_loader::SceneAssets,
-    asset_loader::{Materials, Meshes},
     bevy_utils::run_conditions::event_exists,
+    environment::camera::MainCamera,
     input::DrawSettingsEvent,
     planner::{
-        robot::{Mission, RobotReachedWaypoint},
-        spawner::WaypointCreated,
-        RobotId,
+        lifecycle::RobotReachedWaypoint,
+        robot::{Mission, Radius},
+    },
+    simulation_loader::{LoadSimulation, ReloadSimulation},
+    theme::{
+        CatppuccinTheme, ColorAssociation, ColorFromCatppuccinColourExt, FromCatppuccinColourExt,
     },
-    simulation_loader,
-    theme::ColorAssociation,
 };
 
+/// Seconds of simulated time it takes a reached waypoint to fade out
+pub const FADE_DURATION: f32 = 2.0;
+
+/// Alpha of the markers of waypoints that have not been reached yet
+const MARKER_ALPHA: f32 = 0.75;
+
+/// Radius of a waypoint marker, relative to the radius of the robot
+const MARKER_SCALE: f32 = 0.5;
+
 /// **Bevy** Plugin to visualize robot waypoints
 pub struct WaypointVisualiserPlugin;
 
 impl Plugin for WaypointVisualiserPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
+        app.init_resource::<ReachedWaypoints>().add_systems(
             Update,
             (
-                // remove_when_robot_reached_waypoint,
-                create_waypoint_visualizer,
-                visualize_waypoints.run_if(enabled),
-                // delete_mesh_of_reached_waypoints,
+                clear_reached_waypoints
+                    .run_if(on_event::<LoadSimulation>().or_else(on_event::<ReloadSimulation>())),
+                track_reached_waypoints,
+                (
+                    visualize_waypoints,
+                    visualize_reached_waypoints,
+                    label_waypoints,
+                )
+                    .run_if(enabled),
                 show_or_hide_waypoint_visualizers.run_if(event_exists::<DrawSettingsEvent>),
-            ),
+            )
+                .chain(),
         );
     }
 }
@@ -39,96 +61,174 @@ fn enabled(config: Res<Config>) -> bool {
     config.visualisation.draw.waypoints
 }
 
+/// A waypoint that has been reached, and is fading out
+#[derive(Debug, Clone, Copy)]
+struct ReachedWaypoint {
+    position:   Vec2,
+    /// Radius of the marker
+    radius:     f32,
+    colour:     catppuccin::Colour,
+    /// Simulated time at which the waypoint was reached
+    reached_at: f32,
+}
+
+impl ReachedWaypoint {
+    /// How visible the marker is at `now`, from `1.0` when the waypoint was
+    /// just reached to `0.0` when it has faded out
+    fn visibility(&self, now: f32) -> f32 {
+        (1.0 - (now - self.reached_at) / FADE_DURATION).clamp(0.0, 1.0)
+    }
+}
+
+/// **Bevy** [`Resource`] with the waypoints that are fading out
+#[derive(Debug, Default, Resource)]
+struct ReachedWaypoints(Vec<ReachedWaypoint>);
+
+fn clear_reached_waypoints(mut reached_waypoints: ResMut<ReachedWaypoints>) {
+    reached_waypoints.0.clear();
+}
+
+/// Start fading out the waypoints reached by the robots, and forget the ones
+/// that have faded out
+fn track_reached_waypoints(
+    mut evr_robot_reached_waypoint: EventReader<RobotReachedWaypoint>,
+    mut reached_waypoints: ResMut<ReachedWaypoints>,
+    robots: Query<(&ColorAssociation, &Radius)>,
+    theme: Res<CatppuccinTheme>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed_seconds();
+    reached_waypoints
+        .0
+        .retain(|reached| reached.visibility(now) > 0.0);
+
+    for event in evr_robot_reached_waypoint.read() {
+        // the robot might already be despawned, if it reached its final waypoint
+        let Ok((color_assoc, radius)) = robots.get(event.robot_id) else {
+            continue;
+        };
+        reached_waypoints.0.push(ReachedWaypoint {
+            position:   event.position,
+            radius:     radius.0 * MARKER_SCALE,
+            colour:     theme.get_display_colour(&color_assoc.name),
+            reached_at: now,
+        });
+    }
+}
+
+/// Draw a waypoint marker at `position`. The final goal of a robot is drawn
+/// larger, with a second ring around it
+fn draw_marker(gizmos: &mut Gizmos, position: Vec3, radius: f32, is_goal: bool, color: Color) {
+    if is_goal {
+        gizmos.circle(position, Direction3d::Y, 2.0 * radius, color);
+        gizmos.circle(position, Direction3d::Y, 1.5 * radius, color);
+    } else {
+        gizmos.circle(position, Direction3d::Y, radius, color);
+    }
+}
+
 fn visualize_waypoints(
     mut gizmos: Gizmos,
-    missions: Query<(&Mission, &ColorAssociation)>,
+    robots: Query<(&Transform, &Mission, &ColorAssociation, &Radius)>,
     config: Res<Config>,
-    theme: Res<crate::theme::CatppuccinTheme>,
+    theme: Res<CatppuccinTheme>,
 ) {
-    use crate::theme::ColorFromCatppuccinColourExt;
-
     let height = -config.visualisation.height.objects;
-    for (mission, color_assoc) in &missions {
+    for (transform, mission, color_assoc, radius) in &robots {
         let colour = theme.get_display_colour(&color_assoc.name);
-        let color = Color::from_catppuccin_colour_with_alpha(colour, 0.5);
-        // let color = theme.from_catppuccin_colour(color_assoc.name.);
-        for (wp1, wp2) in mission.waypoints().tuple_windows() {
-            gizmos.line(
-                wp1.position().extend(height).xzy(),
-                wp2.position().extend(height).xzy(),
-                color,
-                // Color::RED,
+        let path_color = Color::from_catppuccin_colour_with_alpha(colour, 0.5);
+        let marker_color = Color::from_catppuccin_colour_with_alpha(colour, MARKER_ALPHA);
+
+        let waypoints = mission
+            .remaining_waypoints()
+            .iter()
+            .map(|waypoint| waypoint.position().extend(height).xzy())
+            .collect::<Vec<_>>();
+
+        let robot_position = transform.translation.xz().extend(height).xzy();
+        for (from, to) in std::iter::once(robot_position)
+            .chain(waypoints.iter().copied())
+            .tuple_windows()
+        {
+            gizmos.line(from, to, path_color);
+        }
+
+        let goal = waypoints.len().saturating_sub(1);
+        for (index, &position) in waypoints.iter().enumerate() {
+            draw_marker(
+                &mut gizmos,
+                position,
+                radius.0 * MARKER_SCALE,
+                index == goal,
+                marker_color,
             );
         }
     }
 }
 
-fn remove_when_robot_reached_waypoint(
-    mut commands: Commands,
-    mut evr_robot_reached_waypoint: EventReader<RobotReachedWaypoint>,
-    // mut evw_waypoint_reached: EventWriter<RobotReachedWaypoint>,
-    waypoint_visualizers: Query<(Entity, &AssociatedWithRobot), With<WaypointVisualiser>>,
+fn visualize_reached_waypoints(
+    mut gizmos: Gizmos,
+    reached_waypoints: Res<ReachedWaypoints>,
+    config: Res<Config>,
+    time: Res<Time>,
 ) {
-    for event in evr_robot_reached_waypoint.read() {
-        // Find the entity id of the waypoint visualizer that has just been reached
-        if let Some(waypoint_id) = waypoint_visualizers
-            .iter()
-            .find(|(_, AssociatedWithRobot(robot_id))| *robot_id == event.robot_id)
-            .map(|(entity, _)| entity)
-        {
-            commands.entity(waypoint_id).despawn();
-        };
+    let height = -config.visualisation.height.objects;
+    let now = time.elapsed_seconds();
+    for reached in &reached_waypoints.0 {
+        let color = Color::from_catppuccin_colour_with_alpha(
+            reached.colour,
+            MARKER_ALPHA * reached.visibility(now),
+        );
+        draw_marker(
+            &mut gizmos,
+            reached.position.extend(height).xzy(),
+            reached.radius,
+            false,
+            color,
+        );
     }
 }
 
-// /// **Bevy** system to delete the mesh of the allocated waypoint visualizer
-// /// whenever the waypoint has been reached.
-// fn delete_mesh_of_reached_waypoints(
-//     mut commands: Commands,
-//     mut evr_delete_waypoint: EventReader<RobotReachedWaypoint>,
-// ) {
-//     for RobotReachedWaypoint(vis) in evr_delete_waypoint.read() {
-//         commands.entity(*vis).despawn();
-//     }
-// }
-
-/// **Bevy** Component to store an association to a robot.
-/// Used to make it easier to retrieve the entity id, of the robot
-/// a visualizer is associated with.
-#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
-struct AssociatedWithRobot(pub RobotId);
-
-fn create_waypoint_visualizer(
-    mut commands: Commands,
+/// Number the remaining waypoints of every robot, starting from 1 at the next
+/// waypoint
+fn label_waypoints(
+    mut contexts: EguiContexts,
+    robots: Query<(&Mission, &ColorAssociation)>,
+    cameras: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
     config: Res<Config>,
-    // scene_assets: Res<SceneAssets>,
-    meshes: Res<Meshes>,
-    materials: Res<Materials>,
-    mut evr_waypoint_created: EventReader<WaypointCreated>,
+    theme: Res<CatppuccinTheme>,
 ) {
-    for event in evr_waypoint_created.read() {
-        let transform = Transform::from_translation(Vec3::new(
-            event.position.x,
-            -config.visualisation.height.objects,
-            event.position.y,
-        ));
-
-        commands.spawn((
-            simulation_loader::Reloadable,
-            WaypointVisualiser,
-            AssociatedWithRobot(event.for_robot),
-            PbrBundle {
-                mesh: meshes.waypoint.clone(),
-                material: materials.waypoint.clone(),
-                transform,
-                visibility: if config.visualisation.draw.waypoints {
-                    Visibility::Visible
-                } else {
-                    Visibility::Hidden
-                },
-                ..default()
-            },
-        ));
+    let Ok((camera, camera_transform)) = cameras.get_single() else {
+        return;
+    };
+
+    let height = -config.visualisation.height.objects;
+    let painter = contexts
+        .ctx_mut()
+        .layer_painter(egui::LayerId::background());
+    for (mission, color_assoc) in &robots {
+        let colour =
+            egui::Color32::from_catppuccin_colour(theme.get_display_colour(&color_assoc.name));
+        let waypoints = mission.remaining_waypoints();
+        let goal = waypoints.len().saturating_sub(1);
+        for (index, waypoint) in waypoints.iter().enumerate() {
+            let position = waypoint.position().extend(height).xzy();
+            let Some(viewport) = camera.world_to_viewport(camera_transform, position) else {
+                continue;
+            };
+            let font = if index == goal {
+                egui::FontId::proportional(16.0)
+            } else {
+                egui::FontId::proportional(12.0)
+            };
+            painter.text(
+                egui::pos2(viewport.x, viewport.y),
+                egui::Align2::CENTER_CENTER,
+                index + 1,
+                font,
+                colour,
+            );
+        }
     }
 }
 
@@ -156,3 +256,22 @@ fn show_or_hide_waypoint_visualizers(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reached_waypoints_fade_out() {
+        let reached = ReachedWaypoint {
+            position:   Vec2::ZERO,
+            radius:     1.0,
+            colour:     catppuccin::Flavour::Mocha.red(),
+            reached_at: 1.0,
+        };
+        assert!((reached.visibility(1.0) - 1.0).abs() < f32::EPSILON);
+        assert!((reached.visibility(1.0 + FADE_DURATION / 2.0) - 0.5).abs() < 1e-6);
+        assert!(reached.visibility(1.0 + FADE_DURATION).abs() < f32::EPSILON);
+        assert!(reached.visibility(100.0).abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,646 @@
+//! Module for running a list of simulations one after another, e.g. for
+//! unattended evaluation runs. A report is exported after each entry, and the
+//! application exits when the last entry has finished.
+//!
+//! Running the same simulation with several seeds and
+//! `simulation.perturbation` set makes a Monte Carlo study of it. The
+//! [`SuccessRate`] of each simulation across its seeds, together with the
+//! mean collisions, makespan and distance travelled of its runs, can be
+//! written to a summary file with [`Playlist::with_summary`].
+
+use std::{
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use gbp_config::{Config, ConfigOverride};
+use itertools::Itertools;
+
+use crate::{
+    export,
+    planner::{
+        collisions::resources::{RobotEnvironmentCollisions, RobotRobotCollisions},
+        spawner::AllFormationsFinished,
+        RobotConnections,
+    },
+    simulation_loader::{LoadSimulation, ReloadSimulation, SimulationManager},
+};
+
+/// Plugin that runs every entry of a [`Playlist`] to completion
+pub struct PlaylistPlugin {
+    playlist: Playlist,
+}
+
+impl PlaylistPlugin {
+    #[must_use]
+    pub const fn new(playlist: Playlist) -> Self {
+        Self { playlist }
+    }
+}
+
+impl Plugin for PlaylistPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.playlist.clone())
+            .init_resource::<DistanceTravelled>()
+            .add_systems(Startup, start_playlist)
+            .add_systems(FixedPostUpdate, track_distance_travelled)
+            .add_systems(
+                Update,
+                (
+                    entry_started.run_if(
+                        on_event::<LoadSimulation>().or_else(on_event::<ReloadSimulation>()),
+                    ),
+                    entry_finished.run_if(
+                        on_event::<AllFormationsFinished>().or_else(virtual_time_exceeds_max_time),
+                    ),
+                    advance_playlist,
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// A single simulation to run as part of a [`Playlist`]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PlaylistEntry {
+    /// Name of the simulation, i.e. the name of its directory
+    pub simulation: String,
+    /// Seed to use instead of the one in the config of the simulation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed:       Option<u64>,
+    /// Changes to the config of the simulation, e.g. from a
+    /// [`Sweep`](crate::sweep::Sweep)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides:  Vec<ConfigOverride>,
+}
+
+impl PlaylistEntry {
+    #[must_use]
+    pub fn new(simulation: impl Into<String>, seed: Option<u64>) -> Self {
+        Self {
+            simulation: simulation.into(),
+            seed,
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Apply `overrides` to the config of the simulation
+    #[must_use]
+    pub fn with_overrides(mut self, overrides: Vec<ConfigOverride>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+}
+
+impl std::fmt::Display for PlaylistEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.seed {
+            Some(seed) => write!(f, "{} (seed {})", self.simulation, seed)?,
+            None => write!(f, "{}", self.simulation)?,
+        }
+        if !self.overrides.is_empty() {
+            write!(f, " [{}]", self.overrides.iter().join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// How a run of a [`PlaylistEntry`] went
+#[derive(Debug, Clone, Copy, serde::Serialize)]
+pub struct Outcome {
+    /// Whether all formations finished before `simulation.max-time`
+    pub finished: bool,
+    pub robot_collisions: usize,
+    pub environment_collisions: usize,
+    /// Simulated time when the run finished. SI unit: s
+    pub makespan: f64,
+    /// Distance travelled by all robots together. SI unit: m
+    pub distance_travelled: f64,
+}
+
+impl Outcome {
+    /// A run succeeded if all formations finished without any collisions
+    #[must_use]
+    pub const fn succeeded(&self) -> bool {
+        self.finished && self.robot_collisions == 0 && self.environment_collisions == 0
+    }
+}
+
+/// A finished entry of a [`Playlist`], and the report exported for it
+#[derive(Debug, Clone, serde::Serialize)]
+struct IndexEntry {
+    #[serde(flatten)]
+    entry:   PlaylistEntry,
+    /// `None` if the export failed
+    export:  Option<PathBuf>,
+    #[serde(flatten)]
+    outcome: Outcome,
+}
+
+/// The fraction of the runs of a simulation that succeeded, across all seeds
+/// it was run with, and the mean [`Outcome`] of the runs
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SuccessRate {
+    pub simulation: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<ConfigOverride>,
+    pub runs: usize,
+    pub succeeded: usize,
+    pub rate: f64,
+    pub mean_robot_collisions: f64,
+    pub mean_environment_collisions: f64,
+    /// SI unit: s
+    pub mean_makespan: f64,
+    /// SI unit: m
+    pub mean_distance_travelled: f64,
+}
+
+impl SuccessRate {
+    fn new(simulation: String, overrides: Vec<ConfigOverride>) -> Self {
+        Self {
+            simulation,
+            overrides,
+            runs: 0,
+            succeeded: 0,
+            rate: 0.0,
+            mean_robot_collisions: 0.0,
+            mean_environment_collisions: 0.0,
+            mean_makespan: 0.0,
+            mean_distance_travelled: 0.0,
+        }
+    }
+
+    /// Add the outcome of another run, updating the rate and the means
+    #[allow(clippy::cast_precision_loss)]
+    fn add(&mut self, outcome: &Outcome) {
+        let mean = |mean: f64, value: f64, runs: usize| mean + (value - mean) / runs as f64;
+
+        self.runs += 1;
+        self.succeeded += usize::from(outcome.succeeded());
+        self.rate = self.succeeded as f64 / self.runs as f64;
+        self.mean_robot_collisions = mean(
+            self.mean_robot_collisions,
+            outcome.robot_collisions as f64,
+            self.runs,
+        );
+        self.mean_environment_collisions = mean(
+            self.mean_environment_collisions,
+            outcome.environment_collisions as f64,
+            self.runs,
+        );
+        self.mean_makespan = mean(self.mean_makespan, outcome.makespan, self.runs);
+        self.mean_distance_travelled = mean(
+            self.mean_distance_travelled,
+            outcome.distance_travelled,
+            self.runs,
+        );
+    }
+}
+
+impl std::fmt::Display for SuccessRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.simulation)?;
+        if !self.overrides.is_empty() {
+            write!(f, " [{}]", self.overrides.iter().join(", "))?;
+        }
+        write!(
+            f,
+            ": {}/{} runs succeeded ({:.1}%), mean makespan {:.2} s, mean distance travelled \
+             {:.2} m, mean collisions {:.2} robot-robot {:.2} robot-environment",
+            self.succeeded,
+            self.runs,
+            self.rate * 100.0,
+            self.mean_makespan,
+            self.mean_distance_travelled,
+            self.mean_robot_collisions,
+            self.mean_environment_collisions,
+        )
+    }
+}
+
+/// Where the playlist is in running the current entry
+#[derive(Debug, Clone, Default)]
+enum PlaylistState {
+    /// Waiting for the current entry to be loaded
+    #[default]
+    Loading,
+    /// The current entry is running
+    Running,
+    /// The current entry has finished, and the next one is started when the
+    /// timer finishes, giving the export time to complete
+    Finished(Timer, Outcome),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PlaylistError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid seed on line {line}: {seed}")]
+    InvalidSeed { line: usize, seed: String },
+    #[error("the playlist is empty")]
+    Empty,
+}
+
+/// **Bevy** [`Resource`]
+/// Simulations to run one after another
+#[derive(Debug, Clone, Resource)]
+pub struct Playlist {
+    entries:  VecDeque<PlaylistEntry>,
+    current:  Option<PlaylistEntry>,
+    state:    PlaylistState,
+    /// File to write the index of finished entries to
+    index:    Option<PathBuf>,
+    /// File to write the success rate of each simulation to
+    summary:  Option<PathBuf>,
+    finished: Vec<IndexEntry>,
+}
+
+impl Playlist {
+    /// Create a playlist running each of `simulations` once, in order
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `simulations` is empty
+    pub fn new(simulations: impl IntoIterator<Item = String>) -> Result<Self, PlaylistError> {
+        Self::from_entries(
+            simulations
+                .into_iter()
+                .map(|simulation| PlaylistEntry::new(simulation, None)),
+        )
+    }
+
+    /// Create a playlist from a list of entries
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `entries` is empty
+    pub fn from_entries(
+        entries: impl IntoIterator<Item = PlaylistEntry>,
+    ) -> Result<Self, PlaylistError> {
+        let entries: VecDeque<_> = entries.into_iter().collect();
+        if entries.is_empty() {
+            return Err(PlaylistError::Empty);
+        }
+
+        Ok(Self {
+            entries,
+            current: None,
+            state: PlaylistState::default(),
+            index: None,
+            summary: None,
+            finished: Vec::new(),
+        })
+    }
+
+    /// After each entry, write an index of the finished entries and the files
+    /// their reports were exported to, as JSON to `path`
+    #[must_use]
+    pub fn with_index(mut self, path: impl Into<PathBuf>) -> Self {
+        self.index = Some(path.into());
+        self
+    }
+
+    /// Write the index of the finished entries, if enabled with
+    /// [`Playlist::with_index`]
+    fn write_index(&self) -> std::io::Result<()> {
+        let Some(ref path) = self.index else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&self.finished)?;
+        std::fs::write(path, json)
+    }
+
+    /// After each entry, write the [`SuccessRate`] of every simulation, and
+    /// set of config overrides, run so far, as JSON to `path`
+    #[must_use]
+    pub fn with_summary(mut self, path: impl Into<PathBuf>) -> Self {
+        self.summary = Some(path.into());
+        self
+    }
+
+    /// Write the success rates, if enabled with [`Playlist::with_summary`]
+    fn write_summary(&self) -> std::io::Result<()> {
+        let Some(ref path) = self.summary else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(&self.success_rates())?;
+        std::fs::write(path, json)
+    }
+
+    /// The success rate of each simulation, and set of config overrides,
+    /// across the seeds it has been run with so far. In the order they were
+    /// first run
+    #[must_use]
+    pub fn success_rates(&self) -> Vec<SuccessRate> {
+        let mut rates: Vec<SuccessRate> = Vec::new();
+        for IndexEntry { entry, outcome, .. } in &self.finished {
+            let index = rates
+                .iter()
+                .position(|rate| {
+                    rate.simulation == entry.simulation && rate.overrides == entry.overrides
+                })
+                .unwrap_or_else(|| {
+                    rates.push(SuccessRate::new(
+                        entry.simulation.clone(),
+                        entry.overrides.clone(),
+                    ));
+                    rates.len() - 1
+                });
+            rates[index].add(outcome);
+        }
+        rates
+    }
+
+    /// Read a playlist from a file, with one simulation per line, optionally
+    /// followed by a seed. Empty lines and lines starting with `#` are ignored.
+    ///
+    /// ```text
+    /// # simulation  seed
+    /// intersection  0
+    /// intersection  1
+    /// circle
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file can not be read, a seed is not a valid
+    /// integer, or the playlist is empty
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, PlaylistError> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents)
+    }
+
+    /// Parse a playlist, see [`Playlist::from_file`] for the format
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if a seed is not a valid integer, or the playlist is
+    /// empty
+    pub fn parse(contents: &str) -> Result<Self, PlaylistError> {
+        let entries = contents
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(line_number, line)| {
+                let mut words = line.split_whitespace();
+                let simulation = words.next().unwrap_or_default();
+                let seed = words
+                    .next()
+                    .map(|seed| {
+                        seed.parse::<u64>().map_err(|_| PlaylistError::InvalidSeed {
+                            line: line_number,
+                            seed: seed.to_string(),
+                        })
+                    })
+                    .transpose()?;
+                Ok(PlaylistEntry::new(simulation, seed))
+            })
+            .collect::<Result<Vec<_>, PlaylistError>>()?;
+
+        Self::from_entries(entries)
+    }
+
+    /// Run every entry without an explicit seed once for each of `seeds`
+    #[must_use]
+    pub fn with_seeds(mut self, seeds: &[u64]) -> Self {
+        if seeds.is_empty() {
+            return self;
+        }
+
+        self.entries = self
+            .entries
+            .into_iter()
+            .flat_map(|entry| match entry.seed {
+                Some(_) => vec![entry],
+                None => seeds
+                    .iter()
+                    .map(|&seed| PlaylistEntry {
+                        seed: Some(seed),
+                        ..entry.clone()
+                    })
+                    .collect(),
+            })
+            .collect();
+        self
+    }
+
+    /// The entry that will be run first
+    #[must_use]
+    pub fn first(&self) -> Option<&PlaylistEntry> {
+        self.current.as_ref().or_else(|| self.entries.front())
+    }
+
+    /// The entry currently running
+    #[must_use]
+    pub const fn current(&self) -> Option<&PlaylistEntry> {
+        self.current.as_ref()
+    }
+
+    /// Number of entries that have not been started yet
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The entries that have not finished yet, starting with the one
+    /// currently running
+    #[must_use]
+    pub fn pending(&self) -> Vec<PlaylistEntry> {
+        let current = match self.state {
+            PlaylistState::Finished(..) => None,
+            PlaylistState::Loading | PlaylistState::Running => self.current.clone(),
+        };
+        current
+            .into_iter()
+            .chain(self.entries.iter().cloned())
+            .collect()
+    }
+}
+
+/// Time given to the export of the finished entry, before the next is loaded
+const DELAY_BETWEEN_ENTRIES: Duration = Duration::from_secs(1);
+
+fn virtual_time_exceeds_max_time(time: Res<Time<Virtual>>, config: Res<Config>) -> bool {
+    time.elapsed_seconds() > config.simulation.max_time.get()
+}
+
+/// Start the first entry. The simulation loader loads it initially, so only
+/// the seed needs to be set
+fn start_playlist(
+    mut playlist: ResMut<Playlist>,
+    mut simulation_manager: ResMut<SimulationManager>,
+) {
+    let entries = std::mem::take(&mut playlist.entries);
+    playlist.entries = entries
+        .into_iter()
+        .filter(|entry| {
+            let exists = simulation_manager.id_from_name(&entry.simulation).is_some();
+            if !exists {
+                error!(
+                    "playlist: no simulation named {}, skipping",
+                    entry.simulation
+                );
+            }
+            exists
+        })
+        .collect();
+
+    let Some(first) = playlist.entries.pop_front() else {
+        error!("playlist: no simulations to run");
+        return;
+    };
+
+    if simulation_manager.active_name() != Some(first.simulation.as_str()) {
+        if let Some(id) = simulation_manager.id_from_name(&first.simulation) {
+            simulation_manager.load(id);
+        }
+    }
+    simulation_manager.override_prng_seed(first.seed);
+    simulation_manager.override_config(first.overrides.clone());
+    info!("playlist: running {}", first);
+    playlist.current = Some(first);
+}
+
+fn entry_started(
+    mut playlist: ResMut<Playlist>,
+    mut distance_travelled: ResMut<DistanceTravelled>,
+) {
+    if matches!(playlist.state, PlaylistState::Loading) {
+        playlist.state = PlaylistState::Running;
+        *distance_travelled = DistanceTravelled::default();
+    }
+}
+
+/// **Bevy** [`Resource`]
+/// Distance travelled by all robots of the current entry, including the ones
+/// that have already been despawned
+#[derive(Debug, Default, Resource)]
+struct DistanceTravelled {
+    total: f64,
+    /// Position of every robot at the previous fixed timestep
+    last:  HashMap<Entity, Vec2>,
+}
+
+fn track_distance_travelled(
+    mut distance_travelled: ResMut<DistanceTravelled>,
+    robots: Query<(Entity, &Transform), With<RobotConnections>>,
+) {
+    let DistanceTravelled { total, last } = &mut *distance_travelled;
+    let mut positions = HashMap::with_capacity(last.len());
+    for (entity, transform) in &robots {
+        let position = transform.translation.xz();
+        if let Some(previous) = last.get(&entity) {
+            *total += f64::from(previous.distance(position));
+        }
+        positions.insert(entity, position);
+    }
+    *last = positions;
+}
+
+/// Export a report of the finished entry. When all formations have finished
+/// the export plugin takes care of it already
+fn entry_finished(
+    mut playlist: ResMut<Playlist>,
+    mut evr_all_formations_finished: EventReader<AllFormationsFinished>,
+    mut evw_export: EventWriter<export::events::Export>,
+    robot_collisions: Res<RobotRobotCollisions>,
+    environment_collisions: Res<RobotEnvironmentCollisions>,
+    distance_travelled: Res<DistanceTravelled>,
+    time_virtual: Res<Time<Virtual>>,
+) {
+    if !matches!(playlist.state, PlaylistState::Running) {
+        return;
+    }
+
+    let all_formations_finished = evr_all_formations_finished.read().count() > 0;
+    if !all_formations_finished {
+        evw_export.send(export::events::Export::default());
+    }
+
+    if let Some(ref current) = playlist.current {
+        info!(
+            "playlist: finished {}, {} remaining",
+            current,
+            playlist.remaining()
+        );
+    }
+
+    let outcome = Outcome {
+        finished: all_formations_finished,
+        robot_collisions: robot_collisions.num_collisions(),
+        environment_collisions: environment_collisions.num_collisions(),
+        makespan: time_virtual.elapsed_seconds_f64(),
+        distance_travelled: distance_travelled.total,
+    };
+    playlist.state =
+        PlaylistState::Finished(Timer::new(DELAY_BETWEEN_ENTRIES, TimerMode::Once), outcome);
+}
+
+fn advance_playlist(
+    mut playlist: ResMut<Playlist>,
+    mut simulation_manager: ResMut<SimulationManager>,
+    mut evw_app_exit: EventWriter<bevy::app::AppExit>,
+    mut latest_export: ResMut<export::resources::LatestExport>,
+    time: Res<Time<Real>>,
+) {
+    let PlaylistState::Finished(ref mut timer, outcome) = playlist.state else {
+        return;
+    };
+
+    if !timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    if let Some(entry) = playlist.current.clone() {
+        // taken, so a failed export is not attributed to the previous file
+        let export = latest_export.take();
+        playlist.finished.push(IndexEntry {
+            entry,
+            export,
+            outcome,
+        });
+        if let Err(err) = playlist.write_index() {
+            error!("playlist: failed to write index: {err}");
+        }
+        if let Err(err) = playlist.write_summary() {
+            error!("playlist: failed to write summary: {err}");
+        }
+    }
+
+    // simulations can be removed from the simulations directory while the
+    // playlist is running
+    while let Some(next) = playlist.entries.pop_front() {
+        let is_active = simulation_manager.active_name() == Some(next.simulation.as_str());
+        let Some(id) = simulation_manager.id_from_name(&next.simulation) else {
+            error!(
+                "playlist: no simulation named {}, skipping",
+                next.simulation
+            );
+            continue;
+        };
+
+        info!("playlist: running {}", next);
+        simulation_manager.override_prng_seed(next.seed);
+        simulation_manager.override_config(next.overrides.clone());
+        if is_active {
+            simulation_manager.reload();
+        } else {
+            simulation_manager.load(id);
+        }
+
+        playlist.current = Some(next);
+        playlist.state = PlaylistState::Loading;
+        return;
+    }
+
+    if playlist.summary.is_some() {
+        for rate in playlist.success_rates() {
+            info!("playlist: {}", rate);
+        }
+    }
+    info!("playlist: all entries finished, exiting");
+    evw_app_exit.send(bevy::app::AppExit);
+}
@@ -1,5 +1,6 @@
 use std::{
     collections::{BTreeMap, VecDeque},
+    sync::Arc,
     time::Duration,
 };
 
@@ -9,10 +10,12 @@ use bevy::{
     time::common_conditions::{on_real_timer, on_timer},
 };
 use bevy_notify::{ToastEvent, ToastLevel, ToastOptions};
-use gbp_config::{Config, FormationGroup};
+use gbp_config::{Config, ConfigOverride, FormationGroup};
 use gbp_environment::Environment;
 use smol_str::SmolStr;
 
+use crate::{factorgraph::factor::obstacle::ObstacleShapes, manifest};
+
 /// Which simulation to load initially
 #[derive(Debug, Default)]
 pub enum InitialSimulation {
@@ -30,6 +33,11 @@ pub struct SimulationLoaderPlugin {
     pub show_toasts: bool,
     pub initial_simulation: InitialSimulation,
     pub reload_after: Option<Duration>,
+    /// Poll the simulations directory for added or removed simulations
+    pub watch_simulations_dir: bool,
+    /// Simulations constructed in code, added alongside the ones found in the
+    /// simulations directory
+    pub simulations: Vec<Simulation>,
 }
 
 impl Default for SimulationLoaderPlugin {
@@ -38,6 +46,8 @@ impl Default for SimulationLoaderPlugin {
             show_toasts: true,
             initial_simulation: InitialSimulation::FirstFoundInFolder,
             reload_after: None,
+            watch_simulations_dir: true,
+            simulations: Vec::new(),
         }
     }
 }
@@ -47,24 +57,281 @@ impl SimulationLoaderPlugin {
         self.reload_after = Some(duration);
         self
     }
+
+    pub fn watch_simulations_dir(mut self, watch: bool) -> Self {
+        self.watch_simulations_dir = watch;
+        self
+    }
+
+    /// Add a simulation constructed in code, e.g. from a procedurally
+    /// generated environment. It replaces a simulation of the same name in
+    /// the simulations directory
+    pub fn register(mut self, simulation: Simulation) -> Self {
+        self.simulations.push(simulation);
+        self
+    }
 }
 
 pub type SdfImage = image::ImageBuffer<image::Rgb<u8>, Vec<u8>>;
 pub type RawImage = image::ImageBuffer<image::Rgb<u8>, Vec<u8>>;
 
 #[derive(Debug, Clone, Resource, Deref, DerefMut)]
-pub struct Sdf(pub SdfImage);
+pub struct Sdf(pub Arc<SdfImage>);
 
 #[derive(Debug, Clone, Resource, Deref, DerefMut)]
 pub struct Raw(pub RawImage);
 
+/// The obstacles of the loaded environment, for the obstacle factors to
+/// measure analytically instead of from [`Sdf`]. `None` unless enabled with
+/// [`gbp_environment::SdfSettings::analytic`]
+#[derive(Debug, Clone, Default, Resource, Deref, DerefMut)]
+pub struct AnalyticSdf(pub Option<ObstacleShapes>);
+
+impl AnalyticSdf {
+    /// Measure the obstacles of `environment`, if its SDF settings ask for it
+    #[must_use]
+    pub fn new(environment: &Arc<Environment>) -> Self {
+        Self(
+            environment
+                .tiles
+                .settings
+                .sdf
+                .analytic
+                .then(|| ObstacleShapes::new(Arc::clone(environment))),
+        )
+    }
+}
+
 // #[derive(Debug)]
 // pub struct Simulations(HashMap<String, Simulation>);
 // #[derive(Resource)]
 // struct Simulations(BTreeMap<String, Simulation>);
 type Simulations = BTreeMap<String, Simulation>;
 
-const SIMULATIONS_DIR: &'static str = "./config/scenarios";
+pub const SIMULATIONS_DIR: &'static str = "./config/scenarios";
+
+/// How often the simulations directory is checked for added or removed
+/// simulations
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Read the config of the simulation that will be loaded initially, i.e. the
+/// one named `initial_simulation`, or the first one in the simulations
+/// directory. Used for settings that must be known before the app is built
+#[must_use]
+pub fn initial_config(initial_simulation: Option<&str>) -> Option<Config> {
+    let dir = match initial_simulation {
+        Some(name) => std::path::Path::new(SIMULATIONS_DIR).join(name),
+        None => std::fs::read_dir(SIMULATIONS_DIR)
+            .ok()?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .min()?,
+    };
+
+    Config::from_file(dir.join("config.toml")).ok()
+}
+
+/// Load a simulation from a directory containing a `config.toml`, an
+/// `environment.yaml` and a `formation.yaml`
+fn load_simulation_from_dir(dir: &std::path::Path) -> anyhow::Result<Simulation> {
+    let name = dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow::anyhow!("invalid simulation directory: {}", dir.display()))?
+        .to_string();
+
+    let config = Config::from_file(dir.join("config.toml"))
+        .map_err(|err| anyhow::anyhow!("failed to load config: {err}"))?;
+    let environment = Environment::from_file(dir.join("environment.yaml"))
+        .map_err(|err| anyhow::anyhow!("failed to load environment: {err}"))?;
+    let formation_group = FormationGroup::from_yaml_file(dir.join("formation.yaml"))
+        .map_err(|err| anyhow::anyhow!("failed to load formation: {err}"))?;
+
+    let sources = SIMULATION_FILES
+        .iter()
+        .map(|&file| {
+            let contents = std::fs::read(dir.join(file))?;
+            Ok((file.to_string(), manifest::sha256(&contents)))
+        })
+        .collect::<std::io::Result<_>>()?;
+
+    Ok(Simulation::new(name, config, environment, formation_group)?.with_sources(sources))
+}
+
+/// The files a simulation directory consists of
+const SIMULATION_FILES: [&str; 3] = ["config.toml", "environment.yaml", "formation.yaml"];
+
+/// Name of the simulation built into the binary, used when no valid
+/// simulations are found
+pub const DEFAULT_SIMULATION: &str = "Default";
+
+/// The simulation built into the binary, with the default config, environment
+/// and formation
+///
+/// # Panics
+///
+/// Panics if the SDF of the default environment can not be generated
+fn default_simulation() -> Simulation {
+    Simulation::new(
+        DEFAULT_SIMULATION,
+        Config::default(),
+        Environment::default(),
+        FormationGroup::default(),
+    )
+    .expect("the default simulation is valid")
+}
+
+/// **Bevy** [`Resource`] with the simulations in [`SIMULATIONS_DIR`] that
+/// failed to load, and why. The watcher only retries a failed simulation once
+/// its files have been modified, so a broken scenario is not loaded again on
+/// every poll
+#[derive(Debug, Default, Resource)]
+pub struct FailedSimulations(BTreeMap<String, FailedSimulation>);
+
+#[derive(Debug)]
+struct FailedSimulation {
+    error:    String,
+    /// See [`last_modified`], taken before the simulation was loaded
+    modified: Option<std::time::SystemTime>,
+}
+
+impl FailedSimulations {
+    /// The names of the simulations that failed to load, and why
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(name, failed)| (name.as_str(), failed.error.as_str()))
+    }
+
+    fn insert(
+        &mut self,
+        name: String,
+        modified: Option<std::time::SystemTime>,
+        err: &anyhow::Error,
+    ) {
+        self.0.insert(name, FailedSimulation {
+            error: err.to_string(),
+            modified,
+        });
+    }
+
+    /// Returns `true` if the simulation failed to load, and its files have not
+    /// been modified since
+    fn unchanged(&self, name: &str, modified: Option<std::time::SystemTime>) -> bool {
+        self.0
+            .get(name)
+            .is_some_and(|failed| failed.modified == modified)
+    }
+}
+
+/// When the directory of a simulation, or any of its files, was last
+/// modified. `None` if none of them can be read
+fn last_modified(dir: &std::path::Path) -> Option<std::time::SystemTime> {
+    std::iter::once(dir.to_path_buf())
+        .chain(SIMULATION_FILES.iter().map(|file| dir.join(file)))
+        .filter_map(|path| {
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .max()
+}
+
+/// Show a toast for every simulation that failed to load at startup
+fn report_failed_simulations(
+    failed: Res<FailedSimulations>,
+    simulation_manager: Res<SimulationManager>,
+    mut evw_toast: EventWriter<ToastEvent>,
+) {
+    for (name, err) in failed.iter() {
+        evw_toast.send(ToastEvent::error(format!(
+            "failed to load simulation {name}: {err}"
+        )));
+    }
+
+    if simulation_manager.is_registered(DEFAULT_SIMULATION)
+        && simulation_manager.names().count() == 1
+    {
+        evw_toast.send(ToastEvent::warning(
+            "no valid simulations found, using the default simulation",
+        ));
+    }
+}
+
+/// Scenarios compiled into the binary, so it can run without a simulations
+/// directory
+#[cfg(feature = "embed-simulations")]
+mod embedded {
+    use include_dir::{include_dir, Dir};
+
+    use super::{manifest, Config, Environment, FormationGroup, Simulation};
+
+    static SIMULATIONS: [(&str, Dir<'static>); 5] = [
+        (
+            "Circle Experiment",
+            include_dir!("$CARGO_MANIFEST_DIR/../../config/scenarios/Circle Experiment"),
+        ),
+        (
+            "Junction Twoway",
+            include_dir!("$CARGO_MANIFEST_DIR/../../config/scenarios/Junction Twoway"),
+        ),
+        (
+            "Merge",
+            include_dir!("$CARGO_MANIFEST_DIR/../../config/scenarios/Merge"),
+        ),
+        (
+            "Obstacle Shapes Showcase",
+            include_dir!("$CARGO_MANIFEST_DIR/../../config/scenarios/Obstacle Shapes Showcase"),
+        ),
+        (
+            "Structured Junction",
+            include_dir!("$CARGO_MANIFEST_DIR/../../config/scenarios/Structured Junction"),
+        ),
+    ];
+
+    fn read_file<'a>(dir: &'a Dir<'static>, file: &str) -> anyhow::Result<&'a str> {
+        dir.get_file(file)
+            .and_then(include_dir::File::contents_utf8)
+            .ok_or_else(|| anyhow::anyhow!("embedded simulation has no {file}"))
+    }
+
+    fn load(name: &str, dir: &Dir<'static>) -> anyhow::Result<Simulation> {
+        let config = Config::parse(read_file(dir, "config.toml")?)
+            .map_err(|err| anyhow::anyhow!("failed to parse config: {err}"))?;
+        let environment = Environment::parse(read_file(dir, "environment.yaml")?)
+            .map_err(|err| anyhow::anyhow!("failed to parse environment: {err}"))?;
+        let formation_group = FormationGroup::parse_from_yaml(read_file(dir, "formation.yaml")?)
+            .map_err(|err| anyhow::anyhow!("failed to parse formation: {err}"))?;
+
+        let sources = super::SIMULATION_FILES
+            .iter()
+            .map(|&file| {
+                let contents = read_file(dir, file)?;
+                Ok((file.to_string(), manifest::sha256(contents.as_bytes())))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Simulation::new(name, config, environment, formation_group)?.with_sources(sources))
+    }
+
+    /// Load all embedded simulations
+    ///
+    /// # Panics
+    ///
+    /// Panics if an embedded simulation is invalid, as they are checked in
+    /// with the source code
+    pub(super) fn simulations() -> Vec<Simulation> {
+        SIMULATIONS
+            .iter()
+            .map(|(name, dir)| {
+                load(name, dir).unwrap_or_else(|err| {
+                    panic!("failed to load embedded simulation {name:?}: {err}")
+                })
+            })
+            .collect()
+    }
+}
 
 impl SimulationLoaderPlugin {
     pub fn new(show_toasts: bool, initial_simulation: Option<String>) -> Self {
@@ -75,6 +342,8 @@ impl SimulationLoaderPlugin {
                     InitialSimulation::Name(name)
                 }),
             reload_after: None,
+            watch_simulations_dir: true,
+            simulations: Vec::new(),
             // reload_after: Some(Duration::from_secs(80)), // for experiments purposes to run
             // overnight
 
@@ -127,86 +396,97 @@ fn elapsed_virtual_time_exceeds(
 
 impl Plugin for SimulationLoaderPlugin {
     fn build(&self, app: &mut App) {
-        let reader =
-            std::fs::read_dir(SIMULATIONS_DIR).expect("failed to read simulation directory");
-
-        let simulations: BTreeMap<_, _> = reader
-            .map(|dir| {
-                let dir = dir.unwrap();
-                let name = dir
-                    .file_name()
-                    .into_string()
-                    .expect("failed to parse simulation name");
-                // println!("about to load: {name:?}");
-                let config_path = dir.path().join("config.toml");
-                let config = Config::from_file(config_path)
-                    .expect(format!("failed to load config for simulation: {name:?}").as_str());
-                let environment_path = dir.path().join("environment.yaml");
-                let environment = Environment::from_file(environment_path).expect(
-                    format!("failed to load environment for simulation: {name:?}").as_str(),
+        let reader = match std::fs::read_dir(SIMULATIONS_DIR) {
+            Ok(reader) => Some(reader),
+            Err(err) => {
+                error!(
+                    "failed to read simulation directory {}: {}",
+                    SIMULATIONS_DIR, err
                 );
-                let formation_path = dir.path().join("formation.yaml");
-                let formation = FormationGroup::from_yaml_file(formation_path)
-                    .expect(format!("failed to load formation for simulation: {name:?}").as_str());
-
-                // println!("name: {name:?}");
-                let sdf_image_buffer = env_to_png::env_to_sdf_image(
-                    &environment,
-                    env_to_png::PixelsPerTile::new(
-                        environment.tiles.settings.sdf.resolution as u32,
-                    ),
-                    env_to_png::Percentage::new(environment.tiles.settings.sdf.expansion),
-                    env_to_png::Percentage::new(environment.tiles.settings.sdf.blur),
-                )
-                .expect("it all just works");
-
-                // let sdf_path = PathBuf::new()
-                //     .join("crates/magics/assets/imgs/obstacles")
-                //     .join(format!("{}.sdf.png", config.environment_image));
-                // info!("sdf_path: {sdf_path:?}");
-                // let sdf_image_buffer =
-                // image::io::Reader::open(sdf_path).unwrap().decode().unwrap();
-                // println!(
-                //     "sdf_image_buffer: {:?} channels: {:?}",
-                //     sdf_image_buffer.dimensions(),
-                //     sdf_image_buffer.color()
-                // );
-
-                // let raw_path = PathBuf::new()
-                //     .join("crates/magics/assets/imgs/obstacles")
-                //     .join(format!("{}.png", config.environment_image));
-                // let raw_image_buffer =
-                // image::io::Reader::open(raw_path).unwrap().decode().unwrap();
-
-                let simulation = Simulation {
-                    name: name.clone(),
-                    config,
-                    environment,
-                    formation_group: formation,
-                    sdf: Sdf(sdf_image_buffer.into()),
-                    // raw: Raw(raw_image_buffer.into()),
-                };
+                None
+            }
+        };
 
-                // println!("loaded: {name:?}");
+        // invalid simulations are skipped, and reported once the app is running
+        let mut failed = FailedSimulations::default();
+        let mut simulations: BTreeMap<_, _> = reader
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                let entry = entry
+                    .inspect_err(|err| error!("failed to read simulation directory entry: {err}"))
+                    .ok()?;
+                if !entry.path().is_dir() {
+                    return None;
+                }
+                let Ok(name) = entry.file_name().into_string() else {
+                    error!(
+                        "simulation directory name is not valid UTF-8: {}",
+                        entry.path().display()
+                    );
+                    return None;
+                };
 
-                (name, simulation)
+                let modified = last_modified(&entry.path());
+                match load_simulation_from_dir(&entry.path()) {
+                    Ok(simulation) => Some((name, simulation)),
+                    Err(err) => {
+                        error!("failed to load simulation {name:?}: {err}");
+                        failed.insert(name, modified, &err);
+                        None
+                    }
+                }
             })
             .collect();
 
-        assert!(
-            !simulations.is_empty(),
-            "No simulations found in {}",
-            SIMULATIONS_DIR
-        );
+        // embedded simulations are treated as registered in code, so the
+        // watcher does not remove them for missing from the directory
+        #[cfg(feature = "embed-simulations")]
+        let mut embedded = if simulations.is_empty() {
+            warn!(
+                "no simulations found in {}, using the embedded simulations",
+                SIMULATIONS_DIR
+            );
+            embedded::simulations()
+        } else {
+            Vec::new()
+        };
+        #[cfg(not(feature = "embed-simulations"))]
+        let mut embedded = Vec::new();
 
-        let initial_simulation = match &self.initial_simulation {
-            InitialSimulation::FirstFoundInFolder => simulations
+        if simulations.is_empty() && embedded.is_empty() && self.simulations.is_empty() {
+            warn!(
+                "no valid simulations found in {}, using the default simulation",
+                SIMULATIONS_DIR
+            );
+            embedded.push(default_simulation());
+        }
+
+        let registered: Vec<SmolStr> = embedded
+            .iter()
+            .chain(&self.simulations)
+            .map(|simulation| SmolStr::from(simulation.name.as_str()))
+            .collect();
+        for simulation in embedded.into_iter().chain(self.simulations.iter().cloned()) {
+            simulations.insert(simulation.name.clone(), simulation);
+        }
+
+        let first_simulation = || {
+            simulations
                 .first_key_value()
-                .map(|(_, v)| v)
-                .expect("there is 1 or more simulations"),
-            InitialSimulation::Name(name) => {
-                simulations.get(name).expect("simulation with name exists")
-            }
+                .map(|(_, simulation)| simulation)
+                .expect("there is at least the default simulation")
+        };
+        let initial_simulation = match &self.initial_simulation {
+            InitialSimulation::FirstFoundInFolder => first_simulation(),
+            InitialSimulation::Name(name) => simulations.get(name).unwrap_or_else(|| {
+                error!("no simulation named {name:?}, loading the first simulation instead");
+                failed
+                    .0
+                    .entry(name.clone())
+                    .or_insert_with(|| "no simulation with this name".to_string());
+                first_simulation()
+            }),
         };
 
         // let initial_simulation = simulations.first_key_value().map(|(_, v)|
@@ -215,6 +495,7 @@ impl Plugin for SimulationLoaderPlugin {
         let config = initial_simulation.config.clone();
         let formation_group = initial_simulation.formation_group.clone();
         let environment = initial_simulation.environment.clone();
+        let analytic_sdf = AnalyticSdf::new(&Arc::new(environment.clone()));
         let sdf = initial_simulation.sdf.clone();
         // let raw = initial_simulation.raw.clone();
 
@@ -229,12 +510,17 @@ impl Plugin for SimulationLoaderPlugin {
             .insert_resource(formation_group)
             .insert_resource(environment)
             .insert_resource(sdf)
+            .insert_resource(analytic_sdf)
             // .insert_resource(raw)
             .add_event::<ReloadSimulation>()
             .add_event::<LoadSimulation>()
             .add_event::<EndSimulation>()
             .add_event::<SaveSettings>()
-            .insert_resource(SimulationManager::new(simulations, Some(initial_simulation_name)))
+            .insert_resource(
+                SimulationManager::new(simulations, Some(initial_simulation_name))
+                    .with_registered(registered),
+            )
+            .insert_resource(failed)
             .add_systems(Update, handle_requests.run_if(on_real_timer(Duration::from_millis(500))))
             .add_systems(
                 Update,
@@ -246,6 +532,18 @@ impl Plugin for SimulationLoaderPlugin {
                 )
             );
 
+        if self.show_toasts {
+            app.add_systems(Startup, report_failed_simulations);
+        }
+
+        // without a simulations directory there is nothing to watch
+        if self.watch_simulations_dir && std::path::Path::new(SIMULATIONS_DIR).is_dir() {
+            app.add_systems(
+                Update,
+                watch_simulations_dir.run_if(on_real_timer(WATCH_INTERVAL)),
+            );
+        }
+
         if let Some(after) = self.reload_after {
             app.add_systems(
                 FixedUpdate,
@@ -270,6 +568,68 @@ pub struct Simulation {
     // pub sdf: Handle<Image>,
     pub sdf: Sdf,
     // pub raw: Raw,
+    /// SHA-256 of the files the simulation was loaded from, by file name.
+    /// Empty for simulations constructed in code
+    pub sources: BTreeMap<String, String>,
+}
+
+impl Simulation {
+    /// Create a simulation from its parts, generating the SDF of the
+    /// environment
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the SDF of the environment can not be generated
+    pub fn new(
+        name: impl Into<String>,
+        config: Config,
+        environment: Environment,
+        formation_group: FormationGroup,
+    ) -> anyhow::Result<Self> {
+        let name = name.into();
+        let derived_world_size = environment.world_size();
+        let world_size = config.simulation.world_size.get_or(derived_world_size);
+        if (world_size - derived_world_size).abs() > f32::EPSILON * derived_world_size {
+            warn!(
+                "simulation '{name}' has world-size = {world_size}, but its environment spans \
+                 {derived_world_size}. Set world-size = \"auto\" to derive it from the environment"
+            );
+        }
+
+        let sdf = generate_sdf(&environment)?;
+
+        Ok(Self {
+            name,
+            config,
+            environment,
+            formation_group,
+            sdf,
+            sources: BTreeMap::new(),
+        })
+    }
+
+    /// Set the hashes of the files the simulation was loaded from
+    #[must_use]
+    pub fn with_sources(mut self, sources: BTreeMap<String, String>) -> Self {
+        self.sources = sources;
+        self
+    }
+}
+
+/// Generate the signed distance field of `environment`, with the resolution,
+/// expansion and blur of its SDF settings
+///
+/// # Errors
+///
+/// Will return `Err` if the environment can not be rendered
+pub fn generate_sdf(environment: &Environment) -> anyhow::Result<Sdf> {
+    let sdf_image_buffer = env_to_png::env_to_sdf_image(
+        environment,
+        env_to_png::PixelsPerTile::new(environment.tiles.settings.sdf.resolution),
+        env_to_png::Percentage::new(environment.tiles.settings.sdf.expansion),
+        env_to_png::Percentage::new(environment.tiles.settings.sdf.blur),
+    )?;
+    Ok(Sdf(sdf_image_buffer.into()))
 }
 
 #[derive(Debug, Resource)]
@@ -284,6 +644,13 @@ pub struct SimulationManager {
     // reload_requested: Option<()>,
     requests: VecDeque<Request>,
     simulations_loaded: usize,
+    /// Seed used instead of `config.simulation.prng_seed` of the simulation
+    prng_seed_override: Option<u64>,
+    /// Applied to the config of the simulation when it is loaded or reloaded
+    config_overrides: Vec<ConfigOverride>,
+    /// Names of the simulations registered in code, which are not removed
+    /// when missing from the simulations directory
+    registered: std::collections::BTreeSet<SmolStr>,
 }
 
 // impl FromWorld for SimulationManager {
@@ -376,14 +743,68 @@ impl SimulationManager {
             // active: None,
             requests,
             simulations_loaded: 0,
+            prng_seed_override: None,
+            config_overrides: Vec::new(),
+            registered: std::collections::BTreeSet::new(),
         }
     }
 
+    #[must_use]
+    fn with_registered(mut self, names: impl IntoIterator<Item = SmolStr>) -> Self {
+        self.registered.extend(names);
+        self
+    }
+
+    /// Add a simulation constructed in code, replacing any simulation with
+    /// the same name. Unlike simulations found in the simulations directory,
+    /// it is kept when no directory of that name exists
+    pub fn register(&mut self, simulation: Simulation) -> SimulationId {
+        let name = SmolStr::from(simulation.name.as_str());
+        self.insert(simulation);
+        self.registered.insert(name.clone());
+        self.id_from_name(&name)
+            .expect("the simulation was just inserted")
+    }
+
+    /// Returns `true` if the simulation was registered in code
+    #[must_use]
+    pub fn is_registered(&self, name: &str) -> bool {
+        self.registered.contains(name)
+    }
+
+    /// Use `seed` instead of the seed in the config of the simulation, the
+    /// next time a simulation is loaded or reloaded
+    pub fn override_prng_seed(&mut self, seed: Option<u64>) {
+        self.prng_seed_override = seed;
+    }
+
+    /// Apply `overrides` to the config of the simulation, the next time a
+    /// simulation is loaded or reloaded. Unless `overrides` is empty, a reload
+    /// starts over from the config of the simulation, instead of keeping
+    /// changes made while it was running.
+    pub fn override_config(&mut self, overrides: Vec<ConfigOverride>) {
+        self.config_overrides = overrides;
+    }
+
+    /// The config of simulation `index` with the overrides applied
+    fn overridden_config(&self, index: usize) -> Result<Config, gbp_config::OverrideError> {
+        let config = &self.simulations[index].config;
+        if self.config_overrides.is_empty() {
+            return Ok(config.clone());
+        }
+        config.with_overrides(&self.config_overrides)
+    }
+
     pub fn active(&self) -> Option<&Simulation> {
         let active = self.active?;
         self.simulations.get(active)
     }
 
+    pub fn active_mut(&mut self) -> Option<&mut Simulation> {
+        let active = self.active?;
+        self.simulations.get_mut(active)
+    }
+
     pub fn active_id(&self) -> Option<SimulationId> {
         self.active.map(SimulationId)
     }
@@ -427,6 +848,14 @@ impl SimulationManager {
         // self.reload_requested = Some(());
     }
 
+    /// End the active simulation, despawning its robots without loading
+    /// another
+    pub fn end(&mut self) {
+        if self.active.is_some() {
+            self.requests.push_back(Request::End);
+        }
+    }
+
     pub fn load_next(&mut self) {
         let next = self
             .active
@@ -445,6 +874,50 @@ impl SimulationManager {
         self.load(SimulationId(next));
     }
 
+    /// Add a simulation, keeping the simulations ordered by name.
+    /// Replaces any existing simulation with the same name.
+    pub fn insert(&mut self, simulation: Simulation) {
+        let name = SmolStr::from(simulation.name.as_str());
+        match self.names.binary_search(&name) {
+            Ok(index) => self.simulations[index] = simulation,
+            Err(index) => {
+                self.names.insert(index, name);
+                self.simulations.insert(index, simulation);
+                self.shift_ids(|id| Some(if id >= index { id + 1 } else { id }));
+            }
+        }
+    }
+
+    /// Remove the simulation with the given name. The active simulation can
+    /// not be removed.
+    pub fn remove(&mut self, name: &str) -> Option<Simulation> {
+        let index = self.names.iter().position(|n| n == name)?;
+        if self.active == Some(index) {
+            return None;
+        }
+
+        self.names.remove(index);
+        self.registered.remove(name);
+        let simulation = self.simulations.remove(index);
+        self.shift_ids(|id| match id.cmp(&index) {
+            std::cmp::Ordering::Less => Some(id),
+            std::cmp::Ordering::Equal => None,
+            std::cmp::Ordering::Greater => Some(id - 1),
+        });
+
+        Some(simulation)
+    }
+
+    /// Update the active index and pending requests after the simulations have
+    /// been reordered. Requests for removed simulations are dropped.
+    fn shift_ids(&mut self, f: impl Fn(usize) -> Option<usize>) {
+        self.active = self.active.and_then(&f);
+        self.requests.retain_mut(|request| match request {
+            Request::Load(SimulationId(id)) => f(*id).map(|new| *id = new).is_some(),
+            _ => true,
+        });
+    }
+
     pub fn ids(&self) -> impl Iterator<Item = SimulationId> + '_ {
         (0..self.simulations.len()).map(SimulationId)
     }
@@ -486,11 +959,40 @@ impl SimulationManager {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct SimulationId(usize);
 
-#[derive(Event)]
-pub struct LoadSimulation(pub SimulationId);
+/// The resources of a simulation as they were when it was (re)loaded. Sent
+/// along with [`LoadSimulation`] and [`ReloadSimulation`], so readers of the
+/// events do not have to go through the [`SimulationManager`] or the global
+/// resources, which may be updated again before the event is read
+#[derive(Debug, Clone)]
+pub struct LoadedSimulation {
+    pub id: SimulationId,
+    pub name: SmolStr,
+    pub config: Arc<Config>,
+    pub environment: Arc<Environment>,
+    pub formation_group: Arc<FormationGroup>,
+    /// See [`Simulation::sources`]
+    pub sources: Arc<BTreeMap<String, String>>,
+}
 
-#[derive(Event)]
-pub struct ReloadSimulation(pub SimulationId);
+#[derive(Event, Deref)]
+pub struct LoadSimulation(pub LoadedSimulation);
+
+#[derive(Event, Deref)]
+pub struct ReloadSimulation(pub LoadedSimulation);
+
+/// The simulation of the latest [`LoadSimulation`] or [`ReloadSimulation`]
+/// event, if any. Only the latest one matters to systems setting up a
+/// simulation, as each one replaces the one before it.
+pub fn latest_loaded_simulation<'a>(
+    evr_load_simulation: &'a mut EventReader<'_, '_, LoadSimulation>,
+    evr_reload_simulation: &'a mut EventReader<'_, '_, ReloadSimulation>,
+) -> Option<&'a LoadedSimulation> {
+    evr_load_simulation
+        .read()
+        .map(|event| &event.0)
+        .chain(evr_reload_simulation.read().map(|event| &event.0))
+        .last()
+}
 
 #[derive(Event)]
 pub struct EndSimulation(pub SimulationId);
@@ -605,6 +1107,7 @@ fn handle_requests(
     // mut variable_timesteps: ResMut<VariableTimesteps>,
     mut environment: ResMut<Environment>,
     mut sdf: ResMut<Sdf>,
+    mut analytic_sdf: ResMut<AnalyticSdf>,
     // mut raw: ResMut<Raw>,
     mut rng: ResMut<bevy_rand::prelude::GlobalEntropy<bevy_prng::WyRand>>,
     reloadable_entities: Query<Entity, With<Reloadable>>,
@@ -617,7 +1120,17 @@ fn handle_requests(
     info!("requests pending: {:?}", simulation_manager.requests.len());
 
     match request {
-        Request::LoadInitial => todo!(),
+        Request::LoadInitial => {
+            // the initial simulation is inserted when the plugin is built
+            warn!("the initial simulation is loaded at startup, ignoring request");
+        }
+        Request::Load(id) if id.0 >= simulation_manager.simulations.len() => {
+            error!("no simulation with id: {}", id.0);
+            evw_toast.send(ToastEvent::error(format!(
+                "no simulation with id: {}",
+                id.0
+            )));
+        }
         // Request::Load(id) if simulation_loader.active.is_none() => {
         //     simulation_manager.active = Some(id.0);
         // }
@@ -640,10 +1153,30 @@ fn handle_requests(
 
             // app.insert_resource(Time::<Fixed>::from_hz(hz))
             *time_fixed = Time::<Fixed>::from_hz(config.simulation.hz);
-            *config = simulation_manager.simulations[id.0].config.clone();
+            *config = match simulation_manager.overridden_config(id.0) {
+                Ok(overridden) => overridden,
+                Err(err) => {
+                    error!("failed to apply config overrides: {err}");
+                    evw_toast.send(ToastEvent::error(format!(
+                        "failed to apply config overrides: {err}"
+                    )));
+                    simulation_manager.simulations[id.0].config.clone()
+                }
+            };
+            if let Some(seed) = simulation_manager.prng_seed_override {
+                config.simulation.prng_seed = seed;
+            } else if let Some(&seed) = config.simulation.random_seeds.first() {
+                config.simulation.prng_seed = seed;
+            }
+            if let Err(err) = crate::persist::apply_user_overrides(&mut config) {
+                error!("failed to apply user visualisation settings: {err}");
+            }
             // config.simulation.t0 =
             *environment = simulation_manager.simulations[id.0].environment.clone();
             *sdf = simulation_manager.simulations[id.0].sdf.clone();
+            // shared by the load event and the obstacle factors
+            let loaded_environment = Arc::new(environment.clone());
+            *analytic_sdf = AnalyticSdf::new(&loaded_environment);
 
             time_virtual.set_relative_speed(config.simulation.time_scale.get());
             // *raw = simulation_manager.simulations[id.0].raw.clone();
@@ -651,7 +1184,16 @@ fn handle_requests(
             let seed: [u8; 8] = config.simulation.prng_seed.to_le_bytes();
             rng.reseed(seed);
 
-            evw_load_simulation.send(LoadSimulation(id));
+            evw_load_simulation.send(LoadSimulation(LoadedSimulation {
+                id,
+                name: simulation_manager.names[id.0].clone(),
+                config: Arc::new(config.clone()),
+                environment: loaded_environment,
+                formation_group: Arc::new(
+                    simulation_manager.simulations[id.0].formation_group.clone(),
+                ),
+                sources: Arc::new(simulation_manager.simulations[id.0].sources.clone()),
+            }));
             info!("sent load simulation event with id: {}", id.0);
             simulation_manager.simulations_loaded += 1;
             let simulation_name = &simulation_manager.names[id.0];
@@ -676,11 +1218,55 @@ fn handle_requests(
                     // commands.entity(entity).despawn_recursive();
                     commands.entity(entity).despawn();
                 }
-                evw_reload_simulation.send(ReloadSimulation(SimulationId(index)));
+                if !simulation_manager.config_overrides.is_empty() {
+                    // what is drawn is kept across reloads, like the camera
+                    let draw = config.visualisation.draw;
+                    match simulation_manager.overridden_config(index) {
+                        Ok(overridden) => {
+                            *config = overridden;
+                            config.visualisation.draw = draw;
+                        }
+                        Err(err) => {
+                            error!("failed to apply config overrides: {err}");
+                            evw_toast.send(ToastEvent::error(format!(
+                                "failed to apply config overrides: {err}"
+                            )));
+                        }
+                    }
+                }
+                if let Some(seed) = simulation_manager.prng_seed_override {
+                    config.simulation.prng_seed = seed;
+                } else if let Some(seed) = config.simulation.next_random_seed() {
+                    config.simulation.prng_seed = seed;
+                }
+                // shared by the reload event and the obstacle factors
+                let loaded_environment = Arc::new(environment.clone());
+                *analytic_sdf = AnalyticSdf::new(&loaded_environment);
+                evw_reload_simulation.send(ReloadSimulation(LoadedSimulation {
+                    id: SimulationId(index),
+                    name: simulation_manager.names[index].clone(),
+                    config: Arc::new(config.clone()),
+                    environment: loaded_environment,
+                    formation_group: Arc::new(
+                        simulation_manager.simulations[index]
+                            .formation_group
+                            .clone(),
+                    ),
+                    sources: Arc::new(simulation_manager.simulations[index].sources.clone()),
+                }));
                 info!("sent reload simulation event with id: {}", index);
                 simulation_manager.simulations_loaded += 1;
+
+                let caption = if config.simulation.random_seeds.is_empty() {
+                    "simulation reloaded".to_string()
+                } else {
+                    format!(
+                        "simulation reloaded with seed: {}",
+                        config.simulation.prng_seed
+                    )
+                };
                 evw_toast.send(ToastEvent {
-                    caption: "simulation reloaded".into(),
+                    caption,
                     options: ToastOptions {
                         level: ToastLevel::Success,
                         show_progress_bar: false,
@@ -699,6 +1285,9 @@ fn handle_requests(
         },
         Request::End => match simulation_manager.active {
             Some(index) => {
+                for entity in &reloadable_entities {
+                    commands.entity(entity).despawn();
+                }
                 simulation_manager.active = None;
                 evw_end_simulation.send(EndSimulation(SimulationId(index)));
                 info!("sent end simulation event with id: {}", index);
@@ -727,6 +1316,73 @@ fn handle_requests(
     }
 }
 
+/// Keep the simulations of the [`SimulationManager`] in sync with the
+/// subdirectories of [`SIMULATIONS_DIR`], so new scenarios can be picked
+/// without restarting
+fn watch_simulations_dir(
+    mut simulation_manager: ResMut<SimulationManager>,
+    mut evw_toast: EventWriter<ToastEvent>,
+    mut failed: ResMut<FailedSimulations>,
+) {
+    let Ok(reader) = std::fs::read_dir(SIMULATIONS_DIR) else {
+        warn!("failed to read simulation directory: {}", SIMULATIONS_DIR);
+        return;
+    };
+
+    let on_disk: BTreeMap<String, std::path::PathBuf> = reader
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| Some((entry.file_name().into_string().ok()?, entry.path())))
+        .collect();
+
+    let removed: Vec<SmolStr> = simulation_manager
+        .names()
+        .filter(|name| {
+            !on_disk.contains_key(name.as_str()) && !simulation_manager.is_registered(name)
+        })
+        .cloned()
+        .collect();
+
+    for name in removed {
+        if simulation_manager.remove(&name).is_some() {
+            info!("simulation removed: {}", name);
+            evw_toast.send(ToastEvent::info(format!("simulation removed: {name}")));
+        }
+    }
+
+    failed.0.retain(|name, _| on_disk.contains_key(name));
+
+    for (name, path) in &on_disk {
+        if simulation_manager.id_from_name(name).is_some() {
+            continue;
+        }
+
+        // the files of a new scenario might not all have been written yet, so
+        // a failure is retried, but only once the files have changed. Loading
+        // generates the SDF, which is too slow to repeat on every poll
+        let modified = last_modified(path);
+        if failed.unchanged(name, modified) {
+            continue;
+        }
+
+        match load_simulation_from_dir(path) {
+            Ok(simulation) => {
+                failed.0.remove(name);
+                simulation_manager.insert(simulation);
+                info!("simulation added: {}", name);
+                evw_toast.send(ToastEvent::info(format!("simulation added: {name}")));
+            }
+            Err(err) => {
+                warn!("failed to load simulation {}: {}", name, err);
+                evw_toast.send(ToastEvent::warning(format!(
+                    "failed to load simulation: {name}"
+                )));
+                failed.insert(name.clone(), modified, &err);
+            }
+        }
+    }
+}
+
 #[inline]
 fn load_previous_simulation(mut simulation_manager: ResMut<SimulationManager>) {
     simulation_manager.load_previous();
@@ -739,21 +1395,33 @@ fn load_next_simulation(mut simulation_manager: ResMut<SimulationManager>) {
 
 /// Save the current state of the `Config` resource to the config.toml of the
 /// current scenario from which it was originally loaded from
-fn save_settings(mut simulation_manager: ResMut<SimulationManager>, config: Res<Config>) {
-    let Some(name) = simulation_manager.active_name() else {
+fn save_settings(
+    mut simulation_manager: ResMut<SimulationManager>,
+    config: Res<Config>,
+    mut evw_toast: EventWriter<ToastEvent>,
+) {
+    let (Some(ix), Some(name)) = (simulation_manager.active, simulation_manager.active_name())
+    else {
         return;
     };
 
-    let dir = std::path::Path::new(SIMULATIONS_DIR).join(name);
+    let path = std::path::Path::new(SIMULATIONS_DIR)
+        .join(name)
+        .join("config.toml");
 
     // serialize to toml
-    let toml = toml::to_string_pretty(config.as_ref()).unwrap();
-    std::fs::write(dir.join("config.toml"), toml).unwrap();
+    let written = toml::to_string_pretty(config.as_ref())
+        .map_err(|err| err.to_string())
+        .and_then(|toml| std::fs::write(&path, toml).map_err(|err| err.to_string()));
+    if let Err(err) = written {
+        error!("failed to save settings to {}: {err}", path.display());
+        evw_toast.send(ToastEvent::error(format!("failed to save settings: {err}")));
+        return;
+    }
 
     // update the simulation manager instance of the config object, such that if the
     // user loads another scenario, and then this, the current, again the changes
     // will be persisted across this application instance
-    let ix = simulation_manager.active.unwrap();
     simulation_manager.simulations[ix].config = config.clone();
-    info!("saved settings to: {}", dir.join("config.toml").display());
+    info!("saved settings to: {}", path.display());
 }
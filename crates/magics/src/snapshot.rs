@@ -0,0 +1,483 @@
+//! Module for saving the state of a running simulation to a file, and
+//! restoring it later, e.g. to re-examine an interesting situation with
+//! different parameters.
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bevy::{input::common_conditions::input_just_pressed, prelude::*};
+use bevy_notify::ToastEvent;
+use gbp_config::{
+    formation::{PlanningStrategy, ReachedWhen},
+    Config,
+};
+use gbp_linalg::Float;
+
+use crate::{
+    factorgraph::prelude::FactorGraph,
+    planner::{
+        robot::{Mission, Priority, Radius, RobotClass, SpeedFactor},
+        spawner::{
+            FormationSpawner, FormationSpawnerSet, RobotSpawnDescription, RobotSpawner, Scoreboard,
+        },
+    },
+    playlist::{Playlist, PlaylistEntry},
+    simulation_loader::SimulationManager,
+    theme::{ColorAssociation, DisplayColour},
+};
+
+#[derive(Default)]
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<events::SaveSnapshot>()
+            .add_event::<events::RestoreSnapshot>()
+            .init_resource::<resources::PendingBeliefs>()
+            .add_systems(
+                Update,
+                (
+                    send_save_snapshot_event.run_if(input_just_pressed(KeyCode::F8)),
+                    send_restore_snapshot_event.run_if(input_just_pressed(KeyCode::F9)),
+                    save_snapshot.run_if(on_event::<events::SaveSnapshot>()),
+                    (
+                        restore_snapshot.run_if(on_event::<events::RestoreSnapshot>()),
+                        restore_beliefs.run_if(beliefs_pending),
+                    )
+                        .chain()
+                        .before(FormationSpawnerSet),
+                ),
+            );
+    }
+}
+
+pub mod events {
+    use super::*;
+
+    /// Save the state of the running simulation to `path`, or to
+    /// `snapshot_<simulation>.json` in the current working directory
+    #[derive(Event, Default)]
+    pub struct SaveSnapshot {
+        pub path: Option<PathBuf>,
+    }
+
+    /// Replace the robots and virtual clock of the running simulation with
+    /// the state saved in `path`, or in `snapshot_<simulation>.json` in the
+    /// current working directory
+    #[derive(Event, Default)]
+    pub struct RestoreSnapshot {
+        pub path: Option<PathBuf>,
+    }
+}
+
+mod resources {
+    use super::*;
+
+    /// Beliefs of restored robots, applied once their factorgraphs exist
+    #[derive(Resource, Deref, DerefMut, Default)]
+    pub(super) struct PendingBeliefs(pub Vec<(Entity, Vec<[Float; 4]>)>);
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// The state of a single robot at the time of the snapshot
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RobotSnapshot {
+    pub radius: f32,
+    pub planning_strategy: PlanningStrategy,
+    pub waypoint_reached_when_intersects: ReachedWhen,
+    pub finished_when_intersects: ReachedWhen,
+    pub color: DisplayColour,
+    /// Current state [x, y, x', y']
+    pub state: [f32; 4],
+    /// Waypoints not yet reached [x, y, x', y']
+    pub waypoints: Vec<[f32; 4]>,
+    /// Mean of the belief of every variable in the factorgraph, ordered by
+    /// creation
+    pub variables: Vec<[Float; 4]>,
+    /// Factor the target speed of the robot is scaled by
+    #[serde(default = "RobotSnapshot::default_speed_factor")]
+    pub speed_factor: f32,
+    /// Priority of the robot towards other robots
+    #[serde(default)]
+    pub priority: u8,
+    /// Class of the robot, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+}
+
+impl RobotSnapshot {
+    const fn default_speed_factor() -> f32 {
+        1.0
+    }
+}
+
+/// The state of a running simulation
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SimulationSnapshot {
+    /// Name of the simulation the snapshot was taken of
+    pub simulation: String,
+    /// Elapsed virtual time in seconds
+    pub elapsed:    f64,
+    pub prng_seed:  u64,
+    pub robots:     Vec<RobotSnapshot>,
+    /// Playlist entries not yet finished, starting with the one running
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub playlist:   Vec<PlaylistEntry>,
+}
+
+impl SimulationSnapshot {
+    /// Write the snapshot as JSON to `path`. The file is written to a
+    /// temporary file first, so an existing snapshot is never left half
+    /// written if the process is killed
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file can not be written
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SnapshotError> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self)?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Read a snapshot from the JSON file at `path`
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file can not be read, or is not a valid
+    /// snapshot
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SnapshotError> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+fn default_snapshot_path(simulation: &str) -> PathBuf {
+    PathBuf::from(format!("snapshot_{}.json", simulation.to_lowercase()))
+}
+
+fn send_save_snapshot_event(mut evw_save_snapshot: EventWriter<events::SaveSnapshot>) {
+    evw_save_snapshot.send(events::SaveSnapshot::default());
+}
+
+fn send_restore_snapshot_event(mut evw_restore_snapshot: EventWriter<events::RestoreSnapshot>) {
+    evw_restore_snapshot.send(events::RestoreSnapshot::default());
+}
+
+#[inline]
+fn beliefs_pending(pending_beliefs: Res<resources::PendingBeliefs>) -> bool {
+    !pending_beliefs.is_empty()
+}
+
+fn save_snapshot(
+    mut evr_save_snapshot: EventReader<events::SaveSnapshot>,
+    mut evw_toast: EventWriter<ToastEvent>,
+    q_robots: Query<(
+        &FactorGraph,
+        &Radius,
+        &Mission,
+        &PlanningStrategy,
+        &ColorAssociation,
+        Option<&SpeedFactor>,
+        Option<&Priority>,
+        Option<&RobotClass>,
+    )>,
+    simulation_manager: Res<SimulationManager>,
+    playlist: Option<Res<Playlist>>,
+    config: Res<Config>,
+    time_virtual: Res<Time<Virtual>>,
+) {
+    for event in evr_save_snapshot.read() {
+        let Some(simulation) = simulation_manager.active_name() else {
+            evw_toast.send(ToastEvent::error("no active simulation to snapshot"));
+            continue;
+        };
+
+        let robots = q_robots
+            .iter()
+            .filter_map(
+                |(fgraph, radius, mission, planning_strategy, color, speed, priority, class)| {
+                    // robots that have completed their mission are not restored
+                    let waypoints = mission.remaining_waypoints();
+                    if waypoints.is_empty() {
+                        return None;
+                    }
+
+                    let variables: Vec<[Float; 4]> = fgraph
+                        .variables()
+                        .map(|(_, variable)| {
+                            let mean = &variable.belief.mean;
+                            [mean[0], mean[1], mean[2], mean[3]]
+                        })
+                        .collect();
+                    #[allow(clippy::cast_possible_truncation)]
+                    let state = variables.first()?.map(|x| x as f32);
+
+                    Some(RobotSnapshot {
+                        radius: radius.0,
+                        planning_strategy: *planning_strategy,
+                        waypoint_reached_when_intersects: mission
+                            .taskpoint_reached_when_intersects(),
+                        finished_when_intersects: mission.finished_when_intersects(),
+                        color: color.name,
+                        state,
+                        waypoints: waypoints
+                            .iter()
+                            .map(|waypoint| waypoint.0.to_array())
+                            .collect(),
+                        variables,
+                        speed_factor: speed.map_or(1.0, |factor| factor.0),
+                        priority: priority.map_or(0, |priority| priority.0),
+                        class: class.map(|class| class.0.clone()),
+                    })
+                },
+            )
+            .collect();
+
+        let snapshot = SimulationSnapshot {
+            simulation: simulation.to_string(),
+            elapsed: time_virtual.elapsed_seconds_f64(),
+            prng_seed: config.simulation.prng_seed,
+            robots,
+            playlist: playlist
+                .as_ref()
+                .map(|playlist| playlist.pending())
+                .unwrap_or_default(),
+        };
+
+        let path = event
+            .path
+            .clone()
+            .unwrap_or_else(|| default_snapshot_path(simulation));
+
+        match snapshot.save(&path) {
+            Ok(()) => {
+                let message = format!("snapshot saved to '{}'", path.display());
+                info!(message);
+                evw_toast.send(ToastEvent::success(message));
+            }
+            Err(err) => {
+                let message = format!("failed to save snapshot to '{}': {}", path.display(), err);
+                error!(message);
+                evw_toast.send(ToastEvent::error(message));
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn restore_snapshot(
+    mut evr_restore_snapshot: EventReader<events::RestoreSnapshot>,
+    mut evw_toast: EventWriter<ToastEvent>,
+    mut spawner: RobotSpawner,
+    mut pending_beliefs: ResMut<resources::PendingBeliefs>,
+    mut time_virtual: ResMut<Time<Virtual>>,
+    mut q_spawners: Query<&mut FormationSpawner>,
+    scoreboard: Option<ResMut<Scoreboard>>,
+    simulation_manager: Res<SimulationManager>,
+    q_robots: Query<Entity, With<FactorGraph>>,
+) {
+    // only the latest request matters, as each restore replaces all robots
+    let Some(event) = evr_restore_snapshot.read().last() else {
+        return;
+    };
+
+    let Some(simulation) = simulation_manager.active_name() else {
+        evw_toast.send(ToastEvent::error("no active simulation to restore into"));
+        return;
+    };
+
+    let path = event
+        .path
+        .clone()
+        .unwrap_or_else(|| default_snapshot_path(simulation));
+
+    let snapshot = match SimulationSnapshot::load(&path) {
+        Ok(snapshot) => snapshot,
+        Err(err) => {
+            let message = format!("failed to load snapshot '{}': {}", path.display(), err);
+            error!(message);
+            evw_toast.send(ToastEvent::error(message));
+            return;
+        }
+    };
+
+    if snapshot.simulation != simulation {
+        evw_toast.send(ToastEvent::error(format!(
+            "snapshot was taken of simulation '{}', not '{}'",
+            snapshot.simulation, simulation
+        )));
+        return;
+    }
+
+    for robot in &q_robots {
+        spawner.commands.entity(robot).despawn();
+    }
+
+    let elapsed = Duration::from_secs_f64(snapshot.elapsed);
+
+    // the formations spawned before the snapshot was taken are part of it, so
+    // the spawners must not spawn them again. The spawners may have run past
+    // the snapshot already, so they are fast forwarded from the start
+    let formation_group = simulation_manager.active_formation_group();
+    let mut robots_left = snapshot.robots.len();
+    for mut formation_spawner in &mut q_spawners {
+        formation_spawner.reset();
+        formation_spawner.fast_forward(elapsed);
+        if let Some(formation) = formation_group.and_then(|group| {
+            group
+                .formations
+                .get(formation_spawner.formation_group_index)
+        }) {
+            robots_left = robots_left.saturating_add(
+                formation
+                    .robots_to_spawn()
+                    .saturating_sub(formation.robots.saturating_mul(formation_spawner.spawned())),
+            );
+        }
+    }
+    if let Some(mut scoreboard) = scoreboard {
+        scoreboard.robots_left = robots_left;
+        scoreboard.game_over = false;
+    }
+
+    pending_beliefs.clear();
+    for robot in snapshot.robots {
+        let entity = spawner.spawn(RobotSpawnDescription {
+            initial_pose: Vec4::from_array(robot.state),
+            waypoints: robot.waypoints.into_iter().map(Vec4::from_array).collect(),
+            radius: robot.radius,
+            planning_strategy: robot.planning_strategy,
+            waypoint_reached_when_intersects: robot.waypoint_reached_when_intersects,
+            finished_when_intersects: robot.finished_when_intersects,
+            priority: robot.priority,
+            class: robot.class,
+            color: Some(robot.color),
+        });
+        spawner
+            .commands
+            .entity(entity)
+            .insert(SpeedFactor(robot.speed_factor));
+        pending_beliefs.push((entity, robot.variables));
+    }
+
+    let is_paused = time_virtual.is_paused();
+    let relative_speed = time_virtual.relative_speed();
+    *time_virtual = Time::<Virtual>::default();
+    time_virtual.set_relative_speed(relative_speed);
+    if is_paused {
+        time_virtual.pause();
+    }
+    time_virtual.advance_by(elapsed);
+
+    let message = format!("snapshot restored from '{}'", path.display());
+    info!(message);
+    evw_toast.send(ToastEvent::success(message));
+}
+
+/// Set the beliefs of the restored robots. Runs after [`restore_snapshot`],
+/// so the spawned robots have their factorgraphs
+fn restore_beliefs(
+    mut pending_beliefs: ResMut<resources::PendingBeliefs>,
+    mut q_factorgraphs: Query<&mut FactorGraph>,
+) {
+    for (entity, means) in pending_beliefs.drain(..) {
+        let Ok(mut fgraph) = q_factorgraphs.get_mut(entity) else {
+            warn!("restored robot {:?} no longer exists", entity);
+            continue;
+        };
+
+        if fgraph.variables().count() == means.len() {
+            fgraph.reset_variables(&means, 1e30, Float::INFINITY);
+        } else {
+            warn!(
+                "number of variables of robot {:?} differs from the snapshot, keeping its initial \
+                 beliefs",
+                entity
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn robot_snapshot() -> RobotSnapshot {
+        RobotSnapshot {
+            radius: 1.5,
+            planning_strategy: PlanningStrategy::OnlyLocal,
+            waypoint_reached_when_intersects: ReachedWhen::same_as_paper(),
+            finished_when_intersects: ReachedWhen::same_as_paper(),
+            color: DisplayColour::Lavender,
+            state: [1.0, 2.0, 0.5, -0.5],
+            waypoints: vec![[10.0, 20.0, 0.5, -0.5], [30.0, 40.0, 0.0, 0.0]],
+            variables: vec![[1.0, 2.0, 0.5, -0.5], [1.5, 1.5, 0.5, -0.5], [
+                2.0, 1.0, 0.5, -0.5,
+            ]],
+            speed_factor: 0.8,
+            priority: 2,
+            class: Some("forklift".to_string()),
+        }
+    }
+
+    #[test]
+    fn snapshot_round_trips_through_a_file() {
+        let snapshot = SimulationSnapshot {
+            simulation: "Junction".to_string(),
+            elapsed:    12.5,
+            prng_seed:  42,
+            robots:     vec![robot_snapshot()],
+            playlist:   Vec::new(),
+        };
+        let path = std::env::temp_dir().join(format!("gbp-snapshot-{}.json", std::process::id()));
+
+        snapshot.save(&path).unwrap();
+        let restored = SimulationSnapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.simulation, snapshot.simulation);
+        assert!((restored.elapsed - snapshot.elapsed).abs() < f64::EPSILON);
+        assert_eq!(restored.prng_seed, snapshot.prng_seed);
+        assert_eq!(restored.robots.len(), 1);
+
+        let (robot, expected) = (&restored.robots[0], &snapshot.robots[0]);
+        assert_eq!(robot.state, expected.state);
+        assert_eq!(robot.waypoints, expected.waypoints);
+        assert_eq!(robot.variables, expected.variables);
+        assert!((robot.radius - expected.radius).abs() < f32::EPSILON);
+        assert!((robot.speed_factor - expected.speed_factor).abs() < f32::EPSILON);
+        assert_eq!(robot.priority, expected.priority);
+        assert_eq!(robot.class, expected.class);
+        assert!(matches!(
+            robot.planning_strategy,
+            PlanningStrategy::OnlyLocal
+        ));
+    }
+
+    #[test]
+    fn missing_optional_fields_take_their_defaults() {
+        let mut json = serde_json::to_value(robot_snapshot()).unwrap();
+        let robot = json.as_object_mut().unwrap();
+        robot.remove("speed_factor");
+        robot.remove("priority");
+        robot.remove("class");
+
+        let robot: RobotSnapshot = serde_json::from_value(json).unwrap();
+        assert!((robot.speed_factor - 1.0).abs() < f32::EPSILON);
+        assert_eq!(robot.priority, 0);
+        assert_eq!(robot.class, None);
+    }
+}
@@ -0,0 +1,204 @@
+//! Parameter sweeps, running simulations for every combination of a set of
+//! config values.
+//!
+//! A sweep is described in a TOML file, listing the values to try for each
+//! config key:
+//!
+//! ```toml
+//! simulations = ["Circle Experiment", "Junction Experiment"]
+//! seeds       = [0, 1, 2]
+//! # where to write the index of the runs, relative to the working directory
+//! index       = "sweep-index.json"
+//! # where to write the summary of the runs, relative to the working directory
+//! summary     = "sweep-summary.json"
+//!
+//! [parameters]
+//! "gbp.sigma-factor-interrobot" = [0.005, 0.01, 0.05]
+//! "robot.communication.radius"  = [10.0, 20.0]
+//! ```
+//!
+//! Every simulation is run once for each seed and combination of the
+//! parameters, i.e. 2 * 3 * 3 * 2 = 36 runs for the sweep above. The sweep is
+//! run as a [`Playlist`], so a report is exported after every run, and the
+//! index lists the simulation, seed and parameters of each run together with
+//! the file its report was exported to. The summary aggregates the runs of
+//! each simulation and combination across the seeds, with their success rate
+//! and mean collisions, makespan and distance travelled, see
+//! [`SuccessRate`](crate::playlist::SuccessRate).
+//!
+//! Run with `--headless` to not show the window while sweeping.
+
+use std::{collections::BTreeMap, path::Path};
+
+use gbp_config::ConfigOverride;
+use itertools::Itertools;
+
+use crate::playlist::{Playlist, PlaylistEntry, PlaylistError};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SweepError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("TOML error: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("no values given for parameter {0}")]
+    NoValues(String),
+}
+
+/// A sweep specification, see the [module documentation](self)
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Sweep {
+    /// Names of the simulations to run
+    pub simulations: Vec<String>,
+    /// Seeds to run every combination with. The seed of the simulation is
+    /// used if empty
+    #[serde(default)]
+    pub seeds:       Vec<u64>,
+    /// File to write the index of the runs to
+    #[serde(default = "Sweep::default_index")]
+    pub index:       String,
+    /// File to write the summary of the runs to
+    #[serde(default = "Sweep::default_summary")]
+    pub summary:     String,
+    /// The values to try for each config key
+    #[serde(default)]
+    pub parameters:  BTreeMap<String, Vec<toml::Value>>,
+}
+
+impl Sweep {
+    fn default_index() -> String {
+        "sweep-index.json".to_string()
+    }
+
+    fn default_summary() -> String {
+        "sweep-summary.json".to_string()
+    }
+
+    /// Read a sweep specification from a file
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if the file can not be read or parsed, or a
+    /// parameter has no values
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, SweepError> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::parse(&contents)
+    }
+
+    /// Parse a sweep specification
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if `contents` is not a valid specification, or a
+    /// parameter has no values
+    pub fn parse(contents: &str) -> Result<Self, SweepError> {
+        let sweep: Self = toml::from_str(contents)?;
+        if let Some((key, _)) = sweep
+            .parameters
+            .iter()
+            .find(|(_, values)| values.is_empty())
+        {
+            return Err(SweepError::NoValues(key.clone()));
+        }
+        Ok(sweep)
+    }
+
+    /// Every combination of the values of the parameters, i.e. their cross
+    /// product
+    #[must_use]
+    pub fn combinations(&self) -> Vec<Vec<ConfigOverride>> {
+        if self.parameters.is_empty() {
+            return vec![vec![]];
+        }
+
+        self.parameters
+            .iter()
+            .map(|(key, values)| {
+                values
+                    .iter()
+                    .map(|value| ConfigOverride::new(key.clone(), value.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .multi_cartesian_product()
+            .collect()
+    }
+
+    /// The playlist running every simulation for each combination of the
+    /// parameters, and each seed
+    ///
+    /// # Errors
+    ///
+    /// Will return `Err` if there are no simulations to run
+    pub fn into_playlist(self) -> Result<Playlist, PlaylistError> {
+        let combinations = self.combinations();
+        let entries = self
+            .simulations
+            .iter()
+            .cartesian_product(&combinations)
+            .map(|(simulation, overrides)| {
+                PlaylistEntry::new(simulation.clone(), None).with_overrides(overrides.clone())
+            });
+
+        Ok(Playlist::from_entries(entries)?
+            .with_seeds(&self.seeds)
+            .with_index(self.index)
+            .with_summary(self.summary))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SWEEP: &str = r#"
+        simulations = ["a", "b"]
+        seeds = [1, 2]
+
+        [parameters]
+        "gbp.sigma-factor-interrobot" = [0.01, 0.05, 0.1]
+        "robot.communication.radius" = [10.0, 20.0]
+    "#;
+
+    #[test]
+    fn combinations_are_the_cross_product() {
+        let sweep = Sweep::parse(SWEEP).unwrap();
+        let combinations = sweep.combinations();
+        assert_eq!(combinations.len(), 6);
+        assert!(combinations.iter().all(|overrides| overrides.len() == 2));
+        for (i, combination) in combinations.iter().enumerate() {
+            assert!(!combinations[i + 1..].contains(combination));
+        }
+    }
+
+    #[test]
+    fn playlist_runs_every_simulation_seed_and_combination() {
+        let playlist = Sweep::parse(SWEEP).unwrap().into_playlist().unwrap();
+        assert_eq!(playlist.remaining(), 2 * 2 * 6);
+    }
+
+    #[test]
+    fn parameters_need_values() {
+        let sweep = Sweep::parse(
+            r#"
+            simulations = ["a"]
+            [parameters]
+            "gbp.variables" = []
+        "#,
+        );
+        assert!(matches!(sweep, Err(SweepError::NoValues(key)) if key == "gbp.variables"));
+    }
+
+    #[test]
+    fn index_and_summary_have_defaults() {
+        let sweep = Sweep::parse(r#"simulations = ["a"]"#).unwrap();
+        assert_eq!(sweep.index, "sweep-index.json");
+        assert_eq!(sweep.summary, "sweep-summary.json");
+    }
+
+    #[test]
+    fn no_parameters_runs_the_simulations_as_is() {
+        let sweep = Sweep::parse(r#"simulations = ["a"]"#).unwrap();
+        assert_eq!(sweep.combinations(), vec![vec![]]);
+    }
+}
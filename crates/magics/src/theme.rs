@@ -12,6 +12,7 @@ use bevy_egui::{
 };
 use bevy_infinite_grid::InfiniteGridSettings;
 use catppuccin::{Colour, Flavour, FlavourColours};
+use gbp_config::ThemeFlavour;
 
 use crate::{
     environment,
@@ -68,7 +69,8 @@ impl FromWorld for CatppuccinTheme {
     }
 }
 
-#[derive(strum_macros::EnumIter, Debug, Clone, Copy)]
+#[derive(strum_macros::EnumIter, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum DisplayColour {
     Rosewater,
     Flamingo,
@@ -427,6 +429,28 @@ impl CatppuccinThemeSelectionExt for Selection {
     }
 }
 
+/// The catppuccin flavour of a [`ThemeFlavour`] from the config
+#[must_use]
+pub const fn flavour_from_config(flavour: ThemeFlavour) -> Flavour {
+    match flavour {
+        ThemeFlavour::Latte => Flavour::Latte,
+        ThemeFlavour::Frappe => Flavour::Frappe,
+        ThemeFlavour::Macchiato => Flavour::Macchiato,
+        ThemeFlavour::Mocha => Flavour::Mocha,
+    }
+}
+
+/// The [`ThemeFlavour`] to store `flavour` as in the config
+#[must_use]
+pub const fn flavour_to_config(flavour: Flavour) -> ThemeFlavour {
+    match flavour {
+        Flavour::Latte => ThemeFlavour::Latte,
+        Flavour::Frappe => ThemeFlavour::Frappe,
+        Flavour::Macchiato => ThemeFlavour::Macchiato,
+        Flavour::Mocha => ThemeFlavour::Mocha,
+    }
+}
+
 /// Signal that theme should be toggled
 #[derive(Event, Debug, Copy, Clone)]
 pub struct CycleTheme(pub Flavour);
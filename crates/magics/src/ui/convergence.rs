@@ -0,0 +1,157 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use bevy::prelude::*;
+use bevy_egui::egui::{self, Color32};
+use egui_plot::{Legend, Line, Plot, PlotPoints};
+use gbp_config::Config;
+
+use super::UiState;
+use crate::{
+    factorgraph::{factorgraph::SolveReport, prelude::FactorGraph},
+    theme::{CatppuccinTheme, ColorAssociation, FromCatppuccinColourExt},
+};
+
+/// **Bevy** [`Plugin`] adding a window plotting the energy and residual of the
+/// factorgraph of every robot per GBP iteration
+pub struct ConvergencePlugin;
+
+impl Plugin for ConvergencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConvergenceHistory>().add_systems(
+            Update,
+            (record_solve_reports, render)
+                .chain()
+                .run_if(convergence_window_visible),
+        );
+    }
+}
+
+/// **Bevy** [`Resource`]
+/// The latest solve reports of the factorgraph of every robot
+#[derive(Debug, Default, Resource)]
+struct ConvergenceHistory {
+    robots:    BTreeMap<Entity, VecDeque<SolveReport>>,
+    /// Plot the logarithm of the energy and residual
+    log_scale: bool,
+}
+
+impl ConvergenceHistory {
+    /// Number of reports kept for every robot
+    const CAPACITY: usize = 500;
+}
+
+#[inline]
+fn convergence_window_visible(ui_state: Res<UiState>) -> bool {
+    ui_state.convergence_window_visible
+}
+
+/// Record a solve report for every robot that has run a GBP iteration since
+/// the last frame, and forget robots that have been despawned
+fn record_solve_reports(
+    q_factorgraphs: Query<(Entity, &FactorGraph)>,
+    mut history: ResMut<ConvergenceHistory>,
+) {
+    history
+        .robots
+        .retain(|&entity, _| q_factorgraphs.contains(entity));
+
+    for (entity, factorgraph) in &q_factorgraphs {
+        let reports = history.robots.entry(entity).or_default();
+        let report = factorgraph.solve_report();
+        if reports
+            .back()
+            .is_some_and(|last| last.iteration == report.iteration)
+        {
+            continue;
+        }
+
+        if reports.len() == ConvergenceHistory::CAPACITY {
+            reports.pop_front();
+        }
+        reports.push_back(report);
+    }
+}
+
+fn render(
+    mut egui_ctx: bevy_egui::EguiContexts,
+    mut history: ResMut<ConvergenceHistory>,
+    mut ui_state: ResMut<UiState>,
+    q_colors: Query<&ColorAssociation>,
+    theme: Res<CatppuccinTheme>,
+    config: Res<Config>,
+) {
+    let mut open = true;
+    egui::Window::new("Convergence")
+        .open(&mut open)
+        .collapsible(true)
+        .resizable(true)
+        .default_size([400.0, 500.0])
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui_state.mouse_over.floating_window = ui.rect_contains_pointer(ui.max_rect())
+                && config.interaction.ui_focus_cancels_inputs;
+
+            ui.checkbox(&mut history.log_scale, "Logarithmic scale");
+
+            let log_scale = history.log_scale;
+            let lines = |value: fn(&SolveReport) -> f64| {
+                history
+                    .robots
+                    .iter()
+                    .map(|(&entity, reports)| {
+                        #[allow(clippy::cast_precision_loss)]
+                        let points: PlotPoints = reports
+                            .iter()
+                            .map(|report| {
+                                let y = value(report);
+                                let y = if log_scale {
+                                    y.max(f64::EPSILON).log10()
+                                } else {
+                                    y
+                                };
+                                [report.iteration as f64, y]
+                            })
+                            .collect();
+
+                        let line = Line::new(points).name(format!("{entity:?}"));
+                        match q_colors.get(entity) {
+                            Ok(color) => line.color(Color32::from_catppuccin_colour(
+                                theme.get_display_colour(&color.name),
+                            )),
+                            Err(_) => line,
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            let y_label = |quantity: &str| {
+                if log_scale {
+                    format!("log10({quantity})")
+                } else {
+                    quantity.to_string()
+                }
+            };
+
+            let height = ui.available_height() / 2.0 - ui.spacing().item_spacing.y;
+            let quantities: [(&str, fn(&SolveReport) -> f64); 2] = [
+                ("energy", |report| report.energy),
+                ("residual", |report| report.residual),
+            ];
+            for (quantity, value) in quantities {
+                let lines = lines(value);
+                Plot::new(quantity)
+                    .height(height)
+                    .legend(Legend::default())
+                    .x_axis_label("iteration")
+                    .y_axis_label(y_label(quantity))
+                    .show(ui, |plot_ui| {
+                        for line in lines {
+                            plot_ui.line(line);
+                        }
+                    });
+            }
+        });
+
+    if !open {
+        ui_state.convergence_window_visible = false;
+    }
+}
@@ -0,0 +1,52 @@
+//! Readout of the position of the cursor on the ground, in the bottom left
+//! corner of the window, for authoring the coordinates of obstacles.
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use gbp_config::Config;
+
+use crate::environment::cursor::CursorCoordinates;
+
+pub struct CursorReadoutPlugin;
+
+impl Plugin for CursorReadoutPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<bevy_egui::EguiPlugin>() {
+            app.add_plugins(bevy_egui::EguiPlugin);
+        }
+
+        app.add_systems(
+            PostUpdate,
+            render.run_if(|config: Res<Config>| config.interaction.cursor.show_readout),
+        );
+    }
+}
+
+/// **Bevy** system to render the cursor readout
+fn render(mut egui_ctx: EguiContexts, cursor: Res<CursorCoordinates>, config: Res<Config>) {
+    let position = cursor.snapped();
+    egui::Area::new(egui::Id::new("cursor readout"))
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(8.0, -8.0))
+        .interactable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.monospace(format!("x: {:8.2}  z: {:8.2}", position.x, position.z));
+                match cursor.tile() {
+                    Some((tile, translation)) => {
+                        ui.monospace(format!("tile: ({}, {})", tile.row, tile.col));
+                        ui.monospace(format!(
+                            "translation: ({:.3}, {:.3})",
+                            translation.x.get(),
+                            translation.y.get()
+                        ));
+                    }
+                    None => {
+                        ui.monospace("tile: outside of the map");
+                    }
+                }
+                let snap = config.interaction.cursor.snap;
+                if snap > 0.0 {
+                    ui.monospace(format!("snap: {snap} m"));
+                }
+            });
+        });
+}
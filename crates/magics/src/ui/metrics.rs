@@ -3,7 +3,7 @@ use std::time::Duration;
 use bevy::{
     diagnostic::{
         DiagnosticsStore, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin,
-        LogDiagnosticsPlugin, SystemInformationDiagnosticsPlugin,
+        SystemInformationDiagnosticsPlugin,
     },
     prelude::*,
 };
@@ -39,30 +39,12 @@ impl Plugin for MetricsPlugin {
             app.add_plugins(bevy_egui::EguiPlugin);
         }
 
-        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
-            app.add_plugins(FrameTimeDiagnosticsPlugin::default());
-        }
-
-        if !app.is_plugin_added::<EntityCountDiagnosticsPlugin>() {
-            app.add_plugins(EntityCountDiagnosticsPlugin::default());
-        }
-
+        // frame time, entity count and robot diagnostics are added depending on
+        // the `[debug]` section of the config, and only shown if present
         if !app.is_plugin_added::<SystemInformationDiagnosticsPlugin>() {
             app.add_plugins(SystemInformationDiagnosticsPlugin::default());
         }
 
-        if !app.is_plugin_added::<RobotDiagnosticsPlugin>() {
-            app.add_plugins(RobotDiagnosticsPlugin::default());
-        }
-
-        if !app.is_plugin_added::<LogDiagnosticsPlugin>() {
-            app.add_plugins(LogDiagnosticsPlugin {
-                debug: true,
-                wait_duration: Duration::from_secs(1),
-                ..Default::default()
-            });
-        }
-
         app.add_systems(PostUpdate, Self::render);
     }
 }
@@ -102,7 +84,27 @@ impl MetricsPlugin {
                     ("robots", &RobotDiagnosticsPlugin::ROBOT_COUNT),
                     ("variables", &RobotDiagnosticsPlugin::VARIABLE_COUNT),
                     ("factors", &RobotDiagnosticsPlugin::FACTOR_COUNT),
+                    (
+                        "non-finite nodes",
+                        &RobotDiagnosticsPlugin::NON_FINITE_NODE_COUNT,
+                    ),
                     ("collisions", &RobotDiagnosticsPlugin::ROBOT_COLLISION_COUNT),
+                    (
+                        "messages sent (internal)",
+                        &RobotDiagnosticsPlugin::MESSAGES_SENT_INTERNAL_COUNT,
+                    ),
+                    (
+                        "messages sent (external)",
+                        &RobotDiagnosticsPlugin::MESSAGES_SENT_EXTERNAL_COUNT,
+                    ),
+                    (
+                        "messages received (internal)",
+                        &RobotDiagnosticsPlugin::MESSAGES_RECEIVED_INTERNAL_COUNT,
+                    ),
+                    (
+                        "messages received (external)",
+                        &RobotDiagnosticsPlugin::MESSAGES_RECEIVED_EXTERNAL_COUNT,
+                    ),
                 ] {
                     #[allow(clippy::cast_possible_truncation)]
                     if let Some(value) = diagnostics
@@ -1,11 +1,15 @@
 pub mod controls;
+mod convergence;
+mod cursor;
 mod custom;
 mod data;
 mod decoration;
 mod metrics;
+mod robot_inspector;
 mod scale;
 // mod selected_entity;
 mod settings;
+mod toolbar;
 
 use std::ops::RangeInclusive;
 
@@ -19,8 +23,9 @@ pub use decoration::ToUiString;
 use strum_macros::EnumIter;
 
 use self::{
-    controls::ControlsPanelPlugin, data::DataPanelPlugin, metrics::MetricsPlugin,
-    scale::ScaleUiPlugin, settings::SettingsPanelPlugin,
+    controls::ControlsPanelPlugin, convergence::ConvergencePlugin, cursor::CursorReadoutPlugin,
+    data::DataPanelPlugin, metrics::MetricsPlugin, robot_inspector::RobotInspectorPlugin,
+    scale::ScaleUiPlugin, settings::SettingsPanelPlugin, toolbar::ToolbarPlugin,
 };
 use crate::{theme::CatppuccinThemeVisualsExt, AppState};
 
@@ -44,7 +49,11 @@ impl PluginGroup for UiPlugins {
             .add(SettingsPanelPlugin)
             //.add(DataPanelPlugin)
             .add(MetricsPlugin::default())
+            .add(ConvergencePlugin)
             .add(ScaleUiPlugin::default())
+            .add(CursorReadoutPlugin)
+            .add(ToolbarPlugin)
+            .add(RobotInspectorPlugin)
     }
 }
 
@@ -61,7 +70,7 @@ impl Plugin for EguiInterfacePlugin {
                 ScaleUiPlugin::default(),
 
 
-                MetricsPlugin::default()            ))
+                MetricsPlugin::default(), ConvergencePlugin, CursorReadoutPlugin, ToolbarPlugin, RobotInspectorPlugin))
             // .add_systems(OnEnter(SimulationState::Loading), load_fonts)
             // .add_systems(Startup, load_fonts)
             // .add_systems(OnEnter(AppState::Loading), load_fonts)
@@ -130,6 +139,10 @@ fn hide_panels(mut ui_state: ResMut<UiState>) {
     if ui_state.metrics_window_visible {
         ui_state.metrics_window_visible = false;
     }
+
+    if ui_state.convergence_window_visible {
+        ui_state.convergence_window_visible = false;
+    }
 }
 
 /// **Bevy** [`Resource`] to block actions from being performed
@@ -224,12 +237,14 @@ pub struct UiState {
     pub left_panel_visible: bool,
     /// Whether the right panel is open
     pub right_panel_visible: bool,
-    /// Whether the top panel is open
+    /// Whether the top panel with the toolbar is open
     pub top_panel_visible: bool,
     /// Whether the bottom panel is open
     pub bottom_panel_visible: bool,
     /// Whether the metrics window is open
     pub metrics_window_visible: bool,
+    /// Whether the convergence plot window is open
+    pub convergence_window_visible: bool,
     /// The type of UI scaling to use
     pub scale_type: UiScaleType,
     /// When `scale_type` is `Custom`, the percentage to scale by
@@ -265,9 +280,10 @@ impl Default for UiState {
         Self {
             left_panel_visible: false,
             right_panel_visible: false,
-            top_panel_visible: false,
+            top_panel_visible: true,
             bottom_panel_visible: false,
             metrics_window_visible: false,
+            convergence_window_visible: false,
             scale_type: UiScaleType::default(),
             scale_percent: Self::DEFAULT_SCALE_PERCENTAGE,
             // scale_percent: 100, // start at default factor 1.0 = 100%
@@ -0,0 +1,194 @@
+//! Window showing the effective parameters of the selected robot, and
+//! overriding them for that robot only, e.g. to see how the others react if
+//! a single robot is slower, without editing the formation.
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use gbp_config::{Config, GbpSection};
+use gbp_linalg::Float;
+
+use super::{custom, UiState};
+use crate::{
+    factorgraph::prelude::{FactorGraph, FactorKind},
+    planner::{
+        robot::{Radius, SigmaOverrides, SpeedFactor},
+        spawner::SelectedRobot,
+    },
+};
+
+/// **Bevy** [`Plugin`] adding the robot inspector window, shown while a robot
+/// is selected
+pub struct RobotInspectorPlugin;
+
+impl Plugin for RobotInspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, render.run_if(robot_selected));
+    }
+}
+
+#[inline]
+fn robot_selected(selected_robot: Res<SelectedRobot>) -> bool {
+    selected_robot.is_some()
+}
+
+/// A sigma of the factors of a robot, that can be overridden
+struct SigmaRow {
+    label:    &'static str,
+    /// The sigma in `config.gbp`
+    default:  fn(&GbpSection) -> f32,
+    /// The override of the sigma
+    value:    fn(&mut SigmaOverrides) -> &mut Option<f32>,
+    /// The factors measured with the sigma
+    measures: fn(&FactorKind) -> bool,
+}
+
+const SIGMA_ROWS: [SigmaRow; 5] = [
+    SigmaRow {
+        label:    "Dynamic",
+        default:  |gbp| gbp.sigma_factor_dynamics,
+        value:    |overrides| &mut overrides.dynamic,
+        measures: |kind| matches!(kind, FactorKind::Dynamic(_)),
+    },
+    SigmaRow {
+        label:    "InterRobot",
+        default:  |gbp| gbp.sigma_factor_interrobot,
+        value:    |overrides| &mut overrides.interrobot,
+        measures: |kind| matches!(kind, FactorKind::InterRobot(_)),
+    },
+    SigmaRow {
+        label:    "Obstacle",
+        default:  |gbp| gbp.sigma_factor_obstacle,
+        value:    |overrides| &mut overrides.obstacle,
+        measures: |kind| matches!(kind, FactorKind::Obstacle(_)),
+    },
+    SigmaRow {
+        label:    "Tracking",
+        default:  |gbp| gbp.sigma_factor_tracking,
+        value:    |overrides| &mut overrides.tracking,
+        measures: |kind| matches!(kind, FactorKind::Tracking(_)),
+    },
+    SigmaRow {
+        label:    "Region",
+        default:  |gbp| gbp.sigma_factor_region,
+        value:    |overrides| &mut overrides.region,
+        measures: |kind| matches!(kind, FactorKind::Region(_)),
+    },
+];
+
+/// Change a sigma of `factorgraph` from `from` to `to`. The strength of the
+/// factors is scaled rather than set, to keep e.g. the priority of the
+/// interrobot factors
+fn change_sigma(factorgraph: &mut FactorGraph, row: &SigmaRow, from: f32, to: f32) {
+    factorgraph.scale_factor_strengths(row.measures, Float::from(to / from));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render(
+    mut contexts: EguiContexts,
+    mut ui_state: ResMut<UiState>,
+    mut selected_robot: ResMut<SelectedRobot>,
+    mut q_robots: Query<(
+        &mut FactorGraph,
+        &mut Radius,
+        Option<&SpeedFactor>,
+        Option<&SigmaOverrides>,
+    )>,
+    mut commands: Commands,
+    config: Res<Config>,
+) {
+    let Some(entity) = selected_robot.0 else {
+        return;
+    };
+    let Ok((mut factorgraph, mut radius, speed_factor, overrides)) = q_robots.get_mut(entity)
+    else {
+        return;
+    };
+
+    let mut overrides = overrides.copied().unwrap_or_default();
+    let previous_overrides = overrides;
+    let target_speed = config.robot.target_speed.get();
+    let speed_factor = speed_factor.copied().unwrap_or_default();
+
+    let mut open = true;
+    egui::Window::new("Robot Inspector")
+        .open(&mut open)
+        .collapsible(true)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui_state.mouse_over.floating_window = ui.rect_contains_pointer(ui.max_rect())
+                && config.interaction.ui_focus_cancels_inputs;
+
+            ui.label(format!("{entity:?}"));
+
+            custom::subheading(ui, "Sigmas", None);
+            custom::grid("robot_inspector_sigma_grid", 2).show(ui, |ui| {
+                for row in &SIGMA_ROWS {
+                    let overridden = (row.value)(&mut overrides).is_some();
+                    let current = (row.value)(&mut overrides).unwrap_or((row.default)(&config.gbp));
+                    let mut sigma = current;
+                    if overridden {
+                        ui.strong(row.label);
+                    } else {
+                        ui.label(row.label);
+                    }
+                    let response = ui.add(
+                        egui::Slider::new(&mut sigma, 1e-3..=10.0)
+                            .logarithmic(true)
+                            .max_decimals(4),
+                    );
+                    if response.changed() && sigma > 0.0 {
+                        change_sigma(&mut factorgraph, row, current, sigma);
+                        *(row.value)(&mut overrides) = Some(sigma);
+                    }
+                    ui.end_row();
+                }
+            });
+
+            if ui
+                .add_enabled(!overrides.is_empty(), egui::Button::new("Reset sigmas"))
+                .on_hover_text("Use the sigmas of the config again")
+                .clicked()
+            {
+                for row in &SIGMA_ROWS {
+                    if let Some(sigma) = (row.value)(&mut overrides).take() {
+                        change_sigma(&mut factorgraph, row, sigma, (row.default)(&config.gbp));
+                    }
+                }
+            }
+
+            custom::subheading(ui, "Robot", None);
+            custom::grid("robot_inspector_robot_grid", 2).show(ui, |ui| {
+                ui.label("Radius");
+                let mut value = radius.0;
+                if ui
+                    .add(egui::Slider::new(&mut value, 0.1..=5.0).suffix(" m"))
+                    .changed()
+                {
+                    if let Ok(robot_radius) = Float::from(value).try_into() {
+                        radius.0 = value;
+                        factorgraph.update_robot_radius(robot_radius);
+                    }
+                }
+                ui.end_row();
+
+                ui.label("Max speed");
+                let mut max_speed = target_speed * speed_factor.0;
+                if ui
+                    .add(egui::Slider::new(&mut max_speed, 0.0..=3.0 * target_speed).suffix(" m/s"))
+                    .changed()
+                {
+                    commands
+                        .entity(entity)
+                        .insert(SpeedFactor(max_speed / target_speed));
+                }
+                ui.end_row();
+            });
+        });
+
+    if overrides != previous_overrides {
+        commands.entity(entity).insert(overrides);
+    }
+
+    if !open {
+        selected_robot.0 = None;
+    }
+}
@@ -1,4 +1,4 @@
-use std::{path::Path, time::Duration};
+use std::path::Path;
 
 use bevy::{prelude::*, window::PrimaryWindow};
 use bevy_egui::{
@@ -24,7 +24,7 @@ use crate::{
     input::{
         screenshot::TakeScreenshot, ChangingBinding, DrawSettingsEvent, ExportFactorGraphAsGraphviz,
     },
-    pause_play::PausePlay,
+    pause_play::{PausePlay, StepSimulation},
     planner::robot::RadioAntenna,
     simulation_loader::{SaveSettings, SimulationId, SimulationManager},
     theme::{CatppuccinTheme, CycleTheme, FromCatppuccinColourExt},
@@ -127,7 +127,7 @@ fn ui_settings_panel(
     _currently_changing: Mut<ChangingBinding>,
     // pause_state: Mut<State<PausedState>>,
     mut time_virtual: Mut<Time<Virtual>>,
-    mut time_fixed: Mut<Time<Fixed>>,
+    time_fixed: Mut<Time<Fixed>>,
     mut config_store: Mut<GizmoConfigStore>,
     mut simulation_manager: Mut<SimulationManager>,
 ) {
@@ -533,6 +533,15 @@ fn ui_settings_panel(
                                 }
                             });
                             ui.end_row();
+
+                            ui.label("Region");
+                            update_float(ui, &mut config.gbp.sigma_factor_region);
+                            custom::float_right(ui, |ui| {
+                                if custom::toggle_ui(ui, &mut config.gbp.factors_enabled.region).clicked() {
+                                    update_enabled_factors(config.gbp.factors_enabled.clone());
+                                }
+                            });
+                            ui.end_row();
                         });
                         //
                         //custom::grid("factors_enabled_grid", 2).show(ui, |ui| {
@@ -614,6 +623,20 @@ fn ui_settings_panel(
 
                             ui.end_row();
 
+                            ui.label("Share Horizon").on_hover_text("Share the full planned horizon with other robots, or only the current state. Applies to new connections");
+                            custom::float_right(ui, |ui| {
+                                let mut share_horizon = config.gbp.intention_sharing == gbp_config::IntentionSharing::Horizon;
+                                if custom::toggle_ui(ui, &mut share_horizon).clicked() {
+                                    config.gbp.intention_sharing = if share_horizon {
+                                        gbp_config::IntentionSharing::Horizon
+                                    } else {
+                                        gbp_config::IntentionSharing::CurrentState
+                                    };
+                                }
+                            });
+
+                            ui.end_row();
+
                         });
                     }
 
@@ -910,6 +933,25 @@ fn ui_settings_panel(
                         }
                     });
 
+                    ui.add_space(2.5);
+                    ui.separator();
+
+                    ui.label("Only for the Selected Robot").on_hover_text("Draw these only for the robot last clicked on, instead of every robot");
+                    custom::grid("only_selected_grid", 2).show(ui, |ui| {
+                        let only_selected = &mut config.visualisation.only_selected;
+                        for (label, setting) in [
+                            ("Trajectories", &mut only_selected.predicted_trajectories),
+                            ("Uncertainty", &mut only_selected.uncertainty),
+                            ("Factors", &mut only_selected.factors),
+                        ] {
+                            ui.label(label);
+                            custom::float_right(ui, |ui| {
+                                custom::toggle_ui(ui, setting);
+                            });
+                            ui.end_row();
+                        }
+                    });
+
                     custom::subheading(
                         ui,
                         "Simulation",
@@ -1000,20 +1042,14 @@ fn ui_settings_panel(
                         custom::grid("manual_controls_settings_grid", 2).show(ui, |ui| {
                             // step forward button
                             // ui.add_enabled_ui(!pause_state.is_paused(), |ui| {
-                            ui.add_enabled_ui(!time_virtual.is_paused(), |ui| {
+                            ui.add_enabled_ui(time_virtual.is_paused(), |ui| {
                                 custom::fill_x(ui, |ui| {
                                     if ui
                                         .button(RichText::new("󰒭").size(25.0))
                                         .on_hover_text("Step forward one step in the simulation")
                                         .clicked()
                                     {
-                                        #[allow(
-                                            clippy::cast_precision_loss,
-                                            clippy::cast_possible_truncation
-                                        )]
-                                        let step_size = config.simulation.manual_step_factor as f32
-                                            / config.simulation.hz as f32;
-                                        time_fixed.advance_by(Duration::from_secs_f32(step_size));
+                                        world.send_event::<StepSimulation>(StepSimulation);
                                     }
                                 });
                             });
@@ -0,0 +1,126 @@
+use bevy::prelude::*;
+use bevy_egui::{
+    egui::{self, RichText},
+    EguiContexts,
+};
+use gbp_config::Config;
+
+use super::{OccupiedScreenSpace, UiState};
+use crate::{
+    pause_play::{PausePlay, StepSimulation},
+    simulation_loader::SimulationManager,
+};
+
+/// **Bevy** `Plugin` to add the toolbar with the simulation controls to the
+/// top of the UI
+pub struct ToolbarPlugin;
+
+impl Plugin for ToolbarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, render_toolbar.run_if(toolbar_enabled));
+    }
+}
+
+#[inline]
+fn toolbar_enabled(ui_state: Res<UiState>) -> bool {
+    ui_state.top_panel_visible
+}
+
+/// Buttons for the simulations, pausing and stepping, and the time scale, so
+/// they can be found without knowing the keybindings
+#[allow(clippy::too_many_arguments)]
+fn render_toolbar(
+    mut contexts: EguiContexts,
+    mut ui_state: ResMut<UiState>,
+    mut occupied_screen_space: ResMut<OccupiedScreenSpace>,
+    mut simulation_manager: ResMut<SimulationManager>,
+    mut config: ResMut<Config>,
+    mut time_virtual: ResMut<Time<Virtual>>,
+    mut evw_pause_play: EventWriter<PausePlay>,
+    mut evw_step_simulation: EventWriter<StepSimulation>,
+) {
+    let top_panel =
+        egui::TopBottomPanel::top("Toolbar")
+            .resizable(false)
+            .show(contexts.ctx_mut(), |ui| {
+                ui_state.mouse_over.top_panel = ui.rect_contains_pointer(ui.max_rect())
+                    && config.interaction.ui_focus_cancels_inputs;
+
+                ui.horizontal(|ui| {
+                    let active = simulation_manager
+                        .active_name()
+                        .map_or_else(|| "No Simulation".to_string(), ToString::to_string);
+                    ui.menu_button(active, |ui| {
+                        #[allow(clippy::needless_collect)] // borrows the manager while loading
+                        for (id, name) in simulation_manager.ids_and_names().collect::<Vec<_>>() {
+                            if ui
+                                .add(egui::Button::new(name.as_str()).wrap(false))
+                                .clicked()
+                            {
+                                simulation_manager.load(id);
+                                ui.close_menu();
+                            }
+                        }
+                    })
+                    .response
+                    .on_hover_text("Load a simulation");
+
+                    let active = simulation_manager.active_id().is_some();
+                    if ui
+                        .add_enabled(active, egui::Button::new("󰑓"))
+                        .on_hover_text("Reload the active simulation (F5)")
+                        .clicked()
+                    {
+                        simulation_manager.reload();
+                    }
+                    if ui
+                        .add_enabled(active, egui::Button::new(""))
+                        .on_hover_text("End the active simulation")
+                        .clicked()
+                    {
+                        simulation_manager.end();
+                    }
+
+                    ui.separator();
+
+                    let paused = time_virtual.is_paused();
+                    if ui
+                        .button(if paused { "" } else { "" })
+                        .on_hover_text("Play or pause the simulation (Space)")
+                        .clicked()
+                    {
+                        evw_pause_play.send(PausePlay::Toggle);
+                    }
+                    if ui
+                        .add_enabled(paused, egui::Button::new("󰒭"))
+                        .on_hover_text("Step forward one step in the simulation")
+                        .clicked()
+                    {
+                        evw_step_simulation.send(StepSimulation);
+                    }
+
+                    ui.separator();
+
+                    let mut time_scale = config.simulation.time_scale.get();
+                    ui.label("Speed");
+                    if ui
+                        .add(egui::Slider::new(&mut time_scale, 0.1..=5.0).suffix("x"))
+                        .changed()
+                    {
+                        if let Ok(time_scale) = time_scale.try_into() {
+                            config.simulation.time_scale = time_scale;
+                            time_virtual.set_relative_speed(config.simulation.time_scale.get());
+                        }
+                    }
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.label(
+                            RichText::new(format!("{:.2} s", time_virtual.elapsed_seconds()))
+                                .monospace(),
+                        );
+                    });
+                });
+            });
+
+    occupied_screen_space.top = top_panel.response.rect.height();
+}
@@ -0,0 +1,9 @@
+//! `Config::parse` must return an error on malformed input, not panic
+#![no_main]
+
+use gbp_config::Config;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|contents: &str| {
+    let _ = Config::parse(contents);
+});
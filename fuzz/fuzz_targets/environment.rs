@@ -0,0 +1,12 @@
+//! `Environment::parse` must return an error on malformed input, not panic
+#![no_main]
+
+use gbp_environment::Environment;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|contents: &str| {
+    if let Ok(environment) = Environment::parse(contents) {
+        // the app relies on a parsed environment having a valid grid
+        let _ = environment.dimensions();
+    }
+});
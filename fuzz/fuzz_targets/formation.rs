@@ -0,0 +1,10 @@
+//! The formation parsers must return an error on malformed input, not panic
+#![no_main]
+
+use gbp_config::FormationGroup;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|contents: &str| {
+    let _ = FormationGroup::parse_from_ron(contents);
+    let _ = FormationGroup::parse_from_yaml(contents);
+});